@@ -0,0 +1,298 @@
+//! Startup configuration for bouvet-agent.
+//!
+//! Read from environment variables once at startup. Unknown `key=value`
+//! kernel boot arguments are passed to init as environment variables, so
+//! these can be set from the host via the VM's boot args.
+
+use crate::disk::MountSpec;
+use crate::redact;
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::warn;
+
+/// Default maximum number of simultaneously served connections.
+const DEFAULT_MAX_CONNECTIONS: usize = 16;
+
+/// Default listen backlog for the vsock socket.
+const DEFAULT_BACKLOG: i32 = 128;
+
+/// Default capacity of a connection's read and write buffers (64 KiB).
+const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Default name of the unprivileged sandbox user.
+const DEFAULT_SANDBOX_USER: &str = "sandbox";
+
+/// Default uid of the sandbox user.
+const DEFAULT_SANDBOX_UID: u32 = 1000;
+
+/// Default open file limit for the agent and its commands.
+const DEFAULT_NOFILE_LIMIT: u64 = 65536;
+
+/// Default process limit per user, which bounds a fork bomb.
+const DEFAULT_NPROC_LIMIT: u64 = 4096;
+
+/// Agent startup configuration.
+///
+/// | Variable | Default |
+/// |----------|---------|
+/// | `BOUVET_AGENT_MAX_CONNECTIONS` | `16` |
+/// | `BOUVET_AGENT_BACKLOG` | `128` |
+/// | `BOUVET_AGENT_BUFFER_SIZE` | `65536` |
+/// | `BOUVET_AGENT_USER` | `sandbox` (empty disables) |
+/// | `BOUVET_AGENT_UID` | `1000` |
+/// | `BOUVET_CLEAN_TMP` | unset (`1` mounts a fresh tmpfs on `/tmp`) |
+/// | `BOUVET_REDACT_PATTERNS` | `*_TOKEN,*_SECRET,*_KEY,*PASSWORD` |
+/// | `BOUVET_AGENT_NOFILE` | `65536` (empty keeps the inherited limit) |
+/// | `BOUVET_AGENT_NPROC` | `4096` (empty keeps the inherited limit) |
+/// | `BOUVET_HTTP_PROXY` | unset (exported to commands as `http_proxy`) |
+/// | `BOUVET_HTTPS_PROXY` | unset (exported to commands as `https_proxy`) |
+/// | `BOUVET_NO_PROXY` | unset (exported to commands as `no_proxy`) |
+/// | `BOUVET_MOUNTS` | unset (`;`-separated `device:mountpoint:fstype[:options]`) |
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentConfig {
+    /// Connections served at once. Connections beyond this are closed
+    /// immediately after being accepted.
+    pub max_connections: usize,
+    /// Pending connections the kernel queues before refusing new ones.
+    pub backlog: i32,
+    /// Capacity of each connection's read and write buffers in bytes.
+    pub buffer_size: usize,
+    /// Unprivileged user created at startup if missing, or `None` to skip.
+    pub sandbox_user: Option<String>,
+    /// Uid for a newly created sandbox user.
+    pub sandbox_uid: u32,
+    /// Mount an empty tmpfs on `/tmp` at startup.
+    pub clean_tmp: bool,
+    /// Variable name patterns whose values are masked in logs.
+    pub redact_patterns: Vec<String>,
+    /// `RLIMIT_NOFILE` set at startup, or `None` to keep the inherited one.
+    pub nofile_limit: Option<u64>,
+    /// `RLIMIT_NPROC` set at startup, or `None` to keep the inherited one.
+    /// The kernel exempts root, so this binds commands that switch to an
+    /// unprivileged user such as the sandbox user, not the agent itself.
+    pub nproc_limit: Option<u64>,
+    /// Proxy variables added to the environment of every command.
+    pub proxy_env: Vec<(String, String)>,
+    /// Filesystems mounted before the agent accepts connections. Startup
+    /// fails if any of them can't be mounted.
+    pub mounts: Vec<MountSpec>,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            backlog: DEFAULT_BACKLOG,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            sandbox_user: Some(DEFAULT_SANDBOX_USER.to_string()),
+            sandbox_uid: DEFAULT_SANDBOX_UID,
+            clean_tmp: false,
+            redact_patterns: redact::DEFAULT_PATTERNS
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            nofile_limit: Some(DEFAULT_NOFILE_LIMIT),
+            nproc_limit: Some(DEFAULT_NPROC_LIMIT),
+            proxy_env: Vec::new(),
+            mounts: Vec::new(),
+        }
+    }
+}
+
+impl AgentConfig {
+    /// Load configuration from environment variables.
+    ///
+    /// Invalid or zero values are ignored with a warning.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            max_connections: env_positive("BOUVET_AGENT_MAX_CONNECTIONS")
+                .unwrap_or(default.max_connections),
+            backlog: env_positive("BOUVET_AGENT_BACKLOG").unwrap_or(default.backlog),
+            buffer_size: env_positive("BOUVET_AGENT_BUFFER_SIZE").unwrap_or(default.buffer_size),
+            sandbox_user: match std::env::var("BOUVET_AGENT_USER") {
+                Ok(name) if name.is_empty() => None,
+                Ok(name) => Some(name),
+                Err(_) => default.sandbox_user,
+            },
+            sandbox_uid: env_positive("BOUVET_AGENT_UID").unwrap_or(default.sandbox_uid),
+            clean_tmp: std::env::var("BOUVET_CLEAN_TMP").is_ok_and(|v| v == "1"),
+            redact_patterns: match std::env::var("BOUVET_REDACT_PATTERNS") {
+                Ok(patterns) => patterns
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|p| !p.is_empty())
+                    .map(String::from)
+                    .collect(),
+                Err(_) => default.redact_patterns,
+            },
+            nofile_limit: env_limit("BOUVET_AGENT_NOFILE", default.nofile_limit),
+            nproc_limit: env_limit("BOUVET_AGENT_NPROC", default.nproc_limit),
+            proxy_env: proxy_env(|name| std::env::var(name).ok()),
+            mounts: std::env::var("BOUVET_MOUNTS")
+                .map(|value| MountSpec::parse_list(&value))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Proxy variables for commands, from the `BOUVET_*_PROXY` settings.
+///
+/// Each is exported under its lower- and upper-case name, as tools differ
+/// in which they read (curl ignores `HTTP_PROXY`, Go ignores `http_proxy`
+/// unless `HTTP_PROXY` is unset).
+fn proxy_env(lookup: impl Fn(&str) -> Option<String>) -> Vec<(String, String)> {
+    let mut vars = Vec::new();
+    for (setting, name) in [
+        ("BOUVET_HTTP_PROXY", "http_proxy"),
+        ("BOUVET_HTTPS_PROXY", "https_proxy"),
+        ("BOUVET_NO_PROXY", "no_proxy"),
+    ] {
+        if let Some(value) = lookup(setting).filter(|v| !v.is_empty()) {
+            vars.push((name.to_string(), value.clone()));
+            vars.push((name.to_ascii_uppercase(), value));
+        }
+    }
+    vars
+}
+
+/// Parse a positive number from an environment variable.
+fn env_positive<T>(name: &str) -> Option<T>
+where
+    T: std::str::FromStr + PartialOrd + Default,
+{
+    let value = std::env::var(name).ok()?;
+    match value.parse::<T>() {
+        Ok(n) if n > T::default() => Some(n),
+        _ => {
+            warn!(name = %name, value = %value, "ignoring invalid value");
+            None
+        }
+    }
+}
+
+/// Read an optional resource limit; an empty value disables it.
+fn env_limit(name: &str, default: Option<u64>) -> Option<u64> {
+    match std::env::var(name) {
+        Ok(value) if value.is_empty() => None,
+        Ok(_) => env_positive(name).or(default),
+        Err(_) => default,
+    }
+}
+
+/// Caps the number of connections served at once.
+#[derive(Debug, Clone)]
+pub struct ConnectionLimiter {
+    semaphore: Arc<Semaphore>,
+}
+
+impl ConnectionLimiter {
+    /// Create a limiter admitting up to `max` connections at once.
+    pub fn new(max: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max)),
+        }
+    }
+
+    /// Admit a connection without waiting.
+    ///
+    /// # Returns
+    /// A permit to hold for the lifetime of the connection, or `None` if
+    /// the limit has been reached.
+    pub fn try_admit(&self) -> Option<OwnedSemaphorePermit> {
+        Arc::clone(&self.semaphore).try_acquire_owned().ok()
+    }
+
+    /// Number of connections that can still be admitted.
+    pub fn available(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn test_default_config() {
+        let config = AgentConfig::default();
+        assert_eq!(config.max_connections, 16);
+        assert_eq!(config.backlog, 128);
+        assert_eq!(config.buffer_size, 64 * 1024);
+        assert_eq!(config.sandbox_user.as_deref(), Some("sandbox"));
+        assert_eq!(config.sandbox_uid, 1000);
+        assert!(!config.clean_tmp);
+        assert_eq!(
+            config.redact_patterns,
+            ["*_TOKEN", "*_SECRET", "*_KEY", "*PASSWORD"]
+        );
+        assert_eq!(config.nofile_limit, Some(65536));
+        assert_eq!(config.nproc_limit, Some(4096));
+        assert!(config.proxy_env.is_empty());
+        assert!(config.mounts.is_empty());
+    }
+
+    #[test]
+    fn test_proxy_env() {
+        let vars = proxy_env(|name| match name {
+            "BOUVET_HTTPS_PROXY" => Some("http://proxy.corp:3128".into()),
+            "BOUVET_NO_PROXY" => Some("localhost,.corp".into()),
+            "BOUVET_HTTP_PROXY" => Some(String::new()),
+            _ => None,
+        });
+        let vars: Vec<(&str, &str)> = vars
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(
+            vars,
+            [
+                ("https_proxy", "http://proxy.corp:3128"),
+                ("HTTPS_PROXY", "http://proxy.corp:3128"),
+                ("no_proxy", "localhost,.corp"),
+                ("NO_PROXY", "localhost,.corp"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connection_limiter_caps_concurrency() {
+        let limiter = ConnectionLimiter::new(3);
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let mut admitted = 0;
+        let mut rejected = 0;
+        let mut tasks = Vec::new();
+
+        // Ten connections arrive while earlier ones are still being served
+        for _ in 0..10 {
+            let Some(permit) = limiter.try_admit() else {
+                rejected += 1;
+                continue;
+            };
+            admitted += 1;
+            let active = Arc::clone(&active);
+            let peak = Arc::clone(&peak);
+            tasks.push(tokio::spawn(async move {
+                let _permit = permit;
+                let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                active.fetch_sub(1, Ordering::SeqCst);
+            }));
+        }
+
+        assert_eq!(admitted, 3);
+        assert_eq!(rejected, 7);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+
+        // Finished connections free their slots
+        assert_eq!(limiter.available(), 3);
+        assert!(limiter.try_admit().is_some());
+    }
+}
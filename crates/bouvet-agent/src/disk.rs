@@ -0,0 +1,197 @@
+//! Block device operations for bouvet-agent.
+//!
+//! Provides growing a filesystem after the host enlarged its drive, and
+//! the mounts requested by the host at startup.
+
+use crate::exec::{exec_argv, RunOptions};
+use std::path::{Component, Path};
+use tracing::{debug, warn};
+
+/// A filesystem to mount at startup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountSpec {
+    /// Device or source to mount, e.g. `/dev/vdb`.
+    pub device: String,
+    /// Absolute directory to mount on, created if missing.
+    pub mountpoint: String,
+    /// Filesystem type, e.g. `ext4`.
+    pub fstype: String,
+    /// Comma-separated mount options; empty for the defaults.
+    pub options: String,
+}
+
+impl MountSpec {
+    /// Parse the `BOUVET_MOUNTS` format: `;`-separated entries of
+    /// `device:mountpoint:fstype[:options]`.
+    ///
+    /// Missing fields are left empty for [`mount_argv`] to reject, so a
+    /// malformed entry fails at mount time rather than being skipped.
+    pub fn parse_list(value: &str) -> Vec<Self> {
+        value
+            .split(';')
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let mut fields = entry.splitn(4, ':').map(String::from);
+                Self {
+                    device: fields.next().unwrap_or_default(),
+                    mountpoint: fields.next().unwrap_or_default(),
+                    fstype: fields.next().unwrap_or_default(),
+                    options: fields.next().unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Build the `mount` command line for `spec`.
+///
+/// # Returns
+/// The argv, or an error message if a field is missing or the mountpoint
+/// is not a plain absolute path.
+pub fn mount_argv(spec: &MountSpec) -> Result<Vec<String>, String> {
+    if spec.device.is_empty() || spec.fstype.is_empty() {
+        return Err(format!(
+            "invalid mount of '{}': device and fstype are required",
+            spec.mountpoint
+        ));
+    }
+    let path = Path::new(&spec.mountpoint);
+    let plain = path.is_absolute()
+        && path
+            .components()
+            .all(|c| matches!(c, Component::RootDir | Component::Normal(_)));
+    if !plain {
+        return Err(format!(
+            "invalid mountpoint '{}': must be an absolute path",
+            spec.mountpoint
+        ));
+    }
+    let mut argv = vec!["mount".to_string(), "-t".to_string(), spec.fstype.clone()];
+    if !spec.options.is_empty() {
+        argv.extend(["-o".to_string(), spec.options.clone()]);
+    }
+    argv.extend([spec.device.clone(), spec.mountpoint.clone()]);
+    Ok(argv)
+}
+
+/// Mount `spec`, creating its mountpoint first.
+///
+/// # Returns
+/// `Ok(())` on success, or an error message including `mount`'s output.
+pub fn mount(spec: &MountSpec) -> Result<(), String> {
+    let argv = mount_argv(spec)?;
+    std::fs::create_dir_all(&spec.mountpoint)
+        .map_err(|e| format!("failed to create '{}': {}", spec.mountpoint, e))?;
+    debug!(device = %spec.device, mountpoint = %spec.mountpoint, "mounting");
+    let result = exec_argv(&argv, &RunOptions::default());
+    if result.exit_code != 0 {
+        return Err(format!(
+            "mounting {} on {} failed with exit code {}: {}",
+            spec.device,
+            spec.mountpoint,
+            result.exit_code,
+            result.stderr.trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Build the `resize2fs` command line that grows the filesystem on `device`
+/// to fill it.
+///
+/// # Returns
+/// The argv, or an error message if `device` is not a path under `/dev`.
+pub fn resize_fs_argv(device: &str) -> Result<Vec<String>, String> {
+    let path = Path::new(device);
+    let under_dev = path.starts_with("/dev")
+        && path.components().count() > 2
+        && path
+            .components()
+            .all(|c| matches!(c, Component::RootDir | Component::Normal(_)));
+    if !under_dev {
+        return Err(format!("invalid device '{}': must be under /dev", device));
+    }
+    Ok(vec!["resize2fs".to_string(), device.to_string()])
+}
+
+/// Grow the ext2/3/4 filesystem on `device` to the size of the device.
+///
+/// Mounted filesystems are grown online.
+///
+/// # Returns
+/// `Ok(())` on success, or `resize2fs`'s error output.
+pub fn resize_fs(device: &str) -> Result<(), String> {
+    let argv = resize_fs_argv(device)?;
+    debug!(device = %device, "growing filesystem");
+    let result = exec_argv(&argv, &RunOptions::default());
+    if result.exit_code != 0 {
+        warn!(device = %device, exit_code = result.exit_code, stderr = %result.stderr, "resize2fs failed");
+        return Err(format!(
+            "resize2fs {} failed with exit code {}: {}",
+            device,
+            result.exit_code,
+            result.stderr.trim()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mount_argv() {
+        let specs = MountSpec::parse_list("/dev/vdb:/data:ext4:noatime,ro;tmpfs:/cache:tmpfs;");
+        assert_eq!(specs.len(), 2);
+        assert_eq!(
+            mount_argv(&specs[0]).unwrap(),
+            [
+                "mount",
+                "-t",
+                "ext4",
+                "-o",
+                "noatime,ro",
+                "/dev/vdb",
+                "/data"
+            ]
+        );
+        assert_eq!(
+            mount_argv(&specs[1]).unwrap(),
+            ["mount", "-t", "tmpfs", "tmpfs", "/cache"]
+        );
+
+        for entry in [
+            "/dev/vdb",
+            "/dev/vdb:/data",
+            "/dev/vdb:data:ext4",
+            "/dev/vdb:/data/../etc:ext4",
+            ":/data:ext4",
+        ] {
+            let spec = &MountSpec::parse_list(entry)[0];
+            assert!(mount_argv(spec).is_err(), "{entry} should be rejected");
+        }
+        assert!(MountSpec::parse_list("").is_empty());
+    }
+
+    #[test]
+    fn test_resize_fs_argv() {
+        assert_eq!(
+            resize_fs_argv("/dev/vdb").unwrap(),
+            ["resize2fs", "/dev/vdb"]
+        );
+        for device in [
+            "vdb",
+            "/dev",
+            "/dev/",
+            "/etc/passwd",
+            "/dev/../etc/passwd",
+            "",
+        ] {
+            assert!(
+                resize_fs_argv(device).is_err(),
+                "{device} should be rejected"
+            );
+        }
+    }
+}
@@ -2,14 +2,48 @@
 //!
 //! Provides functions to execute shell commands and code in various languages.
 
-use crate::protocol::ExecResult;
-use std::process::Command;
+use crate::protocol::{ExecResult, OutputStream, ResourceUsage, Signal};
+use crate::redact::redact;
+use bouvet_common::take_utf8;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::time::Duration;
 use tracing::{debug, trace, warn};
 
 /// Maximum output size in bytes (1 MB).
 /// Prevents memory exhaustion from commands with huge output.
 const MAX_OUTPUT_SIZE: usize = 1024 * 1024;
 
+/// Maximum number of files in an `exec_project` call.
+const MAX_PROJECT_FILES: usize = 256;
+
+/// Counter making project directory names unique within the agent.
+static PROJECT_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Variables added to the environment of every command, set once at startup.
+static COMMAND_ENV: OnceLock<Vec<(String, String)>> = OnceLock::new();
+
+/// Install the variables every command runs with. Later calls are ignored.
+pub fn init_env(vars: Vec<(String, String)>) {
+    let _ = COMMAND_ENV.set(vars);
+}
+
+/// Check that `name` can be set as an environment variable.
+fn valid_env_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['=', '\0'])
+}
+
+/// Add the installed variables to `command`'s environment.
+fn apply_env(command: &mut Command) -> &mut Command {
+    let vars = COMMAND_ENV.get().map_or(&[][..], Vec::as_slice);
+    command.envs(vars.iter().map(|(name, value)| (name, value)))
+}
+
 /// Truncate a string to max bytes, preserving UTF-8 boundaries.
 fn truncate_output(s: String, max_bytes: usize) -> String {
     if s.len() <= max_bytes {
@@ -25,71 +59,515 @@ fn truncate_output(s: String, max_bytes: usize) -> String {
     truncated
 }
 
+/// Receives a command's output as it is read.
+#[derive(Clone)]
+pub struct OutputSink(Arc<dyn Fn(OutputStream, String) + Send + Sync>);
+
+impl OutputSink {
+    /// Wrap a callback, which is called from the threads reading the
+    /// command's pipes.
+    pub fn new(sink: impl Fn(OutputStream, String) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(sink))
+    }
+}
+
+impl std::fmt::Debug for OutputSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("OutputSink")
+    }
+}
+
+/// Handle for signalling a command's process group while it runs.
+///
+/// The command is attached once spawned and detached as soon as it exits,
+/// so a signal never reaches a process group id the kernel has reused. A
+/// signal sent before the command starts is delivered when it does.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessHandle(Arc<Mutex<ProcessState>>);
+
+#[derive(Debug, Default)]
+struct ProcessState {
+    /// Process group of the running command.
+    pgid: Option<libc::pid_t>,
+    /// Last signal sent, delivered on attach if the command hadn't started.
+    signal: Option<Signal>,
+    exited: bool,
+}
+
+impl ProcessHandle {
+    /// Create a handle for a command that hasn't started yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send `signal` to the command's process group.
+    ///
+    /// Returns `false` if the command has already exited.
+    pub fn signal(&self, signal: Signal) -> bool {
+        let mut state = self.0.lock().expect("process state lock poisoned");
+        if state.exited {
+            return false;
+        }
+        state.signal = Some(signal);
+        if let Some(pgid) = state.pgid {
+            send_signal(pgid, signal);
+        }
+        true
+    }
+
+    /// Record the spawned command's process group, delivering any signal
+    /// sent before it started.
+    fn attach(&self, pgid: libc::pid_t) {
+        let mut state = self.0.lock().expect("process state lock poisoned");
+        state.pgid = Some(pgid);
+        if let Some(signal) = state.signal {
+            send_signal(pgid, signal);
+        }
+    }
+
+    /// Forget the process group once the command has exited (or failed to
+    /// start), returning the signal it was sent, if any.
+    fn detach(&self) -> Option<Signal> {
+        let mut state = self.0.lock().expect("process state lock poisoned");
+        state.pgid = None;
+        state.exited = true;
+        state.signal
+    }
+}
+
+/// Send `signal` to every process in group `pgid`.
+fn send_signal(pgid: libc::pid_t, signal: Signal) {
+    let signal = match signal {
+        Signal::Term => libc::SIGTERM,
+        Signal::Kill => libc::SIGKILL,
+    };
+    debug!(pgid = pgid, signal = signal, "signalling process group");
+    // SAFETY: kill(2) has no memory-safety preconditions
+    unsafe { libc::kill(-pgid, signal) };
+}
+
+/// Per-call settings for running a command.
+#[derive(Debug, Default, Clone)]
+pub struct RunOptions {
+    /// Directory to run in; the agent's own when unset.
+    pub cwd: Option<PathBuf>,
+    /// Kill the process group once stdout and stderr together exceed this
+    /// many bytes.
+    pub max_output_bytes: Option<u64>,
+    /// Kill the process group if it is still running after this long.
+    pub timeout: Option<Duration>,
+    /// Variables set for this command only, on top of the agent's own
+    /// environment and the installed variables.
+    pub env: HashMap<String, String>,
+    /// Written to the command's stdin, which is then closed. Without it
+    /// stdin is `/dev/null`.
+    pub stdin: Option<String>,
+    /// Collect the command's resource usage when it exits.
+    pub usage: bool,
+    /// Hand output to this sink as it is read instead of capturing it.
+    /// The result's stdout and stderr are then empty, apart from a
+    /// timeout note.
+    pub output: Option<OutputSink>,
+    /// Let this handle signal the command, which then runs in its own
+    /// process group.
+    pub process: Option<ProcessHandle>,
+}
+
+/// Output of a process run to completion.
+struct RunOutput {
+    status: ExitStatus,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    /// The process group was killed for exceeding its output budget.
+    output_limit_exceeded: bool,
+    /// The process group was killed for running longer than this.
+    timed_out: Option<Duration>,
+    /// Signal sent through the command's `ProcessHandle`, if any.
+    signalled: Option<Signal>,
+    /// Resources used, if requested.
+    usage: Option<ResourceUsage>,
+}
+
+impl RunOutput {
+    /// Captured stderr, truncated, followed by a note if the command was
+    /// killed for running too long or signalled.
+    fn stderr_text(&self) -> String {
+        let mut stderr = truncate_output(
+            String::from_utf8_lossy(&self.stderr).into_owned(),
+            MAX_OUTPUT_SIZE,
+        );
+        if let Some(timeout) = self.timed_out {
+            if !stderr.is_empty() && !stderr.ends_with('\n') {
+                stderr.push('\n');
+            }
+            stderr.push_str(&format!("timed out after {}", format_timeout(timeout)));
+        } else if let Some(signal) = self.signalled {
+            if !stderr.is_empty() && !stderr.ends_with('\n') {
+                stderr.push('\n');
+            }
+            let name = match signal {
+                Signal::Term => "SIGTERM",
+                Signal::Kill => "SIGKILL",
+            };
+            stderr.push_str(&format!("cancelled with {}", name));
+        }
+        stderr
+    }
+}
+
+/// A timeout as `Ns` when it is whole seconds, else as `Nms`, rounded up
+/// so a short timeout never reads as zero.
+fn format_timeout(timeout: Duration) -> String {
+    if timeout.subsec_nanos() == 0 {
+        format!("{}s", timeout.as_secs())
+    } else {
+        format!("{}ms", timeout.as_nanos().div_ceil(1_000_000))
+    }
+}
+
+/// Run a command to completion, capturing its output.
+///
+/// Output is read as it is produced. Each stream keeps only its first
+/// `MAX_OUTPUT_SIZE` bytes (plus one, so truncation is still detected),
+/// so memory stays bounded however much a command prints.
+///
+/// With `max_output_bytes` or `timeout`, the command runs in its own
+/// process group, which is killed with SIGKILL once stdout and stderr
+/// together exceed the budget or the timeout passes. Killing the group
+/// also stops children that would otherwise hold the pipes open. A command
+/// with a `ProcessHandle` also gets its own group, which the handle
+/// signals.
+fn run(command: &mut Command, options: &RunOptions) -> io::Result<RunOutput> {
+    let max_output_bytes = options.max_output_bytes;
+    if let Some(name) = options.env.keys().find(|name| !valid_env_name(name)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid environment variable name: {name:?}"),
+        ));
+    }
+    let stdin = match options.stdin {
+        Some(_) => Stdio::piped(),
+        None => Stdio::null(),
+    };
+    apply_env(command)
+        .envs(&options.env)
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(cwd) = &options.cwd {
+        // Spawning would fail with a bare ENOENT that doesn't say which path
+        if !cwd.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("working directory '{}' is not a directory", cwd.display()),
+            ));
+        }
+        command.current_dir(cwd);
+    }
+    if max_output_bytes.is_some() || options.timeout.is_some() || options.process.is_some() {
+        command.process_group(0);
+    }
+    let spawned = command.spawn();
+    if let Some(process) = &options.process {
+        match &spawned {
+            Ok(child) => process.attach(child.id() as libc::pid_t),
+            Err(_) => {
+                process.detach();
+            }
+        }
+    }
+    let mut child = spawned?;
+    let limit = OutputLimit {
+        budget: max_output_bytes,
+        pgid: child.id() as libc::pid_t,
+        total: AtomicU64::new(0),
+        exceeded: AtomicBool::new(false),
+    };
+
+    let stdin = child.stdin.take();
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let (done, finished) = mpsc::channel::<()>();
+    let (stdout, stderr, status, timed_out, signalled) = std::thread::scope(|scope| {
+        // Dropping `done` once the command exits stops the watchdog early
+        let watchdog = options.timeout.map(|timeout| {
+            scope.spawn(move || {
+                if finished.recv_timeout(timeout) == Err(mpsc::RecvTimeoutError::Timeout) {
+                    warn!(pgid = limit.pgid, timeout = ?timeout, "command timed out, killing process group");
+                    // SAFETY: kill(2) has no memory-safety preconditions
+                    unsafe { libc::kill(-limit.pgid, libc::SIGKILL) };
+                    return Some(timeout);
+                }
+                None
+            })
+        });
+        // Written from its own thread so a command that prints before it
+        // has read all its input can't deadlock against us; closing the
+        // pipe afterwards signals end of input
+        if let (Some(mut pipe), Some(input)) = (stdin, &options.stdin) {
+            scope.spawn(move || {
+                // A command may exit without reading it all
+                if let Err(e) = pipe.write_all(input.as_bytes()) {
+                    if e.kind() != io::ErrorKind::BrokenPipe {
+                        warn!(error = %e, "failed to write command stdin");
+                    }
+                }
+            });
+        }
+        let output = options.output.as_ref();
+        let limit = &limit;
+        let stdout = scope.spawn(move || capture(stdout, OutputStream::Stdout, limit, output));
+        let stderr = capture(stderr, OutputStream::Stderr, limit, output);
+        let stdout = stdout.join().expect("stdout reader panicked");
+        let status = if options.usage {
+            wait_with_usage(&child).map(|(status, usage)| (status, Some(usage)))
+        } else {
+            child.wait().map(|status| (status, None))
+        };
+        let signalled = options.process.as_ref().and_then(ProcessHandle::detach);
+        drop(done);
+        let timed_out = watchdog.and_then(|w| w.join().expect("watchdog panicked"));
+        (stdout, stderr, status, timed_out, signalled)
+    });
+
+    let (status, usage) = status?;
+    Ok(RunOutput {
+        status,
+        stdout: stdout?,
+        stderr: stderr?,
+        output_limit_exceeded: limit.exceeded.into_inner(),
+        timed_out,
+        signalled,
+        usage,
+    })
+}
+
+/// Wait for `child` to exit, also returning the resources it and the
+/// descendants it waited for used.
+///
+/// `Child::wait` can't report usage, so the child is reaped with `wait4`
+/// instead; `child` must not be waited on again.
+fn wait_with_usage(child: &Child) -> io::Result<(ExitStatus, ResourceUsage)> {
+    let pid = child.id() as libc::pid_t;
+    let mut status = 0;
+    // SAFETY: rusage is plain data, for which all zeroes is a valid value
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        // SAFETY: status and rusage are valid for writes
+        if unsafe { libc::wait4(pid, &mut status, 0, &mut rusage) } >= 0 {
+            break;
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+    let millis = |time: libc::timeval| time.tv_sec as u64 * 1000 + time.tv_usec as u64 / 1000;
+    let usage = ResourceUsage {
+        // Linux reports ru_maxrss in KiB
+        max_rss_kib: rusage.ru_maxrss as u64,
+        user_time_ms: millis(rusage.ru_utime),
+        system_time_ms: millis(rusage.ru_stime),
+    };
+    Ok((ExitStatus::from_raw(status), usage))
+}
+
+/// Output budget shared by a process's stdout and stderr.
+struct OutputLimit {
+    budget: Option<u64>,
+    /// Process group to kill when the budget is exceeded.
+    pgid: libc::pid_t,
+    total: AtomicU64,
+    exceeded: AtomicBool,
+}
+
+impl OutputLimit {
+    /// Count `len` more bytes of output, killing the process group the
+    /// first time the total goes over budget.
+    fn record(&self, len: usize) {
+        let Some(budget) = self.budget else {
+            return;
+        };
+        let total = self.total.fetch_add(len as u64, Ordering::Relaxed) + len as u64;
+        if total > budget && !self.exceeded.swap(true, Ordering::Relaxed) {
+            warn!(
+                budget = budget,
+                pgid = self.pgid,
+                "output budget exceeded, killing process group"
+            );
+            // SAFETY: kill(2) has no memory-safety preconditions
+            unsafe { libc::kill(-self.pgid, libc::SIGKILL) };
+        }
+    }
+}
+
+/// Read `pipe` to EOF, keeping the start of the output, or handing all of
+/// it to `output` if set.
+fn capture(
+    mut pipe: impl Read,
+    stream: OutputStream,
+    limit: &OutputLimit,
+    output: Option<&OutputSink>,
+) -> io::Result<Vec<u8>> {
+    let mut kept = Vec::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = match pipe.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        match output {
+            Some(sink) => {
+                // Hold back a character split across reads until the rest arrives
+                kept.extend_from_slice(&buf[..n]);
+                let data = take_utf8(&mut kept);
+                if !data.is_empty() {
+                    (sink.0)(stream, data);
+                }
+            }
+            None => {
+                let keep = n.min((MAX_OUTPUT_SIZE + 1).saturating_sub(kept.len()));
+                kept.extend_from_slice(&buf[..keep]);
+            }
+        }
+        limit.record(n);
+    }
+    if let Some(sink) = output {
+        if !kept.is_empty() {
+            (sink.0)(stream, String::from_utf8_lossy(&kept).into_owned());
+        }
+        kept.clear();
+    }
+    Ok(kept)
+}
+
+/// Build the result of a finished process, logging its exit code and
+/// output sizes as `label` ("command", "code", ...).
+///
+/// Stdout and stderr are truncated to 1MB.
+fn into_exec_result(out: RunOutput, label: &str) -> ExecResult {
+    let exit_code = out.status.code().unwrap_or(-1);
+    let stdout = truncate_output(
+        String::from_utf8_lossy(&out.stdout).into_owned(),
+        MAX_OUTPUT_SIZE,
+    );
+    let stderr = out.stderr_text();
+    debug!(
+        exit_code = exit_code,
+        stdout_len = stdout.len(),
+        stderr_len = stderr.len(),
+        "{} completed",
+        label
+    );
+    ExecResult {
+        exit_code,
+        stdout,
+        stderr,
+        output_limit_exceeded: out.output_limit_exceeded,
+        usage: out.usage,
+    }
+}
+
 /// Execute a shell command via `sh -c`.
 ///
 /// # Arguments
 /// * `cmd` - The shell command to execute.
+/// * `options` - Working directory and output budget.
 ///
 /// # Returns
 /// An `ExecResult` containing exit code, stdout, and stderr.
 /// Output is truncated to 1MB to prevent memory exhaustion.
-pub fn exec_command(cmd: &str) -> ExecResult {
-    debug!(cmd = %cmd, "executing shell command");
-    let output = Command::new("sh").args(["-c", cmd]).output();
+pub fn exec_command(cmd: &str, options: &RunOptions) -> ExecResult {
+    debug!(cmd = %redact(cmd), "executing shell command");
+    let output = run(Command::new("sh").args(["-c", cmd]), options);
 
     match output {
-        Ok(out) => {
-            let exit_code = out.status.code().unwrap_or(-1);
-            let stdout = truncate_output(
-                String::from_utf8_lossy(&out.stdout).into_owned(),
-                MAX_OUTPUT_SIZE,
-            );
-            let stderr = truncate_output(
-                String::from_utf8_lossy(&out.stderr).into_owned(),
-                MAX_OUTPUT_SIZE,
-            );
-            debug!(
-                exit_code = exit_code,
-                stdout_len = stdout.len(),
-                stderr_len = stderr.len(),
-                "command completed"
-            );
-            trace!(stdout = %stdout, stderr = %stderr, "command output");
-            ExecResult {
-                exit_code,
-                stdout,
-                stderr,
-            }
-        }
+        Ok(out) => into_exec_result(out, "command"),
         Err(e) => {
-            warn!(error = %e, cmd = %cmd, "command execution failed");
+            warn!(error = %e, cmd = %redact(cmd), "command execution failed");
             ExecResult::error(&e.to_string())
         }
     }
 }
 
+/// Execute a program directly, without a shell.
+///
+/// `argv[0]` is the program and the rest are passed as arguments verbatim,
+/// so no quoting, globbing or variable expansion takes place.
+///
+/// # Arguments
+/// * `argv` - Program and arguments. Must not be empty.
+/// * `options` - Working directory and output budget.
+///
+/// # Returns
+/// An `ExecResult` containing exit code, stdout, and stderr.
+/// Output is truncated to 1MB to prevent memory exhaustion.
+pub fn exec_argv(argv: &[String], options: &RunOptions) -> ExecResult {
+    let Some((program, args)) = argv.split_first() else {
+        return ExecResult::error("argv must not be empty");
+    };
+
+    debug!(program = %program, argc = argv.len(), "executing argv");
+    let output = run(Command::new(program).args(args), options);
+
+    match output {
+        Ok(out) => into_exec_result(out, "argv"),
+        Err(e) => {
+            warn!(error = %e, program = %program, "argv execution failed");
+            ExecResult::error(&format!("failed to execute {}: {}", program, e))
+        }
+    }
+}
+
 /// Execute code in a specified programming language.
 ///
 /// Supported languages:
 /// - `python`, `python3` - Python 3
 /// - `node`, `javascript` - Node.js
 /// - `bash`, `sh` - Shell script
+/// - `ruby` - Ruby
+/// - `go` - `go run` on a temporary `main.go`
+/// - `typescript`, `ts`, `deno` - `deno run` on a temporary `main.ts`
+/// - `rust` - compiled with `rustc` from a temporary `main.rs`, then run
+///
+/// Languages run from a file get a fresh temporary directory, removed once
+/// the program exits.
 ///
 /// # Arguments
 /// * `lang` - The programming language.
 /// * `code` - The code to execute.
+/// * `options` - Working directory and output budget.
 ///
 /// # Returns
 /// An `ExecResult` containing exit code, stdout, and stderr.
-pub fn exec_code(lang: &str, code: &str) -> ExecResult {
+pub fn exec_code(lang: &str, code: &str, options: &RunOptions) -> ExecResult {
     debug!(lang = %lang, code_len = code.len(), "executing code");
-    trace!(code = %code, "code to execute");
+    trace!(code = %redact(code), "code to execute");
+
+    let lang = lang.to_lowercase();
+    let source_name = source_file_name(&lang);
+    let dir = match source_name.map(|name| write_source(name, code)).transpose() {
+        Ok(dir) => dir,
+        Err(e) => return ExecResult::error(&e),
+    };
+    let file = |name: &str| {
+        dir.as_ref()
+            .map(|dir| dir.0.join(name).to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let (source, binary) = (file(source_name.unwrap_or_default()), file("main"));
 
-    let (program, args): (&str, Vec<&str>) = match lang.to_lowercase().as_str() {
+    let (program, args): (&str, Vec<&str>) = match lang.as_str() {
         "python" | "python3" => ("python3", vec!["-c", code]),
         "node" | "javascript" | "js" => ("node", vec!["-e", code]),
         "bash" => ("bash", vec!["-c", code]),
         "sh" => ("sh", vec!["-c", code]),
+        "ruby" => ("ruby", vec!["-e", code]),
+        "go" => ("go", vec!["run", &source]),
+        "typescript" | "ts" | "deno" => ("deno", vec!["run", "--allow-all", "--quiet", &source]),
+        "rust" => ("sh", vec!["-c", RUST_RUNNER, "sh", &source, &binary]),
         _ => {
             warn!(lang = %lang, "unsupported language requested");
             return ExecResult::error(&format!("unsupported language: {}", lang));
@@ -97,39 +575,188 @@ pub fn exec_code(lang: &str, code: &str) -> ExecResult {
     };
 
     debug!(program = %program, "using interpreter");
-    let output = Command::new(program).args(&args).output();
+    let output = run(Command::new(program).args(&args), options);
 
     match output {
-        Ok(out) => {
-            let exit_code = out.status.code().unwrap_or(-1);
-            let stdout = truncate_output(
-                String::from_utf8_lossy(&out.stdout).into_owned(),
-                MAX_OUTPUT_SIZE,
-            );
-            let stderr = truncate_output(
-                String::from_utf8_lossy(&out.stderr).into_owned(),
-                MAX_OUTPUT_SIZE,
-            );
-            debug!(
-                exit_code = exit_code,
-                stdout_len = stdout.len(),
-                stderr_len = stderr.len(),
-                "code execution completed"
-            );
-            trace!(stdout = %stdout, stderr = %stderr, "code output");
-            ExecResult {
-                exit_code,
-                stdout,
-                stderr,
-            }
-        }
+        Ok(out) => into_exec_result(out, "code"),
         Err(e) => {
             warn!(error = %e, program = %program, "code execution failed");
-            ExecResult {
-                exit_code: -1,
-                stdout: String::new(),
-                stderr: format!("failed to execute {}: {}", program, e),
-            }
+            ExecResult::error(&format!("failed to execute {}: {}", program, e))
+        }
+    }
+}
+
+/// Compiles `$1` to `$2` and runs it, for `exec_code` in Rust.
+const RUST_RUNNER: &str = r#"rustc --edition 2021 -o "$2" "$1" && exec "$2""#;
+
+/// Source file `exec_code` writes `code` to, for languages that can't run
+/// it inline.
+fn source_file_name(lang: &str) -> Option<&'static str> {
+    match lang {
+        "go" => Some("main.go"),
+        "typescript" | "ts" | "deno" => Some("main.ts"),
+        "rust" => Some("main.rs"),
+        _ => None,
+    }
+}
+
+/// Write `code` to `name` in a fresh temporary directory.
+fn write_source(name: &str, code: &str) -> Result<ProjectDir, String> {
+    let dir = ProjectDir::create(None).map_err(|e| {
+        warn!(error = %e, "failed to create source dir");
+        format!("failed to create source dir: {}", e)
+    })?;
+    std::fs::write(dir.0.join(name), code).map_err(|e| {
+        warn!(error = %e, "failed to write source file");
+        format!("failed to write '{}': {}", name, e)
+    })?;
+    Ok(dir)
+}
+
+/// Temporary project directory, removed on drop.
+struct ProjectDir(PathBuf);
+
+impl ProjectDir {
+    /// Create a fresh directory in `parent`, or the system temp dir.
+    fn create(parent: Option<&Path>) -> std::io::Result<Self> {
+        let n = PROJECT_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = parent
+            .map(Path::to_path_buf)
+            .unwrap_or_else(std::env::temp_dir)
+            .join(format!("bouvet-project-{}-{}", std::process::id(), n));
+        std::fs::create_dir_all(&path)?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for ProjectDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.0) {
+            warn!(path = %self.0.display(), error = %e, "failed to remove project dir");
+        }
+    }
+}
+
+/// Check that a project file path stays inside the project directory.
+fn validate_project_path(path: &str) -> Result<(), String> {
+    let relative = !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if relative {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid project path '{}': must be relative without '..'",
+            path
+        ))
+    }
+}
+
+/// Execute a multi-file program.
+///
+/// Writes `files` to a fresh temporary directory, runs `entry` with the
+/// language's interpreter from inside it, and removes the directory
+/// afterwards. Sibling files can be imported by the entry point.
+///
+/// Supported languages:
+/// - `python`, `python3` - `python3 <entry>`
+/// - `node`, `javascript` - `node <entry>`
+/// - `bash`, `sh` - `bash <entry>` / `sh <entry>`
+/// - `go` - `go run .` (a `go.mod` is generated if none is provided)
+///
+/// The program runs like any other command, under the limits in
+/// `options`; its `cwd` is where the project directory is created.
+///
+/// # Arguments
+/// * `lang` - The programming language.
+/// * `entry` - Relative path of the entry point; must be one of `files`.
+/// * `files` - Relative path to content of every project file.
+/// * `options` - Output budget, timeout, environment, stdin and usage.
+///
+/// # Returns
+/// An `ExecResult` containing exit code, stdout, and stderr.
+/// Output is truncated to 1MB to prevent memory exhaustion.
+pub fn exec_project(
+    lang: &str,
+    entry: &str,
+    files: &HashMap<String, String>,
+    options: &RunOptions,
+) -> ExecResult {
+    debug!(lang = %lang, entry = %entry, files = files.len(), "executing project");
+
+    let lang = lang.to_lowercase();
+    let (program, args): (&str, Vec<&str>) = match lang.as_str() {
+        "python" | "python3" => ("python3", vec![entry]),
+        "node" | "javascript" | "js" => ("node", vec![entry]),
+        "bash" => ("bash", vec![entry]),
+        "sh" => ("sh", vec![entry]),
+        "go" => ("go", vec!["run", "."]),
+        _ => {
+            warn!(lang = %lang, "unsupported language requested");
+            return ExecResult::error(&format!("unsupported language: {}", lang));
+        }
+    };
+
+    if files.len() > MAX_PROJECT_FILES {
+        return ExecResult::error(&format!(
+            "too many files ({}, max {})",
+            files.len(),
+            MAX_PROJECT_FILES
+        ));
+    }
+    if let Some(err) = files
+        .keys()
+        .map(String::as_str)
+        .chain([entry])
+        .find_map(|path| validate_project_path(path).err())
+    {
+        return ExecResult::error(&err);
+    }
+    if !files.contains_key(entry) {
+        return ExecResult::error(&format!(
+            "entry '{}' is not one of the provided files",
+            entry
+        ));
+    }
+
+    let dir = match ProjectDir::create(options.cwd.as_deref()) {
+        Ok(dir) => dir,
+        Err(e) => {
+            warn!(error = %e, "failed to create project dir");
+            return ExecResult::error(&format!("failed to create project dir: {}", e));
+        }
+    };
+    for (path, content) in files {
+        let full = dir.0.join(path);
+        let written = match full.parent() {
+            Some(parent) => std::fs::create_dir_all(parent),
+            None => Ok(()),
+        }
+        .and_then(|()| std::fs::write(&full, content));
+        if let Err(e) = written {
+            warn!(path = %path, error = %e, "failed to write project file");
+            return ExecResult::error(&format!("failed to write '{}': {}", path, e));
+        }
+    }
+    if lang == "go" && !files.contains_key("go.mod") {
+        if let Err(e) = std::fs::write(dir.0.join("go.mod"), "module project\n") {
+            return ExecResult::error(&format!("failed to write go.mod: {}", e));
+        }
+    }
+
+    debug!(program = %program, dir = %dir.0.display(), "running project");
+    let options = RunOptions {
+        cwd: Some(dir.0.clone()),
+        ..options.clone()
+    };
+    let output = run(Command::new(program).args(&args), &options);
+
+    match output {
+        Ok(out) => into_exec_result(out, "project"),
+        Err(e) => {
+            warn!(error = %e, program = %program, "project execution failed");
+            ExecResult::error(&format!("failed to execute {}: {}", program, e))
         }
     }
 }
@@ -140,7 +767,7 @@ mod tests {
 
     #[test]
     fn test_exec_command_echo() {
-        let result = exec_command("echo hello");
+        let result = exec_command("echo hello", &RunOptions::default());
         assert_eq!(result.exit_code, 0);
         assert_eq!(result.stdout.trim(), "hello");
         assert!(result.stderr.is_empty());
@@ -148,22 +775,342 @@ mod tests {
 
     #[test]
     fn test_exec_command_exit_code() {
-        let result = exec_command("exit 42");
+        let result = exec_command("exit 42", &RunOptions::default());
         assert_eq!(result.exit_code, 42);
     }
 
     #[test]
     fn test_exec_command_stderr() {
-        let result = exec_command("echo error >&2");
+        let result = exec_command("echo error >&2", &RunOptions::default());
         assert_eq!(result.exit_code, 0);
         assert!(result.stdout.is_empty());
         assert_eq!(result.stderr.trim(), "error");
     }
 
+    #[test]
+    fn test_exec_exports_command_env() {
+        // The only test that installs variables, so others see none
+        init_env(vec![
+            ("http_proxy".into(), "http://proxy.corp:3128".into()),
+            ("HTTP_PROXY".into(), "http://proxy.corp:3128".into()),
+            ("no_proxy".into(), "localhost".into()),
+        ]);
+
+        let result = exec_command(
+            "echo \"$http_proxy $HTTP_PROXY $no_proxy\"",
+            &RunOptions::default(),
+        );
+        assert_eq!(
+            result.stdout.trim(),
+            "http://proxy.corp:3128 http://proxy.corp:3128 localhost"
+        );
+
+        let result = exec_code(
+            "python",
+            "import os; print(os.environ['http_proxy'])",
+            &RunOptions::default(),
+        );
+        assert_eq!(result.exit_code, 0, "stderr: {}", result.stderr);
+        assert_eq!(result.stdout.trim(), "http://proxy.corp:3128");
+    }
+
+    #[test]
+    fn test_exec_sets_call_env() {
+        let options = RunOptions {
+            env: HashMap::from([
+                ("API_TOKEN".to_string(), "s3cret value".to_string()),
+                ("PATH".to_string(), "/usr/bin:/bin".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let result = exec_command("echo \"$API_TOKEN $PATH\"", &options);
+        assert_eq!(result.stdout, "s3cret value /usr/bin:/bin\n");
+
+        let result = exec_code(
+            "python",
+            "import os; print(os.environ['API_TOKEN'])",
+            &options,
+        );
+        assert_eq!(result.exit_code, 0, "stderr: {}", result.stderr);
+        assert_eq!(result.stdout, "s3cret value\n");
+
+        // Nothing carries over to the next command
+        let result = exec_command("echo \"${API_TOKEN:-unset}\"", &RunOptions::default());
+        assert_eq!(result.stdout, "unset\n");
+
+        let result = exec_command(
+            "true",
+            &RunOptions {
+                env: HashMap::from([("A=B".to_string(), String::new())]),
+                ..Default::default()
+            },
+        );
+        assert_eq!(result.exit_code, -1);
+        assert!(result.stderr.contains("invalid environment variable name"));
+    }
+
+    #[test]
+    fn test_exec_feeds_stdin() {
+        let options = RunOptions {
+            stdin: Some("b\na\n".to_string()),
+            ..Default::default()
+        };
+        let result = exec_command("sort", &options);
+        assert_eq!(result.exit_code, 0, "stderr: {}", result.stderr);
+        assert_eq!(result.stdout, "a\nb\n");
+
+        let result = exec_code(
+            "python",
+            "import sys; print(sys.stdin.read().split())",
+            &options,
+        );
+        assert_eq!(result.stdout, "['b', 'a']\n");
+
+        // More than a pipe buffer, to a command that never reads it
+        let options = RunOptions {
+            stdin: Some("x".repeat(1 << 20)),
+            ..Default::default()
+        };
+        let result = exec_command("echo done", &options);
+        assert_eq!(result.stdout, "done\n");
+
+        let result = exec_command("wc -c", &options);
+        assert_eq!(result.stdout.trim(), "1048576");
+
+        // Without input, stdin is empty rather than inherited
+        let result = exec_command("cat", &RunOptions::default());
+        assert_eq!(result.stdout, "");
+    }
+
+    #[test]
+    fn test_usage_reports_cpu_time() {
+        let burn = "i=0; while [ $i -lt 200000 ]; do i=$((i + 1)); done; echo $i";
+        let result = exec_command(burn, &RunOptions::default());
+        assert_eq!(result.stdout, "200000\n");
+        assert!(result.usage.is_none(), "usage is opt-in");
+
+        let options = RunOptions {
+            usage: true,
+            ..Default::default()
+        };
+        let result = exec_command(burn, &options);
+        assert_eq!(result.stdout, "200000\n");
+        let usage = result.usage.expect("usage requested");
+        assert!(usage.user_time_ms > 0, "{usage:?}");
+        assert!(usage.max_rss_kib > 0, "{usage:?}");
+
+        let result = exec_command("exit 7", &options);
+        assert_eq!(result.exit_code, 7);
+        assert!(result.usage.is_some());
+    }
+
+    #[test]
+    fn test_output_sink_receives_output() {
+        let chunks = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink_chunks = Arc::clone(&chunks);
+        let options = RunOptions {
+            output: Some(OutputSink::new(move |stream, data| {
+                sink_chunks.lock().unwrap().push((stream, data));
+            })),
+            timeout: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        let result = exec_command("echo out; echo err >&2; sleep 5", &options);
+        assert_eq!(result.stdout, "");
+        assert_eq!(result.stderr, "timed out after 1s");
+
+        let chunks = chunks.lock().unwrap();
+        assert!(chunks.contains(&(OutputStream::Stdout, "out\n".to_string())));
+        assert!(chunks.contains(&(OutputStream::Stderr, "err\n".to_string())));
+    }
+
+    fn budget(bytes: u64) -> RunOptions {
+        RunOptions {
+            max_output_bytes: Some(bytes),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_exec_runs_in_cwd() {
+        let dir = std::env::temp_dir().join(format!("bouvet-cwd-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let options = RunOptions {
+            cwd: Some(dir.clone()),
+            ..Default::default()
+        };
+
+        let result = exec_command("pwd", &options);
+        assert_eq!(result.stdout.trim(), dir.to_str().unwrap());
+
+        let result = exec_code("python", "import os; print(os.getcwd())", &options);
+        assert_eq!(result.exit_code, 0, "stderr: {}", result.stderr);
+        assert_eq!(result.stdout.trim(), dir.to_str().unwrap());
+
+        let result = exec_command(
+            "pwd",
+            &RunOptions {
+                cwd: Some(dir.join("missing")),
+                ..Default::default()
+            },
+        );
+        assert_eq!(result.exit_code, -1);
+        assert!(
+            result.stderr.contains("missing' is not a directory"),
+            "stderr: {}",
+            result.stderr
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_exec_command_killed_over_output_budget() {
+        // Never exits on its own; a grandchild of the shell does the writing
+        let result = exec_command("yes | cat", &budget(64 * 1024));
+        assert!(result.output_limit_exceeded);
+        assert_eq!(result.exit_code, -1);
+        assert!(result.stdout.starts_with("y\ny\n"));
+
+        let result = exec_command("echo hello", &budget(64 * 1024));
+        assert!(!result.output_limit_exceeded);
+        assert_eq!(result.stdout, "hello\n");
+    }
+
+    #[test]
+    fn test_exec_command_killed_after_timeout() {
+        let options = RunOptions {
+            timeout: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        let start = std::time::Instant::now();
+        // The background sleep would hold stderr open if only sh were killed
+        let result = exec_command("echo started; sleep 30 & sleep 30", &options);
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert_eq!(result.exit_code, -1);
+        assert_eq!(result.stdout, "started\n");
+        assert_eq!(result.stderr, "timed out after 1s");
+
+        let result = exec_code("python", "print('fast')", &options);
+        assert_eq!(result.exit_code, 0, "stderr: {}", result.stderr);
+        assert_eq!(result.stdout, "fast\n");
+    }
+
+    #[test]
+    fn test_exec_command_output_truncated() {
+        let result = exec_command(
+            "head -c 2000000 /dev/zero | tr '\\0' a",
+            &RunOptions::default(),
+        );
+        assert!(!result.output_limit_exceeded);
+        assert!(result.stdout.ends_with("[output truncated]"));
+        assert!(result.stdout.len() < MAX_OUTPUT_SIZE + 64);
+    }
+
+    #[test]
+    fn test_exec_argv_does_not_expand() {
+        let argv = vec!["echo".to_string(), "$HOME".to_string()];
+        let result = exec_argv(&argv, &RunOptions::default());
+        assert_eq!(result.exit_code, 0);
+        assert_eq!(result.stdout.trim(), "$HOME");
+    }
+
+    #[test]
+    fn test_exec_argv_empty() {
+        let result = exec_argv(&[], &RunOptions::default());
+        assert_eq!(result.exit_code, -1);
+        assert!(result.stderr.contains("empty"));
+    }
+
+    #[test]
+    fn test_exec_code_rust() {
+        if Command::new("rustc").arg("--version").output().is_err() {
+            return;
+        }
+        let code = "fn main() { println!(\"{}\", 6 * 7); }";
+        let result = exec_code("rust", code, &RunOptions::default());
+        assert_eq!(result.exit_code, 0, "{}", result.stderr);
+        assert_eq!(result.stdout.trim(), "42");
+
+        let result = exec_code("rust", "fn main() { oops }", &RunOptions::default());
+        assert_ne!(result.exit_code, 0);
+        assert!(result.stderr.contains("error"));
+    }
+
     #[test]
     fn test_exec_code_unsupported() {
-        let result = exec_code("cobol", "DISPLAY 'HELLO'");
+        let result = exec_code("cobol", "DISPLAY 'HELLO'", &RunOptions::default());
         assert_eq!(result.exit_code, -1);
         assert!(result.stderr.contains("unsupported language"));
     }
+
+    #[test]
+    fn test_exec_project_python_imports_sibling() {
+        let files = HashMap::from([
+            (
+                "main.py".to_string(),
+                "from helpers import greet\nprint(greet('sandbox'))\n".to_string(),
+            ),
+            (
+                "helpers.py".to_string(),
+                "def greet(name):\n    return f'hello {name}'\n".to_string(),
+            ),
+        ]);
+        let result = exec_project("python", "main.py", &files, &RunOptions::default());
+        assert_eq!(result.exit_code, 0, "stderr: {}", result.stderr);
+        assert_eq!(result.stdout.trim(), "hello sandbox");
+    }
+
+    #[test]
+    fn test_exec_project_rejects_escaping_paths() {
+        for path in ["../evil.py", "/etc/evil.py", "a/../../evil.py", ""] {
+            let files = HashMap::from([
+                ("main.py".to_string(), "print(1)".to_string()),
+                (path.to_string(), "print(2)".to_string()),
+            ]);
+            let result = exec_project("python", "main.py", &files, &RunOptions::default());
+            assert_eq!(result.exit_code, -1, "{path} should be rejected");
+            assert!(result.stderr.contains("invalid project path"));
+        }
+    }
+
+    #[test]
+    fn test_exec_project_requires_entry_in_files() {
+        let files = HashMap::from([("lib.py".to_string(), String::new())]);
+        let result = exec_project("python", "main.py", &files, &RunOptions::default());
+        assert_eq!(result.exit_code, -1);
+        assert!(result.stderr.contains("entry 'main.py'"));
+    }
+
+    #[test]
+    fn test_format_timeout() {
+        assert_eq!(format_timeout(Duration::from_secs(30)), "30s");
+        assert_eq!(format_timeout(Duration::from_millis(250)), "250ms");
+        assert_eq!(format_timeout(Duration::from_micros(1)), "1ms");
+        assert_eq!(format_timeout(Duration::from_millis(1500)), "1500ms");
+    }
+
+    #[test]
+    fn test_exec_project_runs_under_run_options() {
+        let files = HashMap::from([(
+            "main.sh".to_string(),
+            "read line; echo \"$line $GREETING\"; while :; do :; done".to_string(),
+        )]);
+        let options = RunOptions {
+            timeout: Some(Duration::from_secs(1)),
+            env: HashMap::from([("GREETING".to_string(), "hi".to_string())]),
+            stdin: Some("input\n".to_string()),
+            ..Default::default()
+        };
+        let started = std::time::Instant::now();
+        let result = exec_project("sh", "main.sh", &files, &options);
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(result.exit_code, -1);
+        assert_eq!(result.stdout, "input hi\n");
+        assert!(
+            result.stderr.ends_with("timed out after 1s"),
+            "{}",
+            result.stderr
+        );
+    }
 }
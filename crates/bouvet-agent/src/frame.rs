@@ -0,0 +1,79 @@
+//! Message framing for bouvet-agent.
+//!
+//! Messages are newline-delimited JSON, optionally zstd-compressed with
+//! [`bouvet_common::frame`], which the host uses too. Compression is opt-in
+//! per connection: the host advertises support by sending a compressed
+//! frame, after which large responses are compressed too.
+
+use bouvet_common::frame::{self, ZSTD};
+
+/// Compression algorithms this agent understands, reported by `info`.
+pub const SUPPORTED_COMPRESSION: &[&str] = &[ZSTD];
+
+/// Per-connection framing state.
+#[derive(Debug, Default)]
+pub struct FrameCodec {
+    compress: bool,
+}
+
+impl FrameCodec {
+    /// Decode a received line into a JSON message.
+    ///
+    /// Receiving a compressed frame enables compression of outgoing
+    /// messages for the rest of the connection.
+    pub fn decode(&mut self, line: &str) -> Result<String, String> {
+        if line.starts_with(frame::COMPRESSED_PREFIX) {
+            self.compress = true;
+        }
+        frame::decode(line).map_err(|e| e.to_string())
+    }
+
+    /// Encode a JSON message into a line (without the trailing newline).
+    pub fn encode(&self, json: String) -> String {
+        if self.compress {
+            frame::encode(json)
+        } else {
+            json
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frame::{COMPRESSED_PREFIX, COMPRESSION_THRESHOLD};
+
+    fn large_message() -> String {
+        let content = "hello from the guest\n".repeat(2 * COMPRESSION_THRESHOLD);
+        serde_json::json!({"jsonrpc": "2.0", "id": 1, "result": {"content": content}}).to_string()
+    }
+
+    #[test]
+    fn test_codec_compresses_only_after_peer_opts_in() {
+        let json = large_message();
+        let mut codec = FrameCodec::default();
+        assert_eq!(codec.encode(json.clone()), json);
+
+        let request = r#"{"jsonrpc":"2.0","id":1,"method":"ping","params":{}}"#;
+        let frame = frame::compress(request).unwrap();
+        assert_eq!(codec.decode(&frame).unwrap(), request);
+
+        let encoded = codec.encode(json.clone());
+        assert!(encoded.starts_with(COMPRESSED_PREFIX));
+        assert_eq!(codec.decode(&encoded).unwrap(), json);
+    }
+
+    #[test]
+    fn test_codec_leaves_small_messages_plain() {
+        let mut codec = FrameCodec::default();
+        codec.decode(&frame::encode(large_message())).unwrap();
+        let small = r#"{"jsonrpc":"2.0","id":2,"result":{"pong":true}}"#.to_string();
+        assert_eq!(codec.encode(small.clone()), small);
+    }
+
+    #[test]
+    fn test_codec_rejects_garbage() {
+        let mut codec = FrameCodec::default();
+        assert!(codec.decode("zstd:not base64!").is_err());
+    }
+}
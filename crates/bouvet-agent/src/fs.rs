@@ -1,16 +1,30 @@
 //! File system operations for bouvet-agent.
 //!
-//! Provides functions to read, write, and list files/directories.
-
-use crate::protocol::FileEntry;
+//! Provides functions to read, write, touch, and list files/directories.
+
+use crate::protocol::{
+    DownloadFileResult, FileEntry, FileType, ReadFileChunkResult, SortBy, StatResult,
+};
+use crate::sha256::sha256_hex;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use bouvet_common::glob_match;
+use std::collections::HashSet;
 use std::fs;
-use std::path::Path;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use tracing::{debug, trace, warn};
 
 /// Maximum file size for read_file (10 MB).
 /// Prevents memory exhaustion from reading huge files.
 const MAX_READ_SIZE: u64 = 10 * 1024 * 1024;
 
+/// Maximum size of one `write_file_chunk` or `read_file_chunk` (8 MB).
+/// Larger files are moved in several chunks.
+pub const MAX_FILE_CHUNK: u64 = 8 * 1024 * 1024;
+
 /// Read the contents of a file.
 ///
 /// # Arguments
@@ -44,10 +58,44 @@ pub fn read_file(path: &str) -> Result<String, String> {
     })?;
 
     debug!(path = %path, size = content.len(), "file read successfully");
-    trace!(content = %content, "file content");
     Ok(content)
 }
 
+/// Read a file's raw bytes, base64-encoded, with their size and SHA-256.
+///
+/// Unlike [`read_file`] this works for binary files. Files larger than
+/// 10MB are rejected, as for `read_file`.
+pub fn download_file(path: &str) -> Result<DownloadFileResult, String> {
+    debug!(path = %path, "downloading file");
+
+    let metadata = fs::metadata(path).map_err(|e| {
+        warn!(path = %path, error = %e, "failed to stat file");
+        format!("failed to stat '{}': {}", path, e)
+    })?;
+
+    if metadata.len() > MAX_READ_SIZE {
+        warn!(path = %path, size = metadata.len(), max = MAX_READ_SIZE, "file too large");
+        return Err(format!(
+            "file '{}' is too large ({} bytes, max {} bytes)",
+            path,
+            metadata.len(),
+            MAX_READ_SIZE
+        ));
+    }
+
+    let data = fs::read(path).map_err(|e| {
+        warn!(path = %path, error = %e, "failed to read file");
+        format!("failed to read '{}': {}", path, e)
+    })?;
+
+    debug!(path = %path, size = data.len(), "file downloaded successfully");
+    Ok(DownloadFileResult {
+        content_base64: BASE64.encode(&data),
+        size: data.len() as u64,
+        sha256: sha256_hex(&data),
+    })
+}
+
 /// Write content to a file.
 ///
 /// Creates parent directories if they don't exist.
@@ -55,12 +103,13 @@ pub fn read_file(path: &str) -> Result<String, String> {
 /// # Arguments
 /// * `path` - Path to the file to write.
 /// * `content` - Content to write.
+/// * `mode` - Permission bits to set, e.g. `0o755`; `None` leaves them as
+///   they were (or the umask default for a new file).
 ///
 /// # Returns
 /// `true` on success, or an error message.
-pub fn write_file(path: &str, content: &str) -> Result<bool, String> {
+pub fn write_file(path: &str, content: &str, mode: Option<u32>) -> Result<bool, String> {
     debug!(path = %path, content_len = content.len(), "writing file");
-    trace!(content = %content, "content to write");
 
     // Create parent directories if needed
     if let Some(parent) = Path::new(path).parent() {
@@ -73,63 +122,442 @@ pub fn write_file(path: &str, content: &str) -> Result<bool, String> {
         }
     }
 
-    fs::write(path, content)
+    fs::write(path, content).map_err(|e| {
+        warn!(path = %path, error = %e, "failed to write file");
+        format!("failed to write '{}': {}", path, e)
+    })?;
+
+    if let Some(mode) = mode {
+        fs::set_permissions(path, fs::Permissions::from_mode(mode)).map_err(|e| {
+            warn!(path = %path, mode = format!("{mode:o}"), error = %e, "failed to set mode");
+            format!("failed to set mode of '{}': {}", path, e)
+        })?;
+    }
+
+    debug!(path = %path, "file written successfully");
+    Ok(true)
+}
+
+/// Write one chunk of a file too large for a single `write_file`.
+///
+/// Chunks are written front to back. Offset 0 creates or truncates the
+/// file (creating parent directories); every later chunk is appended and
+/// must start exactly at the file's current end, so a skipped, repeated or
+/// reordered chunk is rejected rather than leaving a hole or overwriting
+/// data. `is_last` syncs the file to disk.
+///
+/// # Returns
+/// The file size after the write, or an error message.
+pub fn write_file_chunk(
+    path: &str,
+    offset: u64,
+    data: &[u8],
+    is_last: bool,
+) -> Result<u64, String> {
+    debug!(path = %path, offset, len = data.len(), is_last, "writing file chunk");
+
+    if data.len() as u64 > MAX_FILE_CHUNK {
+        return Err(format!(
+            "chunk for '{}' is too large ({} bytes, max {} bytes)",
+            path,
+            data.len(),
+            MAX_FILE_CHUNK
+        ));
+    }
+
+    let mut file = if offset == 0 {
+        if let Some(parent) = Path::new(path).parent() {
+            if !parent.exists() {
+                debug!(parent = %parent.display(), "creating parent directories");
+                fs::create_dir_all(parent).map_err(|e| {
+                    warn!(path = %path, error = %e, "failed to create directories");
+                    format!("failed to create directories for '{}': {}", path, e)
+                })?;
+            }
+        }
+        fs::File::create(path).map_err(|e| {
+            warn!(path = %path, error = %e, "failed to create file");
+            format!("failed to create '{}': {}", path, e)
+        })?
+    } else {
+        let file = fs::OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map_err(|e| {
+                warn!(path = %path, error = %e, "failed to open file");
+                format!("failed to open '{}': {}", path, e)
+            })?;
+        let size = file
+            .metadata()
+            .map_err(|e| format!("failed to stat '{}': {}", path, e))?
+            .len();
+        if size != offset {
+            warn!(path = %path, offset, size, "chunk offset does not match file size");
+            return Err(format!(
+                "chunk for '{}' starts at offset {} but the next offset is {}",
+                path, offset, size
+            ));
+        }
+        file
+    };
+
+    file.write_all(data).map_err(|e| {
+        warn!(path = %path, error = %e, "failed to write file chunk");
+        format!("failed to write '{}': {}", path, e)
+    })?;
+    if is_last {
+        file.sync_all().map_err(|e| {
+            warn!(path = %path, error = %e, "failed to sync file");
+            format!("failed to sync '{}': {}", path, e)
+        })?;
+    }
+
+    Ok(offset + data.len() as u64)
+}
+
+/// Read up to `len` bytes of a file starting at `offset`, base64-encoded.
+///
+/// `len` may not exceed [`MAX_FILE_CHUNK`]. Reading at the end of the file
+/// returns an empty chunk; an offset past the end is an error.
+pub fn read_file_chunk(path: &str, offset: u64, len: u64) -> Result<ReadFileChunkResult, String> {
+    debug!(path = %path, offset, len, "reading file chunk");
+
+    if len > MAX_FILE_CHUNK {
+        return Err(format!(
+            "chunk length {} is too large (max {} bytes)",
+            len, MAX_FILE_CHUNK
+        ));
+    }
+
+    let mut file = fs::File::open(path).map_err(|e| {
+        warn!(path = %path, error = %e, "failed to open file");
+        format!("failed to open '{}': {}", path, e)
+    })?;
+    let size = file
+        .metadata()
+        .map_err(|e| format!("failed to stat '{}': {}", path, e))?
+        .len();
+    if offset > size {
+        return Err(format!(
+            "offset {} is past the end of '{}' ({} bytes)",
+            offset, path, size
+        ));
+    }
+
+    let mut data = Vec::with_capacity(len.min(size - offset) as usize);
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.take(len).read_to_end(&mut data))
+        .map_err(|e| {
+            warn!(path = %path, error = %e, "failed to read file chunk");
+            format!("failed to read '{}': {}", path, e)
+        })?;
+
+    Ok(ReadFileChunkResult {
+        eof: offset + data.len() as u64 >= size,
+        data: BASE64.encode(&data),
+        size,
+    })
+}
+
+/// Create a file if it doesn't exist and update its modification time.
+///
+/// Existing files are never truncated. Creates parent directories if they
+/// don't exist.
+///
+/// # Arguments
+/// * `path` - Path to the file to touch.
+///
+/// # Returns
+/// `true` on success, or an error message.
+pub fn touch(path: &str) -> Result<bool, String> {
+    debug!(path = %path, "touching file");
+
+    // Create parent directories if needed
+    if let Some(parent) = Path::new(path).parent() {
+        if !parent.exists() {
+            debug!(parent = %parent.display(), "creating parent directories");
+            fs::create_dir_all(parent).map_err(|e| {
+                warn!(path = %path, error = %e, "failed to create directories");
+                format!("failed to create directories for '{}': {}", path, e)
+            })?;
+        }
+    }
+
+    // Append mode creates the file if missing without truncating existing content
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| {
+            warn!(path = %path, error = %e, "failed to open file for touch");
+            format!("failed to touch '{}': {}", path, e)
+        })?;
+
+    file.set_modified(SystemTime::now())
         .map(|_| {
-            debug!(path = %path, "file written successfully");
+            debug!(path = %path, "file touched successfully");
             true
         })
         .map_err(|e| {
-            warn!(path = %path, error = %e, "failed to write file");
-            format!("failed to write '{}': {}", path, e)
+            warn!(path = %path, error = %e, "failed to update modification time");
+            format!("failed to update mtime of '{}': {}", path, e)
         })
 }
 
+/// Describe a path without reading it: type, size, mode and mtime.
+///
+/// Symlinks are described themselves rather than followed.
+pub fn stat(path: &str) -> Result<StatResult, String> {
+    debug!(path = %path, "stat");
+
+    let metadata = fs::symlink_metadata(path).map_err(|e| {
+        debug!(path = %path, error = %e, "failed to stat path");
+        format!("failed to stat '{}': {}", path, e)
+    })?;
+
+    let file_type = metadata.file_type();
+    let file_type = if file_type.is_symlink() {
+        FileType::Symlink
+    } else if file_type.is_dir() {
+        FileType::Dir
+    } else if file_type.is_file() {
+        FileType::File
+    } else {
+        FileType::Other
+    };
+    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+    let modified_ms = match modified.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(since) => since.as_millis() as i64,
+        Err(e) => -(e.duration().as_millis() as i64),
+    };
+
+    Ok(StatResult {
+        file_type,
+        size: metadata.len(),
+        mode: metadata.permissions().mode() & 0o7777,
+        modified_ms,
+    })
+}
+
+/// Delete a file or directory.
+///
+/// Directories must be empty unless `recursive` is set. Symlinks are
+/// removed, not followed. The root directory can't be deleted.
+///
+/// # Returns
+/// `true` on success, or an error message.
+pub fn delete_file(path: &str, recursive: bool) -> Result<bool, String> {
+    debug!(path = %path, recursive, "deleting path");
+
+    if Path::new(path) == Path::new("/") {
+        return Err("refusing to delete '/'".to_string());
+    }
+
+    let metadata = fs::symlink_metadata(path).map_err(|e| {
+        warn!(path = %path, error = %e, "failed to stat path");
+        format!("failed to stat '{}': {}", path, e)
+    })?;
+
+    let result = if !metadata.is_dir() {
+        fs::remove_file(path)
+    } else if recursive {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_dir(path)
+    };
+    result.map_err(|e| {
+        warn!(path = %path, error = %e, "failed to delete path");
+        format!("failed to delete '{}': {}", path, e)
+    })?;
+
+    debug!(path = %path, "path deleted successfully");
+    Ok(true)
+}
+
+/// Rename or move a file or directory.
+///
+/// An existing file at `to` is replaced. Both paths must be on the same
+/// filesystem.
+///
+/// # Returns
+/// `true` on success, or an error message.
+pub fn rename(from: &str, to: &str) -> Result<bool, String> {
+    debug!(from = %from, to = %to, "renaming path");
+
+    fs::rename(from, to).map_err(|e| {
+        warn!(from = %from, to = %to, error = %e, "failed to rename path");
+        format!("failed to rename '{}' to '{}': {}", from, to, e)
+    })?;
+
+    debug!(from = %from, to = %to, "path renamed successfully");
+    Ok(true)
+}
+
+/// Create a directory and any missing parents.
+///
+/// Succeeds if the directory already exists.
+///
+/// # Returns
+/// `true` on success, or an error message.
+pub fn mkdir(path: &str) -> Result<bool, String> {
+    debug!(path = %path, "creating directory");
+
+    fs::create_dir_all(path).map_err(|e| {
+        warn!(path = %path, error = %e, "failed to create directory");
+        format!("failed to create directory '{}': {}", path, e)
+    })?;
+
+    debug!(path = %path, "directory created successfully");
+    Ok(true)
+}
+
+/// Most entries a single `list_dir` call returns, so a recursive listing of
+/// a large tree can't exhaust the agent's memory.
+pub const MAX_LIST_ENTRIES: usize = 10_000;
+
 /// List contents of a directory.
 ///
+/// Entries below the top level are named by their path relative to `path`,
+/// e.g. `src/main.rs`. Symlinks are listed but never followed, and a
+/// directory already visited (by device and inode) is not descended into
+/// again, so cycles can't make the walk loop.
+///
 /// # Arguments
 /// * `path` - Path to the directory to list.
+/// * `sort_by` - Key to sort entries by; ties are broken by name.
+/// * `descending` - Reverse the sort order.
+/// * `pattern` - Only include entries whose file name matches this glob.
+/// * `max_depth` - Levels to list; 1 lists only `path`'s own entries.
 ///
 /// # Returns
-/// A vector of `FileEntry` items, or an error message.
-pub fn list_dir(path: &str) -> Result<Vec<FileEntry>, String> {
-    debug!(path = %path, "listing directory");
-
-    let entries = fs::read_dir(path).map_err(|e| {
+/// A vector of `FileEntry` items, or an error message if the directory
+/// can't be read or holds more than [`MAX_LIST_ENTRIES`] matching entries.
+pub fn list_dir(
+    path: &str,
+    sort_by: SortBy,
+    descending: bool,
+    pattern: Option<&str>,
+    max_depth: usize,
+) -> Result<Vec<FileEntry>, String> {
+    debug!(path = %path, max_depth = max_depth, "listing directory");
+
+    let root = fs::metadata(path).map_err(|e| {
         warn!(path = %path, error = %e, "failed to read directory");
         format!("failed to read directory '{}': {}", path, e)
     })?;
+    let mut visited = HashSet::from([(root.dev(), root.ino())]);
+    let mut pending = vec![(PathBuf::from(path), String::new(), 1)];
 
     let mut result = Vec::new();
-    for entry in entries {
-        let entry = entry.map_err(|e| {
-            warn!(error = %e, "failed to read directory entry");
-            format!("failed to read entry: {}", e)
+    while let Some((dir, prefix, depth)) = pending.pop() {
+        let entries = fs::read_dir(&dir).map_err(|e| {
+            warn!(path = %dir.display(), error = %e, "failed to read directory");
+            format!("failed to read directory '{}': {}", dir.display(), e)
         })?;
 
-        let metadata = entry.metadata().map_err(|e| {
-            warn!(error = %e, "failed to get entry metadata");
-            format!("failed to get metadata: {}", e)
-        })?;
+        for entry in entries {
+            let entry = entry.map_err(|e| {
+                warn!(error = %e, "failed to read directory entry");
+                format!("failed to read entry: {}", e)
+            })?;
 
-        let name = entry.file_name().to_string_lossy().into_owned();
-        trace!(name = %name, is_dir = metadata.is_dir(), "found entry");
+            // Doesn't follow symlinks, so a symlinked directory is not a dir
+            let metadata = entry.metadata().map_err(|e| {
+                warn!(error = %e, "failed to get entry metadata");
+                format!("failed to get metadata: {}", e)
+            })?;
 
-        result.push(FileEntry {
-            name,
-            is_dir: metadata.is_dir(),
-            size: if metadata.is_file() {
-                metadata.len()
-            } else {
-                0
-            },
-        });
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let name = format!("{prefix}{file_name}");
+            if metadata.is_dir()
+                && depth < max_depth
+                && visited.insert((metadata.dev(), metadata.ino()))
+            {
+                pending.push((entry.path(), format!("{name}/"), depth + 1));
+            }
+            if pattern.is_some_and(|p| !glob_match(p, &file_name)) {
+                continue;
+            }
+            trace!(name = %name, is_dir = metadata.is_dir(), "found entry");
+
+            if result.len() == MAX_LIST_ENTRIES {
+                warn!(path = %path, max = MAX_LIST_ENTRIES, "directory listing too large");
+                return Err(format!(
+                    "listing of '{}' exceeds {} entries; narrow it with max_depth or pattern",
+                    path, MAX_LIST_ENTRIES
+                ));
+            }
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            result.push((
+                FileEntry {
+                    name,
+                    is_dir: metadata.is_dir(),
+                    size: if metadata.is_file() {
+                        metadata.len()
+                    } else {
+                        0
+                    },
+                },
+                modified,
+            ));
+        }
     }
 
-    // Sort by name for consistent output
-    result.sort_by(|a, b| a.name.cmp(&b.name));
+    // Name is the tie-breaker so output is consistent
+    result.sort_by(|(a, a_mtime), (b, b_mtime)| {
+        let order = match sort_by {
+            SortBy::Name => std::cmp::Ordering::Equal,
+            SortBy::Size => a.size.cmp(&b.size),
+            SortBy::Modified => a_mtime.cmp(b_mtime),
+        };
+        let order = order.then_with(|| a.name.cmp(&b.name));
+        if descending {
+            order.reverse()
+        } else {
+            order
+        }
+    });
 
     debug!(path = %path, count = result.len(), "directory listed successfully");
-    Ok(result)
+    Ok(result.into_iter().map(|(entry, _)| entry).collect())
+}
+
+/// Remove everything inside a directory, keeping the directory itself.
+///
+/// # Arguments
+/// * `path` - Directory to empty.
+///
+/// # Returns
+/// The number of top-level entries removed, or an error message.
+pub fn clear_dir(path: &str) -> Result<u64, String> {
+    debug!(path = %path, "clearing directory");
+    let entries = fs::read_dir(path).map_err(|e| {
+        warn!(path = %path, error = %e, "failed to read directory");
+        format!("failed to read directory '{}': {}", path, e)
+    })?;
+
+    let mut removed = 0;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("failed to read entry: {}", e))?;
+        let entry_path = entry.path();
+        let is_dir = entry
+            .file_type()
+            .map_err(|e| format!("failed to get file type: {}", e))?
+            .is_dir();
+        let result = if is_dir {
+            fs::remove_dir_all(&entry_path)
+        } else {
+            fs::remove_file(&entry_path)
+        };
+        result.map_err(|e| {
+            warn!(path = %entry_path.display(), error = %e, "failed to remove entry");
+            format!("failed to remove '{}': {}", entry_path.display(), e)
+        })?;
+        removed += 1;
+    }
+
+    debug!(path = %path, removed = removed, "directory cleared");
+    Ok(removed)
 }
 
 #[cfg(test)]
@@ -158,7 +586,7 @@ mod tests {
         let path_str = path.to_str().unwrap();
 
         let content = "Hello, bouvet-agent!";
-        assert!(write_file(path_str, content).is_ok());
+        assert!(write_file(path_str, content, None).is_ok());
         assert_eq!(read_file(path_str).unwrap(), content);
 
         // Cleanup
@@ -171,13 +599,202 @@ mod tests {
         let path = dir.join("nested/dirs/test.txt");
         let path_str = path.to_str().unwrap();
 
-        assert!(write_file(path_str, "content").is_ok());
+        assert!(write_file(path_str, "content", None).is_ok());
         assert!(path.exists());
 
         // Cleanup
         fs::remove_dir_all(dir).ok();
     }
 
+    #[test]
+    fn test_write_file_sets_mode() {
+        let dir = temp_dir();
+        let path = dir.join("run.sh");
+        let path_str = path.to_str().unwrap();
+
+        write_file(path_str, "#!/bin/sh\n", Some(0o755)).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o7777, 0o755);
+
+        // Rewriting without a mode keeps it
+        write_file(path_str, "#!/bin/sh\necho hi\n", None).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o7777, 0o755);
+
+        // Cleanup
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_write_and_read_file_in_chunks() {
+        let dir = temp_dir();
+        let path = dir.join("nested/data.bin");
+        let path_str = path.to_str().unwrap();
+
+        assert_eq!(write_file_chunk(path_str, 0, b"\x00\x01", false), Ok(2));
+        assert_eq!(write_file_chunk(path_str, 2, b"\xff", false), Ok(3));
+        assert_eq!(write_file_chunk(path_str, 3, b"end", true), Ok(6));
+        assert_eq!(fs::read(&path).unwrap(), b"\x00\x01\xffend");
+
+        let first = read_file_chunk(path_str, 0, 4).unwrap();
+        assert_eq!(BASE64.decode(&first.data).unwrap(), b"\x00\x01\xffe");
+        assert_eq!(first.size, 6);
+        assert!(!first.eof);
+        let rest = read_file_chunk(path_str, 4, 4).unwrap();
+        assert_eq!(BASE64.decode(&rest.data).unwrap(), b"nd");
+        assert!(rest.eof);
+        assert!(read_file_chunk(path_str, 6, 4).unwrap().eof);
+        assert!(read_file_chunk(path_str, 7, 4).is_err());
+
+        // Offset 0 starts the file over
+        assert_eq!(write_file_chunk(path_str, 0, b"new", true), Ok(3));
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_write_file_chunk_rejects_out_of_order_offsets() {
+        let dir = temp_dir();
+        let path = dir.join("data.bin");
+        let path_str = path.to_str().unwrap();
+
+        // The file must be started at offset 0
+        assert!(write_file_chunk(path_str, 4, b"data", false).is_err());
+        assert!(!path.exists());
+
+        write_file_chunk(path_str, 0, b"abcd", false).unwrap();
+        // Repeated and skipped chunks are both rejected
+        let err = write_file_chunk(path_str, 2, b"cd", false).unwrap_err();
+        assert!(err.contains("next offset is 4"), "{err}");
+        assert!(write_file_chunk(path_str, 8, b"ijkl", false).is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"abcd");
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_stat() {
+        let dir = temp_dir();
+        let file = dir.join("script.sh");
+        fs::write(&file, "echo hi").unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o750)).unwrap();
+
+        let result = stat(file.to_str().unwrap()).unwrap();
+        assert_eq!(result.file_type, FileType::File);
+        assert_eq!(result.size, 7);
+        assert_eq!(result.mode, 0o750);
+        let now_ms = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+        assert!((now_ms - result.modified_ms).abs() < 60_000);
+
+        assert_eq!(
+            stat(dir.to_str().unwrap()).unwrap().file_type,
+            FileType::Dir
+        );
+
+        // Symlinks are reported, not followed
+        let link = dir.join("link");
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+        assert_eq!(
+            stat(link.to_str().unwrap()).unwrap().file_type,
+            FileType::Symlink
+        );
+
+        let err = stat(dir.join("missing").to_str().unwrap()).unwrap_err();
+        assert!(err.contains("No such file"), "{err}");
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_delete_file_and_directories() {
+        let dir = temp_dir();
+        let file = dir.join("file.txt");
+        fs::write(&file, "x").unwrap();
+        assert!(delete_file(file.to_str().unwrap(), false).unwrap());
+        assert!(!file.exists());
+        assert!(delete_file(file.to_str().unwrap(), false).is_err());
+
+        let empty = dir.join("empty");
+        fs::create_dir(&empty).unwrap();
+        assert!(delete_file(empty.to_str().unwrap(), false).unwrap());
+        assert!(!empty.exists());
+
+        // Non-empty directories need `recursive`
+        let full = dir.join("full/nested");
+        fs::create_dir_all(&full).unwrap();
+        fs::write(full.join("data"), "x").unwrap();
+        let full = dir.join("full");
+        assert!(delete_file(full.to_str().unwrap(), false).is_err());
+        assert!(full.exists());
+        assert!(delete_file(full.to_str().unwrap(), true).unwrap());
+        assert!(!full.exists());
+
+        assert!(delete_file("/", true).is_err());
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_rename_and_mkdir() {
+        let dir = temp_dir();
+        let nested = dir.join("a/b/c");
+        let nested_str = nested.to_str().unwrap();
+        assert!(mkdir(nested_str).unwrap());
+        assert!(nested.is_dir());
+        // Creating an existing directory succeeds
+        assert!(mkdir(nested_str).unwrap());
+
+        let from = dir.join("old.txt");
+        let to = nested.join("new.txt");
+        fs::write(&from, "moved").unwrap();
+        assert!(rename(from.to_str().unwrap(), to.to_str().unwrap()).unwrap());
+        assert!(!from.exists());
+        assert_eq!(fs::read_to_string(&to).unwrap(), "moved");
+        assert!(rename(from.to_str().unwrap(), to.to_str().unwrap()).is_err());
+
+        // A file in the way of a directory is an error
+        assert!(mkdir(to.join("sub").to_str().unwrap()).is_err());
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_touch_creates_empty_file() {
+        let dir = temp_dir();
+        let path = dir.join("nested/new.lock");
+        let path_str = path.to_str().unwrap();
+
+        assert!(touch(path_str).unwrap());
+        assert!(path.exists());
+        assert_eq!(read_file(path_str).unwrap(), "");
+
+        // Cleanup
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_touch_preserves_existing_content() {
+        let dir = temp_dir();
+        let path = dir.join("existing.txt");
+        let path_str = path.to_str().unwrap();
+
+        write_file(path_str, "keep me", None).unwrap();
+        let old_mtime = fs::metadata(&path).unwrap().modified().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+
+        assert!(touch(path_str).unwrap());
+        assert_eq!(read_file(path_str).unwrap(), "keep me");
+        let new_mtime = fs::metadata(&path).unwrap().modified().unwrap();
+        assert!(new_mtime > old_mtime);
+
+        // Cleanup
+        fs::remove_dir_all(dir).ok();
+    }
+
     #[test]
     fn test_read_nonexistent_file() {
         let result = read_file("/nonexistent/path/file.txt");
@@ -192,7 +809,7 @@ mod tests {
         fs::write(dir.join("file2.txt"), "content").unwrap();
         fs::create_dir(dir.join("subdir")).unwrap();
 
-        let entries = list_dir(dir.to_str().unwrap()).unwrap();
+        let entries = list_dir(dir.to_str().unwrap(), SortBy::Name, false, None, 1).unwrap();
         assert_eq!(entries.len(), 3);
 
         let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
@@ -207,4 +824,104 @@ mod tests {
         // Cleanup
         fs::remove_dir_all(dir).ok();
     }
+
+    #[test]
+    fn test_list_dir_size_descending() {
+        let dir = temp_dir();
+        fs::write(dir.join("small.txt"), "a").unwrap();
+        fs::write(dir.join("large.txt"), "a".repeat(100)).unwrap();
+        fs::write(dir.join("medium.txt"), "a".repeat(10)).unwrap();
+
+        let entries = list_dir(dir.to_str().unwrap(), SortBy::Size, true, None, 1).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["large.txt", "medium.txt", "small.txt"]);
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_list_dir_glob_filter() {
+        let dir = temp_dir();
+        for name in ["main.py", "util.py", "notes.txt", "py"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let entries =
+            list_dir(dir.to_str().unwrap(), SortBy::Name, false, Some("*.py"), 1).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["main.py", "util.py"]);
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_list_dir_recursive() {
+        let dir = temp_dir();
+        fs::create_dir_all(dir.join("src/bin")).unwrap();
+        fs::write(dir.join("README.md"), "").unwrap();
+        fs::write(dir.join("src/lib.rs"), "").unwrap();
+        fs::write(dir.join("src/bin/main.rs"), "").unwrap();
+        // A cycle back to the root is listed but not followed
+        std::os::unix::fs::symlink(&dir, dir.join("src/loop")).unwrap();
+        let path = dir.to_str().unwrap();
+
+        let entries = list_dir(path, SortBy::Name, false, None, usize::MAX).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "README.md",
+                "src",
+                "src/bin",
+                "src/bin/main.rs",
+                "src/lib.rs",
+                "src/loop"
+            ]
+        );
+        let link = entries.iter().find(|e| e.name == "src/loop").unwrap();
+        assert!(!link.is_dir);
+
+        let entries = list_dir(path, SortBy::Name, false, None, 2).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["README.md", "src", "src/bin", "src/lib.rs", "src/loop"]
+        );
+
+        let entries = list_dir(path, SortBy::Name, false, Some("*.rs"), usize::MAX).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["src/bin/main.rs", "src/lib.rs"]);
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_list_dir_entry_limit() {
+        let dir = temp_dir();
+        for i in 0..=MAX_LIST_ENTRIES {
+            fs::write(dir.join(i.to_string()), "").unwrap();
+        }
+
+        let err = list_dir(dir.to_str().unwrap(), SortBy::Name, false, None, 1).unwrap_err();
+        assert!(err.contains("exceeds 10000 entries"), "{err}");
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_clear_dir_removes_files() {
+        // A private directory under /tmp; clearing the real /tmp would
+        // delete other processes' files
+        let dir = temp_dir();
+        fs::write(dir.join("scratch.txt"), "data").unwrap();
+        fs::create_dir_all(dir.join("nested/deeper")).unwrap();
+        fs::write(dir.join("nested/deeper/file"), "x").unwrap();
+
+        assert_eq!(clear_dir(dir.to_str().unwrap()).unwrap(), 2);
+        assert!(!dir.join("scratch.txt").exists());
+        assert!(dir.exists());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        fs::remove_dir_all(dir).ok();
+    }
 }
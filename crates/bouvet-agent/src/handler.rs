@@ -2,45 +2,315 @@
 //!
 //! Routes JSON-RPC requests to the appropriate handlers.
 
-use crate::exec::{exec_code, exec_command};
-use crate::fs::{list_dir, read_file, write_file};
+use crate::disk::resize_fs;
+use crate::exec::{
+    exec_argv, exec_code, exec_command, exec_project, OutputSink, ProcessHandle, RunOptions,
+};
+use crate::frame::SUPPORTED_COMPRESSION;
+use crate::fs::{
+    clear_dir, delete_file, download_file, list_dir, mkdir, read_file, read_file_chunk, rename,
+    stat, touch, write_file, write_file_chunk,
+};
+use crate::hostname::{hostname, set_hostname, validate_hostname};
+use crate::net::{check_connectivity, DEFAULT_CONNECT_TIMEOUT_MS, MAX_CONNECT_TIMEOUT_MS};
+use crate::osinfo::os_info;
 use crate::protocol::{
-    error_codes, ExecCodeParams, ExecParams, ListDirParams, ReadFileParams, Request, Response,
-    WriteFileParams,
+    error_codes, CheckConnectivityParams, ClearTmpResult, ConnectivityResult, DeleteFileParams,
+    DescribeResult, DownloadFileResult, ExecCodeParams, ExecParams, ExecProjectParams, ExecResult,
+    HostnameResult, InfoResult, KillParams, ListDirParams, ListDirResult, MethodDescription,
+    MkdirParams, NoParams, OsInfoResult, PingResult, ReadFileChunkParams, ReadFileChunkResult,
+    ReadFileParams, ReadFileResult, RenameParams, Request, ResizeFsParams, Response,
+    SetHostnameParams, StatParams, StatResult, SuccessResult, TailCancelParams, TailParams,
+    TailResult, TouchParams, UploadBeginParams, UploadChunkParams, UploadFinishResult,
+    UploadIdParams, UploadStatus, WriteFileChunkParams, WriteFileChunkResult, WriteFileParams,
+    PROTOCOL_VERSION,
 };
+use crate::redact::redact;
+use crate::tail::{tail_lines, MAX_TAIL_LINES};
+use crate::upload::{upload_begin, upload_chunk, upload_finish, upload_status};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use schemars::schema::RootSchema;
+use schemars::{schema_for, JsonSchema};
 use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::time::Duration;
 use tracing::{debug, trace, warn};
 
+/// Guest scratch directory emptied by `clear_tmp`.
+const TMP_DIR: &str = "/tmp";
+
+/// A method the agent serves, with the schemas `describe` reports for it.
+struct Method {
+    /// Method name.
+    name: &'static str,
+    /// JSON Schema of the params.
+    params: fn() -> RootSchema,
+    /// JSON Schema of the result.
+    result: fn() -> RootSchema,
+    /// Handler, or `None` for methods served by the connection loop
+    /// because they need connection state.
+    handle: Option<fn(u64, Value) -> Response>,
+}
+
+/// JSON Schema of `T`.
+fn schema<T: JsonSchema>() -> RootSchema {
+    schema_for!(T)
+}
+
+/// Every method the agent serves. [`handle_request`] dispatches through
+/// this table and `describe` reports it, so the two can't drift apart.
+static METHODS: &[Method] = &[
+    Method {
+        name: "ping",
+        params: schema::<NoParams>,
+        result: schema::<PingResult>,
+        handle: Some(|id, _| handle_ping(id)),
+    },
+    Method {
+        name: "info",
+        params: schema::<NoParams>,
+        result: schema::<InfoResult>,
+        handle: Some(|id, _| handle_info(id)),
+    },
+    Method {
+        name: "describe",
+        params: schema::<NoParams>,
+        result: schema::<DescribeResult>,
+        handle: Some(|id, _| handle_describe(id)),
+    },
+    Method {
+        name: "exec",
+        params: schema::<ExecParams>,
+        result: schema::<ExecResult>,
+        handle: Some(handle_exec),
+    },
+    Method {
+        name: "exec_stream",
+        params: schema::<ExecParams>,
+        result: schema::<ExecResult>,
+        handle: None,
+    },
+    Method {
+        name: "exec_code",
+        params: schema::<ExecCodeParams>,
+        result: schema::<ExecResult>,
+        handle: Some(handle_exec_code),
+    },
+    Method {
+        name: "exec_code_stream",
+        params: schema::<ExecCodeParams>,
+        result: schema::<ExecResult>,
+        handle: None,
+    },
+    Method {
+        name: "exec_project",
+        params: schema::<ExecProjectParams>,
+        result: schema::<ExecResult>,
+        handle: Some(handle_exec_project),
+    },
+    Method {
+        name: "read_file",
+        params: schema::<ReadFileParams>,
+        result: schema::<ReadFileResult>,
+        handle: Some(handle_read_file),
+    },
+    Method {
+        name: "download_file",
+        params: schema::<ReadFileParams>,
+        result: schema::<DownloadFileResult>,
+        handle: Some(handle_download_file),
+    },
+    Method {
+        name: "write_file",
+        params: schema::<WriteFileParams>,
+        result: schema::<SuccessResult>,
+        handle: Some(handle_write_file),
+    },
+    Method {
+        name: "write_file_chunk",
+        params: schema::<WriteFileChunkParams>,
+        result: schema::<WriteFileChunkResult>,
+        handle: Some(handle_write_file_chunk),
+    },
+    Method {
+        name: "read_file_chunk",
+        params: schema::<ReadFileChunkParams>,
+        result: schema::<ReadFileChunkResult>,
+        handle: Some(handle_read_file_chunk),
+    },
+    Method {
+        name: "touch",
+        params: schema::<TouchParams>,
+        result: schema::<SuccessResult>,
+        handle: Some(handle_touch),
+    },
+    Method {
+        name: "stat",
+        params: schema::<StatParams>,
+        result: schema::<StatResult>,
+        handle: Some(handle_stat),
+    },
+    Method {
+        name: "delete_file",
+        params: schema::<DeleteFileParams>,
+        result: schema::<SuccessResult>,
+        handle: Some(handle_delete_file),
+    },
+    Method {
+        name: "rename",
+        params: schema::<RenameParams>,
+        result: schema::<SuccessResult>,
+        handle: Some(handle_rename),
+    },
+    Method {
+        name: "mkdir",
+        params: schema::<MkdirParams>,
+        result: schema::<SuccessResult>,
+        handle: Some(handle_mkdir),
+    },
+    Method {
+        name: "list_dir",
+        params: schema::<ListDirParams>,
+        result: schema::<ListDirResult>,
+        handle: Some(handle_list_dir),
+    },
+    Method {
+        name: "clear_tmp",
+        params: schema::<NoParams>,
+        result: schema::<ClearTmpResult>,
+        handle: Some(|id, _| handle_clear_tmp(id)),
+    },
+    Method {
+        name: "resize_fs",
+        params: schema::<ResizeFsParams>,
+        result: schema::<SuccessResult>,
+        handle: Some(handle_resize_fs),
+    },
+    Method {
+        name: "set_hostname",
+        params: schema::<SetHostnameParams>,
+        result: schema::<SuccessResult>,
+        handle: Some(handle_set_hostname),
+    },
+    Method {
+        name: "hostname",
+        params: schema::<NoParams>,
+        result: schema::<HostnameResult>,
+        handle: Some(|id, _| handle_hostname(id)),
+    },
+    Method {
+        name: "os_info",
+        params: schema::<NoParams>,
+        result: schema::<OsInfoResult>,
+        handle: Some(|id, _| handle_os_info(id)),
+    },
+    Method {
+        name: "check_connectivity",
+        params: schema::<CheckConnectivityParams>,
+        result: schema::<ConnectivityResult>,
+        handle: Some(handle_check_connectivity),
+    },
+    Method {
+        name: "upload_begin",
+        params: schema::<UploadBeginParams>,
+        result: schema::<UploadStatus>,
+        handle: Some(handle_upload_begin),
+    },
+    Method {
+        name: "upload_chunk",
+        params: schema::<UploadChunkParams>,
+        result: schema::<UploadStatus>,
+        handle: Some(handle_upload_chunk),
+    },
+    Method {
+        name: "upload_status",
+        params: schema::<UploadIdParams>,
+        result: schema::<UploadStatus>,
+        handle: Some(handle_upload_status),
+    },
+    Method {
+        name: "upload_finish",
+        params: schema::<UploadIdParams>,
+        result: schema::<UploadFinishResult>,
+        handle: Some(handle_upload_finish),
+    },
+    Method {
+        name: "tail",
+        params: schema::<TailParams>,
+        result: schema::<TailResult>,
+        handle: Some(handle_tail),
+    },
+    Method {
+        name: "tail_cancel",
+        params: schema::<TailCancelParams>,
+        result: schema::<SuccessResult>,
+        handle: None,
+    },
+    Method {
+        name: "kill",
+        params: schema::<KillParams>,
+        result: schema::<SuccessResult>,
+        handle: None,
+    },
+];
+
 /// Handle a JSON-RPC request and return a response.
 ///
-/// Supported methods:
+/// Supported methods (see [`METHODS`]):
 /// - `ping` - Health check, returns `{pong: true}`.
+/// - `info` - Protocol and agent version, used for version negotiation.
+/// - `describe` - Every method with JSON Schemas for its params and result.
 /// - `exec` - Execute a shell command.
+/// - `exec_stream` - Execute a command, sending its output as `output`
+///   notifications while it runs; served by the connection loop only.
 /// - `exec_code` - Execute code in a specified language.
+/// - `exec_code_stream` - Execute code, sending its output like
+///   `exec_stream`; served by the connection loop only.
+/// - `exec_project` - Execute a multi-file program.
 /// - `read_file` - Read a file's contents.
+/// - `download_file` - Read a file's bytes as base64, with size and SHA-256.
 /// - `write_file` - Write content to a file.
+/// - `write_file_chunk` - Write base64 data at the end of a file, or start
+///   it over at offset 0.
+/// - `read_file_chunk` - Read part of a file as base64.
+/// - `touch` - Create an empty file or update its modification time.
+/// - `stat` - File type, size, permission bits and modification time.
+/// - `delete_file` - Delete a file or directory (`recursive` for non-empty ones).
+/// - `rename` - Rename or move a file or directory.
+/// - `mkdir` - Create a directory and any missing parents.
 /// - `list_dir` - List directory contents.
+/// - `clear_tmp` - Remove everything in `/tmp`.
+/// - `resize_fs` - Grow a block device's filesystem to fill the device.
+/// - `set_hostname` - Set the guest hostname.
+/// - `hostname` - Get the guest hostname.
+/// - `os_info` - Distribution identifiers from `/etc/os-release`.
+/// - `check_connectivity` - Try a TCP connection from the guest.
+/// - `upload_begin` - Start a resumable upload with a size and SHA-256.
+/// - `upload_chunk` - Write base64 data at the upload's next offset.
+/// - `upload_status` - Report an upload's next expected offset.
+/// - `upload_finish` - Verify the checksum and move the file into place.
+/// - `tail` - Read the last lines of a file. Following (`follow: true`) is
+///   handled by the connection loop, which owns the streaming state.
+/// - `tail_cancel` - Stop following; served by the connection loop only.
+/// - `kill` - Signal the connection's streaming command; served by the
+///   connection loop only.
 pub fn handle_request(req: Request) -> Response {
     debug!(method = %req.method, id = req.id, "handling request");
-    trace!(params = ?req.params, "request params");
-
-    let response = match req.method.as_str() {
-        "ping" => {
-            debug!(id = req.id, "ping request");
-            Response::success(req.id, json!({"pong": true}))
-        }
-
-        "exec" => handle_exec(req.id, req.params),
+    // Params carry commands, env values, stdin and file contents
+    trace!(
+        id = req.id,
+        len = req.params.to_string().len(),
+        "request params"
+    );
 
-        "exec_code" => handle_exec_code(req.id, req.params),
-
-        "read_file" => handle_read_file(req.id, req.params),
-
-        "write_file" => handle_write_file(req.id, req.params),
-
-        "list_dir" => handle_list_dir(req.id, req.params),
-
-        _ => {
+    let handler = METHODS
+        .iter()
+        .find(|method| method.name == req.method)
+        .and_then(|method| method.handle);
+    let response = match handler {
+        Some(handle) => handle(req.id, req.params),
+        None => {
             warn!(method = %req.method, "unknown method");
             Response::error(
                 req.id,
@@ -54,18 +324,130 @@ pub fn handle_request(req: Request) -> Response {
         debug!(id = req.id, error = ?response.error, "request failed");
     } else {
         debug!(id = req.id, "request succeeded");
-        trace!(result = ?response.result, "response result");
+        trace!(
+            id = req.id,
+            len = response
+                .result
+                .as_ref()
+                .map(|result| result.to_string().len()),
+            "response result"
+        );
     }
 
     response
 }
 
+/// Handle the `ping` method.
+fn handle_ping(id: u64) -> Response {
+    debug!(id = id, "ping request");
+    Response::success(id, json!(PingResult { pong: true }))
+}
+
+/// Handle the `describe` method.
+fn handle_describe(id: u64) -> Response {
+    debug!(id = id, "describe request");
+    let result = DescribeResult {
+        protocol_version: PROTOCOL_VERSION,
+        methods: METHODS
+            .iter()
+            .map(|method| MethodDescription {
+                name: method.name.to_string(),
+                params: json!((method.params)()),
+                result: json!((method.result)()),
+            })
+            .collect(),
+    };
+    Response::success(id, json!(result))
+}
+
+/// Handle the `info` method.
+fn handle_info(id: u64) -> Response {
+    debug!(id = id, "info request");
+    let info = InfoResult {
+        protocol_version: PROTOCOL_VERSION,
+        agent_version: env!("CARGO_PKG_VERSION").to_string(),
+        compression: SUPPORTED_COMPRESSION
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+    };
+    match serde_json::to_value(&info) {
+        Ok(v) => Response::success(id, v),
+        Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e.to_string()),
+    }
+}
+
 /// Handle the `exec` method.
 fn handle_exec(id: u64, params: Value) -> Response {
+    run_exec(id, params, None, None)
+}
+
+/// Handle the `exec_stream` method, handing output to `output` as it is
+/// read. The connection loop sends it on as `output` notifications ahead
+/// of the returned response, and signals the command through `process`
+/// for `kill`.
+pub fn handle_exec_stream(
+    id: u64,
+    params: Value,
+    output: OutputSink,
+    process: ProcessHandle,
+) -> Response {
+    run_exec(id, params, Some(output), Some(process))
+}
+
+/// Run the command of an `exec` or `exec_stream` request.
+fn run_exec(
+    id: u64,
+    params: Value,
+    output: Option<OutputSink>,
+    process: Option<ProcessHandle>,
+) -> Response {
     match serde_json::from_value::<ExecParams>(params) {
         Ok(p) => {
-            debug!(id = id, cmd = %p.cmd, "handling exec");
-            let result = exec_command(&p.cmd);
+            let options = RunOptions {
+                cwd: p.cwd.map(PathBuf::from),
+                max_output_bytes: p.max_output_bytes,
+                timeout: p.timeout_secs.map(Duration::from_secs),
+                env: p.env.unwrap_or_default(),
+                stdin: p.stdin,
+                usage: p.usage,
+                output,
+                process,
+            };
+            let result = match (p.cmd, p.argv) {
+                (Some(cmd), None) => {
+                    debug!(id = id, cmd = %redact(&cmd), "handling exec");
+                    exec_command(&cmd, &options)
+                }
+                (None, Some(argv)) if !argv.is_empty() => {
+                    debug!(id = id, argv = %redact(&argv.join(" ")), "handling exec (argv)");
+                    exec_argv(&argv, &options)
+                }
+                (None, Some(_)) => {
+                    warn!(id = id, "empty exec argv");
+                    return Response::error(
+                        id,
+                        error_codes::INVALID_PARAMS,
+                        "invalid params: argv must not be empty",
+                    );
+                }
+                (Some(_), Some(_)) => {
+                    warn!(id = id, "exec params set both cmd and argv");
+                    return Response::error(
+                        id,
+                        error_codes::INVALID_PARAMS,
+                        "invalid params: cmd and argv are mutually exclusive",
+                    );
+                }
+                (None, None) => {
+                    warn!(id = id, "exec params missing cmd or argv");
+                    return Response::error(
+                        id,
+                        error_codes::INVALID_PARAMS,
+                        "invalid params: one of cmd or argv is required",
+                    );
+                }
+            };
             match serde_json::to_value(&result) {
                 Ok(v) => Response::success(id, v),
                 Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e.to_string()),
@@ -84,10 +466,41 @@ fn handle_exec(id: u64, params: Value) -> Response {
 
 /// Handle the `exec_code` method.
 fn handle_exec_code(id: u64, params: Value) -> Response {
+    run_exec_code(id, params, None, None)
+}
+
+/// Handle the `exec_code_stream` method, handing output to `output` and
+/// taking signals from `process` like [`handle_exec_stream`].
+pub fn handle_exec_code_stream(
+    id: u64,
+    params: Value,
+    output: OutputSink,
+    process: ProcessHandle,
+) -> Response {
+    run_exec_code(id, params, Some(output), Some(process))
+}
+
+/// Run the code of an `exec_code` or `exec_code_stream` request.
+fn run_exec_code(
+    id: u64,
+    params: Value,
+    output: Option<OutputSink>,
+    process: Option<ProcessHandle>,
+) -> Response {
     match serde_json::from_value::<ExecCodeParams>(params) {
         Ok(p) => {
             debug!(id = id, lang = %p.lang, code_len = p.code.len(), "handling exec_code");
-            let result = exec_code(&p.lang, &p.code);
+            let options = RunOptions {
+                cwd: p.cwd.map(PathBuf::from),
+                max_output_bytes: p.max_output_bytes,
+                timeout: p.timeout_secs.map(Duration::from_secs),
+                env: p.env.unwrap_or_default(),
+                stdin: p.stdin,
+                usage: p.usage,
+                output,
+                process,
+            };
+            let result = exec_code(&p.lang, &p.code, &options);
             match serde_json::to_value(&result) {
                 Ok(v) => Response::success(id, v),
                 Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e.to_string()),
@@ -104,13 +517,44 @@ fn handle_exec_code(id: u64, params: Value) -> Response {
     }
 }
 
+/// Handle the `exec_project` method.
+fn handle_exec_project(id: u64, params: Value) -> Response {
+    match serde_json::from_value::<ExecProjectParams>(params) {
+        Ok(p) => {
+            debug!(id = id, lang = %p.lang, entry = %p.entry, files = p.files.len(), "handling exec_project");
+            let options = RunOptions {
+                cwd: p.cwd.map(PathBuf::from),
+                max_output_bytes: p.max_output_bytes,
+                timeout: p.timeout_secs.map(Duration::from_secs),
+                env: p.env.unwrap_or_default(),
+                stdin: p.stdin,
+                usage: p.usage,
+                ..Default::default()
+            };
+            let result = exec_project(&p.lang, &p.entry, &p.files, &options);
+            match serde_json::to_value(&result) {
+                Ok(v) => Response::success(id, v),
+                Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e.to_string()),
+            }
+        }
+        Err(e) => {
+            warn!(id = id, error = %e, "invalid exec_project params");
+            Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            )
+        }
+    }
+}
+
 /// Handle the `read_file` method.
 fn handle_read_file(id: u64, params: Value) -> Response {
     match serde_json::from_value::<ReadFileParams>(params) {
         Ok(p) => {
             debug!(id = id, path = %p.path, "handling read_file");
             match read_file(&p.path) {
-                Ok(content) => Response::success(id, json!({"content": content})),
+                Ok(content) => Response::success(id, json!(ReadFileResult { content })),
                 Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
             }
         }
@@ -125,13 +569,34 @@ fn handle_read_file(id: u64, params: Value) -> Response {
     }
 }
 
+/// Handle the `download_file` method.
+fn handle_download_file(id: u64, params: Value) -> Response {
+    match serde_json::from_value::<ReadFileParams>(params) {
+        Ok(p) => {
+            debug!(id = id, path = %p.path, "handling download_file");
+            match download_file(&p.path) {
+                Ok(result) => Response::success(id, json!(result)),
+                Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+            }
+        }
+        Err(e) => {
+            warn!(id = id, error = %e, "invalid download_file params");
+            Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            )
+        }
+    }
+}
+
 /// Handle the `write_file` method.
 fn handle_write_file(id: u64, params: Value) -> Response {
     match serde_json::from_value::<WriteFileParams>(params) {
         Ok(p) => {
             debug!(id = id, path = %p.path, content_len = p.content.len(), "handling write_file");
-            match write_file(&p.path, &p.content) {
-                Ok(success) => Response::success(id, json!({"success": success})),
+            match write_file(&p.path, &p.content, p.mode) {
+                Ok(success) => Response::success(id, json!(SuccessResult { success })),
                 Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
             }
         }
@@ -146,13 +611,289 @@ fn handle_write_file(id: u64, params: Value) -> Response {
     }
 }
 
+/// Handle the `write_file_chunk` method.
+fn handle_write_file_chunk(id: u64, params: Value) -> Response {
+    let p = match serde_json::from_value::<WriteFileChunkParams>(params) {
+        Ok(p) => p,
+        Err(e) => {
+            warn!(id = id, error = %e, "invalid write_file_chunk params");
+            return Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            );
+        }
+    };
+    let data = match BASE64.decode(&p.data) {
+        Ok(data) => data,
+        Err(e) => {
+            return Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: data is not valid base64: {}", e),
+            )
+        }
+    };
+    debug!(id = id, path = %p.path, offset = p.offset, len = data.len(), "handling write_file_chunk");
+    match write_file_chunk(&p.path, p.offset, &data, p.is_last) {
+        Ok(size) => Response::success(id, json!(WriteFileChunkResult { size })),
+        Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+    }
+}
+
+/// Handle the `read_file_chunk` method.
+fn handle_read_file_chunk(id: u64, params: Value) -> Response {
+    match serde_json::from_value::<ReadFileChunkParams>(params) {
+        Ok(p) => {
+            debug!(id = id, path = %p.path, offset = p.offset, len = p.len, "handling read_file_chunk");
+            match read_file_chunk(&p.path, p.offset, p.len) {
+                Ok(result) => Response::success(id, json!(result)),
+                Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+            }
+        }
+        Err(e) => {
+            warn!(id = id, error = %e, "invalid read_file_chunk params");
+            Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            )
+        }
+    }
+}
+
+/// Handle the `upload_begin` method.
+fn handle_upload_begin(id: u64, params: Value) -> Response {
+    match serde_json::from_value::<UploadBeginParams>(params) {
+        Ok(p) => {
+            debug!(id = id, path = %p.path, total_size = p.total_size, "handling upload_begin");
+            match upload_begin(&p.path, p.total_size, &p.sha256) {
+                Ok(status) => Response::success(id, json!(status)),
+                Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+            }
+        }
+        Err(e) => {
+            warn!(id = id, error = %e, "invalid upload_begin params");
+            Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            )
+        }
+    }
+}
+
+/// Handle the `upload_chunk` method.
+fn handle_upload_chunk(id: u64, params: Value) -> Response {
+    let p = match serde_json::from_value::<UploadChunkParams>(params) {
+        Ok(p) => p,
+        Err(e) => {
+            warn!(id = id, error = %e, "invalid upload_chunk params");
+            return Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            );
+        }
+    };
+    let data = match BASE64.decode(&p.data) {
+        Ok(data) => data,
+        Err(e) => {
+            return Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: data is not valid base64: {}", e),
+            )
+        }
+    };
+    debug!(id = id, upload_id = %p.upload_id, offset = p.offset, len = data.len(), "handling upload_chunk");
+    match upload_chunk(&p.upload_id, p.offset, &data) {
+        Ok(status) => Response::success(id, json!(status)),
+        Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+    }
+}
+
+/// Handle the `upload_status` method.
+fn handle_upload_status(id: u64, params: Value) -> Response {
+    match serde_json::from_value::<UploadIdParams>(params) {
+        Ok(p) => {
+            debug!(id = id, upload_id = %p.upload_id, "handling upload_status");
+            match upload_status(&p.upload_id) {
+                Ok(status) => Response::success(id, json!(status)),
+                Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+            }
+        }
+        Err(e) => {
+            warn!(id = id, error = %e, "invalid upload_status params");
+            Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            )
+        }
+    }
+}
+
+/// Handle the `upload_finish` method.
+fn handle_upload_finish(id: u64, params: Value) -> Response {
+    match serde_json::from_value::<UploadIdParams>(params) {
+        Ok(p) => {
+            debug!(id = id, upload_id = %p.upload_id, "handling upload_finish");
+            match upload_finish(&p.upload_id) {
+                Ok(result) => Response::success(id, json!(result)),
+                Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+            }
+        }
+        Err(e) => {
+            warn!(id = id, error = %e, "invalid upload_finish params");
+            Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            )
+        }
+    }
+}
+
+/// Handle the `touch` method.
+fn handle_touch(id: u64, params: Value) -> Response {
+    match serde_json::from_value::<TouchParams>(params) {
+        Ok(p) => {
+            debug!(id = id, path = %p.path, "handling touch");
+            match touch(&p.path) {
+                Ok(success) => Response::success(id, json!(SuccessResult { success })),
+                Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+            }
+        }
+        Err(e) => {
+            warn!(id = id, error = %e, "invalid touch params");
+            Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            )
+        }
+    }
+}
+
+/// Handle the `stat` method.
+fn handle_stat(id: u64, params: Value) -> Response {
+    match serde_json::from_value::<StatParams>(params) {
+        Ok(p) => {
+            debug!(id = id, path = %p.path, "handling stat");
+            match stat(&p.path) {
+                Ok(result) => Response::success(id, json!(result)),
+                Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+            }
+        }
+        Err(e) => {
+            warn!(id = id, error = %e, "invalid stat params");
+            Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            )
+        }
+    }
+}
+
+/// Handle the `delete_file` method.
+fn handle_delete_file(id: u64, params: Value) -> Response {
+    match serde_json::from_value::<DeleteFileParams>(params) {
+        Ok(p) => {
+            debug!(id = id, path = %p.path, recursive = p.recursive, "handling delete_file");
+            match delete_file(&p.path, p.recursive) {
+                Ok(success) => Response::success(id, json!(SuccessResult { success })),
+                Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+            }
+        }
+        Err(e) => {
+            warn!(id = id, error = %e, "invalid delete_file params");
+            Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            )
+        }
+    }
+}
+
+/// Handle the `rename` method.
+fn handle_rename(id: u64, params: Value) -> Response {
+    match serde_json::from_value::<RenameParams>(params) {
+        Ok(p) => {
+            debug!(id = id, from = %p.from, to = %p.to, "handling rename");
+            match rename(&p.from, &p.to) {
+                Ok(success) => Response::success(id, json!(SuccessResult { success })),
+                Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+            }
+        }
+        Err(e) => {
+            warn!(id = id, error = %e, "invalid rename params");
+            Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            )
+        }
+    }
+}
+
+/// Handle the `mkdir` method.
+fn handle_mkdir(id: u64, params: Value) -> Response {
+    match serde_json::from_value::<MkdirParams>(params) {
+        Ok(p) => {
+            debug!(id = id, path = %p.path, "handling mkdir");
+            match mkdir(&p.path) {
+                Ok(success) => Response::success(id, json!(SuccessResult { success })),
+                Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+            }
+        }
+        Err(e) => {
+            warn!(id = id, error = %e, "invalid mkdir params");
+            Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            )
+        }
+    }
+}
+
 /// Handle the `list_dir` method.
 fn handle_list_dir(id: u64, params: Value) -> Response {
     match serde_json::from_value::<ListDirParams>(params) {
         Ok(p) => {
-            debug!(id = id, path = %p.path, "handling list_dir");
-            match list_dir(&p.path) {
-                Ok(entries) => Response::success(id, json!({"entries": entries})),
+            debug!(
+                id = id,
+                path = %p.path,
+                sort_by = ?p.sort_by,
+                descending = p.descending,
+                pattern = ?p.pattern,
+                recursive = p.recursive,
+                max_depth = ?p.max_depth,
+                "handling list_dir"
+            );
+            let max_depth = match (p.recursive, p.max_depth) {
+                (false, _) => 1,
+                (true, Some(0)) => {
+                    warn!(id = id, "list_dir max_depth is zero");
+                    return Response::error(
+                        id,
+                        error_codes::INVALID_PARAMS,
+                        "invalid params: max_depth must be at least 1",
+                    );
+                }
+                (true, depth) => depth.unwrap_or(usize::MAX),
+            };
+            match list_dir(
+                &p.path,
+                p.sort_by,
+                p.descending,
+                p.pattern.as_deref(),
+                max_depth,
+            ) {
+                Ok(entries) => Response::success(id, json!(ListDirResult { entries })),
                 Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
             }
         }
@@ -167,6 +908,155 @@ fn handle_list_dir(id: u64, params: Value) -> Response {
     }
 }
 
+/// Handle the `clear_tmp` method.
+fn handle_clear_tmp(id: u64) -> Response {
+    debug!(id = id, "clear_tmp request");
+    match clear_dir(TMP_DIR) {
+        Ok(removed) => Response::success(id, json!(ClearTmpResult { removed })),
+        Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+    }
+}
+
+/// Handle the `resize_fs` method.
+fn handle_resize_fs(id: u64, params: Value) -> Response {
+    match serde_json::from_value::<ResizeFsParams>(params) {
+        Ok(p) => {
+            debug!(id = id, device = %p.device, "handling resize_fs");
+            match resize_fs(&p.device) {
+                Ok(()) => Response::success(id, json!(SuccessResult { success: true })),
+                Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+            }
+        }
+        Err(e) => {
+            warn!(id = id, error = %e, "invalid resize_fs params");
+            Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            )
+        }
+    }
+}
+
+/// Handle the `set_hostname` method.
+fn handle_set_hostname(id: u64, params: Value) -> Response {
+    match serde_json::from_value::<SetHostnameParams>(params) {
+        Ok(p) => {
+            debug!(id = id, hostname = %p.hostname, "handling set_hostname");
+            if let Err(e) = validate_hostname(&p.hostname) {
+                warn!(id = id, error = %e, "invalid hostname");
+                return Response::error(
+                    id,
+                    error_codes::INVALID_PARAMS,
+                    format!("invalid params: {}", e),
+                );
+            }
+            match set_hostname(&p.hostname) {
+                Ok(()) => Response::success(id, json!(SuccessResult { success: true })),
+                Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+            }
+        }
+        Err(e) => {
+            warn!(id = id, error = %e, "invalid set_hostname params");
+            Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            )
+        }
+    }
+}
+
+/// Handle the `hostname` method.
+fn handle_hostname(id: u64) -> Response {
+    debug!(id = id, "hostname request");
+    match hostname() {
+        Ok(name) => Response::success(id, json!(HostnameResult { hostname: name })),
+        Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+    }
+}
+
+/// Handle the `os_info` method.
+fn handle_os_info(id: u64) -> Response {
+    debug!(id = id, "os_info request");
+    match os_info() {
+        Ok(info) => Response::success(id, json!(info)),
+        Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+    }
+}
+
+/// Handle the `check_connectivity` method.
+fn handle_check_connectivity(id: u64, params: Value) -> Response {
+    match serde_json::from_value::<CheckConnectivityParams>(params) {
+        Ok(p) => {
+            let timeout_ms = p
+                .timeout_ms
+                .unwrap_or(DEFAULT_CONNECT_TIMEOUT_MS)
+                .min(MAX_CONNECT_TIMEOUT_MS);
+            debug!(id = id, host = %p.host, port = p.port, timeout_ms = timeout_ms, "handling check_connectivity");
+            let result = check_connectivity(
+                &p.host,
+                p.port,
+                std::time::Duration::from_millis(timeout_ms),
+            );
+            Response::success(id, json!(result))
+        }
+        Err(e) => {
+            warn!(id = id, error = %e, "invalid check_connectivity params");
+            Response::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            )
+        }
+    }
+}
+
+/// Parse and bound `tail` parameters.
+///
+/// # Returns
+/// The parameters, or an `invalid params` error message.
+pub fn parse_tail_params(id: u64, params: Value) -> Result<TailParams, String> {
+    let p = serde_json::from_value::<TailParams>(params).map_err(|e| {
+        warn!(id = id, error = %e, "invalid tail params");
+        format!("invalid params: {}", e)
+    })?;
+    if p.lines > MAX_TAIL_LINES {
+        return Err(format!(
+            "invalid params: lines must be at most {}",
+            MAX_TAIL_LINES
+        ));
+    }
+    Ok(p)
+}
+
+/// Handle the `tail` method (non-follow).
+fn handle_tail(id: u64, params: Value) -> Response {
+    let p = match parse_tail_params(id, params) {
+        Ok(p) => p,
+        Err(e) => return Response::error(id, error_codes::INVALID_PARAMS, e),
+    };
+    if p.follow {
+        return Response::error(
+            id,
+            error_codes::INVALID_PARAMS,
+            "invalid params: follow requires a streaming connection",
+        );
+    }
+
+    debug!(id = id, path = %p.path, lines = p.lines, "handling tail");
+    match tail_lines(&p.path, p.lines) {
+        Ok(lines) => Response::success(
+            id,
+            json!(TailResult {
+                tail_id: None,
+                lines
+            }),
+        ),
+        Err(e) => Response::error(id, error_codes::INTERNAL_ERROR, e),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -189,6 +1079,18 @@ mod tests {
         assert_eq!(resp.result.unwrap(), json!({"pong": true}));
     }
 
+    #[test]
+    fn test_info() {
+        let req = make_request("info", json!({}));
+        let resp = handle_request(req);
+        assert!(resp.error.is_none());
+        let result = resp.result.unwrap();
+        assert_eq!(result["protocol_version"]["major"], PROTOCOL_VERSION.major);
+        assert_eq!(result["protocol_version"]["minor"], PROTOCOL_VERSION.minor);
+        assert_eq!(result["agent_version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(result["compression"], json!(["zstd"]));
+    }
+
     #[test]
     fn test_exec() {
         let req = make_request("exec", json!({"cmd": "echo test"}));
@@ -199,6 +1101,100 @@ mod tests {
         assert_eq!(result["stdout"].as_str().unwrap().trim(), "test");
     }
 
+    #[test]
+    fn test_exec_keeps_env_values_out_of_trace_logs() {
+        struct SharedWriter(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+        impl std::io::Write for SharedWriter {
+            fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(data);
+                Ok(data.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buf = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let writer = std::sync::Arc::clone(&buf);
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_writer(move || SharedWriter(std::sync::Arc::clone(&writer)))
+            .with_ansi(false)
+            .finish();
+        let req = make_request(
+            "exec",
+            json!({"cmd": "printenv FOO_TOKEN", "env": {"FOO_TOKEN": "s3cr3t-value"}}),
+        );
+        let resp = tracing::subscriber::with_default(subscriber, || handle_request(req));
+        assert_eq!(resp.result.unwrap()["stdout"], "s3cr3t-value\n");
+
+        let logs = String::from_utf8(buf.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("request params"), "{logs}");
+        assert!(logs.contains("response result"), "{logs}");
+        assert!(!logs.contains("s3cr3t-value"), "{logs}");
+    }
+
+    #[test]
+    fn test_exec_argv() {
+        let req = make_request("exec", json!({"argv": ["echo", "$HOME"]}));
+        let resp = handle_request(req);
+        assert!(resp.error.is_none());
+        let result = resp.result.unwrap();
+        assert_eq!(result["exit_code"], 0);
+        assert_eq!(result["stdout"].as_str().unwrap().trim(), "$HOME");
+    }
+
+    #[test]
+    fn test_exec_rejects_cmd_and_argv() {
+        let req = make_request("exec", json!({"cmd": "echo a", "argv": ["echo", "b"]}));
+        let resp = handle_request(req);
+        assert!(resp.result.is_none());
+        assert_eq!(resp.error.unwrap().code, error_codes::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_describe_lists_methods_with_schemas() {
+        let resp = handle_request(make_request("describe", json!({})));
+        assert!(resp.error.is_none());
+        let result = resp.result.unwrap();
+        assert_eq!(result["protocol_version"]["minor"], PROTOCOL_VERSION.minor);
+
+        let methods = result["methods"].as_array().unwrap();
+        let method = |name: &str| {
+            methods
+                .iter()
+                .find(|m| m["name"] == name)
+                .unwrap_or_else(|| panic!("{name} not described"))
+        };
+        for name in [
+            "exec",
+            "read_file",
+            "write_file",
+            "list_dir",
+            "tail",
+            "describe",
+        ] {
+            let method = method(name);
+            assert!(!method["params"].as_object().unwrap().is_empty(), "{name}");
+            assert!(
+                !method["result"]["properties"]
+                    .as_object()
+                    .unwrap()
+                    .is_empty(),
+                "{name}"
+            );
+        }
+        assert_eq!(method("read_file")["params"]["required"], json!(["path"]));
+        assert!(method("exec")["result"]["properties"]["exit_code"].is_object());
+        assert!(method("tail_cancel")["params"]["properties"]["tail_id"].is_object());
+
+        // Every method in the table is described exactly once
+        assert_eq!(methods.len(), METHODS.len());
+        for m in METHODS {
+            assert_eq!(methods.iter().filter(|d| d["name"] == m.name).count(), 1);
+        }
+    }
+
     #[test]
     fn test_method_not_found() {
         let req = make_request("unknown_method", json!({}));
@@ -214,4 +1210,56 @@ mod tests {
         assert!(resp.result.is_none());
         assert_eq!(resp.error.unwrap().code, error_codes::INVALID_PARAMS);
     }
+
+    #[test]
+    fn test_set_hostname_and_hostname() {
+        let req = make_request("set_hostname", json!({"hostname": "sandbox-3f2a9c1b"}));
+        let resp = handle_request(req);
+        assert!(resp.error.is_none());
+        assert_eq!(resp.result.unwrap(), json!({"success": true}));
+
+        // Setting is faked in tests, so this is the test machine's name
+        let expected = std::fs::read_to_string("/proc/sys/kernel/hostname").unwrap();
+        let resp = handle_request(make_request("hostname", json!({})));
+        assert!(resp.error.is_none());
+        assert_eq!(resp.result.unwrap()["hostname"], expected.trim_end());
+    }
+
+    #[test]
+    fn test_set_hostname_rejects_invalid() {
+        let req = make_request("set_hostname", json!({"hostname": "-bad_host-"}));
+        let resp = handle_request(req);
+        assert_eq!(resp.error.unwrap().code, error_codes::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_tail_returns_last_lines() {
+        let path =
+            std::env::temp_dir().join(format!("bouvet-handler-tail-{}.log", std::process::id()));
+        let content: String = (1..=20).map(|i| format!("{}\n", i)).collect();
+        std::fs::write(&path, content).unwrap();
+
+        let req = make_request("tail", json!({"path": path, "lines": 2}));
+        let resp = handle_request(req);
+        assert!(resp.error.is_none());
+        assert_eq!(resp.result.unwrap(), json!({"lines": ["19", "20"]}));
+
+        let req = make_request("tail", json!({"path": path, "lines": MAX_TAIL_LINES + 1}));
+        assert_eq!(
+            handle_request(req).error.unwrap().code,
+            error_codes::INVALID_PARAMS
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_upload_chunk_rejects_bad_base64() {
+        let req = make_request(
+            "upload_chunk",
+            json!({"upload_id": "upload-1", "offset": 0, "data": "not base64!"}),
+        );
+        let err = handle_request(req).error.unwrap();
+        assert_eq!(err.code, error_codes::INVALID_PARAMS);
+        assert!(err.message.contains("base64"));
+    }
 }
@@ -0,0 +1,199 @@
+//! Hostname operations for bouvet-agent.
+//!
+//! Provides functions to validate, set, and read the guest hostname.
+
+use std::io;
+use tracing::{debug, warn};
+
+/// Maximum hostname length accepted by the Linux kernel (`HOST_NAME_MAX`).
+const MAX_HOSTNAME_LEN: usize = 64;
+
+/// Maximum length of a single dot-separated label (RFC 1123).
+const MAX_LABEL_LEN: usize = 63;
+
+/// Check that a hostname follows RFC 1123.
+///
+/// Each dot-separated label must be 1-63 characters of ASCII letters,
+/// digits, and hyphens, and must not start or end with a hyphen.
+///
+/// # Returns
+/// `Ok(())` if the hostname is valid, or an error message.
+pub fn validate_hostname(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("hostname must not be empty".to_string());
+    }
+    if name.len() > MAX_HOSTNAME_LEN {
+        return Err(format!(
+            "hostname is too long ({} bytes, max {})",
+            name.len(),
+            MAX_HOSTNAME_LEN
+        ));
+    }
+
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > MAX_LABEL_LEN {
+            return Err(format!(
+                "invalid hostname '{}': labels must be 1-{} characters",
+                name, MAX_LABEL_LEN
+            ));
+        }
+        if !label
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        {
+            return Err(format!(
+                "invalid hostname '{}': only letters, digits, and hyphens are allowed",
+                name
+            ));
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(format!(
+                "invalid hostname '{}': labels must not start or end with a hyphen",
+                name
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Set the guest hostname.
+///
+/// Writes `/etc/hostname` so the name survives a reboot, then applies it
+/// to the running kernel with `sethostname(2)`.
+///
+/// # Arguments
+/// * `name` - New hostname, validated against RFC 1123.
+///
+/// # Returns
+/// `Ok(())` on success, or an error message.
+pub fn set_hostname(name: &str) -> Result<(), String> {
+    validate_hostname(name)?;
+    debug!(hostname = %name, "setting hostname");
+
+    sys::write_hostname_file(name).map_err(|e| {
+        warn!(error = %e, "failed to write hostname file");
+        format!("failed to write {}: {}", sys::HOSTNAME_FILE, e)
+    })?;
+    sys::sethostname(name).map_err(|e| {
+        warn!(error = %e, "sethostname failed");
+        format!("failed to set hostname: {}", e)
+    })?;
+
+    debug!(hostname = %name, "hostname set successfully");
+    Ok(())
+}
+
+/// Get the current guest hostname.
+///
+/// # Returns
+/// The hostname reported by the kernel, or an error message.
+pub fn hostname() -> Result<String, String> {
+    gethostname().map_err(|e| {
+        warn!(error = %e, "gethostname failed");
+        format!("failed to get hostname: {}", e)
+    })
+}
+
+/// Read the kernel hostname with `gethostname(2)`.
+fn gethostname() -> io::Result<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: the buffer is writable for its full length
+    let rc = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    Ok(String::from_utf8_lossy(&buf[..len]).into_owned())
+}
+
+#[cfg(not(test))]
+mod sys {
+    use std::io;
+
+    pub const HOSTNAME_FILE: &str = "/etc/hostname";
+
+    pub fn write_hostname_file(name: &str) -> io::Result<()> {
+        std::fs::write(HOSTNAME_FILE, format!("{}\n", name))
+    }
+
+    pub fn sethostname(name: &str) -> io::Result<()> {
+        // SAFETY: the pointer and length describe a valid byte buffer
+        let rc = unsafe { libc::sethostname(name.as_ptr().cast(), name.len()) };
+        if rc == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+/// Records the name instead, so tests don't rename the machine running them.
+#[cfg(test)]
+mod sys {
+    use std::cell::RefCell;
+    use std::io;
+
+    pub const HOSTNAME_FILE: &str = "/etc/hostname";
+
+    thread_local! {
+        /// Name passed to the last `sethostname` on this test's thread.
+        pub static LAST_SET: RefCell<Option<String>> = const { RefCell::new(None) };
+    }
+
+    pub fn write_hostname_file(_name: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    pub fn sethostname(name: &str) -> io::Result<()> {
+        LAST_SET.set(Some(name.to_string()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_hostname_valid() {
+        for name in ["a", "sandbox-1", "3f2a9c1b", "web.example.com", "A-b-C"] {
+            assert!(validate_hostname(name).is_ok(), "{name} should be valid");
+        }
+        assert!(validate_hostname(&"a".repeat(63)).is_ok());
+    }
+
+    #[test]
+    fn test_validate_hostname_invalid() {
+        for name in [
+            "",
+            "-leading",
+            "trailing-",
+            "under_score",
+            "sp ace",
+            "dot..dot",
+            ".leading",
+            "trailing.",
+            "ünicode",
+        ] {
+            assert!(validate_hostname(name).is_err(), "{name} should be invalid");
+        }
+        assert!(validate_hostname(&"a".repeat(64)).is_err());
+        assert!(validate_hostname(&format!("{}.{}", "a".repeat(40), "b".repeat(40))).is_err());
+    }
+
+    #[test]
+    fn test_hostname_reads_kernel_hostname() {
+        let expected = std::fs::read_to_string("/proc/sys/kernel/hostname").unwrap();
+        assert_eq!(hostname().unwrap(), expected.trim_end());
+    }
+
+    #[test]
+    fn test_set_hostname_validates_before_setting() {
+        assert!(set_hostname("bad_name").is_err());
+        assert_eq!(sys::LAST_SET.take(), None);
+
+        set_hostname("sandbox-1").unwrap();
+        assert_eq!(sys::LAST_SET.take().as_deref(), Some("sandbox-1"));
+    }
+}
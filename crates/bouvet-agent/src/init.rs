@@ -0,0 +1,119 @@
+//! PID 1 setup for bouvet-agent.
+//!
+//! With fast boot, the kernel runs the agent directly as init, so nothing
+//! else mounts pseudo-filesystems or sets up the environment. The host
+//! signals this with a `BOUVET_PID1=1` boot argument, which the kernel
+//! passes to init as an environment variable.
+
+use std::ffi::{CString, OsStr};
+use std::io;
+
+/// Environment variable set by the host's fast boot args.
+const PID1_ENV: &str = "BOUVET_PID1";
+
+/// `PATH` for spawned programs; the kernel starts init without one.
+const DEFAULT_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+/// Filesystems an init system would normally mount:
+/// (source, target, type, flags).
+const MOUNTS: &[(&str, &str, &str, libc::c_ulong)] = &[
+    (
+        "proc",
+        "/proc",
+        "proc",
+        libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC,
+    ),
+    (
+        "sysfs",
+        "/sys",
+        "sysfs",
+        libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC,
+    ),
+    ("devtmpfs", "/dev", "devtmpfs", libc::MS_NOSUID),
+    ("tmpfs", "/tmp", "tmpfs", libc::MS_NOSUID | libc::MS_NODEV),
+];
+
+/// Check whether the agent must act as init.
+pub fn running_as_pid1() -> bool {
+    pid1_requested(std::process::id(), std::env::var_os(PID1_ENV).as_deref())
+}
+
+fn pid1_requested(pid: u32, env: Option<&OsStr>) -> bool {
+    pid == 1 || env.is_some_and(|v| v == "1")
+}
+
+/// Prepare the guest for running without an init system.
+///
+/// Mounts `/proc`, `/sys`, `/dev` and `/tmp` and sets a default `PATH`.
+/// Must be called before the runtime starts any threads.
+///
+/// # Returns
+/// A message per mount that failed; the agent keeps going regardless.
+pub fn setup() -> Vec<String> {
+    if std::env::var_os("PATH").is_none() {
+        std::env::set_var("PATH", DEFAULT_PATH);
+    }
+
+    MOUNTS
+        .iter()
+        .filter_map(|&(source, target, fstype, flags)| {
+            mount(source, target, fstype, flags)
+                .err()
+                .map(|e| format!("failed to mount {} on {}: {}", fstype, target, e))
+        })
+        .collect()
+}
+
+/// Mount a fresh, empty tmpfs on `/tmp`.
+///
+/// Hides whatever the rootfs image has in `/tmp`, so every boot starts
+/// with clean scratch space. Not needed when running as PID 1, since
+/// [`setup`] already mounts one.
+pub fn mount_clean_tmp() -> io::Result<()> {
+    let &(source, target, fstype, flags) = MOUNTS
+        .iter()
+        .find(|&&(_, target, _, _)| target == "/tmp")
+        .expect("/tmp is in MOUNTS");
+    mount(source, target, fstype, flags)
+}
+
+/// Mount a pseudo-filesystem, treating "already mounted" as success.
+fn mount(source: &str, target: &str, fstype: &str, flags: libc::c_ulong) -> io::Result<()> {
+    std::fs::create_dir_all(target)?;
+    let to_c =
+        |s: &str| CString::new(s).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e));
+    let (source, target, fstype) = (to_c(source)?, to_c(target)?, to_c(fstype)?);
+
+    // SAFETY: all pointers are valid NUL-terminated strings; data may be null
+    let rc = unsafe {
+        libc::mount(
+            source.as_ptr(),
+            target.as_ptr(),
+            fstype.as_ptr(),
+            flags,
+            std::ptr::null(),
+        )
+    };
+    if rc == 0 {
+        return Ok(());
+    }
+    let err = io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::EBUSY) {
+        Ok(())
+    } else {
+        Err(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pid1_requested() {
+        assert!(pid1_requested(1, None));
+        assert!(pid1_requested(42, Some(OsStr::new("1"))));
+        assert!(!pid1_requested(42, Some(OsStr::new("0"))));
+        assert!(!pid1_requested(42, None));
+    }
+}
@@ -3,13 +3,35 @@
 //! Listens on a vsock port inside the VM and handles JSON-RPC requests
 //! for command execution, code execution, and file operations.
 
+mod config;
+mod disk;
 mod exec;
+mod frame;
 mod fs;
 mod handler;
+mod hostname;
+mod init;
+mod net;
+mod osinfo;
 mod protocol;
+mod redact;
+mod rlimit;
+mod sha256;
+mod tail;
+mod upload;
+mod user;
 
-use handler::handle_request;
-use protocol::{error_codes, Request, Response};
+use config::{AgentConfig, ConnectionLimiter};
+use exec::{OutputSink, ProcessHandle};
+use frame::FrameCodec;
+use handler::{handle_exec_code_stream, handle_exec_stream, handle_request, parse_tail_params};
+use protocol::{
+    error_codes, KillParams, Notification, OutputParams, OutputStream, Request, Response, Signal,
+    SuccessResult, TailCancelParams, TailResult,
+};
+use serde_json::json;
+use std::os::fd::AsRawFd;
+use tail::Follower;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
 use tokio_vsock::{VsockAddr, VsockListener, VsockStream, VMADDR_CID_ANY};
 use tracing::{debug, error, info, warn};
@@ -17,9 +39,22 @@ use tracing::{debug, error, info, warn};
 /// Guest port that bouvet-agent listens on.
 const GUEST_PORT: u32 = 52;
 
+/// Output chunks of an `exec_stream` or `exec_code_stream` buffered before its command is
+/// throttled by blocking on its pipes.
+const EXEC_STREAM_CAPACITY: usize = 64;
+
 fn main() {
     // Early debug output (before any async/tracing setup)
     eprintln!("[bouvet-agent] Starting (pid: {})", std::process::id());
+
+    // Fast boot: no init system ran, so set up the guest before any
+    // runtime threads exist
+    if init::running_as_pid1() {
+        eprintln!("[bouvet-agent] Running as init, mounting pseudo-filesystems...");
+        for err in init::setup() {
+            eprintln!("[bouvet-agent] WARNING: {}", err);
+        }
+    }
     eprintln!("[bouvet-agent] Building tokio runtime (current_thread for musl compatibility)...");
 
     // Use current_thread runtime - more reliable on musl systems than multi_thread
@@ -78,6 +113,66 @@ async fn async_main() -> Result<(), Box<dyn std::error::Error>> {
     }
     eprintln!("[bouvet-agent] /dev/vsock exists");
 
+    let config = AgentConfig::from_env();
+    info!(
+        max_connections = config.max_connections,
+        backlog = config.backlog,
+        buffer_size = config.buffer_size,
+        sandbox_user = ?config.sandbox_user,
+        redact_patterns = ?config.redact_patterns,
+        nofile_limit = ?config.nofile_limit,
+        nproc_limit = ?config.nproc_limit,
+        proxy = !config.proxy_env.is_empty(),
+        mounts = config.mounts.len(),
+        "agent configuration"
+    );
+    redact::init(&config.redact_patterns);
+    exec::init_env(config.proxy_env.clone());
+
+    // With fast boot, init::setup() already mounted a fresh /tmp
+    if config.clean_tmp && !init::running_as_pid1() {
+        match init::mount_clean_tmp() {
+            Ok(()) => info!("mounted clean tmpfs on /tmp"),
+            Err(e) => warn!(error = %e, "failed to mount clean /tmp"),
+        }
+    }
+
+    // A requested mount that fails aborts startup, so the host never sees
+    // a ready sandbox without it
+    for spec in &config.mounts {
+        if let Err(e) = disk::mount(spec) {
+            eprintln!("[bouvet-agent] FATAL: {}", e);
+            return Err(e.into());
+        }
+        info!(device = %spec.device, mountpoint = %spec.mountpoint, "mounted");
+    }
+
+    // Before any command runs, so every child inherits the limits
+    let limits = [
+        (rlimit::Resource::OpenFiles, config.nofile_limit),
+        (rlimit::Resource::Processes, config.nproc_limit),
+    ];
+    for (resource, limit) in limits {
+        let Some(limit) = limit else { continue };
+        match rlimit::set_limit(resource, limit) {
+            Ok(soft) => debug!(
+                resource = resource.name(),
+                limit = soft,
+                "resource limit set"
+            ),
+            Err(e) => {
+                warn!(resource = resource.name(), limit, error = %e, "failed to set resource limit")
+            }
+        }
+    }
+
+    if let Some(name) = &config.sandbox_user {
+        match user::ensure_user(name, config.sandbox_uid) {
+            Ok(created) => debug!(user = %name, created, "sandbox user ready"),
+            Err(e) => warn!(user = %name, error = %e, "failed to create sandbox user"),
+        }
+    }
+
     // Create vsock listener on port 52 (accepts connections from any CID)
     eprintln!(
         "[bouvet-agent] Binding vsock listener on port {}...",
@@ -99,9 +194,20 @@ async fn async_main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
+    // Calling listen() again on a listening socket updates its backlog
+    // SAFETY: the fd belongs to the listener, which outlives this call
+    if unsafe { libc::listen(listener.as_raw_fd(), config.backlog) } != 0 {
+        warn!(
+            error = %std::io::Error::last_os_error(),
+            backlog = config.backlog,
+            "failed to set listen backlog, using default"
+        );
+    }
+
     info!(port = GUEST_PORT, "listening on vsock");
     eprintln!("[bouvet-agent] Entering accept loop - ready for connections");
 
+    let limiter = ConnectionLimiter::new(config.max_connections);
     loop {
         match listener.accept().await {
             Ok((stream, peer_addr)) => {
@@ -110,17 +216,32 @@ async fn async_main() -> Result<(), Box<dyn std::error::Error>> {
                     peer_addr.cid(),
                     peer_addr.port()
                 );
+                let Some(permit) = limiter.try_admit() else {
+                    warn!(
+                        cid = peer_addr.cid(),
+                        port = peer_addr.port(),
+                        max_connections = config.max_connections,
+                        "connection limit reached, closing connection"
+                    );
+                    let _ = stream.shutdown(std::net::Shutdown::Both);
+                    continue;
+                };
                 debug!(
                     cid = peer_addr.cid(),
                     port = peer_addr.port(),
+                    available = limiter.available(),
                     "accepted new connection"
                 );
-                // Handle connection in the same task (current_thread runtime)
-                // Using spawn_local would require LocalSet, so we handle inline
-                if let Err(e) = handle_connection(stream).await {
-                    warn!(error = %e, "connection error");
-                    eprintln!("[bouvet-agent] Connection error: {}", e);
-                }
+                // Serve each connection in its own task so a slow request
+                // doesn't hold up other connections
+                let buffer_size = config.buffer_size;
+                tokio::spawn(async move {
+                    let _permit = permit;
+                    if let Err(e) = handle_connection(stream, buffer_size).await {
+                        warn!(error = %e, "connection error");
+                        eprintln!("[bouvet-agent] Connection error: {}", e);
+                    }
+                });
             }
             Err(e) => {
                 error!(error = %e, "failed to accept connection");
@@ -130,17 +251,62 @@ async fn async_main() -> Result<(), Box<dyn std::error::Error>> {
     }
 }
 
+/// Per-connection state.
+#[derive(Debug, Default)]
+struct Connection {
+    codec: FrameCodec,
+    /// File being followed by a `tail` with `follow: true`, if any.
+    follower: Option<Follower>,
+    /// Command running for an `exec_stream` or `exec_code_stream`, if any.
+    exec: Option<StreamingExec>,
+}
+
+/// A command started by `exec_stream` or `exec_code_stream`.
+#[derive(Debug)]
+struct StreamingExec {
+    /// ID of the request, which is answered once the command exits.
+    id: u64,
+    /// Signals the command for `kill`.
+    process: ProcessHandle,
+    /// Output as it is read, followed by the response.
+    events: tokio::sync::mpsc::Receiver<ExecEvent>,
+}
+
+/// Kill the command once nothing is left to read its output, e.g. when the
+/// host disconnects mid-stream; otherwise its process group runs on until it
+/// exits or times out. A command that already exited is left alone.
+impl Drop for StreamingExec {
+    fn drop(&mut self) {
+        if self.process.signal(Signal::Kill) {
+            debug!(exec_id = self.id, "streaming exec abandoned, killing it");
+        }
+    }
+}
+
+/// Progress of an `exec_stream` command.
+#[derive(Debug)]
+enum ExecEvent {
+    Output(OutputStream, String),
+    Done(Response),
+}
+
 /// Handle a single client connection.
 ///
 /// Reads newline-delimited JSON-RPC requests and writes responses.
 /// First handles the Firecracker vsock CONNECT handshake if present.
+/// While a file is followed, appended lines are written as `tail`
+/// notifications between responses, and while an `exec_stream` or
+/// `exec_code_stream` command runs its output is written as `output` notifications ahead of its
+/// response.
 async fn handle_connection(
     mut stream: VsockStream,
+    buffer_size: usize,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let (read_half, write_half) = stream.split();
-    let mut reader = BufReader::new(read_half);
-    let mut writer = BufWriter::new(write_half);
+    let mut reader = BufReader::with_capacity(buffer_size, read_half);
+    let mut writer = BufWriter::with_capacity(buffer_size, write_half);
     let mut line = String::new();
+    let mut conn = Connection::default();
 
     // Handle potential CONNECT handshake from Firecracker vsock proxy
     // The host connects to our vsock socket via Unix socket,
@@ -168,71 +334,542 @@ async fn handle_connection(
             .write_all(format!("OK {}\n", port).as_bytes())
             .await?;
         writer.flush().await?;
-
-        // Clear line for normal request processing
-        line.clear();
     } else if !trimmed.is_empty() {
         // First line was not a CONNECT, treat it as a JSON request
+        // Only the length is logged, as requests carry commands and file
+        // contents
         debug!(
-            request_preview = %if trimmed.len() > 200 { &trimmed[..200] } else { trimmed },
             request_len = trimmed.len(),
             "received request (no handshake)"
         );
 
-        let response = match serde_json::from_str::<Request>(trimmed) {
-            Ok(req) => handle_request(req),
-            Err(e) => {
-                warn!(error = %e, "failed to parse request");
-                Response::error(0, error_codes::PARSE_ERROR, format!("parse error: {}", e))
+        if let Some(json) = process_line(&mut conn, trimmed).await? {
+            writer.write_all(json.as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+            writer.flush().await?;
+        }
+    }
+
+    // Normal JSON-RPC request loop. Lines are read as bytes because
+    // read_until keeps partial input across select! iterations.
+    let mut buf = Vec::new();
+    loop {
+        let json = tokio::select! {
+            result = reader.read_until(b'\n', &mut buf) => {
+                if result? == 0 {
+                    debug!("client disconnected");
+                    break;
+                }
+
+                let line = String::from_utf8_lossy(&buf).into_owned();
+                buf.clear();
+                let trimmed = line.trim();
+                if trimmed.is_empty() {
+                    continue;
+                }
+
+                debug!(request_len = trimmed.len(), "received request");
+
+                // Parse, handle, and encode the response
+                match process_line(&mut conn, trimmed).await? {
+                    Some(json) => json,
+                    None => continue,
+                }
             }
+            lines = next_tail_lines(&mut conn.follower) => tail_notification(&mut conn, lines)?,
+            event = next_exec_event(&mut conn.exec) => exec_message(&mut conn, event)?,
         };
 
-        let json = serde_json::to_string(&response)?;
-        debug!(response = %json, "sending response");
         writer.write_all(json.as_bytes()).await?;
         writer.write_all(b"\n").await?;
         writer.flush().await?;
+    }
+
+    Ok(())
+}
+
+/// Decode a request line, handle it, and encode the response line, or
+/// `None` if the response is sent later by the connection loop.
+async fn process_line(
+    conn: &mut Connection,
+    line: &str,
+) -> Result<Option<String>, serde_json::Error> {
+    let response = match conn.codec.decode(line) {
+        Ok(json) => match serde_json::from_str::<Request>(&json) {
+            Ok(req) => match dispatch(conn, req).await {
+                Some(response) => response,
+                None => return Ok(None),
+            },
+            Err(e) => {
+                warn!(error = %e, "failed to parse request");
+                Response::error(0, error_codes::PARSE_ERROR, format!("parse error: {}", e))
+            }
+        },
+        Err(e) => {
+            warn!(error = %e, "failed to decode frame");
+            Response::error(0, error_codes::PARSE_ERROR, format!("parse error: {}", e))
+        }
+    };
+
+    let json = serde_json::to_string(&response)?;
+    debug!(response_len = json.len(), "sending response");
+    Ok(Some(conn.codec.encode(json)))
+}
 
-        line.clear();
+/// Route a request, handling methods that need connection state here.
+///
+/// Other methods run on the blocking thread pool, since handlers block
+/// (e.g. waiting for a command) and would otherwise stall every connection.
+/// Returns `None` for an `exec_stream` or `exec_code_stream` that started, which is answered
+/// when its command exits.
+async fn dispatch(conn: &mut Connection, req: Request) -> Option<Response> {
+    let follow = req.params.get("follow").and_then(|v| v.as_bool()) == Some(true);
+    match req.method.as_str() {
+        "tail" if follow => Some(start_follow(conn, req)),
+        "tail_cancel" => Some(cancel_follow(conn, req)),
+        "kill" => Some(kill_exec(conn, req)),
+        "exec_stream" | "exec_code_stream" => start_exec_stream(conn, req),
+        _ => {
+            let id = req.id;
+            let response = tokio::task::spawn_blocking(move || handle_request(req))
+                .await
+                .unwrap_or_else(|e| {
+                    error!(id = id, error = %e, "request handler panicked");
+                    Response::error(id, error_codes::INTERNAL_ERROR, "request handler failed")
+                });
+            Some(response)
+        }
     }
+}
 
-    // Normal JSON-RPC request loop
-    loop {
-        line.clear();
-        let bytes_read = reader.read_line(&mut line).await?;
+/// Handle `exec_stream` and `exec_code_stream`, starting the command on the
+/// blocking thread pool.
+///
+/// Output is forwarded through a bounded channel, so a host that stops
+/// reading eventually blocks the command on its pipes rather than growing
+/// the agent's memory.
+fn start_exec_stream(conn: &mut Connection, req: Request) -> Option<Response> {
+    if let Some(active) = &conn.exec {
+        return Some(Response::error(
+            req.id,
+            error_codes::INVALID_PARAMS,
+            format!(
+                "streaming exec {} is already running on this connection",
+                active.id
+            ),
+        ));
+    }
+
+    debug!(id = req.id, method = %req.method, "handling streaming exec");
+    let handle = if req.method == "exec_code_stream" {
+        handle_exec_code_stream
+    } else {
+        handle_exec_stream
+    };
+    let (tx, events) = tokio::sync::mpsc::channel(EXEC_STREAM_CAPACITY);
+    let output_tx = tx.clone();
+    // Once the connection is gone sends fail and the rest of the output is dropped
+    let output = OutputSink::new(move |stream, data| {
+        let _ = output_tx.blocking_send(ExecEvent::Output(stream, data));
+    });
+    let id = req.id;
+    let process = ProcessHandle::new();
+    let handle_process = process.clone();
+    tokio::task::spawn_blocking(move || {
+        let response = handle(id, req.params, output, handle_process);
+        let _ = tx.blocking_send(ExecEvent::Done(response));
+    });
+    conn.exec = Some(StreamingExec {
+        id,
+        process,
+        events,
+    });
+    None
+}
 
-        if bytes_read == 0 {
-            debug!("client disconnected");
-            break;
+/// Handle `kill`, signalling the process group of the connection's
+/// streaming command.
+///
+/// Succeeds with `success: false` if the command exited before the signal
+/// could be sent; its response is then already on its way.
+fn kill_exec(conn: &mut Connection, req: Request) -> Response {
+    let p = match serde_json::from_value::<KillParams>(req.params) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                req.id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            )
+        }
+    };
+    match &conn.exec {
+        Some(exec) if exec.id == p.exec_id => {
+            debug!(exec_id = p.exec_id, signal = ?p.signal, "killing streaming exec");
+            let success = exec.process.signal(p.signal);
+            Response::success(req.id, json!(SuccessResult { success }))
         }
+        _ => Response::error(
+            req.id,
+            error_codes::INVALID_PARAMS,
+            format!("invalid params: no running exec with id {}", p.exec_id),
+        ),
+    }
+}
+
+/// Wait for the next event of the streaming command, or forever if none.
+async fn next_exec_event(exec: &mut Option<StreamingExec>) -> Option<ExecEvent> {
+    match exec {
+        Some(e) => e.events.recv().await,
+        None => std::future::pending().await,
+    }
+}
 
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue;
+/// Build the `output` notification or final response for an event of the
+/// streaming command.
+fn exec_message(
+    conn: &mut Connection,
+    event: Option<ExecEvent>,
+) -> Result<String, serde_json::Error> {
+    let exec_id = conn.exec.as_ref().map(|e| e.id).unwrap_or_default();
+    let json = match event {
+        Some(ExecEvent::Output(stream, data)) => {
+            let params = OutputParams {
+                exec_id,
+                stream,
+                data,
+            };
+            serde_json::to_string(&Notification::new("output", json!(params)))?
         }
+        Some(ExecEvent::Done(response)) => {
+            conn.exec = None;
+            serde_json::to_string(&response)?
+        }
+        None => {
+            // The handler panicked before responding
+            error!(id = exec_id, "exec_stream handler failed");
+            conn.exec = None;
+            serde_json::to_string(&Response::error(
+                exec_id,
+                error_codes::INTERNAL_ERROR,
+                "request handler failed",
+            ))?
+        }
+    };
+    Ok(conn.codec.encode(json))
+}
 
-        debug!(
-            request_preview = %if trimmed.len() > 200 { &trimmed[..200] } else { trimmed },
-            request_len = trimmed.len(),
-            "received request"
+/// Handle `tail` with `follow: true`.
+///
+/// Responds with `{tail_id, lines}`; appended lines then arrive as `tail`
+/// notifications until `tail_cancel` is called.
+fn start_follow(conn: &mut Connection, req: Request) -> Response {
+    let p = match parse_tail_params(req.id, req.params) {
+        Ok(p) => p,
+        Err(e) => return Response::error(req.id, error_codes::INVALID_PARAMS, e),
+    };
+    if let Some(active) = &conn.follower {
+        return Response::error(
+            req.id,
+            error_codes::INVALID_PARAMS,
+            format!(
+                "tail {} is already being followed on this connection",
+                active.id()
+            ),
         );
+    }
 
-        // Parse request and handle
-        let response = match serde_json::from_str::<Request>(trimmed) {
-            Ok(req) => handle_request(req),
-            Err(e) => {
-                warn!(error = %e, "failed to parse request");
-                Response::error(0, error_codes::PARSE_ERROR, format!("parse error: {}", e))
+    debug!(id = req.id, path = %p.path, lines = p.lines, "handling tail (follow)");
+    match Follower::start(req.id, &p.path, p.lines) {
+        Ok((follower, lines)) => {
+            conn.follower = Some(follower);
+            Response::success(
+                req.id,
+                json!(TailResult {
+                    tail_id: Some(req.id),
+                    lines
+                }),
+            )
+        }
+        Err(e) => Response::error(req.id, error_codes::INTERNAL_ERROR, e),
+    }
+}
+
+/// Handle `tail_cancel`, stopping the followed file.
+fn cancel_follow(conn: &mut Connection, req: Request) -> Response {
+    let p = match serde_json::from_value::<TailCancelParams>(req.params) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                req.id,
+                error_codes::INVALID_PARAMS,
+                format!("invalid params: {}", e),
+            )
+        }
+    };
+    match &conn.follower {
+        Some(f) if f.id() == p.tail_id => {
+            debug!(tail_id = p.tail_id, "tail cancelled");
+            conn.follower = None;
+            Response::success(req.id, json!(SuccessResult { success: true }))
+        }
+        _ => Response::error(
+            req.id,
+            error_codes::INVALID_PARAMS,
+            format!("invalid params: no active tail with id {}", p.tail_id),
+        ),
+    }
+}
+
+/// Wait for lines appended to the followed file, or forever if none.
+async fn next_tail_lines(follower: &mut Option<Follower>) -> std::io::Result<Vec<String>> {
+    match follower {
+        Some(f) => f.next_lines().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Build the `tail` notification for newly appended lines.
+///
+/// A read error ends the follow and is reported in the notification.
+fn tail_notification(
+    conn: &mut Connection,
+    lines: std::io::Result<Vec<String>>,
+) -> Result<String, serde_json::Error> {
+    let tail_id = conn.follower.as_ref().map(Follower::id).unwrap_or_default();
+    let params = match lines {
+        Ok(lines) => json!({"tail_id": tail_id, "lines": lines}),
+        Err(e) => {
+            warn!(tail_id = tail_id, error = %e, "followed file read failed");
+            conn.follower = None;
+            json!({"tail_id": tail_id, "error": e.to_string()})
+        }
+    };
+    let json = serde_json::to_string(&Notification::new("tail", params))?;
+    Ok(conn.codec.encode(json))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn request(id: u64, method: &str, params: Value) -> String {
+        json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_follow_tail_streams_until_cancelled() {
+        let path =
+            std::env::temp_dir().join(format!("bouvet-agent-follow-{}.log", std::process::id()));
+        std::fs::write(&path, "a\nb\n").unwrap();
+        let mut conn = Connection::default();
+
+        let resp = process_line(
+            &mut conn,
+            &request(7, "tail", json!({"path": path, "lines": 1, "follow": true})),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        let resp: Value = serde_json::from_str(&resp).unwrap();
+        assert_eq!(resp["result"], json!({"tail_id": 7, "lines": ["b"]}));
+
+        // Only one follow per connection
+        let resp = process_line(
+            &mut conn,
+            &request(8, "tail", json!({"path": path, "follow": true})),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(resp.contains("already being followed"));
+
+        std::fs::write(&path, "a\nb\nc\n").unwrap();
+        let lines = next_tail_lines(&mut conn.follower).await;
+        let note: Value =
+            serde_json::from_str(&tail_notification(&mut conn, lines).unwrap()).unwrap();
+        assert_eq!(note["method"], "tail");
+        assert_eq!(note["params"], json!({"tail_id": 7, "lines": ["c"]}));
+        assert!(note.get("id").is_none());
+
+        let resp = process_line(&mut conn, &request(9, "tail_cancel", json!({"tail_id": 7})))
+            .await
+            .unwrap()
+            .unwrap();
+        let resp: Value = serde_json::from_str(&resp).unwrap();
+        assert_eq!(resp["result"], json!({"success": true}));
+        assert!(conn.follower.is_none());
+
+        let resp = process_line(
+            &mut conn,
+            &request(10, "tail_cancel", json!({"tail_id": 7})),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(resp.contains("no active tail"));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_exec_stream_sends_output_then_result() {
+        let mut conn = Connection::default();
+        let cmd = "printf 'a\\n'; printf 'oops' >&2; sleep 0.1; printf 'b\\n'; exit 3";
+        let resp = process_line(&mut conn, &request(5, "exec_stream", json!({"cmd": cmd})))
+            .await
+            .unwrap();
+        assert!(resp.is_none(), "answered once the command exits");
+
+        // Only one streaming command per connection
+        let resp = process_line(
+            &mut conn,
+            &request(6, "exec_stream", json!({"cmd": "true"})),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(resp.contains("already running"));
+
+        let mut stdout = String::new();
+        let mut stderr = String::new();
+        let result = loop {
+            let event = next_exec_event(&mut conn.exec).await;
+            let message: Value =
+                serde_json::from_str(&exec_message(&mut conn, event).unwrap()).unwrap();
+            if message.get("id").is_some() {
+                break message;
+            }
+            assert_eq!(message["method"], "output");
+            assert_eq!(message["params"]["exec_id"], 5);
+            let data = message["params"]["data"].as_str().unwrap();
+            match message["params"]["stream"].as_str().unwrap() {
+                "stdout" => stdout.push_str(data),
+                "stderr" => stderr.push_str(data),
+                other => panic!("unexpected stream {other}"),
             }
         };
+        assert_eq!(stdout, "a\nb\n");
+        assert_eq!(stderr, "oops");
+        assert_eq!(result["id"], 5);
+        assert_eq!(result["result"]["exit_code"], 3);
+        assert_eq!(result["result"]["stdout"], "");
+        assert!(conn.exec.is_none());
+    }
 
-        // Serialize and send response
-        let json = serde_json::to_string(&response)?;
-        debug!(response = %json, "sending response");
-        writer.write_all(json.as_bytes()).await?;
-        writer.write_all(b"\n").await?;
-        writer.flush().await?;
+    #[tokio::test]
+    async fn test_exec_code_stream_sends_output_then_result() {
+        let mut conn = Connection::default();
+        let params = json!({"lang": "sh", "code": "echo one; sleep 0.1; echo two"});
+        let resp = process_line(&mut conn, &request(8, "exec_code_stream", params))
+            .await
+            .unwrap();
+        assert!(resp.is_none(), "answered once the code exits");
+
+        // Shares the one streaming slot with exec_stream
+        let resp = process_line(
+            &mut conn,
+            &request(9, "exec_stream", json!({"cmd": "true"})),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(resp.contains("exec 8 is already running"), "{resp}");
+
+        let mut stdout = String::new();
+        let result = loop {
+            let event = next_exec_event(&mut conn.exec).await;
+            let message: Value =
+                serde_json::from_str(&exec_message(&mut conn, event).unwrap()).unwrap();
+            if message.get("id").is_some() {
+                break message;
+            }
+            assert_eq!(message["params"]["exec_id"], 8);
+            stdout.push_str(message["params"]["data"].as_str().unwrap());
+        };
+        assert_eq!(stdout, "one\ntwo\n");
+        assert_eq!(result["result"]["exit_code"], 0);
+        assert_eq!(result["result"]["stdout"], "");
     }
 
-    Ok(())
+    #[tokio::test]
+    async fn test_kill_stops_streaming_exec() {
+        let mut conn = Connection::default();
+        let params = json!({"cmd": "echo started; sleep 30 & wait"});
+        let resp = process_line(&mut conn, &request(3, "exec_stream", params))
+            .await
+            .unwrap();
+        assert!(resp.is_none());
+
+        let resp = process_line(&mut conn, &request(4, "kill", json!({"exec_id": 99})))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(resp.contains("no running exec with id 99"), "{resp}");
+
+        // Kill once the command is running, so the signal isn't just queued
+        let event = next_exec_event(&mut conn.exec).await;
+        assert!(exec_message(&mut conn, event).unwrap().contains("started"));
+        let resp = process_line(&mut conn, &request(5, "kill", json!({"exec_id": 3})))
+            .await
+            .unwrap()
+            .unwrap();
+        let resp: Value = serde_json::from_str(&resp).unwrap();
+        assert_eq!(resp["result"]["success"], true);
+
+        let result = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                let event = next_exec_event(&mut conn.exec).await;
+                let message: Value =
+                    serde_json::from_str(&exec_message(&mut conn, event).unwrap()).unwrap();
+                if message.get("id").is_some() {
+                    break message;
+                }
+            }
+        })
+        .await
+        .expect("killed command exits, along with its children");
+        assert_eq!(result["id"], 3);
+        assert_eq!(result["result"]["exit_code"], -1);
+        assert!(result["result"]["stderr"]
+            .as_str()
+            .unwrap()
+            .contains("cancelled with SIGTERM"));
+        assert!(conn.exec.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_kills_streaming_exec() {
+        let mut conn = Connection::default();
+        let params = json!({"cmd": "sleep 30 & echo $!; wait"});
+        let resp = process_line(&mut conn, &request(3, "exec_stream", params))
+            .await
+            .unwrap();
+        assert!(resp.is_none());
+
+        let event = next_exec_event(&mut conn.exec).await;
+        let message: Value =
+            serde_json::from_str(&exec_message(&mut conn, event).unwrap()).unwrap();
+        let pid: u32 = message["params"]["data"]
+            .as_str()
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+
+        // handle_connection drops its connection state when the host goes away
+        drop(conn);
+        let gone = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                match std::fs::read_to_string(format!("/proc/{pid}/stat")) {
+                    Ok(stat) if !stat.contains(") Z ") => {
+                        tokio::time::sleep(std::time::Duration::from_millis(20)).await
+                    }
+                    _ => break,
+                }
+            }
+        })
+        .await;
+        assert!(gone.is_ok(), "sleep {pid} outlived the connection");
+    }
 }
@@ -0,0 +1,103 @@
+//! Network reachability checks for bouvet-agent.
+//!
+//! Attempts a TCP connection from inside the guest, so the host can tell
+//! whether a service is reachable before relying on it.
+
+use crate::protocol::ConnectivityResult;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Default connect timeout (3 seconds).
+pub const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 3_000;
+
+/// Maximum connect timeout (30 seconds).
+pub const MAX_CONNECT_TIMEOUT_MS: u64 = 30_000;
+
+/// Try to open a TCP connection to `host:port`.
+///
+/// Every address the host resolves to is tried in turn, each with the
+/// full timeout. The connection is closed as soon as it is established.
+///
+/// # Arguments
+/// * `host` - Hostname or IP address.
+/// * `port` - TCP port.
+/// * `timeout` - Connect timeout per address.
+///
+/// # Returns
+/// Whether the connection succeeded, with the connect latency on success
+/// or the error on failure.
+pub fn check_connectivity(host: &str, port: u16, timeout: Duration) -> ConnectivityResult {
+    debug!(host = %host, port = port, timeout_ms = timeout.as_millis() as u64, "checking connectivity");
+    let start = Instant::now();
+
+    let addrs = match (host, port).to_socket_addrs() {
+        Ok(addrs) => addrs.collect::<Vec<_>>(),
+        Err(e) => {
+            return ConnectivityResult::failed(format!("failed to resolve '{}': {}", host, e))
+        }
+    };
+    if addrs.is_empty() {
+        return ConnectivityResult::failed(format!("'{}' resolved to no addresses", host));
+    }
+
+    let mut last_error = None;
+    for addr in addrs {
+        let attempt = Instant::now();
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(_) => {
+                let latency_ms = attempt.elapsed().as_millis() as u64;
+                debug!(addr = %addr, latency_ms = latency_ms, total_ms = start.elapsed().as_millis() as u64, "connected");
+                return ConnectivityResult {
+                    reachable: true,
+                    latency_ms: Some(latency_ms),
+                    error: None,
+                };
+            }
+            Err(e) => {
+                debug!(addr = %addr, error = %e, "connect failed");
+                last_error = Some(format!("failed to connect to {}: {}", addr, e));
+            }
+        }
+    }
+    ConnectivityResult::failed(last_error.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_check_connectivity_reachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let result = check_connectivity("127.0.0.1", port, Duration::from_secs(1));
+        assert!(result.reachable, "{:?}", result.error);
+        assert!(result.latency_ms.is_some());
+        assert!(result.error.is_none());
+    }
+
+    #[test]
+    fn test_check_connectivity_refused() {
+        // Bind then drop to get a port nothing listens on
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let result = check_connectivity("127.0.0.1", port, Duration::from_secs(1));
+        assert!(!result.reachable);
+        assert!(result.latency_ms.is_none());
+        assert!(result.error.unwrap().contains("failed to connect"));
+    }
+
+    #[test]
+    fn test_check_connectivity_unresolvable() {
+        let result = check_connectivity("no such host", 80, Duration::from_secs(1));
+        assert!(!result.reachable);
+        assert!(result.error.unwrap().contains("failed to resolve"));
+    }
+}
@@ -0,0 +1,117 @@
+//! Operating system identification for bouvet-agent.
+//!
+//! Parses the `os-release` file so the host can tell which distribution
+//! the guest runs (and so which package manager and paths to use).
+
+use crate::protocol::OsInfoResult;
+use tracing::{debug, warn};
+
+/// Locations of the os-release file, in lookup order.
+const OS_RELEASE_PATHS: &[&str] = &["/etc/os-release", "/usr/lib/os-release"];
+
+/// Read and parse the guest's os-release file.
+///
+/// # Returns
+/// The distribution identifiers, or an error message if neither
+/// `/etc/os-release` nor `/usr/lib/os-release` can be read.
+pub fn os_info() -> Result<OsInfoResult, String> {
+    for path in OS_RELEASE_PATHS {
+        match std::fs::read_to_string(path) {
+            Ok(content) => {
+                debug!(path = %path, "read os-release");
+                return Ok(parse_os_release(&content));
+            }
+            Err(e) => debug!(path = %path, error = %e, "os-release not readable"),
+        }
+    }
+    warn!("no os-release file found");
+    Err(format!(
+        "failed to read os-release (tried {})",
+        OS_RELEASE_PATHS.join(", ")
+    ))
+}
+
+/// Parse the contents of an os-release file.
+///
+/// Missing fields get the defaults from os-release(5): `ID=linux` and
+/// `PRETTY_NAME=Linux`. Unknown keys, comments and malformed lines are
+/// ignored.
+pub fn parse_os_release(content: &str) -> OsInfoResult {
+    let mut info = OsInfoResult {
+        id: "linux".to_string(),
+        version_id: None,
+        pretty_name: "Linux".to_string(),
+    };
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = unquote(value.trim());
+        match key.trim() {
+            "ID" => info.id = value,
+            "VERSION_ID" => info.version_id = Some(value),
+            "PRETTY_NAME" => info.pretty_name = value,
+            _ => {}
+        }
+    }
+    info
+}
+
+/// Strip shell-style quotes and backslash escapes from a value.
+fn unquote(value: &str) -> String {
+    let inner = match value.as_bytes() {
+        [b'"', .., b'"'] | [b'\'', .., b'\''] if value.len() >= 2 => &value[1..value.len() - 1],
+        _ => value,
+    };
+
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(escaped) = chars.next() {
+                out.push(escaped);
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_os_release() {
+        let content = r#"
+NAME="Debian GNU/Linux"
+# comment
+ID=debian
+VERSION_ID="12"
+PRETTY_NAME="Debian GNU/Linux 12 (bookworm)"
+HOME_URL="https://www.debian.org/"
+"#;
+        let info = parse_os_release(content);
+        assert_eq!(info.id, "debian");
+        assert_eq!(info.version_id.as_deref(), Some("12"));
+        assert_eq!(info.pretty_name, "Debian GNU/Linux 12 (bookworm)");
+    }
+
+    #[test]
+    fn test_parse_os_release_defaults_and_escapes() {
+        let info = parse_os_release("ID='alpine'\nPRETTY_NAME=\"Say \\\"hi\\\"\"\nbogus\n");
+        assert_eq!(info.id, "alpine");
+        assert_eq!(info.version_id, None);
+        assert_eq!(info.pretty_name, "Say \"hi\"");
+
+        let info = parse_os_release("");
+        assert_eq!(info.id, "linux");
+        assert_eq!(info.pretty_name, "Linux");
+    }
+}
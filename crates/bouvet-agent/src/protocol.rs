@@ -2,8 +2,20 @@
 //!
 //! Implements the JSON-RPC 2.0 specification for guest-host communication.
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
+
+/// Version of the host-guest RPC protocol implemented by this agent.
+///
+/// The major version changes on breaking changes; the host refuses to talk
+/// to an agent with a different major version. The minor version changes
+/// when methods or optional fields are added.
+pub const PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion {
+    major: 1,
+    minor: 27,
+};
 
 /// JSON-RPC 2.0 standard error codes.
 pub mod error_codes {
@@ -76,6 +88,30 @@ impl Response {
     }
 }
 
+/// JSON-RPC 2.0 notification (a message without an id).
+///
+/// Sent by the agent to stream data for an earlier request.
+#[derive(Debug, Serialize)]
+pub struct Notification {
+    /// Protocol version, always "2.0".
+    pub jsonrpc: String,
+    /// Notification name.
+    pub method: String,
+    /// Notification payload.
+    pub params: Value,
+}
+
+impl Notification {
+    /// Create a notification.
+    pub fn new(method: impl Into<String>, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: method.into(),
+            params,
+        }
+    }
+}
+
 /// JSON-RPC 2.0 error object.
 #[derive(Debug, Serialize)]
 pub struct RpcError {
@@ -89,7 +125,7 @@ pub struct RpcError {
 }
 
 /// Result of command execution.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ExecResult {
     /// Process exit code (-1 if the process couldn't be started).
     pub exit_code: i32,
@@ -97,6 +133,24 @@ pub struct ExecResult {
     pub stdout: String,
     /// Standard error.
     pub stderr: String,
+    /// The process was killed for printing more than `max_output_bytes`.
+    #[serde(default)]
+    pub output_limit_exceeded: bool,
+    /// Resources the process used, if requested with `usage`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ResourceUsage>,
+}
+
+/// Resources used by a process and the descendants it waited for, as
+/// reported by `wait4`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ResourceUsage {
+    /// Peak resident set size of the largest of them, in KiB.
+    pub max_rss_kib: u64,
+    /// CPU time spent in user mode, in milliseconds.
+    pub user_time_ms: u64,
+    /// CPU time spent in the kernel, in milliseconds.
+    pub system_time_ms: u64,
 }
 
 impl ExecResult {
@@ -106,12 +160,120 @@ impl ExecResult {
             exit_code: -1,
             stdout: String::new(),
             stderr: message.to_string(),
+            output_limit_exceeded: false,
+            usage: None,
         }
     }
 }
 
+/// Host-guest RPC protocol version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct ProtocolVersion {
+    /// Major version (breaking changes).
+    pub major: u32,
+    /// Minor version (backwards-compatible additions).
+    pub minor: u32,
+}
+
+/// Result of the `info` method.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct InfoResult {
+    /// Protocol version spoken by the agent.
+    pub protocol_version: ProtocolVersion,
+    /// Agent crate version.
+    pub agent_version: String,
+    /// Frame compression algorithms the agent accepts (e.g. `zstd`).
+    pub compression: Vec<String>,
+}
+
+/// Result of the `os_info` method, parsed from `/etc/os-release`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct OsInfoResult {
+    /// Distribution identifier (e.g. `debian`, `alpine`).
+    pub id: String,
+    /// Distribution version (e.g. `12`), if the file declares one.
+    pub version_id: Option<String>,
+    /// Human-readable name (e.g. `Debian GNU/Linux 12 (bookworm)`).
+    pub pretty_name: String,
+}
+
+/// Result of the `check_connectivity` method.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct ConnectivityResult {
+    /// Whether a TCP connection was established.
+    pub reachable: bool,
+    /// Time taken to connect, if reachable.
+    pub latency_ms: Option<u64>,
+    /// Why the connection failed, if unreachable.
+    pub error: Option<String>,
+}
+
+impl ConnectivityResult {
+    /// An unreachable result with the given error.
+    pub fn failed(error: String) -> Self {
+        Self {
+            reachable: false,
+            latency_ms: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Progress of a resumable upload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct UploadStatus {
+    /// ID to pass to `upload_chunk`, `upload_status` and `upload_finish`.
+    pub upload_id: String,
+    /// Destination path.
+    pub path: String,
+    /// Declared size of the complete file.
+    pub total_size: u64,
+    /// Offset the next chunk must start at (bytes received so far).
+    pub next_offset: u64,
+}
+
+/// Result of the `upload_finish` method.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct UploadFinishResult {
+    /// Path the file was written to.
+    pub path: String,
+    /// File size in bytes.
+    pub size: u64,
+    /// Verified SHA-256, lowercase hex.
+    pub sha256: String,
+}
+
+/// Result of the `download_file` method.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct DownloadFileResult {
+    /// File contents, base64-encoded.
+    pub content_base64: String,
+    /// File size in bytes.
+    pub size: u64,
+    /// SHA-256 of the contents, lowercase hex.
+    pub sha256: String,
+}
+
+/// Result of the `write_file_chunk` method.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct WriteFileChunkResult {
+    /// File size after the write, which is where the next chunk starts.
+    pub size: u64,
+}
+
+/// Result of the `read_file_chunk` method.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct ReadFileChunkResult {
+    /// Chunk contents, base64-encoded.
+    pub data: String,
+    /// Size of the whole file in bytes.
+    pub size: u64,
+    /// Whether the chunk reaches the end of the file.
+    pub eof: bool,
+}
+
 /// File entry for directory listing.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct FileEntry {
     /// File or directory name.
     pub name: String,
@@ -121,43 +283,452 @@ pub struct FileEntry {
     pub size: u64,
 }
 
+/// Kind of file reported by `stat`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FileType {
+    /// Regular file.
+    File,
+    /// Directory.
+    Dir,
+    /// Symbolic link (not followed).
+    Symlink,
+    /// Anything else: device, socket or FIFO.
+    Other,
+}
+
+/// Result of the `stat` method.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct StatResult {
+    /// What the path is.
+    pub file_type: FileType,
+    /// Size in bytes.
+    pub size: u64,
+    /// Permission bits, e.g. 420 (`0o644`).
+    pub mode: u32,
+    /// Last modification time, in milliseconds since the Unix epoch.
+    pub modified_ms: i64,
+}
+
+/// Result of the `ping` method.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PingResult {
+    /// Always true.
+    pub pong: bool,
+}
+
+/// Result of the `read_file` method.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ReadFileResult {
+    /// File contents.
+    pub content: String,
+}
+
+/// Result of methods that only report success (`write_file`, `touch`,
+/// `resize_fs`, `set_hostname`, `tail_cancel`).
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SuccessResult {
+    /// Whether the operation succeeded.
+    pub success: bool,
+}
+
+/// Result of the `list_dir` method.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ListDirResult {
+    /// Directory entries, sorted as requested.
+    pub entries: Vec<FileEntry>,
+}
+
+/// Result of the `clear_tmp` method.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ClearTmpResult {
+    /// Number of entries removed from `/tmp`.
+    pub removed: u64,
+}
+
+/// Result of the `hostname` method.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct HostnameResult {
+    /// Current guest hostname.
+    pub hostname: String,
+}
+
+/// Result of the `tail` method.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TailResult {
+    /// Id to pass to `tail_cancel`; only set when following.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tail_id: Option<u64>,
+    /// The last lines of the file, oldest first.
+    pub lines: Vec<String>,
+}
+
+/// Description of one method, as returned by `describe`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct MethodDescription {
+    /// Method name.
+    pub name: String,
+    /// JSON Schema of the method's params.
+    pub params: Value,
+    /// JSON Schema of the method's result.
+    pub result: Value,
+}
+
+/// Result of the `describe` method.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct DescribeResult {
+    /// Protocol version spoken by the agent.
+    pub protocol_version: ProtocolVersion,
+    /// Every method the agent serves.
+    pub methods: Vec<MethodDescription>,
+}
+
+/// Params of methods that take none.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct NoParams {}
+
 // Parameter types for various methods
 
 /// Parameters for the `exec` method.
-#[derive(Debug, Deserialize)]
+///
+/// Exactly one of `cmd` or `argv` must be set.
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct ExecParams {
-    /// Shell command to execute.
-    pub cmd: String,
+    /// Shell command to execute via `sh -c`.
+    #[serde(default)]
+    pub cmd: Option<String>,
+    /// Program and arguments to execute directly, without a shell.
+    #[serde(default)]
+    pub argv: Option<Vec<String>>,
+    /// Kill the process group once stdout and stderr together exceed this
+    /// many bytes.
+    #[serde(default)]
+    pub max_output_bytes: Option<u64>,
+    /// Directory to run in; the agent's working directory when unset.
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Kill the process group if it is still running after this many
+    /// seconds.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Variables set for this command only, on top of the agent's
+    /// environment. They are not kept for later commands.
+    #[serde(default)]
+    pub env: Option<HashMap<String, String>>,
+    /// Written to the command's stdin, which is then closed. Without it
+    /// the command reads from `/dev/null`.
+    #[serde(default)]
+    pub stdin: Option<String>,
+    /// Report the command's peak memory and CPU time in the result.
+    #[serde(default)]
+    pub usage: bool,
+}
+
+/// Stream a piece of `exec_stream` output was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    /// Standard output.
+    Stdout,
+    /// Standard error.
+    Stderr,
+}
+
+/// Payload of the `output` notifications sent while `exec_stream` runs.
+#[derive(Debug, Serialize)]
+pub struct OutputParams {
+    /// ID of the `exec_stream` request the output belongs to.
+    pub exec_id: u64,
+    /// Stream the data was read from.
+    pub stream: OutputStream,
+    /// Output data, split only at UTF-8 character boundaries.
+    pub data: String,
 }
 
 /// Parameters for the `exec_code` method.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct ExecCodeParams {
-    /// Programming language (python, python3, node, javascript, bash, sh).
+    /// Programming language (python, python3, node, javascript, bash, sh, ruby, go, typescript, deno, rust).
     pub lang: String,
     /// Code to execute.
     pub code: String,
+    /// Kill the process group once stdout and stderr together exceed this
+    /// many bytes.
+    #[serde(default)]
+    pub max_output_bytes: Option<u64>,
+    /// Directory to run in; the agent's working directory when unset.
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Kill the process group if it is still running after this many
+    /// seconds.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Variables set for this command only, on top of the agent's
+    /// environment. They are not kept for later commands.
+    #[serde(default)]
+    pub env: Option<HashMap<String, String>>,
+    /// Written to the command's stdin, which is then closed. Without it
+    /// the command reads from `/dev/null`.
+    #[serde(default)]
+    pub stdin: Option<String>,
+    /// Report the command's peak memory and CPU time in the result.
+    #[serde(default)]
+    pub usage: bool,
 }
 
-/// Parameters for the `read_file` method.
-#[derive(Debug, Deserialize)]
+/// Parameters for the `exec_project` method.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExecProjectParams {
+    /// Programming language (python, python3, node, javascript, bash, sh, go).
+    pub lang: String,
+    /// Relative path of the entry point; must be one of `files`.
+    pub entry: String,
+    /// Project files, as relative path to content.
+    pub files: HashMap<String, String>,
+    /// Directory to create the project in; the system temp dir when unset.
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Kill the process group once stdout and stderr together exceed this
+    /// many bytes.
+    #[serde(default)]
+    pub max_output_bytes: Option<u64>,
+    /// Kill the process group if it is still running after this many
+    /// seconds.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Variables set for this run only, on top of the agent's
+    /// environment. They are not kept for later commands.
+    #[serde(default)]
+    pub env: Option<HashMap<String, String>>,
+    /// Written to the program's stdin, which is then closed. Without it
+    /// the program reads from `/dev/null`.
+    #[serde(default)]
+    pub stdin: Option<String>,
+    /// Report the program's peak memory and CPU time in the result.
+    #[serde(default)]
+    pub usage: bool,
+}
+
+/// Parameters for the `read_file` and `download_file` methods.
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct ReadFileParams {
     /// Path to the file to read.
     pub path: String,
 }
 
 /// Parameters for the `write_file` method.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct WriteFileParams {
     /// Path to the file to write.
     pub path: String,
     /// Content to write.
     pub content: String,
+    /// Permission bits to set after writing.
+    #[serde(default)]
+    pub mode: Option<u32>,
+}
+
+/// Parameters for the `write_file_chunk` method.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct WriteFileChunkParams {
+    /// Path to the file to write.
+    pub path: String,
+    /// Offset the chunk starts at: 0 for the first chunk, then the size
+    /// returned for the previous one.
+    pub offset: u64,
+    /// Chunk contents, base64-encoded.
+    pub data: String,
+    /// Whether this is the final chunk; the file is synced to disk.
+    #[serde(default)]
+    pub is_last: bool,
+}
+
+/// Parameters for the `read_file_chunk` method.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReadFileChunkParams {
+    /// Path to the file to read.
+    pub path: String,
+    /// Offset to start reading at.
+    pub offset: u64,
+    /// Maximum number of bytes to read.
+    pub len: u64,
+}
+
+/// Parameters for the `touch` method.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TouchParams {
+    /// Path to the file to create or update.
+    pub path: String,
+}
+
+/// Parameters for the `stat` method.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct StatParams {
+    /// Path to describe. Symlinks are reported, not followed.
+    pub path: String,
+}
+
+/// Parameters for the `delete_file` method.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DeleteFileParams {
+    /// Path to the file or directory to delete.
+    pub path: String,
+    /// Delete a directory with everything in it; without this only empty
+    /// directories can be deleted.
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+/// Parameters for the `rename` method.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RenameParams {
+    /// Existing path.
+    pub from: String,
+    /// New path; an existing file there is replaced.
+    pub to: String,
+}
+
+/// Parameters for the `mkdir` method.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MkdirParams {
+    /// Directory to create, along with any missing parents.
+    pub path: String,
+}
+
+/// Parameters for the `resize_fs` method.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ResizeFsParams {
+    /// Block device whose filesystem to grow, e.g. `/dev/vdb`.
+    pub device: String,
 }
 
 /// Parameters for the `list_dir` method.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct ListDirParams {
     /// Path to the directory to list.
     pub path: String,
+    /// Key to sort entries by (default: name).
+    #[serde(default)]
+    pub sort_by: SortBy,
+    /// Reverse the sort order.
+    #[serde(default)]
+    pub descending: bool,
+    /// Only return entries whose file name matches this glob (`*`, `?`).
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// Also list subdirectories; their entries are named by relative path.
+    #[serde(default)]
+    pub recursive: bool,
+    /// Levels to list when `recursive` is set (default: unlimited).
+    /// 1 lists only the directory's own entries.
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+}
+
+/// Sort key for `list_dir`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    /// File name, byte-wise.
+    #[default]
+    Name,
+    /// File size (0 for directories).
+    Size,
+    /// Last modification time.
+    Modified,
+}
+
+/// Parameters for the `check_connectivity` method.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CheckConnectivityParams {
+    /// Hostname or IP address to connect to.
+    pub host: String,
+    /// TCP port.
+    pub port: u16,
+    /// Connect timeout in milliseconds (default 3000, max 30000).
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Parameters for the `set_hostname` method.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetHostnameParams {
+    /// New hostname (RFC 1123).
+    pub hostname: String,
+}
+
+/// Parameters for the `tail` method.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TailParams {
+    /// Path to the file to tail.
+    pub path: String,
+    /// Number of trailing lines to return (default: 10).
+    #[serde(default = "default_tail_lines")]
+    pub lines: usize,
+    /// Keep streaming appended lines as `tail` notifications until
+    /// `tail_cancel` is called.
+    #[serde(default)]
+    pub follow: bool,
+}
+
+fn default_tail_lines() -> usize {
+    10
+}
+
+/// Parameters for the `tail_cancel` method.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TailCancelParams {
+    /// Id returned by the `tail` call being cancelled.
+    pub tail_id: u64,
+}
+
+/// Parameters for the `kill` method.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct KillParams {
+    /// Id of the `exec_stream` or `exec_code_stream` request running the
+    /// command, as sent in its `output` notifications.
+    pub exec_id: u64,
+    /// Signal to send to the command's process group (default: term).
+    #[serde(default)]
+    pub signal: Signal,
+}
+
+/// Signal sent by `kill`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Signal {
+    /// SIGTERM, which the command may catch to exit cleanly.
+    #[default]
+    Term,
+    /// SIGKILL, which can't be caught.
+    Kill,
+}
+
+/// Parameters for the `upload_begin` method.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UploadBeginParams {
+    /// Destination path.
+    pub path: String,
+    /// Size of the complete file in bytes.
+    pub total_size: u64,
+    /// Expected SHA-256 of the complete file, hex.
+    pub sha256: String,
+}
+
+/// Parameters for the `upload_chunk` method.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UploadChunkParams {
+    /// Id returned by `upload_begin`.
+    pub upload_id: String,
+    /// Offset the chunk starts at.
+    pub offset: u64,
+    /// Chunk contents, base64-encoded.
+    pub data: String,
+}
+
+/// Parameters for the `upload_status` and `upload_finish` methods.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UploadIdParams {
+    /// Id returned by `upload_begin`.
+    pub upload_id: String,
 }
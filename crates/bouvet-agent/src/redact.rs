@@ -0,0 +1,23 @@
+//! Secret redaction for bouvet-agent logs.
+//!
+//! Commands and code are logged at `debug`/`trace` level, and they often
+//! carry credentials (`export GITHUB_TOKEN=...`). Everything passed to the
+//! log goes through [`redact`] first.
+
+pub use bouvet_common::redact::{Redactor, DEFAULT_PATTERNS};
+use std::sync::OnceLock;
+
+/// Process-wide redactor, set once at startup.
+static REDACTOR: OnceLock<Redactor> = OnceLock::new();
+
+/// Install the redaction patterns. Later calls are ignored.
+pub fn init(patterns: &[String]) {
+    let _ = REDACTOR.set(Redactor::new(patterns));
+}
+
+/// Redact `text` with the installed patterns (or the defaults).
+pub fn redact(text: &str) -> String {
+    REDACTOR
+        .get_or_init(|| Redactor::new(DEFAULT_PATTERNS))
+        .redact(text)
+}
@@ -0,0 +1,101 @@
+//! Process resource limits for bouvet-agent.
+//!
+//! Applied once at startup. Limits are inherited across fork and exec, so
+//! they cover every command the agent runs as well as the agent itself.
+
+use std::io;
+
+/// A resource limit the agent sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    /// Open file descriptors per process (`RLIMIT_NOFILE`).
+    OpenFiles,
+    /// Processes and threads per real user ID (`RLIMIT_NPROC`).
+    Processes,
+}
+
+/// The resource argument's type differs between glibc and musl.
+#[cfg(target_env = "gnu")]
+type RawResource = libc::__rlimit_resource_t;
+#[cfg(not(target_env = "gnu"))]
+type RawResource = libc::c_int;
+
+impl Resource {
+    fn raw(self) -> RawResource {
+        match self {
+            Resource::OpenFiles => libc::RLIMIT_NOFILE,
+            Resource::Processes => libc::RLIMIT_NPROC,
+        }
+    }
+
+    /// Name used in logs, after `ulimit`'s flags.
+    pub fn name(self) -> &'static str {
+        match self {
+            Resource::OpenFiles => "nofile",
+            Resource::Processes => "nproc",
+        }
+    }
+}
+
+/// Set both the soft and hard limit of `resource` to `limit`.
+///
+/// Setting the hard limit too means commands can't raise the limit back,
+/// unless they run as root. Raising a limit above the current hard limit
+/// needs `CAP_SYS_RESOURCE`.
+///
+/// # Returns
+/// The soft limit now in effect, read back from the kernel.
+pub fn set_limit(resource: Resource, limit: u64) -> io::Result<u64> {
+    let value = libc::rlimit {
+        rlim_cur: limit as libc::rlim_t,
+        rlim_max: limit as libc::rlim_t,
+    };
+    // SAFETY: `value` is a valid rlimit for the duration of the call
+    let rc = unsafe { libc::setrlimit(resource.raw(), &value) };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    soft_limit(resource)
+}
+
+/// Current soft limit of `resource`.
+pub fn soft_limit(resource: Resource) -> io::Result<u64> {
+    Ok(get_limit(resource)?.rlim_cur as u64)
+}
+
+fn get_limit(resource: Resource) -> io::Result<libc::rlimit> {
+    let mut value = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: `value` is writable for the duration of the call
+    let rc = unsafe { libc::getrlimit(resource.raw(), &mut value) };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_limit_reads_back_soft_limit() {
+        let original = get_limit(Resource::OpenFiles).unwrap();
+        let hard = original.rlim_max as u64;
+
+        // Raising the soft limit up to the hard limit needs no privilege
+        // and leaves the hard limit as it was
+        assert_eq!(set_limit(Resource::OpenFiles, hard).unwrap(), hard);
+        assert_eq!(soft_limit(Resource::OpenFiles).unwrap(), hard);
+
+        // SAFETY: `original` is a valid rlimit for the duration of the call
+        let rc = unsafe { libc::setrlimit(Resource::OpenFiles.raw(), &original) };
+        assert_eq!(rc, 0);
+        assert_eq!(
+            soft_limit(Resource::OpenFiles).unwrap(),
+            original.rlim_cur as u64
+        );
+    }
+}
@@ -0,0 +1,333 @@
+//! `tail` support for bouvet-agent.
+//!
+//! Reads the last lines of a file and follows files as they grow, using
+//! inotify to wake up on writes instead of polling.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::io::unix::AsyncFd;
+use tracing::{debug, warn};
+
+/// Maximum number of lines a single `tail` may return.
+pub const MAX_TAIL_LINES: usize = 10_000;
+
+/// Maximum number of files followed at once across all connections.
+pub const MAX_CONCURRENT_TAILS: usize = 4;
+
+/// Maximum bytes scanned backwards from the end of a file (10 MB).
+const MAX_TAIL_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Block size for reading a file backwards.
+const BLOCK_SIZE: u64 = 8 * 1024;
+
+/// Maximum bytes read per follow wake-up (1 MB).
+const MAX_FOLLOW_READ: u64 = 1024 * 1024;
+
+/// Number of files currently being followed.
+static ACTIVE_TAILS: AtomicUsize = AtomicUsize::new(0);
+
+/// Read the last `n` lines of a file.
+///
+/// # Arguments
+/// * `path` - Path to the file.
+/// * `n` - Number of lines to return (at most [`MAX_TAIL_LINES`]).
+///
+/// # Returns
+/// Up to `n` lines without their line terminators, or an error message.
+/// At most the last 10 MB of the file is scanned.
+pub fn tail_lines(path: &str, n: usize) -> Result<Vec<String>, String> {
+    debug!(path = %path, lines = n, "tailing file");
+    let mut file = File::open(path).map_err(|e| {
+        warn!(path = %path, error = %e, "failed to open file for tail");
+        format!("failed to open '{}': {}", path, e)
+    })?;
+    let len = file
+        .metadata()
+        .map_err(|e| format!("failed to stat '{}': {}", path, e))?
+        .len();
+    last_lines(&mut file, len, n).map_err(|e| format!("failed to read '{}': {}", path, e))
+}
+
+/// Read the last `n` lines of `file` before byte offset `len`.
+fn last_lines(file: &mut File, len: u64, n: usize) -> io::Result<Vec<String>> {
+    // Read blocks backwards until there are more than n newlines
+    let mut pos = len;
+    let mut buf: Vec<u8> = Vec::new();
+    let mut newlines = 0;
+    while pos > 0 && newlines <= n && len - pos < MAX_TAIL_BYTES {
+        let size = BLOCK_SIZE.min(pos);
+        pos -= size;
+        let mut block = vec![0u8; size as usize];
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut block)?;
+        newlines += block.iter().filter(|&&b| b == b'\n').count();
+        block.extend_from_slice(&buf);
+        buf = block;
+    }
+
+    let text = String::from_utf8_lossy(&buf);
+    let mut lines: Vec<&str> = text.lines().collect();
+    if pos > 0 && !lines.is_empty() {
+        // The first line starts before the scanned region
+        lines.remove(0);
+    }
+    let skip = lines.len().saturating_sub(n);
+    Ok(lines[skip..].iter().map(|l| l.to_string()).collect())
+}
+
+/// Slot in the global follow limit, released on drop.
+#[derive(Debug)]
+struct TailPermit;
+
+impl TailPermit {
+    fn acquire() -> Option<Self> {
+        ACTIVE_TAILS
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |active| {
+                (active < MAX_CONCURRENT_TAILS).then_some(active + 1)
+            })
+            .ok()
+            .map(|_| Self)
+    }
+}
+
+impl Drop for TailPermit {
+    fn drop(&mut self) {
+        ACTIVE_TAILS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// An inotify instance watching a single file.
+#[derive(Debug)]
+struct Inotify {
+    fd: OwnedFd,
+}
+
+impl Inotify {
+    fn watch(path: &str) -> io::Result<Self> {
+        // SAFETY: inotify_init1 has no memory-safety preconditions
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: fd is a freshly created descriptor that we own
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let c_path = std::ffi::CString::new(path)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mask = libc::IN_MODIFY | libc::IN_ATTRIB | libc::IN_CLOSE_WRITE;
+        // SAFETY: c_path is a valid NUL-terminated string
+        let wd = unsafe { libc::inotify_add_watch(fd.as_raw_fd(), c_path.as_ptr(), mask) };
+        if wd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { fd })
+    }
+
+    /// Discard all pending events.
+    fn drain(&self) -> io::Result<()> {
+        let mut buf = [0u8; 4096];
+        loop {
+            // SAFETY: buf is writable for its full length
+            let n = unsafe { libc::read(self.fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+            if n > 0 {
+                continue;
+            }
+            if n == 0 {
+                return Ok(());
+            }
+            let err = io::Error::last_os_error();
+            return if err.kind() == io::ErrorKind::WouldBlock {
+                Ok(())
+            } else {
+                Err(err)
+            };
+        }
+    }
+}
+
+impl AsRawFd for Inotify {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+/// A file being followed for appended lines (`tail -f`).
+///
+/// At most [`MAX_CONCURRENT_TAILS`] followers exist at once.
+#[derive(Debug)]
+pub struct Follower {
+    id: u64,
+    file: File,
+    pos: u64,
+    partial: Vec<u8>,
+    inotify: AsyncFd<Inotify>,
+    _permit: TailPermit,
+}
+
+impl Follower {
+    /// Start following `path` from its current end.
+    ///
+    /// Must be called from within a tokio runtime.
+    ///
+    /// # Returns
+    /// The follower and the last `n` lines before the point where
+    /// following starts, or an error message.
+    pub fn start(id: u64, path: &str, n: usize) -> Result<(Self, Vec<String>), String> {
+        let permit = TailPermit::acquire().ok_or_else(|| {
+            warn!(path = %path, "too many concurrent tails");
+            format!("too many concurrent tails (max {})", MAX_CONCURRENT_TAILS)
+        })?;
+        let mut file = File::open(path).map_err(|e| format!("failed to open '{}': {}", path, e))?;
+        let pos = file
+            .metadata()
+            .map_err(|e| format!("failed to stat '{}': {}", path, e))?
+            .len();
+        let inotify = Inotify::watch(path)
+            .and_then(AsyncFd::new)
+            .map_err(|e| format!("failed to watch '{}': {}", path, e))?;
+
+        let initial = last_lines(&mut file, pos, n)
+            .map_err(|e| format!("failed to read '{}': {}", path, e))?;
+
+        debug!(id = id, path = %path, pos = pos, "following file");
+        let follower = Self {
+            id,
+            file,
+            pos,
+            partial: Vec::new(),
+            inotify,
+            _permit: permit,
+        };
+        Ok((follower, initial))
+    }
+
+    /// Get the id the follower was started with.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Wait until complete lines have been appended and return them.
+    ///
+    /// A trailing line without a newline is held back until it is
+    /// completed. If the file is truncated, following restarts from the
+    /// beginning.
+    pub async fn next_lines(&mut self) -> io::Result<Vec<String>> {
+        loop {
+            let lines = self.read_appended()?;
+            if !lines.is_empty() {
+                return Ok(lines);
+            }
+            let mut guard = self.inotify.readable().await?;
+            guard.get_inner().drain()?;
+            guard.clear_ready();
+        }
+    }
+
+    /// Read whatever has been appended since the last read.
+    fn read_appended(&mut self) -> io::Result<Vec<String>> {
+        let len = self.file.metadata()?.len();
+        if len < self.pos {
+            debug!(
+                id = self.id,
+                "followed file truncated, restarting from start"
+            );
+            self.pos = 0;
+            self.partial.clear();
+        }
+        if len == self.pos {
+            return Ok(Vec::new());
+        }
+
+        let size = (len - self.pos).min(MAX_FOLLOW_READ);
+        let mut chunk = vec![0u8; size as usize];
+        self.file.seek(SeekFrom::Start(self.pos))?;
+        self.file.read_exact(&mut chunk)?;
+        self.pos += size;
+        self.partial.extend_from_slice(&chunk);
+
+        let Some(end) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return Ok(Vec::new());
+        };
+        let complete: Vec<u8> = self.partial.drain(..=end).collect();
+        Ok(String::from_utf8_lossy(&complete)
+            .lines()
+            .map(|l| l.to_string())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn temp_file(name: &str, content: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bouvet-agent-tail-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_tail_lines_returns_last_n() {
+        let content: String = (1..=100).map(|i| format!("line {}\n", i)).collect();
+        let path = temp_file("last-n.log", &content);
+        let path = path.to_str().unwrap();
+
+        assert_eq!(
+            tail_lines(path, 3).unwrap(),
+            ["line 98", "line 99", "line 100"]
+        );
+        assert_eq!(tail_lines(path, 1).unwrap(), ["line 100"]);
+        assert!(tail_lines(path, 0).unwrap().is_empty());
+        assert_eq!(tail_lines(path, 1000).unwrap().len(), 100);
+    }
+
+    #[test]
+    fn test_tail_lines_spans_blocks() {
+        let long = "x".repeat(BLOCK_SIZE as usize);
+        let content = format!("first\n{}\nlast without newline", long);
+        let path = temp_file("blocks.log", &content);
+        let path = path.to_str().unwrap();
+
+        let lines = tail_lines(path, 2).unwrap();
+        assert_eq!(lines, [long.as_str(), "last without newline"]);
+        assert_eq!(tail_lines(path, 5).unwrap()[0], "first");
+    }
+
+    #[test]
+    fn test_tail_lines_missing_file() {
+        assert!(tail_lines("/nonexistent/file.log", 10).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_follower_streams_appended_lines() {
+        let path = temp_file("follow.log", "old 1\nold 2\n");
+        let (mut follower, initial) = Follower::start(1, path.to_str().unwrap(), 1).unwrap();
+        assert_eq!(initial, ["old 2"]);
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"new 1\nnew 2\npart").unwrap();
+
+        let lines = tokio::time::timeout(Duration::from_secs(5), follower.next_lines())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(lines, ["new 1", "new 2"]);
+
+        file.write_all(b"ial\n").unwrap();
+        let lines = tokio::time::timeout(Duration::from_secs(5), follower.next_lines())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(lines, ["partial"]);
+    }
+}
@@ -0,0 +1,362 @@
+//! Resumable file uploads for bouvet-agent.
+//!
+//! A large file is sent as a series of chunks: `upload_begin` announces
+//! the destination, size and SHA-256, `upload_chunk` appends data at the
+//! next expected offset and `upload_finish` verifies the checksum before
+//! moving the file into place. Uploads are kept across connections, so a
+//! host that lost its connection asks `upload_status` for the offset to
+//! resume from.
+
+use crate::protocol::{UploadFinishResult, UploadStatus};
+use crate::sha256::Sha256;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tracing::{debug, warn};
+
+/// Maximum size of an uploaded file (4 GiB).
+pub const MAX_UPLOAD_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Maximum decoded size of one chunk (8 MB).
+pub const MAX_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// In-progress uploads by ID.
+static UPLOADS: Mutex<BTreeMap<String, Upload>> = Mutex::new(BTreeMap::new());
+
+/// Counter making upload IDs unique within the agent.
+static UPLOAD_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// State of one in-progress upload.
+#[derive(Debug)]
+struct Upload {
+    /// Final destination.
+    path: PathBuf,
+    /// Partial file the chunks are written to.
+    part_path: PathBuf,
+    total_size: u64,
+    /// Expected SHA-256, lowercase hex.
+    sha256: String,
+    /// Bytes received so far; the next chunk must start here.
+    next_offset: u64,
+}
+
+impl Upload {
+    fn status(&self, upload_id: &str) -> UploadStatus {
+        UploadStatus {
+            upload_id: upload_id.to_string(),
+            path: self.path.display().to_string(),
+            total_size: self.total_size,
+            next_offset: self.next_offset,
+        }
+    }
+}
+
+/// Start an upload to `path`.
+///
+/// Chunks are written to `<path>.part` until the upload finishes, so an
+/// interrupted upload never leaves a truncated file at `path`. Creates
+/// parent directories if they don't exist.
+///
+/// # Arguments
+/// * `path` - Destination file.
+/// * `total_size` - Size of the complete file in bytes.
+/// * `sha256` - Expected SHA-256 of the complete file, hex.
+///
+/// # Returns
+/// The new upload's status (offset 0), or an error message.
+pub fn upload_begin(path: &str, total_size: u64, sha256: &str) -> Result<UploadStatus, String> {
+    debug!(path = %path, total_size = total_size, "beginning upload");
+    if total_size > MAX_UPLOAD_SIZE {
+        return Err(format!(
+            "upload too large ({} bytes > {} bytes)",
+            total_size, MAX_UPLOAD_SIZE
+        ));
+    }
+    if sha256.len() != 64 || !sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!(
+            "invalid sha256 '{}': expected 64 hex digits",
+            sha256
+        ));
+    }
+
+    let path = PathBuf::from(path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| {
+            format!(
+                "failed to create directories for '{}': {}",
+                path.display(),
+                e
+            )
+        })?;
+    }
+    let mut part_path = path.clone().into_os_string();
+    part_path.push(".part");
+    let part_path = PathBuf::from(part_path);
+    File::create(&part_path)
+        .map_err(|e| format!("failed to create '{}': {}", part_path.display(), e))?;
+
+    let upload_id = format!(
+        "upload-{}",
+        UPLOAD_COUNTER.fetch_add(1, Ordering::Relaxed) + 1
+    );
+    let upload = Upload {
+        path,
+        part_path,
+        total_size,
+        sha256: sha256.to_ascii_lowercase(),
+        next_offset: 0,
+    };
+    let status = upload.status(&upload_id);
+    uploads().insert(upload_id, upload);
+    Ok(status)
+}
+
+/// Write a chunk of an upload.
+///
+/// # Arguments
+/// * `upload_id` - ID returned by [`upload_begin`].
+/// * `offset` - Where the chunk starts; must be the upload's next expected
+///   offset.
+/// * `data` - Chunk contents.
+///
+/// # Returns
+/// The upload's status after the chunk, or an error message. On a wrong
+/// offset the error names the expected one.
+pub fn upload_chunk(upload_id: &str, offset: u64, data: &[u8]) -> Result<UploadStatus, String> {
+    debug!(upload_id = %upload_id, offset = offset, len = data.len(), "writing upload chunk");
+    if data.len() > MAX_CHUNK_SIZE {
+        return Err(format!(
+            "chunk too large ({} bytes > {} bytes)",
+            data.len(),
+            MAX_CHUNK_SIZE
+        ));
+    }
+
+    // Held while writing so chunks of one upload can't interleave
+    let mut uploads = uploads();
+    let upload = uploads
+        .get_mut(upload_id)
+        .ok_or_else(|| format!("unknown upload '{}'", upload_id))?;
+    if offset != upload.next_offset {
+        return Err(format!(
+            "chunk offset {} does not match next expected offset {}",
+            offset, upload.next_offset
+        ));
+    }
+    let end = offset + data.len() as u64;
+    if end > upload.total_size {
+        return Err(format!(
+            "chunk ends at {} bytes, past the declared size of {} bytes",
+            end, upload.total_size
+        ));
+    }
+
+    write_at(&upload.part_path, offset, data)
+        .map_err(|e| format!("failed to write '{}': {}", upload.part_path.display(), e))?;
+    upload.next_offset = end;
+    Ok(upload.status(upload_id))
+}
+
+/// Report an upload's progress, e.g. to resume after a lost connection.
+pub fn upload_status(upload_id: &str) -> Result<UploadStatus, String> {
+    uploads()
+        .get(upload_id)
+        .map(|upload| upload.status(upload_id))
+        .ok_or_else(|| format!("unknown upload '{}'", upload_id))
+}
+
+/// Verify a complete upload and move it into place.
+///
+/// The upload is forgotten whether or not the checksum matches; on a
+/// mismatch the partial file is deleted and the destination is untouched.
+///
+/// # Returns
+/// The final path, size and checksum, or an error message. Finishing
+/// before all bytes arrived is an error that keeps the upload open.
+pub fn upload_finish(upload_id: &str) -> Result<UploadFinishResult, String> {
+    let upload = {
+        let mut uploads = uploads();
+        let upload = uploads
+            .get(upload_id)
+            .ok_or_else(|| format!("unknown upload '{}'", upload_id))?;
+        if upload.next_offset != upload.total_size {
+            return Err(format!(
+                "upload incomplete: {} of {} bytes received",
+                upload.next_offset, upload.total_size
+            ));
+        }
+        uploads.remove(upload_id).expect("upload present")
+    };
+    debug!(upload_id = %upload_id, path = %upload.path.display(), "finishing upload");
+
+    let actual = hash_file(&upload.part_path).map_err(|e| {
+        let _ = fs::remove_file(&upload.part_path);
+        format!("failed to read '{}': {}", upload.part_path.display(), e)
+    })?;
+    if actual != upload.sha256 {
+        warn!(upload_id = %upload_id, expected = %upload.sha256, actual = %actual, "upload checksum mismatch");
+        let _ = fs::remove_file(&upload.part_path);
+        return Err(format!(
+            "checksum mismatch for '{}': expected {}, got {}",
+            upload.path.display(),
+            upload.sha256,
+            actual
+        ));
+    }
+
+    fs::rename(&upload.part_path, &upload.path).map_err(|e| {
+        let _ = fs::remove_file(&upload.part_path);
+        format!(
+            "failed to move upload to '{}': {}",
+            upload.path.display(),
+            e
+        )
+    })?;
+    debug!(path = %upload.path.display(), size = upload.total_size, "upload complete");
+    Ok(UploadFinishResult {
+        path: upload.path.display().to_string(),
+        size: upload.total_size,
+        sha256: actual,
+    })
+}
+
+fn uploads() -> std::sync::MutexGuard<'static, BTreeMap<String, Upload>> {
+    // A panic mid-chunk leaves the map itself consistent
+    UPLOADS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn write_at(path: &Path, offset: u64, data: &[u8]) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(data)
+}
+
+fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            return Ok(hasher.finish_hex());
+        }
+        hasher.update(&buf[..n]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha256::sha256_hex;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "bouvet-upload-{}-{}-{}",
+            name,
+            std::process::id(),
+            UPLOAD_COUNTER.load(Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_upload_then_download_round_trips_binary() {
+        use base64::engine::general_purpose::STANDARD as BASE64;
+        use base64::Engine;
+
+        let dir = temp_dir("roundtrip");
+        let path = dir.join("blob.bin");
+        // Not valid UTF-8, so read_file would reject it
+        let data: Vec<u8> = (0..=255u8).rev().chain([0xff, 0xfe, 0x00]).collect();
+
+        let id = upload_begin(
+            path.to_str().unwrap(),
+            data.len() as u64,
+            &sha256_hex(&data),
+        )
+        .unwrap()
+        .upload_id;
+        upload_chunk(&id, 0, &data).unwrap();
+        let uploaded = upload_finish(&id).unwrap();
+
+        let downloaded = crate::fs::download_file(path.to_str().unwrap()).unwrap();
+        assert_eq!(BASE64.decode(&downloaded.content_base64).unwrap(), data);
+        assert_eq!(downloaded.size, uploaded.size);
+        assert_eq!(downloaded.sha256, uploaded.sha256);
+        assert!(crate::fs::read_file(path.to_str().unwrap()).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_resumable_upload() {
+        let dir = temp_dir("ok");
+        let path = dir.join("nested/data.bin");
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 253) as u8).collect();
+
+        let status = upload_begin(
+            path.to_str().unwrap(),
+            data.len() as u64,
+            &sha256_hex(&data).to_uppercase(),
+        )
+        .unwrap();
+        let id = status.upload_id;
+        assert_eq!(status.next_offset, 0);
+
+        let status = upload_chunk(&id, 0, &data[..4000]).unwrap();
+        assert_eq!(status.next_offset, 4000);
+
+        // After an interruption the host asks where to resume
+        assert_eq!(upload_status(&id).unwrap().next_offset, 4000);
+        let err = upload_chunk(&id, 8000, &data[8000..]).unwrap_err();
+        assert!(err.contains("next expected offset 4000"), "{err}");
+        let err = upload_finish(&id).unwrap_err();
+        assert!(err.contains("4000 of 10000"), "{err}");
+        assert!(!path.exists());
+
+        upload_chunk(&id, 4000, &data[4000..]).unwrap();
+        let result = upload_finish(&id).unwrap();
+        assert_eq!(result.size, 10_000);
+        assert_eq!(result.sha256, sha256_hex(&data));
+        assert_eq!(fs::read(&path).unwrap(), data);
+        assert!(!dir.join("nested/data.bin.part").exists());
+        assert!(upload_status(&id).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_upload_checksum_mismatch() {
+        let dir = temp_dir("mismatch");
+        let path = dir.join("data.txt");
+        let id = upload_begin(path.to_str().unwrap(), 5, &sha256_hex(b"hello"))
+            .unwrap()
+            .upload_id;
+        upload_chunk(&id, 0, b"jello").unwrap();
+
+        let err = upload_finish(&id).unwrap_err();
+        assert!(err.contains("checksum mismatch"), "{err}");
+        assert!(!path.exists());
+        assert!(!dir.join("data.txt.part").exists());
+        assert!(upload_status(&id).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_upload_rejects_bad_params() {
+        assert!(upload_begin("/tmp/x", 1, "abc")
+            .unwrap_err()
+            .contains("invalid sha256"));
+        assert!(upload_begin("/tmp/x", MAX_UPLOAD_SIZE + 1, &"0".repeat(64))
+            .unwrap_err()
+            .contains("too large"));
+        assert!(upload_chunk("upload-none", 0, b"x")
+            .unwrap_err()
+            .contains("unknown upload"));
+    }
+}
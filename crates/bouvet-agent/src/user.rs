@@ -0,0 +1,97 @@
+//! Unprivileged sandbox user for bouvet-agent.
+//!
+//! Ensures a non-root account exists at startup so commands can run with
+//! least privilege without the rootfs having to ship the user.
+
+use std::process::Command;
+use tracing::{debug, info, warn};
+
+/// System account database.
+const PASSWD_FILE: &str = "/etc/passwd";
+
+/// Make sure the sandbox user exists, creating it with a home directory
+/// if missing.
+///
+/// # Arguments
+/// * `name` - Username.
+/// * `uid` - User id to create the account with.
+///
+/// # Returns
+/// `true` if the user was created, `false` if it already existed, or an
+/// error message.
+pub fn ensure_user(name: &str, uid: u32) -> Result<bool, String> {
+    let passwd = std::fs::read_to_string(PASSWD_FILE)
+        .map_err(|e| format!("failed to read {}: {}", PASSWD_FILE, e))?;
+    if passwd_has_user(&passwd, name) {
+        debug!(user = %name, "sandbox user already exists");
+        return Ok(false);
+    }
+
+    info!(user = %name, uid = uid, "creating sandbox user");
+    let output = useradd_command(name, uid)
+        .output()
+        .map_err(|e| format!("failed to run useradd: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        warn!(user = %name, stderr = %stderr.trim(), "useradd failed");
+        return Err(format!(
+            "useradd failed for '{}' ({}): {}",
+            name,
+            output.status,
+            stderr.trim()
+        ));
+    }
+    Ok(true)
+}
+
+/// Check whether an `/etc/passwd` listing contains `name`.
+fn passwd_has_user(passwd: &str, name: &str) -> bool {
+    passwd
+        .lines()
+        .any(|line| line.split(':').next() == Some(name))
+}
+
+/// Build the command that creates the user with a home directory.
+fn useradd_command(name: &str, uid: u32) -> Command {
+    let mut cmd = Command::new("useradd");
+    cmd.args(["--create-home", "--shell", "/bin/sh", "--uid"])
+        .arg(uid.to_string())
+        .arg(name);
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_useradd_command() {
+        // Only inspects the command; running it would modify the host
+        let cmd = useradd_command("sandbox", 1000);
+        assert_eq!(cmd.get_program(), "useradd");
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_str().unwrap()).collect();
+        assert_eq!(
+            args,
+            [
+                "--create-home",
+                "--shell",
+                "/bin/sh",
+                "--uid",
+                "1000",
+                "sandbox"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_passwd_has_user() {
+        let passwd = "root:x:0:0:root:/root:/bin/bash\n\
+                      sandboxer:x:1001:1001::/home/sandboxer:/bin/sh\n";
+        assert!(passwd_has_user(passwd, "root"));
+        assert!(!passwd_has_user(passwd, "sandbox"));
+        assert!(passwd_has_user(
+            &format!("{passwd}sandbox:x:1000:1000::/home/sandbox:/bin/sh\n"),
+            "sandbox"
+        ));
+    }
+}
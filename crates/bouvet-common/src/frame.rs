@@ -0,0 +1,122 @@
+//! Optional zstd compression of agent messages.
+//!
+//! Messages are newline-delimited JSON. A line starting with
+//! [`COMPRESSED_PREFIX`] instead carries a base64-encoded, zstd-compressed
+//! JSON message. Compression is opt-in per connection: the host advertises
+//! support by sending a compressed frame, after which the agent compresses
+//! its large responses too. Both ends encode and decode with this module.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::io::{self, Read};
+
+/// Header marking a compressed frame. JSON messages always start with `{`.
+pub const COMPRESSED_PREFIX: &str = "zstd:";
+
+/// Compression algorithm name advertised by the agent's `info` method.
+pub const ZSTD: &str = "zstd";
+
+/// Messages smaller than this are sent uncompressed (8 KB).
+pub const COMPRESSION_THRESHOLD: usize = 8 * 1024;
+
+/// zstd compression level; low levels are fast and still shrink text well.
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Maximum decompressed frame size (64 MB).
+/// Prevents memory exhaustion from a malicious or corrupt frame.
+pub const MAX_DECOMPRESSED_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Compress a JSON message into a frame, regardless of size.
+pub fn compress(json: &str) -> io::Result<String> {
+    let bytes = zstd::bulk::compress(json.as_bytes(), COMPRESSION_LEVEL)?;
+    Ok(format!("{}{}", COMPRESSED_PREFIX, BASE64.encode(bytes)))
+}
+
+/// Encode a JSON message, compressing it if it is large enough to benefit.
+///
+/// Falls back to the plain message if compression doesn't make it smaller.
+pub fn encode(json: String) -> String {
+    if json.len() < COMPRESSION_THRESHOLD {
+        return json;
+    }
+    match compress(&json) {
+        Ok(frame) if frame.len() < json.len() => frame,
+        _ => json,
+    }
+}
+
+/// Decode a received line, decompressing it if it is a compressed frame.
+///
+/// # Errors
+///
+/// Returns an `InvalidData` error if the frame is corrupt or decompresses
+/// to more than [`MAX_DECOMPRESSED_SIZE`] bytes.
+pub fn decode(line: &str) -> io::Result<String> {
+    let Some(payload) = line.trim_end().strip_prefix(COMPRESSED_PREFIX) else {
+        return Ok(line.to_string());
+    };
+
+    let invalid = |e: &dyn std::fmt::Display| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid compressed frame: {e}"),
+        )
+    };
+    let bytes = BASE64.decode(payload).map_err(|e| invalid(&e))?;
+    let decoder = zstd::stream::read::Decoder::new(bytes.as_slice()).map_err(|e| invalid(&e))?;
+    let mut json = String::new();
+    decoder
+        .take(MAX_DECOMPRESSED_SIZE + 1)
+        .read_to_string(&mut json)
+        .map_err(|e| invalid(&e))?;
+    if json.len() as u64 > MAX_DECOMPRESSED_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("compressed frame exceeds {MAX_DECOMPRESSED_SIZE} bytes"),
+        ));
+    }
+    Ok(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_round_trip_above_threshold() {
+        let content = "fn main() { println!(\"hello\"); }\n".repeat(COMPRESSION_THRESHOLD);
+        let json = format!(r#"{{"jsonrpc":"2.0","id":7,"result":{{"content":{content:?}}}}}"#);
+
+        let frame = encode(json.clone());
+        assert!(frame.starts_with(COMPRESSED_PREFIX));
+        assert!(frame.len() < json.len() / 4);
+        assert!(!frame.contains('\n'));
+        assert_eq!(decode(&format!("{frame}\n")).unwrap(), json);
+    }
+
+    #[test]
+    fn test_small_messages_stay_plain() {
+        let json = r#"{"jsonrpc":"2.0","id":1,"method":"ping","params":{}}"#.to_string();
+        assert_eq!(encode(json.clone()), json);
+        assert_eq!(decode(&json).unwrap(), json);
+    }
+
+    #[test]
+    fn test_compress_forces_small_messages() {
+        let json = r#"{"jsonrpc":"2.0","id":1,"method":"ping","params":{}}"#;
+        let frame = compress(json).unwrap();
+        assert!(frame.starts_with(COMPRESSED_PREFIX));
+        assert_eq!(decode(&frame).unwrap(), json);
+    }
+
+    #[test]
+    fn test_decode_rejects_corrupt_frame() {
+        for frame in [
+            "zstd:!!!".to_string(),
+            format!("zstd:{}", BASE64.encode(b"plain text")),
+        ] {
+            let err = decode(&frame).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Glob matching for names.
+
+/// Match a file name against a glob pattern.
+///
+/// `*` matches any run of characters and `?` matches exactly one; every
+/// other character matches itself.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` and the name position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` swallow one more character
+                Some((star, at)) => {
+                    p = star + 1;
+                    n = at + 1;
+                    backtrack = Some((star, at + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*.tar.gz", "a.tar.gz"));
+        assert!(glob_match("file?.txt", "file1.txt"));
+        assert!(glob_match("*a*b", "xaybab"));
+        assert!(!glob_match("file?.txt", "file10.txt"));
+        assert!(!glob_match("*.py", "main.pyc"));
+    }
+}
@@ -0,0 +1,15 @@
+//! Code shared by the bouvet guest agent and the host crates.
+//!
+//! The agent is a static binary built for the guest, so it can't depend on
+//! bouvet-core (which pulls in Firecracker and hyper). Anything both sides
+//! need lives here, and this crate depends on nothing heavier than the
+//! agent does.
+
+pub mod frame;
+pub mod glob;
+pub mod redact;
+pub mod utf8;
+
+pub use glob::glob_match;
+pub use redact::{Redactor, DEFAULT_PATTERNS};
+pub use utf8::take_utf8;
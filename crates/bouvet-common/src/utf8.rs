@@ -0,0 +1,37 @@
+//! Decoding output that arrives in pieces.
+
+/// Remove and decode the start of `pending`, leaving an incomplete UTF-8
+/// sequence at its end in place.
+pub fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let complete = match std::str::from_utf8(pending) {
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => pending.len(),
+    };
+    let rest = pending.split_off(complete);
+    let data = String::from_utf8_lossy(pending).into_owned();
+    *pending = rest;
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_utf8_holds_back_split_character() {
+        let euro = "€".as_bytes();
+        let mut pending = b"ab".to_vec();
+        pending.extend_from_slice(&euro[..2]);
+        assert_eq!(take_utf8(&mut pending), "ab");
+        assert_eq!(pending, &euro[..2]);
+
+        pending.extend_from_slice(&euro[2..]);
+        assert_eq!(take_utf8(&mut pending), "€");
+        assert!(pending.is_empty());
+
+        // Invalid bytes aren't held back forever
+        let mut pending = vec![b'a', 0xff, b'b'];
+        assert_eq!(take_utf8(&mut pending), "a\u{fffd}b");
+        assert!(pending.is_empty());
+    }
+}
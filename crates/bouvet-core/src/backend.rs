@@ -0,0 +1,97 @@
+//! VM backends: where a sandbox's VM comes from.
+//!
+//! [`Sandbox`](crate::Sandbox) talks to its VM only through [`VmInstance`],
+//! and new VMs come from a [`VmBackend`]. The default
+//! [`FirecrackerBackend`] boots real microVMs; tests can substitute
+//! [`MockBackend`](crate::MockBackend) (feature `mock`) to run the full
+//! create/connect/destroy path without KVM.
+
+use async_trait::async_trait;
+use bouvet_vm::{MachineConfig, VirtualMachine};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// A VM owned by a sandbox.
+#[async_trait]
+pub trait VmInstance: Send + Sync {
+    /// Process ID of the VM, if it runs in a separate process.
+    fn pid(&self) -> Option<u32>;
+
+    /// Host-side path of the vsock socket the guest agent listens on.
+    fn vsock_uds_path(&self) -> Option<&Path>;
+
+    /// Pause all vCPUs.
+    async fn pause(&mut self) -> bouvet_vm::Result<()>;
+
+    /// Resume a paused VM.
+    async fn resume(&mut self) -> bouvet_vm::Result<()>;
+
+    /// Write a full snapshot of a paused VM.
+    async fn snapshot(&self, snapshot_path: &Path, mem_file_path: &Path) -> bouvet_vm::Result<()>;
+
+    /// Grow a writable drive's backing file to `new_size_mib` MiB.
+    async fn resize_drive(&self, drive_id: &str, new_size_mib: u64) -> bouvet_vm::Result<()>;
+
+    /// Stop the VM and release its resources.
+    async fn destroy(self: Box<Self>) -> bouvet_vm::Result<()>;
+}
+
+/// Creates the VMs sandboxes run in.
+#[async_trait]
+pub trait VmBackend: Send + Sync {
+    /// Create and boot a VM with the given ID.
+    ///
+    /// The vsock socket's parent directory exists when this is called.
+    async fn create(
+        &self,
+        id: Uuid,
+        config: MachineConfig,
+    ) -> bouvet_vm::Result<Box<dyn VmInstance>>;
+}
+
+/// Boots Firecracker microVMs through [`VirtualMachine`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FirecrackerBackend;
+
+#[async_trait]
+impl VmBackend for FirecrackerBackend {
+    async fn create(
+        &self,
+        id: Uuid,
+        config: MachineConfig,
+    ) -> bouvet_vm::Result<Box<dyn VmInstance>> {
+        let vm = VirtualMachine::create_with_id(id, config).await?;
+        Ok(Box::new(vm))
+    }
+}
+
+#[async_trait]
+impl VmInstance for VirtualMachine {
+    fn pid(&self) -> Option<u32> {
+        VirtualMachine::pid(self)
+    }
+
+    fn vsock_uds_path(&self) -> Option<&Path> {
+        VirtualMachine::vsock_uds_path(self).map(PathBuf::as_path)
+    }
+
+    async fn pause(&mut self) -> bouvet_vm::Result<()> {
+        VirtualMachine::pause(self).await
+    }
+
+    async fn resume(&mut self) -> bouvet_vm::Result<()> {
+        VirtualMachine::resume(self).await
+    }
+
+    async fn snapshot(&self, snapshot_path: &Path, mem_file_path: &Path) -> bouvet_vm::Result<()> {
+        VirtualMachine::snapshot(self, snapshot_path, mem_file_path).await
+    }
+
+    async fn resize_drive(&self, drive_id: &str, new_size_mib: u64) -> bouvet_vm::Result<()> {
+        VirtualMachine::resize_drive(self, drive_id, new_size_mib).await
+    }
+
+    async fn destroy(self: Box<Self>) -> bouvet_vm::Result<()> {
+        VirtualMachine::destroy(*self).await
+    }
+}
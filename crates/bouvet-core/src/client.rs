@@ -1,21 +1,33 @@
 //! Agent client for communicating with bouvet-agent inside a VM.
 //!
 //! This module implements the vsock connection protocol and JSON-RPC
-//! message exchange with the guest agent.
+//! message exchange with the guest agent. The client runs over any
+//! [`AgentTransport`]: Firecracker's vsock Unix socket, which needs a
+//! `CONNECT` handshake first, or a direct vsock stream, which doesn't.
 
 use crate::error::CoreError;
+use crate::frame;
+use crate::stream::{OutputChunk, Signal};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use std::path::Path;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
 use tokio::net::UnixStream;
+use tokio::sync::{mpsc, watch};
 use tokio::time::timeout;
 
 /// Guest port that bouvet-agent listens on.
 const GUEST_PORT: u32 = 52;
 
 /// Total timeout for connecting to the agent (includes retry time).
-const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub(crate) const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Interval between connection retry attempts.
 const RETRY_INTERVAL: Duration = Duration::from_millis(100);
@@ -23,14 +35,174 @@ const RETRY_INTERVAL: Duration = Duration::from_millis(100);
 /// Timeout for individual RPC calls.
 const RPC_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// Most times one call reconnects after losing the agent connection.
+const MAX_RECONNECT_ATTEMPTS: u32 = 3;
+
+/// Methods safe to send again after the connection dropped with the
+/// request already sent, since running them twice changes nothing.
+const IDEMPOTENT_METHODS: &[&str] = &["ping", "info", "read_file", "stat", "list_dir"];
+
+/// Extra time given to an exec call beyond its own timeout, for the agent
+/// to kill the command and send back what it printed.
+const EXEC_TIMEOUT_GRACE: Duration = Duration::from_secs(5);
+
+/// JSON-RPC error code returned by the agent for unknown methods.
+const METHOD_NOT_FOUND: i32 = -32601;
+
+/// JSON-RPC internal error code.
+const INTERNAL_ERROR: i32 = -32603;
+
+/// Default capacity of a connection's read and write buffers (64 KiB).
+///
+/// Well above the 8 KiB std default, so large file transfers and bulk
+/// output take fewer syscalls.
+pub const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Agent protocol version this client speaks.
+pub const PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion::new(1, 27);
+
+/// Protocol version assumed for agents that predate the `info` method.
+const LEGACY_PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion::new(1, 0);
+
+/// A byte stream to the guest agent.
+///
+/// Implemented for every async stream, e.g. a [`UnixStream`] to
+/// Firecracker's vsock socket or a `tokio_vsock::VsockStream` opened
+/// directly to the guest.
+pub trait AgentTransport: AsyncRead + AsyncWrite + Send + Unpin + 'static {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin + 'static> AgentTransport for T {}
+
+type Transport = Box<dyn AgentTransport>;
+
+/// Settings for an agent connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientConfig {
+    /// Capacity of the read and write buffers in bytes
+    /// (default: [`DEFAULT_BUFFER_SIZE`]).
+    pub buffer_size: usize,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            buffer_size: DEFAULT_BUFFER_SIZE,
+        }
+    }
+}
+
 /// Client for communicating with bouvet-agent inside a VM.
 ///
 /// This client connects to the guest agent via Firecracker's vsock Unix socket
-/// and exchanges JSON-RPC 2.0 messages.
+/// (or any other [`AgentTransport`]) and exchanges JSON-RPC 2.0 messages.
 pub struct AgentClient {
-    reader: BufReader<tokio::io::ReadHalf<UnixStream>>,
-    writer: BufWriter<tokio::io::WriteHalf<UnixStream>>,
+    reader: BufReader<tokio::io::ReadHalf<Transport>>,
+    writer: BufWriter<tokio::io::WriteHalf<Transport>>,
     next_id: u64,
+    protocol_version: ProtocolVersion,
+    agent_compression: Vec<String>,
+    compression: Compression,
+    /// A request was sent and its response not fully read, e.g. because
+    /// the call timed out or was cancelled.
+    in_flight: bool,
+    /// Cuts short any request waiting on this connection.
+    cancel: CancelToken,
+    /// Socket the connection was opened on, to reconnect through if it
+    /// drops. `None` for streams handed in by the caller.
+    vsock_path: Option<PathBuf>,
+    /// Settings the connection was opened with.
+    config: ClientConfig,
+}
+
+/// Cancellation signal shared by the connections of one sandbox.
+///
+/// Once cancelled it stays cancelled: requests waiting for a response
+/// return [`CoreError::Cancelled`], and so do any started afterwards.
+#[derive(Clone)]
+pub(crate) struct CancelToken(Arc<watch::Sender<bool>>);
+
+impl CancelToken {
+    pub(crate) fn new() -> Self {
+        Self(Arc::new(watch::channel(false).0))
+    }
+
+    /// Cancel every operation watching this token.
+    pub(crate) fn cancel(&self) {
+        self.0.send_replace(true);
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Resolve once the token is cancelled.
+    pub(crate) async fn cancelled(&self) {
+        let mut rx = self.0.subscribe();
+        // The sender lives in self, so the channel cannot close
+        let _ = rx.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+/// Frame compression state of a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    /// Messages are sent as plain JSON.
+    Off,
+    /// The next request is compressed to switch the agent over.
+    Pending,
+    /// Large requests are compressed; the agent compresses large responses.
+    On,
+}
+
+/// Wait for the agent behind `vsock_path` to become ready.
+///
+/// Retries the vsock connection (handshake and protocol negotiation) until
+/// it succeeds or `timeout` elapses, then pings the agent to confirm it is
+/// serving requests. Use this for any VM whose agent is (or soon will be)
+/// running: a fresh boot, a reattach, or a restored snapshot.
+///
+/// # Errors
+///
+/// Returns [`CoreError::AgentTimeout`] if no connection is made within
+/// `timeout`, [`CoreError::IncompatibleProtocol`] on a major version
+/// mismatch, or the ping's error if the agent does not answer it.
+pub async fn wait_for_agent(
+    vsock_path: &Path,
+    timeout: Duration,
+) -> Result<AgentClient, CoreError> {
+    wait_for_agent_timed(vsock_path, timeout, &ClientConfig::default())
+        .await
+        .map(|ready| ready.client)
+}
+
+/// A ready agent connection and how long each step took.
+pub(crate) struct AgentReady {
+    pub client: AgentClient,
+    /// Connecting, including retries and version negotiation.
+    pub connect_ms: u64,
+    /// The initial ping round-trip.
+    pub ping_ms: u64,
+}
+
+/// [`wait_for_agent`], reporting the connect and ping times separately.
+pub(crate) async fn wait_for_agent_timed(
+    vsock_path: &Path,
+    timeout: Duration,
+    config: &ClientConfig,
+) -> Result<AgentReady, CoreError> {
+    let start = std::time::Instant::now();
+    let mut client = AgentClient::connect_with(vsock_path, timeout, config).await?;
+    let connect_ms = start.elapsed().as_millis() as u64;
+
+    tracing::trace!(path = %vsock_path.display(), "Pinging agent");
+    client.ping().await?;
+    let ping_ms = start.elapsed().as_millis() as u64 - connect_ms;
+
+    Ok(AgentReady {
+        client,
+        connect_ms,
+        ping_ms,
+    })
 }
 
 impl AgentClient {
@@ -44,10 +216,37 @@ impl AgentClient {
     ///
     /// * `vsock_path` - Path to the vsock Unix socket (e.g., `/tmp/bouvet/vm-1/v.sock`)
     ///
+    /// After connecting, the agent's protocol version is queried and checked
+    /// against [`PROTOCOL_VERSION`].
+    ///
     /// # Errors
     ///
-    /// Returns an error if the connection cannot be established within the timeout.
+    /// Returns an error if the connection cannot be established within the timeout,
+    /// or [`CoreError::IncompatibleProtocol`] if the agent speaks a different
+    /// major protocol version.
     pub async fn connect(vsock_path: &Path) -> Result<Self, CoreError> {
+        Self::connect_timeout(vsock_path, CONNECT_TIMEOUT).await
+    }
+
+    /// Connect to the agent, retrying for up to `connect_timeout`.
+    ///
+    /// Like [`AgentClient::connect`], with a caller-chosen timeout.
+    pub async fn connect_timeout(
+        vsock_path: &Path,
+        connect_timeout: Duration,
+    ) -> Result<Self, CoreError> {
+        Self::connect_with(vsock_path, connect_timeout, &ClientConfig::default()).await
+    }
+
+    /// Connect to the agent, retrying for up to `connect_timeout`, with
+    /// the given connection settings.
+    ///
+    /// Like [`AgentClient::connect_timeout`].
+    pub async fn connect_with(
+        vsock_path: &Path,
+        connect_timeout: Duration,
+        config: &ClientConfig,
+    ) -> Result<Self, CoreError> {
         let start = std::time::Instant::now();
         tracing::debug!(path = %vsock_path.display(), "Connecting to agent");
 
@@ -55,25 +254,27 @@ impl AgentClient {
         let mut attempts = 0u32;
         loop {
             attempts += 1;
-            match Self::try_connect(vsock_path).await {
-                Ok(client) => {
+            match Self::try_connect(vsock_path, config).await {
+                Ok(mut client) => {
+                    client.negotiate_version().await?;
                     tracing::info!(
                         path = %vsock_path.display(),
                         elapsed_ms = start.elapsed().as_millis() as u64,
                         attempts,
+                        protocol_version = %client.protocol_version,
                         "Connected to agent"
                     );
                     return Ok(client);
                 }
                 Err(e) => {
-                    if start.elapsed() >= CONNECT_TIMEOUT {
+                    if start.elapsed() >= connect_timeout {
                         tracing::warn!(
                             path = %vsock_path.display(),
                             elapsed_ms = start.elapsed().as_millis() as u64,
                             attempts,
                             "Agent connection timeout"
                         );
-                        return Err(CoreError::AgentTimeout(CONNECT_TIMEOUT));
+                        return Err(CoreError::AgentTimeout(connect_timeout));
                     }
                     tracing::trace!(error = %e, attempt = attempts, "Connection attempt failed, retrying...");
                     tokio::time::sleep(RETRY_INTERVAL).await;
@@ -82,29 +283,95 @@ impl AgentClient {
         }
     }
 
+    /// Connect over a stream to Firecracker's vsock Unix socket.
+    ///
+    /// Sends the `CONNECT` handshake for the agent's port, then negotiates
+    /// the protocol version. Unlike [`connect`](Self::connect) this makes
+    /// a single attempt on an already open stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CoreError::Connection`] if the handshake is refused, or
+    /// [`CoreError::IncompatibleProtocol`] for a different major version.
+    pub async fn connect_uds(stream: impl AgentTransport) -> Result<Self, CoreError> {
+        Self::connect_uds_with(stream, &ClientConfig::default()).await
+    }
+
+    /// Like [`connect_uds`](Self::connect_uds), with the given connection
+    /// settings.
+    pub async fn connect_uds_with(
+        stream: impl AgentTransport,
+        config: &ClientConfig,
+    ) -> Result<Self, CoreError> {
+        let mut client = Self::handshake(Box::new(stream), config).await?;
+        client.negotiate_version().await?;
+        Ok(client)
+    }
+
+    /// Connect over a direct vsock stream to the agent's port, such as a
+    /// `tokio_vsock::VsockStream` opened by a VM manager other than
+    /// Firecracker. There is no proxy, so no `CONNECT` handshake is sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CoreError::IncompatibleProtocol`] if the agent speaks a
+    /// different major protocol version.
+    pub async fn connect_vsock(stream: impl AgentTransport) -> Result<Self, CoreError> {
+        Self::connect_vsock_with(stream, &ClientConfig::default()).await
+    }
+
+    /// Like [`connect_vsock`](Self::connect_vsock), with the given
+    /// connection settings.
+    pub async fn connect_vsock_with(
+        stream: impl AgentTransport,
+        config: &ClientConfig,
+    ) -> Result<Self, CoreError> {
+        let mut client = Self::from_transport(Box::new(stream), config);
+        client.negotiate_version().await?;
+        Ok(client)
+    }
+
     /// Attempt a single connection to the vsock socket.
-    async fn try_connect(vsock_path: &Path) -> Result<Self, CoreError> {
-        // 1. Connect to the Unix socket
+    async fn try_connect(vsock_path: &Path, config: &ClientConfig) -> Result<Self, CoreError> {
         tracing::trace!(path = %vsock_path.display(), "Attempting socket connection");
         let stream = UnixStream::connect(vsock_path)
             .await
             .map_err(|e| CoreError::Connection(format!("socket connect failed: {e}")))?;
+        let mut client = Self::handshake(Box::new(stream), config).await?;
+        client.vsock_path = Some(vsock_path.to_path_buf());
+        Ok(client)
+    }
 
-        let (read_half, write_half) = tokio::io::split(stream);
-        let mut reader = BufReader::new(read_half);
-        let mut writer = BufWriter::new(write_half);
+    /// Replace a lost connection with a new one through `vsock_path`.
+    ///
+    /// The protocol version is negotiated again and compression restored;
+    /// the cancellation token carries over.
+    async fn reconnect(&mut self, vsock_path: &Path) -> Result<(), CoreError> {
+        let mut fresh = Self::try_connect(vsock_path, &self.config).await?;
+        fresh.negotiate_version().await?;
+        if self.compression != Compression::Off {
+            fresh.enable_compression().await?;
+        }
+        fresh.cancel = self.cancel.clone();
+        *self = fresh;
+        tracing::info!(path = %vsock_path.display(), "Reconnected to agent");
+        Ok(())
+    }
+
+    /// Ask Firecracker's vsock proxy to connect `stream` to the agent.
+    async fn handshake(stream: Transport, config: &ClientConfig) -> Result<Self, CoreError> {
+        let mut client = Self::from_transport(stream, config);
 
-        // 2. Send CONNECT handshake
         tracing::trace!(port = GUEST_PORT, "Sending CONNECT handshake");
-        writer
+        client
+            .writer
             .write_all(format!("CONNECT {GUEST_PORT}\n").as_bytes())
             .await
             .map_err(|e| CoreError::Connection(format!("handshake write failed: {e}")))?;
-        writer.flush().await?;
+        client.writer.flush().await?;
 
-        // 3. Read response
         let mut response = String::new();
-        reader.read_line(&mut response).await?;
+        client.reader.read_line(&mut response).await?;
 
         if !response.starts_with("OK ") {
             tracing::debug!(response = %response.trim(), "Handshake failed");
@@ -115,12 +382,108 @@ impl AgentClient {
         }
 
         tracing::debug!(response = %response.trim(), "vsock handshake successful");
+        Ok(client)
+    }
 
-        Ok(Self {
-            reader,
-            writer,
+    /// Wrap a connected stream, before version negotiation.
+    fn from_transport(stream: Transport, config: &ClientConfig) -> Self {
+        let (read_half, write_half) = tokio::io::split(stream);
+        Self {
+            reader: BufReader::with_capacity(config.buffer_size, read_half),
+            writer: BufWriter::with_capacity(config.buffer_size, write_half),
             next_id: 1,
-        })
+            protocol_version: LEGACY_PROTOCOL_VERSION,
+            agent_compression: Vec::new(),
+            compression: Compression::Off,
+            in_flight: false,
+            cancel: CancelToken::new(),
+            vsock_path: None,
+            config: *config,
+        }
+    }
+
+    /// Query the agent's protocol version and check it is compatible.
+    ///
+    /// Agents without the `info` method are assumed to speak
+    /// the legacy 1.0 protocol.
+    async fn negotiate_version(&mut self) -> Result<(), CoreError> {
+        // Not call: a reconnect negotiates through here
+        let guest = match self
+            .call_once::<InfoResponse>("info", &serde_json::json!({}), RPC_TIMEOUT)
+            .await
+        {
+            Ok(info) => {
+                tracing::debug!(
+                    protocol_version = %info.protocol_version,
+                    agent_version = %info.agent_version,
+                    "Agent info received"
+                );
+                self.agent_compression = info.compression;
+                info.protocol_version
+            }
+            Err(CoreError::Rpc { code, .. }) if code == METHOD_NOT_FOUND => {
+                tracing::debug!("Agent does not support info, assuming legacy protocol");
+                LEGACY_PROTOCOL_VERSION
+            }
+            Err(e) => return Err(e),
+        };
+
+        self.protocol_version = negotiate(PROTOCOL_VERSION, guest)?;
+        Ok(())
+    }
+
+    /// Enable zstd compression of large messages on this connection.
+    ///
+    /// Compression is off by default. It is only enabled if the agent
+    /// advertised `zstd` support in its `info` response; responses are then
+    /// decompressed transparently.
+    ///
+    /// # Returns
+    ///
+    /// Whether compression is now enabled.
+    pub async fn enable_compression(&mut self) -> Result<bool, CoreError> {
+        if self.compression == Compression::On {
+            return Ok(true);
+        }
+        if !self.agent_compression.iter().any(|c| c == frame::ZSTD) {
+            tracing::debug!("Agent does not support compression");
+            return Ok(false);
+        }
+
+        // A compressed frame tells the agent to compress its responses too
+        self.compression = Compression::Pending;
+        let _: PingResponse = self
+            .call_once("ping", &serde_json::json!({}), RPC_TIMEOUT)
+            .await?;
+        tracing::debug!("Frame compression enabled");
+        Ok(true)
+    }
+
+    /// Whether the connection can carry another request.
+    ///
+    /// False once a call ended before its response was read, since a late
+    /// response would be taken as the answer to the next request.
+    pub(crate) fn is_reusable(&self) -> bool {
+        !self.in_flight
+    }
+
+    /// Cut requests on this connection short when `cancel` is cancelled.
+    pub(crate) fn set_cancel(&mut self, cancel: CancelToken) {
+        self.cancel = cancel;
+    }
+
+    /// Fail with [`CoreError::Cancelled`] if the connection's operations
+    /// were cancelled.
+    fn check_cancelled(&self) -> Result<(), CoreError> {
+        if self.cancel.is_cancelled() {
+            return Err(CoreError::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Get the protocol version spoken by the connected agent.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
     }
 
     /// Send a JSON-RPC request and wait for response.
@@ -138,8 +501,106 @@ impl AgentClient {
         method: &str,
         params: P,
     ) -> Result<R, CoreError> {
+        self.call_within(method, params, RPC_TIMEOUT).await
+    }
+
+    /// Like [`AgentClient::call`], waiting up to `rpc_timeout` for the
+    /// response.
+    ///
+    /// If the connection drops (a broken pipe, reset or EOF, e.g. because
+    /// the guest rebooted), the client reconnects through its socket, up to
+    /// [`MAX_RECONNECT_ATTEMPTS`] times. The request is only sent again if
+    /// it never went out, or if it is one of [`IDEMPOTENT_METHODS`]; any
+    /// other request may already have run, so the call fails with
+    /// [`CoreError::Connection`] on the new connection instead.
+    async fn call_within<P: Serialize, R: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        params: P,
+        rpc_timeout: Duration,
+    ) -> Result<R, CoreError> {
+        // Kept to send again on a new connection
+        let params = serde_json::to_value(params)?;
+        let mut attempts = 0;
+        loop {
+            self.check_cancelled()?;
+            let (lost, sent) = match self.send_request(method, &params).await {
+                Ok(id) => match self.read_response(method, id, rpc_timeout).await {
+                    Err(e) if is_disconnect(&e) => (e, true),
+                    result => return result,
+                },
+                Err(e) if is_disconnect(&e) => (e, false),
+                Err(e) => return Err(e),
+            };
+            let Some(vsock_path) = self.vsock_path.clone() else {
+                return Err(lost);
+            };
+            tracing::warn!(method = %method, error = %lost, "Agent connection lost, reconnecting");
+            loop {
+                if attempts == MAX_RECONNECT_ATTEMPTS {
+                    return Err(CoreError::Connection(format!(
+                        "agent connection lost ({lost}) and {attempts} reconnect attempts failed"
+                    )));
+                }
+                attempts += 1;
+                match self.reconnect(&vsock_path).await {
+                    Ok(()) => break,
+                    Err(e) => {
+                        tracing::debug!(attempt = attempts, error = %e, "Reconnect failed");
+                        tokio::time::sleep(RETRY_INTERVAL).await;
+                    }
+                }
+            }
+            if sent && !IDEMPOTENT_METHODS.contains(&method) {
+                return Err(CoreError::Connection(format!(
+                    "agent connection lost ({lost}) after sending {method}; \
+                     reconnected, but not resending it as it may already have run"
+                )));
+            }
+        }
+    }
+
+    /// Send one request and read its response, without reconnecting.
+    async fn call_once<R: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        params: &serde_json::Value,
+        rpc_timeout: Duration,
+    ) -> Result<R, CoreError> {
+        self.check_cancelled()?;
+        let id = self.send_request(method, params).await?;
+        self.read_response(method, id, rpc_timeout).await
+    }
+
+    /// Read the response to request `id`, skipping notifications.
+    async fn read_response<R: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        id: u64,
+        rpc_timeout: Duration,
+    ) -> Result<R, CoreError> {
+        // Read response with timeout, skipping notifications (messages without an id)
+        let response = loop {
+            let message = self.read_message(method, id, rpc_timeout).await?;
+            if message.get("id").is_none() && message.get("method").is_some() {
+                tracing::trace!(method = %method, id, "Skipping notification");
+                continue;
+            }
+            self.in_flight = false;
+            break message;
+        };
+        Self::parse_response(method, id, response)
+    }
+
+    /// Send a request, returning its ID.
+    async fn send_request<P: Serialize>(
+        &mut self,
+        method: &str,
+        params: P,
+    ) -> Result<u64, CoreError> {
         let id = self.next_id;
         self.next_id += 1;
+        self.in_flight = true;
 
         // Build request
         let request = serde_json::json!({
@@ -152,22 +613,54 @@ impl AgentClient {
         // Send request (newline-delimited)
         let request_str = serde_json::to_string(&request)?;
         tracing::debug!(method = %method, id, "Sending RPC request");
-        tracing::trace!(request = %request_str, "RPC request body");
+        // Bodies carry commands, env values, stdin and file contents
+        tracing::trace!(method = %method, id, len = request_str.len(), "RPC request body");
 
-        self.writer.write_all(request_str.as_bytes()).await?;
+        let frame = match self.compression {
+            Compression::Off => request_str,
+            Compression::Pending => {
+                self.compression = Compression::On;
+                frame::compress(&request_str)?
+            }
+            Compression::On => frame::encode(request_str),
+        };
+        self.writer.write_all(frame.as_bytes()).await?;
         self.writer.write_all(b"\n").await?;
         self.writer.flush().await?;
+        Ok(id)
+    }
 
-        // Read response with timeout
+    /// Read the next message, waiting up to `rpc_timeout` for it.
+    async fn read_message(
+        &mut self,
+        method: &str,
+        id: u64,
+        rpc_timeout: Duration,
+    ) -> Result<serde_json::Value, CoreError> {
         let mut response_str = String::new();
-        match timeout(RPC_TIMEOUT, self.reader.read_line(&mut response_str)).await {
+        let read = tokio::select! {
+            read = timeout(rpc_timeout, self.reader.read_line(&mut response_str)) => read,
+            _ = self.cancel.cancelled() => {
+                tracing::debug!(method = %method, id, "RPC cancelled");
+                return Err(CoreError::Cancelled);
+            }
+        };
+        match read {
+            Ok(Ok(0)) => {
+                tracing::warn!(method = %method, id, "Agent closed connection");
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "agent closed connection",
+                )
+                .into());
+            }
             Ok(Ok(_)) => {}
             Ok(Err(e)) => {
                 tracing::warn!(method = %method, id, error = %e, "RPC read error");
                 return Err(e.into());
             }
             Err(_) => {
-                tracing::warn!(method = %method, id, timeout_secs = RPC_TIMEOUT.as_secs(), "RPC response timeout");
+                tracing::warn!(method = %method, id, timeout_secs = rpc_timeout.as_secs(), "RPC response timeout");
                 return Err(CoreError::Rpc {
                     code: -1,
                     message: "response timeout".into(),
@@ -175,11 +668,17 @@ impl AgentClient {
             }
         }
 
-        tracing::trace!(response = %response_str.trim(), "RPC response body");
-
-        // Parse response
-        let response: serde_json::Value = serde_json::from_str(&response_str)?;
+        let response_str = frame::decode(&response_str)?;
+        tracing::trace!(method = %method, id, len = response_str.len(), "RPC response body");
+        Ok(serde_json::from_str(&response_str)?)
+    }
 
+    /// Extract the result of the response to request `id`.
+    fn parse_response<R: DeserializeOwned>(
+        method: &str,
+        id: u64,
+        response: serde_json::Value,
+    ) -> Result<R, CoreError> {
         // Check for error
         if let Some(error) = response.get("error") {
             let code = error.get("code").and_then(|v| v.as_i64()).unwrap_or(-1) as i32;
@@ -213,21 +712,327 @@ impl AgentClient {
 
     /// Execute a shell command.
     pub async fn exec(&mut self, cmd: &str) -> Result<ExecResult, CoreError> {
-        tracing::debug!(cmd = %cmd, "Executing command via agent");
-        self.call("exec", serde_json::json!({ "cmd": cmd })).await
+        self.exec_with(cmd, &ExecOptions::default()).await
+    }
+
+    /// Execute a shell command with per-call limits.
+    pub async fn exec_with(
+        &mut self,
+        cmd: &str,
+        options: &ExecOptions,
+    ) -> Result<ExecResult, CoreError> {
+        tracing::debug!(cmd_len = cmd.len(), ?options, "Executing command via agent");
+        self.call_within(
+            "exec",
+            serde_json::json!({
+                "cmd": cmd,
+                "max_output_bytes": options.max_output_bytes,
+                "cwd": options.cwd,
+                "timeout_secs": options.timeout_secs,
+                "env": options.env,
+                "stdin": options.stdin,
+                "usage": options.usage,
+            }),
+            options.rpc_timeout(),
+        )
+        .await
+    }
+
+    /// Execute a program directly, without a shell.
+    ///
+    /// # Arguments
+    ///
+    /// * `argv` - Program followed by its arguments, passed verbatim
+    pub async fn exec_argv(&mut self, argv: &[String]) -> Result<ExecResult, CoreError> {
+        self.exec_argv_with(argv, &ExecOptions::default()).await
+    }
+
+    /// Execute a program directly, without a shell, with per-call settings.
+    pub async fn exec_argv_with(
+        &mut self,
+        argv: &[String],
+        options: &ExecOptions,
+    ) -> Result<ExecResult, CoreError> {
+        tracing::debug!(argc = argv.len(), ?options, "Executing argv via agent");
+        self.call_within(
+            "exec",
+            serde_json::json!({
+                "argv": argv,
+                "max_output_bytes": options.max_output_bytes,
+                "cwd": options.cwd,
+                "timeout_secs": options.timeout_secs,
+                "env": options.env,
+                "stdin": options.stdin,
+                "usage": options.usage,
+            }),
+            options.rpc_timeout(),
+        )
+        .await
     }
 
     /// Execute code in a specific language.
     ///
     /// # Arguments
     ///
-    /// * `lang` - Language identifier (python, python3, node, javascript, bash, sh)
+    /// * `lang` - Language identifier (python, python3, node, javascript, bash, sh, ruby, go, typescript, deno, rust)
     /// * `code` - Code to execute
     pub async fn exec_code(&mut self, lang: &str, code: &str) -> Result<ExecResult, CoreError> {
-        tracing::debug!(lang = %lang, code_len = code.len(), "Executing code via agent");
-        self.call(
+        self.exec_code_with(lang, code, &ExecOptions::default())
+            .await
+    }
+
+    /// Execute code in a specific language with per-call limits.
+    pub async fn exec_code_with(
+        &mut self,
+        lang: &str,
+        code: &str,
+        options: &ExecOptions,
+    ) -> Result<ExecResult, CoreError> {
+        tracing::debug!(lang = %lang, code_len = code.len(), ?options, "Executing code via agent");
+        self.call_within(
             "exec_code",
-            serde_json::json!({ "lang": lang, "code": code }),
+            serde_json::json!({
+                "lang": lang,
+                "code": code,
+                "max_output_bytes": options.max_output_bytes,
+                "cwd": options.cwd,
+                "timeout_secs": options.timeout_secs,
+                "env": options.env,
+                "stdin": options.stdin,
+                "usage": options.usage,
+            }),
+            options.rpc_timeout(),
+        )
+        .await
+    }
+
+    /// Execute a shell command, sending its output to `tx` as it is
+    /// produced.
+    ///
+    /// Returns once the command exits. The result's `stdout` and `stderr`
+    /// are empty, since the output went to `tx`, apart from a note on
+    /// stderr if the command timed out. Sending waits for room in the
+    /// channel, so a slow receiver throttles the command; if the receiver
+    /// is dropped, the rest of the output is discarded. Each message is
+    /// awaited for as long as an [`exec_with`](Self::exec_with) response.
+    ///
+    /// Signals received on `signals` are sent to the command's process
+    /// group with `kill` (protocol 1.25); a signalled command's result has
+    /// exit code -1 and a note on stderr.
+    pub async fn exec_stream(
+        &mut self,
+        cmd: &str,
+        options: &ExecOptions,
+        tx: mpsc::Sender<OutputChunk>,
+        signals: Option<mpsc::Receiver<Signal>>,
+    ) -> Result<ExecResult, CoreError> {
+        tracing::debug!(cmd_len = cmd.len(), ?options, "Streaming command via agent");
+        self.stream_exec(
+            "exec_stream",
+            serde_json::json!({
+                "cmd": cmd,
+                "max_output_bytes": options.max_output_bytes,
+                "cwd": options.cwd,
+                "timeout_secs": options.timeout_secs,
+                "env": options.env,
+                "stdin": options.stdin,
+                "usage": options.usage,
+            }),
+            options,
+            tx,
+            signals,
+        )
+        .await
+    }
+
+    /// Execute code in a specific language, sending its output to `tx` as
+    /// it is produced.
+    ///
+    /// Behaves like [`exec_stream`](Self::exec_stream). Requires protocol
+    /// 1.23.
+    pub async fn exec_code_stream(
+        &mut self,
+        lang: &str,
+        code: &str,
+        options: &ExecOptions,
+        tx: mpsc::Sender<OutputChunk>,
+        signals: Option<mpsc::Receiver<Signal>>,
+    ) -> Result<ExecResult, CoreError> {
+        tracing::debug!(lang = %lang, code_len = code.len(), ?options, "Streaming code via agent");
+        self.stream_exec(
+            "exec_code_stream",
+            serde_json::json!({
+                "lang": lang,
+                "code": code,
+                "max_output_bytes": options.max_output_bytes,
+                "cwd": options.cwd,
+                "timeout_secs": options.timeout_secs,
+                "env": options.env,
+                "stdin": options.stdin,
+                "usage": options.usage,
+            }),
+            options,
+            tx,
+            signals,
+        )
+        .await
+    }
+
+    /// Send a streaming exec request and forward its `output`
+    /// notifications to `tx` until the response arrives, passing on
+    /// signals from `signals` as `kill` requests.
+    async fn stream_exec(
+        &mut self,
+        method: &'static str,
+        params: serde_json::Value,
+        options: &ExecOptions,
+        tx: mpsc::Sender<OutputChunk>,
+        mut signals: Option<mpsc::Receiver<Signal>>,
+    ) -> Result<ExecResult, CoreError> {
+        self.check_cancelled()?;
+        let id = self.send_request(method, params).await?;
+
+        let rpc_timeout = options.rpc_timeout();
+        // read_until keeps partial input in buf if a signal arrives first
+        let mut buf = Vec::new();
+        let mut result = None;
+        // Responses to kill requests still to be read, so none is left
+        // behind for the next call on this connection
+        let mut pending_kills = 0usize;
+        while result.is_none() || pending_kills > 0 {
+            tokio::select! {
+                signal = next_signal(&mut signals) => match signal {
+                    Some(signal) if result.is_none() => {
+                        tracing::debug!(id, ?signal, "Signalling streamed command");
+                        self.send_request("kill", serde_json::json!({"exec_id": id, "signal": signal}))
+                            .await?;
+                        pending_kills += 1;
+                    }
+                    Some(_) => {}
+                    None => signals = None,
+                },
+                _ = self.cancel.cancelled() => {
+                    // Best effort: the connection is discarded either way
+                    if result.is_none() {
+                        tracing::debug!(id, "Killing streamed command of cancelled operation");
+                        let kill = serde_json::json!({"exec_id": id, "signal": Signal::Kill});
+                        if let Err(e) = self.send_request("kill", kill).await {
+                            tracing::debug!(id, error = %e, "Failed to kill cancelled command");
+                        }
+                    }
+                    return Err(CoreError::Cancelled);
+                }
+                read = timeout(rpc_timeout, self.reader.read_until(b'\n', &mut buf)) => {
+                    match read {
+                        Ok(Ok(0)) => {
+                            return Err(CoreError::Connection(
+                                "agent closed connection during streaming exec".into(),
+                            ))
+                        }
+                        Ok(Ok(_)) => {}
+                        Ok(Err(e)) => {
+                            tracing::warn!(method = %method, id, error = %e, "RPC read error");
+                            return Err(e.into());
+                        }
+                        Err(_) => {
+                            tracing::warn!(method = %method, id, timeout_secs = rpc_timeout.as_secs(), "RPC response timeout");
+                            return Err(CoreError::Rpc {
+                                code: -1,
+                                message: "response timeout".into(),
+                            });
+                        }
+                    }
+                    let line = frame::decode(&String::from_utf8_lossy(&buf))?;
+                    buf.clear();
+                    tracing::trace!(method = %method, id, len = line.len(), "RPC response body");
+                    let message: serde_json::Value = serde_json::from_str(&line)?;
+                    match message.get("id").and_then(|v| v.as_u64()) {
+                        Some(response_id) if response_id == id => result = Some(message),
+                        Some(response_id) => {
+                            pending_kills = pending_kills.saturating_sub(1);
+                            if let Some(error) = message.get("error") {
+                                tracing::debug!(id = response_id, %error, "kill failed");
+                            }
+                        }
+                        None => match serde_json::from_value::<OutputNotification>(message) {
+                            Ok(note) if note.method == "output" && note.params.exec_id == id => {
+                                // Keep reading after the receiver is gone, up to the response
+                                let _ = tx.send(note.params.chunk).await;
+                            }
+                            _ => tracing::trace!(
+                                id,
+                                method,
+                                "Ignoring unexpected message during streaming exec"
+                            ),
+                        },
+                    }
+                }
+            }
+        }
+
+        self.in_flight = false;
+        Self::parse_response(method, id, result.expect("loop ends with a response"))
+    }
+
+    /// Execute a multi-file program.
+    ///
+    /// # Arguments
+    ///
+    /// * `lang` - Language identifier (python, python3, node, javascript, bash, sh, go)
+    /// * `entry` - Relative path of the entry point; must be a key of `files`
+    /// * `files` - Relative path to content of every project file
+    pub async fn exec_project(
+        &mut self,
+        lang: &str,
+        entry: &str,
+        files: &HashMap<String, String>,
+    ) -> Result<ExecResult, CoreError> {
+        self.exec_project_in(lang, entry, files, None).await
+    }
+
+    /// Execute a multi-file program with its project directory created
+    /// inside `cwd` rather than the guest's temp dir.
+    pub async fn exec_project_in(
+        &mut self,
+        lang: &str,
+        entry: &str,
+        files: &HashMap<String, String>,
+        cwd: Option<&str>,
+    ) -> Result<ExecResult, CoreError> {
+        let options = ExecOptions {
+            cwd: cwd.map(str::to_string),
+            ..Default::default()
+        };
+        self.exec_project_with(lang, entry, files, &options).await
+    }
+
+    /// Execute a multi-file program with per-call limits.
+    ///
+    /// `options.cwd` is where the project directory is created. Limits,
+    /// environment, stdin and usage need an agent speaking protocol 1.27;
+    /// older agents ignore them.
+    pub async fn exec_project_with(
+        &mut self,
+        lang: &str,
+        entry: &str,
+        files: &HashMap<String, String>,
+        options: &ExecOptions,
+    ) -> Result<ExecResult, CoreError> {
+        tracing::debug!(lang = %lang, entry = %entry, files = files.len(), ?options, "Executing project via agent");
+        self.call_within(
+            "exec_project",
+            serde_json::json!({
+                "lang": lang,
+                "entry": entry,
+                "files": files,
+                "cwd": options.cwd,
+                "max_output_bytes": options.max_output_bytes,
+                "timeout_secs": options.timeout_secs,
+                "env": options.env,
+                "stdin": options.stdin,
+                "usage": options.usage,
+            }),
+            options.rpc_timeout(),
         )
         .await
     }
@@ -241,46 +1046,571 @@ impl AgentClient {
         Ok(resp.content)
     }
 
+    /// Read a file's raw bytes from the guest, base64-encoded.
+    ///
+    /// Unlike [`read_file`](Self::read_file) this is safe for binary
+    /// files. Needs an agent speaking protocol 1.13.
+    pub async fn download_file(&mut self, path: &str) -> Result<FileDownload, CoreError> {
+        tracing::debug!(path = %path, "Downloading file from guest");
+        self.call("download_file", serde_json::json!({ "path": path }))
+            .await
+    }
+
     /// Write a file to the guest filesystem.
     pub async fn write_file(&mut self, path: &str, content: &str) -> Result<(), CoreError> {
-        tracing::debug!(path = %path, content_len = content.len(), "Writing file to guest");
+        self.write_file_with_mode(path, content, None).await
+    }
+
+    /// Write a file to the guest filesystem and set its permission bits.
+    ///
+    /// With `mode` unset this is [`write_file`](Self::write_file). Agents
+    /// older than protocol 1.10 ignore the mode.
+    pub async fn write_file_with_mode(
+        &mut self,
+        path: &str,
+        content: &str,
+        mode: Option<u32>,
+    ) -> Result<(), CoreError> {
+        tracing::debug!(path = %path, content_len = content.len(), mode = ?mode, "Writing file to guest");
         let _: WriteFileResponse = self
             .call(
                 "write_file",
-                serde_json::json!({ "path": path, "content": content }),
+                serde_json::json!({ "path": path, "content": content, "mode": mode }),
             )
             .await?;
         Ok(())
     }
 
-    /// List directory contents.
-    pub async fn list_dir(&mut self, path: &str) -> Result<Vec<FileEntry>, CoreError> {
-        tracing::debug!(path = %path, "Listing directory on guest");
-        let resp: ListDirResponse = self
-            .call("list_dir", serde_json::json!({ "path": path }))
+    /// Write one chunk of a file at `offset` and return the new file size.
+    ///
+    /// Offset 0 creates or truncates the file; later chunks must start at
+    /// the size the previous one returned. `is_last` syncs the file to disk.
+    /// Requires protocol 1.20.
+    pub async fn write_file_chunk(
+        &mut self,
+        path: &str,
+        offset: u64,
+        data: &[u8],
+        is_last: bool,
+    ) -> Result<u64, CoreError> {
+        tracing::trace!(path = %path, offset, len = data.len(), is_last, "Sending file chunk");
+        let response: WriteFileChunkResponse = self
+            .call(
+                "write_file_chunk",
+                serde_json::json!({
+                    "path": path,
+                    "offset": offset,
+                    "data": BASE64.encode(data),
+                    "is_last": is_last,
+                }),
+            )
             .await?;
-        tracing::trace!(count = resp.entries.len(), "Directory entries received");
-        Ok(resp.entries)
+        Ok(response.size)
     }
-}
 
-/// Result from command execution.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ExecResult {
-    /// Process exit code (-1 if the process couldn't be started).
-    pub exit_code: i32,
-    /// Standard output.
-    pub stdout: String,
-    /// Standard error.
-    pub stderr: String,
-}
+    /// Read up to `len` bytes of a file starting at `offset`.
+    ///
+    /// Requires protocol 1.20.
+    pub async fn read_file_chunk(
+        &mut self,
+        path: &str,
+        offset: u64,
+        len: u64,
+    ) -> Result<FileChunk, CoreError> {
+        tracing::trace!(path = %path, offset, len, "Reading file chunk");
+        let response: ReadFileChunkResponse = self
+            .call(
+                "read_file_chunk",
+                serde_json::json!({ "path": path, "offset": offset, "len": len }),
+            )
+            .await?;
+        let data = BASE64.decode(&response.data).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("agent sent invalid base64 for '{path}': {e}"),
+            )
+        })?;
+        Ok(FileChunk {
+            data,
+            size: response.size,
+            eof: response.eof,
+        })
+    }
 
-impl ExecResult {
-    /// Check if the command succeeded (exit code 0).
-    pub fn success(&self) -> bool {
-        self.exit_code == 0
+    /// Create an empty file or update its modification time.
+    ///
+    /// Existing files are not truncated.
+    pub async fn touch(&mut self, path: &str) -> Result<(), CoreError> {
+        tracing::debug!(path = %path, "Touching file on guest");
+        let _: WriteFileResponse = self
+            .call("touch", serde_json::json!({ "path": path }))
+            .await?;
+        Ok(())
     }
-}
+
+    /// Get a path's type, size, permission bits and modification time.
+    ///
+    /// Symlinks are described rather than followed. Requires protocol 1.22.
+    pub async fn stat(&mut self, path: &str) -> Result<FileStat, CoreError> {
+        tracing::debug!(path = %path, "Stat on guest");
+        self.call("stat", serde_json::json!({ "path": path })).await
+    }
+
+    /// Delete a file or directory; non-empty directories need `recursive`.
+    ///
+    /// Requires protocol 1.21.
+    pub async fn delete_file(&mut self, path: &str, recursive: bool) -> Result<(), CoreError> {
+        tracing::debug!(path = %path, recursive, "Deleting path on guest");
+        let _: WriteFileResponse = self
+            .call(
+                "delete_file",
+                serde_json::json!({ "path": path, "recursive": recursive }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Rename or move a file or directory, replacing an existing file at `to`.
+    ///
+    /// Requires protocol 1.21.
+    pub async fn rename(&mut self, from: &str, to: &str) -> Result<(), CoreError> {
+        tracing::debug!(from = %from, to = %to, "Renaming path on guest");
+        let _: WriteFileResponse = self
+            .call("rename", serde_json::json!({ "from": from, "to": to }))
+            .await?;
+        Ok(())
+    }
+
+    /// Create a directory and any missing parents.
+    ///
+    /// Requires protocol 1.21.
+    pub async fn mkdir(&mut self, path: &str) -> Result<(), CoreError> {
+        tracing::debug!(path = %path, "Creating directory on guest");
+        let _: WriteFileResponse = self
+            .call("mkdir", serde_json::json!({ "path": path }))
+            .await?;
+        Ok(())
+    }
+
+    /// Start a resumable upload of a `total_size`-byte file to `path`.
+    ///
+    /// `sha256` is the hex checksum of the complete file, verified by
+    /// [`upload_finish`](Self::upload_finish).
+    pub async fn upload_begin(
+        &mut self,
+        path: &str,
+        total_size: u64,
+        sha256: &str,
+    ) -> Result<UploadStatus, CoreError> {
+        tracing::debug!(path = %path, total_size, "Beginning upload to guest");
+        self.call(
+            "upload_begin",
+            serde_json::json!({ "path": path, "total_size": total_size, "sha256": sha256 }),
+        )
+        .await
+    }
+
+    /// Write a chunk of an upload at `offset`, which must be the upload's
+    /// next expected offset.
+    pub async fn upload_chunk(
+        &mut self,
+        upload_id: &str,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<UploadStatus, CoreError> {
+        tracing::trace!(upload_id = %upload_id, offset, len = data.len(), "Sending upload chunk");
+        self.call(
+            "upload_chunk",
+            serde_json::json!({
+                "upload_id": upload_id,
+                "offset": offset,
+                "data": BASE64.encode(data),
+            }),
+        )
+        .await
+    }
+
+    /// Get an upload's progress, e.g. the offset to resume from after a
+    /// lost connection.
+    pub async fn upload_status(&mut self, upload_id: &str) -> Result<UploadStatus, CoreError> {
+        self.call(
+            "upload_status",
+            serde_json::json!({ "upload_id": upload_id }),
+        )
+        .await
+    }
+
+    /// Verify an upload's checksum and move the file into place.
+    ///
+    /// Fails if bytes are missing or the checksum doesn't match; on a
+    /// mismatch the upload is discarded.
+    pub async fn upload_finish(&mut self, upload_id: &str) -> Result<UploadFinish, CoreError> {
+        tracing::debug!(upload_id = %upload_id, "Finishing upload");
+        self.call(
+            "upload_finish",
+            serde_json::json!({ "upload_id": upload_id }),
+        )
+        .await
+    }
+
+    /// Try a TCP connection to `host:port` from inside the guest.
+    ///
+    /// The agent caps `timeout` at 30 seconds.
+    pub async fn check_connectivity(
+        &mut self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> Result<ConnectivityResult, CoreError> {
+        tracing::debug!(host = %host, port, "Checking connectivity from guest");
+        self.call(
+            "check_connectivity",
+            serde_json::json!({
+                "host": host,
+                "port": port,
+                "timeout_ms": timeout.as_millis() as u64,
+            }),
+        )
+        .await
+    }
+
+    /// Remove everything in the guest's `/tmp`.
+    ///
+    /// Returns the number of top-level entries removed.
+    pub async fn clear_tmp(&mut self) -> Result<u64, CoreError> {
+        tracing::debug!("Clearing guest /tmp");
+        let resp: ClearTmpResponse = self.call("clear_tmp", serde_json::json!({})).await?;
+        Ok(resp.removed)
+    }
+
+    /// Grow the filesystem on a guest block device to fill the device.
+    pub async fn resize_fs(&mut self, device: &str) -> Result<(), CoreError> {
+        tracing::debug!(device = %device, "Growing guest filesystem");
+        let _: WriteFileResponse = self
+            .call("resize_fs", serde_json::json!({ "device": device }))
+            .await?;
+        Ok(())
+    }
+
+    /// Set the guest hostname.
+    ///
+    /// The agent rejects names that aren't valid RFC 1123 hostnames.
+    /// Requires protocol 1.26.
+    pub async fn set_hostname(&mut self, hostname: &str) -> Result<(), CoreError> {
+        tracing::debug!(hostname = %hostname, "Setting guest hostname");
+        let _: WriteFileResponse = self
+            .call("set_hostname", serde_json::json!({ "hostname": hostname }))
+            .await?;
+        Ok(())
+    }
+
+    /// Get the guest hostname. Requires protocol 1.26.
+    pub async fn hostname(&mut self) -> Result<String, CoreError> {
+        let resp: HostnameResponse = self.call("hostname", serde_json::json!({})).await?;
+        Ok(resp.hostname)
+    }
+
+    /// Get the guest's distribution identifiers from `/etc/os-release`.
+    pub async fn os_info(&mut self) -> Result<OsInfo, CoreError> {
+        self.call("os_info", serde_json::json!({})).await
+    }
+
+    /// List the methods the agent serves, with JSON Schemas for their
+    /// params and results. Needs an agent speaking protocol 1.14.
+    pub async fn describe(&mut self) -> Result<AgentDescription, CoreError> {
+        self.call("describe", serde_json::json!({})).await
+    }
+
+    /// Read the last `lines` lines of a file.
+    pub async fn tail(&mut self, path: &str, lines: usize) -> Result<Vec<String>, CoreError> {
+        tracing::debug!(path = %path, lines, "Tailing file on guest");
+        let resp: TailResponse = self
+            .call("tail", serde_json::json!({ "path": path, "lines": lines }))
+            .await?;
+        Ok(resp.lines)
+    }
+
+    /// Start following a file (`tail -f`).
+    ///
+    /// After this returns, the agent streams appended lines on this
+    /// connection; call [`follow_tail`](Self::follow_tail) next to forward
+    /// them and to stop following.
+    ///
+    /// # Returns
+    ///
+    /// The tail id and the last `lines` lines of the file.
+    pub async fn start_tail(
+        &mut self,
+        path: &str,
+        lines: usize,
+    ) -> Result<(u64, Vec<String>), CoreError> {
+        tracing::debug!(path = %path, lines, "Following file on guest");
+        let resp: TailResponse = self
+            .call(
+                "tail",
+                serde_json::json!({ "path": path, "lines": lines, "follow": true }),
+            )
+            .await?;
+        let tail_id = resp.tail_id.ok_or_else(|| CoreError::Rpc {
+            code: -1,
+            message: "missing tail_id in response".into(),
+        })?;
+        Ok((tail_id, resp.lines))
+    }
+
+    /// Forward lines of a followed file into `tx` until `cancel` resolves.
+    ///
+    /// `initial` lines are sent first. Following also stops when the
+    /// receiver is dropped. The agent is then told to stop with
+    /// `tail_cancel`, so the connection is usable again when this returns.
+    pub async fn follow_tail(
+        &mut self,
+        tail_id: u64,
+        initial: Vec<String>,
+        tx: mpsc::Sender<String>,
+        cancel: impl Future<Output = ()>,
+    ) -> Result<(), CoreError> {
+        tokio::pin!(cancel);
+        let mut receiver_open = true;
+        for line in initial {
+            if tx.send(line).await.is_err() {
+                receiver_open = false;
+                break;
+            }
+        }
+
+        // read_until keeps partial input in buf if another branch wins
+        let mut buf = Vec::new();
+        while receiver_open {
+            tokio::select! {
+                _ = &mut cancel => break,
+                _ = tx.closed() => break,
+                _ = self.cancel.cancelled() => {
+                    // Leaves the tail running; the connection is discarded
+                    self.in_flight = true;
+                    return Err(CoreError::Cancelled);
+                }
+                read = self.reader.read_until(b'\n', &mut buf) => {
+                    if read? == 0 {
+                        return Err(CoreError::Connection("agent closed connection during tail".into()));
+                    }
+                    let message = frame::decode(&String::from_utf8_lossy(&buf))?;
+                    buf.clear();
+                    let note = match serde_json::from_str::<TailNotification>(&message) {
+                        Ok(note) if note.params.tail_id == tail_id => note,
+                        _ => {
+                            tracing::trace!(len = message.len(), "Ignoring unexpected message during tail");
+                            continue;
+                        }
+                    };
+                    if let Some(message) = note.params.error {
+                        return Err(CoreError::Rpc { code: INTERNAL_ERROR, message });
+                    }
+                    for line in note.params.lines {
+                        if tx.send(line).await.is_err() {
+                            receiver_open = false;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Finish a partially read message so the next response parses cleanly
+        if !buf.is_empty() && !buf.ends_with(b"\n") {
+            self.reader.read_until(b'\n', &mut buf).await?;
+        }
+
+        tracing::debug!(tail_id, "Stopping tail");
+        let _: WriteFileResponse = self
+            .call("tail_cancel", serde_json::json!({ "tail_id": tail_id }))
+            .await?;
+        Ok(())
+    }
+
+    /// List directory contents.
+    pub async fn list_dir(
+        &mut self,
+        path: &str,
+        options: &ListDirOptions,
+    ) -> Result<Vec<FileEntry>, CoreError> {
+        tracing::debug!(path = %path, ?options, "Listing directory on guest");
+        let resp: ListDirResponse = self
+            .call(
+                "list_dir",
+                serde_json::json!({
+                    "path": path,
+                    "sort_by": options.sort_by,
+                    "descending": options.descending,
+                    "pattern": options.pattern,
+                    "recursive": options.recursive,
+                    "max_depth": options.max_depth,
+                }),
+            )
+            .await?;
+        tracing::trace!(count = resp.entries.len(), "Directory entries received");
+        Ok(resp.entries)
+    }
+}
+
+/// Result from command execution.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecResult {
+    /// Process exit code (-1 if the process couldn't be started).
+    pub exit_code: i32,
+    /// Standard output.
+    pub stdout: String,
+    /// Standard error.
+    pub stderr: String,
+    /// Whether the process was killed for exceeding
+    /// [`ExecOptions::max_output_bytes`].
+    #[serde(default)]
+    pub output_limit_exceeded: bool,
+    /// Resources the process used, if requested with
+    /// [`ExecOptions::usage`].
+    #[serde(default)]
+    pub usage: Option<ResourceUsage>,
+}
+
+/// Resources used by a command and the descendants it waited for, as
+/// reported by `wait4` in the guest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// Peak resident set size of the largest of them, in KiB.
+    pub max_rss_kib: u64,
+    /// CPU time spent in user mode, in milliseconds.
+    pub user_time_ms: u64,
+    /// CPU time spent in the kernel, in milliseconds.
+    pub system_time_ms: u64,
+}
+
+/// Per-call settings for `exec` and `exec_code`.
+///
+/// The default applies no limits beyond the agent's own.
+#[derive(Default, Clone, PartialEq, Eq)]
+pub struct ExecOptions {
+    /// Kill the process group once stdout and stderr together exceed this
+    /// many bytes. Output is truncated either way.
+    pub max_output_bytes: Option<u64>,
+    /// Guest directory to run in. A [`Sandbox`](crate::Sandbox) falls back
+    /// to its configured `work_dir`, the agent to its own (`/`).
+    pub cwd: Option<String>,
+    /// Kill the process group if it is still running after this many
+    /// seconds. The result then has exit code -1 and stderr ending in
+    /// `timed out after Ns`.
+    pub timeout_secs: Option<u64>,
+    /// Variables set for this command only, on top of the guest's default
+    /// environment. They aren't kept for later commands. Values are never
+    /// logged, at any level, since they often hold secrets.
+    pub env: HashMap<String, String>,
+    /// Input written to the command's stdin, which is then closed
+    /// (default: none, so stdin is empty). Only its length is logged; trace
+    /// logs show the length of the whole request instead of its body.
+    pub stdin: Option<String>,
+    /// Report the command's peak memory and CPU time in
+    /// [`ExecResult::usage`] (default: off).
+    pub usage: bool,
+}
+
+impl fmt::Debug for ExecOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut env: Vec<&str> = self.env.keys().map(String::as_str).collect();
+        env.sort_unstable();
+        f.debug_struct("ExecOptions")
+            .field("max_output_bytes", &self.max_output_bytes)
+            .field("cwd", &self.cwd)
+            .field("timeout_secs", &self.timeout_secs)
+            .field("env", &env)
+            .field("stdin_len", &self.stdin.as_ref().map(String::len))
+            .field("usage", &self.usage)
+            .finish()
+    }
+}
+
+impl ExecOptions {
+    /// How long to wait for the agent's response: the usual RPC timeout,
+    /// or long enough for the command to time out in the guest.
+    fn rpc_timeout(&self) -> Duration {
+        self.timeout_secs
+            .map(|secs| Duration::from_secs(secs) + EXEC_TIMEOUT_GRACE)
+            .map_or(RPC_TIMEOUT, |t| t.max(RPC_TIMEOUT))
+    }
+}
+
+impl ExecResult {
+    /// Check if the command succeeded (exit code 0).
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+
+    /// Check the exit code against an expected value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CoreError::UnexpectedExit`] with the command's stderr if
+    /// the exit code differs.
+    pub fn expect_exit(self, expected: i32) -> Result<Self, CoreError> {
+        if self.exit_code == expected {
+            Ok(self)
+        } else {
+            Err(CoreError::UnexpectedExit {
+                expected,
+                actual: self.exit_code,
+                stderr: self.stderr,
+            })
+        }
+    }
+}
+
+/// Host-guest RPC protocol version.
+///
+/// Agents with a different major version are incompatible. Minor versions
+/// only add methods or optional fields, so a minor mismatch is allowed; the
+/// guest's version can be used to gate newer features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ProtocolVersion {
+    /// Major version (breaking changes).
+    pub major: u32,
+    /// Minor version (backwards-compatible additions).
+    pub minor: u32,
+}
+
+impl ProtocolVersion {
+    /// Create a protocol version.
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+
+    /// Check whether two versions can talk to each other.
+    pub fn is_compatible_with(&self, other: &ProtocolVersion) -> bool {
+        self.major == other.major
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Check the guest's protocol version against the host's.
+///
+/// Returns the guest version on success. A minor version mismatch is
+/// logged but allowed.
+fn negotiate(host: ProtocolVersion, guest: ProtocolVersion) -> Result<ProtocolVersion, CoreError> {
+    if !host.is_compatible_with(&guest) {
+        tracing::error!(host = %host, guest = %guest, "Incompatible agent protocol version");
+        return Err(CoreError::IncompatibleProtocol { host, guest });
+    }
+    if host.minor != guest.minor {
+        tracing::warn!(
+            host = %host,
+            guest = %guest,
+            "Agent protocol minor version differs, newer features may be unavailable"
+        );
+    }
+    Ok(guest)
+}
 
 /// File entry from directory listing.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -293,6 +1623,188 @@ pub struct FileEntry {
     pub size: u64,
 }
 
+/// Kind of file reported by [`AgentClient::stat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileType {
+    /// Regular file.
+    File,
+    /// Directory.
+    Dir,
+    /// Symbolic link (not followed).
+    Symlink,
+    /// Anything else: device, socket or FIFO.
+    Other,
+}
+
+/// Metadata of a guest path, from [`AgentClient::stat`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStat {
+    /// What the path is.
+    pub file_type: FileType,
+    /// Size in bytes.
+    pub size: u64,
+    /// Permission bits, e.g. `0o644`.
+    pub mode: u32,
+    /// Last modification time.
+    #[serde(rename = "modified_ms", with = "chrono::serde::ts_milliseconds")]
+    pub modified: DateTime<Utc>,
+}
+
+/// Sort key for directory listings.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    /// File name.
+    #[default]
+    Name,
+    /// File size (0 for directories).
+    Size,
+    /// Last modification time.
+    Modified,
+}
+
+/// Sorting, filtering and depth for `list_dir`.
+///
+/// The default lists every entry of the directory itself sorted by name,
+/// ascending.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ListDirOptions {
+    /// Key to sort entries by; ties are broken by name.
+    pub sort_by: SortBy,
+    /// Reverse the sort order.
+    pub descending: bool,
+    /// Only return entries whose file name matches this glob (`*` and `?`).
+    pub pattern: Option<String>,
+    /// Also list subdirectories. Their entries are named by path relative
+    /// to the listed directory, e.g. `src/main.rs`. Symlinks aren't
+    /// followed, and the agent fails a listing of more than 10,000 entries.
+    /// Needs protocol 1.24.
+    pub recursive: bool,
+    /// Levels to list when `recursive` is set; `None` is unlimited and 1
+    /// lists only the directory's own entries.
+    pub max_depth: Option<usize>,
+}
+
+/// Outcome of a connectivity check from inside the guest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectivityResult {
+    /// Whether a TCP connection was established.
+    pub reachable: bool,
+    /// Time taken to connect, if reachable.
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+    /// Why the host is unreachable, if it is.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl ConnectivityResult {
+    /// Error reported when the sandbox isn't allowed network access.
+    pub const BLOCKED_BY_POLICY: &'static str = "blocked by policy";
+
+    /// Result for a sandbox without the network capability.
+    pub fn blocked_by_policy() -> Self {
+        Self {
+            reachable: false,
+            latency_ms: None,
+            error: Some(Self::BLOCKED_BY_POLICY.to_string()),
+        }
+    }
+}
+
+/// Progress of a resumable upload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadStatus {
+    /// ID identifying the upload in later calls.
+    pub upload_id: String,
+    /// Destination path in the guest.
+    pub path: String,
+    /// Declared size of the complete file.
+    pub total_size: u64,
+    /// Offset the next chunk must start at (bytes received so far).
+    pub next_offset: u64,
+}
+
+impl UploadStatus {
+    /// Whether every byte has been received.
+    pub fn is_complete(&self) -> bool {
+        self.next_offset >= self.total_size
+    }
+}
+
+/// A finished, checksum-verified upload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadFinish {
+    /// Path the file was written to.
+    pub path: String,
+    /// File size in bytes.
+    pub size: u64,
+    /// Verified SHA-256, lowercase hex.
+    pub sha256: String,
+}
+
+/// A file read as raw bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDownload {
+    /// File contents, base64-encoded.
+    pub content_base64: String,
+    /// File size in bytes.
+    pub size: u64,
+    /// SHA-256 of the contents, lowercase hex.
+    pub sha256: String,
+}
+
+/// Part of a file read with [`AgentClient::read_file_chunk`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChunk {
+    /// The bytes read.
+    pub data: Vec<u8>,
+    /// Size of the whole file in bytes.
+    pub size: u64,
+    /// Whether the chunk reaches the end of the file.
+    pub eof: bool,
+}
+
+/// Guest distribution identifiers, parsed from `/etc/os-release`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OsInfo {
+    /// Distribution identifier (e.g. `debian`, `alpine`).
+    pub id: String,
+    /// Distribution version (e.g. `12`), if declared.
+    #[serde(default)]
+    pub version_id: Option<String>,
+    /// Human-readable name (e.g. `Debian GNU/Linux 12 (bookworm)`).
+    pub pretty_name: String,
+}
+
+/// The methods an agent serves, from `describe`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentDescription {
+    /// Protocol version the agent speaks.
+    pub protocol_version: ProtocolVersion,
+    /// Every method the agent dispatches.
+    pub methods: Vec<MethodSchema>,
+}
+
+impl AgentDescription {
+    /// Look up a method by name.
+    pub fn method(&self, name: &str) -> Option<&MethodSchema> {
+        self.methods.iter().find(|m| m.name == name)
+    }
+}
+
+/// One agent method with the JSON Schemas of its params and result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MethodSchema {
+    /// Method name, as sent in a request.
+    pub name: String,
+    /// JSON Schema of the params object.
+    pub params: serde_json::Value,
+    /// JSON Schema of the result object.
+    pub result: serde_json::Value,
+}
+
 // Internal response types to match bouvet-agent's JSON structure
 
 #[derive(Debug, Deserialize)]
@@ -301,6 +1813,14 @@ struct PingResponse {
     pong: bool,
 }
 
+#[derive(Debug, Deserialize)]
+struct InfoResponse {
+    protocol_version: ProtocolVersion,
+    agent_version: String,
+    #[serde(default)]
+    compression: Vec<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct ReadFileResponse {
     content: String,
@@ -312,6 +1832,87 @@ struct WriteFileResponse {
     success: bool,
 }
 
+#[derive(Debug, Deserialize)]
+struct WriteFileChunkResponse {
+    size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReadFileChunkResponse {
+    data: String,
+    size: u64,
+    eof: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClearTmpResponse {
+    removed: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct HostnameResponse {
+    hostname: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TailResponse {
+    lines: Vec<String>,
+    #[serde(default)]
+    tail_id: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TailNotification {
+    params: TailNotificationParams,
+}
+
+/// Whether `e` means the agent connection is gone, rather than that the
+/// request failed.
+fn is_disconnect(e: &CoreError) -> bool {
+    use std::io::ErrorKind;
+    matches!(
+        e,
+        CoreError::Io(e) if matches!(
+            e.kind(),
+            ErrorKind::BrokenPipe
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::UnexpectedEof
+        )
+    )
+}
+
+/// Wait for the next signal for a streamed command, or forever if it
+/// takes none.
+async fn next_signal(signals: &mut Option<mpsc::Receiver<Signal>>) -> Option<Signal> {
+    match signals {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OutputNotification {
+    method: String,
+    params: OutputNotificationParams,
+}
+
+#[derive(Debug, Deserialize)]
+struct OutputNotificationParams {
+    exec_id: u64,
+    #[serde(flatten)]
+    chunk: OutputChunk,
+}
+
+#[derive(Debug, Deserialize)]
+struct TailNotificationParams {
+    tail_id: u64,
+    #[serde(default)]
+    lines: Vec<String>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct ListDirResponse {
     entries: Vec<FileEntry>,
@@ -321,23 +1922,655 @@ struct ListDirResponse {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_connectivity_result_from_agent() {
+        let result: ConnectivityResult =
+            serde_json::from_str(r#"{"reachable":true,"latency_ms":12,"error":null}"#).unwrap();
+        assert!(result.reachable);
+        assert_eq!(result.latency_ms, Some(12));
+        assert_eq!(result.error, None);
+
+        let result: ConnectivityResult =
+            serde_json::from_str(r#"{"reachable":false,"error":"connection refused"}"#).unwrap();
+        assert!(!result.reachable);
+        assert_eq!(result.latency_ms, None);
+    }
+
+    #[test]
+    fn test_connectivity_result_blocked_by_policy() {
+        let result = ConnectivityResult::blocked_by_policy();
+        assert!(!result.reachable);
+        assert_eq!(result.latency_ms, None);
+        assert_eq!(result.error.as_deref(), Some("blocked by policy"));
+    }
+
+    #[test]
+    fn test_exec_options_rpc_timeout() {
+        assert_eq!(ExecOptions::default().rpc_timeout(), RPC_TIMEOUT);
+        let options = ExecOptions {
+            timeout_secs: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(options.rpc_timeout(), RPC_TIMEOUT);
+        let options = ExecOptions {
+            timeout_secs: Some(120),
+            ..Default::default()
+        };
+        assert_eq!(options.rpc_timeout(), Duration::from_secs(125));
+    }
+
+    #[test]
+    fn test_exec_options_debug_hides_env_values() {
+        let options = ExecOptions {
+            env: HashMap::from([("API_TOKEN".to_string(), "s3cret".to_string())]),
+            ..Default::default()
+        };
+        let options = ExecOptions {
+            stdin: Some("password=hunter2".to_string()),
+            ..options
+        };
+        let debug = format!("{options:?}");
+        assert!(debug.contains("API_TOKEN"));
+        assert!(!debug.contains("s3cret"));
+        assert!(!debug.contains("hunter2"));
+    }
+
     #[test]
     fn test_exec_result_success() {
         let result = ExecResult {
             exit_code: 0,
             stdout: "hello".to_string(),
             stderr: String::new(),
+            output_limit_exceeded: false,
+            usage: None,
         };
         assert!(result.success());
     }
 
+    #[test]
+    fn test_exec_result_usage_is_optional() {
+        let result: ExecResult =
+            serde_json::from_str(r#"{"exit_code":0,"stdout":"","stderr":""}"#).unwrap();
+        assert_eq!(result.usage, None);
+
+        let result: ExecResult = serde_json::from_str(
+            r#"{"exit_code":0,"stdout":"","stderr":"","usage":{"max_rss_kib":2048,"user_time_ms":120,"system_time_ms":8}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            result.usage,
+            Some(ResourceUsage {
+                max_rss_kib: 2048,
+                user_time_ms: 120,
+                system_time_ms: 8,
+            })
+        );
+    }
+
+    #[test]
+    fn test_file_stat_from_agent() {
+        let stat: FileStat = serde_json::from_str(
+            r#"{"file_type":"symlink","size":12,"mode":493,"modified_ms":1700000000123}"#,
+        )
+        .unwrap();
+        assert_eq!(stat.file_type, FileType::Symlink);
+        assert_eq!(stat.mode, 0o755);
+        assert_eq!(
+            stat.modified,
+            DateTime::from_timestamp_millis(1_700_000_000_123).unwrap()
+        );
+    }
+
     #[test]
     fn test_exec_result_failure() {
         let result = ExecResult {
             exit_code: 1,
             stdout: String::new(),
             stderr: "error".to_string(),
+            output_limit_exceeded: false,
+            usage: None,
         };
         assert!(!result.success());
     }
+
+    #[test]
+    fn test_exec_result_expect_exit_matching() {
+        let result = ExecResult {
+            exit_code: 3,
+            stdout: "out".to_string(),
+            stderr: String::new(),
+            output_limit_exceeded: false,
+            usage: None,
+        };
+        let result = result.expect_exit(3).unwrap();
+        assert_eq!(result.stdout, "out");
+    }
+
+    #[test]
+    fn test_exec_result_expect_exit_mismatch() {
+        let result = ExecResult {
+            exit_code: 1,
+            stdout: String::new(),
+            stderr: "assertion failed".to_string(),
+            output_limit_exceeded: false,
+            usage: None,
+        };
+        match result.expect_exit(0) {
+            Err(CoreError::UnexpectedExit {
+                expected,
+                actual,
+                stderr,
+            }) => {
+                assert_eq!((expected, actual), (0, 1));
+                assert_eq!(stderr, "assertion failed");
+            }
+            other => panic!("expected UnexpectedExit, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_negotiate_matching_version() {
+        let guest = negotiate(PROTOCOL_VERSION, PROTOCOL_VERSION).unwrap();
+        assert_eq!(guest, PROTOCOL_VERSION);
+    }
+
+    #[test]
+    fn test_negotiate_minor_mismatch_allowed() {
+        let host = ProtocolVersion::new(1, 2);
+        let older = ProtocolVersion::new(1, 0);
+        let newer = ProtocolVersion::new(1, 5);
+        assert_eq!(negotiate(host, older).unwrap(), older);
+        assert_eq!(negotiate(host, newer).unwrap(), newer);
+    }
+
+    #[test]
+    fn test_negotiate_major_mismatch_rejected() {
+        let host = ProtocolVersion::new(1, 0);
+        let guest = ProtocolVersion::new(2, 0);
+        match negotiate(host, guest) {
+            Err(CoreError::IncompatibleProtocol { host: h, guest: g }) => {
+                assert_eq!(h, host);
+                assert_eq!(g, guest);
+            }
+            other => panic!("expected IncompatibleProtocol, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_protocol_version_display() {
+        assert_eq!(ProtocolVersion::new(1, 3).to_string(), "1.3");
+    }
+
+    /// Serve a fake agent that advertises zstd and answers `read_file`
+    /// with a compressed response once the client has opted in.
+    async fn fake_compressing_agent(listener: tokio::net::UnixListener, content: String) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (read_half, mut writer) = tokio::io::split(stream);
+        let mut reader = BufReader::new(read_half);
+        let mut compressed = false;
+        let mut line = String::new();
+        while reader.read_line(&mut line).await.unwrap() > 0 {
+            if line.starts_with("CONNECT ") {
+                writer.write_all(b"OK 1\n").await.unwrap();
+                line.clear();
+                continue;
+            }
+            compressed |= line.starts_with(bouvet_common::frame::COMPRESSED_PREFIX);
+            let request: serde_json::Value =
+                serde_json::from_str(&frame::decode(&line).unwrap()).unwrap();
+            let result = match request["method"].as_str().unwrap() {
+                "info" => serde_json::json!({
+                    "protocol_version": {"major": 1, "minor": 0},
+                    "agent_version": "test",
+                    "compression": ["zstd"],
+                }),
+                "ping" => serde_json::json!({"pong": true}),
+                "read_file" => serde_json::json!({"content": content}),
+                other => panic!("unexpected method {other}"),
+            };
+            let response =
+                serde_json::json!({"jsonrpc": "2.0", "id": request["id"], "result": result})
+                    .to_string();
+            let out = if compressed {
+                frame::encode(response)
+            } else {
+                response
+            };
+            if request["method"] == "read_file" {
+                assert!(out.starts_with(bouvet_common::frame::COMPRESSED_PREFIX));
+            }
+            writer.write_all(out.as_bytes()).await.unwrap();
+            writer.write_all(b"\n").await.unwrap();
+            line.clear();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compressed_read_file_is_transparent() {
+        let dir = std::env::temp_dir().join(format!("bouvet-zstd-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("v.sock");
+        let _ = std::fs::remove_file(&socket);
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+
+        let content = "0123456789abcdef\n".repeat(16 * 1024);
+        let agent = tokio::spawn(fake_compressing_agent(listener, content.clone()));
+
+        let mut client = AgentClient::connect(&socket).await.unwrap();
+        assert!(client.enable_compression().await.unwrap());
+        assert_eq!(client.read_file("/big.txt").await.unwrap(), content);
+
+        drop(client);
+        agent.await.unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Serve a fake agent whose `tail` follow emits two lines, then keeps
+    /// notifying until cancelled.
+    async fn fake_tailing_agent(listener: tokio::net::UnixListener) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (read_half, mut writer) = tokio::io::split(stream);
+        let mut reader = BufReader::new(read_half);
+        let mut line = String::new();
+        while reader.read_line(&mut line).await.unwrap() > 0 {
+            if line.starts_with("CONNECT ") {
+                writer.write_all(b"OK 1\n").await.unwrap();
+                line.clear();
+                continue;
+            }
+            let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+            let id = request["id"].clone();
+            let mut out = Vec::new();
+            let result = match request["method"].as_str().unwrap() {
+                "info" => serde_json::json!({
+                    "protocol_version": {"major": 1, "minor": 27},
+                    "agent_version": "test",
+                }),
+                "ping" => serde_json::json!({"pong": true}),
+                "tail" => {
+                    assert_eq!(request["params"]["follow"], true);
+                    for lines in [serde_json::json!(["b"]), serde_json::json!(["c", "d"])] {
+                        out.push(serde_json::json!({"jsonrpc": "2.0", "method": "tail",
+                            "params": {"tail_id": id, "lines": lines}}));
+                    }
+                    serde_json::json!({"tail_id": id, "lines": ["a"]})
+                }
+                "tail_cancel" => {
+                    // A notification racing the cancel must be skipped by the client
+                    writer
+                        .write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"tail\",\"params\":{\"tail_id\":2,\"lines\":[\"late\"]}}\n")
+                        .await
+                        .unwrap();
+                    serde_json::json!({"success": true})
+                }
+                other => panic!("unexpected method {other}"),
+            };
+            out.insert(
+                0,
+                serde_json::json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            );
+            for message in out {
+                writer
+                    .write_all(format!("{message}\n").as_bytes())
+                    .await
+                    .unwrap();
+            }
+            line.clear();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_follow_tail_streams_until_cancelled() {
+        let dir = std::env::temp_dir().join(format!("bouvet-tail-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("v.sock");
+        let _ = std::fs::remove_file(&socket);
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        let agent = tokio::spawn(fake_tailing_agent(listener));
+
+        let mut client = AgentClient::connect(&socket).await.unwrap();
+        let (tail_id, initial) = client.start_tail("/var/log/app.log", 1).await.unwrap();
+        assert_eq!(tail_id, 2);
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel::<()>();
+        let follow = tokio::spawn(async move {
+            let cancel = async {
+                let _ = cancel_rx.await;
+            };
+            client.follow_tail(tail_id, initial, tx, cancel).await?;
+            // The connection is usable again once following stops
+            client.ping().await?;
+            Ok::<_, CoreError>(())
+        });
+
+        for expected in ["a", "b", "c", "d"] {
+            assert_eq!(rx.recv().await.unwrap(), expected);
+        }
+        cancel_tx.send(()).unwrap();
+        follow.await.unwrap().unwrap();
+        assert!(rx.recv().await.is_none());
+
+        agent.await.unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Serve a fake agent that drops its first connection on the first
+    /// `drop_on` request, without answering, then serves the next one
+    /// normally.
+    async fn fake_dropping_agent(listener: tokio::net::UnixListener, drop_on: &'static str) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (read_half, mut writer) = tokio::io::split(stream);
+        let mut reader = BufReader::new(read_half);
+        let mut line = String::new();
+        while reader.read_line(&mut line).await.unwrap() > 0 {
+            if line.starts_with("CONNECT ") {
+                writer.write_all(b"OK 1\n").await.unwrap();
+            } else {
+                let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+                if request["method"] == drop_on {
+                    break;
+                }
+                let result = serde_json::json!({
+                    "protocol_version": {"major": 1, "minor": 27},
+                    "agent_version": "test",
+                });
+                let response =
+                    serde_json::json!({"jsonrpc": "2.0", "id": request["id"], "result": result});
+                writer
+                    .write_all(format!("{response}\n").as_bytes())
+                    .await
+                    .unwrap();
+            }
+            line.clear();
+        }
+        drop((reader, writer));
+
+        let (stream, _) = listener.accept().await.unwrap();
+        serve_fake_agent(stream, true).await;
+    }
+
+    #[tokio::test]
+    async fn test_call_reconnects_after_connection_drops() {
+        let dir = std::env::temp_dir().join(format!("bouvet-reconnect-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("v.sock");
+        let _ = std::fs::remove_file(&socket);
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        let agent = tokio::spawn(fake_dropping_agent(listener, "ping"));
+
+        let mut client = AgentClient::connect(&socket).await.unwrap();
+        client.ping().await.unwrap();
+        assert!(client.is_reusable());
+        client.ping().await.unwrap();
+
+        drop(client);
+        agent.await.unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_exec_not_resent_after_connection_drops() {
+        let dir = std::env::temp_dir().join(format!("bouvet-no-resend-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("v.sock");
+        let _ = std::fs::remove_file(&socket);
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        // The second connection's agent panics on any exec, so a resend
+        // fails the agent task below
+        let agent = tokio::spawn(fake_dropping_agent(listener, "exec"));
+
+        let mut client = AgentClient::connect(&socket).await.unwrap();
+        let err = client.exec("touch /once").await.unwrap_err();
+        assert!(matches!(err, CoreError::Connection(_)), "{err:?}");
+        // The client reconnected and still works
+        client.ping().await.unwrap();
+
+        drop(client);
+        agent.await.unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_call_gives_up_when_agent_is_gone() {
+        let dir = std::env::temp_dir().join(format!("bouvet-gone-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("v.sock");
+        let _ = std::fs::remove_file(&socket);
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        let agent = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            // Answer the handshake and info, then vanish with the listener
+            let (read_half, mut writer) = tokio::io::split(stream);
+            let mut reader = BufReader::new(read_half);
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            writer.write_all(b"OK 1\n").await.unwrap();
+            line.clear();
+            reader.read_line(&mut line).await.unwrap();
+            let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+            let response = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": {"protocol_version": {"major": 1, "minor": 27}, "agent_version": "test"},
+            });
+            writer
+                .write_all(format!("{response}\n").as_bytes())
+                .await
+                .unwrap();
+        });
+
+        let mut client = AgentClient::connect(&socket).await.unwrap();
+        agent.await.unwrap();
+        match client.ping().await {
+            Err(CoreError::Connection(message)) => {
+                assert!(message.contains("3 reconnect attempts failed"), "{message}")
+            }
+            other => panic!("expected a connection error, got {other:?}"),
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Serve a fake agent that only starts listening after `delay`, as a
+    /// booting VM would.
+    async fn fake_slow_agent(socket: std::path::PathBuf, delay: Duration) {
+        tokio::time::sleep(delay).await;
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        serve_fake_agent(stream, true).await;
+    }
+
+    /// Answer `info`, `ping`, `write_file` and `read_file` on `stream`.
+    /// With `proxied`, first accept the `CONNECT` handshake Firecracker's
+    /// vsock proxy expects; without, a handshake is a protocol error.
+    async fn serve_fake_agent(stream: impl AgentTransport, proxied: bool) {
+        let (read_half, mut writer) = tokio::io::split(stream);
+        let mut reader = BufReader::new(read_half);
+        let mut files = HashMap::new();
+        let mut line = String::new();
+        while reader.read_line(&mut line).await.unwrap() > 0 {
+            if line.starts_with("CONNECT ") {
+                assert!(proxied, "handshake sent over a direct stream");
+                assert_eq!(line, format!("CONNECT {GUEST_PORT}\n"));
+                writer.write_all(b"OK 1\n").await.unwrap();
+                line.clear();
+                continue;
+            }
+            let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+            let result = match request["method"].as_str().unwrap() {
+                "info" => serde_json::json!({
+                    "protocol_version": {"major": 1, "minor": 27},
+                    "agent_version": "test",
+                }),
+                "ping" => serde_json::json!({"pong": true}),
+                "exec" => serde_json::json!({
+                    "exit_code": 0,
+                    "stdout": request["params"]["env"]["FOO_TOKEN"],
+                    "stderr": "",
+                }),
+                "write_file" => {
+                    let params = &request["params"];
+                    files.insert(params["path"].clone(), params["content"].clone());
+                    serde_json::json!({"success": true})
+                }
+                "read_file" => serde_json::json!({"content": files[&request["params"]["path"]]}),
+                other => panic!("unexpected method {other}"),
+            };
+            let response =
+                serde_json::json!({"jsonrpc": "2.0", "id": request["id"], "result": result});
+            writer
+                .write_all(format!("{response}\n").as_bytes())
+                .await
+                .unwrap();
+            line.clear();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exec_keeps_env_values_and_stdin_out_of_trace_logs() {
+        struct SharedWriter(Arc<std::sync::Mutex<Vec<u8>>>);
+        impl std::io::Write for SharedWriter {
+            fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(data);
+                Ok(data.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buf = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let writer = Arc::clone(&buf);
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_writer(move || SharedWriter(Arc::clone(&writer)))
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let (client_side, agent_side) = tokio::io::duplex(4096);
+        let agent = tokio::spawn(serve_fake_agent(agent_side, true));
+        let mut client = AgentClient::connect_uds(client_side).await.unwrap();
+        let options = ExecOptions {
+            env: HashMap::from([("FOO_TOKEN".into(), "s3cr3t-value".into())]),
+            stdin: Some("s3cr3t-stdin".into()),
+            ..Default::default()
+        };
+        let result = client
+            .exec_with("printenv FOO_TOKEN", &options)
+            .await
+            .unwrap();
+        assert_eq!(result.stdout, "s3cr3t-value");
+        drop(client);
+        agent.await.unwrap();
+
+        let logs = String::from_utf8(buf.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("RPC request body"), "{logs}");
+        assert!(logs.contains("RPC response body"), "{logs}");
+        assert!(!logs.contains("s3cr3t-value"), "{logs}");
+        assert!(!logs.contains("s3cr3t-stdin"), "{logs}");
+    }
+
+    #[tokio::test]
+    async fn test_connect_uds_sends_handshake() {
+        let (client_side, agent_side) = tokio::io::duplex(4096);
+        let agent = tokio::spawn(serve_fake_agent(agent_side, true));
+
+        let mut client = AgentClient::connect_uds(client_side).await.unwrap();
+        assert_eq!(client.protocol_version(), PROTOCOL_VERSION);
+        client.ping().await.unwrap();
+
+        drop(client);
+        agent.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connect_vsock_skips_handshake() {
+        let (client_side, agent_side) = tokio::io::duplex(4096);
+        let agent = tokio::spawn(serve_fake_agent(agent_side, false));
+
+        let mut client = AgentClient::connect_vsock(client_side).await.unwrap();
+        assert_eq!(client.protocol_version(), PROTOCOL_VERSION);
+        client.ping().await.unwrap();
+
+        drop(client);
+        agent.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_custom_buffer_size_round_trips_large_payload() {
+        let (client_side, agent_side) = tokio::io::duplex(4096);
+        let agent = tokio::spawn(serve_fake_agent(agent_side, false));
+
+        let config = ClientConfig { buffer_size: 1024 };
+        let mut client = AgentClient::connect_vsock_with(client_side, &config)
+            .await
+            .unwrap();
+
+        // Each message is thousands of times the buffer size
+        let content = "0123456789abcdef\n".repeat(256 * 1024);
+        client.write_file("/big.txt", &content).await.unwrap();
+        assert_eq!(client.read_file("/big.txt").await.unwrap(), content);
+
+        drop(client);
+        agent.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connect_uds_rejected_handshake() {
+        let (client_side, agent_side) = tokio::io::duplex(4096);
+        let agent = tokio::spawn(async move {
+            let (read_half, mut writer) = tokio::io::split(agent_side);
+            let mut line = String::new();
+            BufReader::new(read_half)
+                .read_line(&mut line)
+                .await
+                .unwrap();
+            writer.write_all(b"FAILURE\n").await.unwrap();
+        });
+
+        match AgentClient::connect_uds(client_side).await {
+            Err(CoreError::Connection(message)) => assert!(message.contains("FAILURE")),
+            Err(e) => panic!("expected a connection error, got {e}"),
+            Ok(_) => panic!("expected a connection error"),
+        }
+        agent.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_agent_retries_until_ready() {
+        let dir = std::env::temp_dir().join(format!("bouvet-wait-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("v.sock");
+        let _ = std::fs::remove_file(&socket);
+        let agent = tokio::spawn(fake_slow_agent(socket.clone(), Duration::from_millis(300)));
+
+        let ready = wait_for_agent_timed(&socket, Duration::from_secs(5), &ClientConfig::default())
+            .await
+            .unwrap();
+        assert!(
+            ready.connect_ms >= 300,
+            "connected after {}ms",
+            ready.connect_ms
+        );
+        assert_eq!(ready.client.protocol_version(), PROTOCOL_VERSION);
+
+        drop(ready);
+        agent.await.unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_agent_times_out() {
+        let socket =
+            std::env::temp_dir().join(format!("bouvet-wait-none-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+
+        let timeout = Duration::from_millis(250);
+        match wait_for_agent(&socket, timeout).await {
+            Err(CoreError::AgentTimeout(t)) => assert_eq!(t, timeout),
+            Err(e) => panic!("expected timeout, got {e}"),
+            Ok(_) => panic!("expected timeout, got a connection"),
+        }
+    }
 }
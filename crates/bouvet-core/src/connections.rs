@@ -0,0 +1,168 @@
+//! Agent connections of a sandbox.
+//!
+//! The guest agent serves each vsock connection independently, so a
+//! sandbox keeps a small pool of them. Connections are opened lazily, up
+//! to a cap, letting independent operations (following a log while a build
+//! runs, say) proceed in parallel instead of queueing behind one stream.
+//!
+//! Requests on different connections are not ordered relative to each
+//! other: callers that need one operation to see another's effects must
+//! wait for the first to finish, as sequential `await`s do.
+
+use crate::client::{AgentClient, CancelToken, ClientConfig, CONNECT_TIMEOUT};
+use crate::error::CoreError;
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Pool of connections to one guest agent.
+pub(crate) struct AgentConnections {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    vsock_path: PathBuf,
+    /// Enable frame compression on newly opened connections.
+    compression: bool,
+    /// Settings for newly opened connections.
+    client_config: ClientConfig,
+    /// Open connections not currently checked out.
+    idle: Mutex<Vec<AgentClient>>,
+    /// One permit per connection that may be checked out at once.
+    permits: Arc<Semaphore>,
+    /// Number of permits.
+    max: usize,
+    /// Shared by every connection, cut short by [`AgentConnections::cancel`].
+    cancel: CancelToken,
+}
+
+impl AgentConnections {
+    /// Pool up to `max` connections to the agent at `vsock_path`, starting
+    /// with the already open `first`.
+    pub(crate) fn new(
+        mut first: AgentClient,
+        vsock_path: PathBuf,
+        max: usize,
+        compression: bool,
+        client_config: ClientConfig,
+    ) -> Self {
+        let cancel = CancelToken::new();
+        first.set_cancel(cancel.clone());
+        Self {
+            inner: Arc::new(Inner {
+                vsock_path,
+                compression,
+                client_config,
+                idle: Mutex::new(vec![first]),
+                permits: Arc::new(Semaphore::new(max.max(1))),
+                max: max.max(1),
+                cancel,
+            }),
+        }
+    }
+
+    /// Number of connections currently checked out.
+    pub(crate) fn in_use(&self) -> usize {
+        self.inner.max - self.inner.permits.available_permits()
+    }
+
+    /// Cancel every operation on the pool's connections, now and later.
+    ///
+    /// Requests waiting for a response, or for a connection, return
+    /// [`CoreError::Cancelled`]; streamed commands are killed on the way.
+    pub(crate) fn cancel(&self) {
+        self.inner.cancel.cancel();
+    }
+
+    /// Check out a connection, opening a new one if none is idle.
+    ///
+    /// Waits for a connection to be returned when `max` are checked out.
+    pub(crate) async fn get(&self) -> Result<PooledClient, CoreError> {
+        let permit = tokio::select! {
+            permit = Arc::clone(&self.inner.permits).acquire_owned() => {
+                permit.expect("connection semaphore is never closed")
+            }
+            _ = self.inner.cancel.cancelled() => return Err(CoreError::Cancelled),
+        };
+        self.checkout(permit).await
+    }
+
+    /// Check out a connection without waiting for one to be returned.
+    ///
+    /// # Returns
+    ///
+    /// `None` if `max` connections are already checked out.
+    pub(crate) async fn try_get(&self) -> Option<Result<PooledClient, CoreError>> {
+        let permit = Arc::clone(&self.inner.permits).try_acquire_owned().ok()?;
+        Some(self.checkout(permit).await)
+    }
+
+    async fn checkout(&self, permit: OwnedSemaphorePermit) -> Result<PooledClient, CoreError> {
+        let idle = self.inner.idle.lock().unwrap().pop();
+        let client = match idle {
+            Some(client) => client,
+            None => self.connect().await?,
+        };
+        Ok(PooledClient {
+            client: Some(client),
+            pool: Arc::clone(&self.inner),
+            _permit: permit,
+        })
+    }
+
+    async fn connect(&self) -> Result<AgentClient, CoreError> {
+        tracing::debug!(path = %self.inner.vsock_path.display(), "Opening another agent connection");
+        let mut client = AgentClient::connect_with(
+            &self.inner.vsock_path,
+            CONNECT_TIMEOUT,
+            &self.inner.client_config,
+        )
+        .await?;
+        client.set_cancel(self.inner.cancel.clone());
+        if self.inner.compression {
+            client.enable_compression().await?;
+        }
+        Ok(client)
+    }
+}
+
+/// A connection checked out of [`AgentConnections`].
+///
+/// Returned to the pool when dropped, unless a request on it was cut short
+/// and left the stream in an unknown state.
+pub(crate) struct PooledClient {
+    client: Option<AgentClient>,
+    pool: Arc<Inner>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl PooledClient {
+    /// Close the connection instead of returning it to the pool.
+    pub(crate) fn discard(mut self) {
+        self.client = None;
+    }
+}
+
+impl Deref for PooledClient {
+    type Target = AgentClient;
+
+    fn deref(&self) -> &AgentClient {
+        self.client.as_ref().expect("client present until drop")
+    }
+}
+
+impl DerefMut for PooledClient {
+    fn deref_mut(&mut self) -> &mut AgentClient {
+        self.client.as_mut().expect("client present until drop")
+    }
+}
+
+impl Drop for PooledClient {
+    fn drop(&mut self) {
+        // Runs before the permit is released, so a waiter finds it idle
+        if let Some(client) = self.client.take().filter(AgentClient::is_reusable) {
+            self.pool.idle.lock().unwrap().push(client);
+        }
+    }
+}
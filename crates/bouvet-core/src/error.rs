@@ -1,6 +1,6 @@
 //! Error types for bouvet-core.
 
-use crate::SandboxId;
+use crate::{Capability, ProtocolVersion, SandboxId, SandboxState};
 use thiserror::Error;
 
 /// Result type alias for bouvet-core operations.
@@ -21,6 +21,23 @@ pub enum CoreError {
     #[error("agent timeout after {0:?}")]
     AgentTimeout(std::time::Duration),
 
+    /// VM started but its agent did not accept connections in time
+    #[error("agent not ready {0:?} after VM boot")]
+    BootTimeout(std::time::Duration),
+
+    /// Booting took longer than the sandbox's `max_boot_ms`; the VM was destroyed
+    #[error("sandbox boot exceeded its {max_boot_ms}ms budget ({elapsed_ms}ms elapsed)")]
+    BootBudgetExceeded {
+        /// The configured budget
+        max_boot_ms: u64,
+        /// Time spent before the boot was aborted
+        elapsed_ms: u64,
+    },
+
+    /// Agent accepted the connection but the handshake or first ping failed
+    #[error("agent connect failed: {0}")]
+    ConnectFailed(String),
+
     /// JSON-RPC error from agent
     #[error("RPC error {code}: {message}")]
     Rpc {
@@ -30,10 +47,64 @@ pub enum CoreError {
         message: String,
     },
 
+    /// Agent speaks an incompatible protocol version
+    #[error("incompatible agent protocol: host speaks {host}, guest speaks {guest}")]
+    IncompatibleProtocol {
+        /// Protocol version of this host
+        host: ProtocolVersion,
+        /// Protocol version reported by the guest agent
+        guest: ProtocolVersion,
+    },
+
     /// Sandbox not found
     #[error("sandbox not found: {0}")]
     NotFound(SandboxId),
 
+    /// No sandbox is registered under the given external key
+    #[error("no sandbox with key: {0}")]
+    KeyNotFound(String),
+
+    /// External key is already bound to another sandbox
+    #[error("sandbox key already in use: {0}")]
+    DuplicateKey(String),
+
+    /// Operation is not permitted by the sandbox's capabilities
+    #[error("capability disabled for this sandbox: {0}")]
+    CapabilityDisabled(Capability),
+
+    /// Command was rejected by the manager's command filter
+    #[error("forbidden: {0}")]
+    Forbidden(String),
+
+    /// Sandbox was created without a scratch drive
+    #[error("sandbox has no scratch drive")]
+    NoScratchDrive,
+
+    /// Export named no directory and the sandbox has no `work_dir`
+    #[error("sandbox has no work_dir")]
+    NoWorkDir,
+
+    /// Archive of an exported directory is over the size limit
+    #[error("archive is {size} bytes, over the {limit}-byte limit")]
+    ArchiveTooLarge {
+        /// Archive size in bytes
+        size: u64,
+        /// Largest size allowed
+        limit: u64,
+    },
+
+    /// Pool was shut down and hands out no more sandboxes
+    #[error("sandbox pool is shutting down")]
+    PoolShuttingDown,
+
+    /// Reservation has no unused slots, or was made by another manager
+    #[error("reservation has no slots left")]
+    ReservationExhausted,
+
+    /// Operation was cut short because its sandbox is being destroyed
+    #[error("operation cancelled")]
+    Cancelled,
+
     /// Invalid sandbox state for operation
     #[error("invalid state: expected {expected}, got {actual}")]
     InvalidState {
@@ -43,6 +114,28 @@ pub enum CoreError {
         actual: String,
     },
 
+    /// Sandbox did not reach the awaited state in time
+    #[error("sandbox did not become {expected} within {timeout:?} (still {actual})")]
+    StateTimeout {
+        /// State that was awaited
+        expected: SandboxState,
+        /// State when the wait gave up
+        actual: SandboxState,
+        /// How long the wait lasted
+        timeout: std::time::Duration,
+    },
+
+    /// Command exited with a different code than expected
+    #[error("command exited with code {actual}, expected {expected}: {stderr}")]
+    UnexpectedExit {
+        /// Exit code the caller expected
+        expected: i32,
+        /// Exit code the command returned
+        actual: i32,
+        /// Standard error of the command
+        stderr: String,
+    },
+
     /// JSON serialization/deserialization error
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
@@ -51,3 +144,36 @@ pub enum CoreError {
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 }
+
+impl CoreError {
+    /// Short, stable name of the error variant (e.g. `boot_timeout`), for
+    /// callers that report errors in machine-readable form.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CoreError::Vm(_) => "vm",
+            CoreError::Connection(_) => "connection",
+            CoreError::AgentTimeout(_) => "agent_timeout",
+            CoreError::BootTimeout(_) => "boot_timeout",
+            CoreError::BootBudgetExceeded { .. } => "boot_budget_exceeded",
+            CoreError::ConnectFailed(_) => "connect_failed",
+            CoreError::Rpc { .. } => "rpc",
+            CoreError::IncompatibleProtocol { .. } => "incompatible_protocol",
+            CoreError::NotFound(_) => "not_found",
+            CoreError::KeyNotFound(_) => "key_not_found",
+            CoreError::DuplicateKey(_) => "duplicate_key",
+            CoreError::CapabilityDisabled(_) => "capability_disabled",
+            CoreError::Forbidden(_) => "forbidden",
+            CoreError::NoScratchDrive => "no_scratch_drive",
+            CoreError::NoWorkDir => "no_work_dir",
+            CoreError::ArchiveTooLarge { .. } => "archive_too_large",
+            CoreError::PoolShuttingDown => "pool_shutting_down",
+            CoreError::ReservationExhausted => "reservation_exhausted",
+            CoreError::Cancelled => "cancelled",
+            CoreError::InvalidState { .. } => "invalid_state",
+            CoreError::StateTimeout { .. } => "state_timeout",
+            CoreError::UnexpectedExit { .. } => "unexpected_exit",
+            CoreError::Json(_) => "json",
+            CoreError::Io(_) => "io",
+        }
+    }
+}
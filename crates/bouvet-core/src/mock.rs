@@ -0,0 +1,618 @@
+//! In-process VM backend for tests.
+//!
+//! [`MockBackend`] creates no VM. Instead it serves a minimal guest agent on
+//! the sandbox's vsock socket, so [`Sandbox`](crate::Sandbox) creation goes
+//! through the real handshake, version negotiation and hostname setup.
+//! Enabled by the `mock` feature.
+
+use crate::backend::{VmBackend, VmInstance};
+use crate::client::{ProtocolVersion, PROTOCOL_VERSION};
+use crate::sandbox::EXPORT_SCRIPT;
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use bouvet_vm::{MachineConfig, VmError};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// A [`VmBackend`] whose "VMs" are agent stubs running in this process.
+///
+/// The stub agent answers `info`, `ping`, `set_hostname`, `hostname` and
+/// `resize_fs`, and VMs accept any `resize_drive` while running;
+/// `exec` echoes the command on stdout with exit code 0, after waiting
+/// first if the command is `sleep <seconds>`, and reporting a timeout if
+/// that exceeds `timeout_secs`; `pwd` prints the requested `cwd`, `/` by
+/// default, `printenv <name>` the requested `env` variable, and `cat` the
+/// `stdin` given. An argv `exec` echoes its arguments, except that `rm -f`
+/// removes a file and the export script archives the in-memory files
+/// under its directory as a real `.tar.gz`, reporting a zero digest.
+/// `exec_code` echoes the code. `exec_stream` and
+/// `exec_code_stream` run the same way, sending their output as `output`
+/// notifications, one per line, ahead of the result, pausing after any
+/// line of the form `sleep <seconds>`; a streamed `sleep` command
+/// ends early on a `kill` for it, reporting the signal on stderr. `write_file`,
+/// `read_file`, `write_file_chunk` and `read_file_chunk` work on an
+/// in-memory filesystem, where writing below an existing file
+/// fails as it would on disk. Other methods return a method-not-found
+/// error. Like the real agent, it serves each connection independently.
+#[derive(Default)]
+pub struct MockBackend {
+    /// Number of upcoming creates that fail.
+    failures: AtomicUsize,
+    /// How long new VMs take before their agent listens, in milliseconds.
+    boot_delay_ms: AtomicU64,
+    /// Protocol version new VMs' agents report, if not [`PROTOCOL_VERSION`].
+    protocol_version: std::sync::Mutex<Option<ProtocolVersion>>,
+    created: Arc<AtomicU64>,
+    destroyed: Arc<AtomicU64>,
+    /// Guest state of every VM created, by ID.
+    guests: std::sync::Mutex<HashMap<Uuid, Arc<Guest>>>,
+}
+
+impl std::fmt::Debug for MockBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockBackend")
+            .field("created", &self.created())
+            .field("destroyed", &self.destroyed())
+            .finish_non_exhaustive()
+    }
+}
+
+impl MockBackend {
+    /// Create a backend whose VMs always boot.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make the next `count` creates fail with [`VmError::Create`].
+    pub fn fail_next(&self, count: usize) {
+        self.failures.store(count, Ordering::Relaxed);
+    }
+
+    /// Make VMs created from now on start their agent after `delay`, as a
+    /// slow boot would.
+    pub fn delay_boot(&self, delay: Duration) {
+        self.boot_delay_ms
+            .store(delay.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Make VMs created from now on run an agent reporting `version`.
+    pub fn agent_protocol(&self, version: ProtocolVersion) {
+        *self.protocol_version.lock().unwrap() = Some(version);
+    }
+
+    /// Make the agent of VM `id` stop answering, as if it had crashed:
+    /// open connections are dropped on their next request and new ones
+    /// are refused.
+    ///
+    /// # Returns
+    ///
+    /// `false` if this backend never created a VM with that ID.
+    pub fn crash_agent(&self, id: Uuid) -> bool {
+        match self.guests.lock().unwrap().get(&id) {
+            Some(guest) => {
+                guest.crashed.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Number of VMs created.
+    pub fn created(&self) -> u64 {
+        self.created.load(Ordering::Relaxed)
+    }
+
+    /// Number of VMs destroyed.
+    pub fn destroyed(&self) -> u64 {
+        self.destroyed.load(Ordering::Relaxed)
+    }
+}
+
+#[async_trait]
+impl VmBackend for MockBackend {
+    async fn create(
+        &self,
+        id: Uuid,
+        config: MachineConfig,
+    ) -> bouvet_vm::Result<Box<dyn VmInstance>> {
+        let fail = self
+            .failures
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            .is_ok();
+        if fail {
+            return Err(VmError::Create(format!("mock VM {id} failed to boot")));
+        }
+
+        let uds_path = config
+            .vsock
+            .as_ref()
+            .map(|v| v.uds_path.clone())
+            .ok_or_else(|| VmError::Config("mock backend needs vsock".into()))?;
+        let guest = Arc::new(Guest {
+            hostname: std::sync::Mutex::new(String::from("localhost")),
+            files: std::sync::Mutex::default(),
+            crashed: AtomicBool::new(false),
+            protocol_version: self
+                .protocol_version
+                .lock()
+                .unwrap()
+                .unwrap_or(PROTOCOL_VERSION),
+        });
+        self.guests.lock().unwrap().insert(id, Arc::clone(&guest));
+        let delay = Duration::from_millis(self.boot_delay_ms.load(Ordering::Relaxed));
+        let agent = if delay.is_zero() {
+            let listener = UnixListener::bind(&uds_path)?;
+            tokio::spawn(serve_agent(listener, guest))
+        } else {
+            let uds_path = uds_path.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                if let Ok(listener) = UnixListener::bind(&uds_path) {
+                    serve_agent(listener, guest).await;
+                }
+            })
+        };
+        self.created.fetch_add(1, Ordering::Relaxed);
+        tracing::debug!(%id, uds_path = %uds_path.display(), "Mock VM created");
+
+        Ok(Box::new(MockVm {
+            uds_path,
+            agent,
+            paused: false,
+            destroyed: Arc::clone(&self.destroyed),
+        }))
+    }
+}
+
+/// A VM created by [`MockBackend`].
+struct MockVm {
+    uds_path: PathBuf,
+    agent: JoinHandle<()>,
+    paused: bool,
+    destroyed: Arc<AtomicU64>,
+}
+
+impl MockVm {
+    fn require_paused(&self, paused: bool) -> bouvet_vm::Result<()> {
+        if self.paused == paused {
+            return Ok(());
+        }
+        let (expected, actual) = if paused {
+            ("paused", "running")
+        } else {
+            ("running", "paused")
+        };
+        Err(VmError::InvalidState {
+            expected: expected.into(),
+            actual: actual.into(),
+        })
+    }
+}
+
+#[async_trait]
+impl VmInstance for MockVm {
+    fn pid(&self) -> Option<u32> {
+        None
+    }
+
+    fn vsock_uds_path(&self) -> Option<&Path> {
+        Some(&self.uds_path)
+    }
+
+    async fn pause(&mut self) -> bouvet_vm::Result<()> {
+        self.require_paused(false)?;
+        self.paused = true;
+        Ok(())
+    }
+
+    async fn resume(&mut self) -> bouvet_vm::Result<()> {
+        self.require_paused(true)?;
+        self.paused = false;
+        Ok(())
+    }
+
+    async fn snapshot(&self, snapshot_path: &Path, mem_file_path: &Path) -> bouvet_vm::Result<()> {
+        self.require_paused(true)?;
+        tokio::fs::write(snapshot_path, b"mock vmstate").await?;
+        tokio::fs::write(mem_file_path, b"mock memory").await?;
+        Ok(())
+    }
+
+    async fn resize_drive(&self, _drive_id: &str, _new_size_mib: u64) -> bouvet_vm::Result<()> {
+        self.require_paused(false)
+    }
+
+    async fn destroy(self: Box<Self>) -> bouvet_vm::Result<()> {
+        self.agent.abort();
+        let _ = tokio::fs::remove_file(&self.uds_path).await;
+        self.destroyed.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Guest state shared by all connections to one stub agent.
+struct Guest {
+    hostname: std::sync::Mutex<String>,
+    /// File contents by path.
+    files: std::sync::Mutex<HashMap<String, Vec<u8>>>,
+    /// Set by [`MockBackend::crash_agent`].
+    crashed: AtomicBool,
+    /// Reported by `info`.
+    protocol_version: ProtocolVersion,
+}
+
+/// Accept agent connections until the task is aborted.
+async fn serve_agent(listener: UnixListener, guest: Arc<Guest>) {
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(serve_connection(stream, Arc::clone(&guest)));
+    }
+}
+
+/// Answer the vsock handshake, then JSON-RPC requests, one per line.
+async fn serve_connection(stream: UnixStream, guest: Arc<Guest>) {
+    let (read_half, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(read_half);
+    let mut line = String::new();
+    while reader.read_line(&mut line).await.unwrap_or(0) > 0 {
+        if guest.crashed.load(Ordering::Relaxed) {
+            return;
+        }
+        let reply = if line.starts_with("CONNECT ") {
+            "OK 1".to_string()
+        } else {
+            let request: serde_json::Value = serde_json::from_str(&line).unwrap_or_default();
+            let streaming =
+                request["method"] == "exec_stream" || request["method"] == "exec_code_stream";
+            let signal = match sleep_duration(&line) {
+                Some(delay) if streaming => {
+                    sleep_or_kill(&mut reader, &mut writer, delay, &request["id"]).await
+                }
+                Some(delay) => {
+                    tokio::time::sleep(delay).await;
+                    None
+                }
+                None => None,
+            };
+            match signal {
+                // Like the agent's timeout note, the cancellation note
+                // stays on the result's stderr rather than being streamed
+                Some(signal) => {
+                    serde_json::json!({"jsonrpc": "2.0", "id": request["id"], "result": {
+                        "exit_code": -1,
+                        "stdout": "",
+                        "stderr": format!("cancelled with SIG{}", signal.to_uppercase()),
+                    }})
+                    .to_string()
+                }
+                None if streaming => {
+                    let mut lines = stream_output(handle_request(&line, &guest));
+                    let reply = lines.pop().unwrap_or_default();
+                    // A whole-command `sleep` has already been waited out
+                    let paced = sleep_duration(&line).is_none();
+                    for note in lines {
+                        if writer
+                            .write_all(format!("{note}\n").as_bytes())
+                            .await
+                            .is_err()
+                        {
+                            return;
+                        }
+                        if let Some(delay) = pause_after(&note).filter(|_| paced) {
+                            tokio::time::sleep(delay).await;
+                        }
+                    }
+                    reply
+                }
+                None => handle_request(&line, &guest).to_string(),
+            }
+        };
+        line.clear();
+        if writer
+            .write_all(format!("{reply}\n").as_bytes())
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// Turn the reply to a streaming exec into `output` notifications of its
+/// output, one per line, followed by the reply without it.
+fn stream_output(mut reply: serde_json::Value) -> Vec<String> {
+    let id = reply["id"].clone();
+    let mut lines = Vec::new();
+    if let Some(result) = reply.get_mut("result") {
+        for stream in ["stdout", "stderr"] {
+            let data = std::mem::take(&mut result[stream]);
+            for data in data.as_str().unwrap_or_default().split_inclusive('\n') {
+                let note = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "method": "output",
+                    "params": {"exec_id": id, "stream": stream, "data": data},
+                });
+                lines.push(note.to_string());
+            }
+            result[stream] = "".into();
+        }
+    }
+    lines.push(reply.to_string());
+    lines
+}
+
+/// How long to hold the stream after an `output` notification whose line
+/// is `sleep <seconds>`.
+fn pause_after(note: &str) -> Option<Duration> {
+    let note: serde_json::Value = serde_json::from_str(note).ok()?;
+    let seconds = note["params"]["data"].as_str()?.strip_prefix("sleep ")?;
+    Duration::try_from_secs_f64(seconds.trim().parse().ok()?).ok()
+}
+
+/// Archive the files below `dir` as a `.tar.gz`, with member names like
+/// `./src/main.rs`, as `tar -czf - -C dir .` would.
+///
+/// Deflate is used in stored mode, so no compressor is needed.
+fn tar_gz(files: &HashMap<String, Vec<u8>>, dir: &str) -> Vec<u8> {
+    let mut entries: Vec<_> = files
+        .iter()
+        .filter_map(|(path, data)| Some((Path::new(path).strip_prefix(dir).ok()?, data)))
+        .collect();
+    entries.sort();
+
+    let mut tar = Vec::new();
+    for (name, data) in entries {
+        let mut header = [0u8; 512];
+        let mut field = |offset: usize, value: &[u8]| {
+            header[offset..offset + value.len()].copy_from_slice(value);
+        };
+        field(0, format!("./{}", name.display()).as_bytes());
+        field(100, b"0000644\0");
+        field(108, b"0000000\0");
+        field(116, b"0000000\0");
+        field(124, format!("{:011o}\0", data.len()).as_bytes());
+        field(136, b"00000000000\0");
+        field(148, b"        ");
+        field(156, b"0");
+        field(257, b"ustar\x0000");
+        let checksum: u32 = header.iter().map(|&b| b as u32).sum();
+        header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+        tar.extend_from_slice(&header);
+        tar.extend_from_slice(data);
+        tar.resize(tar.len().div_ceil(512) * 512, 0);
+    }
+    tar.resize(tar.len() + 1024, 0);
+
+    let mut gz = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    let mut blocks = tar.chunks(u16::MAX as usize).peekable();
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        gz.push(blocks.peek().is_none() as u8);
+        gz.extend_from_slice(&len.to_le_bytes());
+        gz.extend_from_slice(&(!len).to_le_bytes());
+        gz.extend_from_slice(block);
+    }
+    gz.extend_from_slice(&crc32(&tar).to_le_bytes());
+    gz.extend_from_slice(&(tar.len() as u32).to_le_bytes());
+    gz
+}
+
+/// CRC-32 (IEEE), as gzip's trailer uses.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Wait out a streamed `sleep`, unless a `kill` for `exec_id` arrives
+/// first. The kill is answered, and its signal returned.
+async fn sleep_or_kill<R, W>(
+    reader: &mut BufReader<R>,
+    writer: &mut W,
+    delay: Duration,
+    exec_id: &serde_json::Value,
+) -> Option<String>
+where
+    R: tokio::io::AsyncRead + Unpin,
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    let sleep = tokio::time::sleep(delay);
+    tokio::pin!(sleep);
+    let mut buf = Vec::new();
+    loop {
+        tokio::select! {
+            _ = &mut sleep => return None,
+            read = reader.read_until(b'\n', &mut buf) => {
+                if read.unwrap_or(0) == 0 {
+                    return None;
+                }
+                let request: serde_json::Value = serde_json::from_slice(&buf).unwrap_or_default();
+                buf.clear();
+                if request["method"] == "kill" && request["params"]["exec_id"] == *exec_id {
+                    let reply = serde_json::json!({"jsonrpc": "2.0", "id": request["id"], "result": {"success": true}});
+                    let _ = writer.write_all(format!("{reply}\n").as_bytes()).await;
+                    return Some(request["params"]["signal"].as_str().unwrap_or("term").to_string());
+                }
+            }
+        }
+    }
+}
+
+/// How long an `exec` of `sleep <seconds>` should take.
+fn sleep_duration(line: &str) -> Option<Duration> {
+    let request: serde_json::Value = serde_json::from_str(line).ok()?;
+    if request["method"] != "exec" && request["method"] != "exec_stream" {
+        return None;
+    }
+    let seconds = request["params"]["cmd"].as_str()?.strip_prefix("sleep ")?;
+    let delay = Duration::try_from_secs_f64(seconds.trim().parse().ok()?).ok()?;
+    match request["params"]["timeout_secs"].as_u64() {
+        Some(timeout) => Some(delay.min(Duration::from_secs(timeout))),
+        None => Some(delay),
+    }
+}
+
+/// Build the response to one request line.
+fn handle_request(line: &str, guest: &Guest) -> serde_json::Value {
+    let request: serde_json::Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return error_response(serde_json::Value::Null, -32700, e.to_string()),
+    };
+    let id = request["id"].clone();
+    let params = &request["params"];
+    let result = match request["method"].as_str().unwrap_or_default() {
+        "info" => serde_json::json!({
+            "protocol_version": guest.protocol_version,
+            "agent_version": "mock",
+        }),
+        "ping" => serde_json::json!({"pong": true}),
+        "set_hostname" => {
+            let name = params["hostname"].as_str().unwrap_or_default();
+            *guest.hostname.lock().unwrap() = name.to_string();
+            serde_json::json!({"success": true})
+        }
+        "resize_fs" => serde_json::json!({"success": true}),
+        "hostname" => serde_json::json!({"hostname": *guest.hostname.lock().unwrap()}),
+        "write_file" => {
+            let path = params["path"].as_str().unwrap_or_default();
+            let mut files = guest.files.lock().unwrap();
+            let blocked = Path::new(path)
+                .ancestors()
+                .skip(1)
+                .any(|parent| files.contains_key(parent.to_str().unwrap_or_default()));
+            if blocked {
+                let message = format!("failed to write '{path}': Not a directory (os error 20)");
+                return error_response(id, -32603, message);
+            }
+            let content = params["content"].as_str().unwrap_or_default();
+            files.insert(path.to_string(), content.as_bytes().to_vec());
+            serde_json::json!({"success": true})
+        }
+        "read_file" => {
+            let path = params["path"].as_str().unwrap_or_default();
+            match guest.files.lock().unwrap().get(path) {
+                Some(content) => serde_json::json!({"content": String::from_utf8_lossy(content)}),
+                None => {
+                    let message =
+                        format!("failed to stat '{path}': No such file or directory (os error 2)");
+                    return error_response(id, -32603, message);
+                }
+            }
+        }
+        "write_file_chunk" => {
+            let path = params["path"].as_str().unwrap_or_default();
+            let offset = params["offset"].as_u64().unwrap_or_default();
+            let data = BASE64
+                .decode(params["data"].as_str().unwrap_or_default())
+                .unwrap_or_default();
+            let mut files = guest.files.lock().unwrap();
+            if offset == 0 {
+                files.insert(path.to_string(), Vec::new());
+            }
+            let Some(file) = files
+                .get_mut(path)
+                .filter(|file| file.len() as u64 == offset)
+            else {
+                let message = format!("chunk for '{path}' does not start at offset {offset}");
+                return error_response(id, -32603, message);
+            };
+            file.extend_from_slice(&data);
+            serde_json::json!({"size": file.len()})
+        }
+        "read_file_chunk" => {
+            let path = params["path"].as_str().unwrap_or_default();
+            let offset = params["offset"].as_u64().unwrap_or_default() as usize;
+            let len = params["len"].as_u64().unwrap_or_default() as usize;
+            let files = guest.files.lock().unwrap();
+            let Some(file) = files.get(path).filter(|file| offset <= file.len()) else {
+                let message =
+                    format!("failed to open '{path}': No such file or directory (os error 2)");
+                return error_response(id, -32603, message);
+            };
+            let end = file.len().min(offset + len);
+            serde_json::json!({
+                "data": BASE64.encode(&file[offset..end]),
+                "size": file.len(),
+                "eof": end == file.len(),
+            })
+        }
+        "exec" | "exec_stream" if params["argv"].is_array() => {
+            let argv: Vec<&str> = params["argv"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|arg| arg.as_str())
+                .collect();
+            let stdout = match argv[..] {
+                ["rm", "-f", path] => {
+                    guest.files.lock().unwrap().remove(path);
+                    String::new()
+                }
+                ["sh", "-c", script, _, archive, dir, ..] if script == EXPORT_SCRIPT => {
+                    let mut files = guest.files.lock().unwrap();
+                    let data = tar_gz(&files, dir);
+                    let stdout = format!("{}\n{}  {archive}\n", data.len(), "0".repeat(64));
+                    files.insert(archive.to_string(), data);
+                    stdout
+                }
+                _ => format!("{}\n", argv.join(" ")),
+            };
+            serde_json::json!({"exit_code": 0, "stdout": stdout, "stderr": ""})
+        }
+        "exec" | "exec_stream" => {
+            let cmd = params["cmd"].as_str().unwrap_or_default();
+            let timed_out = params["timeout_secs"].as_u64().filter(|&timeout| {
+                cmd.strip_prefix("sleep ")
+                    .and_then(|seconds| seconds.trim().parse::<f64>().ok())
+                    .is_some_and(|seconds| seconds > timeout as f64)
+            });
+            if let Some(timeout) = timed_out {
+                return serde_json::json!({"jsonrpc": "2.0", "id": id, "result": {
+                    "exit_code": -1,
+                    "stdout": "",
+                    "stderr": format!("timed out after {timeout}s"),
+                }});
+            }
+            let stdout = match cmd {
+                "pwd" => params["cwd"].as_str().unwrap_or("/"),
+                "cat" => params["stdin"].as_str().unwrap_or_default(),
+                cmd => match cmd.strip_prefix("printenv ") {
+                    Some(name) => params["env"][name].as_str().unwrap_or_default(),
+                    None => cmd,
+                },
+            };
+            serde_json::json!({
+                "exit_code": 0,
+                "stdout": format!("{stdout}\n"),
+                "stderr": "",
+            })
+        }
+        "exec_code" | "exec_code_stream" => serde_json::json!({
+            "exit_code": 0,
+            "stdout": params["code"],
+            "stderr": "",
+        }),
+        other => return error_response(id, -32601, format!("Method not found: {other}")),
+    };
+    serde_json::json!({"jsonrpc": "2.0", "id": id, "result": result})
+}
+
+fn error_response(id: serde_json::Value, code: i32, message: String) -> serde_json::Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": code, "message": message},
+    })
+}
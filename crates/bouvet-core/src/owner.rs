@@ -0,0 +1,72 @@
+//! Ownership tags for VM directories.
+//!
+//! Several host processes may share one `chroot_path`. Each VM directory
+//! carries an `owner` file naming the process that manages it and the boot
+//! it ran in, so scans for leftover VMs can tell a crashed owner's VMs
+//! from those of a server that is still running.
+
+use std::path::Path;
+
+/// Name of the tag file inside a VM directory.
+const OWNER_FILE: &str = "owner";
+
+/// Mark `vm_dir` as owned by this process.
+pub(crate) fn claim(vm_dir: &Path) -> std::io::Result<()> {
+    let tag = format!("{} {}\n", std::process::id(), boot_id().unwrap_or_default());
+    std::fs::write(vm_dir.join(OWNER_FILE), tag)
+}
+
+/// The PID of another live process that owns `vm_dir`, if any.
+///
+/// A directory without a readable tag, or whose owner has exited or ran in
+/// an earlier boot, has no live owner. This process never counts as
+/// another owner.
+pub(crate) fn live_owner(vm_dir: &Path) -> Option<u32> {
+    let tag = std::fs::read_to_string(vm_dir.join(OWNER_FILE)).ok()?;
+    let (pid, boot) = parse(&tag)?;
+    let alive = pid != std::process::id()
+        && Some(boot) == boot_id().as_deref()
+        && Path::new(&format!("/proc/{pid}")).exists();
+    alive.then_some(pid)
+}
+
+fn parse(tag: &str) -> Option<(u32, &str)> {
+    let mut fields = tag.split_whitespace();
+    let pid = fields.next()?.parse().ok()?;
+    let boot = fields.next()?;
+    Some((pid, boot))
+}
+
+/// The kernel's ID for the current boot; PIDs are only comparable within one.
+fn boot_id() -> Option<String> {
+    std::fs::read_to_string("/proc/sys/kernel/random/boot_id")
+        .ok()
+        .map(|id| id.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_live_owner() {
+        let dir = std::env::temp_dir().join(format!("bouvet-owner-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let boot = boot_id().unwrap();
+
+        assert_eq!(live_owner(&dir), None);
+        claim(&dir).unwrap();
+        assert_eq!(live_owner(&dir), None, "this process is not another owner");
+
+        std::fs::write(dir.join(OWNER_FILE), format!("1 {boot}\n")).unwrap();
+        assert_eq!(live_owner(&dir), Some(1));
+        std::fs::write(dir.join(OWNER_FILE), "1 not-this-boot\n").unwrap();
+        assert_eq!(live_owner(&dir), None);
+        std::fs::write(dir.join(OWNER_FILE), format!("{} {boot}\n", u32::MAX)).unwrap();
+        assert_eq!(live_owner(&dir), None);
+        std::fs::write(dir.join(OWNER_FILE), "garbage").unwrap();
+        assert_eq!(live_owner(&dir), None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -32,22 +32,32 @@
 //! pool.shutdown().await?;
 //! ```
 
+use crate::backend::{FirecrackerBackend, VmBackend};
 use crate::config::SandboxConfig;
 use crate::error::CoreError;
-use crate::sandbox::Sandbox;
+use crate::sandbox::{Sandbox, SandboxId};
 use std::collections::VecDeque;
-use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::{Mutex, Notify, Semaphore};
+use tokio::sync::{broadcast, Mutex, Notify, Semaphore};
 use tokio::task::JoinHandle;
 
 // ============================================================================
 // Configuration
 // ============================================================================
 
+/// Future returned by a [`ReadinessCheck`].
+pub type ReadinessFuture<'a> = Pin<Box<dyn Future<Output = bool> + Send + 'a>>;
+
+/// Decides whether a freshly booted sandbox is ready to enter the pool.
+pub type ReadinessCheck = Arc<dyn for<'a> Fn(&'a Sandbox) -> ReadinessFuture<'a> + Send + Sync>;
+
 /// Configuration for the sandbox pool.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct PoolConfig {
     /// Minimum number of warm sandboxes to maintain.
     ///
@@ -69,6 +79,52 @@ pub struct PoolConfig {
 
     /// Sandbox configuration template for creating new VMs.
     pub sandbox_config: SandboxConfig,
+
+    /// Readiness check run on each new sandbox before it is pooled.
+    ///
+    /// Sandboxes that fail it are destroyed instead of pooled. Use this when
+    /// a template needs more than a booted agent (a warmed database, a
+    /// listening server). Default: `None`, which pings the agent.
+    pub readiness: Option<ReadinessCheck>,
+}
+
+impl PoolConfig {
+    /// Set the readiness check run before a sandbox enters the pool.
+    ///
+    /// ```ignore
+    /// let config = PoolConfig::default().readiness(|sandbox| {
+    ///     Box::pin(async move {
+    ///         sandbox.execute("pg_isready").await.is_ok_and(|r| r.success())
+    ///     })
+    /// });
+    /// ```
+    pub fn readiness<F>(mut self, check: F) -> Self
+    where
+        F: for<'a> Fn(&'a Sandbox) -> ReadinessFuture<'a> + Send + Sync + 'static,
+    {
+        self.readiness = Some(Arc::new(check));
+        self
+    }
+
+    /// Run the readiness check (or the default ping) on `sandbox`.
+    async fn is_ready(&self, sandbox: &Sandbox) -> bool {
+        match &self.readiness {
+            Some(check) => check(sandbox).await,
+            None => sandbox.is_healthy().await,
+        }
+    }
+}
+
+impl fmt::Debug for PoolConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolConfig")
+            .field("min_size", &self.min_size)
+            .field("max_concurrent_boots", &self.max_concurrent_boots)
+            .field("fill_interval", &self.fill_interval)
+            .field("sandbox_config", &self.sandbox_config)
+            .field("readiness", &self.readiness.as_ref().map(|_| "<fn>"))
+            .finish()
+    }
 }
 
 impl Default for PoolConfig {
@@ -78,6 +134,7 @@ impl Default for PoolConfig {
             max_concurrent_boots: 2,
             fill_interval: Duration::from_secs(1),
             sandbox_config: SandboxConfig::default(),
+            readiness: None,
         }
     }
 }
@@ -99,6 +156,9 @@ pub struct PoolStats {
     pub created: AtomicU64,
     /// Total sandboxes destroyed by the pool.
     pub destroyed: AtomicU64,
+    /// Sandboxes the filler is booting that haven't been pooled or
+    /// discarded yet.
+    pub in_flight_boots: AtomicUsize,
 }
 
 impl PoolStats {
@@ -122,6 +182,14 @@ impl PoolStats {
         self.destroyed.load(Ordering::Relaxed)
     }
 
+    /// Get the number of boots in progress.
+    ///
+    /// An empty pool with boots in flight is filling; one with none in
+    /// flight and failing fills is stuck.
+    pub fn in_flight_boots(&self) -> usize {
+        self.in_flight_boots.load(Ordering::Relaxed)
+    }
+
     /// Calculate the warm hit rate as a percentage.
     pub fn hit_rate(&self) -> f64 {
         let hits = self.warm_hits() as f64;
@@ -135,6 +203,80 @@ impl PoolStats {
     }
 }
 
+/// Counts a filler boot as in flight for as long as it is alive, however
+/// the boot task ends.
+struct InFlightBoot(Arc<PoolStats>);
+
+impl InFlightBoot {
+    fn start(stats: Arc<PoolStats>) -> Self {
+        stats.in_flight_boots.fetch_add(1, Ordering::Relaxed);
+        Self(stats)
+    }
+}
+
+impl Drop for InFlightBoot {
+    fn drop(&mut self) {
+        self.0.in_flight_boots.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// ============================================================================
+// Events
+// ============================================================================
+
+/// Capacity of the pool event channel. Subscribers that fall further
+/// behind than this miss the oldest events.
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Something that happened in the pool, published to
+/// [`SandboxPool::subscribe`] receivers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoolEvent {
+    /// The filler started booting a sandbox.
+    FillStarted,
+    /// A booted sandbox was added to the pool.
+    FillSucceeded {
+        /// The pooled sandbox.
+        sandbox_id: SandboxId,
+        /// Pool size after adding it.
+        pool_size: usize,
+    },
+    /// The filler failed to boot a sandbox.
+    FillFailed {
+        /// Why creation failed.
+        error: String,
+    },
+    /// `acquire` handed out a warm sandbox.
+    AcquireHit {
+        /// The acquired sandbox.
+        sandbox_id: SandboxId,
+        /// Pool size after taking it.
+        pool_size: usize,
+    },
+    /// `acquire` found no healthy sandbox and fell back to a cold start.
+    AcquireMiss,
+    /// A sandbox was destroyed instead of being pooled or handed out.
+    Discarded {
+        /// The destroyed sandbox.
+        sandbox_id: SandboxId,
+        /// Why it was destroyed.
+        reason: DiscardReason,
+    },
+}
+
+/// Why the pool destroyed a sandbox.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscardReason {
+    /// Failed the readiness check after booting.
+    NotReady,
+    /// Failed the health check on acquire.
+    Unhealthy,
+    /// Booted after the pool was already full.
+    PoolFull,
+    /// The pool is shutting down.
+    Shutdown,
+}
+
 // ============================================================================
 // Pool Implementation
 // ============================================================================
@@ -165,6 +307,10 @@ pub struct SandboxPool {
     stats: Arc<PoolStats>,
     /// Counter for assigning unique vsock CIDs (starts at 3, the minimum valid CID).
     cid_counter: Arc<AtomicU32>,
+    /// Publisher for pool events.
+    events: broadcast::Sender<PoolEvent>,
+    /// Creates the VMs for new sandboxes.
+    backend: Arc<dyn VmBackend>,
 }
 
 impl SandboxPool {
@@ -173,6 +319,11 @@ impl SandboxPool {
     /// The pool is created but the background filler is not started.
     /// Call [`start()`](Self::start) to begin filling the pool.
     pub fn new(config: PoolConfig) -> Self {
+        Self::with_backend(config, Arc::new(FirecrackerBackend))
+    }
+
+    /// Create a sandbox pool whose sandboxes get their VMs from `backend`.
+    pub fn with_backend(config: PoolConfig, backend: Arc<dyn VmBackend>) -> Self {
         tracing::info!(
             min_size = config.min_size,
             max_concurrent_boots = config.max_concurrent_boots,
@@ -187,7 +338,9 @@ impl SandboxPool {
             filler_handle: None,
             stats: Arc::new(PoolStats::default()),
             cid_counter: Arc::new(AtomicU32::new(10000)), // Start at offset to avoid collision with manager's CIDs
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
             config,
+            backend,
         }
     }
 
@@ -208,6 +361,8 @@ impl SandboxPool {
         let semaphore = Arc::clone(&self.boot_semaphore);
         let stats = Arc::clone(&self.stats);
         let cid_counter = Arc::clone(&self.cid_counter);
+        let events = self.events.clone();
+        let backend = Arc::clone(&self.backend);
 
         let handle = tokio::spawn(async move {
             Self::filler_loop(
@@ -218,6 +373,8 @@ impl SandboxPool {
                 semaphore,
                 stats,
                 cid_counter,
+                events,
+                backend,
             )
             .await;
         });
@@ -230,6 +387,7 @@ impl SandboxPool {
     ///
     /// Runs until shutdown is signaled, periodically checking pool level
     /// and spawning VM creation tasks as needed.
+    #[allow(clippy::too_many_arguments)]
     async fn filler_loop(
         pool: Arc<Mutex<VecDeque<Sandbox>>>,
         config: PoolConfig,
@@ -238,6 +396,8 @@ impl SandboxPool {
         semaphore: Arc<Semaphore>,
         stats: Arc<PoolStats>,
         cid_counter: Arc<AtomicU32>,
+        events: broadcast::Sender<PoolEvent>,
+        backend: Arc<dyn VmBackend>,
     ) {
         tracing::debug!("Filler loop started");
 
@@ -285,12 +445,15 @@ impl SandboxPool {
                         };
 
                         let pool = Arc::clone(&pool);
+                        let config = config.clone();
                         let mut cfg = config.sandbox_config.clone();
                         // Assign a unique CID to prevent vsock collisions
                         cfg.vsock_cid = cid_counter.fetch_add(1, Ordering::Relaxed);
                         let stats = Arc::clone(&stats);
                         let shutdown = Arc::clone(&shutdown);
                         let min_size = config.min_size;
+                        let events = events.clone();
+                        let backend = Arc::clone(&backend);
 
                         tokio::spawn(async move {
                             // Hold permit until this task completes
@@ -301,13 +464,30 @@ impl SandboxPool {
                                 tracing::trace!("Skipping sandbox creation due to shutdown");
                                 return;
                             }
+                            let _in_flight = InFlightBoot::start(Arc::clone(&stats));
 
                             tracing::debug!("Creating sandbox for pool");
-                            match Sandbox::create(cfg).await {
+                            let _ = events.send(PoolEvent::FillStarted);
+                            match Sandbox::create(cfg, backend.as_ref()).await {
                                 Ok(sandbox) => {
+                                    let discard = |reason| PoolEvent::Discarded {
+                                        sandbox_id: sandbox.id(),
+                                        reason,
+                                    };
+
+                                    // Only pool sandboxes the template considers ready
+                                    if !config.is_ready(&sandbox).await {
+                                        tracing::warn!(sandbox_id = %sandbox.id(), "Sandbox failed readiness check, destroying");
+                                        stats.destroyed.fetch_add(1, Ordering::Relaxed);
+                                        let _ = events.send(discard(DiscardReason::NotReady));
+                                        let _ = sandbox.destroy().await;
+                                        return;
+                                    }
+
                                     // Check shutdown again and pool size before adding
                                     if shutdown.load(Ordering::Relaxed) {
                                         tracing::debug!("Shutdown during sandbox creation, destroying");
+                                        let _ = events.send(discard(DiscardReason::Shutdown));
                                         let _ = sandbox.destroy().await;
                                         return;
                                     }
@@ -317,17 +497,26 @@ impl SandboxPool {
                                     if guard.len() >= min_size {
                                         drop(guard);
                                         tracing::debug!("Pool already full, destroying excess sandbox");
+                                        let _ = events.send(discard(DiscardReason::PoolFull));
                                         let _ = sandbox.destroy().await;
                                         return;
                                     }
                                     stats.created.fetch_add(1, Ordering::Relaxed);
+                                    let sandbox_id = sandbox.id();
                                     guard.push_back(sandbox);
                                     let new_size = guard.len();
                                     drop(guard);
                                     tracing::debug!(pool_size = new_size, "Added sandbox to pool");
+                                    let _ = events.send(PoolEvent::FillSucceeded {
+                                        sandbox_id,
+                                        pool_size: new_size,
+                                    });
                                 }
                                 Err(e) => {
                                     tracing::warn!(error = %e, "Failed to create sandbox for pool");
+                                    let _ = events.send(PoolEvent::FillFailed {
+                                        error: e.to_string(),
+                                    });
                                 }
                             }
                         });
@@ -354,8 +543,16 @@ impl SandboxPool {
     ///
     /// # Errors
     ///
-    /// Returns an error if sandbox creation fails (only possible on cold-start).
+    /// Returns [`CoreError::PoolShuttingDown`] once [`shutdown`](Self::shutdown)
+    /// has started, rather than cold-starting a sandbox nobody would
+    /// destroy. Otherwise returns an error if sandbox creation fails (only
+    /// possible on cold-start).
     pub async fn acquire(&self) -> Result<Sandbox, CoreError> {
+        if self.shutdown.load(Ordering::Relaxed) {
+            tracing::debug!("Pool is shutting down, refusing acquire");
+            return Err(CoreError::PoolShuttingDown);
+        }
+
         // Try to get a healthy sandbox from the pool
         loop {
             let sandbox = {
@@ -373,6 +570,10 @@ impl SandboxPool {
                 self.stats.warm_hits.fetch_add(1, Ordering::Relaxed);
                 let pool_size = self.pool.lock().await.len();
                 tracing::debug!(pool_size, "Acquired sandbox from warm pool");
+                let _ = self.events.send(PoolEvent::AcquireHit {
+                    sandbox_id: sandbox.id(),
+                    pool_size,
+                });
                 return Ok(sandbox);
             }
 
@@ -380,6 +581,10 @@ impl SandboxPool {
             let sandbox_id = sandbox.id();
             tracing::warn!(sandbox_id = %sandbox_id, "Discarding unhealthy sandbox from pool");
             self.stats.destroyed.fetch_add(1, Ordering::Relaxed);
+            let _ = self.events.send(PoolEvent::Discarded {
+                sandbox_id,
+                reason: DiscardReason::Unhealthy,
+            });
             if let Err(e) = sandbox.destroy().await {
                 tracing::error!(error = %e, "Failed to destroy unhealthy sandbox");
             }
@@ -388,10 +593,11 @@ impl SandboxPool {
         // Pool exhausted, perform cold-start
         self.stats.cold_misses.fetch_add(1, Ordering::Relaxed);
         tracing::info!("Pool empty, performing cold-start");
+        let _ = self.events.send(PoolEvent::AcquireMiss);
         let mut cfg = self.config.sandbox_config.clone();
         // Assign a unique CID to prevent vsock collisions
         cfg.vsock_cid = self.cid_counter.fetch_add(1, Ordering::Relaxed);
-        Sandbox::create(cfg).await
+        Sandbox::create(cfg, self.backend.as_ref()).await
     }
 
     /// Get the current number of sandboxes in the pool.
@@ -404,11 +610,25 @@ impl SandboxPool {
         &self.config
     }
 
+    /// Get the number of sandboxes the filler is booting right now.
+    pub fn in_flight_boots(&self) -> usize {
+        self.stats.in_flight_boots()
+    }
+
     /// Get the pool statistics.
     pub fn stats(&self) -> &PoolStats {
         &self.stats
     }
 
+    /// Subscribe to pool events.
+    ///
+    /// Only events published after subscribing are received. A receiver
+    /// that falls more than 256 events behind gets
+    /// [`broadcast::error::RecvError::Lagged`] and skips ahead.
+    pub fn subscribe(&self) -> broadcast::Receiver<PoolEvent> {
+        self.events.subscribe()
+    }
+
     /// Check if the filler task is running.
     pub fn is_running(&self) -> bool {
         self.filler_handle.is_some() && !self.shutdown.load(Ordering::Relaxed)
@@ -452,6 +672,10 @@ impl SandboxPool {
         for sandbox in sandboxes {
             let sandbox_id = sandbox.id();
             self.stats.destroyed.fetch_add(1, Ordering::Relaxed);
+            let _ = self.events.send(PoolEvent::Discarded {
+                sandbox_id,
+                reason: DiscardReason::Shutdown,
+            });
             if let Err(e) = sandbox.destroy().await {
                 tracing::error!(
                     sandbox_id = %sandbox_id,
@@ -496,6 +720,7 @@ mod tests {
         assert_eq!(stats.cold_misses(), 0);
         assert_eq!(stats.created(), 0);
         assert_eq!(stats.destroyed(), 0);
+        assert_eq!(stats.in_flight_boots(), 0);
     }
 
     #[test]
@@ -524,4 +749,256 @@ mod tests {
         let pool = SandboxPool::new(config);
         assert_eq!(pool.size().await, 0);
     }
+
+    /// Attach a sandbox to fake Firecracker and agent sockets under `dir`.
+    ///
+    /// Nothing is booted, so the sandbox must be dropped, not destroyed.
+    async fn fake_sandbox(dir: &std::path::Path) -> Sandbox {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let id = crate::SandboxId::new();
+        let vm_dir = dir.join(id.to_string());
+        std::fs::create_dir_all(&vm_dir).unwrap();
+        // The API socket never answers, so attaching assumes the VM is running
+        let api = tokio::net::UnixListener::bind(vm_dir.join("firecracker.socket")).unwrap();
+        let agent = tokio::net::UnixListener::bind(vm_dir.join("v.sock")).unwrap();
+        tokio::spawn(async move {
+            let _api = api;
+            let (stream, _) = agent.accept().await.unwrap();
+            let (read_half, mut writer) = tokio::io::split(stream);
+            let mut reader = BufReader::new(read_half);
+            let mut line = String::new();
+            while reader.read_line(&mut line).await.unwrap_or(0) > 0 {
+                if line.starts_with("CONNECT ") {
+                    writer.write_all(b"OK 1\n").await.unwrap();
+                    line.clear();
+                    continue;
+                }
+                let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+                let result = match request["method"].as_str().unwrap() {
+                    "info" => serde_json::json!({
+                        "protocol_version": {"major": 1, "minor": 27},
+                        "agent_version": "test",
+                    }),
+                    "ping" => serde_json::json!({"pong": true}),
+                    "hostname" => serde_json::json!({"hostname": "fake"}),
+                    other => panic!("unexpected method {other}"),
+                };
+                let response =
+                    serde_json::json!({"jsonrpc": "2.0", "id": request["id"], "result": result});
+                let _ = writer.write_all(format!("{response}\n").as_bytes()).await;
+                line.clear();
+            }
+        });
+
+        let config = SandboxConfig {
+            chroot_path: dir.to_path_buf(),
+            ..Default::default()
+        };
+        Sandbox::attach(id, config).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pool_readiness_check() {
+        let dir = std::env::temp_dir().join(format!("bouvet-pool-ready-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let sandbox = fake_sandbox(&dir).await;
+
+        // Default: the agent answers a ping
+        assert!(PoolConfig::default().is_ready(&sandbox).await);
+
+        let config = PoolConfig::default().readiness(|sandbox| {
+            Box::pin(async move {
+                // e.g. waiting for an app inside the VM to finish starting
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                sandbox.is_healthy().await
+            })
+        });
+        assert!(format!("{config:?}").contains("readiness: Some(\"<fn>\")"));
+        let start = std::time::Instant::now();
+        assert!(config.is_ready(&sandbox).await);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        let config = PoolConfig::default().readiness(|_| Box::pin(async { false }));
+        assert!(!config.is_ready(&sandbox).await);
+
+        drop(sandbox);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Receive the next pool event, failing the test if none arrives.
+    async fn next(events: &mut broadcast::Receiver<PoolEvent>) -> PoolEvent {
+        tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .expect("pool event")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pool_events() {
+        let dir = std::env::temp_dir().join(format!("bouvet-pool-events-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut pool = SandboxPool::new(PoolConfig {
+            min_size: 1,
+            max_concurrent_boots: 1,
+            fill_interval: Duration::from_millis(10),
+            // Below the VM minimum, so boots fail without starting Firecracker
+            sandbox_config: SandboxConfig {
+                memory_mib: 64,
+                chroot_path: dir.clone(),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let mut events = pool.subscribe();
+
+        // A fill attempt
+        pool.start();
+        assert_eq!(next(&mut events).await, PoolEvent::FillStarted);
+        assert!(matches!(
+            next(&mut events).await,
+            PoolEvent::FillFailed { .. }
+        ));
+        pool.shutdown().await.unwrap();
+
+        // A hit on a pooled sandbox, then a miss once the pool is empty
+        // (a fresh pool, so late fill events from the first can't interfere)
+        let pool = SandboxPool::new(pool.config.clone());
+        let mut events = pool.subscribe();
+        let sandbox = fake_sandbox(&dir).await;
+        let sandbox_id = sandbox.id();
+        pool.pool.lock().await.push_back(sandbox);
+        let acquired = pool.acquire().await.unwrap();
+        assert_eq!(
+            next(&mut events).await,
+            PoolEvent::AcquireHit {
+                sandbox_id,
+                pool_size: 0
+            }
+        );
+        assert!(pool.acquire().await.is_err());
+        assert_eq!(next(&mut events).await, PoolEvent::AcquireMiss);
+
+        drop(acquired);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_pool_fills_with_mock_backend() {
+        let dir = std::env::temp_dir().join(format!("bouvet-pool-mock-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let backend = Arc::new(crate::MockBackend::new());
+        let mut pool = SandboxPool::with_backend(
+            PoolConfig {
+                min_size: 1,
+                max_concurrent_boots: 1,
+                fill_interval: Duration::from_millis(10),
+                sandbox_config: SandboxConfig {
+                    chroot_path: dir.clone(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            backend.clone(),
+        );
+        let mut events = pool.subscribe();
+
+        pool.start();
+        assert_eq!(next(&mut events).await, PoolEvent::FillStarted);
+        let PoolEvent::FillSucceeded { sandbox_id, .. } = next(&mut events).await else {
+            panic!("expected FillSucceeded");
+        };
+        let sandbox = pool.acquire().await.unwrap();
+        assert_eq!(sandbox.id(), sandbox_id);
+        assert_eq!(pool.stats().warm_hits(), 1);
+
+        sandbox.destroy().await.unwrap();
+        pool.shutdown().await.unwrap();
+        assert_eq!(backend.destroyed(), backend.created());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_after_shutdown_is_refused() {
+        let dir = std::env::temp_dir().join(format!("bouvet-pool-drain-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let backend = Arc::new(crate::MockBackend::new());
+        let mut pool = SandboxPool::with_backend(
+            PoolConfig {
+                min_size: 1,
+                max_concurrent_boots: 1,
+                fill_interval: Duration::from_millis(10),
+                sandbox_config: SandboxConfig {
+                    chroot_path: dir.clone(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            backend.clone(),
+        );
+        let mut events = pool.subscribe();
+
+        pool.start();
+        assert_eq!(next(&mut events).await, PoolEvent::FillStarted);
+        assert!(matches!(
+            next(&mut events).await,
+            PoolEvent::FillSucceeded { .. }
+        ));
+        pool.shutdown().await.unwrap();
+        let created = backend.created();
+
+        // Neither the drained pool nor a cold start hands out a sandbox
+        assert!(matches!(
+            pool.acquire().await,
+            Err(CoreError::PoolShuttingDown)
+        ));
+        assert_eq!(backend.created(), created);
+        assert_eq!(backend.destroyed(), created);
+        assert_eq!(pool.stats().cold_misses(), 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_pool_counts_in_flight_boots() {
+        let dir = std::env::temp_dir().join(format!("bouvet-pool-boots-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let backend = Arc::new(crate::MockBackend::new());
+        backend.delay_boot(Duration::from_millis(300));
+        let mut pool = SandboxPool::with_backend(
+            PoolConfig {
+                min_size: 1,
+                max_concurrent_boots: 1,
+                fill_interval: Duration::from_millis(10),
+                sandbox_config: SandboxConfig {
+                    chroot_path: dir.clone(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            backend.clone(),
+        );
+        let mut events = pool.subscribe();
+        assert_eq!(pool.in_flight_boots(), 0);
+
+        pool.start();
+        assert_eq!(next(&mut events).await, PoolEvent::FillStarted);
+        assert_eq!(pool.in_flight_boots(), 1);
+        assert_eq!(pool.size().await, 0);
+        assert!(matches!(
+            next(&mut events).await,
+            PoolEvent::FillSucceeded { .. }
+        ));
+        // The boot task drops its count just after publishing
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while pool.in_flight_boots() != 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("boot count returns to zero");
+
+        pool.shutdown().await.unwrap();
+        assert_eq!(backend.destroyed(), backend.created());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
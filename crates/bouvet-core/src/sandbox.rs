@@ -1,14 +1,47 @@
 //! Sandbox type - a running microVM with agent connection.
 
-use crate::client::{AgentClient, ExecResult, FileEntry};
-use crate::config::SandboxConfig;
+use crate::backend::{VmBackend, VmInstance};
+use crate::client::{
+    wait_for_agent_timed, AgentClient, AgentReady, ConnectivityResult, ExecOptions, ExecResult,
+    FileDownload, FileEntry, FileStat, ListDirOptions, OsInfo, UploadFinish, UploadStatus,
+    CONNECT_TIMEOUT,
+};
+use crate::config::{Capability, SandboxCapabilities, SandboxConfig};
+use crate::connections::AgentConnections;
 use crate::error::CoreError;
+use crate::stream::{
+    output_channel, ExecStream, RunningExecs, Signal, TailStream, OUTPUT_CHANNEL_CAPACITY,
+};
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::watch;
 use uuid::Uuid;
 
+/// Firecracker drive ID of a sandbox's scratch drive.
+pub const SCRATCH_DRIVE_ID: &str = "scratch";
+
+/// Guest block device of the scratch drive: the first drive after the rootfs.
+pub const SCRATCH_DEVICE: &str = "/dev/vdb";
+
+/// Size of the chunks [`Sandbox::upload_file`] and [`Sandbox::download_file`]
+/// move per request (1 MiB).
+pub const TRANSFER_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Guest directories [`Sandbox::export_workspace`] leaves out: kernel and
+/// runtime filesystems rather than files the sandbox produced.
+pub const EXPORT_SKIP_DIRS: &[&str] = &["/proc", "/sys", "/dev", "/run"];
+
+/// Guest script behind [`Sandbox::export_workspace`]: archive `$2` into
+/// `$1` with the remaining arguments as tar options, then print the
+/// archive's size and SHA-256.
+pub(crate) const EXPORT_SCRIPT: &str = r#"archive=$1 dir=$2; shift 2; tar -czf "$archive" -C "$dir" "$@" . && wc -c < "$archive" && sha256sum "$archive""#;
+
 /// Unique identifier for a sandbox.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct SandboxId(Uuid);
@@ -23,6 +56,13 @@ impl SandboxId {
     pub fn as_uuid(&self) -> Uuid {
         self.0
     }
+
+    /// Get the short form of the ID: the first 8 hex digits of the UUID.
+    ///
+    /// Used as the default guest hostname.
+    pub fn short(&self) -> String {
+        self.0.simple().to_string()[..8].to_string()
+    }
 }
 
 impl Default for SandboxId {
@@ -44,7 +84,9 @@ impl From<Uuid> for SandboxId {
 }
 
 /// Current state of a sandbox.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// Serializes to the same strings produced by `Display`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SandboxState {
     /// Sandbox is being created (VM booting, agent connecting).
     Creating,
@@ -64,6 +106,154 @@ impl fmt::Display for SandboxState {
     }
 }
 
+/// Time spent in each phase of sandbox creation.
+///
+/// Phases are measured back to back, so their sum equals `total_ms` up to
+/// millisecond rounding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreationTiming {
+    /// Preparing the VM config and booting the microVM.
+    pub vm_create_ms: u64,
+    /// Connecting to the guest agent over vsock (includes connect retries).
+    pub agent_connect_ms: u64,
+    /// Initial ping round-trip to the agent.
+    pub ping_ms: u64,
+    /// Total creation time.
+    pub total_ms: u64,
+}
+
+impl CreationTiming {
+    /// Sum of the individual phase durations.
+    pub fn phases_ms(&self) -> u64 {
+        self.vm_create_ms + self.agent_connect_ms + self.ping_ms
+    }
+}
+
+/// A hibernated sandbox: where its snapshot lives and how to restore it.
+///
+/// Returned by [`SandboxManager::hibernate`](crate::SandboxManager::hibernate)
+/// and passed back to
+/// [`resume_from_hibernate`](crate::SandboxManager::resume_from_hibernate).
+#[derive(Debug, Clone)]
+pub struct SnapshotHandle {
+    /// ID of the sandbox; kept when it is resumed.
+    pub sandbox_id: SandboxId,
+    /// VM state file.
+    pub snapshot_path: PathBuf,
+    /// Guest memory file.
+    pub mem_file_path: PathBuf,
+    /// Configuration the sandbox was created with.
+    pub config: SandboxConfig,
+    /// When the sandbox was originally created.
+    pub created_at: DateTime<Utc>,
+    /// When the sandbox was hibernated.
+    pub hibernated_at: DateTime<Utc>,
+}
+
+impl SnapshotHandle {
+    /// Lay out the snapshot files for a sandbox under `snapshot_dir/<id>/`.
+    pub(crate) fn new(
+        snapshot_dir: &Path,
+        sandbox_id: SandboxId,
+        config: SandboxConfig,
+        created_at: DateTime<Utc>,
+    ) -> Self {
+        let dir = snapshot_dir.join(sandbox_id.to_string());
+        Self {
+            sandbox_id,
+            snapshot_path: dir.join("vmstate"),
+            mem_file_path: dir.join("memory"),
+            config,
+            created_at,
+            hibernated_at: Utc::now(),
+        }
+    }
+
+    /// Directory holding the snapshot files.
+    pub fn dir(&self) -> &Path {
+        self.snapshot_path.parent().unwrap_or(Path::new("/"))
+    }
+}
+
+/// A guest directory archived to the host by
+/// [`Sandbox::export_workspace`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceExport {
+    /// Guest directory that was archived.
+    pub dir: String,
+    /// Size of the `.tar.gz` archive in bytes.
+    pub size: u64,
+    /// SHA-256 of the archive as computed in the guest, lowercase hex.
+    pub sha256: String,
+}
+
+/// Lap timer used to measure consecutive creation phases.
+struct PhaseTimer {
+    start: Instant,
+    last: Instant,
+}
+
+impl PhaseTimer {
+    fn start() -> Self {
+        let now = Instant::now();
+        Self {
+            start: now,
+            last: now,
+        }
+    }
+
+    /// Milliseconds since the previous lap (or start).
+    fn lap(&mut self) -> u64 {
+        let now = Instant::now();
+        let ms = now.duration_since(self.last).as_millis() as u64;
+        self.last = now;
+        ms
+    }
+
+    /// Time since start, up to now.
+    fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Milliseconds from start to the most recent lap.
+    fn total(&self) -> u64 {
+        self.last.duration_since(self.start).as_millis() as u64
+    }
+}
+
+/// Warns when a sandbox is dropped without being destroyed.
+///
+/// Dropping a sandbox closes its agent connection (once any [`TailStream`]
+/// following a file, or [`ExecStream`] still running, is done too) but does not stop the VM or remove its
+/// directory, so `destroy` is the only correct teardown.
+#[derive(Debug)]
+struct DropGuard {
+    id: SandboxId,
+    armed: bool,
+}
+
+impl DropGuard {
+    fn new(id: SandboxId) -> Self {
+        Self { id, armed: true }
+    }
+
+    /// Mark the sandbox as torn down properly.
+    fn disarm(&mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for DropGuard {
+    fn drop(&mut self) {
+        if self.armed {
+            tracing::warn!(
+                sandbox_id = %self.id,
+                "Sandbox dropped while ready without destroy(); VM may be left running"
+            );
+        }
+    }
+}
+
 /// A running sandbox with VM and agent connection.
 ///
 /// A sandbox represents a complete isolated execution environment consisting of:
@@ -72,26 +262,41 @@ impl fmt::Display for SandboxState {
 ///
 /// Use the methods on this type to execute commands and work with files
 /// in the isolated environment.
+///
+/// Always tear a sandbox down with [`Sandbox::destroy`]; dropping a ready
+/// sandbox logs a warning.
 pub struct Sandbox {
     id: SandboxId,
-    vm: bouvet_vm::VirtualMachine,
-    client: Arc<Mutex<AgentClient>>,
+    vm: Box<dyn VmInstance>,
+    connections: AgentConnections,
     config: SandboxConfig,
-    state: SandboxState,
+    /// Current state, published to [`Sandbox::watch_state`] receivers.
+    state: watch::Sender<SandboxState>,
     created_at: DateTime<Utc>,
+    creation_timing: CreationTiming,
+    hostname: std::sync::RwLock<Option<String>>,
+    os_info: std::sync::OnceLock<OsInfo>,
+    /// Streamed commands that can be cancelled.
+    execs: Arc<RunningExecs>,
+    /// When the last operation started, for idle expiry.
+    last_used: std::sync::Mutex<Instant>,
+    drop_guard: DropGuard,
 }
 
 impl Sandbox {
     /// Create a new sandbox (called by SandboxManager).
     ///
     /// This will:
-    /// 1. Create and boot a microVM
+    /// 1. Create and boot a microVM with `backend`
     /// 2. Wait for the guest agent to start
     /// 3. Connect to the agent via vsock
     /// 4. Verify the agent is responsive
-    pub(crate) async fn create(config: SandboxConfig) -> Result<Self, CoreError> {
+    pub(crate) async fn create(
+        config: SandboxConfig,
+        backend: &dyn VmBackend,
+    ) -> Result<Self, CoreError> {
         let id = SandboxId::new();
-        let start = std::time::Instant::now();
+        let mut timer = PhaseTimer::start();
         tracing::info!(
             sandbox_id = %id,
             vcpus = config.vcpu_count,
@@ -117,21 +322,15 @@ impl Sandbox {
                 CoreError::Connection(format!("Failed to create vsock directory: {}", e))
             })?;
         }
+        Self::claim_vm_dir(id, &config);
 
         // 1. Build VM config with unique vsock path
         tracing::debug!(sandbox_id = %id, "Building VM configuration");
-        let vm_config = bouvet_vm::VmBuilder::new()
-            .vcpus(config.vcpu_count)
-            .memory_mib(config.memory_mib)
-            .kernel(&config.kernel_path)
-            .rootfs(&config.rootfs_path)
-            .chroot_path(&config.chroot_path)
-            .with_vsock_config(vsock_config)
-            .build_config();
+        let vm_config = Self::vm_config(&config, vsock_config);
 
         // 2. Create and boot VM with the same ID as the sandbox
         tracing::debug!(sandbox_id = %id, "Creating and booting VM");
-        let vm = match bouvet_vm::VirtualMachine::create_with_id(id.as_uuid(), vm_config).await {
+        let vm = match backend.create(id.as_uuid(), vm_config).await {
             Ok(vm) => vm,
             Err(e) => {
                 tracing::error!(sandbox_id = %id, error = %e, "VM creation failed");
@@ -141,40 +340,351 @@ impl Sandbox {
                 return Err(e.into());
             }
         };
-        tracing::debug!(
-            sandbox_id = %id,
-            elapsed_ms = start.elapsed().as_millis() as u64,
-            "VM created and started"
-        );
+        let vm_create_ms = timer.lap();
+        tracing::debug!(sandbox_id = %id, vm_create_ms, "VM created and started");
 
-        // 3. Get vsock path and connect to agent
-        let vsock_path = vm
-            .vsock_uds_path()
-            .ok_or_else(|| CoreError::Connection("vsock not configured".into()))?;
+        let budget = config.max_boot_ms.map(Duration::from_millis);
+        if budget.is_some_and(|budget| timer.elapsed() >= budget) {
+            return Err(Self::abort_boot(id, vm, &config, timer.elapsed()).await);
+        }
+
+        // 3. Get vsock path
+        let Some(vsock_path) = vm.vsock_uds_path().map(|path| path.to_path_buf()) else {
+            Self::discard_vm(id, vm, &config).await;
+            return Err(CoreError::Connection("vsock not configured".into()));
+        };
+
+        // 4. Wait for the agent to accept connections and answer a ping,
+        // for no longer than what is left of the boot budget
+        tracing::debug!(sandbox_id = %id, path = %vsock_path.display(), "Waiting for agent");
+        let agent_timeout = budget.map_or(CONNECT_TIMEOUT, |budget| {
+            CONNECT_TIMEOUT.min(budget.saturating_sub(timer.elapsed()))
+        });
+        let ready = wait_for_agent_timed(&vsock_path, agent_timeout, &config.client_config()).await;
+        if budget.is_some_and(|budget| timer.elapsed() >= budget) {
+            drop(ready);
+            return Err(Self::abort_boot(id, vm, &config, timer.elapsed()).await);
+        }
+        let AgentReady {
+            mut client,
+            connect_ms: agent_connect_ms,
+            ping_ms,
+        } = match ready {
+            Ok(ready) => ready,
+            Err(e) => {
+                tracing::error!(sandbox_id = %id, error = %e, "Agent did not become ready");
+                Self::discard_vm(id, vm, &config).await;
+                return Err(match e {
+                    CoreError::AgentTimeout(timeout) => CoreError::BootTimeout(timeout),
+                    e @ CoreError::IncompatibleProtocol { .. } => e,
+                    e => CoreError::ConnectFailed(e.to_string()),
+                });
+            }
+        };
+        // Split into connect and ping above; the lap keeps total() in step
+        timer.lap();
+        tracing::debug!(sandbox_id = %id, agent_connect_ms, ping_ms, "Agent ready");
 
-        tracing::debug!(sandbox_id = %id, path = %vsock_path.display(), "Connecting to agent");
-        let mut client = AgentClient::connect(vsock_path).await?;
-        tracing::debug!(sandbox_id = %id, "Agent connected");
+        if config.compression {
+            Self::enable_compression(id, &mut client).await;
+        }
+
+        let creation_timing = CreationTiming {
+            vm_create_ms,
+            agent_connect_ms,
+            ping_ms,
+            total_ms: timer.total(),
+        };
+        // 5. Name the guest after the sandbox so shells and logs are distinguishable
+        let hostname = id.short();
+        let hostname = match client.set_hostname(&hostname).await {
+            Ok(()) => Some(hostname),
+            Err(e) => {
+                tracing::warn!(sandbox_id = %id, error = %e, "Failed to set default hostname");
+                None
+            }
+        };
+        // 6. Make sure commands have a working directory to land in
+        if let Some(dir) = &config.work_dir {
+            let argv = ["mkdir".to_string(), "-p".to_string(), dir.clone()];
+            match client.exec_argv(&argv).await {
+                Ok(r) if r.success() => {}
+                Ok(r) => {
+                    tracing::warn!(sandbox_id = %id, work_dir = %dir, stderr = %r.stderr, "Failed to create work_dir")
+                }
+                Err(e) => {
+                    tracing::warn!(sandbox_id = %id, work_dir = %dir, error = %e, "Failed to create work_dir")
+                }
+            }
+        }
 
-        // 4. Verify agent is responsive
-        tracing::trace!(sandbox_id = %id, "Pinging agent");
-        client.ping().await?;
         tracing::info!(
             sandbox_id = %id,
-            elapsed_ms = start.elapsed().as_millis() as u64,
+            vm_create_ms,
+            agent_connect_ms,
+            ping_ms,
+            elapsed_ms = creation_timing.total_ms,
             "Sandbox ready"
         );
 
         Ok(Self {
             id,
             vm,
-            client: Arc::new(Mutex::new(client)),
+            connections: AgentConnections::new(
+                client,
+                vsock_path,
+                config.agent_connections,
+                config.compression,
+                config.client_config(),
+            ),
             config,
-            state: SandboxState::Ready,
+            state: watch::channel(SandboxState::Ready).0,
             created_at: Utc::now(),
+            creation_timing,
+            hostname: std::sync::RwLock::new(hostname),
+            os_info: std::sync::OnceLock::new(),
+            execs: Arc::default(),
+            last_used: std::sync::Mutex::new(Instant::now()),
+            drop_guard: DropGuard::new(id),
+        })
+    }
+
+    /// Tag the VM directory as this process's, so other host processes
+    /// sharing the chroot don't take its VM for an orphan.
+    fn claim_vm_dir(id: SandboxId, config: &SandboxConfig) {
+        let vm_dir = config.chroot_path.join(id.to_string());
+        if let Err(e) = crate::owner::claim(&vm_dir) {
+            tracing::warn!(sandbox_id = %id, error = %e, "Failed to tag VM directory with its owner");
+        }
+    }
+
+    /// Reconnect to a sandbox whose VM outlived the host process.
+    ///
+    /// The Firecracker process must still be running with its API and vsock
+    /// sockets under `config.chroot_path/<id>/`. A VM that Firecracker
+    /// reports as paused (say, the host died mid-hibernate) is resumed so its
+    /// agent can answer; then the agent connection is re-established.
+    pub(crate) async fn attach(id: SandboxId, config: SandboxConfig) -> Result<Self, CoreError> {
+        tracing::info!(sandbox_id = %id, "Reattaching sandbox");
+        let vm_dir = config.chroot_path.join(id.to_string());
+
+        let vsock_config =
+            bouvet_vm::VsockConfig::for_vm(config.vsock_cid, &config.chroot_path, &id.to_string());
+        let vm_config = Self::vm_config(&config, vsock_config);
+        let mut vm = bouvet_vm::VirtualMachine::attach(
+            id.as_uuid(),
+            vm_dir.join("firecracker.socket"),
+            vm_config,
+        )
+        .await?;
+        if vm.state() == bouvet_vm::VmState::Paused {
+            tracing::info!(sandbox_id = %id, "Resuming VM found paused");
+            vm.resume().await?;
+        }
+        Self::claim_vm_dir(id, &config);
+
+        let vsock_path = vm
+            .vsock_uds_path()
+            .map(|path| path.to_path_buf())
+            .ok_or_else(|| CoreError::Connection("vsock not configured".into()))?;
+        let mut client =
+            wait_for_agent_timed(&vsock_path, CONNECT_TIMEOUT, &config.client_config())
+                .await?
+                .client;
+        let hostname = client.hostname().await.ok();
+        if config.compression {
+            Self::enable_compression(id, &mut client).await;
+        }
+
+        // The VM directory is created at boot, so its mtime approximates creation time
+        let created_at = tokio::fs::metadata(&vm_dir)
+            .await
+            .and_then(|m| m.modified())
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+
+        tracing::info!(sandbox_id = %id, "Sandbox reattached");
+        Ok(Self {
+            id,
+            vm: Box::new(vm),
+            connections: AgentConnections::new(
+                client,
+                vsock_path,
+                config.agent_connections,
+                config.compression,
+                config.client_config(),
+            ),
+            config,
+            state: watch::channel(SandboxState::Ready).0,
+            created_at,
+            creation_timing: CreationTiming::default(),
+            hostname: std::sync::RwLock::new(hostname),
+            os_info: std::sync::OnceLock::new(),
+            execs: Arc::default(),
+            last_used: std::sync::Mutex::new(Instant::now()),
+            drop_guard: DropGuard::new(id),
+        })
+    }
+
+    /// Restore a hibernated sandbox from its snapshot.
+    ///
+    /// The VM resumes where it was paused, under the same ID and vsock CID;
+    /// the agent connection is re-established. The snapshot files are left
+    /// in place.
+    pub(crate) async fn restore(
+        handle: &SnapshotHandle,
+        firecracker_path: &Path,
+    ) -> Result<Self, CoreError> {
+        let id = handle.sandbox_id;
+        let config = handle.config.clone();
+        let mut timer = PhaseTimer::start();
+        tracing::info!(sandbox_id = %id, snapshot = %handle.snapshot_path.display(), "Restoring sandbox");
+
+        let vsock_config =
+            bouvet_vm::VsockConfig::for_vm(config.vsock_cid, &config.chroot_path, &id.to_string());
+        let mut vm_config = Self::vm_config(&config, vsock_config);
+        vm_config.firecracker_path = firecracker_path.to_path_buf();
+        let vm = match bouvet_vm::VirtualMachine::restore(
+            id.as_uuid(),
+            vm_config,
+            &handle.snapshot_path,
+            &handle.mem_file_path,
+        )
+        .await
+        {
+            Ok(vm) => vm,
+            Err(e) => {
+                tracing::error!(sandbox_id = %id, error = %e, "VM restore failed");
+                let _ = tokio::fs::remove_dir_all(config.chroot_path.join(id.to_string())).await;
+                return Err(e.into());
+            }
+        };
+        Self::claim_vm_dir(id, &config);
+        let vm_create_ms = timer.lap();
+
+        let vsock_path = vm
+            .vsock_uds_path()
+            .map(|path| path.to_path_buf())
+            .ok_or_else(|| CoreError::Connection("vsock not configured".into()))?;
+        let AgentReady {
+            mut client,
+            connect_ms: agent_connect_ms,
+            ping_ms,
+        } = match wait_for_agent_timed(&vsock_path, CONNECT_TIMEOUT, &config.client_config()).await
+        {
+            Ok(ready) => ready,
+            Err(e) => {
+                tracing::error!(sandbox_id = %id, error = %e, "Agent did not come back after restore");
+                let _ = vm.destroy().await;
+                let _ = tokio::fs::remove_dir_all(config.chroot_path.join(id.to_string())).await;
+                return Err(e);
+            }
+        };
+        timer.lap();
+        if config.compression {
+            Self::enable_compression(id, &mut client).await;
+        }
+        let hostname = client.hostname().await.ok();
+
+        let creation_timing = CreationTiming {
+            vm_create_ms,
+            agent_connect_ms,
+            ping_ms,
+            total_ms: timer.total(),
+        };
+        tracing::info!(sandbox_id = %id, elapsed_ms = creation_timing.total_ms, "Sandbox restored");
+        Ok(Self {
+            id,
+            vm: Box::new(vm),
+            connections: AgentConnections::new(
+                client,
+                vsock_path,
+                config.agent_connections,
+                config.compression,
+                config.client_config(),
+            ),
+            config,
+            state: watch::channel(SandboxState::Ready).0,
+            created_at: handle.created_at,
+            creation_timing,
+            hostname: std::sync::RwLock::new(hostname),
+            os_info: std::sync::OnceLock::new(),
+            execs: Arc::default(),
+            last_used: std::sync::Mutex::new(Instant::now()),
+            drop_guard: DropGuard::new(id),
         })
     }
 
+    /// Destroy a VM whose boot went over the config's `max_boot_ms`.
+    async fn abort_boot(
+        id: SandboxId,
+        vm: Box<dyn VmInstance>,
+        config: &SandboxConfig,
+        elapsed: Duration,
+    ) -> CoreError {
+        let max_boot_ms = config.max_boot_ms.unwrap_or_default();
+        let elapsed_ms = elapsed.as_millis() as u64;
+        tracing::warn!(sandbox_id = %id, max_boot_ms, elapsed_ms, "Boot budget exceeded, destroying VM");
+        Self::discard_vm(id, vm, config).await;
+        CoreError::BootBudgetExceeded {
+            max_boot_ms,
+            elapsed_ms,
+        }
+    }
+
+    /// Destroy a VM whose sandbox failed to come up and remove its
+    /// directory, so a retried create doesn't leave it running.
+    async fn discard_vm(id: SandboxId, vm: Box<dyn VmInstance>, config: &SandboxConfig) {
+        if let Err(e) = vm.destroy().await {
+            tracing::warn!(sandbox_id = %id, error = %e, "Failed to destroy VM after aborted boot");
+        }
+        let vsock_dir = config.chroot_path.join(id.to_string());
+        let _ = tokio::fs::remove_dir_all(&vsock_dir).await;
+    }
+
+    /// Opt in to compressed agent messages, falling back to plain JSON.
+    async fn enable_compression(id: SandboxId, client: &mut AgentClient) {
+        match client.enable_compression().await {
+            Ok(true) => tracing::debug!(sandbox_id = %id, "Agent message compression enabled"),
+            Ok(false) => {
+                tracing::debug!(sandbox_id = %id, "Agent does not support compression")
+            }
+            Err(e) => {
+                tracing::warn!(sandbox_id = %id, error = %e, "Failed to enable compression")
+            }
+        }
+    }
+
+    /// Build the VM configuration for a sandbox.
+    fn vm_config(
+        config: &SandboxConfig,
+        vsock_config: bouvet_vm::VsockConfig,
+    ) -> bouvet_vm::MachineConfig {
+        let mut builder = bouvet_vm::VmBuilder::new()
+            .vcpus(config.vcpu_count)
+            .memory_mib(config.memory_mib)
+            .kernel(&config.kernel_path)
+            .rootfs(&config.rootfs_path)
+            .chroot_path(&config.chroot_path)
+            .fast_boot(config.fast_boot)
+            .firecracker_log_level(config.firecracker_log_level)
+            .with_vsock_config(vsock_config);
+        if config.clean_tmp {
+            // Read by the agent at startup
+            builder = builder.boot_arg("BOUVET_CLEAN_TMP=1");
+        }
+        for arg in config.proxy.boot_args() {
+            builder = builder.boot_arg(arg);
+        }
+        if let Some(arg) = crate::config::mounts_boot_arg(&config.mounts) {
+            builder = builder.boot_arg(arg);
+        }
+        if let Some(scratch) = &config.scratch_drive {
+            builder = builder.with_drive(SCRATCH_DRIVE_ID, scratch);
+        }
+        builder.build_config()
+    }
+
     /// Get the sandbox ID.
     pub fn id(&self) -> SandboxId {
         self.id
@@ -182,7 +692,24 @@ impl Sandbox {
 
     /// Get the current state.
     pub fn state(&self) -> SandboxState {
-        self.state
+        *self.state.borrow()
+    }
+
+    /// Subscribe to state transitions.
+    ///
+    /// The receiver sees the current state immediately and every change
+    /// after it. Once the sandbox is destroyed the last value is
+    /// [`SandboxState::Destroyed`] and the channel closes.
+    pub fn watch_state(&self) -> watch::Receiver<SandboxState> {
+        self.state.subscribe()
+    }
+
+    /// Move to `state`, notifying subscribers.
+    fn set_state(&self, state: SandboxState) {
+        let previous = self.state.send_replace(state);
+        if previous != state {
+            tracing::debug!(sandbox_id = %self.id, from = %previous, to = %state, "Sandbox state changed");
+        }
     }
 
     /// Get the creation timestamp.
@@ -190,11 +717,60 @@ impl Sandbox {
         self.created_at
     }
 
+    /// Get the time spent in each phase of creation.
+    pub fn creation_timing(&self) -> CreationTiming {
+        self.creation_timing
+    }
+
+    /// Get the operations this sandbox is allowed to perform.
+    pub fn capabilities(&self) -> SandboxCapabilities {
+        self.config.capabilities
+    }
+
+    /// Get the guest hostname, if it has been set or read from the agent.
+    pub fn hostname(&self) -> Option<String> {
+        self.hostname.read().unwrap().clone()
+    }
+
+    /// Get the Firecracker process ID of the sandbox's VM, if known.
+    pub fn vm_pid(&self) -> Option<u32> {
+        self.vm.pid()
+    }
+
     /// Get the configuration used to create this sandbox.
     pub fn config(&self) -> &SandboxConfig {
         &self.config
     }
 
+    /// Get the labels the sandbox was created with.
+    pub fn labels(&self) -> &HashMap<String, String> {
+        &self.config.labels
+    }
+
+    /// Get the time since the last operation on the sandbox started.
+    pub fn idle_for(&self) -> Duration {
+        self.last_used.lock().unwrap().elapsed()
+    }
+
+    /// Check whether an operation is running, holding an agent connection.
+    pub fn is_busy(&self) -> bool {
+        self.connections.in_use() > 0
+    }
+
+    /// Cut short every operation on the sandbox, in flight or yet to start.
+    ///
+    /// They return [`CoreError::Cancelled`]; streamed commands are killed
+    /// in the guest first. Called on destroy, before the VM goes away.
+    pub(crate) fn cancel_operations(&self) {
+        tracing::debug!(sandbox_id = %self.id, in_use = self.connections.in_use(), "Cancelling sandbox operations");
+        self.connections.cancel();
+    }
+
+    /// Restart the idle clock, e.g. when a pooled sandbox is handed out.
+    pub(crate) fn mark_used(&self) {
+        *self.last_used.lock().unwrap() = Instant::now();
+    }
+
     /// Execute a shell command.
     ///
     /// # Arguments
@@ -205,80 +781,868 @@ impl Sandbox {
     ///
     /// The execution result including exit code, stdout, and stderr.
     pub async fn execute(&self, cmd: &str) -> Result<ExecResult, CoreError> {
-        tracing::debug!(sandbox_id = %self.id, cmd = %cmd, "Executing command");
+        self.execute_with(cmd, &ExecOptions::default()).await
+    }
+
+    /// Execute a shell command with per-call settings.
+    ///
+    /// The command runs in `options.cwd`, else the configured `work_dir`,
+    /// else the agent's own directory (`/`).
+    ///
+    /// # Arguments
+    ///
+    /// * `cmd` - Shell command to execute
+    /// * `options` - Settings for this command, such as its output budget
+    pub async fn execute_with(
+        &self,
+        cmd: &str,
+        options: &ExecOptions,
+    ) -> Result<ExecResult, CoreError> {
+        tracing::debug!(sandbox_id = %self.id, cmd_len = cmd.len(), "Executing command");
         self.ensure_ready()?;
-        let mut client = self.client.lock().await;
-        let result = client.exec(cmd).await;
+        self.config.capabilities.require(Capability::Exec)?;
+        let mut client = self.connections.get().await?;
+        let result = client.exec_with(cmd, &self.resolve_cwd(options)).await;
         if let Ok(ref r) = result {
             tracing::debug!(
                 sandbox_id = %self.id,
                 exit_code = r.exit_code,
                 stdout_len = r.stdout.len(),
                 stderr_len = r.stderr.len(),
+                output_limit_exceeded = r.output_limit_exceeded,
                 "Command completed"
             );
         }
         result
     }
 
-    /// Execute code in a specific language.
+    /// Execute a shell command with extra environment variables.
+    ///
+    /// The variables are set for this command only, on top of the guest's
+    /// default environment; later commands don't see them.
     ///
     /// # Arguments
     ///
-    /// * `lang` - Language identifier (python, python3, node, javascript, bash, sh)
-    /// * `code` - Code to execute
+    /// * `cmd` - Shell command to execute
+    /// * `env` - Variables to set, such as API keys
+    pub async fn execute_with_env(
+        &self,
+        cmd: &str,
+        env: HashMap<String, String>,
+    ) -> Result<ExecResult, CoreError> {
+        let options = ExecOptions {
+            env,
+            ..Default::default()
+        };
+        self.execute_with(cmd, &options).await
+    }
+
+    /// Execute a shell command, feeding it `stdin`.
+    ///
+    /// The input is written to the command's stdin, which is then closed,
+    /// for tools that read from a pipe (`jq`, `sort`, `python -`).
+    ///
+    /// # Arguments
+    ///
+    /// * `cmd` - Shell command to execute
+    /// * `stdin` - Input for the command
+    pub async fn execute_with_stdin(
+        &self,
+        cmd: &str,
+        stdin: impl Into<String>,
+    ) -> Result<ExecResult, CoreError> {
+        let options = ExecOptions {
+            stdin: Some(stdin.into()),
+            ..Default::default()
+        };
+        self.execute_with(cmd, &options).await
+    }
+
+    /// Execute a shell command, streaming its output while it runs.
+    ///
+    /// Output arrives in chunks as the command produces it, rather than all
+    /// at once when it exits; [`ExecStream::finish`] returns the exit code.
+    /// The agent connection is held until the command exits, so other
+    /// operations on this sandbox may wait for it. The command can be
+    /// stopped with [`cancel`](Self::cancel) and the stream's
+    /// [`id`](ExecStream::id).
+    ///
+    /// # Arguments
+    ///
+    /// * `cmd` - Shell command to execute
+    /// * `options` - Working directory, output budget, timeout, environment
+    ///   and stdin
+    pub async fn execute_stream(
+        &self,
+        cmd: &str,
+        options: &ExecOptions,
+    ) -> Result<ExecStream, CoreError> {
+        tracing::debug!(sandbox_id = %self.id, cmd_len = cmd.len(), "Streaming command");
+        self.ensure_ready()?;
+        self.config.capabilities.require(Capability::Exec)?;
+        let mut client = self.connections.get().await?;
+        let options = self.resolve_cwd(options);
+        let cmd = cmd.to_string();
+
+        let (tx, rx) = output_channel();
+        let (exec_id, signals, guard) = self.execs.register();
+        let sandbox_id = self.id;
+        let task = tokio::spawn(async move {
+            let _guard = guard;
+            let result = client.exec_stream(&cmd, &options, tx, Some(signals)).await;
+            match &result {
+                Ok(r) => tracing::debug!(
+                    sandbox_id = %sandbox_id,
+                    exit_code = r.exit_code,
+                    output_limit_exceeded = r.output_limit_exceeded,
+                    "Streamed command completed"
+                ),
+                Err(e) => {
+                    tracing::warn!(sandbox_id = %sandbox_id, error = %e, "Streamed command failed");
+                    client.discard();
+                }
+            }
+            result
+        });
+        Ok(ExecStream::new(exec_id, rx, task))
+    }
+
+    /// Execute a shell command and check its exit code.
+    ///
+    /// # Arguments
+    ///
+    /// * `cmd` - Shell command to execute
+    /// * `expected_exit` - Exit code the command must return
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CoreError::UnexpectedExit`], including the command's
+    /// stderr, if the exit code differs from `expected_exit`.
+    pub async fn execute_expect(
+        &self,
+        cmd: &str,
+        expected_exit: i32,
+    ) -> Result<ExecResult, CoreError> {
+        self.execute(cmd).await?.expect_exit(expected_exit)
+    }
+
+    /// Execute a program directly, without a shell.
+    ///
+    /// Unlike [`execute`](Self::execute), arguments are passed verbatim:
+    /// no quoting, globbing or variable expansion takes place.
+    ///
+    /// # Arguments
+    ///
+    /// * `argv` - Program followed by its arguments
     ///
     /// # Returns
     ///
     /// The execution result including exit code, stdout, and stderr.
-    pub async fn execute_code(&self, lang: &str, code: &str) -> Result<ExecResult, CoreError> {
-        tracing::debug!(sandbox_id = %self.id, lang = %lang, code_len = code.len(), "Executing code");
+    pub async fn execute_argv(&self, argv: &[String]) -> Result<ExecResult, CoreError> {
+        tracing::debug!(sandbox_id = %self.id, argc = argv.len(), "Executing argv");
         self.ensure_ready()?;
-        let mut client = self.client.lock().await;
-        let result = client.exec_code(lang, code).await;
+        self.config.capabilities.require(Capability::Exec)?;
+        let mut client = self.connections.get().await?;
+        let options = self.resolve_cwd(&ExecOptions::default());
+        let result = client.exec_argv_with(argv, &options).await;
         if let Ok(ref r) = result {
             tracing::debug!(
                 sandbox_id = %self.id,
                 exit_code = r.exit_code,
                 stdout_len = r.stdout.len(),
                 stderr_len = r.stderr.len(),
-                "Code execution completed"
+                "Argv completed"
             );
         }
         result
     }
 
-    /// Read a file from the guest filesystem.
+    /// Execute code in a specific language.
     ///
     /// # Arguments
     ///
-    /// * `path` - Absolute path to the file
+    /// * `lang` - Language identifier (python, python3, node, javascript, bash, sh, ruby, go, typescript, deno, rust)
+    /// * `code` - Code to execute
     ///
     /// # Returns
     ///
-    /// The file contents as a string.
-    pub async fn read_file(&self, path: &str) -> Result<String, CoreError> {
-        tracing::debug!(sandbox_id = %self.id, path = %path, "Reading file");
+    /// The execution result including exit code, stdout, and stderr.
+    pub async fn execute_code(&self, lang: &str, code: &str) -> Result<ExecResult, CoreError> {
+        self.execute_code_with(lang, code, &ExecOptions::default())
+            .await
+    }
+
+    /// Execute code in a specific language with per-call settings.
+    ///
+    /// The code runs in the same directory [`execute_with`](Self::execute_with)
+    /// would pick.
+    ///
+    /// # Arguments
+    ///
+    /// * `lang` - Language identifier (python, python3, node, javascript, bash, sh, ruby, go, typescript, deno, rust)
+    /// * `code` - Code to execute
+    /// * `options` - Settings for this run, such as its output budget
+    pub async fn execute_code_with(
+        &self,
+        lang: &str,
+        code: &str,
+        options: &ExecOptions,
+    ) -> Result<ExecResult, CoreError> {
+        tracing::debug!(sandbox_id = %self.id, lang = %lang, code_len = code.len(), "Executing code");
         self.ensure_ready()?;
-        let mut client = self.client.lock().await;
-        let result = client.read_file(path).await;
-        if let Ok(ref content) = result {
-            tracing::trace!(sandbox_id = %self.id, size = content.len(), "File read");
+        self.config.capabilities.require(Capability::Exec)?;
+        let mut client = self.connections.get().await?;
+        let result = client
+            .exec_code_with(lang, code, &self.resolve_cwd(options))
+            .await;
+        if let Ok(ref r) = result {
+            tracing::debug!(
+                sandbox_id = %self.id,
+                exit_code = r.exit_code,
+                stdout_len = r.stdout.len(),
+                stderr_len = r.stderr.len(),
+                output_limit_exceeded = r.output_limit_exceeded,
+                "Code execution completed"
+            );
         }
         result
     }
 
-    /// Write a file to the guest filesystem.
+    /// Execute code with extra environment variables.
+    ///
+    /// Like [`execute_with_env`](Self::execute_with_env), the variables
+    /// apply to this run only.
+    ///
+    /// # Arguments
+    ///
+    /// * `lang` - Language identifier (python, python3, node, javascript, bash, sh, ruby, go, typescript, deno, rust)
+    /// * `code` - Code to execute
+    /// * `env` - Variables to set, such as API keys
+    pub async fn execute_code_with_env(
+        &self,
+        lang: &str,
+        code: &str,
+        env: HashMap<String, String>,
+    ) -> Result<ExecResult, CoreError> {
+        let options = ExecOptions {
+            env,
+            ..Default::default()
+        };
+        self.execute_code_with(lang, code, &options).await
+    }
+
+    /// Execute code in a specific language, streaming its output while it
+    /// runs.
+    ///
+    /// Like [`execute_stream`](Self::execute_stream), the agent connection
+    /// is held until the code exits. Needs an agent speaking protocol 1.23.
+    ///
+    /// # Arguments
+    ///
+    /// * `lang` - Language identifier (python, python3, node, javascript, bash, sh, ruby, go, typescript, deno, rust)
+    /// * `code` - Code to execute
+    /// * `options` - Working directory, output budget, timeout, environment
+    ///   and stdin
+    pub async fn execute_code_stream(
+        &self,
+        lang: &str,
+        code: &str,
+        options: &ExecOptions,
+    ) -> Result<ExecStream, CoreError> {
+        tracing::debug!(sandbox_id = %self.id, lang = %lang, code_len = code.len(), "Streaming code");
+        self.ensure_ready()?;
+        self.config.capabilities.require(Capability::Exec)?;
+        let mut client = self.connections.get().await?;
+        let options = self.resolve_cwd(options);
+        let (lang, code) = (lang.to_string(), code.to_string());
+
+        let (tx, rx) = output_channel();
+        let (exec_id, signals, guard) = self.execs.register();
+        let sandbox_id = self.id;
+        let task = tokio::spawn(async move {
+            let _guard = guard;
+            let result = client
+                .exec_code_stream(&lang, &code, &options, tx, Some(signals))
+                .await;
+            match &result {
+                Ok(r) => tracing::debug!(
+                    sandbox_id = %sandbox_id,
+                    exit_code = r.exit_code,
+                    output_limit_exceeded = r.output_limit_exceeded,
+                    "Streamed code completed"
+                ),
+                Err(e) => {
+                    tracing::warn!(sandbox_id = %sandbox_id, error = %e, "Streamed code failed");
+                    client.discard();
+                }
+            }
+            result
+        });
+        Ok(ExecStream::new(exec_id, rx, task))
+    }
+
+    /// Send `signal` to a command started by
+    /// [`execute_stream`](Self::execute_stream) or
+    /// [`execute_code_stream`](Self::execute_code_stream).
+    ///
+    /// The signal reaches the command's whole process group. It is sent
+    /// asynchronously: the stream's result, once the command exits, has
+    /// exit code -1 and a note on stderr. Needs an agent speaking protocol
+    /// 1.25.
+    ///
+    /// # Returns
+    ///
+    /// `false` if no command with `exec_id` is running.
+    pub fn cancel(&self, exec_id: u64, signal: Signal) -> bool {
+        tracing::debug!(sandbox_id = %self.id, exec_id, ?signal, "Cancelling streamed command");
+        self.execs.signal(exec_id, signal)
+    }
+
+    /// IDs of the streamed commands still running, oldest first.
+    pub fn running_execs(&self) -> Vec<u64> {
+        self.execs.ids()
+    }
+
+    /// Execute a multi-file program.
+    ///
+    /// The agent writes `files` to a temporary directory, runs `entry` with
+    /// the language's interpreter from inside it (`go run .` for Go), and
+    /// removes the directory afterwards, so the entry point can import
+    /// sibling files. With a `work_dir` configured, that directory is
+    /// created inside it.
+    ///
+    /// # Arguments
+    ///
+    /// * `lang` - Language identifier (python, python3, node, javascript, bash, sh, go)
+    /// * `entry` - Relative path of the entry point; must be a key of `files`
+    /// * `files` - Relative path to content of every project file
+    ///
+    /// # Returns
+    ///
+    /// The execution result including exit code, stdout, and stderr.
+    pub async fn exec_project(
+        &self,
+        lang: &str,
+        entry: &str,
+        files: &HashMap<String, String>,
+    ) -> Result<ExecResult, CoreError> {
+        self.exec_project_in(lang, entry, files, None).await
+    }
+
+    /// Execute a multi-file program with its project directory created
+    /// inside `cwd`, falling back to the configured `work_dir`.
+    pub async fn exec_project_in(
+        &self,
+        lang: &str,
+        entry: &str,
+        files: &HashMap<String, String>,
+        cwd: Option<&str>,
+    ) -> Result<ExecResult, CoreError> {
+        let options = ExecOptions {
+            cwd: cwd.map(str::to_string),
+            ..Default::default()
+        };
+        self.exec_project_with(lang, entry, files, &options).await
+    }
+
+    /// Execute a multi-file program with per-call settings.
+    ///
+    /// The project directory is created inside `options.cwd`, falling back
+    /// to the configured `work_dir`. Needs an agent speaking protocol 1.27
+    /// for anything but `cwd`.
+    ///
+    /// # Arguments
+    ///
+    /// * `lang` - Language identifier (python, python3, node, javascript, bash, sh, go)
+    /// * `entry` - Relative path of the entry point; must be a key of `files`
+    /// * `files` - Relative path to content of every project file
+    /// * `options` - Output budget, timeout, environment, stdin and usage
+    pub async fn exec_project_with(
+        &self,
+        lang: &str,
+        entry: &str,
+        files: &HashMap<String, String>,
+        options: &ExecOptions,
+    ) -> Result<ExecResult, CoreError> {
+        tracing::debug!(sandbox_id = %self.id, lang = %lang, entry = %entry, files = files.len(), "Executing project");
+        self.ensure_ready()?;
+        self.config.capabilities.require(Capability::Exec)?;
+        let mut client = self.connections.get().await?;
+        let result = client
+            .exec_project_with(lang, entry, files, &self.resolve_cwd(options))
+            .await;
+        if let Ok(ref r) = result {
+            tracing::debug!(
+                sandbox_id = %self.id,
+                exit_code = r.exit_code,
+                stdout_len = r.stdout.len(),
+                stderr_len = r.stderr.len(),
+                output_limit_exceeded = r.output_limit_exceeded,
+                "Project execution completed"
+            );
+        }
+        result
+    }
+
+    /// Read a file from the guest filesystem.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Absolute path to the file
+    ///
+    /// # Returns
+    ///
+    /// The file contents as a string.
+    pub async fn read_file(&self, path: &str) -> Result<String, CoreError> {
+        tracing::debug!(sandbox_id = %self.id, path = %path, "Reading file");
+        self.ensure_ready()?;
+        let mut client = self.connections.get().await?;
+        let result = client.read_file(path).await;
+        if let Ok(ref content) = result {
+            tracing::trace!(sandbox_id = %self.id, size = content.len(), "File read");
+        }
+        result
+    }
+
+    /// Read a file's raw bytes, base64-encoded with their size and SHA-256.
+    ///
+    /// Use this for binary files, which [`read_file`](Self::read_file)
+    /// can't return intact. The agent's 10MB read limit applies.
+    pub async fn read_file_base64(&self, path: &str) -> Result<FileDownload, CoreError> {
+        tracing::debug!(sandbox_id = %self.id, path = %path, "Downloading file");
+        self.ensure_ready()?;
+        let mut client = self.connections.get().await?;
+        let download = client.download_file(path).await?;
+        tracing::trace!(sandbox_id = %self.id, size = download.size, "File downloaded");
+        Ok(download)
+    }
+
+    /// Write a file to the guest filesystem.
     ///
     /// # Arguments
     ///
     /// * `path` - Absolute path to the file
     /// * `content` - Content to write
     pub async fn write_file(&self, path: &str, content: &str) -> Result<(), CoreError> {
-        tracing::debug!(sandbox_id = %self.id, path = %path, content_len = content.len(), "Writing file");
+        self.write_file_with_mode(path, content, None).await
+    }
+
+    /// Write a file to the guest filesystem and set its permission bits.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Absolute path to the file
+    /// * `content` - Content to write
+    /// * `mode` - Permission bits, e.g. `0o755`; `None` leaves them unchanged
+    pub async fn write_file_with_mode(
+        &self,
+        path: &str,
+        content: &str,
+        mode: Option<u32>,
+    ) -> Result<(), CoreError> {
+        tracing::debug!(sandbox_id = %self.id, path = %path, content_len = content.len(), mode = ?mode, "Writing file");
         self.ensure_ready()?;
-        let mut client = self.client.lock().await;
-        client.write_file(path, content).await
+        self.config.capabilities.require(Capability::Write)?;
+        let mut client = self.connections.get().await?;
+        client.write_file_with_mode(path, content, mode).await
+    }
+
+    /// Create an empty file or update its modification time.
+    ///
+    /// Existing files keep their content. Parent directories are created
+    /// if they don't exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Absolute path to the file
+    pub async fn touch(&self, path: &str) -> Result<(), CoreError> {
+        tracing::debug!(sandbox_id = %self.id, path = %path, "Touching file");
+        self.ensure_ready()?;
+        self.config.capabilities.require(Capability::Write)?;
+        let mut client = self.connections.get().await?;
+        client.touch(path).await
+    }
+
+    /// Get a path's type, size, permission bits and modification time,
+    /// without reading its contents.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Absolute path; symlinks are described, not followed
+    pub async fn stat(&self, path: &str) -> Result<FileStat, CoreError> {
+        tracing::debug!(sandbox_id = %self.id, path = %path, "Stat");
+        self.ensure_ready()?;
+        let mut client = self.connections.get().await?;
+        client.stat(path).await
+    }
+
+    /// Delete a file or directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Absolute path to delete
+    /// * `recursive` - Also delete non-empty directories and their contents
+    pub async fn delete_file(&self, path: &str, recursive: bool) -> Result<(), CoreError> {
+        tracing::debug!(sandbox_id = %self.id, path = %path, recursive, "Deleting path");
+        self.ensure_ready()?;
+        self.config.capabilities.require(Capability::Write)?;
+        let mut client = self.connections.get().await?;
+        client.delete_file(path, recursive).await
+    }
+
+    /// Rename or move a file or directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - Absolute path to move
+    /// * `to` - New absolute path; an existing file there is replaced
+    pub async fn rename(&self, from: &str, to: &str) -> Result<(), CoreError> {
+        tracing::debug!(sandbox_id = %self.id, from = %from, to = %to, "Renaming path");
+        self.ensure_ready()?;
+        self.config.capabilities.require(Capability::Write)?;
+        let mut client = self.connections.get().await?;
+        client.rename(from, to).await
+    }
+
+    /// Create a directory and any missing parents.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Absolute path of the directory
+    pub async fn mkdir(&self, path: &str) -> Result<(), CoreError> {
+        tracing::debug!(sandbox_id = %self.id, path = %path, "Creating directory");
+        self.ensure_ready()?;
+        self.config.capabilities.require(Capability::Write)?;
+        let mut client = self.connections.get().await?;
+        client.mkdir(path).await
+    }
+
+    /// Start a resumable upload of a `total_size`-byte file to `path`.
+    ///
+    /// Send the data with [`upload_chunk`](Self::upload_chunk) and complete
+    /// it with [`upload_finish`](Self::upload_finish), which checks the
+    /// file against `sha256` (hex). Chunks go to `<path>.part` until then.
+    pub async fn upload_begin(
+        &self,
+        path: &str,
+        total_size: u64,
+        sha256: &str,
+    ) -> Result<UploadStatus, CoreError> {
+        tracing::debug!(sandbox_id = %self.id, path = %path, total_size, "Beginning upload");
+        self.ensure_ready()?;
+        self.config.capabilities.require(Capability::Write)?;
+        let mut client = self.connections.get().await?;
+        client.upload_begin(path, total_size, sha256).await
+    }
+
+    /// Write a chunk of an upload at its next expected offset.
+    pub async fn upload_chunk(
+        &self,
+        upload_id: &str,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<UploadStatus, CoreError> {
+        self.ensure_ready()?;
+        self.config.capabilities.require(Capability::Write)?;
+        let mut client = self.connections.get().await?;
+        client.upload_chunk(upload_id, offset, data).await
+    }
+
+    /// Get an upload's progress; `next_offset` is where to resume.
+    pub async fn upload_status(&self, upload_id: &str) -> Result<UploadStatus, CoreError> {
+        self.ensure_ready()?;
+        let mut client = self.connections.get().await?;
+        client.upload_status(upload_id).await
+    }
+
+    /// Verify an upload's checksum and move the file into place.
+    pub async fn upload_finish(&self, upload_id: &str) -> Result<UploadFinish, CoreError> {
+        tracing::debug!(sandbox_id = %self.id, upload_id = %upload_id, "Finishing upload");
+        self.ensure_ready()?;
+        self.config.capabilities.require(Capability::Write)?;
+        let mut client = self.connections.get().await?;
+        client.upload_finish(upload_id).await
+    }
+
+    /// Copy a host file into the guest, in [`TRANSFER_CHUNK_SIZE`] chunks.
+    ///
+    /// Not limited by the agent's 10MB message cap, so datasets and models
+    /// can be copied. `progress` is called after each chunk with the bytes
+    /// sent so far and the file's total size. The guest file is replaced
+    /// from the first chunk on; a failed upload leaves it partly written.
+    /// Use [`upload_begin`](Self::upload_begin) instead when the file must
+    /// only appear once verified. Returns the number of bytes copied.
+    ///
+    /// # Arguments
+    ///
+    /// * `host_path` - File to read on the host
+    /// * `guest_path` - Absolute path to write in the guest
+    /// * `progress` - Called with `(bytes_sent, total_bytes)`
+    pub async fn upload_file(
+        &self,
+        host_path: impl AsRef<Path>,
+        guest_path: &str,
+        mut progress: impl FnMut(u64, u64) + Send,
+    ) -> Result<u64, CoreError> {
+        let host_path = host_path.as_ref();
+        tracing::debug!(sandbox_id = %self.id, host_path = %host_path.display(), guest_path = %guest_path, "Uploading file");
+        self.ensure_ready()?;
+        self.config.capabilities.require(Capability::Write)?;
+
+        let host_error = |e: std::io::Error| {
+            CoreError::Io(std::io::Error::new(
+                e.kind(),
+                format!("failed to read '{}': {e}", host_path.display()),
+            ))
+        };
+        let mut file = tokio::fs::File::open(host_path).await.map_err(host_error)?;
+        let total = file.metadata().await.map_err(host_error)?.len();
+
+        let mut client = self.connections.get().await?;
+        let mut chunk = Vec::with_capacity(TRANSFER_CHUNK_SIZE);
+        let mut offset = 0;
+        loop {
+            chunk.clear();
+            (&mut file)
+                .take(TRANSFER_CHUNK_SIZE as u64)
+                .read_to_end(&mut chunk)
+                .await
+                .map_err(host_error)?;
+            // A short chunk means end of file, even if it shrank meanwhile
+            let is_last = chunk.len() < TRANSFER_CHUNK_SIZE || offset + chunk.len() as u64 >= total;
+            offset = client
+                .write_file_chunk(guest_path, offset, &chunk, is_last)
+                .await?;
+            progress(offset, total.max(offset));
+            if is_last {
+                break;
+            }
+        }
+
+        tracing::debug!(sandbox_id = %self.id, size = offset, "File uploaded");
+        Ok(offset)
+    }
+
+    /// Copy a guest file to the host, in [`TRANSFER_CHUNK_SIZE`] chunks.
+    ///
+    /// The counterpart of [`upload_file`](Self::upload_file): `progress` is
+    /// called after each chunk with the bytes received so far and the guest
+    /// file's size. The host file is created or truncated. Returns the
+    /// number of bytes copied.
+    ///
+    /// # Arguments
+    ///
+    /// * `guest_path` - Absolute path to read in the guest
+    /// * `host_path` - File to write on the host
+    /// * `progress` - Called with `(bytes_received, total_bytes)`
+    pub async fn download_file(
+        &self,
+        guest_path: &str,
+        host_path: impl AsRef<Path>,
+        mut progress: impl FnMut(u64, u64) + Send,
+    ) -> Result<u64, CoreError> {
+        let host_path = host_path.as_ref();
+        tracing::debug!(sandbox_id = %self.id, guest_path = %guest_path, host_path = %host_path.display(), "Downloading file");
+        self.ensure_ready()?;
+
+        let host_error = |e: std::io::Error| {
+            CoreError::Io(std::io::Error::new(
+                e.kind(),
+                format!("failed to write '{}': {e}", host_path.display()),
+            ))
+        };
+        let mut client = self.connections.get().await?;
+        let mut file = tokio::fs::File::create(host_path)
+            .await
+            .map_err(host_error)?;
+        let mut offset = 0;
+        loop {
+            let chunk = client
+                .read_file_chunk(guest_path, offset, TRANSFER_CHUNK_SIZE as u64)
+                .await?;
+            file.write_all(&chunk.data).await.map_err(host_error)?;
+            offset += chunk.data.len() as u64;
+            progress(offset, chunk.size.max(offset));
+            // An empty chunk also ends the loop if the file shrank meanwhile
+            if chunk.eof || chunk.data.is_empty() {
+                break;
+            }
+        }
+        file.flush().await.map_err(host_error)?;
+
+        tracing::debug!(sandbox_id = %self.id, size = offset, "File downloaded");
+        Ok(offset)
+    }
+
+    /// Archive a guest directory as a `.tar.gz` and copy it to the host.
+    ///
+    /// `tar` runs in the guest, writing to a temporary file in `/tmp`,
+    /// which is copied in [`TRANSFER_CHUNK_SIZE`] chunks and then removed.
+    /// [`EXPORT_SKIP_DIRS`] inside `dir` are left out, and a `dir` inside
+    /// one of them is refused. The archive is checked against `max_bytes`
+    /// before anything is copied.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Guest directory to archive; `None` for the configured `work_dir`
+    /// * `host_path` - File to write the archive to on the host
+    /// * `max_bytes` - Largest archive to copy
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CoreError::NoWorkDir`] if `dir` is `None` and no
+    /// `work_dir` is configured, [`CoreError::ArchiveTooLarge`] if the
+    /// archive is over `max_bytes`, and [`CoreError::UnexpectedExit`] if
+    /// `tar` fails.
+    pub async fn export_workspace(
+        &self,
+        dir: Option<&str>,
+        host_path: impl AsRef<Path>,
+        max_bytes: u64,
+    ) -> Result<WorkspaceExport, CoreError> {
+        let dir = dir
+            .or(self.config.work_dir.as_deref())
+            .ok_or(CoreError::NoWorkDir)?
+            .to_string();
+        tracing::debug!(sandbox_id = %self.id, dir = %dir, max_bytes, "Exporting directory");
+        self.ensure_ready()?;
+        self.config.capabilities.require(Capability::Exec)?;
+        if !dir.starts_with('/') {
+            return Err(CoreError::Forbidden(format!(
+                "export directory must be absolute: {dir}"
+            )));
+        }
+        if let Some(skip) = EXPORT_SKIP_DIRS
+            .iter()
+            .find(|skip| Path::new(&dir).starts_with(skip))
+        {
+            return Err(CoreError::Forbidden(format!(
+                "cannot export {dir}: {skip} is a system directory"
+            )));
+        }
+
+        let archive = format!("/tmp/bouvet-export-{}.tar.gz", Uuid::new_v4());
+        let mut argv: Vec<String> = ["sh", "-c", EXPORT_SCRIPT, "sh", &archive, &dir]
+            .map(String::from)
+            .into();
+        // Patterns are relative to `dir`, like the archive's member names
+        argv.extend(EXPORT_SKIP_DIRS.iter().filter_map(|skip| {
+            let rest = Path::new(skip).strip_prefix(&dir).ok()?;
+            Some(format!("--exclude=./{}", rest.display()))
+        }));
+        argv.push(format!("--exclude={}", &archive["/tmp/".len()..]));
+
+        let result = self
+            .copy_archive(&argv, &archive, host_path.as_ref(), max_bytes)
+            .await;
+        let mut client = self.connections.get().await?;
+        let remove = ["rm", "-f", &archive].map(String::from);
+        if let Err(e) = client
+            .exec_argv_with(&remove, &ExecOptions::default())
+            .await
+        {
+            tracing::warn!(sandbox_id = %self.id, archive = %archive, error = %e, "Failed to remove export archive");
+        }
+
+        let (size, sha256) = result?;
+        tracing::debug!(sandbox_id = %self.id, dir = %dir, size, "Directory exported");
+        Ok(WorkspaceExport { dir, size, sha256 })
+    }
+
+    /// Run the export script and copy the archive it wrote to `host_path`,
+    /// returning the archive's size and SHA-256.
+    async fn copy_archive(
+        &self,
+        argv: &[String],
+        archive: &str,
+        host_path: &Path,
+        max_bytes: u64,
+    ) -> Result<(u64, String), CoreError> {
+        let out = {
+            let mut client = self.connections.get().await?;
+            client.exec_argv_with(argv, &ExecOptions::default()).await?
+        };
+        let out = out.expect_exit(0)?;
+        let mut lines = out.stdout.lines();
+        let size = lines
+            .next()
+            .and_then(|line| line.trim().parse::<u64>().ok());
+        let sha256 = lines.next().and_then(|line| line.split_whitespace().next());
+        let (Some(size), Some(sha256)) = (size, sha256) else {
+            return Err(CoreError::Connection(format!(
+                "unexpected export output: {:?}",
+                out.stdout
+            )));
+        };
+        if size > max_bytes {
+            return Err(CoreError::ArchiveTooLarge {
+                size,
+                limit: max_bytes,
+            });
+        }
+        self.download_file(archive, host_path, |_, _| {}).await?;
+        Ok((size, sha256.to_string()))
+    }
+
+    /// Check whether `host:port` is reachable over TCP from inside the guest.
+    ///
+    /// Without the network capability no connection is attempted and the
+    /// result reports "blocked by policy".
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - Hostname or IP address
+    /// * `port` - TCP port
+    /// * `timeout` - Connect timeout (at most 30 seconds)
+    pub async fn check_connectivity(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: std::time::Duration,
+    ) -> Result<ConnectivityResult, CoreError> {
+        tracing::debug!(sandbox_id = %self.id, host = %host, port, "Checking connectivity");
+        self.ensure_ready()?;
+        if !self.config.capabilities.allows(Capability::Network) {
+            tracing::debug!(sandbox_id = %self.id, host = %host, "Connectivity blocked by policy");
+            return Ok(ConnectivityResult::blocked_by_policy());
+        }
+        let mut client = self.connections.get().await?;
+        client.check_connectivity(host, port, timeout).await
+    }
+
+    /// Remove everything in the guest's `/tmp`, leaving it empty.
+    ///
+    /// # Returns
+    ///
+    /// The number of top-level entries removed.
+    pub async fn clear_tmp(&self) -> Result<u64, CoreError> {
+        tracing::debug!(sandbox_id = %self.id, "Clearing /tmp");
+        self.ensure_ready()?;
+        self.config.capabilities.require(Capability::Write)?;
+        let mut client = self.connections.get().await?;
+        client.clear_tmp().await
+    }
+
+    /// Grow the scratch drive to `new_size_mib` MiB, filesystem included.
+    ///
+    /// The backing image is extended on the host, then the guest runs
+    /// `resize2fs` on [`SCRATCH_DEVICE`]. Drives only grow.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CoreError::NoScratchDrive`] if the sandbox was created
+    /// without [`SandboxConfig::scratch_drive`], and a VM error if
+    /// `new_size_mib` is not larger than the drive.
+    pub async fn resize_scratch(&self, new_size_mib: u64) -> Result<(), CoreError> {
+        tracing::debug!(sandbox_id = %self.id, new_size_mib, "Resizing scratch drive");
+        self.ensure_ready()?;
+        self.config.capabilities.require(Capability::Write)?;
+        if self.config.scratch_drive.is_none() {
+            return Err(CoreError::NoScratchDrive);
+        }
+        self.vm.resize_drive(SCRATCH_DRIVE_ID, new_size_mib).await?;
+        let mut client = self.connections.get().await?;
+        client.resize_fs(SCRATCH_DEVICE).await
     }
 
     /// List directory contents.
@@ -286,35 +1650,137 @@ impl Sandbox {
     /// # Arguments
     ///
     /// * `path` - Absolute path to the directory
+    /// * `options` - Sort order and name filter
     ///
     /// # Returns
     ///
     /// A list of file entries in the directory.
-    pub async fn list_dir(&self, path: &str) -> Result<Vec<FileEntry>, CoreError> {
+    pub async fn list_dir(
+        &self,
+        path: &str,
+        options: &ListDirOptions,
+    ) -> Result<Vec<FileEntry>, CoreError> {
         tracing::debug!(sandbox_id = %self.id, path = %path, "Listing directory");
         self.ensure_ready()?;
-        let mut client = self.client.lock().await;
-        let result = client.list_dir(path).await;
+        let mut client = self.connections.get().await?;
+        let result = client.list_dir(path, options).await;
         if let Ok(ref entries) = result {
             tracing::trace!(sandbox_id = %self.id, count = entries.len(), "Directory listed");
         }
         result
     }
 
+    /// Read the last `lines` lines of a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Absolute path to the file
+    /// * `lines` - Number of lines (at most 10,000)
+    pub async fn tail_lines(&self, path: &str, lines: usize) -> Result<Vec<String>, CoreError> {
+        tracing::debug!(sandbox_id = %self.id, path = %path, lines, "Tailing file");
+        self.ensure_ready()?;
+        let mut client = self.connections.get().await?;
+        client.tail(path, lines).await
+    }
+
+    /// Stream the last `lines` lines of a file, optionally following it.
+    ///
+    /// Without `follow`, the stream yields the last lines and ends. With
+    /// `follow`, lines appended to the file keep arriving until the stream
+    /// is cancelled or dropped. The agent connection is held for the whole
+    /// follow, so other operations on this sandbox wait until it ends.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Absolute path to the file
+    /// * `lines` - Number of trailing lines to start with (at most 10,000)
+    /// * `follow` - Keep streaming appended lines
+    pub async fn tail(
+        &self,
+        path: &str,
+        lines: usize,
+        follow: bool,
+    ) -> Result<TailStream, CoreError> {
+        if !follow {
+            return self
+                .tail_lines(path, lines)
+                .await
+                .map(TailStream::from_lines);
+        }
+
+        tracing::debug!(sandbox_id = %self.id, path = %path, lines, "Following file");
+        self.ensure_ready()?;
+        let mut client = self.connections.get().await?;
+        let (tail_id, initial) = client.start_tail(path, lines).await?;
+
+        let (tx, rx) = tokio::sync::mpsc::channel(OUTPUT_CHANNEL_CAPACITY);
+        let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel::<()>();
+        let sandbox_id = self.id;
+        let task = tokio::spawn(async move {
+            let cancel = async {
+                let _ = cancel_rx.await;
+            };
+            let result = client.follow_tail(tail_id, initial, tx, cancel).await;
+            if let Err(e) = &result {
+                tracing::warn!(sandbox_id = %sandbox_id, tail_id, error = %e, "Tail ended with error");
+                client.discard();
+            }
+            result
+        });
+        Ok(TailStream::following(rx, cancel_tx, task))
+    }
+
+    /// Set the guest hostname.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - New hostname; must be a valid RFC 1123 hostname
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CoreError::CapabilityDisabled`] if writes are disabled.
+    pub async fn set_hostname(&self, name: &str) -> Result<(), CoreError> {
+        tracing::debug!(sandbox_id = %self.id, hostname = %name, "Setting hostname");
+        self.ensure_ready()?;
+        self.config.capabilities.require(Capability::Write)?;
+        let mut client = self.connections.get().await?;
+        client.set_hostname(name).await?;
+        *self.hostname.write().unwrap() = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Get the guest's distribution identifiers from `/etc/os-release`.
+    ///
+    /// Fetched from the agent on first call and cached for the lifetime of
+    /// the sandbox, since the guest OS doesn't change.
+    pub async fn os_info(&self) -> Result<OsInfo, CoreError> {
+        if let Some(info) = self.os_info.get() {
+            return Ok(info.clone());
+        }
+        tracing::debug!(sandbox_id = %self.id, "Reading guest os-release");
+        self.ensure_ready()?;
+        let info = self.connections.get().await?.os_info().await?;
+        Ok(self.os_info.get_or_init(|| info).clone())
+    }
+
     /// Check if the sandbox is healthy and responsive.
     ///
     /// This pings the agent to verify it's still running and responsive.
     /// Returns true if the agent responds, false otherwise.
     pub async fn is_healthy(&self) -> bool {
-        if self.state != SandboxState::Ready {
-            tracing::trace!(sandbox_id = %self.id, state = ?self.state, "Health check: not ready");
+        if self.state() != SandboxState::Ready {
+            tracing::trace!(sandbox_id = %self.id, state = ?self.state(), "Health check: not ready");
             return false;
         }
-        let mut client = match self.client.try_lock() {
-            Ok(c) => c,
-            Err(_) => {
-                tracing::trace!(sandbox_id = %self.id, "Health check: client busy, assuming healthy");
-                return true; // Client busy = still working
+        let mut client = match self.connections.try_get().await {
+            Some(Ok(c)) => c,
+            Some(Err(e)) => {
+                tracing::trace!(sandbox_id = %self.id, error = %e, "Health check: connect failed");
+                return false;
+            }
+            None => {
+                tracing::trace!(sandbox_id = %self.id, "Health check: all connections busy, assuming healthy");
+                return true; // Connections busy = still working
             }
         };
         let healthy = client.ping().await.is_ok();
@@ -322,13 +1788,47 @@ impl Sandbox {
         healthy
     }
 
+    /// Pause the VM and write a full snapshot under `snapshot_dir/<id>/`.
+    ///
+    /// The VM is left paused; the caller destroys it once the handle is
+    /// recorded. If the snapshot fails the VM is resumed.
+    pub(crate) async fn snapshot(
+        &mut self,
+        snapshot_dir: &Path,
+    ) -> Result<SnapshotHandle, CoreError> {
+        self.ensure_ready()?;
+        let handle =
+            SnapshotHandle::new(snapshot_dir, self.id, self.config.clone(), self.created_at);
+        tracing::info!(sandbox_id = %self.id, dir = %handle.dir().display(), "Snapshotting sandbox");
+        tokio::fs::create_dir_all(handle.dir()).await.map_err(|e| {
+            CoreError::Connection(format!("failed to create snapshot directory: {e}"))
+        })?;
+
+        self.vm.pause().await?;
+        if let Err(e) = self
+            .vm
+            .snapshot(&handle.snapshot_path, &handle.mem_file_path)
+            .await
+        {
+            tracing::error!(sandbox_id = %self.id, error = %e, "Snapshot failed, resuming VM");
+            if let Err(e) = self.vm.resume().await {
+                tracing::error!(sandbox_id = %self.id, error = %e, "Failed to resume VM");
+            }
+            let _ = tokio::fs::remove_dir_all(handle.dir()).await;
+            return Err(e.into());
+        }
+        Ok(handle)
+    }
+
     /// Destroy the sandbox.
     ///
     /// This stops the VM and releases all resources.
     pub async fn destroy(mut self) -> Result<(), CoreError> {
         let start = std::time::Instant::now();
         tracing::info!(sandbox_id = %self.id, "Destroying sandbox");
-        self.state = SandboxState::Destroyed;
+        self.set_state(SandboxState::Destroyed);
+        self.drop_guard.disarm();
+        self.cancel_operations();
 
         tracing::debug!(sandbox_id = %self.id, "Stopping VM");
         self.vm.destroy().await?;
@@ -348,14 +1848,25 @@ impl Sandbox {
         Ok(())
     }
 
-    /// Ensure the sandbox is in the Ready state.
+    /// Fill in the configured `work_dir` unless the call names a directory.
+    fn resolve_cwd(&self, options: &ExecOptions) -> ExecOptions {
+        ExecOptions {
+            cwd: options.cwd.clone().or_else(|| self.config.work_dir.clone()),
+            ..options.clone()
+        }
+    }
+
+    /// Ensure the sandbox is in the Ready state, and mark it used.
     fn ensure_ready(&self) -> Result<(), CoreError> {
-        if self.state != SandboxState::Ready {
+        let state = self.state();
+        if state != SandboxState::Ready {
             return Err(CoreError::InvalidState {
                 expected: "Ready".into(),
-                actual: format!("{:?}", self.state),
+                actual: format!("{:?}", state),
             });
         }
+        // Every operation checks this first, so it marks the sandbox used
+        self.mark_used();
         Ok(())
     }
 }
@@ -363,6 +1874,7 @@ impl Sandbox {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
 
     #[test]
     fn test_sandbox_id_display() {
@@ -373,6 +1885,14 @@ mod tests {
         assert!(s.contains('-'));
     }
 
+    #[test]
+    fn test_sandbox_id_short() {
+        let id = SandboxId::new();
+        let short = id.short();
+        assert_eq!(short.len(), 8);
+        assert!(id.to_string().starts_with(&short));
+    }
+
     #[test]
     fn test_sandbox_state_display() {
         assert_eq!(format!("{}", SandboxState::Creating), "Creating");
@@ -380,10 +1900,163 @@ mod tests {
         assert_eq!(format!("{}", SandboxState::Destroyed), "Destroyed");
     }
 
+    #[test]
+    fn test_sandbox_state_serde_matches_display() {
+        for state in [
+            SandboxState::Creating,
+            SandboxState::Ready,
+            SandboxState::Destroyed,
+        ] {
+            let json = serde_json::to_string(&state).unwrap();
+            assert_eq!(json, format!("\"{}\"", state));
+
+            let parsed: SandboxState = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, state);
+        }
+    }
+
+    #[test]
+    fn test_creation_timing_sums_to_total() {
+        let mut timer = PhaseTimer::start();
+        std::thread::sleep(std::time::Duration::from_millis(15));
+        let vm_create_ms = timer.lap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        let agent_connect_ms = timer.lap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let ping_ms = timer.lap();
+
+        let timing = CreationTiming {
+            vm_create_ms,
+            agent_connect_ms,
+            ping_ms,
+            total_ms: timer.total(),
+        };
+
+        assert!(timing.vm_create_ms >= 15);
+        assert!(timing.agent_connect_ms >= 10);
+        assert!(timing.ping_ms >= 5);
+        // Each phase can lose under 1ms to truncation
+        assert!(timing.phases_ms() <= timing.total_ms);
+        assert!(timing.total_ms - timing.phases_ms() < 3);
+    }
+
     #[test]
     fn test_sandbox_id_from_uuid() {
         let uuid = Uuid::new_v4();
         let id: SandboxId = uuid.into();
         assert_eq!(format!("{}", id), format!("{}", uuid));
     }
+
+    /// Capture log output written while `f` runs.
+    fn capture_logs(f: impl FnOnce()) -> String {
+        let buf = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let writer = Arc::clone(&buf);
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || SharedWriter(Arc::clone(&writer)))
+            .with_ansi(false)
+            .finish();
+        tracing::subscriber::with_default(subscriber, f);
+        let bytes = buf.lock().unwrap().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    struct SharedWriter(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for SharedWriter {
+        fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(data);
+            Ok(data.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_drop_guard_warns_when_dropped_ready() {
+        let id = SandboxId::new();
+        let logs = capture_logs(|| drop(DropGuard::new(id)));
+        assert!(logs.contains("WARN"), "{logs}");
+        assert!(logs.contains("dropped while ready without destroy()"));
+        assert!(logs.contains(&id.to_string()));
+    }
+
+    #[test]
+    fn test_drop_guard_silent_after_destroy() {
+        let logs = capture_logs(|| {
+            let mut guard = DropGuard::new(SandboxId::new());
+            guard.disarm();
+        });
+        assert!(logs.is_empty(), "{logs}");
+    }
+
+    #[test]
+    fn test_vm_config_clean_tmp_boot_arg() {
+        let vsock = bouvet_vm::VsockConfig::default();
+        let config = SandboxConfig::default();
+        let vm_config = Sandbox::vm_config(&config, vsock.clone());
+        assert!(!vm_config.boot_args.contains("BOUVET_CLEAN_TMP"));
+
+        let config = SandboxConfig {
+            clean_tmp: true,
+            ..Default::default()
+        };
+        let vm_config = Sandbox::vm_config(&config, vsock);
+        assert!(vm_config.boot_args.ends_with(" BOUVET_CLEAN_TMP=1"));
+    }
+
+    #[test]
+    fn test_vm_config_proxy_boot_args() {
+        let vsock = bouvet_vm::VsockConfig::default();
+        let vm_config = Sandbox::vm_config(&SandboxConfig::default(), vsock.clone());
+        assert!(!vm_config.boot_args.contains("PROXY"));
+
+        let config = SandboxConfig {
+            proxy: crate::ProxyConfig {
+                http_proxy: Some("http://proxy.corp:3128".into()),
+                no_proxy: Some("localhost,.corp".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let vm_config = Sandbox::vm_config(&config, vsock);
+        assert!(vm_config.boot_args.ends_with(
+            " BOUVET_HTTP_PROXY=http://proxy.corp:3128 BOUVET_NO_PROXY=localhost,.corp"
+        ));
+    }
+
+    #[test]
+    fn test_vm_config_mounts_boot_arg() {
+        let vsock = bouvet_vm::VsockConfig::default();
+        let vm_config = Sandbox::vm_config(&SandboxConfig::default(), vsock.clone());
+        assert!(!vm_config.boot_args.contains("BOUVET_MOUNTS"));
+
+        let config = SandboxConfig {
+            mounts: vec![crate::MountSpec::new(SCRATCH_DEVICE, "/data", "ext4")],
+            ..Default::default()
+        };
+        let vm_config = Sandbox::vm_config(&config, vsock);
+        assert!(vm_config
+            .boot_args
+            .ends_with(" BOUVET_MOUNTS=/dev/vdb:/data:ext4"));
+    }
+
+    #[test]
+    fn test_vm_config_scratch_drive() {
+        let vsock = bouvet_vm::VsockConfig::default();
+        let vm_config = Sandbox::vm_config(&SandboxConfig::default(), vsock.clone());
+        assert!(vm_config.extra_drives.is_empty());
+
+        let config = SandboxConfig {
+            scratch_drive: Some("/var/lib/bouvet/scratch.ext4".into()),
+            ..Default::default()
+        };
+        let vm_config = Sandbox::vm_config(&config, vsock);
+        assert_eq!(vm_config.extra_drives.len(), 1);
+        let drive = &vm_config.extra_drives[0];
+        assert_eq!(drive.drive_id, SCRATCH_DRIVE_ID);
+        assert!(!drive.is_read_only);
+        assert!(vm_config.validate().is_ok());
+    }
 }
@@ -0,0 +1,415 @@
+//! Bounded, backpressured channels for streaming command output.
+//!
+//! Streaming exec output flows guest → host through a chain of bounded
+//! buffers so that a slow consumer throttles the producer instead of
+//! causing unbounded memory growth:
+//!
+//! ```text
+//! child pipe ──▶ agent ──▶ vsock ──▶ AgentClient ──▶ mpsc (bounded) ──▶ consumer
+//! ```
+//!
+//! # Buffer Size
+//!
+//! The host-side channel holds at most [`OUTPUT_CHANNEL_CAPACITY`] chunks of
+//! at most [`OUTPUT_CHUNK_SIZE`] bytes each, i.e. 512 KiB of buffered output
+//! per stream.
+//!
+//! # Behavior When the Consumer Lags
+//!
+//! When the channel is full, [`pump_output`] stops reading from its source
+//! until the consumer makes room. Nothing is dropped or truncated. On the
+//! host this means the vsock socket is no longer drained, its kernel buffer
+//! fills, and the agent's writes block; the agent in turn stops reading the
+//! child's pipe, and the child blocks on its next write. If the receiver is
+//! dropped, pumping stops early and the remaining output is discarded.
+
+use crate::client::ExecResult;
+use crate::error::CoreError;
+use bouvet_common::take_utf8;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// Maximum number of chunks buffered between producer and consumer.
+pub const OUTPUT_CHANNEL_CAPACITY: usize = 64;
+
+/// Maximum size of a single output chunk in bytes (8 KiB).
+pub const OUTPUT_CHUNK_SIZE: usize = 8 * 1024;
+
+/// Which output stream a chunk came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    /// Standard output.
+    Stdout,
+    /// Standard error.
+    Stderr,
+}
+
+/// Signal sent to a streamed command to cancel it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Signal {
+    /// SIGTERM, which the command may catch to exit cleanly.
+    #[default]
+    Term,
+    /// SIGKILL, which can't be caught.
+    Kill,
+}
+
+/// A piece of streamed command output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputChunk {
+    /// Stream the data was read from.
+    pub stream: OutputStream,
+    /// Output data (lossily decoded as UTF-8).
+    pub data: String,
+}
+
+/// Create a bounded channel for streamed output.
+///
+/// The channel holds at most [`OUTPUT_CHANNEL_CAPACITY`] chunks.
+pub fn output_channel() -> (mpsc::Sender<OutputChunk>, mpsc::Receiver<OutputChunk>) {
+    mpsc::channel(OUTPUT_CHANNEL_CAPACITY)
+}
+
+/// Read from `reader` until EOF, forwarding output into a bounded channel.
+///
+/// Each read is at most [`OUTPUT_CHUNK_SIZE`] bytes and is sent as one
+/// [`OutputChunk`]. A UTF-8 character split across reads is held back until
+/// the rest arrives, so chunks never end mid-character. Sending awaits free
+/// capacity, so no further reads happen while the channel is full.
+///
+/// # Returns
+///
+/// The number of bytes forwarded. Stops early (without error) if the
+/// receiver is dropped.
+///
+/// # Errors
+///
+/// Returns an error if reading from `reader` fails.
+pub async fn pump_output<R: AsyncRead + Unpin>(
+    mut reader: R,
+    stream: OutputStream,
+    tx: &mpsc::Sender<OutputChunk>,
+) -> std::io::Result<u64> {
+    // Bytes read but not yet sent: an incomplete character at the end of
+    // the last read, held back until the rest arrives
+    let mut pending = Vec::with_capacity(OUTPUT_CHUNK_SIZE);
+    let mut total = 0u64;
+
+    loop {
+        let held = pending.len();
+        pending.resize(OUTPUT_CHUNK_SIZE, 0);
+        let n = reader.read(&mut pending[held..]).await?;
+        pending.truncate(held + n);
+        if n == 0 {
+            break;
+        }
+
+        let data = take_utf8(&mut pending);
+        if data.is_empty() {
+            continue;
+        }
+        let taken = held + n - pending.len();
+
+        // Blocks while the channel is full - this is the backpressure point
+        if tx.send(OutputChunk { stream, data }).await.is_err() {
+            tracing::debug!(?stream, total, "Output receiver dropped, stopping pump");
+            return Ok(total);
+        }
+        total += taken as u64;
+    }
+
+    // A sequence cut short by EOF is sent as replacement characters
+    if !pending.is_empty() {
+        let data = String::from_utf8_lossy(&pending).into_owned();
+        if tx.send(OutputChunk { stream, data }).await.is_ok() {
+            total += pending.len() as u64;
+        }
+    }
+
+    tracing::trace!(?stream, total, "Output pump finished");
+    Ok(total)
+}
+
+/// Output of a command from
+/// [`Sandbox::execute_stream`](crate::Sandbox::execute_stream), followed by
+/// its result.
+///
+/// Output is buffered in a channel of [`OUTPUT_CHANNEL_CAPACITY`] chunks;
+/// a lagging consumer throttles the command.
+#[derive(Debug)]
+pub struct ExecStream {
+    id: u64,
+    rx: mpsc::Receiver<OutputChunk>,
+    task: JoinHandle<Result<ExecResult, CoreError>>,
+}
+
+impl ExecStream {
+    /// Create a stream fed by a background exec task.
+    pub(crate) fn new(
+        id: u64,
+        rx: mpsc::Receiver<OutputChunk>,
+        task: JoinHandle<Result<ExecResult, CoreError>>,
+    ) -> Self {
+        Self { id, rx, task }
+    }
+
+    /// ID of the command within its sandbox, for
+    /// [`Sandbox::cancel`](crate::Sandbox::cancel).
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Receive the next chunk of output.
+    ///
+    /// Returns `None` once the command has exited and all its output has
+    /// been received.
+    pub async fn next(&mut self) -> Option<OutputChunk> {
+        self.rx.recv().await
+    }
+
+    /// Wait for the command to exit, discarding output not yet received.
+    ///
+    /// The result's `stdout` and `stderr` are empty, since the output was
+    /// streamed, apart from a note on stderr if the command timed out.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command couldn't be run or the agent
+    /// connection failed.
+    pub async fn finish(self) -> Result<ExecResult, CoreError> {
+        drop(self.rx);
+        self.task
+            .await
+            .map_err(|e| CoreError::Connection(format!("exec task failed: {e}")))?
+    }
+}
+
+/// Streamed commands running in a sandbox, by exec ID, so they can be
+/// signalled from outside the task that runs them.
+#[derive(Debug, Default)]
+pub(crate) struct RunningExecs {
+    next_id: AtomicU64,
+    signals: Mutex<HashMap<u64, mpsc::Sender<Signal>>>,
+}
+
+impl RunningExecs {
+    /// Register a new command, returning its ID, the receiver its task
+    /// takes signals from, and a guard that unregisters it when dropped.
+    pub(crate) fn register(self: &Arc<Self>) -> (u64, mpsc::Receiver<Signal>, ExecGuard) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        // Room for a SIGTERM followed by a SIGKILL
+        let (tx, rx) = mpsc::channel(2);
+        self.lock().insert(id, tx);
+        let guard = ExecGuard {
+            id,
+            execs: Arc::clone(self),
+        };
+        (id, rx, guard)
+    }
+
+    /// Send `signal` to command `id`.
+    ///
+    /// Returns `false` if no such command is running.
+    pub(crate) fn signal(&self, id: u64, signal: Signal) -> bool {
+        match self.lock().get(&id) {
+            Some(tx) => {
+                // A full channel already holds signals the task hasn't sent yet
+                let _ = tx.try_send(signal);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// IDs of the running commands, in start order.
+    pub(crate) fn ids(&self) -> Vec<u64> {
+        let mut ids: Vec<u64> = self.lock().keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, mpsc::Sender<Signal>>> {
+        self.signals.lock().unwrap()
+    }
+}
+
+/// Unregisters a streamed command from [`RunningExecs`] when dropped.
+#[derive(Debug)]
+pub(crate) struct ExecGuard {
+    id: u64,
+    execs: Arc<RunningExecs>,
+}
+
+impl Drop for ExecGuard {
+    fn drop(&mut self) {
+        self.execs.lock().remove(&self.id);
+    }
+}
+
+/// Lines of a file from [`Sandbox::tail`](crate::Sandbox::tail).
+///
+/// Lines are buffered in a channel of [`OUTPUT_CHANNEL_CAPACITY`] lines; a
+/// lagging consumer throttles the agent like other output streams. A
+/// followed tail runs until [`cancel`](Self::cancel) is called or the
+/// stream is dropped.
+#[derive(Debug)]
+pub struct TailStream {
+    rx: mpsc::Receiver<String>,
+    cancel: Option<oneshot::Sender<()>>,
+    task: Option<JoinHandle<Result<(), CoreError>>>,
+}
+
+impl TailStream {
+    /// Create a finished stream that yields `lines` and ends.
+    pub(crate) fn from_lines(lines: Vec<String>) -> Self {
+        let (tx, rx) = mpsc::channel(lines.len().max(1));
+        for line in lines {
+            // Capacity covers every line, so this never fails
+            let _ = tx.try_send(line);
+        }
+        Self {
+            rx,
+            cancel: None,
+            task: None,
+        }
+    }
+
+    /// Create a stream fed by a background follow task.
+    pub(crate) fn following(
+        rx: mpsc::Receiver<String>,
+        cancel: oneshot::Sender<()>,
+        task: JoinHandle<Result<(), CoreError>>,
+    ) -> Self {
+        Self {
+            rx,
+            cancel: Some(cancel),
+            task: Some(task),
+        }
+    }
+
+    /// Receive the next line.
+    ///
+    /// Returns `None` once the stream has ended.
+    pub async fn next(&mut self) -> Option<String> {
+        self.rx.recv().await
+    }
+
+    /// Stop following and wait until the agent has stopped.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error that ended the follow early, if any.
+    pub async fn cancel(mut self) -> Result<(), CoreError> {
+        if let Some(cancel) = self.cancel.take() {
+            let _ = cancel.send(());
+        }
+        match self.task.take() {
+            Some(task) => task
+                .await
+                .map_err(|e| CoreError::Connection(format!("tail task failed: {e}")))?,
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn test_slow_consumer_bounds_buffered_output() {
+        const PIPE_SIZE: usize = 64 * 1024;
+        const TOTAL: u64 = 16 * 1024 * 1024;
+
+        let (mut producer, pipe) = tokio::io::duplex(PIPE_SIZE);
+        let written = Arc::new(AtomicU64::new(0));
+
+        // Simulated child process that writes far more than the buffers can hold
+        let producer_written = Arc::clone(&written);
+        let producer_task = tokio::spawn(async move {
+            let block = vec![b'x'; 4096];
+            while producer_written.load(Ordering::SeqCst) < TOTAL {
+                producer.write_all(&block).await.unwrap();
+                producer_written.fetch_add(block.len() as u64, Ordering::SeqCst);
+            }
+        });
+
+        let (tx, mut rx) = output_channel();
+        let pump_task =
+            tokio::spawn(async move { pump_output(pipe, OutputStream::Stdout, &tx).await });
+
+        // Consumer is stalled: the producer must be throttled
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let bound = (OUTPUT_CHANNEL_CAPACITY * OUTPUT_CHUNK_SIZE + 2 * PIPE_SIZE) as u64;
+        let stalled_at = written.load(Ordering::SeqCst);
+        assert!(
+            stalled_at <= bound,
+            "producer wrote {stalled_at} bytes with a stalled consumer (bound {bound})"
+        );
+
+        // Draining the channel lets everything through without loss
+        let mut received = 0u64;
+        while let Some(chunk) = rx.recv().await {
+            assert_eq!(chunk.stream, OutputStream::Stdout);
+            assert!(chunk.data.len() <= OUTPUT_CHUNK_SIZE);
+            received += chunk.data.len() as u64;
+        }
+
+        producer_task.await.unwrap();
+        assert_eq!(pump_task.await.unwrap().unwrap(), TOTAL);
+        assert_eq!(received, TOTAL);
+    }
+
+    #[tokio::test]
+    async fn test_pump_stops_when_receiver_dropped() {
+        let (tx, rx) = output_channel();
+        drop(rx);
+
+        let total = pump_output(&b"hello"[..], OutputStream::Stderr, &tx)
+            .await
+            .unwrap();
+        assert_eq!(total, 0);
+    }
+
+    #[tokio::test]
+    async fn test_pump_keeps_characters_split_across_reads() {
+        // "é" is 0xc3 0xa9; the chain returns each half from its own read
+        let reader = (&b"caf\xc3"[..]).chain(&b"\xa9!"[..]);
+        let (tx, mut rx) = output_channel();
+
+        let total = pump_output(reader, OutputStream::Stdout, &tx)
+            .await
+            .unwrap();
+        drop(tx);
+        assert_eq!(total, 6);
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = rx.recv().await {
+            chunks.push(chunk.data);
+        }
+        assert_eq!(chunks, ["caf", "é!"]);
+    }
+
+    #[test]
+    fn test_output_stream_serialization() {
+        assert_eq!(
+            serde_json::to_string(&OutputStream::Stdout).unwrap(),
+            "\"stdout\""
+        );
+        assert_eq!(
+            serde_json::to_string(&OutputStream::Stderr).unwrap(),
+            "\"stderr\""
+        );
+    }
+}
@@ -2,8 +2,10 @@
 //!
 //! Configuration is loaded from environment variables with sensible defaults.
 
+use bouvet_core::{FirecrackerLogLevel, SandboxCapabilities, SandboxConfig, SandboxConfigBuilder};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Maximum size for code/content input in bytes (10 MB).
 pub const MAX_INPUT_SIZE_BYTES: usize = 10 * 1024 * 1024;
@@ -11,6 +13,23 @@ pub const MAX_INPUT_SIZE_BYTES: usize = 10 * 1024 * 1024;
 /// Maximum command length in characters.
 pub const MAX_COMMAND_LENGTH: usize = 1024 * 1024; // 1 MB
 
+/// Maximum number of lines `tail_file` returns (matches the agent limit).
+pub const MAX_TAIL_LINES: usize = 10_000;
+
+/// Largest archive `export_workspace` hands back (10 MB, like
+/// `download_file`).
+pub const MAX_EXPORT_SIZE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Output of a streamed command kept for its tool result, per stream
+/// (matches the agent's truncation of unstreamed output).
+pub const MAX_STREAMED_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// Default `check_connectivity` timeout in milliseconds.
+pub const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 3_000;
+
+/// Maximum `check_connectivity` timeout in milliseconds (matches the agent limit).
+pub const MAX_CONNECT_TIMEOUT_MS: u64 = 30_000;
+
 /// Transport mode for the MCP server.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum TransportMode {
@@ -68,11 +87,67 @@ pub struct BouvetConfig {
     /// Maximum concurrent boots during pool fill (default: 2).
     pub pool_max_boots: usize,
 
+    /// Report `/health` as degraded while the warm pool holds fewer than
+    /// `pool_min_size` sandboxes (default: false).
+    pub health_require_pool: bool,
+
+    /// Memory of sandboxes that don't request their own, warm or cold, in
+    /// MiB (default: 256).
+    pub default_memory_mib: u32,
+
+    /// vCPUs of sandboxes that don't request their own, warm or cold
+    /// (default: 2).
+    pub default_vcpu: u8,
+
     /// Transport mode (default: both stdio and HTTP).
     pub transport_mode: TransportMode,
 
     /// HTTP server bind address.
     pub http_addr: SocketAddr,
+
+    /// Idle time before TCP keepalive probes are sent on HTTP connections,
+    /// so half-open ones are noticed and closed (default: 60s, zero
+    /// disables).
+    pub http_keepalive: Duration,
+
+    /// Time allowed for an HTTP request's response to start; slower ones
+    /// get 408 Request Timeout (default: 120s, zero disables).
+    pub http_request_timeout: Duration,
+
+    /// Time allowed for a client to send a request's headers before the
+    /// connection is closed (default: 30s, zero disables).
+    pub http_header_timeout: Duration,
+
+    /// Operations sandboxes are allowed to perform. Tools for disabled
+    /// operations are not advertised.
+    pub capabilities: SandboxCapabilities,
+
+    /// Time allowed for graceful sandbox teardown at shutdown before
+    /// remaining VMs are force-killed (default: 25s).
+    pub shutdown_timeout: Duration,
+
+    /// Let `create_sandbox` override the kernel and rootfs (default: false).
+    pub allow_custom_images: bool,
+
+    /// Directory that custom kernel and rootfs paths must be inside
+    /// (default: `/var/lib/bouvet/images`).
+    pub image_dir: PathBuf,
+
+    /// Variable name patterns whose values are masked in logs
+    /// (default: `*_TOKEN`, `*_SECRET`, `*_KEY`, `*PASSWORD`).
+    pub redact_patterns: Vec<String>,
+
+    /// Level of each sandbox's Firecracker log, written to
+    /// `firecracker.log` in the VM directory (default: warn).
+    pub firecracker_log_level: FirecrackerLogLevel,
+
+    /// Idle time after which a sandbox is destroyed (default: none, kept
+    /// until destroyed).
+    pub sandbox_idle_ttl: Option<Duration>,
+
+    /// Offer host maintenance tools, such as cleaning up orphaned VMs
+    /// (default: false).
+    pub admin_tools: bool,
 }
 
 /// Configuration validation error.
@@ -101,8 +176,25 @@ impl Default for BouvetConfig {
             pool_enabled: true,
             pool_min_size: 3,
             pool_max_boots: 2,
+            health_require_pool: false,
+            default_memory_mib: 256,
+            default_vcpu: 2,
             transport_mode: TransportMode::Both,
             http_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 8080),
+            http_keepalive: Duration::from_secs(60),
+            http_request_timeout: Duration::from_secs(120),
+            http_header_timeout: Duration::from_secs(30),
+            capabilities: SandboxCapabilities::default(),
+            shutdown_timeout: Duration::from_secs(25),
+            allow_custom_images: false,
+            image_dir: PathBuf::from("/var/lib/bouvet/images"),
+            redact_patterns: bouvet_common::DEFAULT_PATTERNS
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            firecracker_log_level: FirecrackerLogLevel::Warn,
+            sandbox_idle_ttl: None,
+            admin_tools: false,
         }
     }
 }
@@ -119,9 +211,24 @@ impl BouvetConfig {
     /// | `BOUVET_POOL_ENABLED` | `true` |
     /// | `BOUVET_POOL_MIN_SIZE` | `3` |
     /// | `BOUVET_POOL_MAX_BOOTS` | `2` |
+    /// | `BOUVET_HEALTH_REQUIRE_POOL` | `false` |
+    /// | `BOUVET_DEFAULT_MEMORY_MIB` | `256` |
+    /// | `BOUVET_DEFAULT_VCPU` | `2` |
     /// | `BOUVET_TRANSPORT` | `both` (stdio, http, both) |
     /// | `BOUVET_HTTP_HOST` | `0.0.0.0` |
     /// | `BOUVET_HTTP_PORT` | `8080` |
+    /// | `BOUVET_HTTP_KEEPALIVE_SECS` | `60` (0 disables) |
+    /// | `BOUVET_HTTP_REQUEST_TIMEOUT_SECS` | `120` (0 disables) |
+    /// | `BOUVET_HTTP_HEADER_TIMEOUT_SECS` | `30` (0 disables) |
+    /// | `BOUVET_ALLOW_EXEC` | `true` |
+    /// | `BOUVET_ALLOW_WRITE` | `true` |
+    /// | `BOUVET_SHUTDOWN_TIMEOUT_SECS` | `25` |
+    /// | `BOUVET_ALLOW_CUSTOM_IMAGES` | `false` |
+    /// | `BOUVET_IMAGE_DIR` | `/var/lib/bouvet/images` |
+    /// | `BOUVET_REDACT_PATTERNS` | `*_TOKEN,*_SECRET,*_KEY,*PASSWORD` |
+    /// | `BOUVET_FC_LOG_LEVEL` | `warn` (error, warn, info, debug) |
+    /// | `BOUVET_SANDBOX_IDLE_TTL_SECS` | unset (0 disables) |
+    /// | `BOUVET_ADMIN_TOOLS` | `false` |
     pub fn from_env() -> Self {
         let default = Self::default();
 
@@ -159,13 +266,96 @@ impl BouvetConfig {
                 .ok()
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(default.pool_max_boots),
+            health_require_pool: std::env::var("BOUVET_HEALTH_REQUIRE_POOL")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(default.health_require_pool),
+            default_memory_mib: std::env::var("BOUVET_DEFAULT_MEMORY_MIB")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&mib| mib > 0)
+                .unwrap_or(default.default_memory_mib),
+            default_vcpu: std::env::var("BOUVET_DEFAULT_VCPU")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&count| count > 0)
+                .unwrap_or(default.default_vcpu),
             transport_mode: std::env::var("BOUVET_TRANSPORT")
                 .map(|v| TransportMode::parse(&v))
                 .unwrap_or(default.transport_mode),
             http_addr: SocketAddr::new(http_host, http_port),
+            http_keepalive: std::env::var("BOUVET_HTTP_KEEPALIVE_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(default.http_keepalive),
+            http_request_timeout: std::env::var("BOUVET_HTTP_REQUEST_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(default.http_request_timeout),
+            http_header_timeout: std::env::var("BOUVET_HTTP_HEADER_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(default.http_header_timeout),
+            capabilities: SandboxCapabilities {
+                exec: std::env::var("BOUVET_ALLOW_EXEC")
+                    .map(|v| v != "false" && v != "0")
+                    .unwrap_or(default.capabilities.exec),
+                write: std::env::var("BOUVET_ALLOW_WRITE")
+                    .map(|v| v != "false" && v != "0")
+                    .unwrap_or(default.capabilities.write),
+                ..default.capabilities
+            },
+            shutdown_timeout: std::env::var("BOUVET_SHUTDOWN_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(default.shutdown_timeout),
+            allow_custom_images: std::env::var("BOUVET_ALLOW_CUSTOM_IMAGES")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(default.allow_custom_images),
+            image_dir: std::env::var("BOUVET_IMAGE_DIR")
+                .map(PathBuf::from)
+                .unwrap_or(default.image_dir),
+            redact_patterns: std::env::var("BOUVET_REDACT_PATTERNS")
+                .map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|p| !p.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or(default.redact_patterns),
+            firecracker_log_level: std::env::var("BOUVET_FC_LOG_LEVEL")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.firecracker_log_level),
+            sandbox_idle_ttl: std::env::var("BOUVET_SANDBOX_IDLE_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(|secs| (secs > 0).then(|| Duration::from_secs(secs)))
+                .unwrap_or(default.sandbox_idle_ttl),
+            admin_tools: std::env::var("BOUVET_ADMIN_TOOLS")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(default.admin_tools),
         }
     }
 
+    /// Start a sandbox config from the server's images and defaults.
+    ///
+    /// Both the warm pool template and cold starts begin here, so pooled
+    /// and cold-started sandboxes match unless a request overrides them.
+    pub fn sandbox_config_builder(&self) -> SandboxConfigBuilder {
+        SandboxConfig::builder()
+            .kernel(&self.kernel_path)
+            .rootfs(&self.rootfs_path)
+            .capabilities(self.capabilities)
+            .firecracker_log_level(self.firecracker_log_level)
+            .memory_mib(self.default_memory_mib)
+            .vcpu_count(self.default_vcpu)
+    }
+
     /// Validate that all configured paths exist.
     ///
     /// Call this at startup to get clear error messages about missing files.
@@ -194,6 +384,43 @@ impl BouvetConfig {
         Ok(())
     }
 
+    /// Resolve a caller-supplied kernel or rootfs path.
+    ///
+    /// The path must exist and, after resolving symlinks and `..`, lie
+    /// inside `image_dir`, so callers can't boot arbitrary host files (and
+    /// read them from inside the guest).
+    ///
+    /// # Errors
+    /// Returns a message if custom images are disabled or `image_dir` is
+    /// inaccessible. Every rejected path gets the same message, whether it
+    /// is missing or outside `image_dir`, so callers can't probe which host
+    /// files exist.
+    pub fn resolve_image(&self, path: &str) -> Result<PathBuf, String> {
+        if !self.allow_custom_images {
+            return Err("custom kernel/rootfs paths are disabled on this server".into());
+        }
+        let image_dir = self.image_dir.canonicalize().map_err(|e| {
+            format!(
+                "image directory {} is not accessible: {e}",
+                self.image_dir.display()
+            )
+        })?;
+        let rejected = || {
+            format!(
+                "image {path} must be a file inside {}",
+                self.image_dir.display()
+            )
+        };
+        let resolved = Path::new(path).canonicalize().map_err(|e| {
+            tracing::debug!(path = %path, error = %e, "Custom image not accessible");
+            rejected()
+        })?;
+        if !resolved.starts_with(&image_dir) || !resolved.is_file() {
+            return Err(rejected());
+        }
+        Ok(resolved)
+    }
+
     /// Validate configuration but only log warnings instead of failing.
     ///
     /// Use this for development environments where paths may not exist yet.
@@ -231,6 +458,9 @@ mod tests {
         assert_eq!(config.chroot_path, PathBuf::from("/tmp/bouvet"));
         assert_eq!(config.transport_mode, TransportMode::Both);
         assert_eq!(config.http_addr.port(), 8080);
+        assert_eq!(config.http_keepalive, Duration::from_secs(60));
+        assert_eq!(config.http_request_timeout, Duration::from_secs(120));
+        assert_eq!(config.http_header_timeout, Duration::from_secs(30));
     }
 
     #[test]
@@ -265,6 +495,7 @@ mod tests {
         std::env::remove_var("BOUVET_TRANSPORT");
         std::env::remove_var("BOUVET_HTTP_HOST");
         std::env::remove_var("BOUVET_HTTP_PORT");
+        std::env::remove_var("BOUVET_FC_LOG_LEVEL");
 
         let config = BouvetConfig::from_env();
         let default = BouvetConfig::default();
@@ -274,6 +505,30 @@ mod tests {
         assert_eq!(config.firecracker_path, default.firecracker_path);
         assert_eq!(config.chroot_path, default.chroot_path);
         assert_eq!(config.transport_mode, TransportMode::Both);
+        assert_eq!(config.firecracker_log_level, FirecrackerLogLevel::Warn);
+    }
+
+    #[test]
+    fn test_from_env_default_sandbox_size() {
+        std::env::set_var("BOUVET_DEFAULT_MEMORY_MIB", "1024");
+        std::env::set_var("BOUVET_DEFAULT_VCPU", "4");
+        let config = BouvetConfig::from_env();
+        assert_eq!(config.default_memory_mib, 1024);
+        assert_eq!(config.default_vcpu, 4);
+
+        let sandbox = config.sandbox_config_builder().build().unwrap();
+        assert_eq!(sandbox.memory_mib, 1024);
+        assert_eq!(sandbox.vcpu_count, 4);
+
+        // Zero or unparsable values fall back to the defaults
+        std::env::set_var("BOUVET_DEFAULT_MEMORY_MIB", "0");
+        std::env::set_var("BOUVET_DEFAULT_VCPU", "many");
+        let config = BouvetConfig::from_env();
+        assert_eq!(config.default_memory_mib, 256);
+        assert_eq!(config.default_vcpu, 2);
+
+        std::env::remove_var("BOUVET_DEFAULT_MEMORY_MIB");
+        std::env::remove_var("BOUVET_DEFAULT_VCPU");
     }
 
     #[test]
@@ -282,4 +537,60 @@ mod tests {
         assert_eq!(MAX_INPUT_SIZE_BYTES, 10 * 1024 * 1024);
         assert_eq!(MAX_COMMAND_LENGTH, 1024 * 1024);
     }
+
+    #[test]
+    fn test_resolve_image() {
+        let root = std::env::temp_dir().join(format!("bouvet-images-{}", std::process::id()));
+        let image_dir = root.join("images");
+        std::fs::create_dir_all(&image_dir).unwrap();
+        std::fs::write(image_dir.join("custom.ext4"), b"").unwrap();
+        std::fs::write(root.join("secret"), b"").unwrap();
+        std::os::unix::fs::symlink(root.join("secret"), image_dir.join("link.ext4")).unwrap();
+
+        let config = BouvetConfig {
+            allow_custom_images: true,
+            image_dir: image_dir.clone(),
+            ..Default::default()
+        };
+        let path = |name: &str| image_dir.join(name).to_string_lossy().to_string();
+
+        // Allowed: an existing file inside the image directory
+        let resolved = config.resolve_image(&path("custom.ext4")).unwrap();
+        assert_eq!(
+            resolved,
+            image_dir.canonicalize().unwrap().join("custom.ext4")
+        );
+
+        // Denied, all with the same message: outside the directory
+        // (directly, via `..` or via a symlink), the directory itself, and
+        // files that don't exist
+        let secret = root.join("secret").to_string_lossy().to_string();
+        for denied in [
+            secret,
+            path("../secret"),
+            path("link.ext4"),
+            path(""),
+            path("missing.ext4"),
+        ] {
+            assert_eq!(
+                config.resolve_image(&denied).unwrap_err(),
+                format!(
+                    "image {denied} must be a file inside {}",
+                    image_dir.display()
+                )
+            );
+        }
+
+        // Denied: custom images disabled
+        let config = BouvetConfig {
+            allow_custom_images: false,
+            ..config
+        };
+        assert!(config
+            .resolve_image(&path("custom.ext4"))
+            .unwrap_err()
+            .contains("disabled"));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
@@ -0,0 +1,177 @@
+//! Readiness checks behind `GET /health`.
+//!
+//! Orchestrators use `/health` to decide whether to route traffic to the
+//! server, so it checks what creating a sandbox depends on: the Firecracker
+//! binary can be executed, the chroot directory can be written, and, when
+//! `BOUVET_HEALTH_REQUIRE_POOL` is set, the warm pool is filled to its
+//! minimum size. `GET /livez` only reports that the process is up.
+
+use crate::server::BouvetServer;
+use serde::Serialize;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+/// Outcome of a single readiness check.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthCheck {
+    /// What was checked: `firecracker`, `chroot` or `pool`.
+    pub name: &'static str,
+
+    /// Whether the check passed.
+    pub ok: bool,
+
+    /// What was found, or why the check failed.
+    pub detail: String,
+}
+
+/// Outcome of every readiness check.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    /// `healthy` if every check passed, otherwise `degraded`.
+    pub status: &'static str,
+
+    /// Service name.
+    pub service: &'static str,
+
+    /// Individual check results.
+    pub checks: Vec<HealthCheck>,
+}
+
+impl HealthReport {
+    /// Whether every check passed.
+    pub fn is_healthy(&self) -> bool {
+        self.checks.iter().all(|check| check.ok)
+    }
+}
+
+/// Run the readiness checks for `server`.
+pub async fn check(server: &BouvetServer) -> HealthReport {
+    let config = server.config();
+    let mut checks = vec![
+        check_firecracker(&config.firecracker_path),
+        check_chroot(&config.chroot_path),
+    ];
+
+    if config.health_require_pool {
+        let min_size = config.pool_min_size;
+        checks.push(match server.pool_size().await {
+            Some(size) => HealthCheck {
+                name: "pool",
+                ok: size >= min_size,
+                detail: format!("{size} of {min_size} warm sandboxes"),
+            },
+            None => HealthCheck {
+                name: "pool",
+                ok: false,
+                detail: "warm pool is disabled".into(),
+            },
+        });
+    }
+
+    let mut report = HealthReport {
+        status: "healthy",
+        service: "bouvet-mcp",
+        checks,
+    };
+    if !report.is_healthy() {
+        report.status = "degraded";
+        tracing::warn!(checks = ?report.checks, "Health check failed");
+    }
+    report
+}
+
+/// Check that the Firecracker binary is a file with an execute bit set.
+fn check_firecracker(path: &Path) -> HealthCheck {
+    let (ok, detail) = match std::fs::metadata(path) {
+        Ok(meta) if !meta.is_file() => (false, format!("{} is not a file", path.display())),
+        Ok(meta) if meta.permissions().mode() & 0o111 == 0 => {
+            (false, format!("{} is not executable", path.display()))
+        }
+        Ok(_) => (true, path.display().to_string()),
+        Err(e) => (false, format!("{}: {e}", path.display())),
+    };
+    HealthCheck {
+        name: "firecracker",
+        ok,
+        detail,
+    }
+}
+
+/// Check that the chroot directory exists, or can be created, and that a
+/// file can be written in it.
+fn check_chroot(path: &Path) -> HealthCheck {
+    let probe = path.join(format!(".bouvet-health-{}", std::process::id()));
+    let result = std::fs::create_dir_all(path)
+        .and_then(|()| std::fs::write(&probe, b""))
+        .and_then(|()| std::fs::remove_file(&probe));
+    let (ok, detail) = match result {
+        Ok(()) => (true, path.display().to_string()),
+        Err(e) => (false, format!("{} is not writable: {e}", path.display())),
+    };
+    HealthCheck {
+        name: "chroot",
+        ok,
+        detail,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BouvetConfig;
+
+    /// A config whose Firecracker binary and chroot pass their checks.
+    fn healthy_config(dir: &Path) -> BouvetConfig {
+        std::fs::create_dir_all(dir).unwrap();
+        let firecracker = dir.join("firecracker");
+        std::fs::write(&firecracker, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&firecracker, std::fs::Permissions::from_mode(0o755)).unwrap();
+        BouvetConfig {
+            firecracker_path: firecracker,
+            chroot_path: dir.join("chroot"),
+            pool_enabled: false,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_healthy() {
+        let dir = std::env::temp_dir().join(format!("bouvet-health-{}", uuid::Uuid::new_v4()));
+        let report = check(&BouvetServer::new(healthy_config(&dir))).await;
+
+        assert!(report.is_healthy(), "{report:?}");
+        assert_eq!(report.status, "healthy");
+        let names: Vec<_> = report.checks.iter().map(|c| c.name).collect();
+        assert_eq!(names, ["firecracker", "chroot"]);
+        assert!(dir.join("chroot").is_dir());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_degraded() {
+        let dir = std::env::temp_dir().join(format!("bouvet-health-{}", uuid::Uuid::new_v4()));
+        let mut config = healthy_config(&dir);
+        std::fs::set_permissions(
+            &config.firecracker_path,
+            std::fs::Permissions::from_mode(0o644),
+        )
+        .unwrap();
+        // A regular file can't hold the chroot directory
+        config.chroot_path = config.firecracker_path.join("chroot");
+        // The pool is never started, so it stays empty
+        config.pool_enabled = true;
+        config.health_require_pool = true;
+
+        let report = check(&BouvetServer::new(config)).await;
+        assert!(!report.is_healthy());
+        assert_eq!(report.status, "degraded");
+        assert!(report.checks.iter().all(|c| !c.ok), "{report:?}");
+        assert!(report.checks[0].detail.contains("not executable"));
+        assert!(report.checks[1].detail.contains("not writable"));
+        assert_eq!(report.checks[2].name, "pool");
+        assert_eq!(report.checks[2].detail, "0 of 3 warm sandboxes");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
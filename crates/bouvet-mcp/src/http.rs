@@ -8,22 +8,53 @@
 //!
 //! - `POST /mcp` - JSON-RPC requests
 //! - `GET /mcp` - SSE stream for server-initiated messages
-//! - `GET /health` - Health check
+//! - `GET /health` - Readiness check, 503 with details when degraded
+//! - `GET /livez` - Liveness check
+//! - `GET /metrics` - Per-tool metrics (Prometheus text format)
 //! - `GET /` - Server info
+//!
+//! ## Connection limits
+//!
+//! Remote clients on flaky networks can leave half-open connections, and
+//! slow ones can hold a connection open by trickling bytes. The listener
+//! sets TCP keepalive so dead peers are noticed, closes connections whose
+//! request headers don't arrive in time, and answers 408 to requests whose
+//! response doesn't start in time. The limits come from [`BouvetConfig`].
+//!
+//! ## Streaming output
+//!
+//! A `tools/call` of `execute_code` or `run_command` that carries a
+//! progress token gets its output as `notifications/progress` events on
+//! the call's SSE response while the command runs, followed by the result.
 
+use crate::config::BouvetConfig;
+use crate::health;
 use crate::server::BouvetServer;
 use axum::{
+    http::{header, StatusCode},
     response::{Html, IntoResponse, Json},
     routing::get,
     Router,
 };
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::server::graceful::GracefulShutdown;
+use hyper_util::service::TowerToHyperService;
 use rmcp::transport::streamable_http_server::{
     session::local::LocalSessionManager, StreamableHttpServerConfig, StreamableHttpService,
 };
+use socket2::{SockRef, TcpKeepalive};
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpSocket};
 use tower_http::cors::{Any, CorsLayer};
+use tower_http::timeout::TimeoutLayer;
 use tower_http::trace::TraceLayer;
 
+/// Pending connections the listener queues before refusing more.
+const LISTEN_BACKLOG: u32 = 1024;
+
 /// Build the HTTP router for the MCP server.
 ///
 /// The returned router can be served directly with axum or composed
@@ -31,6 +62,11 @@ use tower_http::trace::TraceLayer;
 pub fn build_router(server: BouvetServer) -> Router {
     tracing::debug!("Building HTTP router");
 
+    // Config is fixed for the server's lifetime, so render the page once
+    let root_page: Arc<str> = render_root_page(&server).into();
+    let metrics_server = server.clone();
+    let health_server = server.clone();
+
     // Create session manager for handling MCP sessions
     let session_manager = Arc::new(LocalSessionManager::default());
 
@@ -43,10 +79,19 @@ pub fn build_router(server: BouvetServer) -> Router {
 
     // Build the router
     let router = Router::new()
-        // Health check
-        .route("/health", get(health_handler))
+        // Readiness and liveness checks
+        .route(
+            "/health",
+            get(move || health_handler(health_server.clone())),
+        )
+        .route("/livez", get(livez_handler))
+        // Prometheus metrics
+        .route(
+            "/metrics",
+            get(move || metrics_handler(metrics_server.clone())),
+        )
         // Server info at root
-        .route("/", get(root_handler))
+        .route("/", get(move || root_handler(Arc::clone(&root_page))))
         // MCP endpoint as a fallback/nested service
         .fallback_service(mcp_service)
         // Add middleware
@@ -58,84 +103,232 @@ pub fn build_router(server: BouvetServer) -> Router {
         )
         .layer(TraceLayer::new_for_http());
 
-    tracing::debug!("HTTP router built with routes: /, /health, /mcp");
+    tracing::debug!("HTTP router built with routes: /, /health, /livez, /metrics, /mcp");
     router
 }
 
-/// Health check endpoint.
-async fn health_handler() -> impl IntoResponse {
+/// Readiness endpoint: 200 if sandboxes can be created, 503 otherwise.
+async fn health_handler(server: BouvetServer) -> impl IntoResponse {
     tracing::trace!("Health check request");
+    let report = health::check(&server).await;
+    let status = if report.is_healthy() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
+
+/// Liveness endpoint: answers as long as the server is running.
+async fn livez_handler() -> impl IntoResponse {
+    tracing::trace!("Liveness check request");
     Json(serde_json::json!({
-        "status": "healthy",
+        "status": "alive",
         "service": "bouvet-mcp"
     }))
 }
 
+/// Metrics endpoint in the Prometheus text exposition format.
+async fn metrics_handler(server: BouvetServer) -> impl IntoResponse {
+    tracing::trace!("Metrics request");
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        server.tool_metrics().render_prometheus(),
+    )
+}
+
 /// Root endpoint with server info.
-async fn root_handler() -> impl IntoResponse {
+async fn root_handler(page: Arc<str>) -> impl IntoResponse {
     tracing::trace!("Root page request");
-    Html(
+    Html(page.to_string())
+}
+
+/// Render the root info page from the server's runtime configuration.
+///
+/// The tool list comes from the same source as MCP `tools/list`, so the
+/// page stays accurate as tools are added or disabled by capabilities.
+fn render_root_page(server: &BouvetServer) -> String {
+    let config = server.config();
+    let on_off = |enabled: bool| if enabled { "on" } else { "off" };
+
+    // A wildcard bind address isn't reachable as-is; show localhost instead
+    let example_host = if config.http_addr.ip().is_unspecified() {
+        format!("localhost:{}", config.http_addr.port())
+    } else {
+        config.http_addr.to_string()
+    };
+
+    let tools: String = server
+        .tools()
+        .iter()
+        .map(|tool| {
+            format!(
+                "        <li><code>{}</code> - {}</li>\n",
+                escape_html(&tool.name),
+                escape_html(tool.description.as_deref().unwrap_or_default())
+            )
+        })
+        .collect();
+
+    format!(
         r#"<!DOCTYPE html>
 <html>
 <head>
     <title>Bouvet MCP Server</title>
     <style>
-        body { font-family: system-ui; max-width: 800px; margin: 50px auto; padding: 20px; }
-        code { background: #f4f4f4; padding: 2px 6px; border-radius: 3px; }
-        pre { background: #f4f4f4; padding: 16px; border-radius: 6px; overflow-x: auto; }
+        body {{ font-family: system-ui; max-width: 800px; margin: 50px auto; padding: 20px; }}
+        code {{ background: #f4f4f4; padding: 2px 6px; border-radius: 3px; }}
+        pre {{ background: #f4f4f4; padding: 16px; border-radius: 6px; overflow-x: auto; }}
     </style>
 </head>
 <body>
     <h1>🔥 Bouvet MCP Server</h1>
     <p>Model Context Protocol server for isolated code execution sandboxes.</p>
     
+    <h2>Configuration</h2>
+    <ul>
+        <li>Bind address: <code>{addr}</code></li>
+        <li>Warm pool: {pool}</li>
+        <li>Exec: {exec}</li>
+        <li>Writes: {write}</li>
+    </ul>
+    
     <h2>Endpoints</h2>
     <ul>
         <li><code>POST /mcp</code> - MCP JSON-RPC requests</li>
         <li><code>GET /mcp</code> - SSE stream for server messages</li>
-        <li><code>GET /health</code> - Health check</li>
+        <li><code>GET /health</code> - Readiness check</li>
+        <li><code>GET /livez</code> - Liveness check</li>
+        <li><code>GET /metrics</code> - Per-tool metrics (Prometheus)</li>
     </ul>
     
     <h2>Example</h2>
-    <pre>curl -X POST http://localhost:8080/mcp \
+    <pre>curl -X POST http://{example_host}/mcp \
   -H "Content-Type: application/json" \
-  -d '{"jsonrpc":"2.0","id":1,"method":"tools/list"}'</pre>
+  -d '{{"jsonrpc":"2.0","id":1,"method":"tools/list"}}'</pre>
     
     <h2>Available Tools</h2>
     <ul>
-        <li><code>create_sandbox</code> - Create a new isolated sandbox</li>
-        <li><code>destroy_sandbox</code> - Destroy a sandbox</li>
-        <li><code>list_sandboxes</code> - List active sandboxes</li>
-        <li><code>execute_code</code> - Execute code (Python, Node, Bash)</li>
-        <li><code>run_command</code> - Run shell command</li>
-        <li><code>read_file</code> - Read file from sandbox</li>
-        <li><code>write_file</code> - Write file to sandbox</li>
-        <li><code>list_directory</code> - List directory contents</li>
-    </ul>
+{tools}    </ul>
 </body>
 </html>"#,
+        addr = config.http_addr,
+        pool = on_off(config.pool_enabled),
+        exec = on_off(config.capabilities.exec),
+        write = on_off(config.capabilities.write),
     )
 }
 
+/// Escape text for inclusion in HTML.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
 /// Start the HTTP server.
 ///
 /// This function runs until the server is shut down via the provided
-/// shutdown signal.
+/// shutdown signal. Keepalive and timeouts are taken from the server's
+/// configuration.
 pub async fn serve(
     server: BouvetServer,
-    addr: std::net::SocketAddr,
+    addr: SocketAddr,
     shutdown: impl std::future::Future<Output = ()> + Send + 'static,
 ) -> Result<(), std::io::Error> {
+    let config = server.config().clone();
     let router = build_router(server);
 
     tracing::info!(%addr, "Starting HTTP/SSE server");
 
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    tracing::debug!(%addr, "TCP listener bound");
+    let listener = bind(addr, config.http_keepalive)?;
+    tracing::debug!(%addr, keepalive = ?config.http_keepalive, "TCP listener bound");
+
+    serve_router(listener, router, &config, shutdown).await;
+    Ok(())
+}
+
+/// Bind a TCP listener whose connections use TCP keepalive.
+///
+/// Keepalive set on the listening socket is inherited by every accepted
+/// connection. Probes start after `keepalive` of idleness and repeat at
+/// the same interval; zero leaves keepalive off.
+fn bind(addr: SocketAddr, keepalive: Duration) -> std::io::Result<TcpListener> {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    socket.set_reuseaddr(true)?;
+    if !keepalive.is_zero() {
+        let params = TcpKeepalive::new()
+            .with_time(keepalive)
+            .with_interval(keepalive);
+        SockRef::from(&socket).set_tcp_keepalive(&params)?;
+    }
+    socket.bind(addr)?;
+    socket.listen(LISTEN_BACKLOG)
+}
+
+/// Serve `router` on `listener` until `shutdown` completes, then wait for
+/// open connections to finish.
+///
+/// Like `axum::serve`, but with the request and header-read timeouts
+/// from `config`, which axum doesn't expose.
+async fn serve_router(
+    listener: TcpListener,
+    router: Router,
+    config: &BouvetConfig,
+    shutdown: impl std::future::Future<Output = ()>,
+) {
+    let router = if config.http_request_timeout.is_zero() {
+        router
+    } else {
+        router.layer(TimeoutLayer::new(config.http_request_timeout))
+    };
+    let header_timeout = Some(config.http_header_timeout).filter(|t| !t.is_zero());
+
+    let graceful = GracefulShutdown::new();
+    let mut shutdown = std::pin::pin!(shutdown);
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    // E.g. out of file descriptors; back off instead of spinning
+                    tracing::warn!(error = %e, "Failed to accept HTTP connection");
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            },
+            () = &mut shutdown => break,
+        };
+        tracing::trace!(%peer, "Accepted HTTP connection");
+
+        let mut builder = Builder::new(TokioExecutor::new());
+        builder
+            .http1()
+            .timer(TokioTimer::new())
+            .header_read_timeout(header_timeout);
+        let connection = builder
+            .serve_connection_with_upgrades(
+                TokioIo::new(stream),
+                TowerToHyperService::new(router.clone()),
+            )
+            .into_owned();
+        let connection = graceful.watch(connection);
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                // Includes clients closing the connection and header timeouts
+                tracing::debug!(%peer, error = %e, "HTTP connection ended with error");
+            }
+        });
+    }
 
-    axum::serve(listener, router)
-        .with_graceful_shutdown(shutdown)
-        .await
+    drop(listener);
+    tracing::debug!("HTTP listener closed, waiting for open connections");
+    graceful.shutdown().await;
 }
 
 #[cfg(test)]
@@ -150,4 +343,321 @@ mod tests {
         let _router = build_router(server);
         // Router builds without panic
     }
+
+    #[test]
+    fn test_root_page_lists_tools_and_config() {
+        let config = BouvetConfig {
+            http_addr: "127.0.0.1:9090".parse().unwrap(),
+            pool_enabled: false,
+            ..Default::default()
+        };
+        let page = render_root_page(&BouvetServer::new(config));
+
+        assert!(page.contains("<code>tail_file</code>"));
+        assert!(page.contains("<code>run_command</code>"));
+        assert!(page.contains("<code>127.0.0.1:9090</code>"));
+        assert!(page.contains("http://127.0.0.1:9090/mcp"));
+        assert!(page.contains("Warm pool: off"));
+    }
+
+    /// Serve `router` with `config`'s limits on a local port until the
+    /// returned sender is dropped.
+    async fn spawn_server(
+        router: Router,
+        config: BouvetConfig,
+    ) -> (SocketAddr, tokio::sync::oneshot::Sender<()>) {
+        let listener = bind("127.0.0.1:0".parse().unwrap(), config.http_keepalive).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        tokio::spawn(async move {
+            serve_router(listener, router, &config, async {
+                let _ = rx.await;
+            })
+            .await;
+        });
+        (addr, tx)
+    }
+
+    /// Send `request` and read until the server closes the connection.
+    async fn exchange(addr: SocketAddr, request: &[u8]) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request).await.unwrap();
+        let mut response = Vec::new();
+        let _ = stream.read_to_end(&mut response).await;
+        String::from_utf8_lossy(&response).into_owned()
+    }
+
+    #[tokio::test]
+    async fn test_slow_request_times_out() {
+        let router = Router::new()
+            .route("/fast", get(|| async { "done" }))
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(30)).await;
+                    "done"
+                }),
+            );
+        let config = BouvetConfig {
+            http_request_timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let (addr, _stop) = spawn_server(router, config).await;
+
+        let response = exchange(
+            addr,
+            b"GET /fast HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+
+        let started = std::time::Instant::now();
+        let response = exchange(
+            addr,
+            b"GET /slow HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 408"), "{response}");
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn test_incomplete_headers_close_the_connection() {
+        let router = Router::new().route("/", get(|| async { "done" }));
+        let config = BouvetConfig {
+            http_header_timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let (addr, _stop) = spawn_server(router, config).await;
+
+        // The blank line ending the headers never arrives
+        let started = std::time::Instant::now();
+        let response = exchange(addr, b"GET / HTTP/1.1\r\nHost: x\r\n").await;
+        assert!(!response.contains("done"), "{response}");
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn test_health_and_livez_status() {
+        let dir = std::env::temp_dir().join(format!("bouvet-http-health-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let firecracker = dir.join("firecracker");
+        std::fs::write(&firecracker, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(
+            &firecracker,
+            std::os::unix::fs::PermissionsExt::from_mode(0o755),
+        )
+        .unwrap();
+        let healthy = BouvetConfig {
+            firecracker_path: firecracker,
+            chroot_path: dir.join("chroot"),
+            pool_enabled: false,
+            ..Default::default()
+        };
+        let degraded = BouvetConfig {
+            firecracker_path: dir.join("missing"),
+            ..healthy.clone()
+        };
+
+        let (addr, _stop) =
+            spawn_server(build_router(BouvetServer::new(healthy.clone())), healthy).await;
+        let response = exchange(
+            addr,
+            b"GET /health HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.contains(r#""status":"healthy""#), "{response}");
+
+        let (addr, _stop) =
+            spawn_server(build_router(BouvetServer::new(degraded.clone())), degraded).await;
+        let response = exchange(
+            addr,
+            b"GET /health HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 503"), "{response}");
+        assert!(response.contains(r#""status":"degraded""#), "{response}");
+        assert!(
+            response.contains(r#""name":"firecracker","ok":false"#),
+            "{response}"
+        );
+
+        // Liveness doesn't depend on the checks
+        let response = exchange(
+            addr,
+            b"GET /livez HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// POST a JSON-RPC message to `/mcp` and return the raw response.
+    async fn post_mcp(addr: SocketAddr, session: Option<&str>, body: serde_json::Value) -> String {
+        let body = body.to_string();
+        let session = session
+            .map(|id| format!("Mcp-Session-Id: {id}\r\n"))
+            .unwrap_or_default();
+        let request = format!(
+            "POST /mcp HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\
+             Content-Type: application/json\r\nAccept: application/json, text/event-stream\r\n\
+             {session}Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        exchange(addr, request.as_bytes()).await
+    }
+
+    /// JSON-RPC messages in the `data:` lines of an SSE response, in order.
+    fn sse_messages(response: &str) -> Vec<serde_json::Value> {
+        response
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .filter_map(|data| serde_json::from_str(data.trim()).ok())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_execute_code_streams_output_over_sse() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let root = std::env::temp_dir().join(format!("bouvet-sse-{}", uuid::Uuid::new_v4()));
+        let config = BouvetConfig {
+            pool_enabled: false,
+            ..Default::default()
+        };
+        let manager = bouvet_core::SandboxManager::with_backend(
+            bouvet_core::ManagerConfig::new(
+                &config.kernel_path,
+                &config.rootfs_path,
+                &config.firecracker_path,
+                &root,
+            ),
+            Arc::new(bouvet_core::MockBackend::new()),
+        );
+        let server = BouvetServer::new(config.clone()).with_manager(manager);
+        let sandbox_config = bouvet_core::SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+        let id = server.manager().create(sandbox_config).await.unwrap();
+        let (addr, _stop) = spawn_server(build_router(server.clone()), config).await;
+
+        let response = post_mcp(
+            addr,
+            None,
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "initialize",
+                "params": {
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": {},
+                    "clientInfo": {"name": "test", "version": "0"},
+                },
+            }),
+        )
+        .await;
+        let session = response
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("mcp-session-id")
+                    .then(|| value.trim().to_string())
+            })
+            .expect("session id header");
+        post_mcp(
+            addr,
+            Some(&session),
+            serde_json::json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+        )
+        .await;
+
+        // The mock agent echoes the code, one output chunk per line, and
+        // holds the stream after the `sleep` line
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "tools/call",
+            "params": {
+                "name": "execute_code",
+                "arguments": {
+                    "sandbox_id": id.to_string(),
+                    "language": "python",
+                    "code": "print(1)\nsleep 0.5\nprint(2)\n",
+                },
+                "_meta": {"progressToken": "build"},
+            },
+        })
+        .to_string();
+        let request = format!(
+            "POST /mcp HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\
+             Content-Type: application/json\r\nAccept: application/json, text/event-stream\r\n\
+             Mcp-Session-Id: {session}\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        // The first line of output arrives while the command still runs
+        let mut response = Vec::new();
+        let mut buf = [0u8; 4096];
+        while !sse_messages(&String::from_utf8_lossy(&response))
+            .iter()
+            .any(|message| message["params"]["message"] == "print(1)\n")
+        {
+            let n = stream.read(&mut buf).await.unwrap();
+            assert!(n > 0, "stream ended before the first output");
+            response.extend_from_slice(&buf[..n]);
+        }
+        let first_output = std::time::Instant::now();
+        assert!(sse_messages(&String::from_utf8_lossy(&response))
+            .iter()
+            .all(|message| message.get("result").is_none()));
+        stream.read_to_end(&mut response).await.unwrap();
+        assert!(first_output.elapsed() >= Duration::from_millis(400));
+
+        let messages = sse_messages(&String::from_utf8_lossy(&response));
+        let (result, progress) = messages.split_last().expect("SSE messages");
+        let chunks: Vec<&str> = progress
+            .iter()
+            .map(|message| {
+                assert_eq!(message["method"], "notifications/progress");
+                assert_eq!(message["params"]["progressToken"], "build");
+                message["params"]["message"].as_str().unwrap()
+            })
+            .collect();
+        // The exec ID comes first, for cancel_execution
+        assert_eq!(
+            chunks,
+            ["exec_id: 1", "print(1)\n", "sleep 0.5\n", "print(2)\n"]
+        );
+
+        // The result still carries the whole output
+        assert_eq!(result["id"], 2);
+        let text = result["result"]["content"][0]["text"].as_str().unwrap();
+        let exec: serde_json::Value = serde_json::from_str(text).unwrap();
+        assert_eq!(exec["exit_code"], 0);
+        assert_eq!(exec["stdout"], "print(1)\nsleep 0.5\nprint(2)\n");
+
+        server.manager().destroy_all().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_root_page_hides_disabled_tools() {
+        let config = BouvetConfig {
+            capabilities: bouvet_core::SandboxCapabilities::read_only(),
+            ..Default::default()
+        };
+        let page = render_root_page(&BouvetServer::new(config));
+
+        assert!(page.contains("<code>read_file</code>"));
+        assert!(!page.contains("<code>run_command</code>"));
+        assert!(page.contains("Exec: off"));
+        assert!(page.contains("http://localhost:8080/mcp"));
+    }
 }
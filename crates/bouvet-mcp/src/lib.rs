@@ -49,19 +49,43 @@
 //! |------|-------------|
 //! | `create_sandbox` | Create new isolated sandbox |
 //! | `destroy_sandbox` | Destroy sandbox and release resources |
+//! | `destroy_sandbox_by_key` | Destroy sandbox by its client-supplied key |
 //! | `list_sandboxes` | List all active sandboxes |
-//! | `execute_code` | Execute code in language (python, node, bash) |
+//! | `health_check_all` | Ping every sandbox and report which are healthy |
+//! | `get_resource_totals` | Total vCPUs and memory of active sandboxes |
+//! | `get_tool_metrics` | Per-tool call counts, error rates, latency |
+//! | `get_pool_stats` | Warm pool hits and create latency by source |
+//! | `execute_code` | Execute code in language (python, node, bash, go, rust, ...) |
+//! | `execute_project` | Execute a multi-file program |
 //! | `run_command` | Execute shell command |
+//! | `run_command_expect` | Execute shell command, fail on unexpected exit code |
+//! | `run_argv` | Execute program directly, without a shell |
+//! | `cancel_execution` | Send SIGTERM or SIGKILL to a streamed command |
 //! | `read_file` | Read file from sandbox |
+//! | `download_file` | Download a file as base64, with size and SHA-256 |
+//! | `export_workspace` | Archive a directory as a `.tar.gz`, base64 or to a host path |
+//! | `tail_file` | Read the last lines of a file |
 //! | `write_file` | Write file to sandbox |
+//! | `write_files` | Write several files, with per-file results |
+//! | `touch_file` | Create empty file or update its mtime |
+//! | `resize_scratch` | Grow the scratch drive and its filesystem |
 //! | `list_directory` | List directory contents |
+//! | `check_connectivity` | TCP reachability check from inside the sandbox |
+//! | `os_info` | Guest distribution from /etc/os-release |
 
 mod config;
+mod health;
 pub mod http;
+mod metrics;
 mod server;
+mod shutdown;
 mod types;
 
-pub use config::{ConfigError, BouvetConfig, TransportMode, MAX_COMMAND_LENGTH, MAX_INPUT_SIZE_BYTES};
+pub use config::{
+    BouvetConfig, ConfigError, TransportMode, MAX_COMMAND_LENGTH, MAX_INPUT_SIZE_BYTES,
+};
 pub use http::build_router;
+pub use metrics::{CreateLatencyStats, CreateSource, ToolMetrics, ToolStats};
 pub use server::BouvetServer;
+pub use shutdown::{shutdown_signal, ShutdownSignal};
 pub use types::*;
@@ -10,10 +10,9 @@
 //! - **stdio**: Only stdio transport
 //! - **http**: Only HTTP/SSE transport
 
-use bouvet_mcp::{http, BouvetConfig, BouvetServer};
+use bouvet_mcp::{http, shutdown_signal, BouvetConfig, BouvetServer};
 use rmcp::transport::stdio;
 use rmcp::ServiceExt;
-use tokio::signal;
 use tokio::sync::broadcast;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
@@ -37,9 +36,21 @@ async fn main() -> anyhow::Result<()> {
     // Create the server
     let server = BouvetServer::new(config.clone());
 
+    // Recover sandboxes left running by a previous server process
+    match server.manager().reattach_all().await {
+        Ok(ids) if !ids.is_empty() => {
+            tracing::info!(count = ids.len(), "Reattached surviving sandboxes");
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!(error = %e, "Failed to reattach surviving sandboxes"),
+    }
+
     // Start the warm pool filler (if enabled)
     server.start_pool().await;
 
+    // Destroy sandboxes left idle (if a TTL is configured)
+    server.start_reaper();
+
     // Create shutdown broadcast channel
     let (shutdown_tx, _) = broadcast::channel::<()>(1);
 
@@ -115,9 +126,9 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
-    // Wait for shutdown signal
-    signal::ctrl_c().await?;
-    tracing::info!("Received shutdown signal, cleaning up...");
+    // Wait for shutdown signal (Ctrl-C or SIGTERM)
+    let signal = shutdown_signal().await?;
+    tracing::info!(%signal, "Received shutdown signal, cleaning up...");
 
     // Broadcast shutdown to all transports
     let _ = shutdown_tx.send(());
@@ -125,11 +136,16 @@ async fn main() -> anyhow::Result<()> {
     // Shutdown the warm pool
     cleanup_server.shutdown_pool().await;
 
-    // Destroy all managed sandboxes
-    if let Err(e) = cleanup_manager.destroy_all().await {
-        tracing::error!(error = %e, "Error during sandbox cleanup");
-    } else {
-        tracing::info!("All sandboxes cleaned up");
+    // Destroy all managed sandboxes, force-killing stragglers at the deadline
+    match cleanup_manager
+        .destroy_all_within(config.shutdown_timeout)
+        .await
+    {
+        Ok(killed) if !killed.is_empty() => {
+            tracing::warn!(count = killed.len(), "Some sandboxes were force-killed");
+        }
+        Ok(_) => tracing::info!("All sandboxes cleaned up"),
+        Err(e) => tracing::error!(error = %e, "Error during sandbox cleanup"),
     }
 
     // Wait for transport handles to complete
@@ -0,0 +1,352 @@
+//! Per-tool call metrics.
+//!
+//! Counts invocations, errors and cumulative latency for every MCP tool,
+//! exposed through the `get_tool_metrics` tool and the `/metrics` endpoint.
+//! Sandbox creation latency is also tracked as a histogram split by whether
+//! the sandbox came from the warm pool or a cold start.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Counters for a single tool.
+#[derive(Debug, Default)]
+struct ToolCounters {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    latency_us: AtomicU64,
+}
+
+/// Snapshot of a tool's counters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolStats {
+    /// Tool name.
+    pub tool: String,
+    /// Number of invocations.
+    pub calls: u64,
+    /// Number of invocations that returned an error.
+    pub errors: u64,
+    /// Cumulative time spent in the tool.
+    pub total_latency: Duration,
+}
+
+impl ToolStats {
+    /// Fraction of calls that failed (0.0 if never called).
+    pub fn error_rate(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.calls as f64
+        }
+    }
+
+    /// Mean latency per call (zero if never called).
+    pub fn avg_latency(&self) -> Duration {
+        if self.calls == 0 {
+            return Duration::ZERO;
+        }
+        let nanos = self.total_latency.as_nanos() / u128::from(self.calls);
+        Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+    }
+}
+
+/// Upper bounds of the create latency histogram buckets, in seconds.
+pub const CREATE_LATENCY_BUCKETS: [f64; 8] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Where a created sandbox came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreateSource {
+    /// Acquired from the warm pool.
+    Pool,
+    /// Booted on demand (pool disabled, empty or bypassed).
+    ColdStart,
+}
+
+impl CreateSource {
+    /// All sources, in reporting order.
+    pub const ALL: [CreateSource; 2] = [CreateSource::Pool, CreateSource::ColdStart];
+
+    /// Label used in logs and metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CreateSource::Pool => "pool",
+            CreateSource::ColdStart => "cold-start",
+        }
+    }
+}
+
+/// Atomic latency histogram with the [`CREATE_LATENCY_BUCKETS`] bounds.
+#[derive(Debug, Default)]
+struct LatencyHistogram {
+    /// Per-bucket counts (not cumulative); the last slot is `+Inf`.
+    buckets: [AtomicU64; CREATE_LATENCY_BUCKETS.len() + 1],
+    count: AtomicU64,
+    sum_us: AtomicU64,
+}
+
+impl LatencyHistogram {
+    fn observe(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        let index = CREATE_LATENCY_BUCKETS
+            .iter()
+            .position(|&bound| secs <= bound)
+            .unwrap_or(CREATE_LATENCY_BUCKETS.len());
+        self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        self.sum_us.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self, source: CreateSource) -> CreateLatencyStats {
+        let mut cumulative = 0;
+        let buckets = self
+            .buckets
+            .iter()
+            .map(|bucket| {
+                cumulative += bucket.load(Ordering::Relaxed);
+                cumulative
+            })
+            .collect();
+        CreateLatencyStats {
+            source,
+            count: self.count.load(Ordering::Relaxed),
+            total_latency: Duration::from_micros(self.sum_us.load(Ordering::Relaxed)),
+            buckets,
+        }
+    }
+}
+
+/// Snapshot of the create latency histogram for one source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateLatencyStats {
+    /// Where the sandboxes came from.
+    pub source: CreateSource,
+    /// Number of sandboxes created.
+    pub count: u64,
+    /// Cumulative creation time.
+    pub total_latency: Duration,
+    /// Cumulative bucket counts, one per [`CREATE_LATENCY_BUCKETS`] bound
+    /// followed by `+Inf` (which equals `count`).
+    pub buckets: Vec<u64>,
+}
+
+impl CreateLatencyStats {
+    /// Mean creation time (zero if nothing was created).
+    pub fn avg_latency(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        let nanos = self.total_latency.as_nanos() / u128::from(self.count);
+        Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+    }
+}
+
+/// Atomic call and error counters keyed by tool name.
+///
+/// The set of tools is fixed at construction, so recording never takes a
+/// lock. Calls to unknown tool names are not recorded.
+#[derive(Debug, Default)]
+pub struct ToolMetrics {
+    tools: HashMap<String, ToolCounters>,
+    /// Create latency, indexed like [`CreateSource::ALL`].
+    creates: [LatencyHistogram; 2],
+}
+
+impl ToolMetrics {
+    /// Create metrics for the given tool names, all counters at zero.
+    pub fn new<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            tools: names
+                .into_iter()
+                .map(|name| (name.into(), ToolCounters::default()))
+                .collect(),
+            creates: Default::default(),
+        }
+    }
+
+    /// Record one invocation of `tool`.
+    pub fn record(&self, tool: &str, elapsed: Duration, is_error: bool) {
+        let Some(counters) = self.tools.get(tool) else {
+            return;
+        };
+        counters.calls.fetch_add(1, Ordering::Relaxed);
+        if is_error {
+            counters.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        counters.latency_us.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    /// Record a successful sandbox creation and how long it took.
+    pub fn record_create(&self, source: CreateSource, elapsed: Duration) {
+        self.creates[source as usize].observe(elapsed);
+    }
+
+    /// Get the create latency histogram for each source.
+    pub fn create_latency(&self) -> Vec<CreateLatencyStats> {
+        CreateSource::ALL
+            .iter()
+            .map(|&source| self.creates[source as usize].snapshot(source))
+            .collect()
+    }
+
+    /// Get the current counters for every tool, sorted by name.
+    pub fn snapshot(&self) -> Vec<ToolStats> {
+        let mut stats: Vec<ToolStats> = self
+            .tools
+            .iter()
+            .map(|(tool, counters)| ToolStats {
+                tool: tool.clone(),
+                calls: counters.calls.load(Ordering::Relaxed),
+                errors: counters.errors.load(Ordering::Relaxed),
+                total_latency: Duration::from_micros(counters.latency_us.load(Ordering::Relaxed)),
+            })
+            .collect();
+        stats.sort_by(|a, b| a.tool.cmp(&b.tool));
+        stats
+    }
+
+    /// Render the counters in the Prometheus text exposition format.
+    pub fn render_prometheus(&self) -> String {
+        let stats = self.snapshot();
+        let mut out = String::new();
+
+        write_counter(
+            &mut out,
+            "bouvet_tool_calls_total",
+            "Total MCP tool invocations.",
+            &stats,
+            |s| s.calls.to_string(),
+        );
+        write_counter(
+            &mut out,
+            "bouvet_tool_errors_total",
+            "MCP tool invocations that returned an error.",
+            &stats,
+            |s| s.errors.to_string(),
+        );
+        write_counter(
+            &mut out,
+            "bouvet_tool_latency_seconds_total",
+            "Cumulative time spent in MCP tools.",
+            &stats,
+            |s| s.total_latency.as_secs_f64().to_string(),
+        );
+        write_create_histogram(&mut out, &self.create_latency());
+        out
+    }
+}
+
+/// Write one Prometheus counter with a series per tool.
+fn write_counter(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    stats: &[ToolStats],
+    value: impl Fn(&ToolStats) -> String,
+) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} counter");
+    for s in stats {
+        let _ = writeln!(out, "{name}{{tool=\"{}\"}} {}", s.tool, value(s));
+    }
+}
+
+/// Write the create latency histogram with a series per source.
+fn write_create_histogram(out: &mut String, stats: &[CreateLatencyStats]) {
+    let name = "bouvet_sandbox_create_seconds";
+    let _ = writeln!(
+        out,
+        "# HELP {name} Sandbox creation latency by source (pool or cold-start)."
+    );
+    let _ = writeln!(out, "# TYPE {name} histogram");
+    for s in stats {
+        let source = s.source.as_str();
+        let bounds = CREATE_LATENCY_BUCKETS
+            .iter()
+            .map(|b| b.to_string())
+            .chain(std::iter::once("+Inf".to_string()));
+        for (le, count) in bounds.zip(&s.buckets) {
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{source=\"{source}\",le=\"{le}\"}} {count}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{name}_sum{{source=\"{source}\"}} {}",
+            s.total_latency.as_secs_f64()
+        );
+        let _ = writeln!(out, "{name}_count{{source=\"{source}\"}} {}", s.count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_counts_calls_and_errors() {
+        let metrics = ToolMetrics::new(["read_file", "run_command"]);
+        metrics.record("read_file", Duration::from_millis(10), false);
+        metrics.record("read_file", Duration::from_millis(30), true);
+        metrics.record("unknown_tool", Duration::from_millis(5), true);
+
+        let stats = metrics.snapshot();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].tool, "read_file");
+        assert_eq!(stats[0].calls, 2);
+        assert_eq!(stats[0].errors, 1);
+        assert_eq!(stats[0].avg_latency(), Duration::from_millis(20));
+        assert_eq!(stats[0].error_rate(), 0.5);
+        assert_eq!(stats[1].calls, 0);
+        assert_eq!(stats[1].error_rate(), 0.0);
+    }
+
+    #[test]
+    fn test_render_prometheus() {
+        let metrics = ToolMetrics::new(["list_sandboxes"]);
+        metrics.record("list_sandboxes", Duration::from_millis(500), true);
+
+        let text = metrics.render_prometheus();
+        assert!(text.contains("# TYPE bouvet_tool_calls_total counter"));
+        assert!(text.contains("bouvet_tool_calls_total{tool=\"list_sandboxes\"} 1"));
+        assert!(text.contains("bouvet_tool_errors_total{tool=\"list_sandboxes\"} 1"));
+        assert!(text.contains("bouvet_tool_latency_seconds_total{tool=\"list_sandboxes\"} 0.5"));
+    }
+
+    #[test]
+    fn test_record_create_splits_by_source() {
+        let metrics = ToolMetrics::new(["create_sandbox"]);
+        metrics.record_create(CreateSource::Pool, Duration::from_millis(40));
+        metrics.record_create(CreateSource::Pool, Duration::from_millis(60));
+        metrics.record_create(CreateSource::ColdStart, Duration::from_secs(3));
+
+        let stats = metrics.create_latency();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].source, CreateSource::Pool);
+        assert_eq!(stats[0].count, 2);
+        assert_eq!(stats[0].avg_latency(), Duration::from_millis(50));
+        // 40ms lands in the 0.05s bucket, 60ms in 0.1s; buckets are cumulative
+        assert_eq!(stats[0].buckets, [1, 2, 2, 2, 2, 2, 2, 2, 2]);
+        assert_eq!(stats[1].source, CreateSource::ColdStart);
+        assert_eq!(stats[1].count, 1);
+        assert_eq!(stats[1].buckets, [0, 0, 0, 0, 0, 0, 1, 1, 1]);
+
+        let text = metrics.render_prometheus();
+        assert!(text.contains("# TYPE bouvet_sandbox_create_seconds histogram"));
+        assert!(
+            text.contains("bouvet_sandbox_create_seconds_bucket{source=\"pool\",le=\"0.05\"} 1")
+        );
+        assert!(text.contains("bouvet_sandbox_create_seconds_count{source=\"pool\"} 2"));
+        assert!(text
+            .contains("bouvet_sandbox_create_seconds_bucket{source=\"cold-start\",le=\"2.5\"} 0"));
+        assert!(text.contains("bouvet_sandbox_create_seconds_count{source=\"cold-start\"} 1"));
+        assert!(text.contains("bouvet_sandbox_create_seconds_sum{source=\"cold-start\"} 3"));
+    }
+}
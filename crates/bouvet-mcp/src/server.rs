@@ -3,14 +3,25 @@
 //! This module implements the core MCP server manually implementing ServerHandler
 //! to expose sandbox lifecycle, code execution, and file operation tools.
 
-use crate::config::{BouvetConfig, MAX_COMMAND_LENGTH, MAX_INPUT_SIZE_BYTES};
+use crate::config::{
+    BouvetConfig, DEFAULT_CONNECT_TIMEOUT_MS, MAX_COMMAND_LENGTH, MAX_CONNECT_TIMEOUT_MS,
+    MAX_EXPORT_SIZE_BYTES, MAX_INPUT_SIZE_BYTES, MAX_STREAMED_OUTPUT_BYTES, MAX_TAIL_LINES,
+};
+use crate::metrics::{CreateSource, ToolMetrics};
 use crate::types::*;
 
-use bouvet_core::{ManagerConfig, PoolConfig, SandboxConfig, SandboxManager, SandboxPool};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use bouvet_common::Redactor;
+use bouvet_core::{
+    CoreError, ExecOptions, ExecResult, ExecStream, ListDirOptions, ManagerConfig, OutputStream,
+    PoolConfig, PoolEvent, ProxyConfig, SandboxCapabilities, SandboxManager, SandboxPool, Signal,
+    SortBy,
+};
 use rmcp::{
     handler::server::ServerHandler,
     model::*,
-    service::{RequestContext, RoleServer},
+    service::{Peer, RequestContext, RoleServer},
     ErrorData,
 };
 use schemars::schema_for;
@@ -31,6 +42,77 @@ pub struct BouvetServer {
 
     /// Warm sandbox pool (optional, based on config)
     pool: Option<Arc<TokioMutex<SandboxPool>>>,
+
+    /// Per-tool call and error counters
+    metrics: Arc<ToolMetrics>,
+
+    /// Masks secrets in logged code and commands
+    redactor: Arc<Redactor>,
+}
+
+/// Sends a tool call's output to the client while the command runs.
+///
+/// Each chunk becomes a `notifications/progress` message for the call's
+/// progress token; over streamable HTTP these arrive on the call's SSE
+/// response ahead of its result.
+#[derive(Clone)]
+struct OutputProgress {
+    peer: Peer<RoleServer>,
+    token: ProgressToken,
+}
+
+impl OutputProgress {
+    /// Forward `stream`'s output until the command exits.
+    ///
+    /// The first notification announces the command's exec ID, as
+    /// `exec_id: N`, for `cancel_execution`. The returned result also
+    /// carries the output, up to [`MAX_STREAMED_OUTPUT_BYTES`] per stream,
+    /// for clients that only read the final result.
+    async fn forward(&self, mut stream: ExecStream) -> Result<ExecResult, CoreError> {
+        let mut stdout = String::new();
+        let mut stderr = String::new();
+        let mut sent = 0u64;
+        self.notify(0, format!("exec_id: {}", stream.id())).await;
+        while let Some(chunk) = stream.next().await {
+            let buf = match chunk.stream {
+                OutputStream::Stdout => &mut stdout,
+                OutputStream::Stderr => &mut stderr,
+            };
+            push_capped(buf, &chunk.data, MAX_STREAMED_OUTPUT_BYTES);
+            sent += chunk.data.len() as u64;
+            self.notify(sent, chunk.data).await;
+        }
+
+        let mut result = stream.finish().await?;
+        // The result only has a timeout note on stderr, after the output
+        stderr.push_str(&result.stderr);
+        result.stdout = stdout;
+        result.stderr = stderr;
+        Ok(result)
+    }
+
+    /// Send a progress notification, `progress` being the bytes sent so far.
+    async fn notify(&self, progress: u64, message: String) {
+        let notification = ProgressNotificationParam {
+            progress_token: self.token.clone(),
+            progress: progress as f64,
+            total: None,
+            message: Some(message),
+        };
+        // Keep draining if the client went away, so the command can finish
+        if let Err(e) = self.peer.notify_progress(notification).await {
+            tracing::debug!(error = %e, "Failed to send output progress");
+        }
+    }
+}
+
+/// Append `data` to `buf` without letting it grow past `max` bytes.
+fn push_capped(buf: &mut String, data: &str, max: usize) {
+    let mut end = max.saturating_sub(buf.len()).min(data.len());
+    while !data.is_char_boundary(end) {
+        end -= 1;
+    }
+    buf.push_str(&data[..end]);
 }
 
 impl BouvetServer {
@@ -43,12 +125,13 @@ impl BouvetServer {
             "Creating BouvetServer"
         );
 
-        let manager_config = ManagerConfig::new(
+        let mut manager_config = ManagerConfig::new(
             &config.kernel_path,
             &config.rootfs_path,
             &config.firecracker_path,
             &config.chroot_path,
         );
+        manager_config.default_ttl = config.sandbox_idle_ttl;
 
         let manager = Arc::new(SandboxManager::new(manager_config));
 
@@ -57,9 +140,8 @@ impl BouvetServer {
             let pool_config = PoolConfig {
                 min_size: config.pool_min_size,
                 max_concurrent_boots: config.pool_max_boots,
-                sandbox_config: SandboxConfig::builder()
-                    .kernel(&config.kernel_path)
-                    .rootfs(&config.rootfs_path)
+                sandbox_config: config
+                    .sandbox_config_builder()
                     .build()
                     .expect("valid sandbox config from validated paths"),
                 ..Default::default()
@@ -68,6 +150,8 @@ impl BouvetServer {
                 pool_enabled = true,
                 min_size = config.pool_min_size,
                 max_boots = config.pool_max_boots,
+                memory_mib = config.default_memory_mib,
+                vcpu_count = config.default_vcpu,
                 "Warm pool configured"
             );
             Some(Arc::new(TokioMutex::new(SandboxPool::new(pool_config))))
@@ -76,11 +160,32 @@ impl BouvetServer {
             None
         };
 
+        // Key metrics by every known tool, including gated ones
+        let metrics = Arc::new(ToolMetrics::new(
+            Self::build_tools_list(&SandboxCapabilities::default())
+                .into_iter()
+                .chain(Self::build_admin_tools_list())
+                .map(|tool| tool.name),
+        ));
+
+        let redactor = Arc::new(Redactor::new(&config.redact_patterns));
+
         tracing::debug!("BouvetServer created");
         Self {
             manager,
             config,
             pool,
+            metrics,
+            redactor,
+        }
+    }
+
+    /// Replace the sandbox manager, e.g. with one on a mock backend.
+    #[cfg(test)]
+    pub(crate) fn with_manager(self, manager: SandboxManager) -> Self {
+        Self {
+            manager: Arc::new(manager),
+            ..self
         }
     }
 
@@ -89,11 +194,35 @@ impl BouvetServer {
     /// Call this after creating the server to begin pre-warming sandboxes.
     pub async fn start_pool(&self) {
         if let Some(pool) = &self.pool {
-            pool.lock().await.start();
+            let mut pool = pool.lock().await;
+            tokio::spawn(Self::log_pool_events(pool.subscribe()));
+            pool.start();
             tracing::info!("Warm pool started");
         }
     }
 
+    /// Start destroying sandboxes idle longer than `sandbox_idle_ttl`, if
+    /// one is configured.
+    pub fn start_reaper(&self) {
+        if self.config.sandbox_idle_ttl.is_some() {
+            self.manager.start_reaper();
+        }
+    }
+
+    /// Log pool events at debug level until the pool is dropped.
+    async fn log_pool_events(mut events: tokio::sync::broadcast::Receiver<PoolEvent>) {
+        use tokio::sync::broadcast::error::RecvError;
+        loop {
+            match events.recv().await {
+                Ok(event) => tracing::debug!(event = ?event, "Pool event"),
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "Pool event log fell behind")
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+
     /// Gracefully shutdown the warm pool.
     ///
     /// Call this before stopping the server to clean up pooled sandboxes.
@@ -125,6 +254,29 @@ impl BouvetServer {
         &self.config
     }
 
+    /// Number of warm sandboxes in the pool, or `None` if pooling is
+    /// disabled.
+    pub async fn pool_size(&self) -> Option<usize> {
+        match &self.pool {
+            Some(pool) => Some(pool.lock().await.size().await),
+            None => None,
+        }
+    }
+
+    /// Get the per-tool call metrics.
+    pub fn tool_metrics(&self) -> &ToolMetrics {
+        &self.metrics
+    }
+
+    /// Get the tools advertised to clients, given the configured capabilities.
+    pub fn tools(&self) -> Vec<Tool> {
+        let mut tools = Self::build_tools_list(&self.config.capabilities);
+        if self.config.admin_tools {
+            tools.extend(Self::build_admin_tools_list());
+        }
+        tools
+    }
+
     /// Parse a sandbox ID from string.
     /// Uses a generic error message to prevent ID enumeration.
     fn parse_sandbox_id(id: &str) -> Result<bouvet_core::SandboxId, String> {
@@ -133,12 +285,26 @@ impl BouvetServer {
             .map_err(|_| "Sandbox not found or invalid ID".to_string())
     }
 
-    /// Truncate sensitive content for logging.
-    fn truncate_for_log(s: &str, max_len: usize) -> String {
-        if s.len() <= max_len {
-            s.to_string()
+    /// Convert a command's resource usage for a tool result.
+    fn usage_info(usage: bouvet_core::ResourceUsage) -> ResourceUsageInfo {
+        ResourceUsageInfo {
+            max_rss_kib: usage.max_rss_kib,
+            user_time_ms: usage.user_time_ms,
+            system_time_ms: usage.system_time_ms,
+        }
+    }
+
+    /// Redact and truncate sensitive content for logging.
+    fn truncate_for_log(&self, s: &str, max_len: usize) -> String {
+        let redacted = self.redactor.redact(s);
+        if redacted.len() <= max_len {
+            redacted
         } else {
-            format!("{}... ({} bytes total)", &s[..max_len], s.len())
+            let mut end = max_len;
+            while !redacted.is_char_boundary(end) {
+                end -= 1;
+            }
+            format!("{}... ({} bytes total)", &redacted[..end], s.len())
         }
     }
 
@@ -156,6 +322,66 @@ impl BouvetServer {
         }
     }
 
+    /// Resolve an input given either as plain text or base64-encoded as
+    /// `<field>_base64`, and validate its decoded size.
+    fn resolve_input(
+        plain: Option<String>,
+        encoded: Option<String>,
+        field_name: &str,
+        max_bytes: usize,
+    ) -> Result<String, String> {
+        let value = match (plain, encoded) {
+            (Some(_), Some(_)) => {
+                return Err(format!(
+                    "Give either {field_name} or {field_name}_base64, not both"
+                ))
+            }
+            (None, None) => {
+                return Err(format!(
+                    "Missing required parameter: {field_name} or {field_name}_base64"
+                ))
+            }
+            (Some(value), None) => value,
+            (None, Some(encoded)) => {
+                // Reject oversized input before decoding it
+                Self::validate_size(&encoded, max_bytes.div_ceil(3) * 4, field_name)?;
+                let bytes = BASE64
+                    .decode(encoded.trim())
+                    .map_err(|e| format!("Invalid base64 in {field_name}_base64: {e}"))?;
+                String::from_utf8(bytes)
+                    .map_err(|_| format!("{field_name}_base64 does not decode to UTF-8"))?
+            }
+        };
+        Self::validate_size(&value, max_bytes, field_name)?;
+        Ok(value)
+    }
+
+    /// Parse a tool's arguments, treating missing arguments as `{}`.
+    ///
+    /// On failure the error names the problem and every required field, so
+    /// a caller that sent the wrong shape can correct it in one go.
+    fn parse_params<T: serde::de::DeserializeOwned + schemars::JsonSchema>(
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> Result<T, CallToolResult> {
+        let value = serde_json::Value::Object(args.unwrap_or_default());
+        serde_json::from_value(value).map_err(|e| {
+            let required = schema_for!(T)
+                .schema
+                .object
+                .map(|object| object.required.into_iter().collect::<Vec<_>>())
+                .unwrap_or_default();
+            tracing::warn!(params = std::any::type_name::<T>(), error = %e, "Invalid tool parameters");
+            Self::error_result(format!(
+                "Invalid parameters: {e}. Required: {}",
+                if required.is_empty() {
+                    "none".to_string()
+                } else {
+                    required.join(", ")
+                }
+            ))
+        })
+    }
+
     /// Helper to create success result with JSON content
     fn json_result<T: serde::Serialize>(data: &T) -> CallToolResult {
         match serde_json::to_string_pretty(data) {
@@ -171,6 +397,32 @@ impl BouvetServer {
         CallToolResult::error(vec![Content::text(message.into())])
     }
 
+    /// Helper to create an error result with a JSON payload
+    fn json_error_result<T: serde::Serialize>(data: &T) -> CallToolResult {
+        match serde_json::to_string_pretty(data) {
+            Ok(json) => CallToolResult::error(vec![Content::text(json)]),
+            Err(e) => Self::error_result(format!("JSON serialization error: {e}")),
+        }
+    }
+
+    /// Describe a sandbox creation failure, naming the step that failed.
+    fn create_error(e: &CoreError) -> CreateSandboxError {
+        let phase = match e {
+            CoreError::DuplicateKey(_) => "register",
+            CoreError::PoolShuttingDown => "pool",
+            CoreError::Vm(_) => "vm_boot",
+            CoreError::BootTimeout(_) => "agent_wait",
+            CoreError::BootBudgetExceeded { .. } => "boot_budget",
+            CoreError::ConnectFailed(_) | CoreError::IncompatibleProtocol { .. } => "agent_connect",
+            _ => "create",
+        };
+        CreateSandboxError {
+            phase: phase.to_string(),
+            kind: e.kind().to_string(),
+            message: format!("Failed to create sandbox: {e}"),
+        }
+    }
+
     /// Convert schemars RootSchema to rmcp JsonObject
     fn schema_to_json_object<T: schemars::JsonSchema>(
     ) -> Arc<serde_json::Map<String, serde_json::Value>> {
@@ -198,19 +450,88 @@ impl BouvetServer {
         &self,
         args: Option<serde_json::Map<String, serde_json::Value>>,
     ) -> CallToolResult {
-        let params: CreateSandboxParams = args
-            .and_then(|a| serde_json::from_value(serde_json::Value::Object(a)).ok())
-            .unwrap_or_default();
+        let params: CreateSandboxParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
 
         let start = std::time::Instant::now();
         tracing::info!(
             memory_mib = params.memory_mib,
             vcpu_count = params.vcpu_count,
+            key = ?params.key,
+            allow_exec = ?params.allow_exec,
+            allow_write = ?params.allow_write,
+            kernel_path = ?params.kernel_path,
+            rootfs_path = ?params.rootfs_path,
+            max_boot_ms = ?params.max_boot_ms,
+            work_dir = ?params.work_dir,
+            no_proxy = ?params.no_proxy,
+            labels = ?params.labels,
             "Tool: create_sandbox"
         );
 
-        // Try to acquire from warm pool first
-        if let Some(pool) = &self.pool {
+        // Proxy URLs may carry credentials, so they stay out of the log
+        let proxy = ProxyConfig {
+            http_proxy: params.http_proxy,
+            https_proxy: params.https_proxy,
+            no_proxy: params.no_proxy,
+        };
+
+        // Check custom images before doing anything else
+        let image = |path: &Option<String>| {
+            path.as_deref()
+                .map(|p| self.config.resolve_image(p))
+                .transpose()
+        };
+        let (kernel_path, rootfs_path) =
+            match (image(&params.kernel_path), image(&params.rootfs_path)) {
+                (Ok(kernel), Ok(rootfs)) => (kernel, rootfs),
+                (Err(e), _) | (_, Err(e)) => {
+                    tracing::warn!(error = %e, "Rejected custom image");
+                    return Self::json_error_result(&CreateSandboxError {
+                        phase: "config".to_string(),
+                        kind: "invalid_image".to_string(),
+                        message: e,
+                    });
+                }
+            };
+
+        // Requested capabilities can only narrow the server's
+        let capabilities = self.config.capabilities.intersect(&SandboxCapabilities {
+            exec: params.allow_exec.unwrap_or(true),
+            write: params.allow_write.unwrap_or(true),
+            network: true,
+        });
+
+        // Idempotent create: reuse the sandbox already bound to this key
+        if let Some(key) = &params.key {
+            if let Some(id) = self.manager.find_by_key(key).await {
+                tracing::info!(sandbox_id = %id, key = %key, "Sandbox already exists for key");
+                return Self::json_result(&CreateSandboxResult {
+                    sandbox_id: id.to_string(),
+                });
+            }
+        }
+
+        // Try to acquire from warm pool first (pooled sandboxes use the
+        // server's capabilities, images and default size, and have no
+        // labels, so restricted or resized sandboxes, custom images, work
+        // dirs, proxies and labels always cold-start)
+        if let Some(pool) = self.pool.as_ref().filter(|_| {
+            capabilities == self.config.capabilities
+                && params
+                    .memory_mib
+                    .is_none_or(|mib| mib == self.config.default_memory_mib)
+                && params
+                    .vcpu_count
+                    .is_none_or(|count| count == self.config.default_vcpu)
+                && kernel_path.is_none()
+                && rootfs_path.is_none()
+                && params.work_dir.is_none()
+                && proxy.is_empty()
+                && params.labels.is_empty()
+        }) {
             tracing::debug!("Attempting to acquire from warm pool");
             let acquire_result = {
                 let pool_guard = pool.lock().await;
@@ -220,12 +541,26 @@ impl BouvetServer {
             match acquire_result {
                 Ok(sandbox) => {
                     // Register the pooled sandbox with manager for lifecycle tracking
-                    match self.manager.register(sandbox).await {
+                    let pooled_id = sandbox.id();
+                    let registered = match &params.key {
+                        Some(key) => self.manager.register_with_key(sandbox, key).await,
+                        None => self.manager.register(sandbox).await,
+                    };
+                    match registered {
+                        Ok(id) if id != pooled_id => {
+                            // A concurrent create bound the key first
+                            tracing::info!(sandbox_id = %id, key = ?params.key, "Sandbox already exists for key");
+                            return Self::json_result(&CreateSandboxResult {
+                                sandbox_id: id.to_string(),
+                            });
+                        }
                         Ok(id) => {
+                            let elapsed = start.elapsed();
+                            self.metrics.record_create(CreateSource::Pool, elapsed);
                             tracing::info!(
                                 sandbox_id = %id,
-                                elapsed_ms = start.elapsed().as_millis() as u64,
-                                source = "pool",
+                                elapsed_ms = elapsed.as_millis() as u64,
+                                source = CreateSource::Pool.as_str(),
                                 "Sandbox created"
                             );
                             return Self::json_result(&CreateSandboxResult {
@@ -242,6 +577,11 @@ impl BouvetServer {
                         }
                     }
                 }
+                Err(e @ CoreError::PoolShuttingDown) => {
+                    // A cold start now would boot a VM the draining server never destroys
+                    tracing::info!("Rejecting sandbox creation while the pool shuts down");
+                    return Self::json_error_result(&Self::create_error(&e));
+                }
                 Err(e) => {
                     tracing::debug!(error = %e, "Pool acquire failed, falling back to cold-start");
                 }
@@ -250,9 +590,13 @@ impl BouvetServer {
 
         // Fallback: cold-start path (original behavior)
         tracing::debug!("Creating sandbox via cold-start");
-        let mut config_builder = SandboxConfig::builder()
-            .kernel(&self.config.kernel_path)
-            .rootfs(&self.config.rootfs_path);
+        let mut config_builder = self
+            .config
+            .sandbox_config_builder()
+            .kernel(kernel_path.as_ref().unwrap_or(&self.config.kernel_path))
+            .rootfs(rootfs_path.as_ref().unwrap_or(&self.config.rootfs_path))
+            .capabilities(capabilities)
+            .proxy(proxy);
 
         if let Some(memory) = params.memory_mib {
             config_builder = config_builder.memory_mib(memory);
@@ -262,20 +606,43 @@ impl BouvetServer {
             config_builder = config_builder.vcpu_count(vcpus);
         }
 
+        if let Some(max_boot_ms) = params.max_boot_ms {
+            config_builder = config_builder.max_boot_ms(max_boot_ms);
+        }
+
+        if let Some(work_dir) = &params.work_dir {
+            config_builder = config_builder.work_dir(work_dir);
+        }
+
+        for (key, value) in params.labels {
+            config_builder = config_builder.label(key, value);
+        }
+
         let sandbox_config = match config_builder.build() {
             Ok(c) => c,
             Err(e) => {
                 tracing::warn!(error = %e, "Invalid sandbox configuration");
-                return Self::error_result(format!("Invalid sandbox configuration: {e}"));
+                return Self::json_error_result(&CreateSandboxError {
+                    phase: "config".to_string(),
+                    kind: "invalid_config".to_string(),
+                    message: format!("Invalid sandbox configuration: {e}"),
+                });
             }
         };
 
-        match self.manager.create(sandbox_config).await {
+        let created = match &params.key {
+            Some(key) => self.manager.create_with_key(key, sandbox_config).await,
+            None => self.manager.create(sandbox_config).await,
+        };
+
+        match created {
             Ok(id) => {
+                let elapsed = start.elapsed();
+                self.metrics.record_create(CreateSource::ColdStart, elapsed);
                 tracing::info!(
                     sandbox_id = %id,
-                    elapsed_ms = start.elapsed().as_millis() as u64,
-                    source = "cold-start",
+                    elapsed_ms = elapsed.as_millis() as u64,
+                    source = CreateSource::ColdStart.as_str(),
                     "Sandbox created"
                 );
                 Self::json_result(&CreateSandboxResult {
@@ -283,8 +650,9 @@ impl BouvetServer {
                 })
             }
             Err(e) => {
-                tracing::error!(error = %e, "Failed to create sandbox");
-                Self::error_result(format!("Failed to create sandbox: {e}"))
+                let error = Self::create_error(&e);
+                tracing::error!(error = %e, phase = %error.phase, "Failed to create sandbox");
+                Self::json_error_result(&error)
             }
         }
     }
@@ -293,15 +661,9 @@ impl BouvetServer {
         &self,
         args: Option<serde_json::Map<String, serde_json::Value>>,
     ) -> CallToolResult {
-        let params: DestroySandboxParams = match args
-            .map(|a| serde_json::from_value(serde_json::Value::Object(a)))
-            .transpose()
-        {
-            Ok(Some(p)) => p,
-            _ => {
-                tracing::warn!("destroy_sandbox called without sandbox_id");
-                return Self::error_result("Missing required parameter: sandbox_id");
-            }
+        let params: DestroySandboxParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
         };
 
         let start = std::time::Instant::now();
@@ -331,6 +693,34 @@ impl BouvetServer {
         }
     }
 
+    async fn handle_destroy_sandbox_by_key(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> CallToolResult {
+        let params: DestroySandboxByKeyParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        let start = std::time::Instant::now();
+        tracing::info!(key = %params.key, "Tool: destroy_sandbox_by_key");
+
+        match self.manager.destroy_by_key(&params.key).await {
+            Ok(()) => {
+                tracing::info!(
+                    key = %params.key,
+                    elapsed_ms = start.elapsed().as_millis() as u64,
+                    "Sandbox destroyed by key"
+                );
+                Self::json_result(&DestroySandboxResult { success: true })
+            }
+            Err(e) => {
+                tracing::error!(key = %params.key, error = %e, "Failed to destroy sandbox by key");
+                Self::error_result(format!("Failed to destroy sandbox: {e}"))
+            }
+        }
+    }
+
     async fn handle_list_sandboxes(&self) -> CallToolResult {
         tracing::debug!("Tool: list_sandboxes");
 
@@ -342,10 +732,27 @@ impl BouvetServer {
         for id in ids {
             if let Ok(info) = self
                 .manager
-                .with_sandbox(id, |sandbox| SandboxInfo {
-                    sandbox_id: sandbox.id().to_string(),
-                    state: sandbox.state().to_string(),
-                    created_at: sandbox.created_at().to_rfc3339(),
+                .with_sandbox(id, |sandbox| {
+                    let timing = sandbox.creation_timing();
+                    let caps = sandbox.capabilities();
+                    SandboxInfo {
+                        sandbox_id: sandbox.id().to_string(),
+                        state: sandbox.state().to_string(),
+                        created_at: sandbox.created_at().to_rfc3339(),
+                        hostname: sandbox.hostname(),
+                        creation_timing: CreationTimingInfo {
+                            vm_create_ms: timing.vm_create_ms,
+                            agent_connect_ms: timing.agent_connect_ms,
+                            ping_ms: timing.ping_ms,
+                            total_ms: timing.total_ms,
+                        },
+                        capabilities: CapabilitiesInfo {
+                            exec: caps.exec,
+                            write: caps.write,
+                            network: caps.network,
+                        },
+                        labels: sandbox.labels().clone(),
+                    }
                 })
                 .await
             {
@@ -357,37 +764,225 @@ impl BouvetServer {
         Self::json_result(&ListSandboxesResult { sandboxes })
     }
 
-    async fn handle_execute_code(
+    async fn handle_get_sandbox(
         &self,
         args: Option<serde_json::Map<String, serde_json::Value>>,
     ) -> CallToolResult {
-        let params: ExecuteCodeParams = match args
-            .map(|a| serde_json::from_value(serde_json::Value::Object(a)))
-            .transpose()
-        {
-            Ok(Some(p)) => p,
-            _ => {
-                tracing::warn!("execute_code called without required parameters");
-                return Self::error_result(
-                    "Missing required parameters: sandbox_id, language, code",
-                );
+        let params: GetSandboxParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        tracing::debug!(sandbox_id = %params.sandbox_id, "Tool: get_sandbox");
+
+        let id = match Self::parse_sandbox_id(&params.sandbox_id) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::debug!(sandbox_id = %params.sandbox_id, "Invalid sandbox ID");
+                return Self::error_result(e);
             }
         };
 
-        // Validate input sizes
-        if let Err(e) = Self::validate_size(&params.code, MAX_INPUT_SIZE_BYTES, "code") {
-            tracing::warn!(sandbox_id = %params.sandbox_id, error = %e, "Code size validation failed");
-            return Self::error_result(e);
+        match self.manager.info(id).await {
+            Ok(info) => Self::json_result(&GetSandboxResult {
+                sandbox_id: info.id.to_string(),
+                state: info.state.to_string(),
+                created_at: info.created_at.to_rfc3339(),
+                uptime_secs: info.uptime.as_secs(),
+                vcpu_count: info.vcpu_count,
+                memory_mib: info.memory_mib,
+                labels: info.labels,
+            }),
+            Err(e) => {
+                // Same message as a malformed ID, so IDs can't be probed
+                tracing::debug!(sandbox_id = %id, error = %e, "Sandbox not found");
+                Self::error_result("Sandbox not found or invalid ID")
+            }
+        }
+    }
+
+    async fn handle_get_resource_totals(&self) -> CallToolResult {
+        tracing::debug!("Tool: get_resource_totals");
+
+        let totals = self.manager.resource_totals().await;
+        tracing::debug!(
+            count = totals.count,
+            total_vcpus = totals.total_vcpus,
+            total_memory_mib = totals.total_memory_mib,
+            "Computed resource totals"
+        );
+        Self::json_result(&ResourceTotalsResult {
+            count: totals.count,
+            total_vcpus: totals.total_vcpus,
+            total_memory_mib: totals.total_memory_mib,
+        })
+    }
+
+    async fn handle_health_check_all(&self) -> CallToolResult {
+        tracing::debug!("Tool: health_check_all");
+
+        let sandboxes: Vec<SandboxHealth> = self
+            .manager
+            .ping_all()
+            .await
+            .into_iter()
+            .map(|(id, healthy)| SandboxHealth {
+                sandbox_id: id.to_string(),
+                healthy,
+            })
+            .collect();
+        let healthy = sandboxes.iter().filter(|s| s.healthy).count();
+        let unhealthy = sandboxes.len() - healthy;
+        tracing::debug!(healthy, unhealthy, "Health-checked all sandboxes");
+        Self::json_result(&HealthCheckAllResult {
+            healthy,
+            unhealthy,
+            sandboxes,
+        })
+    }
+
+    /// Convert an orphaned VM for a tool result.
+    fn orphan_info(orphan: bouvet_core::OrphanVm) -> OrphanVmInfo {
+        OrphanVmInfo {
+            sandbox_id: orphan.id.to_string(),
+            socket_path: orphan.socket_path.display().to_string(),
+            pid: orphan.pid,
+        }
+    }
+
+    async fn handle_find_orphans(&self) -> CallToolResult {
+        tracing::debug!("Tool: find_orphans");
+        if !self.config.admin_tools {
+            return Self::error_result("Admin tools are disabled");
+        }
+
+        match self.manager.find_orphans().await {
+            Ok(orphans) => {
+                tracing::debug!(count = orphans.len(), "Found orphaned VMs");
+                Self::json_result(&FindOrphansResult {
+                    orphans: orphans.into_iter().map(Self::orphan_info).collect(),
+                })
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to scan for orphaned VMs");
+                Self::error_result(format!("Failed to scan for orphaned VMs: {e}"))
+            }
+        }
+    }
+
+    async fn handle_cleanup_orphans(&self) -> CallToolResult {
+        tracing::info!("Tool: cleanup_orphans");
+        if !self.config.admin_tools {
+            return Self::error_result("Admin tools are disabled");
+        }
+
+        match self.manager.cleanup_orphans().await {
+            Ok(cleaned) => {
+                tracing::info!(count = cleaned.len(), "Cleaned up orphaned VMs");
+                Self::json_result(&CleanupOrphansResult {
+                    cleaned: cleaned.into_iter().map(Self::orphan_info).collect(),
+                })
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to clean up orphaned VMs");
+                Self::error_result(format!("Failed to clean up orphaned VMs: {e}"))
+            }
         }
+    }
+
+    fn handle_get_tool_metrics(&self) -> CallToolResult {
+        tracing::debug!("Tool: get_tool_metrics");
+
+        let tools = self
+            .metrics
+            .snapshot()
+            .into_iter()
+            .map(|stats| ToolMetricsInfo {
+                error_rate: stats.error_rate(),
+                avg_latency_ms: stats.avg_latency().as_secs_f64() * 1000.0,
+                tool: stats.tool,
+                calls: stats.calls,
+                errors: stats.errors,
+            })
+            .collect();
+        Self::json_result(&ToolMetricsResult { tools })
+    }
+
+    async fn handle_get_pool_stats(&self) -> CallToolResult {
+        tracing::debug!("Tool: get_pool_stats");
+
+        let create_latency = self
+            .metrics
+            .create_latency()
+            .into_iter()
+            .map(|stats| CreateLatencyInfo {
+                source: stats.source.as_str().to_string(),
+                count: stats.count,
+                avg_latency_ms: stats.avg_latency().as_secs_f64() * 1000.0,
+            })
+            .collect();
+
+        let Some(pool) = &self.pool else {
+            return Self::json_result(&PoolStatsResult {
+                enabled: false,
+                available: 0,
+                warm_hits: 0,
+                cold_misses: 0,
+                hit_rate: 0.0,
+                created: 0,
+                destroyed: 0,
+                in_flight_boots: 0,
+                create_latency,
+            });
+        };
+
+        let pool = pool.lock().await;
+        let stats = pool.stats();
+        Self::json_result(&PoolStatsResult {
+            enabled: true,
+            available: pool.size().await,
+            warm_hits: stats.warm_hits(),
+            cold_misses: stats.cold_misses(),
+            hit_rate: stats.hit_rate(),
+            created: stats.created(),
+            destroyed: stats.destroyed(),
+            in_flight_boots: stats.in_flight_boots(),
+            create_latency,
+        })
+    }
+
+    async fn handle_execute_code(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+        progress: Option<OutputProgress>,
+    ) -> CallToolResult {
+        let params: ExecuteCodeParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        // Decode and validate input sizes
+        let code = match Self::resolve_input(
+            params.code,
+            params.code_base64,
+            "code",
+            MAX_INPUT_SIZE_BYTES,
+        ) {
+            Ok(code) => code,
+            Err(e) => {
+                tracing::warn!(sandbox_id = %params.sandbox_id, error = %e, "Code validation failed");
+                return Self::error_result(e);
+            }
+        };
 
         let start = std::time::Instant::now();
         tracing::info!(
             sandbox_id = %params.sandbox_id,
             language = %params.language,
-            code_len = params.code.len(),
+            code_len = code.len(),
             "Tool: execute_code"
         );
-        tracing::trace!(code_preview = %Self::truncate_for_log(&params.code, 200), "Code content");
+        tracing::trace!(code_preview = %self.truncate_for_log(&code, 200), "Code content");
 
         let id = match Self::parse_sandbox_id(&params.sandbox_id) {
             Ok(id) => id,
@@ -398,11 +993,32 @@ impl BouvetServer {
         };
 
         // Use the new direct execute_code method
-        match self
-            .manager
-            .execute_code(id, &params.language, &params.code)
-            .await
-        {
+        let options = ExecOptions {
+            max_output_bytes: params.max_output_bytes,
+            cwd: params.cwd,
+            timeout_secs: params.timeout_secs,
+            env: params.env.unwrap_or_default(),
+            usage: params.usage,
+            ..Default::default()
+        };
+        let result = match progress {
+            Some(progress) => {
+                match self
+                    .manager
+                    .execute_code_stream(id, &params.language, &code, &options)
+                    .await
+                {
+                    Ok(stream) => progress.forward(stream).await,
+                    Err(e) => Err(e),
+                }
+            }
+            None => {
+                self.manager
+                    .execute_code_with(id, &params.language, &code, &options)
+                    .await
+            }
+        };
+        match result {
             Ok(result) => {
                 tracing::info!(
                     sandbox_id = %id,
@@ -419,6 +1035,8 @@ impl BouvetServer {
                     exit_code: result.exit_code,
                     stdout: result.stdout,
                     stderr: result.stderr,
+                    output_limit_exceeded: result.output_limit_exceeded,
+                    usage: result.usage.map(Self::usage_info),
                 })
             }
             Err(e) => {
@@ -428,34 +1046,32 @@ impl BouvetServer {
         }
     }
 
-    async fn handle_run_command(
+    async fn handle_execute_project(
         &self,
         args: Option<serde_json::Map<String, serde_json::Value>>,
     ) -> CallToolResult {
-        let params: RunCommandParams = match args
-            .map(|a| serde_json::from_value(serde_json::Value::Object(a)))
-            .transpose()
-        {
-            Ok(Some(p)) => p,
-            _ => {
-                tracing::warn!("run_command called without required parameters");
-                return Self::error_result("Missing required parameters: sandbox_id, command");
-            }
+        let params: ExecuteProjectParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
         };
 
-        // Validate command length
-        if let Err(e) = Self::validate_size(&params.command, MAX_COMMAND_LENGTH, "command") {
-            tracing::warn!(sandbox_id = %params.sandbox_id, error = %e, "Command size validation failed");
-            return Self::error_result(e);
+        // Validate input sizes
+        let total_size: usize = params.files.values().map(String::len).sum();
+        if total_size > MAX_INPUT_SIZE_BYTES {
+            tracing::warn!(sandbox_id = %params.sandbox_id, total_size, "Project size validation failed");
+            return Self::error_result(format!(
+                "files exceed maximum total size ({total_size} bytes > {MAX_INPUT_SIZE_BYTES} bytes)"
+            ));
         }
 
         let start = std::time::Instant::now();
         tracing::info!(
             sandbox_id = %params.sandbox_id,
-            cmd_len = params.command.len(),
-            "Tool: run_command"
+            language = %params.language,
+            entry = %params.entry,
+            files = params.files.len(),
+            "Tool: execute_project"
         );
-        tracing::trace!(cmd = %Self::truncate_for_log(&params.command, 200), "Command content");
 
         let id = match Self::parse_sandbox_id(&params.sandbox_id) {
             Ok(id) => id,
@@ -465,53 +1081,79 @@ impl BouvetServer {
             }
         };
 
-        // Use the new direct execute method
-        match self.manager.execute(id, &params.command).await {
+        let options = ExecOptions {
+            max_output_bytes: params.max_output_bytes,
+            cwd: params.cwd,
+            timeout_secs: params.timeout_secs,
+            env: params.env.unwrap_or_default(),
+            usage: params.usage,
+            ..Default::default()
+        };
+        match self
+            .manager
+            .exec_project_with(id, &params.language, &params.entry, &params.files, &options)
+            .await
+        {
             Ok(result) => {
                 tracing::info!(
                     sandbox_id = %id,
                     exit_code = result.exit_code,
                     elapsed_ms = start.elapsed().as_millis() as u64,
-                    "Command completed"
-                );
-                tracing::trace!(
-                    stdout_len = result.stdout.len(),
-                    stderr_len = result.stderr.len(),
-                    "Command output"
+                    "Project execution completed"
                 );
                 Self::json_result(&ExecResponse {
                     exit_code: result.exit_code,
                     stdout: result.stdout,
                     stderr: result.stderr,
+                    output_limit_exceeded: result.output_limit_exceeded,
+                    usage: result.usage.map(Self::usage_info),
                 })
             }
             Err(e) => {
-                tracing::error!(sandbox_id = %id, error = %e, "Command execution failed");
+                tracing::error!(sandbox_id = %id, error = %e, "Project execution failed");
                 Self::error_result(format!("Execution failed: {e}"))
             }
         }
     }
 
-    async fn handle_read_file(
+    async fn handle_run_command(
         &self,
         args: Option<serde_json::Map<String, serde_json::Value>>,
+        progress: Option<OutputProgress>,
     ) -> CallToolResult {
-        let params: ReadFileParams = match args
-            .map(|a| serde_json::from_value(serde_json::Value::Object(a)))
-            .transpose()
-        {
-            Ok(Some(p)) => p,
-            _ => {
-                tracing::warn!("read_file called without required parameters");
-                return Self::error_result("Missing required parameters: sandbox_id, path");
+        let params: RunCommandParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        // Decode and validate command length
+        let command = match Self::resolve_input(
+            params.command,
+            params.command_base64,
+            "command",
+            MAX_COMMAND_LENGTH,
+        ) {
+            Ok(command) => command,
+            Err(e) => {
+                tracing::warn!(sandbox_id = %params.sandbox_id, error = %e, "Command validation failed");
+                return Self::error_result(e);
             }
         };
 
+        if let Some(stdin) = &params.stdin {
+            if let Err(e) = Self::validate_size(stdin, MAX_INPUT_SIZE_BYTES, "stdin") {
+                tracing::warn!(sandbox_id = %params.sandbox_id, error = %e, "Stdin validation failed");
+                return Self::error_result(e);
+            }
+        }
+
+        let start = std::time::Instant::now();
         tracing::info!(
             sandbox_id = %params.sandbox_id,
-            path = %params.path,
-            "Tool: read_file"
+            cmd_len = command.len(),
+            "Tool: run_command"
         );
+        tracing::trace!(cmd = %self.truncate_for_log(&command, 200), "Command content");
 
         let id = match Self::parse_sandbox_id(&params.sandbox_id) {
             Ok(id) => id,
@@ -521,51 +1163,782 @@ impl BouvetServer {
             }
         };
 
-        match self.manager.read_file(id, &params.path).await {
-            Ok(content) => {
-                tracing::debug!(
+        // Use the new direct execute method
+        let options = ExecOptions {
+            max_output_bytes: params.max_output_bytes,
+            cwd: params.cwd,
+            timeout_secs: params.timeout_secs,
+            env: params.env.unwrap_or_default(),
+            stdin: params.stdin,
+            usage: params.usage,
+        };
+        let result = match progress {
+            Some(progress) => match self.manager.execute_stream(id, &command, &options).await {
+                Ok(stream) => progress.forward(stream).await,
+                Err(e) => Err(e),
+            },
+            None => self.manager.execute_with(id, &command, &options).await,
+        };
+        match result {
+            Ok(result) => {
+                tracing::info!(
                     sandbox_id = %id,
-                    path = %params.path,
-                    size = content.len(),
-                    "File read successfully"
+                    exit_code = result.exit_code,
+                    elapsed_ms = start.elapsed().as_millis() as u64,
+                    "Command completed"
                 );
-                Self::json_result(&ReadFileResult { content })
-            }
-            Err(e) => {
-                tracing::warn!(sandbox_id = %id, path = %params.path, error = %e, "Failed to read file");
-                Self::error_result(format!("Failed to read file: {e}"))
-            }
+                tracing::trace!(
+                    stdout_len = result.stdout.len(),
+                    stderr_len = result.stderr.len(),
+                    "Command output"
+                );
+                Self::json_result(&ExecResponse {
+                    exit_code: result.exit_code,
+                    stdout: result.stdout,
+                    stderr: result.stderr,
+                    output_limit_exceeded: result.output_limit_exceeded,
+                    usage: result.usage.map(Self::usage_info),
+                })
+            }
+            Err(e) => {
+                tracing::error!(sandbox_id = %id, error = %e, "Command execution failed");
+                Self::error_result(format!("Execution failed: {e}"))
+            }
+        }
+    }
+
+    async fn handle_cancel_execution(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> CallToolResult {
+        let params: CancelExecutionParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        tracing::info!(
+            sandbox_id = %params.sandbox_id,
+            exec_id = ?params.exec_id,
+            force = params.force,
+            "Tool: cancel_execution"
+        );
+
+        let id = match Self::parse_sandbox_id(&params.sandbox_id) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::debug!(sandbox_id = %params.sandbox_id, "Invalid sandbox ID");
+                return Self::error_result(e);
+            }
+        };
+
+        let exec_ids = match params.exec_id {
+            Some(exec_id) => vec![exec_id],
+            None => match self.manager.running_execs(id).await {
+                Ok(exec_ids) => exec_ids,
+                Err(e) => return Self::error_result(format!("Failed to cancel: {e}")),
+            },
+        };
+        let signal = if params.force {
+            Signal::Kill
+        } else {
+            Signal::Term
+        };
+
+        let mut cancelled = Vec::new();
+        for exec_id in exec_ids {
+            match self.manager.cancel_exec(id, exec_id, signal).await {
+                Ok(true) => cancelled.push(exec_id),
+                Ok(false) => {}
+                Err(e) => return Self::error_result(format!("Failed to cancel: {e}")),
+            }
+        }
+        if let (Some(exec_id), true) = (params.exec_id, cancelled.is_empty()) {
+            return Self::error_result(format!("No running command with exec_id {exec_id}"));
+        }
+
+        tracing::info!(sandbox_id = %id, cancelled = ?cancelled, "Commands signalled");
+        Self::json_result(&CancelExecutionResult { cancelled })
+    }
+
+    async fn handle_run_command_expect(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> CallToolResult {
+        let params: RunCommandExpectParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        // Validate command length
+        if let Err(e) = Self::validate_size(&params.command, MAX_COMMAND_LENGTH, "command") {
+            tracing::warn!(sandbox_id = %params.sandbox_id, error = %e, "Command size validation failed");
+            return Self::error_result(e);
+        }
+
+        let start = std::time::Instant::now();
+        tracing::info!(
+            sandbox_id = %params.sandbox_id,
+            cmd_len = params.command.len(),
+            expected_exit_code = params.expected_exit_code,
+            "Tool: run_command_expect"
+        );
+        tracing::trace!(cmd = %self.truncate_for_log(&params.command, 200), "Command content");
+
+        let id = match Self::parse_sandbox_id(&params.sandbox_id) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::debug!(sandbox_id = %params.sandbox_id, "Invalid sandbox ID");
+                return Self::error_result(e);
+            }
+        };
+
+        match self
+            .manager
+            .execute_expect(id, &params.command, params.expected_exit_code)
+            .await
+        {
+            Ok(result) => {
+                tracing::info!(
+                    sandbox_id = %id,
+                    exit_code = result.exit_code,
+                    elapsed_ms = start.elapsed().as_millis() as u64,
+                    "Command completed with expected exit code"
+                );
+                Self::json_result(&ExecResponse {
+                    exit_code: result.exit_code,
+                    stdout: result.stdout,
+                    stderr: result.stderr,
+                    output_limit_exceeded: result.output_limit_exceeded,
+                    usage: result.usage.map(Self::usage_info),
+                })
+            }
+            Err(e @ CoreError::UnexpectedExit { .. }) => {
+                tracing::info!(sandbox_id = %id, error = %e, "Command exit code mismatch");
+                Self::error_result(e.to_string())
+            }
+            Err(e) => {
+                tracing::error!(sandbox_id = %id, error = %e, "Command execution failed");
+                Self::error_result(format!("Execution failed: {e}"))
+            }
+        }
+    }
+
+    async fn handle_run_argv(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> CallToolResult {
+        let params: RunArgvParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        if params.argv.is_empty() {
+            tracing::warn!(sandbox_id = %params.sandbox_id, "run_argv called with empty argv");
+            return Self::error_result("argv must contain at least the program name");
+        }
+
+        // Validate combined argument length
+        let argv_len: usize = params.argv.iter().map(String::len).sum();
+        if argv_len > MAX_COMMAND_LENGTH {
+            tracing::warn!(sandbox_id = %params.sandbox_id, argv_len, "Argv size validation failed");
+            return Self::error_result(format!(
+                "argv exceeds maximum size of {} bytes (got {} bytes)",
+                MAX_COMMAND_LENGTH, argv_len
+            ));
+        }
+
+        let start = std::time::Instant::now();
+        tracing::info!(
+            sandbox_id = %params.sandbox_id,
+            program = %params.argv[0],
+            argc = params.argv.len(),
+            "Tool: run_argv"
+        );
+
+        let id = match Self::parse_sandbox_id(&params.sandbox_id) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::debug!(sandbox_id = %params.sandbox_id, "Invalid sandbox ID");
+                return Self::error_result(e);
+            }
+        };
+
+        match self.manager.execute_argv(id, &params.argv).await {
+            Ok(result) => {
+                tracing::info!(
+                    sandbox_id = %id,
+                    exit_code = result.exit_code,
+                    elapsed_ms = start.elapsed().as_millis() as u64,
+                    "Argv completed"
+                );
+                Self::json_result(&ExecResponse {
+                    exit_code: result.exit_code,
+                    stdout: result.stdout,
+                    stderr: result.stderr,
+                    output_limit_exceeded: result.output_limit_exceeded,
+                    usage: result.usage.map(Self::usage_info),
+                })
+            }
+            Err(e) => {
+                tracing::error!(sandbox_id = %id, error = %e, "Argv execution failed");
+                Self::error_result(format!("Execution failed: {e}"))
+            }
+        }
+    }
+
+    async fn handle_read_file(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> CallToolResult {
+        let params: ReadFileParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        tracing::info!(
+            sandbox_id = %params.sandbox_id,
+            path = %params.path,
+            "Tool: read_file"
+        );
+
+        let id = match Self::parse_sandbox_id(&params.sandbox_id) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::debug!(sandbox_id = %params.sandbox_id, "Invalid sandbox ID");
+                return Self::error_result(e);
+            }
+        };
+
+        match self.manager.read_file(id, &params.path).await {
+            Ok(content) => {
+                tracing::debug!(
+                    sandbox_id = %id,
+                    path = %params.path,
+                    size = content.len(),
+                    "File read successfully"
+                );
+                Self::json_result(&ReadFileResult { content })
+            }
+            Err(e) => {
+                tracing::warn!(sandbox_id = %id, path = %params.path, error = %e, "Failed to read file");
+                Self::error_result(format!("Failed to read file: {e}"))
+            }
+        }
+    }
+
+    async fn handle_download_file(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> CallToolResult {
+        let params: DownloadFileParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        tracing::info!(
+            sandbox_id = %params.sandbox_id,
+            path = %params.path,
+            "Tool: download_file"
+        );
+
+        let id = match Self::parse_sandbox_id(&params.sandbox_id) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::debug!(sandbox_id = %params.sandbox_id, "Invalid sandbox ID");
+                return Self::error_result(e);
+            }
+        };
+
+        match self.manager.read_file_base64(id, &params.path).await {
+            Ok(download) => {
+                tracing::debug!(
+                    sandbox_id = %id,
+                    path = %params.path,
+                    size = download.size,
+                    "File downloaded successfully"
+                );
+                Self::json_result(&DownloadFileResult {
+                    content_base64: download.content_base64,
+                    size: download.size,
+                    sha256: download.sha256,
+                })
+            }
+            Err(e) => {
+                tracing::warn!(sandbox_id = %id, path = %params.path, error = %e, "Failed to download file");
+                Self::error_result(format!("Failed to download file: {e}"))
+            }
+        }
+    }
+
+    async fn handle_export_workspace(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> CallToolResult {
+        let params: ExportWorkspaceParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        tracing::info!(
+            sandbox_id = %params.sandbox_id,
+            dir = ?params.dir,
+            host_path = ?params.host_path,
+            "Tool: export_workspace"
+        );
+
+        let id = match Self::parse_sandbox_id(&params.sandbox_id) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::debug!(sandbox_id = %params.sandbox_id, "Invalid sandbox ID");
+                return Self::error_result(e);
+            }
+        };
+
+        if let Some(host_path) = &params.host_path {
+            if !std::path::Path::new(host_path).is_absolute() {
+                return Self::error_result(format!("host_path must be absolute: {host_path}"));
+            }
+        }
+        // Without a host path, the archive goes through a temporary file
+        let target = match &params.host_path {
+            Some(host_path) => std::path::PathBuf::from(host_path),
+            None => {
+                std::env::temp_dir().join(format!("bouvet-export-{}.tar.gz", uuid::Uuid::new_v4()))
+            }
+        };
+
+        let export = self
+            .manager
+            .export_workspace(id, params.dir.as_deref(), &target, MAX_EXPORT_SIZE_BYTES)
+            .await;
+        let content = match (&export, &params.host_path) {
+            (Ok(_), None) => Some(std::fs::read(&target)),
+            _ => None,
+        };
+        if params.host_path.is_none() {
+            let _ = std::fs::remove_file(&target);
+        }
+
+        let export = match export {
+            Ok(export) => export,
+            Err(e) => {
+                tracing::warn!(sandbox_id = %id, error = %e, "Failed to export workspace");
+                return Self::error_result(format!("Failed to export workspace: {e}"));
+            }
+        };
+        let content_base64 = match content.transpose() {
+            Ok(content) => content.map(|content| BASE64.encode(content)),
+            Err(e) => {
+                tracing::warn!(sandbox_id = %id, error = %e, "Failed to read exported archive");
+                return Self::error_result(format!("Failed to read exported archive: {e}"));
+            }
+        };
+        tracing::debug!(
+            sandbox_id = %id,
+            dir = %export.dir,
+            size = export.size,
+            "Workspace exported"
+        );
+        Self::json_result(&ExportWorkspaceResult {
+            dir: export.dir,
+            size: export.size,
+            sha256: export.sha256,
+            content_base64,
+            host_path: params.host_path,
+        })
+    }
+
+    async fn handle_stat_file(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> CallToolResult {
+        let params: StatFileParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        tracing::info!(
+            sandbox_id = %params.sandbox_id,
+            path = %params.path,
+            "Tool: stat_file"
+        );
+
+        let id = match Self::parse_sandbox_id(&params.sandbox_id) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::debug!(sandbox_id = %params.sandbox_id, "Invalid sandbox ID");
+                return Self::error_result(e);
+            }
+        };
+
+        match self.manager.stat(id, &params.path).await {
+            Ok(stat) => Self::json_result(&Self::stat_result(&stat)),
+            Err(e) => {
+                tracing::debug!(sandbox_id = %id, path = %params.path, error = %e, "Failed to stat file");
+                Self::error_result(format!("Failed to stat file: {e}"))
+            }
+        }
+    }
+
+    /// Convert a core file stat for the tool response.
+    fn stat_result(stat: &bouvet_core::FileStat) -> StatFileResult {
+        let file_type = match stat.file_type {
+            bouvet_core::FileType::File => "file",
+            bouvet_core::FileType::Dir => "dir",
+            bouvet_core::FileType::Symlink => "symlink",
+            bouvet_core::FileType::Other => "other",
+        };
+        StatFileResult {
+            file_type: file_type.to_string(),
+            size: stat.size,
+            mode: format!("{:o}", stat.mode),
+            modified: stat.modified.to_rfc3339(),
+        }
+    }
+
+    async fn handle_tail_file(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> CallToolResult {
+        let params: TailFileParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        tracing::info!(
+            sandbox_id = %params.sandbox_id,
+            path = %params.path,
+            lines = params.lines,
+            "Tool: tail_file"
+        );
+
+        if params.lines > MAX_TAIL_LINES {
+            return Self::error_result(format!(
+                "Too many lines requested: {} (max {MAX_TAIL_LINES})",
+                params.lines
+            ));
+        }
+
+        let id = match Self::parse_sandbox_id(&params.sandbox_id) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::debug!(sandbox_id = %params.sandbox_id, "Invalid sandbox ID");
+                return Self::error_result(e);
+            }
+        };
+
+        match self
+            .manager
+            .tail_lines(id, &params.path, params.lines)
+            .await
+        {
+            Ok(lines) => {
+                tracing::debug!(
+                    sandbox_id = %id,
+                    path = %params.path,
+                    count = lines.len(),
+                    "File tailed successfully"
+                );
+                Self::json_result(&TailFileResult { lines })
+            }
+            Err(e) => {
+                tracing::warn!(sandbox_id = %id, path = %params.path, error = %e, "Failed to tail file");
+                Self::error_result(format!("Failed to tail file: {e}"))
+            }
+        }
+    }
+
+    async fn handle_write_file(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> CallToolResult {
+        let params: WriteFileParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        // Validate content size
+        if let Err(e) = Self::validate_size(&params.content, MAX_INPUT_SIZE_BYTES, "content") {
+            tracing::warn!(sandbox_id = %params.sandbox_id, error = %e, "Content size validation failed");
+            return Self::error_result(e);
+        }
+
+        tracing::info!(
+            sandbox_id = %params.sandbox_id,
+            path = %params.path,
+            content_len = params.content.len(),
+            "Tool: write_file"
+        );
+
+        let id = match Self::parse_sandbox_id(&params.sandbox_id) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::debug!(sandbox_id = %params.sandbox_id, "Invalid sandbox ID");
+                return Self::error_result(e);
+            }
+        };
+
+        match self
+            .manager
+            .write_file(id, &params.path, &params.content)
+            .await
+        {
+            Ok(()) => {
+                tracing::debug!(
+                    sandbox_id = %id,
+                    path = %params.path,
+                    "File written successfully"
+                );
+                Self::json_result(&WriteFileResult { success: true })
+            }
+            Err(e) => {
+                tracing::warn!(sandbox_id = %id, path = %params.path, error = %e, "Failed to write file");
+                Self::error_result(format!("Failed to write file: {e}"))
+            }
+        }
+    }
+
+    async fn handle_write_files(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> CallToolResult {
+        let params: WriteFilesParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        // Validate input sizes
+        let total_size: usize = params.files.iter().map(|f| f.content.len()).sum();
+        if total_size > MAX_INPUT_SIZE_BYTES {
+            tracing::warn!(sandbox_id = %params.sandbox_id, total_size, "Batch size validation failed");
+            return Self::error_result(format!(
+                "files exceed maximum total size ({total_size} bytes > {MAX_INPUT_SIZE_BYTES} bytes)"
+            ));
+        }
+
+        tracing::info!(
+            sandbox_id = %params.sandbox_id,
+            files = params.files.len(),
+            total_size,
+            "Tool: write_files"
+        );
+
+        let id = match Self::parse_sandbox_id(&params.sandbox_id) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::debug!(sandbox_id = %params.sandbox_id, "Invalid sandbox ID");
+                return Self::error_result(e);
+            }
+        };
+
+        // Written one at a time, in order, so a failure doesn't stop the rest
+        let mut outcomes = Vec::with_capacity(params.files.len());
+        for file in params.files {
+            let result = match file.mode.as_deref().map(Self::parse_mode).transpose() {
+                Ok(mode) => self
+                    .manager
+                    .write_file_with_mode(id, &file.path, &file.content, mode)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = &result {
+                tracing::warn!(sandbox_id = %id, path = %file.path, error = %e, "Failed to write file");
+            }
+            outcomes.push(FileWriteOutcome {
+                path: file.path,
+                success: result.is_ok(),
+                error: result.err(),
+            });
+        }
+
+        let written = outcomes.iter().filter(|o| o.success).count();
+        let failed = outcomes.len() - written;
+        tracing::debug!(sandbox_id = %id, written, failed, "Batch written");
+        Self::json_result(&WriteFilesResult {
+            written,
+            failed,
+            files: outcomes,
+        })
+    }
+
+    /// Parse permission bits given in octal, like `chmod` (`755`, `0644`).
+    fn parse_mode(mode: &str) -> Result<u32, String> {
+        u32::from_str_radix(mode.trim_start_matches("0o"), 8)
+            .ok()
+            .filter(|bits| *bits <= 0o7777)
+            .ok_or_else(|| {
+                format!("invalid mode {mode:?}: expected octal permission bits like \"755\"")
+            })
+    }
+
+    async fn handle_touch_file(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> CallToolResult {
+        let params: TouchFileParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        tracing::info!(
+            sandbox_id = %params.sandbox_id,
+            path = %params.path,
+            "Tool: touch_file"
+        );
+
+        let id = match Self::parse_sandbox_id(&params.sandbox_id) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::debug!(sandbox_id = %params.sandbox_id, "Invalid sandbox ID");
+                return Self::error_result(e);
+            }
+        };
+
+        match self.manager.touch(id, &params.path).await {
+            Ok(()) => {
+                tracing::debug!(
+                    sandbox_id = %id,
+                    path = %params.path,
+                    "File touched successfully"
+                );
+                Self::json_result(&TouchFileResult { success: true })
+            }
+            Err(e) => {
+                tracing::warn!(sandbox_id = %id, path = %params.path, error = %e, "Failed to touch file");
+                Self::error_result(format!("Failed to touch file: {e}"))
+            }
+        }
+    }
+
+    async fn handle_delete_file(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> CallToolResult {
+        let params: DeleteFileParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        tracing::info!(
+            sandbox_id = %params.sandbox_id,
+            path = %params.path,
+            recursive = params.recursive,
+            "Tool: delete_file"
+        );
+
+        let id = match Self::parse_sandbox_id(&params.sandbox_id) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::debug!(sandbox_id = %params.sandbox_id, "Invalid sandbox ID");
+                return Self::error_result(e);
+            }
+        };
+
+        match self
+            .manager
+            .delete_file(id, &params.path, params.recursive)
+            .await
+        {
+            Ok(()) => {
+                tracing::debug!(sandbox_id = %id, path = %params.path, "Path deleted successfully");
+                Self::json_result(&DeleteFileResult { success: true })
+            }
+            Err(e) => {
+                tracing::warn!(sandbox_id = %id, path = %params.path, error = %e, "Failed to delete path");
+                Self::error_result(format!("Failed to delete file: {e}"))
+            }
+        }
+    }
+
+    async fn handle_move_file(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> CallToolResult {
+        let params: MoveFileParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        tracing::info!(
+            sandbox_id = %params.sandbox_id,
+            source = %params.source,
+            destination = %params.destination,
+            "Tool: move_file"
+        );
+
+        let id = match Self::parse_sandbox_id(&params.sandbox_id) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::debug!(sandbox_id = %params.sandbox_id, "Invalid sandbox ID");
+                return Self::error_result(e);
+            }
+        };
+
+        match self
+            .manager
+            .rename(id, &params.source, &params.destination)
+            .await
+        {
+            Ok(()) => {
+                tracing::debug!(sandbox_id = %id, source = %params.source, "Path moved successfully");
+                Self::json_result(&MoveFileResult { success: true })
+            }
+            Err(e) => {
+                tracing::warn!(sandbox_id = %id, source = %params.source, error = %e, "Failed to move path");
+                Self::error_result(format!("Failed to move file: {e}"))
+            }
+        }
+    }
+
+    async fn handle_make_directory(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> CallToolResult {
+        let params: MakeDirectoryParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        tracing::info!(
+            sandbox_id = %params.sandbox_id,
+            path = %params.path,
+            "Tool: make_directory"
+        );
+
+        let id = match Self::parse_sandbox_id(&params.sandbox_id) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::debug!(sandbox_id = %params.sandbox_id, "Invalid sandbox ID");
+                return Self::error_result(e);
+            }
+        };
+
+        match self.manager.mkdir(id, &params.path).await {
+            Ok(()) => {
+                tracing::debug!(sandbox_id = %id, path = %params.path, "Directory created successfully");
+                Self::json_result(&MakeDirectoryResult { success: true })
+            }
+            Err(e) => {
+                tracing::warn!(sandbox_id = %id, path = %params.path, error = %e, "Failed to create directory");
+                Self::error_result(format!("Failed to create directory: {e}"))
+            }
         }
     }
 
-    async fn handle_write_file(
+    async fn handle_resize_scratch(
         &self,
         args: Option<serde_json::Map<String, serde_json::Value>>,
     ) -> CallToolResult {
-        let params: WriteFileParams = match args
-            .map(|a| serde_json::from_value(serde_json::Value::Object(a)))
-            .transpose()
-        {
-            Ok(Some(p)) => p,
-            _ => {
-                tracing::warn!("write_file called without required parameters");
-                return Self::error_result(
-                    "Missing required parameters: sandbox_id, path, content",
-                );
-            }
+        let params: ResizeScratchParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
         };
 
-        // Validate content size
-        if let Err(e) = Self::validate_size(&params.content, MAX_INPUT_SIZE_BYTES, "content") {
-            tracing::warn!(sandbox_id = %params.sandbox_id, error = %e, "Content size validation failed");
-            return Self::error_result(e);
-        }
-
         tracing::info!(
             sandbox_id = %params.sandbox_id,
-            path = %params.path,
-            content_len = params.content.len(),
-            "Tool: write_file"
+            size_mib = params.size_mib,
+            "Tool: resize_scratch"
         );
 
         let id = match Self::parse_sandbox_id(&params.sandbox_id) {
@@ -576,22 +1949,17 @@ impl BouvetServer {
             }
         };
 
-        match self
-            .manager
-            .write_file(id, &params.path, &params.content)
-            .await
-        {
+        match self.manager.resize_scratch(id, params.size_mib).await {
             Ok(()) => {
-                tracing::debug!(
-                    sandbox_id = %id,
-                    path = %params.path,
-                    "File written successfully"
-                );
-                Self::json_result(&WriteFileResult { success: true })
+                tracing::debug!(sandbox_id = %id, size_mib = params.size_mib, "Scratch drive resized");
+                Self::json_result(&ResizeScratchResult {
+                    success: true,
+                    size_mib: params.size_mib,
+                })
             }
             Err(e) => {
-                tracing::warn!(sandbox_id = %id, path = %params.path, error = %e, "Failed to write file");
-                Self::error_result(format!("Failed to write file: {e}"))
+                tracing::warn!(sandbox_id = %id, size_mib = params.size_mib, error = %e, "Failed to resize scratch drive");
+                Self::error_result(format!("Failed to resize scratch drive: {e}"))
             }
         }
     }
@@ -600,20 +1968,19 @@ impl BouvetServer {
         &self,
         args: Option<serde_json::Map<String, serde_json::Value>>,
     ) -> CallToolResult {
-        let params: ListDirectoryParams = match args
-            .map(|a| serde_json::from_value(serde_json::Value::Object(a)))
-            .transpose()
-        {
-            Ok(Some(p)) => p,
-            _ => {
-                tracing::warn!("list_directory called without required parameters");
-                return Self::error_result("Missing required parameters: sandbox_id, path");
-            }
+        let params: ListDirectoryParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
         };
 
         tracing::info!(
             sandbox_id = %params.sandbox_id,
             path = %params.path,
+            sort_by = ?params.sort_by,
+            descending = params.descending,
+            pattern = ?params.pattern,
+            recursive = params.recursive,
+            max_depth = ?params.max_depth,
             "Tool: list_directory"
         );
 
@@ -625,7 +1992,19 @@ impl BouvetServer {
             }
         };
 
-        match self.manager.list_dir(id, &params.path).await {
+        let options = ListDirOptions {
+            sort_by: match params.sort_by {
+                ListSortBy::Name => SortBy::Name,
+                ListSortBy::Size => SortBy::Size,
+                ListSortBy::Modified => SortBy::Modified,
+            },
+            descending: params.descending,
+            pattern: params.pattern,
+            recursive: params.recursive,
+            max_depth: params.max_depth,
+        };
+
+        match self.manager.list_dir(id, &params.path, &options).await {
             Ok(entries) => {
                 let count = entries.len();
                 let entries: Vec<FileEntryResponse> = entries
@@ -651,9 +2030,132 @@ impl BouvetServer {
         }
     }
 
+    async fn handle_check_connectivity(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> CallToolResult {
+        let params: CheckConnectivityParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        tracing::info!(
+            sandbox_id = %params.sandbox_id,
+            host = %params.host,
+            port = params.port,
+            "Tool: check_connectivity"
+        );
+
+        let id = match Self::parse_sandbox_id(&params.sandbox_id) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::debug!(sandbox_id = %params.sandbox_id, "Invalid sandbox ID");
+                return Self::error_result(e);
+            }
+        };
+
+        let timeout = std::time::Duration::from_millis(
+            params
+                .timeout_ms
+                .unwrap_or(DEFAULT_CONNECT_TIMEOUT_MS)
+                .min(MAX_CONNECT_TIMEOUT_MS),
+        );
+        match self
+            .manager
+            .check_connectivity(id, &params.host, params.port, timeout)
+            .await
+        {
+            Ok(result) => {
+                tracing::debug!(
+                    sandbox_id = %id,
+                    host = %params.host,
+                    reachable = result.reachable,
+                    "Connectivity checked"
+                );
+                Self::json_result(&CheckConnectivityResult {
+                    reachable: result.reachable,
+                    latency_ms: result.latency_ms,
+                    error: result.error,
+                })
+            }
+            Err(e) => {
+                tracing::warn!(sandbox_id = %id, error = %e, "Connectivity check failed");
+                Self::error_result(format!("Connectivity check failed: {e}"))
+            }
+        }
+    }
+
+    async fn handle_os_info(
+        &self,
+        args: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> CallToolResult {
+        let params: OsInfoParams = match Self::parse_params(args) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+
+        tracing::info!(sandbox_id = %params.sandbox_id, "Tool: os_info");
+
+        let id = match Self::parse_sandbox_id(&params.sandbox_id) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::debug!(sandbox_id = %params.sandbox_id, "Invalid sandbox ID");
+                return Self::error_result(e);
+            }
+        };
+
+        match self.manager.os_info(id).await {
+            Ok(info) => {
+                tracing::debug!(sandbox_id = %id, os = %info.id, "OS info read");
+                Self::json_result(&OsInfoResult {
+                    id: info.id,
+                    version_id: info.version_id,
+                    pretty_name: info.pretty_name,
+                })
+            }
+            Err(e) => {
+                tracing::warn!(sandbox_id = %id, error = %e, "Failed to read OS info");
+                Self::error_result(format!("Failed to read OS info: {e}"))
+            }
+        }
+    }
+
+    /// Tools that require the exec capability.
+    const EXEC_TOOLS: &'static [&'static str] = &[
+        "execute_code",
+        "execute_project",
+        "run_command",
+        "run_command_expect",
+        "run_argv",
+        "cancel_execution",
+        "export_workspace",
+    ];
+
+    /// Tools that require the write capability.
+    const WRITE_TOOLS: &'static [&'static str] = &[
+        "write_file",
+        "write_files",
+        "touch_file",
+        "delete_file",
+        "move_file",
+        "make_directory",
+        "resize_scratch",
+    ];
+
+    /// Check whether a tool is usable with the given capabilities.
+    fn tool_allowed(name: &str, capabilities: &SandboxCapabilities) -> bool {
+        if Self::EXEC_TOOLS.contains(&name) {
+            return capabilities.exec;
+        }
+        if Self::WRITE_TOOLS.contains(&name) {
+            return capabilities.write;
+        }
+        true
+    }
+
     /// Build the list of available tools
-    fn build_tools_list() -> Vec<Tool> {
-        vec![
+    fn build_tools_list(capabilities: &SandboxCapabilities) -> Vec<Tool> {
+        let mut tools = vec![
             Tool::new(
                 "create_sandbox",
                 "Create a new isolated sandbox for code execution. Returns sandbox_id.",
@@ -664,36 +2166,164 @@ impl BouvetServer {
                 "Destroy a sandbox and release all resources.",
                 Self::schema_to_json_object::<DestroySandboxParams>(),
             ),
+            Tool::new(
+                "destroy_sandbox_by_key",
+                "Destroy the sandbox that was created with the given key.",
+                Self::schema_to_json_object::<DestroySandboxByKeyParams>(),
+            ),
             Tool::new(
                 "list_sandboxes",
                 "List all active sandboxes with their metadata.",
                 Self::empty_schema(),
             ),
+            Tool::new(
+                "get_sandbox",
+                "Get one sandbox's state, creation time, uptime, size and labels.",
+                Self::schema_to_json_object::<GetSandboxParams>(),
+            ),
+            Tool::new(
+                "health_check_all",
+                "Ping every sandbox's agent and report which are healthy. Nothing is destroyed; use destroy_sandbox on the unhealthy ones if they should go.",
+                Self::empty_schema(),
+            ),
+            Tool::new(
+                "get_resource_totals",
+                "Get the total vCPUs and memory committed to active sandboxes, to judge whether the host can take more.",
+                Self::empty_schema(),
+            ),
+            Tool::new(
+                "get_tool_metrics",
+                "Get per-tool call counts, error rates and mean latency since the server started.",
+                Self::empty_schema(),
+            ),
+            Tool::new(
+                "get_pool_stats",
+                "Get warm pool hit/miss counters and create_sandbox latency split by source (pool vs cold-start).",
+                Self::empty_schema(),
+            ),
             Tool::new(
                 "execute_code",
-                "Execute code in a specific language (python, node, bash, etc.) inside a sandbox.",
+                "Execute code in a specific language (python, node, bash, ruby, go, typescript, rust) inside a sandbox. Go, TypeScript (via Deno) and Rust code is written to a temporary file and run or compiled from there.",
                 Self::schema_to_json_object::<ExecuteCodeParams>(),
             ),
+            Tool::new(
+                "execute_project",
+                "Execute a multi-file program inside a sandbox. Files are written to a temporary directory and the entry point is run with the language's interpreter (`go run .` for Go), so it can import sibling files.",
+                Self::schema_to_json_object::<ExecuteProjectParams>(),
+            ),
             Tool::new(
                 "run_command",
                 "Execute a shell command inside a sandbox.",
                 Self::schema_to_json_object::<RunCommandParams>(),
             ),
+            Tool::new(
+                "run_command_expect",
+                "Execute a shell command inside a sandbox and fail unless it exits with expected_exit_code (default 0). The error includes stderr.",
+                Self::schema_to_json_object::<RunCommandExpectParams>(),
+            ),
+            Tool::new(
+                "run_argv",
+                "Run a program directly inside a sandbox without a shell. Arguments are passed verbatim, avoiding quoting and injection issues.",
+                Self::schema_to_json_object::<RunArgvParams>(),
+            ),
+            Tool::new(
+                "cancel_execution",
+                "Cancel a streamed execute_code or run_command call by sending SIGTERM (SIGKILL with force: true) to its process group. Streamed calls announce their exec_id in their first progress message; without exec_id, every streamed command in the sandbox is cancelled.",
+                Self::schema_to_json_object::<CancelExecutionParams>(),
+            ),
             Tool::new(
                 "read_file",
                 "Read a file from the sandbox filesystem.",
                 Self::schema_to_json_object::<ReadFileParams>(),
             ),
+            Tool::new(
+                "download_file",
+                "Download a file from the sandbox as base64, with its size and SHA-256. Use this instead of read_file for binary files such as archives, wheels or images (max 10MB).",
+                Self::schema_to_json_object::<DownloadFileParams>(),
+            ),
+            Tool::new(
+                "export_workspace",
+                "Archive a directory in the sandbox (default: its work_dir) as a .tar.gz and return it as base64 with its size and SHA-256, or write it to host_path on the host and return the path. /proc, /sys, /dev and /run are left out (max 10MB).",
+                Self::schema_to_json_object::<ExportWorkspaceParams>(),
+            ),
+            Tool::new(
+                "stat_file",
+                "Get a file's type (file, dir, symlink), size, octal mode and modification time without reading it. Use it to check that a file exists or is fresh.",
+                Self::schema_to_json_object::<StatFileParams>(),
+            ),
+            Tool::new(
+                "tail_file",
+                "Return the last lines of a file in the sandbox, like `tail -n`. Useful for checking logs.",
+                Self::schema_to_json_object::<TailFileParams>(),
+            ),
             Tool::new(
                 "write_file",
                 "Write a file to the sandbox filesystem.",
                 Self::schema_to_json_object::<WriteFileParams>(),
             ),
+            Tool::new(
+                "write_files",
+                "Write several files in one call, e.g. to scaffold a project. Each file may set an octal mode like \"755\". Returns per-file success or error; a failed file doesn't stop the others.",
+                Self::schema_to_json_object::<WriteFilesParams>(),
+            ),
+            Tool::new(
+                "touch_file",
+                "Create an empty file or update its modification time without changing its content.",
+                Self::schema_to_json_object::<TouchFileParams>(),
+            ),
+            Tool::new(
+                "delete_file",
+                "Delete a file or directory in the sandbox. Directories must be empty unless recursive is true.",
+                Self::schema_to_json_object::<DeleteFileParams>(),
+            ),
+            Tool::new(
+                "move_file",
+                "Move or rename a file or directory in the sandbox. An existing file at the destination is replaced.",
+                Self::schema_to_json_object::<MoveFileParams>(),
+            ),
+            Tool::new(
+                "make_directory",
+                "Create a directory in the sandbox, along with any missing parent directories.",
+                Self::schema_to_json_object::<MakeDirectoryParams>(),
+            ),
+            Tool::new(
+                "resize_scratch",
+                "Grow the sandbox's scratch drive (guest device /dev/vdb) to size_mib and expand its filesystem, when it fills up. Drives only grow. Fails if the sandbox has no scratch drive.",
+                Self::schema_to_json_object::<ResizeScratchParams>(),
+            ),
             Tool::new(
                 "list_directory",
-                "List contents of a directory in the sandbox.",
+                "List contents of a directory in the sandbox. Optionally sort by name, size or modified (descending: true to reverse) and filter names with a glob pattern like \"*.py\".",
                 Self::schema_to_json_object::<ListDirectoryParams>(),
             ),
+            Tool::new(
+                "check_connectivity",
+                "Check whether host:port is reachable over TCP from inside the sandbox. Returns reachable, latency_ms, and error (\"blocked by policy\" if the sandbox has no network access).",
+                Self::schema_to_json_object::<CheckConnectivityParams>(),
+            ),
+            Tool::new(
+                "os_info",
+                "Get the sandbox's Linux distribution (id, version_id, pretty_name from /etc/os-release), e.g. to pick a package manager.",
+                Self::schema_to_json_object::<OsInfoParams>(),
+            ),
+        ];
+        tools.retain(|tool| Self::tool_allowed(&tool.name, capabilities));
+        tools
+    }
+
+    /// Host maintenance tools, offered only with `admin_tools` enabled.
+    fn build_admin_tools_list() -> Vec<Tool> {
+        vec![
+            Tool::new(
+                "find_orphans",
+                "List VMs left in the chroot directory with no sandbox registered for them, e.g. after a crash, with their Firecracker PID if still running.",
+                Self::empty_schema(),
+            ),
+            Tool::new(
+                "cleanup_orphans",
+                "Kill orphaned VMs (see find_orphans) and delete their directories.",
+                Self::empty_schema(),
+            ),
         ]
     }
 }
@@ -724,7 +2354,7 @@ impl ServerHandler for BouvetServer {
         _context: RequestContext<RoleServer>,
     ) -> Result<ListToolsResult, ErrorData> {
         Ok(ListToolsResult {
-            tools: Self::build_tools_list(),
+            tools: self.tools(),
             next_cursor: None,
             meta: None,
         })
@@ -733,33 +2363,83 @@ impl ServerHandler for BouvetServer {
     async fn call_tool(
         &self,
         request: CallToolRequestParam,
-        _context: RequestContext<RoleServer>,
+        context: RequestContext<RoleServer>,
     ) -> Result<CallToolResult, ErrorData> {
+        // A progress token asks for output as it is produced
+        let progress = context
+            .meta
+            .get_progress_token()
+            .map(|token| OutputProgress {
+                peer: context.peer,
+                token,
+            });
+        Ok(self.invoke_tool(request, progress).await)
+    }
+}
+
+impl BouvetServer {
+    /// Dispatch a tool call and record it in the tool metrics.
+    ///
+    /// With `progress`, `execute_code` and `run_command` stream their
+    /// output through it while they run.
+    async fn invoke_tool(
+        &self,
+        request: CallToolRequestParam,
+        progress: Option<OutputProgress>,
+    ) -> CallToolResult {
         let tool_name = request.name.as_ref();
         tracing::debug!(tool = tool_name, "MCP tool invocation");
 
+        let start = std::time::Instant::now();
         let result = match tool_name {
             "create_sandbox" => self.handle_create_sandbox(request.arguments).await,
             "destroy_sandbox" => self.handle_destroy_sandbox(request.arguments).await,
+            "destroy_sandbox_by_key" => self.handle_destroy_sandbox_by_key(request.arguments).await,
             "list_sandboxes" => self.handle_list_sandboxes().await,
-            "execute_code" => self.handle_execute_code(request.arguments).await,
-            "run_command" => self.handle_run_command(request.arguments).await,
+            "get_sandbox" => self.handle_get_sandbox(request.arguments).await,
+            "get_resource_totals" => self.handle_get_resource_totals().await,
+            "get_tool_metrics" => self.handle_get_tool_metrics(),
+            "health_check_all" => self.handle_health_check_all().await,
+            "get_pool_stats" => self.handle_get_pool_stats().await,
+            "find_orphans" => self.handle_find_orphans().await,
+            "cleanup_orphans" => self.handle_cleanup_orphans().await,
+            "execute_code" => self.handle_execute_code(request.arguments, progress).await,
+            "execute_project" => self.handle_execute_project(request.arguments).await,
+            "run_command" => self.handle_run_command(request.arguments, progress).await,
+            "run_command_expect" => self.handle_run_command_expect(request.arguments).await,
+            "run_argv" => self.handle_run_argv(request.arguments).await,
+            "cancel_execution" => self.handle_cancel_execution(request.arguments).await,
             "read_file" => self.handle_read_file(request.arguments).await,
+            "download_file" => self.handle_download_file(request.arguments).await,
+            "export_workspace" => self.handle_export_workspace(request.arguments).await,
+            "stat_file" => self.handle_stat_file(request.arguments).await,
+            "tail_file" => self.handle_tail_file(request.arguments).await,
             "write_file" => self.handle_write_file(request.arguments).await,
+            "write_files" => self.handle_write_files(request.arguments).await,
+            "touch_file" => self.handle_touch_file(request.arguments).await,
+            "delete_file" => self.handle_delete_file(request.arguments).await,
+            "move_file" => self.handle_move_file(request.arguments).await,
+            "make_directory" => self.handle_make_directory(request.arguments).await,
+            "resize_scratch" => self.handle_resize_scratch(request.arguments).await,
             "list_directory" => self.handle_list_directory(request.arguments).await,
+            "os_info" => self.handle_os_info(request.arguments).await,
+            "check_connectivity" => self.handle_check_connectivity(request.arguments).await,
             _ => {
                 tracing::warn!(tool = tool_name, "Unknown tool invoked");
                 Self::error_result(format!("Unknown tool: {}", request.name))
             }
         };
 
-        Ok(result)
+        self.metrics
+            .record(tool_name, start.elapsed(), result.is_error.unwrap_or(false));
+        result
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use bouvet_core::SandboxConfig;
 
     #[test]
     fn test_parse_sandbox_id_valid() {
@@ -776,10 +2456,701 @@ mod tests {
 
     #[test]
     fn test_build_tools_list() {
-        let tools = BouvetServer::build_tools_list();
-        assert_eq!(tools.len(), 8);
+        let tools = BouvetServer::build_tools_list(&SandboxCapabilities::default());
+        assert_eq!(tools.len(), 30);
         assert!(tools.iter().any(|t| t.name.as_ref() == "create_sandbox"));
         assert!(tools.iter().any(|t| t.name.as_ref() == "destroy_sandbox"));
+        assert!(tools
+            .iter()
+            .any(|t| t.name.as_ref() == "destroy_sandbox_by_key"));
         assert!(tools.iter().any(|t| t.name.as_ref() == "execute_code"));
+        assert!(tools.iter().any(|t| t.name.as_ref() == "execute_project"));
+        assert!(tools.iter().any(|t| t.name.as_ref() == "touch_file"));
+        assert!(tools.iter().any(|t| t.name.as_ref() == "tail_file"));
+    }
+
+    #[test]
+    fn test_build_tools_list_hides_disabled_capabilities() {
+        let tools = BouvetServer::build_tools_list(&SandboxCapabilities::read_only());
+        let names: Vec<&str> = tools.iter().map(|t| t.name.as_ref()).collect();
+        for hidden in [
+            "execute_code",
+            "execute_project",
+            "run_command",
+            "run_command_expect",
+            "run_argv",
+            "cancel_execution",
+            "export_workspace",
+            "write_file",
+            "write_files",
+            "touch_file",
+            "delete_file",
+            "move_file",
+            "make_directory",
+            "resize_scratch",
+        ] {
+            assert!(!names.contains(&hidden), "{hidden} should be hidden");
+        }
+        assert!(names.contains(&"read_file"));
+        assert!(names.contains(&"download_file"));
+        assert!(names.contains(&"list_directory"));
+        assert!(names.contains(&"tail_file"));
+        assert!(names.contains(&"stat_file"));
+        assert!(names.contains(&"get_resource_totals"));
+        assert!(names.contains(&"os_info"));
+        assert!(names.contains(&"check_connectivity"));
+        assert!(names.contains(&"get_pool_stats"));
+        assert!(names.contains(&"health_check_all"));
+        assert!(names.contains(&"get_sandbox"));
+        assert_eq!(tools.len(), 16);
+    }
+
+    #[tokio::test]
+    async fn test_admin_tools_require_opt_in() {
+        let chroot =
+            std::env::temp_dir().join(format!("bouvet-mcp-admin-{}", uuid::Uuid::new_v4()));
+        let call = |name: &str| CallToolRequestParam {
+            name: name.to_string().into(),
+            arguments: None,
+        };
+
+        let server = BouvetServer::new(BouvetConfig {
+            pool_enabled: false,
+            chroot_path: chroot.clone(),
+            ..Default::default()
+        });
+        assert!(!server.tools().iter().any(|t| t.name == "find_orphans"));
+        let result = server.invoke_tool(call("cleanup_orphans"), None).await;
+        assert_eq!(result.is_error, Some(true));
+
+        let server = BouvetServer::new(BouvetConfig {
+            pool_enabled: false,
+            chroot_path: chroot,
+            admin_tools: true,
+            ..Default::default()
+        });
+        let names: Vec<_> = server.tools().into_iter().map(|t| t.name).collect();
+        assert!(names.iter().any(|n| n == "find_orphans"));
+        assert!(names.iter().any(|n| n == "cleanup_orphans"));
+        let result = server.invoke_tool(call("find_orphans"), None).await;
+        assert_ne!(result.is_error, Some(true));
+        let text = result.content[0].as_text().unwrap().text.clone();
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["orphans"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_stat_result() {
+        let stat: bouvet_core::FileStat = serde_json::from_value(serde_json::json!({
+            "file_type": "dir",
+            "size": 4096,
+            "mode": 0o755,
+            "modified_ms": 1_700_000_000_000i64,
+        }))
+        .unwrap();
+        let json = serde_json::to_value(BouvetServer::stat_result(&stat)).unwrap();
+        assert_eq!(json["file_type"], "dir");
+        assert_eq!(json["mode"], "755");
+        assert_eq!(json["modified"], "2023-11-14T22:13:20+00:00");
+    }
+
+    #[test]
+    fn test_create_error_phase() {
+        let error =
+            BouvetServer::create_error(&CoreError::BootTimeout(std::time::Duration::from_secs(10)));
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["phase"], "agent_wait");
+        assert_eq!(json["kind"], "boot_timeout");
+        assert!(json["message"]
+            .as_str()
+            .unwrap()
+            .contains("agent not ready"));
+
+        let error = BouvetServer::create_error(&CoreError::ConnectFailed("bad handshake".into()));
+        assert_eq!(error.phase, "agent_connect");
+        assert_eq!(error.kind, "connect_failed");
+
+        let error = BouvetServer::create_error(&CoreError::BootBudgetExceeded {
+            max_boot_ms: 500,
+            elapsed_ms: 512,
+        });
+        assert_eq!(error.phase, "boot_budget");
+        assert_eq!(error.kind, "boot_budget_exceeded");
+
+        let error = BouvetServer::create_error(&CoreError::PoolShuttingDown);
+        assert_eq!(error.phase, "pool");
+        assert_eq!(error.kind, "pool_shutting_down");
+    }
+
+    #[test]
+    fn test_truncate_for_log_redacts_secrets() {
+        let server = BouvetServer::new(BouvetConfig {
+            pool_enabled: false,
+            ..Default::default()
+        });
+        assert_eq!(
+            server.truncate_for_log("FOO_TOKEN=hunter2 ./deploy.sh", 200),
+            "FOO_TOKEN=*** ./deploy.sh"
+        );
+
+        // Redaction happens before truncation, so a cut can't expose a prefix
+        let command = format!("FOO_TOKEN=hunter2 {}", "x".repeat(300));
+        let preview = server.truncate_for_log(&command, 20);
+        assert!(preview.starts_with("FOO_TOKEN=*** xxxxxx..."));
+        assert!(!preview.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_resolve_input_base64_matches_plain() {
+        let code = "print(\"tab\\there\")\r\n# ünïcode \u{1b}[0m\n";
+        let plain = BouvetServer::resolve_input(Some(code.into()), None, "code", 1024).unwrap();
+        let decoded =
+            BouvetServer::resolve_input(None, Some(BASE64.encode(code)), "code", 1024).unwrap();
+        assert_eq!(plain, code);
+        assert_eq!(decoded, plain);
+
+        let err =
+            BouvetServer::resolve_input(Some(code.into()), Some(BASE64.encode(code)), "code", 1024)
+                .unwrap_err();
+        assert!(err.contains("not both"), "{err}");
+        assert!(BouvetServer::resolve_input(None, None, "code", 1024)
+            .unwrap_err()
+            .contains("code_base64"));
+        assert!(
+            BouvetServer::resolve_input(None, Some("not base64!".into()), "code", 1024)
+                .unwrap_err()
+                .contains("Invalid base64")
+        );
+        assert!(
+            BouvetServer::resolve_input(None, Some(BASE64.encode([0xff, 0xfe])), "code", 1024)
+                .unwrap_err()
+                .contains("UTF-8")
+        );
+
+        // The limit applies to the decoded size
+        let big = "x".repeat(100);
+        assert!(BouvetServer::resolve_input(None, Some(BASE64.encode(&big)), "code", 100).is_ok());
+        assert!(
+            BouvetServer::resolve_input(None, Some(BASE64.encode(&big)), "code", 99)
+                .unwrap_err()
+                .contains("exceeds maximum size")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_command_rejects_plain_and_base64() {
+        let server = BouvetServer::new(BouvetConfig {
+            pool_enabled: false,
+            ..Default::default()
+        });
+        let result = server
+            .invoke_tool(
+                CallToolRequestParam {
+                    name: "run_command".into(),
+                    arguments: serde_json::json!({
+                        "sandbox_id": "550e8400-e29b-41d4-a716-446655440000",
+                        "command": "echo hi",
+                        "command_base64": BASE64.encode("echo hi"),
+                    })
+                    .as_object()
+                    .cloned(),
+                },
+                None,
+            )
+            .await;
+        assert_eq!(result.is_error, Some(true));
+        let text = result.content[0].as_text().unwrap().text.clone();
+        assert!(text.contains("not both"), "{text}");
+    }
+
+    #[tokio::test]
+    async fn test_create_sandbox_invalid_config_error_json() {
+        let server = BouvetServer::new(BouvetConfig {
+            pool_enabled: false,
+            ..Default::default()
+        });
+        let result = server
+            .invoke_tool(
+                CallToolRequestParam {
+                    name: "create_sandbox".into(),
+                    arguments: serde_json::json!({"memory_mib": 0}).as_object().cloned(),
+                },
+                None,
+            )
+            .await;
+        assert_eq!(result.is_error, Some(true));
+        let text = result.content[0].as_text().unwrap().text.clone();
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["phase"], "config");
+        assert_eq!(json["kind"], "invalid_config");
+    }
+
+    #[tokio::test]
+    async fn test_create_sandbox_rejects_invalid_proxy() {
+        let server = BouvetServer::new(BouvetConfig {
+            pool_enabled: false,
+            ..Default::default()
+        });
+        let result = server
+            .invoke_tool(
+                CallToolRequestParam {
+                    name: "create_sandbox".into(),
+                    arguments: serde_json::json!({"https_proxy": "http://proxy:3128 init=/bin/sh"})
+                        .as_object()
+                        .cloned(),
+                },
+                None,
+            )
+            .await;
+        assert_eq!(result.is_error, Some(true));
+        let text = result.content[0].as_text().unwrap().text.clone();
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["kind"], "invalid_config");
+        assert!(json["message"].as_str().unwrap().contains("https_proxy"));
+    }
+
+    #[tokio::test]
+    async fn test_create_sandbox_rejects_custom_image_when_disabled() {
+        let server = BouvetServer::new(BouvetConfig {
+            pool_enabled: false,
+            ..Default::default()
+        });
+        let result = server
+            .invoke_tool(
+                CallToolRequestParam {
+                    name: "create_sandbox".into(),
+                    arguments: serde_json::json!({"rootfs_path": "/etc/shadow"})
+                        .as_object()
+                        .cloned(),
+                },
+                None,
+            )
+            .await;
+        assert_eq!(result.is_error, Some(true));
+        let text = result.content[0].as_text().unwrap().text.clone();
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["phase"], "config");
+        assert_eq!(json["kind"], "invalid_image");
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(BouvetServer::parse_mode("755"), Ok(0o755));
+        assert_eq!(BouvetServer::parse_mode("0644"), Ok(0o644));
+        assert_eq!(BouvetServer::parse_mode("0o4755"), Ok(0o4755));
+        assert!(BouvetServer::parse_mode("rwx").is_err());
+        assert!(BouvetServer::parse_mode("789").is_err());
+        assert!(BouvetServer::parse_mode("17777").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_files_reports_each_file() {
+        use bouvet_core::MockBackend;
+
+        let root = std::env::temp_dir().join(format!("bouvet-mcp-batch-{}", uuid::Uuid::new_v4()));
+        let config = BouvetConfig {
+            pool_enabled: false,
+            ..Default::default()
+        };
+        let manager = SandboxManager::with_backend(
+            ManagerConfig::new(
+                &config.kernel_path,
+                &config.rootfs_path,
+                &config.firecracker_path,
+                &root,
+            ),
+            Arc::new(MockBackend::new()),
+        );
+        let server = BouvetServer {
+            manager: Arc::new(manager),
+            ..BouvetServer::new(config)
+        };
+        let sandbox_config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+        let id = server.manager.create(sandbox_config).await.unwrap();
+
+        // The second path is below the first file, so it can't be written
+        let result = server
+            .invoke_tool(
+                CallToolRequestParam {
+                    name: "write_files".into(),
+                    arguments: serde_json::json!({
+                        "sandbox_id": id.to_string(),
+                        "files": [
+                            {"path": "/work/main.py", "content": "print(1)"},
+                            {"path": "/work/main.py/util.py", "content": "x = 1"},
+                            {"path": "/work/run.sh", "content": "python3 main.py", "mode": "755"},
+                        ],
+                    })
+                    .as_object()
+                    .cloned(),
+                },
+                None,
+            )
+            .await;
+        assert_ne!(result.is_error, Some(true));
+        let text = result.content[0].as_text().unwrap().text.clone();
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["written"], 2);
+        assert_eq!(json["failed"], 1);
+        let files = json["files"].as_array().unwrap();
+        assert_eq!(files[0]["success"], true);
+        assert!(files[0].get("error").is_none());
+        assert_eq!(files[1]["path"], "/work/main.py/util.py");
+        assert_eq!(files[1]["success"], false);
+        assert!(files[1]["error"]
+            .as_str()
+            .unwrap()
+            .contains("Not a directory"));
+        assert_eq!(files[2]["success"], true);
+        assert_eq!(
+            server.manager.read_file(id, "/work/run.sh").await.unwrap(),
+            "python3 main.py"
+        );
+
+        server.manager.destroy(id).await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_execution() {
+        use bouvet_core::MockBackend;
+
+        let root = std::env::temp_dir().join(format!("bouvet-mcp-cancel-{}", uuid::Uuid::new_v4()));
+        let config = BouvetConfig {
+            pool_enabled: false,
+            ..Default::default()
+        };
+        let manager = SandboxManager::with_backend(
+            ManagerConfig::new(
+                &config.kernel_path,
+                &config.rootfs_path,
+                &config.firecracker_path,
+                &root,
+            ),
+            Arc::new(MockBackend::new()),
+        );
+        let server = BouvetServer::new(config).with_manager(manager);
+        let sandbox_config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .build()
+            .unwrap();
+        let id = server.manager.create(sandbox_config).await.unwrap();
+        let stream = server
+            .manager
+            .execute_stream(id, "sleep 30", &ExecOptions::default())
+            .await
+            .unwrap();
+
+        let cancel = |arguments: serde_json::Value| {
+            server.invoke_tool(
+                CallToolRequestParam {
+                    name: "cancel_execution".into(),
+                    arguments: arguments.as_object().cloned(),
+                },
+                None,
+            )
+        };
+        let result = cancel(serde_json::json!({"sandbox_id": id.to_string()})).await;
+        assert_ne!(result.is_error, Some(true));
+        let text = result.content[0].as_text().unwrap().text.clone();
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["cancelled"], serde_json::json!([stream.id()]));
+
+        let result = stream.finish().await.unwrap();
+        assert!(result.stderr.contains("cancelled with SIGTERM"));
+
+        let result = cancel(serde_json::json!({"sandbox_id": id.to_string(), "exec_id": 1})).await;
+        assert_eq!(result.is_error, Some(true));
+
+        server.manager.destroy(id).await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_get_sandbox() {
+        use bouvet_core::MockBackend;
+
+        let root = std::env::temp_dir().join(format!("bouvet-mcp-get-{}", uuid::Uuid::new_v4()));
+        let config = BouvetConfig {
+            pool_enabled: false,
+            ..Default::default()
+        };
+        let manager = SandboxManager::with_backend(
+            ManagerConfig::new(
+                &config.kernel_path,
+                &config.rootfs_path,
+                &config.firecracker_path,
+                &root,
+            ),
+            Arc::new(MockBackend::new()),
+        );
+        let server = BouvetServer::new(config).with_manager(manager);
+        let sandbox_config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .memory_mib(512)
+            .label("session", "abc")
+            .build()
+            .unwrap();
+        let id = server.manager.create(sandbox_config).await.unwrap();
+
+        let get = |sandbox_id: String| {
+            server.invoke_tool(
+                CallToolRequestParam {
+                    name: "get_sandbox".into(),
+                    arguments: serde_json::json!({ "sandbox_id": sandbox_id })
+                        .as_object()
+                        .cloned(),
+                },
+                None,
+            )
+        };
+        let result = get(id.to_string()).await;
+        assert_ne!(result.is_error, Some(true));
+        let text = result.content[0].as_text().unwrap().text.clone();
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["sandbox_id"], id.to_string());
+        assert_eq!(json["state"], "Ready");
+        assert_eq!(json["memory_mib"], 512);
+        assert_eq!(json["labels"]["session"], "abc");
+
+        // Unknown and malformed IDs are indistinguishable
+        let unknown = get(uuid::Uuid::new_v4().to_string()).await;
+        let malformed = get("not-an-id".into()).await;
+        assert_eq!(unknown.is_error, Some(true));
+        assert_eq!(
+            unknown.content[0].as_text().unwrap().text,
+            malformed.content[0].as_text().unwrap().text
+        );
+
+        server.manager.destroy(id).await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_export_workspace() {
+        use bouvet_core::MockBackend;
+
+        let root = std::env::temp_dir().join(format!("bouvet-mcp-export-{}", uuid::Uuid::new_v4()));
+        let config = BouvetConfig {
+            pool_enabled: false,
+            ..Default::default()
+        };
+        let manager = SandboxManager::with_backend(
+            ManagerConfig::new(
+                &config.kernel_path,
+                &config.rootfs_path,
+                &config.firecracker_path,
+                &root,
+            ),
+            Arc::new(MockBackend::new()),
+        );
+        let server = BouvetServer::new(config).with_manager(manager);
+        let sandbox_config = SandboxConfig::builder()
+            .kernel("/vmlinux")
+            .rootfs("/rootfs.ext4")
+            .chroot_path(&root)
+            .work_dir("/work")
+            .build()
+            .unwrap();
+        let id = server.manager.create(sandbox_config).await.unwrap();
+        server
+            .manager
+            .write_file(id, "/work/main.py", "print(1)")
+            .await
+            .unwrap();
+        server
+            .manager
+            .write_file(id, "/work/src/util.py", "x = 1")
+            .await
+            .unwrap();
+        server
+            .manager
+            .write_file(id, "/etc/hosts", "127.0.0.1 localhost")
+            .await
+            .unwrap();
+
+        let export = |arguments: serde_json::Value| {
+            server.invoke_tool(
+                CallToolRequestParam {
+                    name: "export_workspace".into(),
+                    arguments: arguments.as_object().cloned(),
+                },
+                None,
+            )
+        };
+        let result = export(serde_json::json!({"sandbox_id": id.to_string()})).await;
+        assert_ne!(result.is_error, Some(true));
+        let text = result.content[0].as_text().unwrap().text.clone();
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["dir"], "/work");
+        let archive = BASE64
+            .decode(json["content_base64"].as_str().unwrap())
+            .unwrap();
+        assert_eq!(json["size"], archive.len() as u64);
+
+        // The archive is a real .tar.gz holding just the work_dir
+        let archive_path = root.join("work.tar.gz");
+        std::fs::write(&archive_path, &archive).unwrap();
+        let listing = std::process::Command::new("tar")
+            .arg("-tzf")
+            .arg(&archive_path)
+            .output()
+            .unwrap();
+        assert!(listing.status.success());
+        let mut names: Vec<_> = String::from_utf8(listing.stdout)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["./main.py", "./src/util.py"]);
+
+        // With a host path the archive is written there instead
+        let host_path = root.join("out.tar.gz");
+        let result = export(serde_json::json!({
+            "sandbox_id": id.to_string(),
+            "host_path": host_path.to_str().unwrap(),
+        }))
+        .await;
+        assert_ne!(result.is_error, Some(true));
+        let text = result.content[0].as_text().unwrap().text.clone();
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert!(json.get("content_base64").is_none());
+        assert_eq!(json["host_path"], host_path.to_str().unwrap());
+        assert_eq!(std::fs::read(&host_path).unwrap(), archive);
+
+        // System directories are refused
+        let result =
+            export(serde_json::json!({"sandbox_id": id.to_string(), "dir": "/proc/1"})).await;
+        assert_eq!(result.is_error, Some(true));
+
+        server.manager.destroy(id).await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_missing_params_error_is_uniform() {
+        let server = BouvetServer::new(BouvetConfig {
+            pool_enabled: false,
+            ..Default::default()
+        });
+        let tools = [
+            ("destroy_sandbox", "sandbox_id"),
+            ("destroy_sandbox_by_key", "key"),
+            ("execute_code", "language, sandbox_id"),
+            ("execute_project", "entry, files, language, sandbox_id"),
+            ("run_command", "sandbox_id"),
+            ("run_command_expect", "command, sandbox_id"),
+            ("run_argv", "argv, sandbox_id"),
+            ("cancel_execution", "sandbox_id"),
+            ("read_file", "path, sandbox_id"),
+            ("download_file", "path, sandbox_id"),
+            ("export_workspace", "sandbox_id"),
+            ("stat_file", "path, sandbox_id"),
+            ("tail_file", "path, sandbox_id"),
+            ("write_file", "content, path, sandbox_id"),
+            ("write_files", "files, sandbox_id"),
+            ("touch_file", "path, sandbox_id"),
+            ("delete_file", "path, sandbox_id"),
+            ("move_file", "destination, sandbox_id, source"),
+            ("make_directory", "path, sandbox_id"),
+            ("resize_scratch", "sandbox_id, size_mib"),
+            ("list_directory", "path, sandbox_id"),
+            ("os_info", "sandbox_id"),
+            ("get_sandbox", "sandbox_id"),
+            ("check_connectivity", "host, port, sandbox_id"),
+        ];
+        for (tool, required) in tools {
+            for arguments in [None, serde_json::json!({}).as_object().cloned()] {
+                let result = server
+                    .invoke_tool(
+                        CallToolRequestParam {
+                            name: tool.into(),
+                            arguments,
+                        },
+                        None,
+                    )
+                    .await;
+                assert_eq!(result.is_error, Some(true), "{tool}");
+                let text = result.content[0].as_text().unwrap().text.clone();
+                assert!(
+                    text.starts_with("Invalid parameters: missing field"),
+                    "{tool}: {text}"
+                );
+                assert!(
+                    text.ends_with(&format!("Required: {required}")),
+                    "{tool}: {text}"
+                );
+            }
+        }
+
+        // Wrong shapes are reported too, rather than silently defaulted
+        let result = server
+            .invoke_tool(
+                CallToolRequestParam {
+                    name: "create_sandbox".into(),
+                    arguments: serde_json::json!({"memory_mib": "lots"})
+                        .as_object()
+                        .cloned(),
+                },
+                None,
+            )
+            .await;
+        assert_eq!(result.is_error, Some(true));
+        let text = result.content[0].as_text().unwrap().text.clone();
+        assert!(
+            text.starts_with("Invalid parameters: invalid type"),
+            "{text}"
+        );
+        assert!(text.ends_with("Required: none"), "{text}");
+    }
+
+    #[tokio::test]
+    async fn test_invoke_tool_records_metrics() {
+        let server = BouvetServer::new(BouvetConfig::default());
+        let call = |name: &'static str, args: serde_json::Value| CallToolRequestParam {
+            name: name.into(),
+            arguments: args.as_object().cloned(),
+        };
+        let stats = |server: &BouvetServer, name: &str| {
+            server
+                .tool_metrics()
+                .snapshot()
+                .into_iter()
+                .find(|s| s.tool == name)
+                .unwrap()
+        };
+
+        let result = server
+            .invoke_tool(call("list_sandboxes", serde_json::json!({})), None)
+            .await;
+        assert_ne!(result.is_error, Some(true));
+        let list = stats(&server, "list_sandboxes");
+        assert_eq!((list.calls, list.errors), (1, 0));
+
+        let result = server
+            .invoke_tool(
+                call(
+                    "read_file",
+                    serde_json::json!({"sandbox_id": "not-a-uuid", "path": "/etc/hostname"}),
+                ),
+                None,
+            )
+            .await;
+        assert_eq!(result.is_error, Some(true));
+        let read = stats(&server, "read_file");
+        assert_eq!((read.calls, read.errors), (1, 1));
+
+        // Other tools are untouched
+        assert_eq!(stats(&server, "run_command").calls, 0);
     }
 }
@@ -0,0 +1,118 @@
+//! Process shutdown signal handling.
+//!
+//! Waits for either Ctrl-C (SIGINT) or SIGTERM. Process supervisors such as
+//! systemd and Kubernetes stop services with SIGTERM, so listening for Ctrl-C
+//! alone would skip pool shutdown and sandbox cleanup and leak VMs.
+
+use std::fmt;
+use std::future::Future;
+
+/// The signal that requested shutdown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownSignal {
+    /// Ctrl-C / SIGINT.
+    CtrlC,
+    /// SIGTERM.
+    Terminate,
+}
+
+impl fmt::Display for ShutdownSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CtrlC => write!(f, "SIGINT"),
+            Self::Terminate => write!(f, "SIGTERM"),
+        }
+    }
+}
+
+/// Wait until the process is asked to shut down.
+///
+/// Resolves on Ctrl-C, or on SIGTERM on Unix platforms.
+///
+/// # Errors
+///
+/// Returns an error if a signal handler can't be installed.
+pub async fn shutdown_signal() -> std::io::Result<ShutdownSignal> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut term = signal(SignalKind::terminate())?;
+        select_shutdown(tokio::signal::ctrl_c(), async {
+            term.recv().await;
+        })
+        .await
+    }
+
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await?;
+        Ok(ShutdownSignal::CtrlC)
+    }
+}
+
+/// Resolve with whichever of the Ctrl-C or terminate futures completes first.
+async fn select_shutdown(
+    ctrl_c: impl Future<Output = std::io::Result<()>>,
+    terminate: impl Future<Output = ()>,
+) -> std::io::Result<ShutdownSignal> {
+    tokio::select! {
+        result = ctrl_c => result.map(|()| ShutdownSignal::CtrlC),
+        () = terminate => Ok(ShutdownSignal::Terminate),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_select_shutdown_ctrl_c() {
+        let signal = select_shutdown(async { Ok(()) }, std::future::pending())
+            .await
+            .unwrap();
+        assert_eq!(signal, ShutdownSignal::CtrlC);
+    }
+
+    #[tokio::test]
+    async fn test_select_shutdown_terminate() {
+        let signal = select_shutdown(std::future::pending(), async {})
+            .await
+            .unwrap();
+        assert_eq!(signal, ShutdownSignal::Terminate);
+    }
+
+    #[tokio::test]
+    async fn test_select_shutdown_ctrl_c_error() {
+        let result = select_shutdown(
+            async { Err(std::io::Error::other("no handler")) },
+            std::future::pending(),
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shutdown_signal_on_sigterm() {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        // Install the handler first so SIGTERM can't kill the test process
+        let _guard = signal(SignalKind::terminate()).unwrap();
+        let waiter = tokio::spawn(shutdown_signal());
+        tokio::task::yield_now().await;
+
+        let status = std::process::Command::new("kill")
+            .args(["-TERM", &std::process::id().to_string()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let signal = tokio::time::timeout(std::time::Duration::from_secs(5), waiter)
+            .await
+            .expect("SIGTERM not observed")
+            .unwrap()
+            .unwrap();
+        assert_eq!(signal, ShutdownSignal::Terminate);
+    }
+}
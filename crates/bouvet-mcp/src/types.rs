@@ -5,6 +5,7 @@
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 // ============================================================================
 // Sandbox Lifecycle
@@ -13,13 +14,68 @@ use serde::{Deserialize, Serialize};
 /// Parameters for creating a new sandbox.
 #[derive(Debug, Default, Deserialize, JsonSchema)]
 pub struct CreateSandboxParams {
-    /// Memory in MiB (default: 256).
+    /// Memory in MiB (default: `BOUVET_DEFAULT_MEMORY_MIB`, 256).
     #[serde(default)]
     pub memory_mib: Option<u32>,
 
-    /// vCPU count (default: 2).
+    /// vCPU count (default: `BOUVET_DEFAULT_VCPU`, 2).
     #[serde(default)]
     pub vcpu_count: Option<u8>,
+
+    /// Client-supplied key (e.g. an idempotency key). If a sandbox already
+    /// exists for this key, its ID is returned instead of creating a new one.
+    #[serde(default)]
+    pub key: Option<String>,
+
+    /// Allow running commands and code (default: server setting). Can only
+    /// restrict what the server allows.
+    #[serde(default)]
+    pub allow_exec: Option<bool>,
+
+    /// Allow writing files (default: server setting). Can only restrict
+    /// what the server allows.
+    #[serde(default)]
+    pub allow_write: Option<bool>,
+
+    /// Kernel image to boot instead of the server default. Requires
+    /// custom images to be enabled; must be inside the server's image
+    /// directory.
+    #[serde(default)]
+    pub kernel_path: Option<String>,
+
+    /// Rootfs image to boot instead of the server default. Same
+    /// restrictions as `kernel_path`.
+    #[serde(default)]
+    pub rootfs_path: Option<String>,
+
+    /// Fail instead of waiting if booting takes longer than this many
+    /// milliseconds. A warm-pool sandbox is always within budget.
+    #[serde(default)]
+    pub max_boot_ms: Option<u64>,
+
+    /// Absolute guest directory, created at boot, that commands and code
+    /// run in unless a call gives its own `cwd` (default: `/`).
+    #[serde(default)]
+    pub work_dir: Option<String>,
+
+    /// Proxy URL for plain HTTP, exported to every command as `http_proxy`
+    /// and `HTTP_PROXY`.
+    #[serde(default)]
+    pub http_proxy: Option<String>,
+
+    /// Proxy URL for HTTPS, exported as `https_proxy` and `HTTPS_PROXY`.
+    #[serde(default)]
+    pub https_proxy: Option<String>,
+
+    /// Comma-separated hosts that bypass the proxies, exported as
+    /// `no_proxy` and `NO_PROXY`.
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+
+    /// Key/value tags, e.g. the task or session the sandbox is for, shown
+    /// by `list_sandboxes` so the right sandbox can be found again.
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
 }
 
 /// Result of creating a sandbox.
@@ -29,6 +85,21 @@ pub struct CreateSandboxResult {
     pub sandbox_id: String,
 }
 
+/// Error payload returned when `create_sandbox` fails.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CreateSandboxError {
+    /// Step that failed: `config`, `register`, `vm_boot`, `agent_wait`,
+    /// `agent_connect`, or `create` for anything else.
+    pub phase: String,
+
+    /// Error kind (e.g. `boot_timeout`, `vm`, `invalid_config`,
+    /// `invalid_image`).
+    pub kind: String,
+
+    /// Human-readable error message.
+    pub message: String,
+}
+
 /// Parameters for destroying a sandbox.
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct DestroySandboxParams {
@@ -36,6 +107,13 @@ pub struct DestroySandboxParams {
     pub sandbox_id: String,
 }
 
+/// Parameters for destroying a sandbox by its client-supplied key.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DestroySandboxByKeyParams {
+    /// Key the sandbox was created with.
+    pub key: String,
+}
+
 /// Result of destroying a sandbox.
 #[derive(Debug, Serialize, JsonSchema)]
 pub struct DestroySandboxResult {
@@ -50,6 +128,93 @@ pub struct ListSandboxesResult {
     pub sandboxes: Vec<SandboxInfo>,
 }
 
+/// Parameters for getting one sandbox's details.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetSandboxParams {
+    /// ID of the sandbox.
+    pub sandbox_id: String,
+}
+
+/// Details of one sandbox.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct GetSandboxResult {
+    /// Unique identifier for the sandbox.
+    pub sandbox_id: String,
+    /// Current state of the sandbox.
+    pub state: String,
+    /// When the sandbox was created (ISO 8601).
+    pub created_at: String,
+    /// Seconds since the sandbox was created.
+    pub uptime_secs: u64,
+    /// Configured vCPU count.
+    pub vcpu_count: u8,
+    /// Configured memory in MiB.
+    pub memory_mib: u32,
+    /// Labels given at creation.
+    pub labels: HashMap<String, String>,
+}
+
+/// A VM left in the chroot directory with no sandbox registered for it.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct OrphanVmInfo {
+    /// ID the VM was created with.
+    pub sandbox_id: String,
+    /// Firecracker API socket of the VM.
+    pub socket_path: String,
+    /// Firecracker process ID, if it is still running.
+    pub pid: Option<u32>,
+}
+
+/// Result of scanning for orphaned VMs.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct FindOrphansResult {
+    /// Orphaned VMs, ordered by ID.
+    pub orphans: Vec<OrphanVmInfo>,
+}
+
+/// Result of cleaning up orphaned VMs.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CleanupOrphansResult {
+    /// Orphaned VMs that were killed and removed.
+    pub cleaned: Vec<OrphanVmInfo>,
+}
+
+/// Aggregate resources committed to active sandboxes.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ResourceTotalsResult {
+    /// Number of active sandboxes.
+    pub count: usize,
+
+    /// Sum of vCPUs configured across sandboxes.
+    pub total_vcpus: u64,
+
+    /// Sum of memory configured across sandboxes, in MiB.
+    pub total_memory_mib: u64,
+}
+
+/// Result of health-checking every sandbox.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct HealthCheckAllResult {
+    /// Number of sandboxes whose agent answered.
+    pub healthy: usize,
+
+    /// Number of sandboxes that didn't answer or aren't ready.
+    pub unhealthy: usize,
+
+    /// Health of each sandbox.
+    pub sandboxes: Vec<SandboxHealth>,
+}
+
+/// Health of one sandbox.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SandboxHealth {
+    /// Unique identifier for the sandbox.
+    pub sandbox_id: String,
+
+    /// Whether its agent answered a ping in time.
+    pub healthy: bool,
+}
+
 /// Information about a sandbox.
 #[derive(Debug, Serialize, JsonSchema)]
 pub struct SandboxInfo {
@@ -59,6 +224,38 @@ pub struct SandboxInfo {
     pub state: String,
     /// When the sandbox was created (ISO 8601).
     pub created_at: String,
+    /// Guest hostname, if known (defaults to the first 8 characters of the ID).
+    pub hostname: Option<String>,
+    /// Time spent in each phase of creation.
+    pub creation_timing: CreationTimingInfo,
+    /// Operations the sandbox is allowed to perform.
+    pub capabilities: CapabilitiesInfo,
+    /// Labels given at creation.
+    pub labels: HashMap<String, String>,
+}
+
+/// Operations a sandbox is allowed to perform.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CapabilitiesInfo {
+    /// Running commands and code.
+    pub exec: bool,
+    /// Writing files.
+    pub write: bool,
+    /// Outbound network access.
+    pub network: bool,
+}
+
+/// Time spent in each phase of sandbox creation, in milliseconds.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CreationTimingInfo {
+    /// Booting the microVM.
+    pub vm_create_ms: u64,
+    /// Connecting to the guest agent.
+    pub agent_connect_ms: u64,
+    /// Initial agent ping.
+    pub ping_ms: u64,
+    /// Total creation time.
+    pub total_ms: u64,
 }
 
 // ============================================================================
@@ -71,11 +268,83 @@ pub struct ExecuteCodeParams {
     /// ID of the sandbox to execute in.
     pub sandbox_id: String,
 
-    /// Language to execute (python, python3, node, javascript, bash, sh).
+    /// Language to execute (python, python3, node, javascript, bash, sh, ruby, go, typescript, deno, rust).
+    pub language: String,
+
+    /// Code to execute. Give either this or `code_base64`.
+    #[serde(default)]
+    pub code: Option<String>,
+
+    /// Code to execute, base64-encoded, for code that doesn't survive JSON
+    /// string escaping. Must decode to UTF-8.
+    #[serde(default)]
+    pub code_base64: Option<String>,
+
+    /// Kill the process once stdout and stderr together exceed this many
+    /// bytes (default: none; output is truncated at 1 MiB per stream).
+    #[serde(default)]
+    pub max_output_bytes: Option<u64>,
+
+    /// Guest directory to run in (default: the sandbox's `work_dir`, else
+    /// `/`).
+    #[serde(default)]
+    pub cwd: Option<String>,
+
+    /// Kill the process if it is still running after this many seconds
+    /// (default: none).
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+
+    /// Environment variables for this run only, e.g. {"API_KEY": "..."}.
+    /// Added to the guest's default environment; later runs don't see them.
+    #[serde(default)]
+    pub env: Option<HashMap<String, String>>,
+
+    /// Report peak memory and CPU time in the result's `usage`
+    /// (default: false).
+    #[serde(default)]
+    pub usage: bool,
+}
+
+/// Parameters for executing a multi-file program.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExecuteProjectParams {
+    /// ID of the sandbox to execute in.
+    pub sandbox_id: String,
+
+    /// Language to execute (python, python3, node, javascript, bash, sh, go).
     pub language: String,
 
-    /// Code to execute.
-    pub code: String,
+    /// Relative path of the entry point, e.g. "main.py". Must be one of `files`.
+    pub entry: String,
+
+    /// Project files as relative path to content, e.g. {"main.py": "...", "utils.py": "..."}.
+    pub files: HashMap<String, String>,
+
+    /// Guest directory to create the project directory in (default: the
+    /// sandbox's `work_dir`, else the temp dir).
+    #[serde(default)]
+    pub cwd: Option<String>,
+
+    /// Kill the program once stdout and stderr together exceed this many
+    /// bytes (default: none; output is truncated at 1 MiB per stream).
+    #[serde(default)]
+    pub max_output_bytes: Option<u64>,
+
+    /// Kill the program if it is still running after this many seconds
+    /// (default: none).
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+
+    /// Environment variables for this run only, e.g. {"API_KEY": "..."}.
+    /// Added to the guest's default environment; later runs don't see them.
+    #[serde(default)]
+    pub env: Option<HashMap<String, String>>,
+
+    /// Report peak memory and CPU time in the result's `usage`
+    /// (default: false).
+    #[serde(default)]
+    pub usage: bool,
 }
 
 /// Parameters for running a shell command.
@@ -84,8 +353,68 @@ pub struct RunCommandParams {
     /// ID of the sandbox to execute in.
     pub sandbox_id: String,
 
+    /// Shell command to execute. Give either this or `command_base64`.
+    #[serde(default)]
+    pub command: Option<String>,
+
+    /// Shell command to execute, base64-encoded. Must decode to UTF-8.
+    #[serde(default)]
+    pub command_base64: Option<String>,
+
+    /// Kill the process once stdout and stderr together exceed this many
+    /// bytes (default: none; output is truncated at 1 MiB per stream).
+    #[serde(default)]
+    pub max_output_bytes: Option<u64>,
+
+    /// Guest directory to run in (default: the sandbox's `work_dir`, else
+    /// `/`).
+    #[serde(default)]
+    pub cwd: Option<String>,
+
+    /// Kill the process if it is still running after this many seconds
+    /// (default: none).
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+
+    /// Environment variables for this run only, e.g. {"API_KEY": "..."}.
+    /// Added to the guest's default environment; later runs don't see them.
+    #[serde(default)]
+    pub env: Option<HashMap<String, String>>,
+
+    /// Input written to the command's stdin, which is then closed
+    /// (default: empty stdin).
+    #[serde(default)]
+    pub stdin: Option<String>,
+
+    /// Report peak memory and CPU time in the result's `usage`
+    /// (default: false).
+    #[serde(default)]
+    pub usage: bool,
+}
+
+/// Parameters for running a shell command that must exit with a given code.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RunCommandExpectParams {
+    /// ID of the sandbox to execute in.
+    pub sandbox_id: String,
+
     /// Shell command to execute.
     pub command: String,
+
+    /// Exit code the command must return (default: 0).
+    #[serde(default)]
+    pub expected_exit_code: i32,
+}
+
+/// Parameters for running a program directly, without a shell.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RunArgvParams {
+    /// ID of the sandbox to execute in.
+    pub sandbox_id: String,
+
+    /// Program followed by its arguments. Passed verbatim, with no shell
+    /// quoting, globbing or variable expansion.
+    pub argv: Vec<String>,
 }
 
 /// Result of code or command execution.
@@ -99,6 +428,49 @@ pub struct ExecResponse {
 
     /// Standard error.
     pub stderr: String,
+
+    /// Whether the process was killed for exceeding `max_output_bytes`.
+    pub output_limit_exceeded: bool,
+
+    /// Resources the process used, when requested with `usage`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ResourceUsageInfo>,
+}
+
+/// Resources used by a command and the processes it waited for.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ResourceUsageInfo {
+    /// Peak resident set size of the largest of them, in KiB.
+    pub max_rss_kib: u64,
+
+    /// CPU time spent in user mode, in milliseconds.
+    pub user_time_ms: u64,
+
+    /// CPU time spent in the kernel, in milliseconds.
+    pub system_time_ms: u64,
+}
+
+/// Parameters for cancelling streamed commands.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CancelExecutionParams {
+    /// ID of the sandbox the command runs in.
+    pub sandbox_id: String,
+
+    /// ID of the command, from the `exec_id: N` progress message sent when
+    /// it started (default: every running streamed command).
+    #[serde(default)]
+    pub exec_id: Option<u64>,
+
+    /// Send SIGKILL instead of SIGTERM (default: false).
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Result of cancelling streamed commands.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CancelExecutionResult {
+    /// IDs of the commands that were signalled.
+    pub cancelled: Vec<u64>,
 }
 
 // ============================================================================
@@ -122,6 +494,117 @@ pub struct ReadFileResult {
     pub content: String,
 }
 
+/// Parameters for downloading a file.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DownloadFileParams {
+    /// ID of the sandbox.
+    pub sandbox_id: String,
+
+    /// Absolute path to the file.
+    pub path: String,
+}
+
+/// Result of downloading a file.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct DownloadFileResult {
+    /// File contents, base64-encoded.
+    pub content_base64: String,
+
+    /// File size in bytes.
+    pub size: u64,
+
+    /// SHA-256 of the contents, lowercase hex.
+    pub sha256: String,
+}
+
+/// Parameters for exporting a directory as an archive.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExportWorkspaceParams {
+    /// ID of the sandbox.
+    pub sandbox_id: String,
+
+    /// Absolute path of the directory to archive. Defaults to the sandbox's work_dir.
+    #[serde(default)]
+    pub dir: Option<String>,
+
+    /// Absolute host path to write the archive to. When omitted, the archive
+    /// is returned as base64.
+    #[serde(default)]
+    pub host_path: Option<String>,
+}
+
+/// Result of exporting a directory.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ExportWorkspaceResult {
+    /// Directory that was archived.
+    pub dir: String,
+
+    /// Archive size in bytes.
+    pub size: u64,
+
+    /// SHA-256 of the archive, lowercase hex.
+    pub sha256: String,
+
+    /// The `.tar.gz` archive, base64-encoded, unless it was written to `host_path`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_base64: Option<String>,
+
+    /// Host path the archive was written to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host_path: Option<String>,
+}
+
+/// Parameters for getting a file's metadata.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct StatFileParams {
+    /// ID of the sandbox.
+    pub sandbox_id: String,
+
+    /// Absolute path to the file or directory.
+    pub path: String,
+}
+
+/// Metadata of a file or directory.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct StatFileResult {
+    /// `file`, `dir`, `symlink` or `other`.
+    pub file_type: String,
+
+    /// Size in bytes.
+    pub size: u64,
+
+    /// Permission bits in octal, e.g. "644".
+    pub mode: String,
+
+    /// Last modification time (RFC 3339).
+    pub modified: String,
+}
+
+/// Parameters for tailing a file.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TailFileParams {
+    /// ID of the sandbox.
+    pub sandbox_id: String,
+
+    /// Absolute path to the file.
+    pub path: String,
+
+    /// Number of trailing lines to return (default: 10, max: 10000).
+    #[serde(default = "default_tail_lines")]
+    pub lines: usize,
+}
+
+fn default_tail_lines() -> usize {
+    10
+}
+
+/// Result of tailing a file.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TailFileResult {
+    /// The last lines of the file, oldest first.
+    pub lines: Vec<String>,
+}
+
 /// Parameters for writing a file.
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct WriteFileParams {
@@ -142,6 +625,153 @@ pub struct WriteFileResult {
     pub success: bool,
 }
 
+/// Parameters for writing several files at once.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct WriteFilesParams {
+    /// ID of the sandbox.
+    pub sandbox_id: String,
+
+    /// Files to write, in order.
+    pub files: Vec<FileToWrite>,
+}
+
+/// One file of a `write_files` batch.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FileToWrite {
+    /// Absolute path to the file.
+    pub path: String,
+
+    /// Content to write.
+    pub content: String,
+
+    /// Permission bits in octal, e.g. "755" (default: left unchanged).
+    #[serde(default)]
+    pub mode: Option<String>,
+}
+
+/// Result of writing several files.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct WriteFilesResult {
+    /// Number of files written.
+    pub written: usize,
+
+    /// Number of files that failed.
+    pub failed: usize,
+
+    /// Outcome of each file, in request order.
+    pub files: Vec<FileWriteOutcome>,
+}
+
+/// Outcome of writing one file of a batch.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct FileWriteOutcome {
+    /// Path of the file.
+    pub path: String,
+
+    /// Whether the file was written.
+    pub success: bool,
+
+    /// Why the file wasn't written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Parameters for touching a file.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TouchFileParams {
+    /// ID of the sandbox.
+    pub sandbox_id: String,
+
+    /// Absolute path to the file.
+    pub path: String,
+}
+
+/// Result of touching a file.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TouchFileResult {
+    /// Whether the operation succeeded.
+    pub success: bool,
+}
+
+/// Parameters for deleting a file or directory.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DeleteFileParams {
+    /// ID of the sandbox.
+    pub sandbox_id: String,
+
+    /// Absolute path to the file or directory.
+    pub path: String,
+
+    /// Also delete a non-empty directory and everything in it
+    /// (default: false).
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+/// Result of deleting a file or directory.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct DeleteFileResult {
+    /// Whether the operation succeeded.
+    pub success: bool,
+}
+
+/// Parameters for moving or renaming a file or directory.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MoveFileParams {
+    /// ID of the sandbox.
+    pub sandbox_id: String,
+
+    /// Absolute path to move.
+    pub source: String,
+
+    /// New absolute path. An existing file there is replaced.
+    pub destination: String,
+}
+
+/// Result of moving a file or directory.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct MoveFileResult {
+    /// Whether the operation succeeded.
+    pub success: bool,
+}
+
+/// Parameters for creating a directory.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MakeDirectoryParams {
+    /// ID of the sandbox.
+    pub sandbox_id: String,
+
+    /// Absolute path of the directory. Missing parents are created too.
+    pub path: String,
+}
+
+/// Result of creating a directory.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct MakeDirectoryResult {
+    /// Whether the operation succeeded.
+    pub success: bool,
+}
+
+/// Parameters for growing a sandbox's scratch drive.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ResizeScratchParams {
+    /// ID of the sandbox.
+    pub sandbox_id: String,
+
+    /// New size of the scratch drive in MiB. Must be larger than the
+    /// current size; drives never shrink.
+    pub size_mib: u64,
+}
+
+/// Result of growing a scratch drive.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ResizeScratchResult {
+    /// Whether the drive and its filesystem were grown.
+    pub success: bool,
+    /// Size of the scratch drive now, in MiB.
+    pub size_mib: u64,
+}
+
 /// Parameters for listing a directory.
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct ListDirectoryParams {
@@ -150,6 +780,41 @@ pub struct ListDirectoryParams {
 
     /// Absolute path to the directory.
     pub path: String,
+
+    /// Sort entries by "name" (default), "size", or "modified".
+    #[serde(default)]
+    pub sort_by: ListSortBy,
+
+    /// Reverse the sort order (default: false).
+    #[serde(default)]
+    pub descending: bool,
+
+    /// Only list entries whose file name matches this glob, e.g. "*.py".
+    #[serde(default)]
+    pub pattern: Option<String>,
+
+    /// Also list subdirectories, naming entries by relative path such as
+    /// "src/main.rs" (default: false). At most 10,000 entries.
+    #[serde(default)]
+    pub recursive: bool,
+
+    /// With `recursive`, how many levels to list; 1 lists only the
+    /// directory's own entries (default: unlimited).
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+}
+
+/// Sort key for listing a directory.
+#[derive(Debug, Default, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ListSortBy {
+    /// File name.
+    #[default]
+    Name,
+    /// File size.
+    Size,
+    /// Last modification time.
+    Modified,
 }
 
 /// Result of listing a directory.
@@ -171,3 +836,128 @@ pub struct FileEntryResponse {
     /// File size in bytes (0 for directories).
     pub size: u64,
 }
+
+/// Parameters for getting a sandbox's operating system.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct OsInfoParams {
+    /// ID of the sandbox.
+    pub sandbox_id: String,
+}
+
+/// Guest distribution identifiers from `/etc/os-release`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct OsInfoResult {
+    /// Distribution identifier (e.g. "debian", "alpine").
+    pub id: String,
+
+    /// Distribution version (e.g. "12"), if declared.
+    pub version_id: Option<String>,
+
+    /// Human-readable name (e.g. "Debian GNU/Linux 12 (bookworm)").
+    pub pretty_name: String,
+}
+
+/// Parameters for checking connectivity from inside a sandbox.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CheckConnectivityParams {
+    /// ID of the sandbox.
+    pub sandbox_id: String,
+
+    /// Hostname or IP address to connect to.
+    pub host: String,
+
+    /// TCP port.
+    pub port: u16,
+
+    /// Connect timeout in milliseconds (default: 3000, max: 30000).
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Result of a connectivity check.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CheckConnectivityResult {
+    /// Whether a TCP connection was established.
+    pub reachable: bool,
+
+    /// Time taken to connect, if reachable.
+    pub latency_ms: Option<u64>,
+
+    /// Why the host is unreachable ("blocked by policy" without network access).
+    pub error: Option<String>,
+}
+
+// ============================================================================
+// Metrics
+// ============================================================================
+
+/// Result of getting tool metrics.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ToolMetricsResult {
+    /// Counters for each tool, sorted by name.
+    pub tools: Vec<ToolMetricsInfo>,
+}
+
+/// Call counters for a single tool.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ToolMetricsInfo {
+    /// Tool name.
+    pub tool: String,
+
+    /// Number of invocations.
+    pub calls: u64,
+
+    /// Number of invocations that returned an error.
+    pub errors: u64,
+
+    /// Fraction of invocations that failed (0.0-1.0).
+    pub error_rate: f64,
+
+    /// Mean time per invocation in milliseconds.
+    pub avg_latency_ms: f64,
+}
+
+/// Result of getting warm pool statistics.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PoolStatsResult {
+    /// Whether the warm pool is enabled.
+    pub enabled: bool,
+
+    /// Sandboxes currently waiting in the pool.
+    pub available: usize,
+
+    /// Sandboxes handed out from the pool.
+    pub warm_hits: u64,
+
+    /// Acquires that found the pool empty.
+    pub cold_misses: u64,
+
+    /// Fraction of acquires served from the pool (0.0-1.0).
+    pub hit_rate: f64,
+
+    /// Sandboxes booted by the pool.
+    pub created: u64,
+
+    /// Sandboxes destroyed by the pool.
+    pub destroyed: u64,
+
+    /// Sandboxes the pool is booting right now. Zero while the pool is
+    /// empty means it isn't refilling.
+    pub in_flight_boots: usize,
+
+    /// `create_sandbox` latency split by source (`pool` or `cold-start`).
+    pub create_latency: Vec<CreateLatencyInfo>,
+}
+
+/// Sandbox creation latency for one source.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CreateLatencyInfo {
+    /// Where the sandboxes came from (`pool` or `cold-start`).
+    pub source: String,
+
+    /// Number of sandboxes created.
+    pub count: u64,
+
+    /// Mean creation time in milliseconds.
+    pub avg_latency_ms: f64,
+}
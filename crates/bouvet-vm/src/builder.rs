@@ -1,9 +1,13 @@
 //! Builder pattern for ergonomic VirtualMachine configuration.
 
-use crate::config::{DriveConfig, MachineConfig, NetworkConfig, VsockConfig};
+use crate::config::{
+    BalloonConfig, CpuTemplate, DriveConfig, FirecrackerLogLevel, MachineConfig, NetworkConfig,
+    PanicBehavior, VsockConfig,
+};
 use crate::error::Result;
 use crate::VirtualMachine;
 use std::path::PathBuf;
+use std::time::Duration;
 
 /// Fluent builder for configuring and creating VirtualMachine instances.
 ///
@@ -67,6 +71,34 @@ impl VmBuilder {
         self
     }
 
+    /// Append one argument to the kernel boot arguments.
+    ///
+    /// Unknown `key=value` arguments are passed to init (and with
+    /// `fast_boot`, the agent) as environment variables.
+    pub fn boot_arg(mut self, arg: impl AsRef<str>) -> Self {
+        self.config.boot_args.push(' ');
+        self.config.boot_args.push_str(arg.as_ref());
+        self
+    }
+
+    /// Boot the guest agent directly as PID 1 instead of the init system.
+    pub fn fast_boot(mut self, enabled: bool) -> Self {
+        self.config.fast_boot = enabled;
+        self
+    }
+
+    /// Set what the guest kernel does on panic.
+    pub fn panic_behavior(mut self, behavior: PanicBehavior) -> Self {
+        self.config.panic_behavior = behavior;
+        self
+    }
+
+    /// Set the level of Firecracker's own log.
+    pub fn firecracker_log_level(mut self, level: FirecrackerLogLevel) -> Self {
+        self.config.firecracker_log_level = level;
+        self
+    }
+
     /// Set the path to the root filesystem image.
     pub fn rootfs(mut self, path: impl Into<PathBuf>) -> Self {
         self.config.root_drive.path_on_host = path.into();
@@ -79,6 +111,19 @@ impl VmBuilder {
         self
     }
 
+    /// Keep the root drive read-only and give the guest a writable layer:
+    /// a fresh ext4 drive of `size_mib` MiB, created in the VM directory
+    /// at boot and deleted by `destroy()`.
+    ///
+    /// Lets many VMs share one immutable rootfs image. The rootfs's init
+    /// has to mount the drive named by `overlay_root=` over `/`; see
+    /// [`MachineConfig::overlay_device`].
+    pub fn with_overlay(self, size_mib: u32) -> Self {
+        let mut builder = self.rootfs_read_only();
+        builder.config.overlay_size_mib = Some(size_mib);
+        builder
+    }
+
     /// Add an extra drive.
     pub fn with_drive(mut self, drive_id: &str, path: impl Into<PathBuf>) -> Self {
         self.config.extra_drives.push(DriveConfig {
@@ -86,25 +131,36 @@ impl VmBuilder {
             path_on_host: path.into(),
             is_root_device: false,
             is_read_only: false,
+            rate_limiter: None,
         });
         self
     }
 
-    /// Configure network interface with the given tap device.
-    pub fn with_network(mut self, host_dev: &str) -> Self {
-        self.config.network = Some(NetworkConfig {
+    /// Add a network interface on the given tap device, named `eth<n>`
+    /// after the interfaces already added.
+    pub fn with_network(self, host_dev: &str) -> Self {
+        let iface_id = format!("eth{}", self.config.networks.len());
+        self.add_network(NetworkConfig {
+            iface_id,
             host_dev_name: host_dev.to_string(),
-            ..Default::default()
-        });
-        self
+            guest_mac: None,
+            static_ip: None,
+            rate_limiter: None,
+            allow_mmds_requests: false,
+        })
     }
 
-    /// Configure network interface with full options.
-    pub fn with_network_config(mut self, config: NetworkConfig) -> Self {
-        self.config.network = Some(config);
+    /// Add a network interface with full options.
+    pub fn add_network(mut self, config: NetworkConfig) -> Self {
+        self.config.networks.push(config);
         self
     }
 
+    /// Add a network interface with full options.
+    pub fn with_network_config(self, config: NetworkConfig) -> Self {
+        self.add_network(config)
+    }
+
     /// Configure vsock with the given guest CID.
     pub fn with_vsock(mut self, cid: u32) -> Self {
         self.config.vsock = Some(VsockConfig {
@@ -120,6 +176,75 @@ impl VmBuilder {
         self
     }
 
+    /// Attach a virtio-balloon device holding `amount_mib` MiB at boot,
+    /// resizable with [`VirtualMachine::set_balloon`]. With
+    /// `deflate_on_oom`, the guest deflates it rather than run out of
+    /// memory.
+    pub fn with_balloon(mut self, amount_mib: u32, deflate_on_oom: bool) -> Self {
+        self.config.balloon = Some(BalloonConfig {
+            amount_mib,
+            deflate_on_oom,
+        });
+        self
+    }
+
+    /// Serve `data` to the guest through Firecracker's MMDS. Needs an
+    /// interface added with [`NetworkConfig::allow_mmds_requests`] set.
+    pub fn with_mmds(mut self, data: serde_json::Value) -> Self {
+        self.config.mmds = Some(data);
+        self
+    }
+
+    /// Capture the guest serial console to `console.log` in the VM
+    /// directory, for [`VirtualMachine::console_log`].
+    pub fn capture_console(mut self) -> Self {
+        self.config.capture_console = true;
+        self
+    }
+
+    /// Attach a virtio-rng entropy device, so guest reads of
+    /// `/dev/random` don't block. Needs a kernel with virtio-rng support.
+    pub fn with_entropy(mut self) -> Self {
+        self.config.entropy = true;
+        self
+    }
+
+    /// Have Firecracker write metrics to `path`, for
+    /// [`VirtualMachine::metrics`].
+    pub fn with_metrics(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.metrics_path = Some(path.into());
+        self
+    }
+
+    /// Have `destroy()` send Ctrl+Alt+Del and wait up to `timeout` for the
+    /// guest to shut down cleanly before killing it.
+    pub fn graceful_shutdown(mut self, timeout: Duration) -> Self {
+        self.config.shutdown_timeout_ms = Some(timeout.as_millis() as u64);
+        self
+    }
+
+    /// Fail `create` with [`VmError::BootTimeout`](crate::VmError::BootTimeout)
+    /// if the guest hasn't started within `timeout` (default: 15s).
+    pub fn boot_timeout(mut self, timeout: Duration) -> Self {
+        self.config.boot_timeout = timeout;
+        self
+    }
+
+    /// Fail `create` with [`VmError::Timeout`](crate::VmError::Timeout),
+    /// killing Firecracker, if creating the VM takes longer than
+    /// `timeout` in all (default: 30s).
+    pub fn create_timeout(mut self, timeout: Duration) -> Self {
+        self.config.create_timeout = timeout;
+        self
+    }
+
+    /// Present the guest a fixed CPU through a Firecracker CPU template.
+    /// `build()` fails if the template is for another architecture.
+    pub fn cpu_template(mut self, template: CpuTemplate) -> Self {
+        self.config.cpu_template = Some(template);
+        self
+    }
+
     /// Set the path to the Firecracker binary.
     pub fn firecracker_path(mut self, path: impl Into<PathBuf>) -> Self {
         self.config.firecracker_path = path.into();
@@ -157,7 +282,7 @@ mod tests {
         let config = MachineConfig::default();
         assert_eq!(config.vcpu_count, 2);
         assert_eq!(config.memory_mib, 256);
-        assert!(config.network.is_none());
+        assert!(config.networks.is_empty());
         assert!(config.vsock.is_none());
     }
 
@@ -169,6 +294,15 @@ mod tests {
         assert_eq!(config.memory_mib, 512);
     }
 
+    #[test]
+    fn test_builder_boot_arg_appends() {
+        let config = VmBuilder::new()
+            .boot_args("console=ttyS0")
+            .boot_arg("BOUVET_CLEAN_TMP=1")
+            .build_config();
+        assert_eq!(config.boot_args, "console=ttyS0 BOUVET_CLEAN_TMP=1");
+    }
+
     #[test]
     fn test_builder_kernel_rootfs() {
         let config = VmBuilder::new()
@@ -187,9 +321,29 @@ mod tests {
     fn test_builder_with_network() {
         let config = VmBuilder::new().with_network("tap0").build_config();
 
-        assert!(config.network.is_some());
-        let net = config.network.unwrap();
-        assert_eq!(net.host_dev_name, "tap0");
+        assert_eq!(config.networks.len(), 1);
+        assert_eq!(config.networks[0].iface_id, "eth0");
+        assert_eq!(config.networks[0].host_dev_name, "tap0");
+
+        let config = VmBuilder::new()
+            .with_network("tap0")
+            .add_network(NetworkConfig {
+                iface_id: "internal".into(),
+                host_dev_name: "tap1".into(),
+                guest_mac: Some("06:00:00:00:00:01".into()),
+                static_ip: None,
+                rate_limiter: None,
+                allow_mmds_requests: false,
+            })
+            .with_network("tap2")
+            .build_config();
+        let ifaces: Vec<_> = config
+            .networks
+            .iter()
+            .map(|n| n.iface_id.as_str())
+            .collect();
+        assert_eq!(ifaces, ["eth0", "internal", "eth2"]);
+        assert!(config.validate().is_ok());
     }
 
     #[test]
@@ -201,6 +355,100 @@ mod tests {
         assert_eq!(vsock.guest_cid, 5);
     }
 
+    #[test]
+    fn test_builder_with_entropy() {
+        assert!(!VmBuilder::new().build_config().entropy);
+        assert!(VmBuilder::new().with_entropy().build_config().entropy);
+    }
+
+    #[test]
+    fn test_builder_with_balloon() {
+        assert!(VmBuilder::new().build_config().balloon.is_none());
+        let config = VmBuilder::new().with_balloon(64, true).build_config();
+        assert_eq!(
+            config.balloon,
+            Some(BalloonConfig {
+                amount_mib: 64,
+                deflate_on_oom: true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_builder_with_mmds() {
+        let config = VmBuilder::new()
+            .add_network(NetworkConfig {
+                allow_mmds_requests: true,
+                ..Default::default()
+            })
+            .with_mmds(serde_json::json!({"sandbox_id": "abc"}))
+            .build_config();
+        assert_eq!(config.mmds, Some(serde_json::json!({"sandbox_id": "abc"})));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_builder_with_metrics() {
+        assert!(VmBuilder::new().build_config().metrics_path.is_none());
+        let config = VmBuilder::new()
+            .with_metrics("/tmp/vm.metrics")
+            .build_config();
+        assert_eq!(config.metrics_path, Some(PathBuf::from("/tmp/vm.metrics")));
+    }
+
+    #[test]
+    fn test_builder_graceful_shutdown() {
+        assert!(VmBuilder::new()
+            .build_config()
+            .shutdown_timeout_ms
+            .is_none());
+        let config = VmBuilder::new()
+            .graceful_shutdown(Duration::from_secs(3))
+            .build_config();
+        assert_eq!(config.shutdown_timeout_ms, Some(3000));
+    }
+
+    #[test]
+    fn test_builder_capture_console() {
+        assert!(!VmBuilder::new().build_config().capture_console);
+        assert!(
+            VmBuilder::new()
+                .capture_console()
+                .build_config()
+                .capture_console
+        );
+    }
+
+    #[test]
+    fn test_builder_boot_timeout() {
+        assert_eq!(
+            VmBuilder::new().build_config().boot_timeout,
+            crate::DEFAULT_BOOT_TIMEOUT
+        );
+        let config = VmBuilder::new()
+            .boot_timeout(Duration::from_secs(5))
+            .build_config();
+        assert_eq!(config.boot_timeout, Duration::from_secs(5));
+
+        let config = VmBuilder::new()
+            .create_timeout(Duration::from_secs(60))
+            .build_config();
+        assert_eq!(config.create_timeout, Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_builder_with_overlay() {
+        let config = VmBuilder::new()
+            .with_drive("data", "/path/to/data.ext4")
+            .with_overlay(512)
+            .build_config();
+
+        assert!(config.root_drive.is_read_only);
+        assert_eq!(config.overlay_size_mib, Some(512));
+        assert_eq!(config.overlay_device().as_deref(), Some("vdc"));
+        assert!(config.validate().is_ok());
+    }
+
     #[test]
     fn test_builder_with_extra_drive() {
         let config = VmBuilder::new()
@@ -2,7 +2,40 @@
 
 use crate::error::{Result, VmError};
 use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Path of the guest agent in the rootfs, run as init when fast booting.
+pub const AGENT_INIT_PATH: &str = "/usr/local/bin/bouvet-agent";
+
+/// Default kernel command line for x86_64 guests. There is no PCI bus, so
+/// `pci=off` skips probing for one.
+#[cfg(not(target_arch = "aarch64"))]
+pub const DEFAULT_BOOT_ARGS: &str = "console=ttyS0 reboot=k panic=1 pci=off";
+
+/// Default kernel command line for aarch64 guests. The serial console is
+/// only registered late in boot, so `keep_bootcon` keeps the early console
+/// output; there is no `pci=off`, as the arm64 kernel has no such option.
+#[cfg(target_arch = "aarch64")]
+pub const DEFAULT_BOOT_ARGS: &str = "keep_bootcon console=ttyS0 reboot=k panic=1";
+
+/// Drive ID of the overlay drive attached with [`MachineConfig::overlay_size_mib`].
+pub const OVERLAY_DRIVE_ID: &str = "overlay";
+
+/// Default for [`MachineConfig::boot_timeout`].
+pub const DEFAULT_BOOT_TIMEOUT: Duration = Duration::from_secs(15);
+
+fn default_boot_timeout() -> Duration {
+    DEFAULT_BOOT_TIMEOUT
+}
+
+/// Default for [`MachineConfig::create_timeout`].
+pub const DEFAULT_CREATE_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn default_create_timeout() -> Duration {
+    DEFAULT_CREATE_TIMEOUT
+}
 
 /// Configuration for creating a new MicroVM.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,14 +52,194 @@ pub struct MachineConfig {
     pub root_drive: DriveConfig,
     /// Additional drives (optional)
     pub extra_drives: Vec<DriveConfig>,
-    /// Network configuration (optional)
-    pub network: Option<NetworkConfig>,
+    /// Network interfaces, attached in order (optional). Also read from a
+    /// single `network` object, as configs were written before interfaces
+    /// could be repeated.
+    #[serde(default, alias = "network", deserialize_with = "deserialize_networks")]
+    pub networks: Vec<NetworkConfig>,
     /// vsock configuration for guest-host communication (optional)
     pub vsock: Option<VsockConfig>,
     /// Path to Firecracker binary
     pub firecracker_path: PathBuf,
     /// Working directory for VM sockets and state
     pub chroot_path: PathBuf,
+    /// Boot the guest agent directly as PID 1, skipping the init system
+    /// (default: false). See [`MachineConfig::kernel_boot_args`].
+    #[serde(default)]
+    pub fast_boot: bool,
+    /// What the guest kernel does on panic (default: reboot).
+    #[serde(default)]
+    pub panic_behavior: PanicBehavior,
+    /// Level of Firecracker's own log, written to `firecracker.log` in the
+    /// VM directory (default: warn).
+    #[serde(default)]
+    pub firecracker_log_level: FirecrackerLogLevel,
+    /// Attach a virtio-rng entropy device (default: false). The guest
+    /// kernel must be built with `CONFIG_HW_RANDOM_VIRTIO`.
+    #[serde(default)]
+    pub entropy: bool,
+    /// File Firecracker appends metrics records to (optional). Required
+    /// by [`VirtualMachine::metrics`](crate::VirtualMachine::metrics).
+    #[serde(default)]
+    pub metrics_path: Option<PathBuf>,
+    /// How long `destroy()` waits for the guest to shut down after
+    /// Ctrl+Alt+Del before killing it, in milliseconds (optional; without
+    /// it the VM is killed straight away).
+    #[serde(default)]
+    pub shutdown_timeout_ms: Option<u64>,
+    /// How long `create` waits for Firecracker to start the guest before
+    /// killing it and failing with [`VmError::BootTimeout`] (default: 15s).
+    #[serde(default = "default_boot_timeout")]
+    pub boot_timeout: Duration,
+    /// How long `create` may take in all, from spawning Firecracker
+    /// through every API call to the start of the guest, before the VM is
+    /// killed and `create` fails with [`VmError::Timeout`] (default: 30s).
+    #[serde(default = "default_create_timeout")]
+    pub create_timeout: Duration,
+    /// Firecracker CPU template, masking host CPU features so the guest
+    /// sees the same CPU on different hosts (optional). Templates only
+    /// exist for one architecture each.
+    #[serde(default)]
+    pub cpu_template: Option<CpuTemplate>,
+    /// Write the guest serial console to `console.log` in the VM directory
+    /// (default: false). Needs a Firecracker with the `/serial` endpoint.
+    /// See [`VirtualMachine::console_log`](crate::VirtualMachine::console_log).
+    #[serde(default)]
+    pub capture_console: bool,
+    /// Size of a scratch ext4 drive to create as the writable layer over
+    /// a read-only root drive, in MiB (optional). See
+    /// [`MachineConfig::overlay_device`].
+    #[serde(default)]
+    pub overlay_size_mib: Option<u32>,
+    /// virtio-balloon device, so guest memory can be reclaimed at runtime
+    /// with [`VirtualMachine::set_balloon`](crate::VirtualMachine::set_balloon)
+    /// (optional).
+    #[serde(default)]
+    pub balloon: Option<BalloonConfig>,
+    /// Metadata served to the guest by Firecracker's MMDS at
+    /// `http://169.254.169.254/` (optional). Must be a JSON object, and an
+    /// interface must set [`NetworkConfig::allow_mmds_requests`].
+    #[serde(default)]
+    pub mmds: Option<serde_json::Value>,
+}
+
+/// virtio-balloon device configuration.
+///
+/// The guest kernel needs `CONFIG_VIRTIO_BALLOON`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalloonConfig {
+    /// Memory the balloon holds at boot, in MiB; the guest can't use it
+    pub amount_mib: u32,
+    /// Let the guest deflate the balloon when it would otherwise run out
+    /// of memory
+    pub deflate_on_oom: bool,
+}
+
+/// Firecracker static CPU template, set through `PUT /machine-config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CpuTemplate {
+    /// Intel, feature set of an AWS C3 instance (x86_64).
+    C3,
+    /// Intel, feature set of an AWS T2 instance (x86_64).
+    T2,
+    /// T2, also snapshot-compatible between Skylake and Cascade Lake hosts (x86_64).
+    T2S,
+    /// T2 for Intel Cascade Lake and newer hosts (x86_64).
+    T2CL,
+    /// T2 for AMD Milan hosts (x86_64).
+    T2A,
+    /// Neoverse V1 presented as N1, for Graviton3 hosts (aarch64).
+    V1N1,
+}
+
+impl CpuTemplate {
+    /// The template name Firecracker's API expects.
+    pub fn api_value(&self) -> &'static str {
+        match self {
+            CpuTemplate::C3 => "C3",
+            CpuTemplate::T2 => "T2",
+            CpuTemplate::T2S => "T2S",
+            CpuTemplate::T2CL => "T2CL",
+            CpuTemplate::T2A => "T2A",
+            CpuTemplate::V1N1 => "V1N1",
+        }
+    }
+
+    /// The host architecture the template is for, as named by
+    /// [`std::env::consts::ARCH`].
+    pub fn arch(&self) -> &'static str {
+        match self {
+            CpuTemplate::V1N1 => "aarch64",
+            _ => "x86_64",
+        }
+    }
+}
+
+/// Guest kernel behavior on panic, set through the `panic=` boot argument.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PanicBehavior {
+    /// Reboot immediately (`panic=1`). With `reboot=k` the VM exits.
+    #[default]
+    Reboot,
+    /// Halt and wait (`panic=0`), leaving the panic on the console for
+    /// debugging a crashing guest.
+    Halt,
+}
+
+impl PanicBehavior {
+    /// The `panic=` kernel boot argument for this behavior.
+    pub fn boot_arg(&self) -> &'static str {
+        match self {
+            PanicBehavior::Reboot => "panic=1",
+            PanicBehavior::Halt => "panic=0",
+        }
+    }
+}
+
+/// Level of the Firecracker process log, set through `PUT /logger`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FirecrackerLogLevel {
+    /// Errors only.
+    Error,
+    /// Errors and warnings, including rejected API requests.
+    #[default]
+    Warn,
+    /// Informational messages.
+    Info,
+    /// Everything, including each API request.
+    Debug,
+}
+
+impl FirecrackerLogLevel {
+    /// The level name Firecracker's logger API expects.
+    pub fn api_value(&self) -> &'static str {
+        match self {
+            FirecrackerLogLevel::Error => "Error",
+            FirecrackerLogLevel::Warn => "Warning",
+            FirecrackerLogLevel::Info => "Info",
+            FirecrackerLogLevel::Debug => "Debug",
+        }
+    }
+}
+
+impl std::str::FromStr for FirecrackerLogLevel {
+    type Err = VmError;
+
+    /// Parse a level name, case-insensitively (`warning` is accepted for
+    /// `warn`).
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" => Ok(Self::Error),
+            "warn" | "warning" => Ok(Self::Warn),
+            "info" => Ok(Self::Info),
+            "debug" => Ok(Self::Debug),
+            _ => Err(VmError::Config(format!(
+                "unknown firecracker log level '{s}' (expected error, warn, info or debug)"
+            ))),
+        }
+    }
 }
 
 impl Default for MachineConfig {
@@ -35,18 +248,119 @@ impl Default for MachineConfig {
             vcpu_count: 2,
             memory_mib: 256,
             kernel_path: PathBuf::from("/var/lib/bouvet/kernel/vmlinux"),
-            boot_args: "console=ttyS0 reboot=k panic=1 pci=off".into(),
+            boot_args: DEFAULT_BOOT_ARGS.into(),
             root_drive: DriveConfig::default(),
             extra_drives: Vec::new(),
-            network: None,
+            networks: Vec::new(),
             vsock: None,
             firecracker_path: PathBuf::from("/usr/local/bin/firecracker"),
             chroot_path: PathBuf::from("/tmp/bouvet"),
+            fast_boot: false,
+            panic_behavior: PanicBehavior::Reboot,
+            firecracker_log_level: FirecrackerLogLevel::Warn,
+            entropy: false,
+            metrics_path: None,
+            shutdown_timeout_ms: None,
+            boot_timeout: DEFAULT_BOOT_TIMEOUT,
+            create_timeout: DEFAULT_CREATE_TIMEOUT,
+            cpu_template: None,
+            capture_console: false,
+            overlay_size_mib: None,
+            balloon: None,
+            mmds: None,
         }
     }
 }
 
 impl MachineConfig {
+    /// Compose the kernel command line passed to Firecracker.
+    ///
+    /// Any `panic=` argument in `boot_args` is replaced by the one for
+    /// `panic_behavior` (appended if absent).
+    ///
+    /// If a network interface has a [`StaticIp`], its `ip=` argument
+    /// replaces any in `boot_args`.
+    ///
+    /// With `capture_console`, appends `console=ttyS0` unless `boot_args`
+    /// already name a console, so kernel output reaches the serial port.
+    ///
+    /// With an overlay, appends `overlay_root=` naming its device.
+    ///
+    /// With `fast_boot`, appends `init=` pointing at the guest agent, plus
+    /// `BOUVET_PID1=1`, which the kernel passes to init as an environment
+    /// variable so the agent mounts `/proc`, `/sys`, `/dev` and `/tmp`
+    /// itself. This saves the init system's startup time, at the cost of
+    /// services it would have started (networking, logging, zombie
+    /// reaping) not running.
+    pub fn kernel_boot_args(&self) -> String {
+        let panic_arg = self.panic_behavior.boot_arg();
+        let ip_arg = self
+            .networks
+            .iter()
+            .find_map(|net| net.static_ip.as_ref())
+            .map(StaticIp::boot_arg);
+        let mut args: Vec<&str> = Vec::new();
+        let mut has_panic = false;
+        for arg in self.boot_args.split_whitespace() {
+            if ip_arg.is_some() && arg.starts_with("ip=") {
+                continue;
+            }
+            if arg.starts_with("panic=") {
+                if !has_panic {
+                    args.push(panic_arg);
+                    has_panic = true;
+                }
+            } else {
+                args.push(arg);
+            }
+        }
+        if !has_panic {
+            args.push(panic_arg);
+        }
+        if let Some(ip_arg) = &ip_arg {
+            args.push(ip_arg);
+        }
+        if self.capture_console && !args.iter().any(|arg| arg.starts_with("console=")) {
+            args.push("console=ttyS0");
+        }
+        let overlay_arg = self
+            .overlay_device()
+            .map(|device| format!("overlay_root={device}"));
+        if let Some(overlay_arg) = &overlay_arg {
+            args.push(overlay_arg);
+        }
+
+        let args = args.join(" ");
+        if self.fast_boot {
+            format!("{} init={} BOUVET_PID1=1", args, AGENT_INIT_PATH)
+        } else {
+            args
+        }
+    }
+
+    /// IDs of the interfaces the guest can reach MMDS through.
+    pub fn mmds_interfaces(&self) -> Vec<&str> {
+        self.networks
+            .iter()
+            .filter(|net| net.allow_mmds_requests)
+            .map(|net| net.iface_id.as_str())
+            .collect()
+    }
+
+    /// Guest block device of the overlay drive, or `None` without an
+    /// overlay or if there are too many drives to name one.
+    ///
+    /// Firecracker names drives `vda` to `vdz` with the root drive first
+    /// and the rest in the order they were added; the overlay is added
+    /// last. The rootfs's init is expected to mount it over `/`,
+    /// following Firecracker's `overlay_root=` convention.
+    pub fn overlay_device(&self) -> Option<String> {
+        self.overlay_size_mib?;
+        // vda is the root drive
+        let letter = (b'a'..=b'z').nth(1 + self.extra_drives.len())?;
+        Some(format!("vd{}", char::from(letter)))
+    }
+
     /// Validate the configuration.
     ///
     /// # Errors
@@ -74,6 +388,35 @@ impl MachineConfig {
             )));
         }
 
+        if let Some(balloon) = &self.balloon {
+            if balloon.amount_mib > self.memory_mib {
+                return Err(VmError::Config(format!(
+                    "balloon amount_mib ({}) exceeds memory_mib ({})",
+                    balloon.amount_mib, self.memory_mib
+                )));
+            }
+        }
+
+        if self.boot_timeout.is_zero() {
+            return Err(VmError::Config("boot_timeout must be > 0".into()));
+        }
+        if self.create_timeout.is_zero() {
+            return Err(VmError::Config("create_timeout must be > 0".into()));
+        }
+
+        // Firecracker rejects templates for another architecture, but only
+        // once the VM is half configured
+        if let Some(template) = self.cpu_template {
+            if template.arch() != std::env::consts::ARCH {
+                return Err(VmError::Config(format!(
+                    "CPU template {} is only supported on {} hosts, not {}",
+                    template.api_value(),
+                    template.arch(),
+                    std::env::consts::ARCH
+                )));
+            }
+        }
+
         // Validate vsock CID (must be > 2, as 0, 1, 2 are reserved)
         if let Some(vsock) = &self.vsock {
             if vsock.guest_cid <= 2 {
@@ -96,10 +439,95 @@ impl MachineConfig {
             drive_ids.push(extra.drive_id.clone());
         }
 
+        if let Some(size_mib) = self.overlay_size_mib {
+            if size_mib == 0 {
+                return Err(VmError::Config("overlay_size_mib must be > 0".into()));
+            }
+            if !self.root_drive.is_read_only {
+                return Err(VmError::Config(
+                    "an overlay needs a read-only root drive".into(),
+                ));
+            }
+            if drive_ids.iter().any(|id| id == OVERLAY_DRIVE_ID) {
+                return Err(VmError::Config(format!(
+                    "drive_id {OVERLAY_DRIVE_ID} is reserved for the overlay"
+                )));
+            }
+            if self.overlay_device().is_none() {
+                return Err(VmError::Config("too many drives to add an overlay".into()));
+            }
+        }
+
+        // Validate rate limiters refill
+        for drive in std::iter::once(&self.root_drive).chain(&self.extra_drives) {
+            if let Some(limiter) = &drive.rate_limiter {
+                limiter.validate(&format!("drive {}", drive.drive_id))?;
+            }
+        }
+        for net in &self.networks {
+            if let Some(limiter) = &net.rate_limiter {
+                limiter.validate(&format!("interface {}", net.iface_id))?;
+            }
+        }
+
+        // Validate interface IDs are unique
+        let mut iface_ids: Vec<&str> = Vec::new();
+        for net in &self.networks {
+            if iface_ids.contains(&net.iface_id.as_str()) {
+                return Err(VmError::Config(format!(
+                    "duplicate iface_id: {}",
+                    net.iface_id
+                )));
+            }
+            iface_ids.push(&net.iface_id);
+        }
+
+        if let Some(mmds) = &self.mmds {
+            if !mmds.is_object() {
+                return Err(VmError::Config("mmds data must be a JSON object".into()));
+            }
+            if self.mmds_interfaces().is_empty() {
+                return Err(VmError::Config(
+                    "mmds data needs a network interface with allow_mmds_requests".into(),
+                ));
+            }
+        }
+
+        // The kernel's ip= configures a single interface
+        let mut static_ips = self.networks.iter().filter_map(|n| n.static_ip.as_ref());
+        if let Some(static_ip) = static_ips.next() {
+            if static_ips.next().is_some() {
+                return Err(VmError::Config(
+                    "only one network interface can have a static_ip".into(),
+                ));
+            }
+            static_ip.validate()?;
+        }
+
         Ok(())
     }
 }
 
+/// Accept `networks` as a list, or the older single `network` object or null.
+fn deserialize_networks<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Vec<NetworkConfig>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Networks {
+        Many(Vec<NetworkConfig>),
+        One(Option<NetworkConfig>),
+    }
+
+    Ok(match Networks::deserialize(deserializer)? {
+        Networks::Many(networks) => networks,
+        Networks::One(network) => network.into_iter().collect(),
+    })
+}
+
 /// Configuration for a block device (drive).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DriveConfig {
@@ -111,6 +539,9 @@ pub struct DriveConfig {
     pub is_root_device: bool,
     /// Read-only flag
     pub is_read_only: bool,
+    /// Throttle for the drive's I/O (optional)
+    #[serde(default)]
+    pub rate_limiter: Option<RateLimiter>,
 }
 
 impl Default for DriveConfig {
@@ -120,6 +551,7 @@ impl Default for DriveConfig {
             path_on_host: PathBuf::from("/var/lib/bouvet/images/debian.ext4"),
             is_root_device: true,
             is_read_only: false,
+            rate_limiter: None,
         }
     }
 }
@@ -133,6 +565,19 @@ pub struct NetworkConfig {
     pub host_dev_name: String,
     /// Guest MAC address (optional, auto-generated if None)
     pub guest_mac: Option<String>,
+    /// Address the guest kernel assigns at boot, instead of relying on
+    /// DHCP (optional)
+    #[serde(default)]
+    pub static_ip: Option<StaticIp>,
+    /// Throttle applied to each direction of the interface's traffic
+    /// (optional)
+    #[serde(default)]
+    pub rate_limiter: Option<RateLimiter>,
+    /// Route the guest's requests to 169.254.169.254 on this interface to
+    /// Firecracker's metadata service (default: false). See
+    /// [`MachineConfig::mmds`].
+    #[serde(default)]
+    pub allow_mmds_requests: bool,
 }
 
 impl Default for NetworkConfig {
@@ -141,7 +586,122 @@ impl Default for NetworkConfig {
             iface_id: "eth0".into(),
             host_dev_name: "tap0".into(),
             guest_mac: None,
+            static_ip: None,
+            rate_limiter: None,
+            allow_mmds_requests: false,
+        }
+    }
+}
+
+/// Firecracker rate limiter for a drive or network interface. Either
+/// bucket may be left unset to leave that dimension unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimiter {
+    /// Bytes per refill period
+    #[serde(default)]
+    pub bandwidth: Option<TokenBucket>,
+    /// Operations (requests or packets) per refill period
+    #[serde(default)]
+    pub ops: Option<TokenBucket>,
+}
+
+impl RateLimiter {
+    /// Check that every configured bucket refills.
+    ///
+    /// # Errors
+    /// Returns an error naming `owner` if a bucket's `refill_time_ms` is 0.
+    pub fn validate(&self, owner: &str) -> Result<()> {
+        for (name, bucket) in [("bandwidth", &self.bandwidth), ("ops", &self.ops)] {
+            if bucket.as_ref().is_some_and(|b| b.refill_time_ms == 0) {
+                return Err(VmError::Config(format!(
+                    "{owner}: {name} refill_time_ms must be > 0"
+                )));
+            }
         }
+        Ok(())
+    }
+}
+
+/// Token bucket: `size` tokens, refilled completely every
+/// `refill_time_ms`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenBucket {
+    /// Bucket capacity, in bytes or operations
+    pub size: u64,
+    /// Time to refill an empty bucket, in milliseconds (must be > 0)
+    pub refill_time_ms: u64,
+    /// Extra tokens available once, at startup (optional)
+    #[serde(default)]
+    pub one_time_burst: Option<u64>,
+}
+
+/// Static IPv4 configuration, applied by the guest kernel through the
+/// `ip=` boot argument. Needs a kernel built with `CONFIG_IP_PNP`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StaticIp {
+    /// Guest address
+    pub addr: Ipv4Addr,
+    /// Default gateway, on the same subnet as `addr`
+    pub gateway: Ipv4Addr,
+    /// Subnet mask, e.g. 255.255.255.0
+    pub netmask: Ipv4Addr,
+    /// Guest interface name, e.g. eth0
+    pub iface: String,
+}
+
+impl StaticIp {
+    /// Render as `ip=<addr>::<gateway>:<netmask>::<iface>:off`, with no
+    /// NFS server or hostname and autoconfiguration off.
+    pub fn boot_arg(&self) -> String {
+        format!(
+            "ip={}::{}:{}::{}:off",
+            self.addr, self.gateway, self.netmask, self.iface
+        )
+    }
+
+    /// Check that the addresses form a usable subnet.
+    ///
+    /// # Errors
+    /// Returns an error if the netmask isn't contiguous, `addr` isn't a
+    /// host address, `gateway` is outside the subnet, or `iface` would
+    /// break the `ip=` syntax.
+    pub fn validate(&self) -> Result<()> {
+        let mask = u32::from(self.netmask);
+        if mask == 0 || mask.leading_ones() + mask.trailing_zeros() != 32 {
+            return Err(VmError::Config(format!(
+                "static_ip netmask {} is not a valid subnet mask",
+                self.netmask
+            )));
+        }
+
+        let addr = u32::from(self.addr);
+        let host_bits = addr & !mask;
+        if self.addr.is_unspecified()
+            || self.addr.is_multicast()
+            || self.addr.is_broadcast()
+            || (mask != u32::MAX && (host_bits == 0 || host_bits == !mask))
+        {
+            return Err(VmError::Config(format!(
+                "static_ip addr {} is not a host address in its subnet",
+                self.addr
+            )));
+        }
+
+        if u32::from(self.gateway) & mask != addr & mask || self.gateway == self.addr {
+            return Err(VmError::Config(format!(
+                "static_ip gateway {} must be another address in {}'s subnet",
+                self.gateway, self.addr
+            )));
+        }
+
+        if self.iface.is_empty() || self.iface.contains(|c: char| c == ':' || c.is_whitespace()) {
+            return Err(VmError::Config(format!(
+                "static_ip iface {:?} is not a valid interface name",
+                self.iface
+            )));
+        }
+
+        Ok(())
     }
 }
 
@@ -262,14 +822,410 @@ mod tests {
             path_on_host: PathBuf::from("/tmp/extra.ext4"),
             is_root_device: false,
             is_read_only: true,
+            rate_limiter: None,
+        });
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_rate_limiter_refill() {
+        let bucket = TokenBucket {
+            size: 1 << 20,
+            refill_time_ms: 100,
+            one_time_burst: None,
+        };
+        let mut config = MachineConfig::default();
+        config.root_drive.rate_limiter = Some(RateLimiter {
+            bandwidth: Some(bucket.clone()),
+            ops: None,
+        });
+        config.networks.push(NetworkConfig {
+            rate_limiter: Some(RateLimiter {
+                bandwidth: None,
+                ops: Some(bucket.clone()),
+            }),
+            ..Default::default()
+        });
+        assert!(config.validate().is_ok());
+
+        config.networks[0].rate_limiter = Some(RateLimiter {
+            bandwidth: None,
+            ops: Some(TokenBucket {
+                refill_time_ms: 0,
+                ..bucket
+            }),
         });
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("interface eth0: ops"), "{err}");
+    }
+
+    #[test]
+    fn test_validate_duplicate_iface_ids() {
+        let mut config = MachineConfig::default();
+        config.networks.push(NetworkConfig::default());
+        config.networks.push(NetworkConfig {
+            iface_id: "eth1".into(),
+            host_dev_name: "br-internal".into(),
+            guest_mac: None,
+            static_ip: None,
+            rate_limiter: None,
+            allow_mmds_requests: false,
+        });
+        assert!(config.validate().is_ok());
+
+        config.networks.push(NetworkConfig {
+            host_dev_name: "tap1".into(),
+            ..Default::default()
+        });
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_deserialize_legacy_network() {
+        let mut value = serde_json::to_value(MachineConfig::default()).unwrap();
+        let object = value.as_object_mut().unwrap();
+        object.remove("networks");
+
+        object.insert("network".into(), serde_json::Value::Null);
+        let config: MachineConfig = serde_json::from_value(value.clone()).unwrap();
+        assert!(config.networks.is_empty());
+
+        let object = value.as_object_mut().unwrap();
+        object.insert(
+            "network".into(),
+            serde_json::json!({"iface_id": "eth0", "host_dev_name": "tap7", "guest_mac": null}),
+        );
+        let config: MachineConfig = serde_json::from_value(value).unwrap();
+        assert_eq!(config.networks.len(), 1);
+        assert_eq!(config.networks[0].host_dev_name, "tap7");
+    }
+
+    #[test]
+    fn test_validate_mmds() {
+        let mut config = MachineConfig {
+            mmds: Some(serde_json::json!({"sandbox_id": "abc"})),
+            networks: vec![NetworkConfig::default()],
+            ..Default::default()
+        };
+        // No interface lets the guest reach it
+        assert!(config.validate().is_err());
+
+        config.networks[0].allow_mmds_requests = true;
+        assert_eq!(config.mmds_interfaces(), ["eth0"]);
+        assert!(config.validate().is_ok());
+
+        config.mmds = Some(serde_json::json!(["not", "an", "object"]));
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_validate_balloon() {
+        let mut config = MachineConfig {
+            memory_mib: 512,
+            balloon: Some(BalloonConfig {
+                amount_mib: 512,
+                deflate_on_oom: true,
+            }),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        config.balloon = Some(BalloonConfig {
+            amount_mib: 513,
+            deflate_on_oom: true,
+        });
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_boot_timeout() {
+        // Configs written before the fields existed get the defaults
+        let mut value = serde_json::to_value(MachineConfig::default()).unwrap();
+        value.as_object_mut().unwrap().remove("boot_timeout");
+        value.as_object_mut().unwrap().remove("create_timeout");
+        let config: MachineConfig = serde_json::from_value(value).unwrap();
+        assert_eq!(config.boot_timeout, DEFAULT_BOOT_TIMEOUT);
+        assert_eq!(config.create_timeout, DEFAULT_CREATE_TIMEOUT);
+        assert!(config.validate().is_ok());
+
+        let config = MachineConfig {
+            boot_timeout: Duration::ZERO,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        let config = MachineConfig {
+            create_timeout: Duration::ZERO,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_vsock_config_default() {
+        let config = VsockConfig::default();
+        assert_eq!(config.guest_cid, 3);
+        assert_eq!(config.uds_path, PathBuf::from("/tmp/bouvet-vsock.sock"));
+    }
+
     #[test]
     fn test_vsock_for_vm() {
         let config = VsockConfig::for_vm(5, &PathBuf::from("/tmp/bouvet"), "vm-123");
         assert_eq!(config.guest_cid, 5);
         assert_eq!(config.uds_path, PathBuf::from("/tmp/bouvet/vm-123/v.sock"));
     }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_default_boot_args_x86_64() {
+        let config = MachineConfig::default();
+        assert_eq!(config.boot_args, "console=ttyS0 reboot=k panic=1 pci=off");
+        assert_eq!(config.kernel_boot_args(), config.boot_args);
+    }
+
+    #[test]
+    #[cfg(target_arch = "aarch64")]
+    fn test_default_boot_args_aarch64() {
+        let config = MachineConfig::default();
+        assert_eq!(
+            config.boot_args,
+            "keep_bootcon console=ttyS0 reboot=k panic=1"
+        );
+        assert!(!config.kernel_boot_args().contains("pci=off"));
+    }
+
+    #[test]
+    fn test_validate_cpu_template_arch() {
+        let with_template = |template| MachineConfig {
+            cpu_template: Some(template),
+            ..Default::default()
+        };
+        for template in [
+            CpuTemplate::C3,
+            CpuTemplate::T2,
+            CpuTemplate::T2S,
+            CpuTemplate::T2CL,
+            CpuTemplate::T2A,
+            CpuTemplate::V1N1,
+        ] {
+            let result = with_template(template).validate();
+            assert_eq!(
+                result.is_ok(),
+                template.arch() == std::env::consts::ARCH,
+                "{template:?}: {result:?}"
+            );
+        }
+        #[cfg(target_arch = "aarch64")]
+        assert!(matches!(
+            with_template(CpuTemplate::T2).validate(),
+            Err(VmError::Config(msg)) if msg.contains("x86_64")
+        ));
+        #[cfg(target_arch = "x86_64")]
+        assert!(matches!(
+            with_template(CpuTemplate::V1N1).validate(),
+            Err(VmError::Config(msg)) if msg.contains("aarch64")
+        ));
+    }
+
+    #[test]
+    fn test_kernel_boot_args_fast_boot() {
+        let config = MachineConfig::default();
+        assert_eq!(config.kernel_boot_args(), config.boot_args);
+        assert!(!config.kernel_boot_args().contains("init="));
+
+        let config = MachineConfig {
+            boot_args: "console=ttyS0 reboot=k panic=1 pci=off".into(),
+            fast_boot: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.kernel_boot_args(),
+            "console=ttyS0 reboot=k panic=1 pci=off init=/usr/local/bin/bouvet-agent BOUVET_PID1=1"
+        );
+    }
+
+    #[test]
+    fn test_kernel_boot_args_panic_behavior() {
+        let config = MachineConfig::default();
+        assert_eq!(config.panic_behavior, PanicBehavior::Reboot);
+        assert!(config.kernel_boot_args().contains("panic=1"));
+
+        let config = MachineConfig {
+            boot_args: "console=ttyS0 reboot=k panic=1 pci=off".into(),
+            panic_behavior: PanicBehavior::Halt,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.kernel_boot_args(),
+            "console=ttyS0 reboot=k panic=0 pci=off"
+        );
+
+        // Appended when the boot args don't set it
+        let config = MachineConfig {
+            boot_args: "console=ttyS0".into(),
+            panic_behavior: PanicBehavior::Halt,
+            ..Default::default()
+        };
+        assert_eq!(config.kernel_boot_args(), "console=ttyS0 panic=0");
+    }
+
+    fn sample_static_ip() -> StaticIp {
+        StaticIp {
+            addr: Ipv4Addr::new(172, 16, 0, 2),
+            gateway: Ipv4Addr::new(172, 16, 0, 1),
+            netmask: Ipv4Addr::new(255, 255, 255, 0),
+            iface: "eth0".into(),
+        }
+    }
+
+    #[test]
+    fn test_kernel_boot_args_capture_console() {
+        let config = MachineConfig {
+            boot_args: "reboot=k panic=1".into(),
+            capture_console: true,
+            ..Default::default()
+        };
+        assert_eq!(config.kernel_boot_args(), "reboot=k panic=1 console=ttyS0");
+
+        // A console already in the boot args is kept
+        let config = MachineConfig {
+            boot_args: "console=ttyS1 panic=1".into(),
+            capture_console: true,
+            ..Default::default()
+        };
+        assert_eq!(config.kernel_boot_args(), "console=ttyS1 panic=1");
+    }
+
+    #[test]
+    fn test_kernel_boot_args_overlay() {
+        let mut config = MachineConfig {
+            boot_args: "reboot=k panic=1".into(),
+            overlay_size_mib: Some(256),
+            ..Default::default()
+        };
+        assert_eq!(config.overlay_device().as_deref(), Some("vdb"));
+        assert_eq!(
+            config.kernel_boot_args(),
+            "reboot=k panic=1 overlay_root=vdb"
+        );
+
+        config.overlay_size_mib = None;
+        assert_eq!(config.overlay_device(), None);
+        assert_eq!(config.kernel_boot_args(), "reboot=k panic=1");
+    }
+
+    #[test]
+    fn test_validate_overlay() {
+        let mut config = MachineConfig {
+            overlay_size_mib: Some(256),
+            ..Default::default()
+        };
+        // The root drive must be read-only
+        assert!(config.validate().is_err());
+        config.root_drive.is_read_only = true;
+        assert!(config.validate().is_ok());
+
+        config.overlay_size_mib = Some(0);
+        assert!(config.validate().is_err());
+        config.overlay_size_mib = Some(256);
+
+        config.extra_drives.push(DriveConfig {
+            drive_id: OVERLAY_DRIVE_ID.into(),
+            is_root_device: false,
+            ..Default::default()
+        });
+        assert!(config.validate().is_err());
+
+        config.extra_drives = (0..25)
+            .map(|i| DriveConfig {
+                drive_id: format!("data{i}"),
+                is_root_device: false,
+                ..Default::default()
+            })
+            .collect();
+        assert_eq!(config.overlay_device(), None);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_kernel_boot_args_static_ip() {
+        let config = MachineConfig {
+            boot_args: "console=ttyS0 ip=dhcp panic=1".into(),
+            networks: vec![NetworkConfig {
+                static_ip: Some(sample_static_ip()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.kernel_boot_args(),
+            "console=ttyS0 panic=1 ip=172.16.0.2::172.16.0.1:255.255.255.0::eth0:off"
+        );
+    }
+
+    #[test]
+    fn test_validate_static_ip() {
+        assert!(sample_static_ip().validate().is_ok());
+
+        let invalid = [
+            StaticIp {
+                netmask: Ipv4Addr::new(255, 0, 255, 0),
+                ..sample_static_ip()
+            },
+            StaticIp {
+                addr: Ipv4Addr::new(172, 16, 0, 255),
+                ..sample_static_ip()
+            },
+            StaticIp {
+                gateway: Ipv4Addr::new(10, 0, 0, 1),
+                ..sample_static_ip()
+            },
+            StaticIp {
+                gateway: Ipv4Addr::new(172, 16, 0, 2),
+                ..sample_static_ip()
+            },
+            StaticIp {
+                iface: "eth0:1".into(),
+                ..sample_static_ip()
+            },
+        ];
+        for static_ip in invalid {
+            assert!(static_ip.validate().is_err(), "{static_ip:?}");
+        }
+
+        // Only one interface can be configured through ip=
+        let net = NetworkConfig {
+            static_ip: Some(sample_static_ip()),
+            ..Default::default()
+        };
+        let config = MachineConfig {
+            networks: vec![
+                net.clone(),
+                NetworkConfig {
+                    iface_id: "eth1".into(),
+                    ..net
+                },
+            ],
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_firecracker_log_level_parse() {
+        assert_eq!(
+            MachineConfig::default().firecracker_log_level,
+            FirecrackerLogLevel::Warn
+        );
+        assert_eq!(
+            "DEBUG".parse::<FirecrackerLogLevel>().unwrap(),
+            FirecrackerLogLevel::Debug
+        );
+        assert_eq!(
+            "warning".parse::<FirecrackerLogLevel>().unwrap(),
+            FirecrackerLogLevel::Warn
+        );
+        assert!("trace".parse::<FirecrackerLogLevel>().is_err());
+        assert_eq!(FirecrackerLogLevel::Warn.api_value(), "Warning");
+    }
 }
@@ -28,6 +28,27 @@ pub enum VmError {
     #[error("configuration error: {0}")]
     Config(String),
 
+    /// Firecracker API rejected a request
+    #[error("Firecracker API {method} {path} failed with status {status}: {fault}")]
+    Api {
+        method: String,
+        path: String,
+        status: u16,
+        fault: String,
+    },
+
+    /// Firecracker failed to write a snapshot
+    #[error("failed to create snapshot: {0}")]
+    SnapshotCreate(String),
+
+    /// Firecracker failed to load a snapshot
+    #[error("failed to load snapshot: {0}")]
+    SnapshotLoad(String),
+
+    /// Metrics are disabled or couldn't be read
+    #[error("metrics unavailable: {0}")]
+    Metrics(String),
+
     /// Firecracker/firepilot error
     #[error("firepilot error: {0}")]
     Firepilot(String),
@@ -36,6 +57,15 @@ pub enum VmError {
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
+    /// Firecracker didn't start the guest within `boot_timeout`; the VM was killed
+    #[error("VM {id} did not boot within {elapsed:?}")]
+    BootTimeout {
+        /// The VM that failed to boot
+        id: uuid::Uuid,
+        /// Time spent waiting before giving up
+        elapsed: std::time::Duration,
+    },
+
     /// Timeout waiting for operation
     #[error("operation timed out after {0:?}")]
     Timeout(std::time::Duration),
@@ -0,0 +1,863 @@
+//! Typed client for the Firecracker HTTP API.
+//!
+//! firepilot's high-level API doesn't expose every endpoint we need
+//! (logger, metrics, machine config, rate limiters, vsock, entropy, balloon, pause/resume, snapshots), so these are sent
+//! directly over the API socket. Each endpoint builds an [`ApiRequest`];
+//! sending, status checks and fault-message parsing are shared.
+
+use crate::config::{
+    CpuTemplate, DriveConfig, FirecrackerLogLevel, NetworkConfig, RateLimiter, TokenBucket,
+    VsockConfig,
+};
+use crate::error::{Result, VmError};
+use crate::machine::VmState;
+use firepilot_models::models::{MachineConfiguration, Vsock};
+use hyper::{Body, Client, Method, Request};
+use hyperlocal::{UnixClientExt, Uri};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+/// VM state requested through `PATCH /vm`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum VmStateChange {
+    /// Pause all vCPUs.
+    Paused,
+    /// Resume a paused VM.
+    Resumed,
+}
+
+/// Action sent through `PUT /actions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum InstanceAction {
+    /// Boot a configured VM.
+    InstanceStart,
+    /// Ask the guest to shut down (x86_64 only).
+    SendCtrlAltDel,
+    /// Flush the metrics file.
+    FlushMetrics,
+}
+
+/// Response of `GET /`: who Firecracker is and what state its VM is in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstanceInfo {
+    /// Instance ID given with `--id` (`anonymous-instance` by default).
+    pub id: String,
+    /// VM state as Firecracker sees it: `Not started`, `Running` or `Paused`.
+    pub state: String,
+    /// Firecracker version.
+    pub vmm_version: String,
+    /// Always `Firecracker`.
+    pub app_name: String,
+}
+
+impl InstanceInfo {
+    /// Map Firecracker's state to ours.
+    ///
+    /// # Returns
+    /// `None` for a state this crate doesn't know.
+    pub fn vm_state(&self) -> Option<VmState> {
+        match self.state.as_str() {
+            "Not started" => Some(VmState::Creating),
+            "Running" => Some(VmState::Running),
+            "Paused" => Some(VmState::Paused),
+            _ => None,
+        }
+    }
+}
+
+/// A Firecracker API request, before it is sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiRequest {
+    /// HTTP method.
+    pub method: Method,
+    /// Endpoint path (e.g. `/machine-config`).
+    pub path: Cow<'static, str>,
+    /// JSON request body.
+    pub body: String,
+}
+
+impl ApiRequest {
+    fn new(
+        method: Method,
+        path: impl Into<Cow<'static, str>>,
+        body: &impl Serialize,
+    ) -> Result<Self> {
+        let path = path.into();
+        let body = serde_json::to_string(body)
+            .map_err(|e| VmError::Config(format!("failed to serialize {path} request: {e}")))?;
+        Ok(Self { method, path, body })
+    }
+
+    /// Build a `GET /` request for the [`InstanceInfo`].
+    pub fn instance_info() -> Self {
+        Self {
+            method: Method::GET,
+            path: Cow::Borrowed("/"),
+            body: String::new(),
+        }
+    }
+
+    /// Build a `PUT /machine-config` request.
+    pub fn machine_config(
+        vcpu_count: u8,
+        mem_size_mib: u32,
+        cpu_template: Option<CpuTemplate>,
+    ) -> Result<Self> {
+        let config = MachineConfiguration::new(mem_size_mib as i32, vcpu_count as i32);
+        let mut body = serde_json::to_value(&config).map_err(|e| {
+            VmError::Config(format!("failed to serialize /machine-config request: {e}"))
+        })?;
+        if let Some(template) = cpu_template {
+            body["cpu_template"] = template.api_value().into();
+        }
+        Self::new(Method::PUT, "/machine-config", &body)
+    }
+
+    /// Build a `PUT /logger` request.
+    ///
+    /// Firecracker opens `log_path` without creating it, so the file must
+    /// exist.
+    pub fn logger(log_path: &Path, level: FirecrackerLogLevel) -> Result<Self> {
+        Self::new(
+            Method::PUT,
+            "/logger",
+            &serde_json::json!({
+                "log_path": log_path,
+                "level": level.api_value(),
+                "show_level": true,
+                "show_log_origin": true,
+            }),
+        )
+    }
+
+    /// Build a `PUT /metrics` request.
+    ///
+    /// Like the log file, `metrics_path` must exist.
+    pub fn metrics(metrics_path: &Path) -> Result<Self> {
+        Self::new(
+            Method::PUT,
+            "/metrics",
+            &serde_json::json!({ "metrics_path": metrics_path }),
+        )
+    }
+
+    /// Build a `PUT /serial` request, sending the guest serial console to
+    /// `serial_out_path`.
+    pub fn serial(serial_out_path: &Path) -> Result<Self> {
+        Self::new(
+            Method::PUT,
+            "/serial",
+            &serde_json::json!({ "serial_out_path": serial_out_path }),
+        )
+    }
+
+    /// Build a `PUT /vsock` request.
+    pub fn vsock(config: &VsockConfig) -> Result<Self> {
+        let vsock = Vsock::new(
+            config.guest_cid as i32,
+            config.uds_path.to_string_lossy().to_string(),
+        );
+        Self::new(Method::PUT, "/vsock", &vsock)
+    }
+
+    /// Build a `PUT /drives/{drive_id}` request describing the whole drive,
+    /// including its rate limiter. Before boot this replaces the drive
+    /// firepilot configured.
+    pub fn drive(drive: &DriveConfig) -> Result<Self> {
+        Self::new(
+            Method::PUT,
+            format!("/drives/{}", drive.drive_id),
+            &serde_json::json!({
+                "drive_id": drive.drive_id,
+                "path_on_host": drive.path_on_host,
+                "is_root_device": drive.is_root_device,
+                "is_read_only": drive.is_read_only,
+                "rate_limiter": drive.rate_limiter.as_ref().map(rate_limiter_body),
+            }),
+        )
+    }
+
+    /// Build a `PUT /network-interfaces/{iface_id}` request describing the
+    /// whole interface. Its rate limiter applies to both rx and tx.
+    pub fn network_interface(net: &NetworkConfig) -> Result<Self> {
+        let rate_limiter = net.rate_limiter.as_ref().map(rate_limiter_body);
+        let mut body = serde_json::json!({
+            "iface_id": net.iface_id,
+            "host_dev_name": net.host_dev_name,
+            "rx_rate_limiter": rate_limiter,
+            "tx_rate_limiter": rate_limiter,
+        });
+        if let Some(mac) = &net.guest_mac {
+            body["guest_mac"] = mac.as_str().into();
+        }
+        Self::new(
+            Method::PUT,
+            format!("/network-interfaces/{}", net.iface_id),
+            &body,
+        )
+    }
+
+    /// Build a `PUT /mmds/config` request enabling MMDS version 2 (session
+    /// tokens) on the given interfaces.
+    pub fn mmds_config(iface_ids: &[&str]) -> Result<Self> {
+        Self::new(
+            Method::PUT,
+            "/mmds/config",
+            &serde_json::json!({
+                "version": "V2",
+                "network_interfaces": iface_ids,
+            }),
+        )
+    }
+
+    /// Build a `PUT /mmds` request replacing the metadata store.
+    pub fn mmds(data: &serde_json::Value) -> Result<Self> {
+        Self::new(Method::PUT, "/mmds", data)
+    }
+
+    /// Build a `PUT /entropy` request. The device takes no options beyond
+    /// an optional rate limiter, which we don't set.
+    pub fn entropy() -> Result<Self> {
+        Self::new(Method::PUT, "/entropy", &serde_json::json!({}))
+    }
+
+    /// Build a `PUT /balloon` request.
+    pub fn balloon(amount_mib: u32, deflate_on_oom: bool) -> Result<Self> {
+        Self::new(
+            Method::PUT,
+            "/balloon",
+            &serde_json::json!({
+                "amount_mib": amount_mib,
+                "deflate_on_oom": deflate_on_oom,
+            }),
+        )
+    }
+
+    /// Build a `PATCH /balloon` request, which changes the balloon's
+    /// target size.
+    ///
+    /// Only accepted once the VM is running.
+    pub fn balloon_update(amount_mib: u32) -> Result<Self> {
+        Self::new(
+            Method::PATCH,
+            "/balloon",
+            &serde_json::json!({ "amount_mib": amount_mib }),
+        )
+    }
+
+    /// Build a `PATCH /drives/{drive_id}` request, which makes Firecracker
+    /// reopen the drive's backing file and pick up its new size.
+    ///
+    /// Only accepted once the VM is running.
+    pub fn drive_update(drive_id: &str, path_on_host: &Path) -> Result<Self> {
+        Self::new(
+            Method::PATCH,
+            format!("/drives/{drive_id}"),
+            &serde_json::json!({
+                "drive_id": drive_id,
+                "path_on_host": path_on_host,
+            }),
+        )
+    }
+
+    /// Build a `PATCH /vm` request.
+    pub fn vm_state(state: VmStateChange) -> Result<Self> {
+        Self::new(Method::PATCH, "/vm", &serde_json::json!({ "state": state }))
+    }
+
+    /// Build a `PUT /snapshot/create` request for a full snapshot.
+    ///
+    /// The VM must be paused.
+    pub fn snapshot_create(snapshot_path: &Path, mem_file_path: &Path) -> Result<Self> {
+        Self::new(
+            Method::PUT,
+            "/snapshot/create",
+            &serde_json::json!({
+                "snapshot_type": "Full",
+                "snapshot_path": snapshot_path,
+                "mem_file_path": mem_file_path,
+            }),
+        )
+    }
+
+    /// Build a `PUT /snapshot/load` request.
+    ///
+    /// Only accepted by a Firecracker process that hasn't been configured.
+    pub fn snapshot_load(
+        snapshot_path: &Path,
+        mem_file_path: &Path,
+        resume_vm: bool,
+    ) -> Result<Self> {
+        Self::new(
+            Method::PUT,
+            "/snapshot/load",
+            &serde_json::json!({
+                "snapshot_path": snapshot_path,
+                "mem_backend": {
+                    "backend_type": "File",
+                    "backend_path": mem_file_path,
+                },
+                "resume_vm": resume_vm,
+            }),
+        )
+    }
+
+    /// Build a `PUT /actions` request.
+    pub fn action(action: InstanceAction) -> Result<Self> {
+        Self::new(
+            Method::PUT,
+            "/actions",
+            &serde_json::json!({ "action_type": action }),
+        )
+    }
+}
+
+/// Client for one Firecracker instance's API socket.
+#[derive(Debug, Clone)]
+pub struct FcApi {
+    socket_path: PathBuf,
+}
+
+impl FcApi {
+    /// Create a client for the API socket at `socket_path`.
+    pub fn new(socket_path: impl Into<PathBuf>) -> Self {
+        Self {
+            socket_path: socket_path.into(),
+        }
+    }
+
+    /// Get the API socket path.
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    /// Set vCPU count and memory size.
+    ///
+    /// **Must be called BEFORE starting the VM.**
+    pub async fn put_machine_config(
+        &self,
+        vcpu_count: u8,
+        mem_size_mib: u32,
+        cpu_template: Option<CpuTemplate>,
+    ) -> Result<()> {
+        tracing::debug!(
+            vcpu_count,
+            mem_size_mib,
+            ?cpu_template,
+            "Configuring machine resources"
+        );
+        self.send(ApiRequest::machine_config(
+            vcpu_count,
+            mem_size_mib,
+            cpu_template,
+        )?)
+        .await
+    }
+
+    /// Send Firecracker's own log to `log_path`.
+    ///
+    /// **Must be called BEFORE starting the VM.**
+    pub async fn put_logger(&self, log_path: &Path, level: FirecrackerLogLevel) -> Result<()> {
+        tracing::debug!(log_path = %log_path.display(), level = ?level, "Configuring logger");
+        self.send(ApiRequest::logger(log_path, level)?).await
+    }
+
+    /// Send metrics records to `metrics_path`.
+    ///
+    /// **Must be called BEFORE starting the VM.**
+    pub async fn put_metrics(&self, metrics_path: &Path) -> Result<()> {
+        tracing::debug!(metrics_path = %metrics_path.display(), "Configuring metrics");
+        self.send(ApiRequest::metrics(metrics_path)?).await
+    }
+
+    /// Write the guest serial console to `serial_out_path`.
+    ///
+    /// **Must be called BEFORE starting the VM.**
+    pub async fn put_serial(&self, serial_out_path: &Path) -> Result<()> {
+        tracing::debug!(serial_out_path = %serial_out_path.display(), "Configuring serial console");
+        self.send(ApiRequest::serial(serial_out_path)?).await
+    }
+
+    /// Configure the vsock device.
+    ///
+    /// **Must be called BEFORE starting the VM.**
+    pub async fn put_vsock(&self, config: &VsockConfig) -> Result<()> {
+        tracing::debug!(
+            cid = config.guest_cid,
+            uds_path = %config.uds_path.display(),
+            "Configuring vsock"
+        );
+        self.send(ApiRequest::vsock(config)?).await
+    }
+
+    /// Configure a drive, replacing any with the same id.
+    ///
+    /// **Must be called BEFORE starting the VM.**
+    pub async fn put_drive(&self, drive: &DriveConfig) -> Result<()> {
+        self.send(ApiRequest::drive(drive)?).await
+    }
+
+    /// Configure a network interface, replacing any with the same id.
+    ///
+    /// **Must be called BEFORE starting the VM.**
+    pub async fn put_network_interface(&self, net: &NetworkConfig) -> Result<()> {
+        self.send(ApiRequest::network_interface(net)?).await
+    }
+
+    /// Attach a virtio-rng entropy device.
+    ///
+    /// **Must be called BEFORE starting the VM.**
+    pub async fn put_entropy(&self) -> Result<()> {
+        tracing::debug!("Configuring entropy device");
+        self.send(ApiRequest::entropy()?).await
+    }
+
+    /// Configure the memory balloon device.
+    pub async fn put_balloon(&self, amount_mib: u32, deflate_on_oom: bool) -> Result<()> {
+        tracing::debug!(amount_mib, deflate_on_oom, "Configuring balloon");
+        self.send(ApiRequest::balloon(amount_mib, deflate_on_oom)?)
+            .await
+    }
+
+    /// Enable MMDS on the given interfaces.
+    ///
+    /// **Must be called BEFORE starting the VM, after the interfaces.**
+    pub async fn put_mmds_config(&self, iface_ids: &[&str]) -> Result<()> {
+        tracing::debug!(?iface_ids, "Configuring MMDS");
+        self.send(ApiRequest::mmds_config(iface_ids)?).await
+    }
+
+    /// Replace the data MMDS serves.
+    pub async fn put_mmds(&self, data: &serde_json::Value) -> Result<()> {
+        tracing::debug!("Setting MMDS data");
+        self.send(ApiRequest::mmds(data)?).await
+    }
+
+    /// Change the balloon's target size on a running VM.
+    pub async fn patch_balloon(&self, amount_mib: u32) -> Result<()> {
+        tracing::debug!(amount_mib, "Updating balloon");
+        self.send(ApiRequest::balloon_update(amount_mib)?).await
+    }
+
+    /// Tell Firecracker a drive's backing file changed, e.g. was grown.
+    pub async fn patch_drive(&self, drive_id: &str, path_on_host: &Path) -> Result<()> {
+        tracing::debug!(drive_id, path = %path_on_host.display(), "Updating drive");
+        self.send(ApiRequest::drive_update(drive_id, path_on_host)?)
+            .await
+    }
+
+    /// Pause or resume the VM.
+    pub async fn patch_vm(&self, state: VmStateChange) -> Result<()> {
+        tracing::debug!(state = ?state, "Changing VM state");
+        self.send(ApiRequest::vm_state(state)?).await
+    }
+
+    /// Write a full snapshot of a paused VM.
+    pub async fn put_snapshot_create(
+        &self,
+        snapshot_path: &Path,
+        mem_file_path: &Path,
+    ) -> Result<()> {
+        tracing::debug!(
+            snapshot = %snapshot_path.display(),
+            mem_file = %mem_file_path.display(),
+            "Creating snapshot"
+        );
+        self.send(ApiRequest::snapshot_create(snapshot_path, mem_file_path)?)
+            .await
+    }
+
+    /// Load a snapshot into a fresh Firecracker process.
+    pub async fn put_snapshot_load(
+        &self,
+        snapshot_path: &Path,
+        mem_file_path: &Path,
+        resume_vm: bool,
+    ) -> Result<()> {
+        tracing::debug!(
+            snapshot = %snapshot_path.display(),
+            mem_file = %mem_file_path.display(),
+            resume_vm,
+            "Loading snapshot"
+        );
+        self.send(ApiRequest::snapshot_load(
+            snapshot_path,
+            mem_file_path,
+            resume_vm,
+        )?)
+        .await
+    }
+
+    /// Perform an instance action.
+    pub async fn put_actions(&self, action: InstanceAction) -> Result<()> {
+        tracing::debug!(action = ?action, "Sending instance action");
+        self.send(ApiRequest::action(action)?).await
+    }
+
+    /// Get the instance info, including the VM state Firecracker reports.
+    pub async fn get_instance_info(&self) -> Result<InstanceInfo> {
+        let body = self.request(ApiRequest::instance_info()).await?;
+        serde_json::from_slice(&body)
+            .map_err(|e| VmError::Firepilot(format!("invalid GET / response: {e}")))
+    }
+
+    /// Send a request and check that Firecracker accepted it.
+    pub async fn send(&self, request: ApiRequest) -> Result<()> {
+        self.request(request).await.map(drop)
+    }
+
+    /// Send a request and return the body of a successful response.
+    async fn request(&self, request: ApiRequest) -> Result<hyper::body::Bytes> {
+        let start = std::time::Instant::now();
+        let ApiRequest { method, path, body } = request;
+        tracing::trace!(method = %method, path = %path, body = %body, socket = %self.socket_path.display(), "Firecracker API request");
+
+        let uri: hyper::Uri = Uri::new(&self.socket_path, &path).into();
+        let http_request = Request::builder()
+            .method(method.clone())
+            .uri(uri)
+            .header("Content-Type", "application/json")
+            .body(Body::from(body))
+            .map_err(|e| VmError::Config(format!("failed to build {path} request: {e}")))?;
+
+        let response = Client::unix().request(http_request).await.map_err(|e| {
+            tracing::error!(method = %method, path = %path, error = %e, "Firecracker API request failed");
+            VmError::Firepilot(format!("{method} {path} request failed: {e}"))
+        })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = hyper::body::to_bytes(response.into_body())
+                .await
+                .unwrap_or_default();
+            let fault = fault_message(&body);
+            tracing::error!(method = %method, path = %path, status = %status, fault = %fault, "Firecracker API error");
+            return Err(VmError::Api {
+                method: method.to_string(),
+                path: path.to_string(),
+                status: status.as_u16(),
+                fault,
+            });
+        }
+
+        tracing::debug!(
+            method = %method,
+            path = %path,
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "Firecracker API request succeeded"
+        );
+        hyper::body::to_bytes(response.into_body())
+            .await
+            .map_err(|e| VmError::Firepilot(format!("{method} {path} response failed: {e}")))
+    }
+}
+
+/// Firecracker's error response body.
+#[derive(Debug, Deserialize)]
+struct Fault {
+    fault_message: String,
+}
+
+/// Extract the fault message from an error response body.
+///
+/// Falls back to the raw body if it isn't a Firecracker fault object.
+/// Firecracker's JSON for a rate limiter, which names the refill period
+/// `refill_time` (in ms).
+fn rate_limiter_body(limiter: &RateLimiter) -> serde_json::Value {
+    let bucket = |bucket: &TokenBucket| {
+        serde_json::json!({
+            "size": bucket.size,
+            "refill_time": bucket.refill_time_ms,
+            "one_time_burst": bucket.one_time_burst,
+        })
+    };
+    serde_json::json!({
+        "bandwidth": limiter.bandwidth.as_ref().map(bucket),
+        "ops": limiter.ops.as_ref().map(bucket),
+    })
+}
+
+fn fault_message(body: &[u8]) -> String {
+    if let Ok(fault) = serde_json::from_slice::<Fault>(body) {
+        return fault.fault_message;
+    }
+    let raw = String::from_utf8_lossy(body).trim().to_string();
+    if raw.is_empty() {
+        "no error message".to_string()
+    } else {
+        raw
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(request: &ApiRequest) -> serde_json::Value {
+        serde_json::from_str(&request.body).unwrap()
+    }
+
+    #[test]
+    fn test_machine_config_request() {
+        let request = ApiRequest::machine_config(2, 256, None).unwrap();
+        assert_eq!(request.method, Method::PUT);
+        assert_eq!(request.path, "/machine-config");
+        let untemplated = body(&request);
+        assert_eq!(untemplated["mem_size_mib"], 256);
+        assert_eq!(untemplated["vcpu_count"], 2);
+        assert!(untemplated.get("cpu_template").is_none());
+
+        let request = ApiRequest::machine_config(2, 256, Some(CpuTemplate::T2S)).unwrap();
+        assert_eq!(body(&request)["cpu_template"], "T2S");
+    }
+
+    #[test]
+    fn test_drive_update_request() {
+        let request =
+            ApiRequest::drive_update("scratch", Path::new("/tmp/vm/scratch.ext4")).unwrap();
+        assert_eq!(request.method, Method::PATCH);
+        assert_eq!(request.path, "/drives/scratch");
+        assert_eq!(
+            body(&request),
+            serde_json::json!({"drive_id": "scratch", "path_on_host": "/tmp/vm/scratch.ext4"})
+        );
+    }
+
+    #[test]
+    fn test_logger_request() {
+        let request = ApiRequest::logger(
+            Path::new("/tmp/vm/firecracker.log"),
+            FirecrackerLogLevel::Warn,
+        )
+        .unwrap();
+        assert_eq!(request.method, Method::PUT);
+        assert_eq!(request.path, "/logger");
+        assert_eq!(
+            body(&request),
+            serde_json::json!({
+                "log_path": "/tmp/vm/firecracker.log",
+                "level": "Warning",
+                "show_level": true,
+                "show_log_origin": true,
+            })
+        );
+
+        let request =
+            ApiRequest::logger(Path::new("/tmp/fc.log"), FirecrackerLogLevel::Debug).unwrap();
+        assert_eq!(body(&request)["level"], "Debug");
+    }
+
+    #[test]
+    fn test_metrics_request() {
+        let request = ApiRequest::metrics(Path::new("/tmp/vm.metrics")).unwrap();
+        assert_eq!(request.method, Method::PUT);
+        assert_eq!(request.path, "/metrics");
+        assert_eq!(
+            body(&request),
+            serde_json::json!({"metrics_path": "/tmp/vm.metrics"})
+        );
+    }
+
+    #[test]
+    fn test_serial_request() {
+        let request = ApiRequest::serial(Path::new("/tmp/vm/console.log")).unwrap();
+        assert_eq!(request.method, Method::PUT);
+        assert_eq!(request.path, "/serial");
+        assert_eq!(
+            body(&request),
+            serde_json::json!({"serial_out_path": "/tmp/vm/console.log"})
+        );
+    }
+
+    #[test]
+    fn test_vsock_request() {
+        let config = VsockConfig {
+            guest_cid: 5,
+            uds_path: PathBuf::from("/tmp/test.sock"),
+        };
+        let request = ApiRequest::vsock(&config).unwrap();
+        assert_eq!(request.method, Method::PUT);
+        assert_eq!(request.path, "/vsock");
+        let body = body(&request);
+        assert_eq!(body["guest_cid"], 5);
+        assert_eq!(body["uds_path"], "/tmp/test.sock");
+    }
+
+    #[test]
+    fn test_drive_request_with_rate_limiter() {
+        let drive = DriveConfig {
+            drive_id: "scratch".into(),
+            path_on_host: PathBuf::from("/tmp/scratch.ext4"),
+            is_root_device: false,
+            is_read_only: false,
+            rate_limiter: Some(RateLimiter {
+                bandwidth: Some(TokenBucket {
+                    size: 1048576,
+                    refill_time_ms: 1000,
+                    one_time_burst: Some(4096),
+                }),
+                ops: None,
+            }),
+        };
+        let request = ApiRequest::drive(&drive).unwrap();
+        assert_eq!(request.method, Method::PUT);
+        assert_eq!(request.path, "/drives/scratch");
+        let body = body(&request);
+        assert_eq!(
+            body["rate_limiter"],
+            serde_json::json!({
+                "bandwidth": {"size": 1048576, "refill_time": 1000, "one_time_burst": 4096},
+                "ops": null,
+            })
+        );
+        assert_eq!(body["is_read_only"], false);
+    }
+
+    #[test]
+    fn test_network_interface_request_limits_both_directions() {
+        let net = NetworkConfig {
+            rate_limiter: Some(RateLimiter {
+                bandwidth: None,
+                ops: Some(TokenBucket {
+                    size: 100,
+                    refill_time_ms: 10,
+                    one_time_burst: None,
+                }),
+            }),
+            ..Default::default()
+        };
+        let request = ApiRequest::network_interface(&net).unwrap();
+        assert_eq!(request.path, "/network-interfaces/eth0");
+        let body = body(&request);
+        assert_eq!(body["host_dev_name"], "tap0");
+        assert_eq!(body["rx_rate_limiter"]["ops"]["refill_time"], 10);
+        assert_eq!(body["rx_rate_limiter"], body["tx_rate_limiter"]);
+        assert!(body.get("guest_mac").is_none());
+    }
+
+    #[test]
+    fn test_entropy_request() {
+        let request = ApiRequest::entropy().unwrap();
+        assert_eq!(request.method, Method::PUT);
+        assert_eq!(request.path, "/entropy");
+        assert_eq!(body(&request), serde_json::json!({}));
+    }
+
+    #[test]
+    fn test_mmds_requests() {
+        let request = ApiRequest::mmds_config(&["eth0"]).unwrap();
+        assert_eq!(request.method, Method::PUT);
+        assert_eq!(request.path, "/mmds/config");
+        assert_eq!(
+            body(&request),
+            serde_json::json!({"version": "V2", "network_interfaces": ["eth0"]})
+        );
+
+        let data = serde_json::json!({"latest": {"meta-data": {"instance-id": "vm-1"}}});
+        let request = ApiRequest::mmds(&data).unwrap();
+        assert_eq!(request.path, "/mmds");
+        assert_eq!(body(&request), data);
+    }
+
+    #[test]
+    fn test_balloon_request() {
+        let request = ApiRequest::balloon(128, true).unwrap();
+        assert_eq!(request.method, Method::PUT);
+        assert_eq!(request.path, "/balloon");
+        assert_eq!(
+            body(&request),
+            serde_json::json!({"amount_mib": 128, "deflate_on_oom": true})
+        );
+
+        let request = ApiRequest::balloon_update(64).unwrap();
+        assert_eq!(request.method, Method::PATCH);
+        assert_eq!(request.path, "/balloon");
+        assert_eq!(body(&request), serde_json::json!({"amount_mib": 64}));
+    }
+
+    #[test]
+    fn test_vm_state_request() {
+        let request = ApiRequest::vm_state(VmStateChange::Paused).unwrap();
+        assert_eq!(request.method, Method::PATCH);
+        assert_eq!(request.path, "/vm");
+        assert_eq!(body(&request), serde_json::json!({"state": "Paused"}));
+
+        let request = ApiRequest::vm_state(VmStateChange::Resumed).unwrap();
+        assert_eq!(body(&request), serde_json::json!({"state": "Resumed"}));
+    }
+
+    #[test]
+    fn test_snapshot_requests() {
+        let request =
+            ApiRequest::snapshot_create(Path::new("/s/vmstate"), Path::new("/s/memory")).unwrap();
+        assert_eq!(request.method, Method::PUT);
+        assert_eq!(request.path, "/snapshot/create");
+        assert_eq!(
+            body(&request),
+            serde_json::json!({
+                "snapshot_type": "Full",
+                "snapshot_path": "/s/vmstate",
+                "mem_file_path": "/s/memory",
+            })
+        );
+
+        let request =
+            ApiRequest::snapshot_load(Path::new("/s/vmstate"), Path::new("/s/memory"), true)
+                .unwrap();
+        assert_eq!(request.path, "/snapshot/load");
+        let body = body(&request);
+        assert_eq!(body["mem_backend"]["backend_type"], "File");
+        assert_eq!(body["mem_backend"]["backend_path"], "/s/memory");
+        assert_eq!(body["resume_vm"], true);
+    }
+
+    #[test]
+    fn test_action_request() {
+        let request = ApiRequest::action(InstanceAction::SendCtrlAltDel).unwrap();
+        assert_eq!(request.method, Method::PUT);
+        assert_eq!(request.path, "/actions");
+        assert_eq!(
+            body(&request),
+            serde_json::json!({"action_type": "SendCtrlAltDel"})
+        );
+    }
+
+    #[test]
+    fn test_instance_info() {
+        let request = ApiRequest::instance_info();
+        assert_eq!(request.method, Method::GET);
+        assert_eq!(request.path, "/");
+        assert!(request.body.is_empty());
+
+        let info: InstanceInfo = serde_json::from_str(
+            r#"{"id":"anonymous-instance","state":"Paused","vmm_version":"1.7.0","app_name":"Firecracker"}"#,
+        )
+        .unwrap();
+        assert_eq!(info.id, "anonymous-instance");
+        assert_eq!(info.vmm_version, "1.7.0");
+        assert_eq!(info.app_name, "Firecracker");
+        assert_eq!(info.vm_state(), Some(VmState::Paused));
+
+        let state = |state: &str| {
+            InstanceInfo {
+                state: state.to_string(),
+                ..info.clone()
+            }
+            .vm_state()
+        };
+        assert_eq!(state("Not started"), Some(VmState::Creating));
+        assert_eq!(state("Running"), Some(VmState::Running));
+        assert_eq!(state("Exploded"), None);
+    }
+
+    #[test]
+    fn test_fault_message() {
+        assert_eq!(
+            fault_message(br#"{"fault_message":"The requested operation is not supported"}"#),
+            "The requested operation is not supported"
+        );
+        assert_eq!(fault_message(b"  Bad Request \n"), "Bad Request");
+        assert_eq!(fault_message(b""), "no error message");
+    }
+}
@@ -38,11 +38,17 @@
 mod builder;
 mod config;
 mod error;
+mod firecracker_api;
 mod machine;
-mod machine_config;
-mod vsock;
+mod metrics;
 
 pub use builder::VmBuilder;
-pub use config::{DriveConfig, MachineConfig, NetworkConfig, VsockConfig};
+pub use config::{
+    BalloonConfig, CpuTemplate, DriveConfig, FirecrackerLogLevel, MachineConfig, NetworkConfig,
+    PanicBehavior, RateLimiter, StaticIp, TokenBucket, VsockConfig, AGENT_INIT_PATH,
+    DEFAULT_BOOT_ARGS, DEFAULT_BOOT_TIMEOUT, DEFAULT_CREATE_TIMEOUT, OVERLAY_DRIVE_ID,
+};
 pub use error::{Result, VmError};
-pub use machine::{VirtualMachine, VmState};
+pub use firecracker_api::{ApiRequest, FcApi, InstanceAction, InstanceInfo, VmStateChange};
+pub use machine::{kill_process, socket_peer_pid, VirtualMachine, VmState};
+pub use metrics::VmMetrics;
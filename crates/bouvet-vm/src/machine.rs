@@ -1,18 +1,35 @@
 //! VirtualMachine type - main interface for managing MicroVMs.
 
-use crate::config::MachineConfig;
+use crate::config::{DriveConfig, FirecrackerLogLevel, MachineConfig, OVERLAY_DRIVE_ID};
 use crate::error::{Result, VmError};
-use crate::machine_config::configure_machine;
-use crate::vsock::configure_vsock;
+use crate::firecracker_api::{FcApi, InstanceAction, InstanceInfo, VmStateChange};
+use crate::metrics::{self, VmMetrics};
 use firepilot::builder::drive::DriveBuilder;
 use firepilot::builder::executor::FirecrackerExecutorBuilder;
 use firepilot::builder::kernel::KernelBuilder;
 use firepilot::builder::network_interface::NetworkInterfaceBuilder;
 use firepilot::builder::{Builder, Configuration};
 use firepilot::machine::Machine;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use uuid::Uuid;
 
+/// How long a restored Firecracker process has to open its API socket.
+const API_SOCKET_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long an attached Firecracker process has to report its state.
+const INSTANCE_INFO_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Name of the Firecracker log file in the VM directory.
+const FIRECRACKER_LOG_FILE: &str = "firecracker.log";
+
+/// Name of the guest serial console capture in the VM directory.
+const CONSOLE_LOG_FILE: &str = "console.log";
+
+/// Name of the overlay drive image in the VM directory.
+const OVERLAY_FILE: &str = "overlay.ext4";
+
 /// Represents a running or stopped MicroVM instance.
 pub struct VirtualMachine {
     /// Unique identifier for this VM
@@ -25,10 +42,17 @@ pub struct VirtualMachine {
     machine: Machine,
     /// Path to the Firecracker API socket
     socket_path: PathBuf,
+    /// Firecracker process ID, if it could be resolved from the API socket
+    pid: Option<u32>,
+    /// Whether this handle was attached to an existing process (no firepilot handle)
+    attached: bool,
 }
 
 /// Current state of the VM.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// Serializes to the same lowercase strings produced by `Display`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum VmState {
     /// VM is being created
     Creating,
@@ -57,9 +81,16 @@ impl VirtualMachine {
     /// This will:
     /// 1. Build the firepilot configuration
     /// 2. Create the Machine instance
-    /// 3. Configure vsock if specified
+    /// 3. Configure vsock and the entropy device if specified
     /// 4. Start the VM
     ///
+    /// With [`MachineConfig::overlay_size_mib`], the overlay image is
+    /// created and formatted first, in the VM directory.
+    ///
+    /// All of it is bounded by [`MachineConfig::create_timeout`]: past it,
+    /// Firecracker is killed, the overlay image deleted and
+    /// [`VmError::Timeout`] returned.
+    ///
     /// # Errors
     /// Returns an error if the VM creation or startup fails.
     pub async fn create(config: MachineConfig) -> Result<Self> {
@@ -85,11 +116,77 @@ impl VirtualMachine {
             "Creating new MicroVM"
         );
 
+        // Bound everything Firecracker does, so a hung process can't block
+        // the caller forever
+        let mut machine = Machine::new();
+        let booted = match tokio::time::timeout(
+            config.create_timeout,
+            Self::boot(id, &config, &mut machine),
+        )
+        .await
+        {
+            Ok(booted) => booted,
+            Err(_) => {
+                tracing::error!(%id, timeout = ?config.create_timeout, "VM creation timed out, killing it");
+                if let Err(e) = machine.kill().await {
+                    tracing::warn!(%id, error = ?e, "Failed to kill VM after create timeout");
+                }
+                Err(VmError::Timeout(config.create_timeout))
+            }
+        };
+        let (socket_path, pid) = match booted {
+            Ok(booted) => booted,
+            Err(e) => {
+                if config.overlay_size_mib.is_some() {
+                    let _ = tokio::fs::remove_file(overlay_path(&config.chroot_path, id)).await;
+                }
+                return Err(e);
+            }
+        };
+
+        let elapsed_ms = start.elapsed().as_millis() as u64;
+        tracing::info!(%id, elapsed_ms, "MicroVM started successfully");
+
+        Ok(Self {
+            id,
+            config,
+            state: VmState::Running,
+            machine,
+            socket_path,
+            pid,
+            attached: false,
+        })
+    }
+
+    /// Configure and start the VM in `machine`, returning the API socket
+    /// path and the Firecracker process ID.
+    async fn boot(
+        id: Uuid,
+        config: &MachineConfig,
+        machine: &mut Machine,
+    ) -> Result<(PathBuf, Option<u32>)> {
+        // The overlay image has to exist before Firecracker opens it
+        let overlay = match config.overlay_size_mib {
+            Some(size_mib) => {
+                let path = overlay_path(&config.chroot_path, id);
+                tracing::debug!(%id, path = %path.display(), size_mib, "Creating overlay drive");
+                create_overlay(&path, size_mib).await?;
+                Some(DriveConfig {
+                    drive_id: OVERLAY_DRIVE_ID.into(),
+                    path_on_host: path,
+                    is_root_device: false,
+                    is_read_only: false,
+                    rate_limiter: None,
+                })
+            }
+            None => None,
+        };
+
         // Build kernel configuration
         tracing::debug!(%id, "Building kernel configuration");
         let kernel = KernelBuilder::new()
             .with_kernel_image_path(config.kernel_path.to_string_lossy().to_string())
-            .with_boot_args(config.boot_args.clone())
+            .with_boot_args(config.kernel_boot_args())
             .try_build()
             .map_err(|e| {
                 tracing::error!(%id, error = ?e, "Failed to build kernel config");
@@ -137,8 +234,8 @@ impl VirtualMachine {
             .with_executor(executor)
             .with_drive(drive);
 
-        // Add extra drives
-        for extra_drive in &config.extra_drives {
+        // Add extra drives, then the overlay
+        for extra_drive in config.extra_drives.iter().chain(&overlay) {
             tracing::trace!(%id, drive_id = %extra_drive.drive_id, "Adding extra drive");
             let mut extra_builder = DriveBuilder::new()
                 .with_drive_id(extra_drive.drive_id.clone())
@@ -159,8 +256,8 @@ impl VirtualMachine {
             fp_config = fp_config.with_drive(extra);
         }
 
-        // Add network interface if configured
-        if let Some(net) = &config.network {
+        // Add network interfaces, in order
+        for net in &config.networks {
             tracing::debug!(%id, iface_id = %net.iface_id, host_dev = %net.host_dev_name, "Adding network interface");
             let mut net_builder = NetworkInterfaceBuilder::new()
                 .with_iface_id(net.iface_id.clone())
@@ -171,8 +268,8 @@ impl VirtualMachine {
             }
 
             let iface = net_builder.try_build().map_err(|e| {
-                tracing::error!(%id, error = ?e, "Failed to build network config");
-                VmError::Config(format!("network config: {:?}", e))
+                tracing::error!(%id, error = ?e, iface_id = %net.iface_id, "Failed to build network config");
+                VmError::Config(format!("network config {}: {:?}", net.iface_id, e))
             })?;
 
             fp_config = fp_config.with_interface(iface);
@@ -180,8 +277,6 @@ impl VirtualMachine {
 
         // Create the machine (this starts the Firecracker process and socket)
         tracing::debug!(%id, "Creating Firecracker machine instance");
-        let mut machine = Machine::new();
-
         machine.create(fp_config).await.map_err(|e| {
             tracing::error!(%id, error = ?e, "Failed to create machine");
             VmError::Create(format!("{:?}", e))
@@ -193,37 +288,250 @@ impl VirtualMachine {
             .join(id.to_string())
             .join("firecracker.socket");
         tracing::trace!(%id, socket = %socket_path.display(), "Firecracker socket path");
+        let pid = socket_peer_pid(&socket_path).await.ok().flatten();
+        tracing::trace!(%id, pid = ?pid, "Firecracker process");
+
+        // Configure logging first so rejections of later requests are logged
+        let api = FcApi::new(&socket_path);
+        configure_logger(&api, &socket_path, config.firecracker_log_level).await?;
+        if let Some(metrics_path) = &config.metrics_path {
+            configure_metrics(&api, metrics_path).await?;
+        }
+        if config.capture_console {
+            let console_path = socket_path.with_file_name(CONSOLE_LOG_FILE);
+            tracing::debug!(%id, path = %console_path.display(), "Capturing serial console");
+            configure_console(&api, &console_path).await?;
+        }
 
         // Configure machine resources BEFORE starting the VM
         // This is required - Firecracker needs explicit vcpu/memory config
         tracing::debug!(%id, "Configuring machine resources");
-        configure_machine(&socket_path, config.vcpu_count, config.memory_mib).await?;
+        api.put_machine_config(config.vcpu_count, config.memory_mib, config.cpu_template)
+            .await?;
 
         // Configure vsock BEFORE starting the VM (Firecracker requires this)
         if let Some(vsock_config) = &config.vsock {
             tracing::debug!(%id, cid = vsock_config.guest_cid, "Configuring vsock");
-            configure_vsock(&socket_path, vsock_config).await?;
+            api.put_vsock(vsock_config).await?;
         }
 
-        // Start the VM
-        tracing::debug!(%id, "Starting VM");
-        machine.start().await.map_err(|e| {
-            tracing::error!(%id, error = ?e, "Failed to start VM");
-            VmError::Start(format!("{:?}", e))
+        // firepilot's builders can't set rate limiters, so re-send the
+        // throttled devices with them; a PUT before boot replaces the device
+        for drive in std::iter::once(&config.root_drive).chain(&config.extra_drives) {
+            if drive.rate_limiter.is_some() {
+                tracing::debug!(%id, drive_id = %drive.drive_id, "Configuring drive rate limiter");
+                api.put_drive(drive).await?;
+            }
+        }
+        for net in &config.networks {
+            if net.rate_limiter.is_some() {
+                tracing::debug!(%id, iface_id = %net.iface_id, "Configuring interface rate limiter");
+                api.put_network_interface(net).await?;
+            }
+        }
+
+        // Attach the entropy device, also only accepted before boot
+        if config.entropy {
+            tracing::debug!(%id, "Configuring entropy device");
+            api.put_entropy().await?;
+        }
+        // MMDS needs its interfaces configured first, which machine.create did
+        if let Some(data) = &config.mmds {
+            tracing::debug!(%id, "Configuring MMDS");
+            api.put_mmds_config(&config.mmds_interfaces()).await?;
+            api.put_mmds(data).await?;
+        }
+        if let Some(balloon) = &config.balloon {
+            tracing::debug!(%id, amount_mib = balloon.amount_mib, "Configuring balloon");
+            api.put_balloon(balloon.amount_mib, balloon.deflate_on_oom)
+                .await?;
+        }
+
+        // Start the VM, giving up if Firecracker hangs
+        tracing::debug!(%id, timeout = ?config.boot_timeout, "Starting VM");
+        let boot_start = std::time::Instant::now();
+        match tokio::time::timeout(config.boot_timeout, machine.start()).await {
+            Ok(started) => started.map_err(|e| {
+                tracing::error!(%id, error = ?e, "Failed to start VM");
+                VmError::Start(format!("{:?}", e))
+            })?,
+            Err(_) => {
+                let elapsed = boot_start.elapsed();
+                tracing::error!(%id, ?elapsed, "VM did not boot in time, killing it");
+                if let Err(e) = machine.kill().await {
+                    tracing::warn!(%id, error = ?e, "Failed to kill VM after boot timeout");
+                }
+                return Err(VmError::BootTimeout { id, elapsed });
+            }
+        }
+
+        Ok((socket_path, pid))
+    }
+
+    /// Attach to a VM whose Firecracker process is already running.
+    ///
+    /// Used to recover VMs after the host process restarts. The state is
+    /// taken from Firecracker's [`InstanceInfo`], so a VM paused before the
+    /// restart is attached as `Paused`; if Firecracker doesn't answer, it's
+    /// assumed to be `Running`. The Firecracker process ID is taken from the
+    /// API socket's peer credentials so the VM can still be killed.
+    ///
+    /// # Errors
+    /// Returns an error if nothing is listening on `socket_path`.
+    pub async fn attach(
+        id: Uuid,
+        socket_path: impl Into<PathBuf>,
+        config: MachineConfig,
+    ) -> Result<Self> {
+        let socket_path = socket_path.into();
+        tracing::info!(%id, socket = %socket_path.display(), "Attaching to running MicroVM");
+
+        let pid = socket_peer_pid(&socket_path).await.map_err(|e| {
+            tracing::debug!(%id, error = %e, "Firecracker socket not reachable");
+            VmError::Firepilot(format!(
+                "firecracker socket {} not reachable: {e}",
+                socket_path.display()
+            ))
         })?;
+        tracing::debug!(%id, pid = ?pid, "Attached to Firecracker process");
 
-        let elapsed_ms = start.elapsed().as_millis() as u64;
-        tracing::info!(%id, elapsed_ms, "MicroVM started successfully");
+        let api = FcApi::new(&socket_path);
+        let state = match tokio::time::timeout(INSTANCE_INFO_TIMEOUT, api.get_instance_info()).await
+        {
+            Ok(Ok(info)) => info.vm_state().unwrap_or_else(|| {
+                tracing::warn!(%id, state = %info.state, "Unknown Firecracker state, assuming running");
+                VmState::Running
+            }),
+            Ok(Err(e)) => {
+                tracing::warn!(%id, error = %e, "Firecracker instance info unavailable, assuming running");
+                VmState::Running
+            }
+            Err(_) => {
+                tracing::warn!(%id, "Firecracker instance info timed out, assuming running");
+                VmState::Running
+            }
+        };
+        tracing::debug!(%id, %state, "Reconciled attached VM state");
+
+        Ok(Self {
+            id,
+            config,
+            state,
+            machine: Machine::new(),
+            socket_path,
+            pid,
+            attached: true,
+        })
+    }
+
+    /// Restore a VM from a snapshot written by [`snapshot`](Self::snapshot).
+    ///
+    /// Spawns a fresh Firecracker process with its API socket under
+    /// `config.chroot_path/<id>/`, loads the snapshot and resumes it. The
+    /// snapshot records the drive and vsock paths, so `config` must match
+    /// the one the VM was created with. Like an attached VM, the result has
+    /// no firepilot handle and is killed by pid.
+    ///
+    /// # Errors
+    /// Returns an error if Firecracker can't be spawned or doesn't open its
+    /// API socket in time, and [`VmError::SnapshotLoad`] if it rejects the
+    /// snapshot.
+    pub async fn restore(
+        id: Uuid,
+        config: MachineConfig,
+        snapshot_path: &Path,
+        mem_file_path: &Path,
+    ) -> Result<Self> {
+        let start = std::time::Instant::now();
+        let vm_dir = config.chroot_path.join(id.to_string());
+        let socket_path = vm_dir.join("firecracker.socket");
+        tracing::info!(
+            %id,
+            snapshot = %snapshot_path.display(),
+            socket = %socket_path.display(),
+            "Restoring MicroVM from snapshot"
+        );
+
+        tokio::fs::create_dir_all(&vm_dir).await?;
+        // Firecracker binds both sockets itself and fails if they exist
+        let _ = tokio::fs::remove_file(&socket_path).await;
+        if let Some(vsock) = &config.vsock {
+            let _ = tokio::fs::remove_file(&vsock.uds_path).await;
+        }
+
+        let mut child = tokio::process::Command::new(&config.firecracker_path)
+            .arg("--api-sock")
+            .arg(&socket_path)
+            .stdin(std::process::Stdio::null())
+            .spawn()
+            .map_err(|e| {
+                tracing::error!(%id, error = %e, "Failed to spawn firecracker");
+                VmError::Create(format!(
+                    "failed to spawn {}: {e}",
+                    config.firecracker_path.display()
+                ))
+            })?;
+        let pid = child.id();
+        // Reap the process when it exits; it is killed by pid like attached VMs
+        tokio::spawn(async move {
+            let _ = child.wait().await;
+        });
+
+        let loaded = async {
+            wait_for_socket(&socket_path, API_SOCKET_TIMEOUT).await?;
+            let api = FcApi::new(&socket_path);
+            configure_logger(&api, &socket_path, config.firecracker_log_level).await?;
+            if let Some(metrics_path) = &config.metrics_path {
+                configure_metrics(&api, metrics_path).await?;
+            }
+            api.put_snapshot_load(snapshot_path, mem_file_path, true)
+                .await
+                .map_err(|e| VmError::SnapshotLoad(e.to_string()))
+        }
+        .await;
+        if let Err(e) = loaded {
+            tracing::error!(%id, error = %e, "Failed to restore snapshot");
+            if let Some(pid) = pid {
+                let _ = kill_process(pid);
+            }
+            return Err(e);
+        }
 
+        tracing::info!(
+            %id,
+            pid = ?pid,
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "MicroVM restored"
+        );
         Ok(Self {
             id,
             config,
             state: VmState::Running,
-            machine,
+            machine: Machine::new(),
             socket_path,
+            pid,
+            attached: true,
         })
     }
 
+    /// Restore a snapshot as a new VM with a fresh ID.
+    ///
+    /// Takes the paths in the order [`create_snapshot`](Self::create_snapshot)
+    /// does; see [`restore`](Self::restore) for how the VM is brought up.
+    /// The guest's vsock socket path comes from the snapshot, so VMs
+    /// restored from one snapshot must not run side by side in the same
+    /// mount namespace.
+    ///
+    /// # Errors
+    /// As for [`restore`](Self::restore).
+    pub async fn restore_from_snapshot(
+        config: MachineConfig,
+        mem_file_path: &Path,
+        snapshot_path: &Path,
+    ) -> Result<Self> {
+        Self::restore(Uuid::new_v4(), config, snapshot_path, mem_file_path).await
+    }
+
     /// Get the unique ID of this VM.
     pub fn id(&self) -> Uuid {
         self.id
@@ -247,6 +555,101 @@ impl VirtualMachine {
         &self.socket_path
     }
 
+    /// Get a client for this VM's Firecracker API socket.
+    pub fn api(&self) -> FcApi {
+        FcApi::new(&self.socket_path)
+    }
+
+    /// Query Firecracker for its instance info.
+    ///
+    /// Firecracker's view of the VM state can diverge from [`state`](Self::state),
+    /// e.g. if the VM was paused through the API socket directly.
+    ///
+    /// # Errors
+    /// Returns an error if the API socket doesn't answer.
+    pub async fn instance_info(&self) -> Result<InstanceInfo> {
+        self.api().get_instance_info().await
+    }
+
+    /// Get the path of Firecracker's own log, next to the API socket.
+    pub fn firecracker_log_path(&self) -> PathBuf {
+        firecracker_log_path(&self.socket_path)
+    }
+
+    /// Read Firecracker's own log.
+    ///
+    /// Useful when Firecracker rejects an API request: the log usually says
+    /// why in more detail than the fault message. How much is logged is set
+    /// by [`MachineConfig::firecracker_log_level`].
+    ///
+    /// # Errors
+    /// Returns an error if the log file can't be read.
+    pub async fn firecracker_log(&self) -> Result<String> {
+        let bytes = tokio::fs::read(self.firecracker_log_path()).await?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Get the path the guest serial console is captured to, next to the
+    /// API socket.
+    pub fn console_log_path(&self) -> PathBuf {
+        self.socket_path.with_file_name(CONSOLE_LOG_FILE)
+    }
+
+    /// Read the captured guest serial console.
+    ///
+    /// Holds the kernel's boot messages and anything else written to the
+    /// console, including a panic, so it explains a guest whose agent never
+    /// came up. Only written with [`MachineConfig::capture_console`].
+    ///
+    /// # Errors
+    /// Returns an error if the capture file can't be read.
+    pub async fn console_log(&self) -> Result<String> {
+        let bytes = tokio::fs::read(self.console_log_path()).await?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Get the path of the overlay drive image, if the VM has one.
+    pub fn overlay_path(&self) -> Option<PathBuf> {
+        self.config
+            .overlay_size_mib
+            .map(|_| self.socket_path.with_file_name(OVERLAY_FILE))
+    }
+
+    /// Read the VM's latest metrics.
+    ///
+    /// Asks Firecracker to flush a fresh record if the VM is running or
+    /// paused, then parses the last record in the metrics file. Block and
+    /// network counters cover the interval since the previous record; vCPU
+    /// time is cumulative.
+    ///
+    /// # Errors
+    /// Returns [`VmError::Metrics`] if [`MachineConfig::metrics_path`] was
+    /// not set or the file holds no valid record.
+    pub async fn metrics(&self) -> Result<VmMetrics> {
+        let metrics_path = self
+            .config
+            .metrics_path
+            .as_ref()
+            .ok_or_else(|| VmError::Metrics("metrics were not enabled for this VM".into()))?;
+        if matches!(self.state, VmState::Running | VmState::Paused) {
+            self.api().put_actions(InstanceAction::FlushMetrics).await?;
+        }
+        let contents = tokio::fs::read_to_string(metrics_path)
+            .await
+            .map_err(|e| VmError::Metrics(format!("{}: {e}", metrics_path.display())))?;
+        let mut metrics = VmMetrics::parse_latest(&contents)?;
+        metrics.vcpu_time_ms = self.pid.and_then(metrics::vcpu_time_ms);
+        Ok(metrics)
+    }
+
+    /// Get the Firecracker process ID, if known.
+    ///
+    /// Resolved from the API socket's peer credentials. Can be used with
+    /// [`kill_process`] to force-terminate the VM without this handle.
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
     /// Get the vsock UDS path if vsock is configured.
     ///
     /// This path is used by the host to communicate with the guest agent.
@@ -319,16 +722,235 @@ impl VirtualMachine {
         Ok(())
     }
 
+    /// Ask the guest to shut down cleanly, then wait up to `timeout` for
+    /// Firecracker to exit.
+    ///
+    /// Sends `SendCtrlAltDel` through `PUT /actions`, which the guest
+    /// kernel sees as a keyboard reset, so it can flush filesystem
+    /// buffers before going down. Firecracker exits once the guest
+    /// reboots; exit is detected by its API socket refusing connections.
+    /// Only x86_64 guests implement the action.
+    ///
+    /// # Errors
+    /// Returns an error if the VM is not running, Firecracker rejects the
+    /// action, or [`VmError::Timeout`] if the VM is still up after
+    /// `timeout`, in which case it is left running.
+    pub async fn send_ctrl_alt_del(&mut self, timeout: Duration) -> Result<()> {
+        if self.state != VmState::Running {
+            tracing::warn!(id = %self.id, state = %self.state, "Cannot send Ctrl+Alt+Del: VM not running");
+            return Err(VmError::InvalidState {
+                expected: "running".into(),
+                actual: self.state.to_string(),
+            });
+        }
+
+        tracing::info!(id = %self.id, timeout_ms = timeout.as_millis() as u64, "Sending Ctrl+Alt+Del");
+        self.api()
+            .put_actions(InstanceAction::SendCtrlAltDel)
+            .await?;
+        wait_for_exit(&self.socket_path, timeout).await?;
+
+        self.state = VmState::Stopped;
+        tracing::debug!(id = %self.id, "VM shut down");
+        Ok(())
+    }
+
+    /// Pause all vCPUs.
+    ///
+    /// # Errors
+    /// Returns an error if the VM is not running.
+    pub async fn pause(&mut self) -> Result<()> {
+        if self.state != VmState::Running {
+            tracing::warn!(id = %self.id, state = %self.state, "Cannot pause: VM not running");
+            return Err(VmError::InvalidState {
+                expected: "running".into(),
+                actual: self.state.to_string(),
+            });
+        }
+
+        self.api().patch_vm(VmStateChange::Paused).await?;
+        self.state = VmState::Paused;
+        tracing::debug!(id = %self.id, "VM paused");
+        Ok(())
+    }
+
+    /// Resume a paused VM.
+    ///
+    /// # Errors
+    /// Returns an error if the VM is not paused.
+    pub async fn resume(&mut self) -> Result<()> {
+        if self.state != VmState::Paused {
+            tracing::warn!(id = %self.id, state = %self.state, "Cannot resume: VM not paused");
+            return Err(VmError::InvalidState {
+                expected: "paused".into(),
+                actual: self.state.to_string(),
+            });
+        }
+
+        self.api().patch_vm(VmStateChange::Resumed).await?;
+        self.state = VmState::Running;
+        tracing::debug!(id = %self.id, "VM resumed");
+        Ok(())
+    }
+
+    /// Write a full snapshot of a paused VM.
+    ///
+    /// `snapshot_path` receives the device and vCPU state, `mem_file_path`
+    /// the guest memory. The VM stays paused; restore it later with
+    /// [`restore`](Self::restore).
+    ///
+    /// # Errors
+    /// Returns [`VmError::InvalidState`] if the VM is not paused and
+    /// [`VmError::SnapshotCreate`] if Firecracker fails to write the snapshot.
+    pub async fn snapshot(&self, snapshot_path: &Path, mem_file_path: &Path) -> Result<()> {
+        if self.state != VmState::Paused {
+            tracing::warn!(id = %self.id, state = %self.state, "Cannot snapshot: VM not paused");
+            return Err(VmError::InvalidState {
+                expected: "paused".into(),
+                actual: self.state.to_string(),
+            });
+        }
+
+        let start = std::time::Instant::now();
+        self.api()
+            .put_snapshot_create(snapshot_path, mem_file_path)
+            .await
+            .map_err(|e| {
+                tracing::error!(id = %self.id, error = %e, "Failed to create snapshot");
+                VmError::SnapshotCreate(e.to_string())
+            })?;
+        tracing::info!(
+            id = %self.id,
+            snapshot = %snapshot_path.display(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "VM snapshot created"
+        );
+        Ok(())
+    }
+
+    /// Grow a drive's backing file to `new_size_mib` MiB and have
+    /// Firecracker pick up the new size.
+    ///
+    /// The guest sees the larger block device straight away; growing the
+    /// filesystem on it (e.g. with `resize2fs`) is up to the guest. Drives
+    /// only grow: shrinking would cut off data the filesystem still uses.
+    ///
+    /// # Errors
+    /// Returns [`VmError::InvalidState`] if the VM is not running and
+    /// [`VmError::Config`] if there is no writable drive `drive_id` or
+    /// `new_size_mib` is not larger than its current size.
+    pub async fn resize_drive(&self, drive_id: &str, new_size_mib: u64) -> Result<()> {
+        if self.state != VmState::Running {
+            return Err(VmError::InvalidState {
+                expected: "running".into(),
+                actual: self.state.to_string(),
+            });
+        }
+        let drive = std::iter::once(&self.config.root_drive)
+            .chain(&self.config.extra_drives)
+            .find(|drive| drive.drive_id == drive_id)
+            .ok_or_else(|| VmError::Config(format!("no drive with drive_id: {drive_id}")))?;
+        if drive.is_read_only {
+            return Err(VmError::Config(format!("drive {drive_id} is read-only")));
+        }
+
+        let file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(&drive.path_on_host)
+            .await?;
+        let current = file.metadata().await?.len();
+        let new_size = grown_size(current, new_size_mib)
+            .map_err(|e| VmError::Config(format!("cannot resize drive {drive_id}: {e}")))?;
+        // Sparse: the new space costs nothing on the host until written
+        file.set_len(new_size).await?;
+        self.api()
+            .patch_drive(drive_id, &drive.path_on_host)
+            .await?;
+        tracing::info!(
+            id = %self.id,
+            drive_id,
+            old_bytes = current,
+            new_bytes = new_size,
+            "Drive resized"
+        );
+        Ok(())
+    }
+
+    /// Inflate or deflate the balloon to `amount_mib` MiB.
+    ///
+    /// Memory the balloon holds is taken from the guest and can be
+    /// reclaimed by the host, so an idle VM can be shrunk without being
+    /// destroyed; lowering the target hands it back. The guest's balloon
+    /// driver works towards the target in the background.
+    ///
+    /// # Errors
+    /// Returns [`VmError::InvalidState`] if the VM is not running and
+    /// [`VmError::Config`] if it has no balloon device or `amount_mib`
+    /// exceeds its memory.
+    pub async fn set_balloon(&self, amount_mib: u32) -> Result<()> {
+        if self.state != VmState::Running {
+            return Err(VmError::InvalidState {
+                expected: "running".into(),
+                actual: self.state.to_string(),
+            });
+        }
+        if self.config.balloon.is_none() {
+            return Err(VmError::Config("VM has no balloon device".into()));
+        }
+        if amount_mib > self.config.memory_mib {
+            return Err(VmError::Config(format!(
+                "balloon of {amount_mib} MiB exceeds the VM's {} MiB",
+                self.config.memory_mib
+            )));
+        }
+
+        self.api().patch_balloon(amount_mib).await?;
+        tracing::info!(id = %self.id, amount_mib, "Balloon target set");
+        Ok(())
+    }
+
+    /// Pause the VM if it is running, then write a full snapshot.
+    ///
+    /// `mem_file_path` receives the guest memory, `snapshot_path` the device
+    /// and vCPU state. The VM is left paused either way; resume it to carry
+    /// on, or destroy it and bring the snapshot back with
+    /// [`restore_from_snapshot`](Self::restore_from_snapshot).
+    ///
+    /// # Errors
+    /// As for [`pause`](Self::pause) and [`snapshot`](Self::snapshot).
+    pub async fn create_snapshot(
+        &mut self,
+        mem_file_path: &Path,
+        snapshot_path: &Path,
+    ) -> Result<()> {
+        if self.state == VmState::Running {
+            self.pause().await?;
+        }
+        self.snapshot(snapshot_path, mem_file_path).await
+    }
+
     /// Force kill the VM.
     ///
     /// This immediately terminates the VM without graceful shutdown.
     pub async fn kill(&mut self) -> Result<()> {
         tracing::warn!(id = %self.id, state = %self.state, "Force killing VM");
 
-        self.machine.kill().await.map_err(|e| {
-            tracing::error!(id = %self.id, error = ?e, "Failed to kill VM");
-            VmError::Stop(format!("kill failed: {:?}", e))
-        })?;
+        if self.attached {
+            // Attached VMs have no firepilot process handle
+            let pid = self.pid.ok_or_else(|| {
+                tracing::error!(id = %self.id, "Cannot kill attached VM: unknown pid");
+                VmError::Stop("kill failed: unknown firecracker pid".into())
+            })?;
+            kill_process(pid).map_err(|e| {
+                tracing::error!(id = %self.id, pid, error = %e, "Failed to kill VM");
+                VmError::Stop(format!("kill failed: {e}"))
+            })?;
+        } else {
+            self.machine.kill().await.map_err(|e| {
+                tracing::error!(id = %self.id, error = ?e, "Failed to kill VM");
+                VmError::Stop(format!("kill failed: {:?}", e))
+            })?;
+        }
 
         self.state = VmState::Stopped;
         tracing::debug!(id = %self.id, "VM killed");
@@ -338,22 +960,734 @@ impl VirtualMachine {
     /// Destroy the VM and cleanup resources.
     ///
     /// This consumes the VirtualMachine, stopping it if running and cleaning up.
+    /// With [`MachineConfig::shutdown_timeout_ms`] set, a running guest is
+    /// first given that long to shut down after Ctrl+Alt+Del. The overlay
+    /// image, if any, is deleted once the VM is gone.
     pub async fn destroy(mut self) -> Result<()> {
         let start = std::time::Instant::now();
         tracing::info!(id = %self.id, state = %self.state, "Destroying VM");
 
-        // Stop if running
-        if self.state == VmState::Running {
+        if let Some(timeout_ms) = self.config.shutdown_timeout_ms {
+            if self.state == VmState::Running {
+                let timeout = Duration::from_millis(timeout_ms);
+                if let Err(e) = self.send_ctrl_alt_del(timeout).await {
+                    tracing::warn!(id = %self.id, error = %e, "Graceful shutdown failed, killing VM");
+                }
+            }
+        }
+
+        // Stop if running (a paused VM still has a live process)
+        if matches!(self.state, VmState::Running | VmState::Paused) {
             tracing::debug!(id = %self.id, "VM running, killing before destroy");
             let _ = self.kill().await;
         }
 
         // Machine is dropped here, which cleans up resources
+        let overlay = self.overlay_path();
         tracing::trace!(id = %self.id, "Dropping machine handle");
         drop(self.machine);
 
+        if let Some(overlay) = overlay {
+            match tokio::fs::remove_file(&overlay).await {
+                Ok(()) => tracing::debug!(id = %self.id, "Removed overlay drive"),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    tracing::warn!(id = %self.id, path = %overlay.display(), error = %e, "Failed to remove overlay drive")
+                }
+            }
+        }
+
         let elapsed_ms = start.elapsed().as_millis() as u64;
         tracing::info!(id = %self.id, elapsed_ms, "VM destroyed");
         Ok(())
     }
 }
+
+/// Resolve the process listening on a Unix socket via its peer credentials.
+///
+/// # Returns
+///
+/// `None` if the kernel doesn't report the peer's PID.
+///
+/// # Errors
+///
+/// Returns an error if nothing accepts connections on the socket, e.g.
+/// because the process that bound it is gone.
+pub async fn socket_peer_pid(socket_path: &std::path::Path) -> std::io::Result<Option<u32>> {
+    let stream = tokio::net::UnixStream::connect(socket_path).await?;
+    Ok(stream
+        .peer_cred()
+        .ok()
+        .and_then(|cred| cred.pid())
+        .and_then(|pid| u32::try_from(pid).ok()))
+}
+
+/// Path of the Firecracker log for the VM whose API socket is `socket_path`.
+fn firecracker_log_path(socket_path: &Path) -> PathBuf {
+    socket_path.with_file_name(FIRECRACKER_LOG_FILE)
+}
+
+/// Create the Firecracker log file and point Firecracker's logger at it.
+async fn configure_logger(
+    api: &FcApi,
+    socket_path: &Path,
+    level: FirecrackerLogLevel,
+) -> Result<()> {
+    let log_path = firecracker_log_path(socket_path);
+    // Firecracker won't create the file itself
+    tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .await?;
+    api.put_logger(&log_path, level).await
+}
+
+/// Create the console capture file and send the guest serial console to it.
+async fn configure_console(api: &FcApi, console_path: &Path) -> Result<()> {
+    // Start empty, so the capture only holds this boot
+    tokio::fs::File::create(console_path).await?;
+    api.put_serial(console_path).await
+}
+
+/// Create the metrics file and point Firecracker's metrics at it.
+async fn configure_metrics(api: &FcApi, metrics_path: &Path) -> Result<()> {
+    // Firecracker won't create the file itself
+    tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(metrics_path)
+        .await?;
+    api.put_metrics(metrics_path).await
+}
+
+/// Path of the overlay drive image of VM `id`.
+fn overlay_path(chroot_path: &Path, id: Uuid) -> PathBuf {
+    chroot_path.join(id.to_string()).join(OVERLAY_FILE)
+}
+
+/// Create an ext4 image of `size_mib` MiB at `path`, its space reserved
+/// up front so the guest can't run the host out of disk mid-write.
+async fn create_overlay(path: &Path, size_mib: u32) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let file = std::fs::File::create(path)?;
+    allocate(&file, u64::from(size_mib) * 1024 * 1024)?;
+    drop(file);
+
+    let output = tokio::process::Command::new("mkfs.ext4")
+        .args(["-q", "-F"])
+        .arg(path)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await
+        .map_err(|e| VmError::Create(format!("failed to run mkfs.ext4: {e}")));
+    let failure = match output {
+        Ok(output) if output.status.success() => return Ok(()),
+        Ok(output) => VmError::Create(format!(
+            "mkfs.ext4 {} failed: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        Err(e) => e,
+    };
+    let _ = tokio::fs::remove_file(path).await;
+    Err(failure)
+}
+
+/// Reserve `len` bytes for `file` with fallocate(2), falling back to a
+/// sparse file on filesystems that don't support it.
+fn allocate(file: &std::fs::File, len: u64) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let off_len = libc::off_t::try_from(len)
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "size out of range"))?;
+    // SAFETY: the descriptor stays open for the duration of the call
+    if unsafe { libc::fallocate(file.as_raw_fd(), 0, 0, off_len) } == 0 {
+        return Ok(());
+    }
+    let err = std::io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::EOPNOTSUPP) {
+        file.set_len(len)
+    } else {
+        Err(err)
+    }
+}
+
+/// Wait until something accepts connections on a Unix socket.
+async fn wait_for_socket(socket_path: &Path, timeout: Duration) -> Result<()> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        if tokio::net::UnixStream::connect(socket_path).await.is_ok() {
+            return Ok(());
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return Err(VmError::Timeout(timeout));
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+/// Wait until nothing accepts connections on a Unix socket, i.e. the
+/// process serving it has exited.
+async fn wait_for_exit(socket_path: &Path, timeout: Duration) -> Result<()> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        if tokio::net::UnixStream::connect(socket_path).await.is_err() {
+            return Ok(());
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return Err(VmError::Timeout(timeout));
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+/// Send SIGKILL to a process.
+///
+/// Used to force-terminate a Firecracker process by [`VirtualMachine::pid`].
+pub fn kill_process(pid: u32) -> std::io::Result<()> {
+    let pid = libc::pid_t::try_from(pid)
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "pid out of range"))?;
+    // SAFETY: kill(2) has no memory-safety preconditions
+    if unsafe { libc::kill(pid, libc::SIGKILL) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// Size in bytes for growing a `current_bytes` drive to `new_size_mib` MiB.
+///
+/// Errors unless the new size is strictly larger.
+fn grown_size(current_bytes: u64, new_size_mib: u64) -> std::result::Result<u64, String> {
+    let new_bytes = new_size_mib
+        .checked_mul(1024 * 1024)
+        .ok_or_else(|| format!("{new_size_mib} MiB is too large"))?;
+    if new_bytes <= current_bytes {
+        return Err(format!(
+            "drives can only grow: {new_size_mib} MiB is not larger than the current {} MiB",
+            current_bytes.div_ceil(1024 * 1024)
+        ));
+    }
+    Ok(new_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{BalloonConfig, DriveConfig};
+
+    #[test]
+    fn test_vm_state_serde_matches_display() {
+        for state in [
+            VmState::Creating,
+            VmState::Running,
+            VmState::Paused,
+            VmState::Stopped,
+        ] {
+            let json = serde_json::to_string(&state).unwrap();
+            assert_eq!(json, format!("\"{}\"", state));
+
+            let parsed: VmState = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, state);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_attach_fails_without_firecracker() {
+        let socket = std::env::temp_dir().join(format!("bouvet-missing-{}.socket", Uuid::new_v4()));
+        let result =
+            VirtualMachine::attach(Uuid::new_v4(), &socket, MachineConfig::default()).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_attach_resolves_process_id() {
+        let socket = std::env::temp_dir().join(format!("bouvet-attach-{}.socket", Uuid::new_v4()));
+        let _listener = tokio::net::UnixListener::bind(&socket).unwrap();
+
+        let id = Uuid::new_v4();
+        let vm = VirtualMachine::attach(id, &socket, MachineConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(vm.id(), id);
+        assert_eq!(vm.state(), VmState::Running);
+        assert_eq!(vm.socket_path(), &socket);
+        // The listener lives in this process
+        assert_eq!(vm.pid(), Some(std::process::id()));
+
+        let _ = std::fs::remove_file(&socket);
+    }
+
+    /// Serve a Firecracker API socket that reports the VM in `state` on
+    /// `GET /` and accepts every other request with `204 No Content`,
+    /// sending each request line to `requests`.
+    fn fake_api(
+        socket: &Path,
+        state: &'static str,
+        requests: tokio::sync::mpsc::UnboundedSender<String>,
+    ) -> tokio::task::JoinHandle<()> {
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+        let listener = tokio::net::UnixListener::bind(socket).unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let requests = requests.clone();
+                tokio::spawn(async move {
+                    let mut reader = BufReader::new(stream);
+                    let mut request_line = String::new();
+                    if reader.read_line(&mut request_line).await.unwrap_or(0) == 0 {
+                        return;
+                    }
+                    let mut content_length = 0;
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).await.unwrap();
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = line.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                content_length = value.trim().parse().unwrap();
+                            }
+                        }
+                    }
+                    let mut body = vec![0; content_length];
+                    reader.read_exact(&mut body).await.unwrap();
+                    let response = if request_line.starts_with("GET / ") {
+                        let info = format!(
+                            r#"{{"id":"anonymous-instance","state":"{state}","vmm_version":"1.7.0","app_name":"Firecracker"}}"#
+                        );
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{info}",
+                            info.len()
+                        )
+                    } else if String::from_utf8_lossy(&body).contains("/fail/") {
+                        let fault = r#"{"fault_message":"no space left"}"#;
+                        format!(
+                            "HTTP/1.1 400 Bad Request\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{fault}",
+                            fault.len()
+                        )
+                    } else {
+                        "HTTP/1.1 204 No Content\r\n\r\n".to_string()
+                    };
+                    let _ = requests.send(request_line.trim().to_string());
+                    let _ = reader.into_inner().write_all(response.as_bytes()).await;
+                });
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_pause_snapshot_resume_transitions() {
+        let socket = std::env::temp_dir().join(format!("bouvet-snap-{}.socket", Uuid::new_v4()));
+        let (tx, mut requests) = tokio::sync::mpsc::unbounded_channel();
+        let server = fake_api(&socket, "Running", tx);
+
+        // Attached to this process: never destroy it, that would SIGKILL us
+        let mut vm = VirtualMachine::attach(Uuid::new_v4(), &socket, MachineConfig::default())
+            .await
+            .unwrap();
+        let (snapshot, memory) = (Path::new("/s/vmstate"), Path::new("/s/memory"));
+
+        // Snapshots need a paused VM
+        assert!(matches!(
+            vm.snapshot(snapshot, memory).await,
+            Err(VmError::InvalidState { .. })
+        ));
+        assert!(vm.resume().await.is_err());
+
+        vm.pause().await.unwrap();
+        assert_eq!(vm.state(), VmState::Paused);
+        assert!(vm.pause().await.is_err());
+        vm.snapshot(snapshot, memory).await.unwrap();
+        assert_eq!(vm.state(), VmState::Paused);
+        vm.resume().await.unwrap();
+        assert_eq!(vm.state(), VmState::Running);
+
+        let mut seen = Vec::new();
+        while let Ok(request) = requests.try_recv() {
+            seen.push(request);
+        }
+        assert_eq!(
+            seen,
+            [
+                "GET / HTTP/1.1",
+                "PATCH /vm HTTP/1.1",
+                "PUT /snapshot/create HTTP/1.1",
+                "PATCH /vm HTTP/1.1"
+            ]
+        );
+
+        server.abort();
+        let _ = std::fs::remove_file(&socket);
+    }
+
+    #[tokio::test]
+    async fn test_console_log_reads_capture() {
+        let dir = std::env::temp_dir().join(format!("bouvet-console-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("firecracker.socket");
+        let (tx, mut requests) = tokio::sync::mpsc::unbounded_channel();
+        let server = fake_api(&socket, "Running", tx);
+
+        // Attached to this process: never destroy it, that would SIGKILL us
+        let vm = VirtualMachine::attach(Uuid::new_v4(), &socket, MachineConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(requests.recv().await.unwrap(), "GET / HTTP/1.1");
+        assert_eq!(vm.console_log_path(), dir.join("console.log"));
+        assert!(matches!(vm.console_log().await, Err(VmError::Io(_))));
+
+        std::fs::write(
+            vm.console_log_path(),
+            b"[    0.000000] Linux version 6.1\n\xff[    0.52] Kernel panic\n",
+        )
+        .unwrap();
+        let console = vm.console_log().await.unwrap();
+        assert!(console.starts_with("[    0.000000] Linux version 6.1\n"));
+        assert!(console.ends_with("Kernel panic\n"));
+
+        server.abort();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_flushes_and_reads_latest_record() {
+        let socket = std::env::temp_dir().join(format!("bouvet-metrics-{}.socket", Uuid::new_v4()));
+        let (tx, mut requests) = tokio::sync::mpsc::unbounded_channel();
+        let server = fake_api(&socket, "Running", tx);
+
+        // Attached to this process: never destroy it, that would SIGKILL us
+        let vm = VirtualMachine::attach(Uuid::new_v4(), &socket, MachineConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(requests.recv().await.unwrap(), "GET / HTTP/1.1");
+        assert!(matches!(vm.metrics().await, Err(VmError::Metrics(_))));
+
+        let metrics_path = socket.with_extension("metrics");
+        std::fs::write(
+            &metrics_path,
+            "{\"utc_timestamp_ms\":1}\n{\"utc_timestamp_ms\":2,\"net\":{\"tx_bytes_count\":9}}\n",
+        )
+        .unwrap();
+        let config = MachineConfig {
+            metrics_path: Some(metrics_path.clone()),
+            ..Default::default()
+        };
+        let vm = VirtualMachine::attach(Uuid::new_v4(), &socket, config)
+            .await
+            .unwrap();
+        assert_eq!(requests.recv().await.unwrap(), "GET / HTTP/1.1");
+
+        let metrics = vm.metrics().await.unwrap();
+        assert_eq!(requests.recv().await.unwrap(), "PUT /actions HTTP/1.1");
+        assert_eq!(metrics.utc_timestamp_ms, 2);
+        assert_eq!(metrics.net_tx_bytes, 9);
+        // The test runner has no vCPU threads
+        assert_eq!(metrics.vcpu_time_ms, None);
+
+        server.abort();
+        let _ = std::fs::remove_file(&socket);
+        let _ = std::fs::remove_file(&metrics_path);
+    }
+
+    #[tokio::test]
+    async fn test_send_ctrl_alt_del_waits_for_exit() {
+        let socket = std::env::temp_dir().join(format!("bouvet-cad-{}.socket", Uuid::new_v4()));
+        let (tx, mut requests) = tokio::sync::mpsc::unbounded_channel();
+        let server = fake_api(&socket, "Running", tx);
+
+        // Attached to this process: never destroy it, that would SIGKILL us
+        let mut vm = VirtualMachine::attach(Uuid::new_v4(), &socket, MachineConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(requests.recv().await.unwrap(), "GET / HTTP/1.1");
+
+        // The guest ignores it: the VM is left running
+        assert!(matches!(
+            vm.send_ctrl_alt_del(Duration::from_millis(100)).await,
+            Err(VmError::Timeout(_))
+        ));
+        assert_eq!(vm.state(), VmState::Running);
+        assert_eq!(requests.recv().await.unwrap(), "PUT /actions HTTP/1.1");
+
+        // Firecracker exits once it sees the second request
+        let exit = tokio::spawn(async move {
+            assert_eq!(requests.recv().await.unwrap(), "PUT /actions HTTP/1.1");
+            server.abort();
+        });
+        vm.send_ctrl_alt_del(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(vm.state(), VmState::Stopped);
+        exit.await.unwrap();
+        assert!(vm.send_ctrl_alt_del(Duration::from_secs(1)).await.is_err());
+
+        let _ = std::fs::remove_file(&socket);
+    }
+
+    #[tokio::test]
+    async fn test_create_snapshot_pauses_running_vm() {
+        let socket = std::env::temp_dir().join(format!("bouvet-snap-{}.socket", Uuid::new_v4()));
+        let (tx, mut requests) = tokio::sync::mpsc::unbounded_channel();
+        let server = fake_api(&socket, "Running", tx);
+
+        // Attached to this process: never destroy it, that would SIGKILL us
+        let mut vm = VirtualMachine::attach(Uuid::new_v4(), &socket, MachineConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(requests.recv().await.unwrap(), "GET / HTTP/1.1");
+
+        vm.create_snapshot(Path::new("/s/memory"), Path::new("/s/vmstate"))
+            .await
+            .unwrap();
+        assert_eq!(vm.state(), VmState::Paused);
+        assert_eq!(requests.recv().await.unwrap(), "PATCH /vm HTTP/1.1");
+        assert_eq!(
+            requests.recv().await.unwrap(),
+            "PUT /snapshot/create HTTP/1.1"
+        );
+
+        // Already paused: straight to the snapshot, whose failure is reported
+        let result = vm
+            .create_snapshot(Path::new("/fail/memory"), Path::new("/fail/vmstate"))
+            .await;
+        match result {
+            Err(VmError::SnapshotCreate(message)) => assert!(message.contains("no space left")),
+            other => panic!("expected SnapshotCreate, got {other:?}"),
+        }
+        assert_eq!(
+            requests.recv().await.unwrap(),
+            "PUT /snapshot/create HTTP/1.1"
+        );
+        assert_eq!(vm.state(), VmState::Paused);
+
+        server.abort();
+        let _ = std::fs::remove_file(&socket);
+    }
+
+    #[test]
+    fn test_grown_size_rejects_shrink() {
+        const MIB: u64 = 1024 * 1024;
+        assert_eq!(grown_size(64 * MIB, 128), Ok(128 * MIB));
+        assert!(grown_size(64 * MIB, 64).is_err());
+        assert!(grown_size(64 * MIB, 32).unwrap_err().contains("only grow"));
+        assert!(grown_size(0, u64::MAX).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_create_overlay_formats_ext4() {
+        let dir = std::env::temp_dir().join(format!("bouvet-overlay-{}", Uuid::new_v4()));
+        let image = dir.join(OVERLAY_FILE);
+
+        create_overlay(&image, 16).await.unwrap();
+        let bytes = std::fs::read(&image).unwrap();
+        assert_eq!(bytes.len(), 16 * 1024 * 1024);
+        // ext4 superblock magic, 0xEF53 little-endian at offset 1024 + 56
+        assert_eq!(&bytes[1080..1082], &[0x53, 0xEF]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_create_times_out_and_cleans_up() {
+        // Short, so the socket path under <chroot>/<id>/ fits in sun_path
+        let chroot = std::env::temp_dir().join(format!("bc-{}", std::process::id()));
+        let id = Uuid::new_v4();
+        let vm_dir = chroot.join(id.to_string());
+        std::fs::create_dir_all(&vm_dir).unwrap();
+
+        // A Firecracker that accepts API connections but never answers
+        let listener = tokio::net::UnixListener::bind(vm_dir.join("firecracker.socket")).unwrap();
+        let server = tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                held.push(stream);
+            }
+        });
+        let firecracker = chroot.join("firecracker");
+        std::fs::write(&firecracker, "#!/bin/sh\nexec sleep 60\n").unwrap();
+        std::fs::set_permissions(
+            &firecracker,
+            std::os::unix::fs::PermissionsExt::from_mode(0o755),
+        )
+        .unwrap();
+
+        // firepilot copies the kernel and rootfs before spawning anything
+        let kernel = chroot.join("vmlinux");
+        let rootfs = chroot.join("rootfs.ext4");
+        std::fs::write(&kernel, "kernel").unwrap();
+        std::fs::write(&rootfs, "rootfs").unwrap();
+
+        let timeout = Duration::from_millis(300);
+        let mut config = MachineConfig {
+            chroot_path: chroot.clone(),
+            firecracker_path: firecracker,
+            kernel_path: kernel,
+            create_timeout: timeout,
+            overlay_size_mib: Some(8),
+            ..Default::default()
+        };
+        config.root_drive.path_on_host = rootfs;
+        config.root_drive.is_read_only = true;
+
+        let start = std::time::Instant::now();
+        let result = VirtualMachine::create_with_id(id, config).await;
+        assert!(matches!(result, Err(VmError::Timeout(t)) if t == timeout));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(!overlay_path(&chroot, id).exists());
+
+        server.abort();
+        let _ = std::fs::remove_dir_all(&chroot);
+    }
+
+    #[tokio::test]
+    async fn test_set_balloon() {
+        let socket = std::env::temp_dir().join(format!("bouvet-balloon-{}.socket", Uuid::new_v4()));
+        let (tx, mut requests) = tokio::sync::mpsc::unbounded_channel();
+        let server = fake_api(&socket, "Running", tx);
+
+        let config = MachineConfig {
+            memory_mib: 256,
+            balloon: Some(BalloonConfig {
+                amount_mib: 0,
+                deflate_on_oom: true,
+            }),
+            ..Default::default()
+        };
+        // Attached to this process: never destroy it, that would SIGKILL us
+        let vm = VirtualMachine::attach(Uuid::new_v4(), &socket, config)
+            .await
+            .unwrap();
+        assert_eq!(requests.recv().await.unwrap(), "GET / HTTP/1.1");
+
+        vm.set_balloon(128).await.unwrap();
+        assert_eq!(requests.recv().await.unwrap(), "PATCH /balloon HTTP/1.1");
+        assert!(matches!(vm.set_balloon(257).await, Err(VmError::Config(_))));
+
+        let vm = VirtualMachine::attach(Uuid::new_v4(), &socket, MachineConfig::default())
+            .await
+            .unwrap();
+        assert!(matches!(vm.set_balloon(64).await, Err(VmError::Config(_))));
+
+        server.abort();
+        let _ = std::fs::remove_file(&socket);
+    }
+
+    #[tokio::test]
+    async fn test_resize_drive_grows_backing_file() {
+        let dir = std::env::temp_dir().join(format!("bouvet-resize-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("firecracker.socket");
+        let image = dir.join("scratch.ext4");
+        std::fs::File::create(&image)
+            .unwrap()
+            .set_len(1024 * 1024)
+            .unwrap();
+        let (tx, mut requests) = tokio::sync::mpsc::unbounded_channel();
+        let server = fake_api(&socket, "Running", tx);
+
+        let config = MachineConfig {
+            extra_drives: vec![DriveConfig {
+                drive_id: "scratch".into(),
+                path_on_host: image.clone(),
+                is_root_device: false,
+                is_read_only: false,
+                rate_limiter: None,
+            }],
+            ..Default::default()
+        };
+        // Attached to this process: never destroy it, that would SIGKILL us
+        let vm = VirtualMachine::attach(Uuid::new_v4(), &socket, config)
+            .await
+            .unwrap();
+        assert_eq!(requests.recv().await.unwrap(), "GET / HTTP/1.1");
+
+        vm.resize_drive("scratch", 2).await.unwrap();
+        assert_eq!(std::fs::metadata(&image).unwrap().len(), 2 * 1024 * 1024);
+        assert_eq!(
+            requests.recv().await.unwrap(),
+            "PATCH /drives/scratch HTTP/1.1"
+        );
+
+        assert!(matches!(
+            vm.resize_drive("scratch", 1).await,
+            Err(VmError::Config(_))
+        ));
+        assert!(matches!(
+            vm.resize_drive("missing", 4).await,
+            Err(VmError::Config(_))
+        ));
+        assert_eq!(std::fs::metadata(&image).unwrap().len(), 2 * 1024 * 1024);
+
+        server.abort();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_attach_reconciles_state_with_firecracker() {
+        let socket = std::env::temp_dir().join(format!("bouvet-info-{}.socket", Uuid::new_v4()));
+        let (tx, mut requests) = tokio::sync::mpsc::unbounded_channel();
+        let server = fake_api(&socket, "Paused", tx);
+
+        // Attached to this process: never destroy it, that would SIGKILL us
+        let mut vm = VirtualMachine::attach(Uuid::new_v4(), &socket, MachineConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(requests.recv().await.unwrap(), "GET / HTTP/1.1");
+        assert_eq!(vm.state(), VmState::Paused);
+        assert_eq!(vm.instance_info().await.unwrap().vmm_version, "1.7.0");
+        vm.resume().await.unwrap();
+        assert_eq!(vm.state(), VmState::Running);
+
+        server.abort();
+        let _ = std::fs::remove_file(&socket);
+    }
+
+    #[tokio::test]
+    async fn test_restore_fails_without_firecracker() {
+        let chroot = std::env::temp_dir().join(format!("bouvet-restore-{}", Uuid::new_v4()));
+        let config = MachineConfig {
+            firecracker_path: chroot.join("missing-firecracker"),
+            chroot_path: chroot.clone(),
+            ..Default::default()
+        };
+        let result = VirtualMachine::restore(
+            Uuid::new_v4(),
+            config,
+            Path::new("/s/vmstate"),
+            Path::new("/s/memory"),
+        )
+        .await;
+        assert!(matches!(result, Err(VmError::Create(_))));
+
+        let _ = std::fs::remove_dir_all(&chroot);
+    }
+
+    #[tokio::test]
+    async fn test_configure_logger_and_read_log() {
+        let dir = std::env::temp_dir().join(format!("bouvet-fclog-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("firecracker.socket");
+        let (tx, mut requests) = tokio::sync::mpsc::unbounded_channel();
+        let server = fake_api(&socket, "Running", tx);
+
+        configure_logger(&FcApi::new(&socket), &socket, FirecrackerLogLevel::Debug)
+            .await
+            .unwrap();
+        assert_eq!(requests.recv().await.unwrap(), "PUT /logger HTTP/1.1");
+
+        // Attached to this process: never destroy it, that would SIGKILL us
+        let vm = VirtualMachine::attach(Uuid::new_v4(), &socket, MachineConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(vm.firecracker_log_path(), dir.join("firecracker.log"));
+        assert_eq!(vm.firecracker_log().await.unwrap(), "");
+        std::fs::write(vm.firecracker_log_path(), "[WARN] bad request\n").unwrap();
+        assert_eq!(vm.firecracker_log().await.unwrap(), "[WARN] bad request\n");
+
+        server.abort();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,186 @@
+//! Firecracker machine metrics.
+//!
+//! Firecracker appends one JSON record per flush to the file set through
+//! `PUT /metrics`: every 60 seconds, and whenever a `FlushMetrics` action
+//! is sent. Most counters in a record cover only the interval since the
+//! previous flush. vCPU time isn't among them, so it is read from the
+//! Firecracker process's `fc_vcpu` threads in `/proc` instead.
+
+use crate::error::{Result, VmError};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Counters from the most recent Firecracker metrics record.
+///
+/// Block and network counters are totals over all drives and interfaces,
+/// for the interval since the previous record.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VmMetrics {
+    /// When Firecracker wrote the record, in milliseconds since the epoch
+    pub utc_timestamp_ms: u64,
+    /// Bytes read from drives
+    pub block_read_bytes: u64,
+    /// Bytes written to drives
+    pub block_write_bytes: u64,
+    /// Drive read requests
+    pub block_read_count: u64,
+    /// Drive write requests
+    pub block_write_count: u64,
+    /// Bytes received on network interfaces
+    pub net_rx_bytes: u64,
+    /// Bytes sent on network interfaces
+    pub net_tx_bytes: u64,
+    /// Packets received on network interfaces
+    pub net_rx_packets: u64,
+    /// Packets sent on network interfaces
+    pub net_tx_packets: u64,
+    /// CPU time used by the vCPU threads since boot, in milliseconds, or
+    /// `None` if the Firecracker process is unknown
+    pub vcpu_time_ms: Option<u64>,
+}
+
+/// The parts of a Firecracker metrics record we read.
+#[derive(Deserialize)]
+struct Record {
+    utc_timestamp_ms: u64,
+    #[serde(default)]
+    block: BlockRecord,
+    #[serde(default)]
+    net: NetRecord,
+}
+
+#[derive(Default, Deserialize)]
+struct BlockRecord {
+    #[serde(default)]
+    read_bytes: u64,
+    #[serde(default)]
+    write_bytes: u64,
+    #[serde(default)]
+    read_count: u64,
+    #[serde(default)]
+    write_count: u64,
+}
+
+#[derive(Default, Deserialize)]
+struct NetRecord {
+    #[serde(default)]
+    rx_bytes_count: u64,
+    #[serde(default)]
+    tx_bytes_count: u64,
+    #[serde(default)]
+    rx_packets_count: u64,
+    #[serde(default)]
+    tx_packets_count: u64,
+}
+
+impl VmMetrics {
+    /// Parse the last record in the contents of a metrics file.
+    pub(crate) fn parse_latest(contents: &str) -> Result<Self> {
+        let line = contents
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .ok_or_else(|| VmError::Metrics("no metrics record written yet".into()))?;
+        let record: Record = serde_json::from_str(line)
+            .map_err(|e| VmError::Metrics(format!("invalid metrics record: {e}")))?;
+        Ok(Self {
+            utc_timestamp_ms: record.utc_timestamp_ms,
+            block_read_bytes: record.block.read_bytes,
+            block_write_bytes: record.block.write_bytes,
+            block_read_count: record.block.read_count,
+            block_write_count: record.block.write_count,
+            net_rx_bytes: record.net.rx_bytes_count,
+            net_tx_bytes: record.net.tx_bytes_count,
+            net_rx_packets: record.net.rx_packets_count,
+            net_tx_packets: record.net.tx_packets_count,
+            vcpu_time_ms: None,
+        })
+    }
+}
+
+/// Total user and system CPU time of a process's `fc_vcpu` threads, in
+/// milliseconds, or `None` if it has none.
+pub(crate) fn vcpu_time_ms(pid: u32) -> Option<u64> {
+    // SAFETY: sysconf(3) has no memory-safety preconditions
+    let ticks_per_sec = u64::try_from(unsafe { libc::sysconf(libc::_SC_CLK_TCK) })
+        .ok()
+        .filter(|&ticks| ticks > 0)?;
+    let tasks = std::fs::read_dir(Path::new("/proc").join(pid.to_string()).join("task")).ok()?;
+
+    let mut ticks = None;
+    for task in tasks.flatten() {
+        let Ok(stat) = std::fs::read_to_string(task.path().join("stat")) else {
+            continue;
+        };
+        if let Some((comm, cpu_ticks)) = parse_task_stat(&stat) {
+            if comm.starts_with("fc_vcpu") {
+                *ticks.get_or_insert(0) += cpu_ticks;
+            }
+        }
+    }
+    ticks.map(|ticks| ticks * 1000 / ticks_per_sec)
+}
+
+/// Thread name and utime + stime from a `/proc/<pid>/task/<tid>/stat` line.
+fn parse_task_stat(stat: &str) -> Option<(&str, u64)> {
+    // The name is parenthesized and may contain spaces or parentheses
+    let (_, rest) = stat.split_once('(')?;
+    let (comm, fields) = rest.rsplit_once(')')?;
+    // Fields after the name start at field 3 (state); utime and stime are 14 and 15
+    let mut fields = fields.split_whitespace().skip(11);
+    let utime: u64 = fields.next()?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    Some((comm, utime + stime))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_latest_record() {
+        let contents = concat!(
+            r#"{"utc_timestamp_ms":1000,"block":{"read_bytes":1},"net":{}}"#,
+            "\n",
+            r#"{"utc_timestamp_ms":2000,"block":{"read_bytes":4096,"write_bytes":512,"read_count":2,"write_count":1},"#,
+            r#""block_rootfs":{"read_bytes":4096},"net":{"rx_bytes_count":60,"tx_bytes_count":40,"rx_packets_count":1,"tx_packets_count":1},"vcpu":{"exit_io_in":3}}"#,
+            "\n\n",
+        );
+        let metrics = VmMetrics::parse_latest(contents).unwrap();
+        assert_eq!(
+            metrics,
+            VmMetrics {
+                utc_timestamp_ms: 2000,
+                block_read_bytes: 4096,
+                block_write_bytes: 512,
+                block_read_count: 2,
+                block_write_count: 1,
+                net_rx_bytes: 60,
+                net_tx_bytes: 40,
+                net_rx_packets: 1,
+                net_tx_packets: 1,
+                vcpu_time_ms: None,
+            }
+        );
+
+        assert!(matches!(
+            VmMetrics::parse_latest("\n"),
+            Err(VmError::Metrics(_))
+        ));
+        assert!(matches!(
+            VmMetrics::parse_latest("not json"),
+            Err(VmError::Metrics(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_task_stat() {
+        let stat =
+            "4242 (fc_vcpu 0) S 4240 4240 1 0 -1 4194368 100 0 0 0 250 50 0 0 20 0 1 0 5 0 0";
+        assert_eq!(parse_task_stat(stat), Some(("fc_vcpu 0", 300)));
+        // Names can hold parentheses
+        let stat = "7 (a) b) R 1 7 7 0 -1 0 0 0 0 0 3 4 0 0 20 0 1 0 5 0 0";
+        assert_eq!(parse_task_stat(stat), Some(("a) b", 7)));
+        assert_eq!(parse_task_stat("garbage"), None);
+    }
+}
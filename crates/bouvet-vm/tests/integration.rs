@@ -70,7 +70,7 @@ async fn test_vm_with_network() {
         .expect("Failed to create VM with network");
 
     assert_eq!(vm.state(), VmState::Running);
-    assert!(vm.config().network.is_some());
+    assert_eq!(vm.config().networks.len(), 1);
 
     vm.destroy().await.expect("Failed to destroy VM");
 }